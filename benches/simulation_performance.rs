@@ -1,8 +1,9 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use traffic_sim::{
     config::SimulationConfig,
-    simulation::SimulationState,
+    simulation::{SimulationState, PhysicsEngine, CarId},
     compute::{ComputeBackend, SimulationBackend},
+    graphics::{CarDetail, Viewport},
 };
 
 fn benchmark_cpu_simulation(c: &mut Criterion) {
@@ -29,7 +30,7 @@ fn benchmark_cpu_simulation(c: &mut Criterion) {
     });
 }
 
-#[cfg(feature = "gpu-sim")]
+#[cfg(feature = "opencl")]
 fn benchmark_gpu_simulation(c: &mut Criterion) {
     let config = SimulationConfig::load_from_files("route.toml", "cars.toml")
         .expect("Failed to load configuration");
@@ -91,11 +92,98 @@ fn benchmark_simulation_scaling(c: &mut Criterion) {
     group.finish();
 }
 
+fn benchmark_dense_traffic_physics(c: &mut Criterion) {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")
+        .expect("Failed to load configuration");
+    
+    let mut backend = ComputeBackend::new_cpu(
+        config.cars.clone(),
+        config.route.clone(),
+        Some(42)
+    );
+    
+    // Spawn one car and use it as a template for heavy ring traffic
+    let mut template_state = SimulationState::new(1.0 / 60.0);
+    while template_state.cars.is_empty() {
+        backend.update(&mut template_state).unwrap();
+    }
+    let template = template_state.cars[0].clone();
+    
+    // Grow the ring so 10k cars sit at a realistic 10m spacing
+    let car_count = 10_000;
+    let car_spacing = 10.0;
+    let mut route = config.route.clone();
+    let geometry = &mut route.route.geometry;
+    let cars_per_lane = car_count / geometry.lane_count as usize;
+    let road_width = geometry.outer_radius - geometry.inner_radius;
+    geometry.inner_radius = cars_per_lane as f32 * car_spacing / std::f32::consts::TAU;
+    geometry.outer_radius = geometry.inner_radius + road_width;
+    let geometry = &route.route.geometry;
+    
+    let mut state = SimulationState::new(1.0 / 60.0);
+    for i in 0..car_count {
+        let lane = (i / cars_per_lane) as u32 % geometry.lane_count + 1;
+        let angle = (i % cars_per_lane) as f32 / cars_per_lane as f32 * std::f32::consts::TAU;
+        let radius = geometry.inner_radius + geometry.lane_width * (lane as f32 - 0.5);
+        
+        let mut car = template.clone();
+        car.id = CarId::new(i as u32, 0);
+        car.current_lane = lane;
+        car.position = nalgebra::Point2::new(radius * angle.cos(), radius * angle.sin());
+        state.add_car(car);
+    }
+    
+    let mut physics = PhysicsEngine::new(route.clone(), config.cars.collision_avoidance.clone(), config.cars.reaction.clone());
+    
+    c.bench_function("cpu_physics_10k_cars", |b| {
+        b.iter(|| {
+            physics.update(black_box(&mut state));
+        })
+    });
+}
+
+fn benchmark_car_detail(c: &mut Criterion) {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")
+        .expect("Failed to load configuration");
+        
+    let mut backend = ComputeBackend::new_cpu(
+        config.cars.clone(),
+        config.route.clone(),
+        Some(42)
+    );
+    
+    let mut template_state = SimulationState::new(1.0 / 60.0);
+    while template_state.cars.is_empty() {
+        backend.update(&mut template_state).unwrap();
+    }
+    let template = template_state.cars[0].clone();
+    
+    // 50k cars packed into the default view, where they are all drawn as dots
+    let cars: Vec<_> = (0..50_000).map(|i| {
+        let mut car = template.clone();
+        car.id = CarId::new(i as u32, 0);
+        car.position = nalgebra::Point2::new((i % 250) as f32 * 1.5 - 187.0, (i / 250) as f32 * 1.2 - 120.0);
+        car
+    }).collect();
+    let view_matrix = Viewport::new(1200.0, 800.0).get_view_matrix();
+    
+    c.bench_function("car_detail_50k_cars", |b| {
+        b.iter(|| {
+            CarDetail::select(black_box(&cars), &view_matrix, 1200).dots.len()
+        })
+    });
+}
+
 criterion_group!(
     benches, 
     benchmark_cpu_simulation,
-    #[cfg(feature = "gpu-sim")]
-    benchmark_gpu_simulation,
-    benchmark_simulation_scaling
+    benchmark_simulation_scaling,
+    benchmark_dense_traffic_physics,
+    benchmark_car_detail
 );
+#[cfg(feature = "opencl")]
+criterion_group!(gpu_benches, benchmark_gpu_simulation);
+#[cfg(feature = "opencl")]
+criterion_main!(benches, gpu_benches);
+#[cfg(not(feature = "opencl"))]
 criterion_main!(benches);
\ No newline at end of file
@@ -1,11 +1,13 @@
-use crate::simulation::{SimulationState, PhysicsEngine, TrafficManager};
+use crate::simulation::{SimulationState, Car, CarId, Point, PhysicsEngine, CollisionDetector, TrafficManager, EventObserver, EventObservers};
 use crate::config::{CarsConfig, RouteConfig};
 use anyhow::Result;
 use super::SimulationBackend;
 
 pub struct CpuBackend {
     physics_engine: PhysicsEngine,
+    collision_detector: CollisionDetector,
     traffic_manager: TrafficManager,
+    observers: EventObservers,
 }
 
 impl CpuBackend {
@@ -16,8 +18,10 @@ impl CpuBackend {
     ) -> Self {
         let physics_engine = PhysicsEngine::new(
             route_config.clone(), 
-            cars_config.collision_avoidance.clone()
+            cars_config.collision_avoidance.clone(),
+            cars_config.reaction.clone()
         );
+        let collision_detector = CollisionDetector::new(&cars_config.collision_avoidance, &route_config.route.geometry);
         
         let traffic_manager = TrafficManager::new(
             cars_config,
@@ -27,19 +31,27 @@ impl CpuBackend {
         
         Self {
             physics_engine,
+            collision_detector,
             traffic_manager,
+            observers: EventObservers::default(),
         }
     }
 }
 
 impl SimulationBackend for CpuBackend {
     fn update(&mut self, state: &mut SimulationState) -> Result<()> {
+        state.events.clear();
+        
         // Update traffic management (spawning/despawning, behavior)
         self.traffic_manager.update(state);
         
         // Update physics (movement, collision avoidance)
         self.physics_engine.update(state);
         
+        // Detect cars that ended up overlapping
+        self.collision_detector.update(state);
+        
+        self.observers.notify(&state.events);
         Ok(())
     }
     
@@ -50,10 +62,47 @@ impl SimulationBackend for CpuBackend {
     fn supports_gpu(&self) -> bool {
         false
     }
+    
+    fn add_observer(&mut self, observer: EventObserver) {
+        self.observers.add(observer);
+    }
 }
 
 impl CpuBackend {
-    pub fn spawn_manual_car(&mut self, behavior_name: &str, state: &mut SimulationState) {
-        self.traffic_manager.spawn_manual_car(behavior_name, state);
+    pub fn spawn_manual_car(&mut self, behavior_name: &str, state: &mut SimulationState) -> Result<CarId> {
+        self.traffic_manager.spawn_manual_car(behavior_name, state)
+    }
+    
+    pub fn spawn_car_at(&mut self, point: Point, behavior_name: &str, car_type: Option<&str>, state: &mut SimulationState) -> Result<CarId> {
+        self.traffic_manager.spawn_car_at(point, behavior_name, car_type, state)
+    }
+    
+    pub fn populate(&mut self, density: f32, state: &mut SimulationState) -> Result<usize> {
+        self.traffic_manager.populate(density, state)
+    }
+    
+    pub fn admit_car(&mut self, car: &Car, entry_id: &str, state: &mut SimulationState) -> Result<CarId> {
+        self.traffic_manager.admit_car(car, entry_id, state)
+    }
+    
+    pub fn set_transfer_exits(&mut self, exits: impl IntoIterator<Item = String>) {
+        self.traffic_manager.set_transfer_exits(exits);
+    }
+    
+    pub fn restore_checkpoint(&mut self, state: &SimulationState, seed: Option<u64>) {
+        self.traffic_manager.restore(state, seed);
+        self.collision_detector.reset();
+    }
+    
+    pub fn reset(&mut self, seed: Option<u64>) {
+        self.traffic_manager.reset(seed);
+        self.collision_detector.reset();
+    }
+    
+    pub fn reconfigure(&mut self, cars_config: CarsConfig, route_config: RouteConfig, state: &SimulationState, seed: Option<u64>) {
+        let observers = std::mem::take(&mut self.observers);
+        *self = Self::new(cars_config, route_config, seed);
+        self.observers = observers;
+        self.restore_checkpoint(state, seed);
     }
 }
\ No newline at end of file
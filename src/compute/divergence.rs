@@ -0,0 +1,165 @@
+use crate::simulation::{SimulationState, CarId};
+use crate::config::{CarsConfig, RouteConfig};
+use anyhow::Result;
+use std::collections::HashMap;
+use super::{ComputeBackend, SimulationBackend};
+
+/// How far apart two runs of the same simulation are, compared car by car
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Divergence {
+    pub time: f32,
+    pub max_position: f32, // m between the two positions of the car furthest apart
+    pub max_velocity: f32, // m/s between the two velocities of the car furthest apart
+    pub worst_car: Option<CarId>, // the car furthest out of place
+    pub unmatched_cars: u32, // cars on the road in one run only
+}
+
+impl Divergence {
+    /// Compare the cars of `other` against the same cars in `reference`
+    pub fn between(reference: &SimulationState, other: &SimulationState) -> Self {
+        let others: HashMap<CarId, _> = other.cars.iter().map(|car| (car.id, car)).collect();
+        let mut divergence = Self { time: reference.time, ..Self::default() };
+        let mut matched = 0;
+        for car in &reference.cars {
+            let Some(other) = others.get(&car.id) else {
+                divergence.unmatched_cars += 1;
+                continue;
+            };
+            matched += 1;
+            let position = (car.position - other.position).magnitude();
+            if position > divergence.max_position {
+                divergence.max_position = position;
+                divergence.worst_car = Some(car.id);
+            }
+            divergence.max_velocity = divergence.max_velocity.max((car.velocity - other.velocity).magnitude());
+        }
+        divergence.unmatched_cars += (other.cars.len() - matched) as u32;
+        divergence
+    }
+
+    /// Whether any car is further out than `tolerance` allows, or is missing from a run
+    pub fn exceeds(&self, tolerance: &DivergenceTolerance) -> bool {
+        self.max_position > tolerance.position || self.max_velocity > tolerance.velocity || self.unmatched_cars > 0
+    }
+}
+
+/// Largest divergence between backends that still counts as the same result
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DivergenceTolerance {
+    pub position: f32, // m
+    pub velocity: f32, // m/s
+}
+
+impl Default for DivergenceTolerance {
+    fn default() -> Self {
+        Self { position: 0.1, velocity: 0.1 }
+    }
+}
+
+/// Second backend stepped in lockstep with a run on its own copy of the state, to catch
+/// the moment the two stop agreeing. Whatever changes the run outside its steps has to
+/// be repeated here, through `resync`, `reset` or `reconfigure`.
+pub struct DivergenceMonitor {
+    backend: ComputeBackend,
+    state: SimulationState,
+    tolerance: DivergenceTolerance,
+    latest: Divergence,
+    peak: Divergence, // largest figures since the last resync, each on its own
+}
+
+impl DivergenceMonitor {
+    /// Follow a run at `state` with `backend`, which should be created with the run's seed
+    pub fn new(backend: ComputeBackend, state: &SimulationState, tolerance: DivergenceTolerance) -> Self {
+        Self {
+            backend,
+            state: state.clone(),
+            tolerance,
+            latest: Divergence::default(),
+            peak: Divergence::default(),
+        }
+    }
+
+    /// Take the same step the run just took to reach `state` and compare the two. Returns
+    /// whether this step took the divergence over tolerance, after being within it.
+    pub fn step(&mut self, state: &SimulationState) -> Result<bool> {
+        // Bookkeeping the main loop does after each backend update
+        self.backend.update(&mut self.state)?;
+        self.state.update_car_speeds();
+        self.state.active_cars = self.state.cars.len() as u32;
+
+        let was_exceeded = self.exceeded();
+        self.latest = Divergence::between(state, &self.state);
+        self.peak.time = self.latest.time;
+        self.peak.max_position = self.peak.max_position.max(self.latest.max_position);
+        self.peak.max_velocity = self.peak.max_velocity.max(self.latest.max_velocity);
+        self.peak.unmatched_cars = self.peak.unmatched_cars.max(self.latest.unmatched_cars);
+        if self.latest.max_position >= self.peak.max_position {
+            self.peak.worst_car = self.latest.worst_car;
+        }
+        Ok(!was_exceeded && self.exceeded())
+    }
+
+    /// Continue from `state`, as the run does after its backend was restored from it
+    pub fn resync(&mut self, state: &SimulationState, seed: Option<u64>) {
+        self.backend.restore_checkpoint(state, seed);
+        self.state = state.clone();
+        self.clear();
+    }
+
+    /// Start over with `seed`, as the run does
+    pub fn reset(&mut self, seed: Option<u64>) {
+        self.backend.reset(seed);
+        self.state = SimulationState::new(self.state.dt);
+        self.clear();
+    }
+
+    /// Rebuild the backend with new configurations and continue from `state`
+    pub fn reconfigure(&mut self, cars_config: CarsConfig, route_config: RouteConfig, state: &SimulationState, seed: Option<u64>) -> Result<()> {
+        self.backend.reconfigure(cars_config, route_config, state, seed)?;
+        self.state = state.clone();
+        self.clear();
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.latest = Divergence { time: self.state.time, ..Divergence::default() };
+        self.peak = self.latest;
+    }
+
+    /// Name of the backend checked against the run
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.get_name()
+    }
+
+    pub fn latest(&self) -> &Divergence {
+        &self.latest
+    }
+
+    pub fn peak(&self) -> &Divergence {
+        &self.peak
+    }
+
+    pub fn tolerance(&self) -> &DivergenceTolerance {
+        &self.tolerance
+    }
+
+    /// Whether the latest step is over tolerance
+    pub fn exceeded(&self) -> bool {
+        self.latest.exceeds(&self.tolerance)
+    }
+
+    /// The latest and peak divergence, for the status panel
+    pub fn describe(&self) -> String {
+        let mut text = format!(
+            "{:.3} m, {:.3} m/s (peak {:.3} m, {:.3} m/s)",
+            self.latest.max_position, self.latest.max_velocity, self.peak.max_position, self.peak.max_velocity
+        );
+        if let Some(car) = self.latest.worst_car.filter(|_| self.exceeded()) {
+            text.push_str(&format!(", worst car {}", car));
+        }
+        if self.latest.unmatched_cars > 0 {
+            text.push_str(&format!(", {} cars in one run only", self.latest.unmatched_cars));
+        }
+        text
+    }
+}
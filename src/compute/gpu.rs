@@ -2,27 +2,63 @@ use opencl3::{
     context::Context,
     device::{Device, get_all_devices, CL_DEVICE_TYPE_GPU},
     kernel::{ExecuteKernel, Kernel},
-    memory::{Buffer, CL_MEM_READ_WRITE, CL_MEM_READ_ONLY},
+    memory::{Buffer, ClMem, CL_MEM_ALLOC_HOST_PTR, CL_MEM_READ_WRITE, CL_MEM_READ_ONLY, CL_MAP_READ, CL_MAP_WRITE},
     program::Program,
     command_queue::{CommandQueue, CL_QUEUE_PROFILING_ENABLE},
-    types::CL_TRUE,
+    event::Event,
+    types::{cl_mem, CL_FALSE, CL_TRUE},
 };
 
-use crate::simulation::{SimulationState, TrafficManager, Car};
-use crate::config::{CarsConfig, RouteConfig};
+use crate::simulation::{SimulationState, SimulationEvent, TrafficManager, CollisionDetector, Car, CarId, Point, Weather, TurnSignal, ExitRamps, RampMotion, EventObserver, EventObservers, GpuTiming};
+use crate::config::{CarsConfig, RouteConfig, RoadSurface};
 use anyhow::{Result, anyhow};
 use super::SimulationBackend;
+use std::collections::HashMap;
 use std::ptr;
+use std::time::{Duration, Instant};
 
 pub struct GpuBackend {
-    context: Context,
+    _context: Context, // keeps the OpenCL context alive for the buffers and queue made from it
     queue: CommandQueue,
-    program: Program,
+    behavior_kernel: Kernel,
     physics_kernel: Kernel,
+    compact_kernel: Kernel,
     traffic_manager: TrafficManager,
-    car_buffer: Option<Buffer<u8>>,
+    collision_detector: CollisionDetector,
+    car_buffer: Buffer<GpuCar>, // device copy of the cars, `max_cars` long
+    spare_buffer: Buffer<GpuCar>, // compaction target, swapped with `car_buffer`
+    staging: PinnedCars, // host mirror of `car_buffer` after every step
+    slot_buffer: Buffer<u32>, // new index of each device car during compaction
+    decision_buffer: Buffer<GpuDecision>, // behavior decided on the device, from one kernel to the next
+    slots: Vec<u32>,
+    resident: Vec<CarId>, // cars held in the device buffer, in order
     route_buffer: Buffer<u8>,
+    surface: RoadSurface,
+    exit_ramps: ExitRamps,
+    exits: HashMap<String, ExitParams>, // by exit id, for the ring cars heading there
     max_cars: usize,
+    observers: EventObservers,
+    work_items: usize, // work-items the device runs at once
+    timing: Option<GpuTiming>, // of the latest step
+    pipelined: bool,
+    in_flight: Option<StepInFlight>, // step left running on the device when pipelined
+}
+
+/// A step whose commands are queued on the device. The host part before the kernels is
+/// done; waiting for the download and everything after it is left to `complete_step`.
+struct StepInFlight {
+    dt: f32,
+    device: Option<DeviceWork>, // none when the road was empty
+}
+
+struct DeviceWork {
+    count: usize,
+    ramp_motions: Vec<Option<RampMotion>>, // of the resident cars, by index
+    uploads: Vec<Event>,
+    compaction: Option<(Event, Event)>,
+    behavior_event: Event,
+    kernel_event: Event,
+    download: Event,
 }
 
 const PHYSICS_KERNEL_SOURCE: &str = r#"
@@ -37,15 +73,60 @@ typedef struct {
     float preferred_speed;     // preferred speed
     uint current_lane;         // current lane
     uint target_lane;          // target lane (0 = no target)
-    float lane_change_progress; // 0.0 to 1.0
+    float lateral_offset;      // meters from the middle of the current lane
     float following_distance_factor;
     float target_speed;
     float reaction_time;
     float last_lane_change_time;
-    // Padding to align to 16-float boundary
-    float padding[2];
+    // Grid path: the waypoint being driven to, the one after it and how many are left
+    float waypoint_x, waypoint_y;
+    float after_x, after_y;
+    uint waypoints_left;       // 0 once the path is done, or for cars without one
+    // Behavior, decided by update_behavior for cars flagged BEHAVIOR_ON_DEVICE
+    uint car_id;               // counter of the car's random draws
+    uint behavior_flags;
+    float speed_variance;
+    float lane_change_frequency; // changes per minute
+    float exit_angle;          // radians, of the destination exit on a ring
+    uint exit_lane;            // lane of the destination exit, 0 without one
+    uint exit_signal;          // turn signal for the destination exit
+    uint merge_intent;         // lane the car waits for a gap in, 0 if none
+    uint turn_signal;
 } Car;
 
+// Behavior flags, in Car.behavior_flags
+#define BEHAVIOR_ON_DEVICE 1u      // the kernel makes this car's behavior decisions
+#define BEHAVIOR_STOPPED 2u        // broken down or dwelling at a bus stop, to be steered around
+#define BEHAVIOR_ON_SHOULDER 4u    // off the lanes, ignored by the cars around
+
+// Turn signals, in Car.turn_signal
+#define SIGNAL_NONE 0u
+#define SIGNAL_LEFT 1u
+#define SIGNAL_RIGHT 2u
+
+// Behavior distances, as in the CPU behavior engine
+#define EXIT_APPROACH_DISTANCE 300.0f
+#define EXIT_SIGNAL_DISTANCE 100.0f
+#define BREAKDOWN_AVOIDANCE_DISTANCE 60.0f
+#define LANE_CHANGE_GAP 10.0f
+#define REFERENCE_CAR_LENGTH 5.0f
+#define NEIGHBOR_LOOKAHEAD 100.0f
+
+// Behavior decided for a car by update_behavior, applied by update_physics
+typedef struct {
+    float target_speed;
+    uint target_lane;
+    uint merge_intent;
+    uint turn_signal;
+    float last_lane_change_time;
+    uint decided;              // 0 for cars the host decided for
+} Decision;
+
+// Geometry types, in RouteParams.geometry_type
+#define GEOMETRY_DONUT 0u
+#define GEOMETRY_CLOVERLEAF 1u
+#define GEOMETRY_GRID 2u
+
 // Route parameters
 typedef struct {
     float center_x, center_y;
@@ -58,35 +139,27 @@ typedef struct {
     float emergency_brake_distance;
     float warning_distance;
     float safety_margin;
+    uint geometry_type;
+    float highway_half_width;  // cloverleaf: half the width of each highway
+    float loop_radius;         // cloverleaf: radius of the loop ramps
 } RouteParams;
 
-__kernel void update_physics(
-    __global Car* cars,
-    const __global RouteParams* route,
-    const float dt,
+// Arc distance to the nearest car ahead in the same or target lane of a ring road,
+// INFINITY if there is none
+float front_car_on_ring(
+    const __global Car* cars,
     const uint car_count,
-    const float simulation_time
+    const uint gid,
+    const __global RouteParams* r,
+    float* front_speed
 ) {
-    const uint gid = get_global_id(0);
-    if (gid >= car_count) return;
-    
-    __global Car* car = &cars[gid];
-    const __global RouteParams* r = route;
-    
-    // Calculate current position on donut
+    const __global Car* car = &cars[gid];
     const float to_car_x = car->pos_x - r->center_x;
     const float to_car_y = car->pos_y - r->center_y;
     const float current_angle = atan2(to_car_y, to_car_x);
     const float current_radius = sqrt(to_car_x * to_car_x + to_car_y * to_car_y);
     
-    // Calculate target lane radius
-    const float lane_offset = ((float)car->current_lane - 1.0f) * r->lane_width;
-    const float target_radius = r->inner_radius + r->lane_width * 0.5f + lane_offset;
-    
-    // Find nearest car in front for collision avoidance
     float min_front_distance = INFINITY;
-    float front_car_speed = 0.0f;
-    
     for (uint i = 0; i < car_count; i++) {
         if (i == gid) continue;
         
@@ -110,47 +183,121 @@ __kernel void update_physics(
             const float arc_distance = angle_diff * current_radius;
             if (arc_distance < min_front_distance) {
                 min_front_distance = arc_distance;
-                front_car_speed = sqrt(other->vel_x * other->vel_x + other->vel_y * other->vel_y);
+                *front_speed = sqrt(other->vel_x * other->vel_x + other->vel_y * other->vel_y);
             }
         }
     }
+    return min_front_distance;
+}
+
+// Straight-line distance to the nearest car ahead of a cloverleaf car in its lane or
+// target lane, INFINITY if there is none
+float front_car_straight(
+    const __global Car* cars,
+    const uint car_count,
+    const uint gid,
+    float* front_speed
+) {
+    const __global Car* car = &cars[gid];
+    const float speed = sqrt(car->vel_x * car->vel_x + car->vel_y * car->vel_y);
+    const float dir_x = speed > 0.1f ? car->vel_x / speed : 1.0f;
+    const float dir_y = speed > 0.1f ? car->vel_y / speed : 0.0f;
     
-    // Calculate target speed based on traffic (matching CPU implementation)
-    float target_speed = car->target_speed;
-    
-    // Use route collision avoidance parameters (from config)
-    const float emergency_brake_distance = r->emergency_brake_distance;
-    const float warning_distance = r->warning_distance;
-    const float safety_margin = r->safety_margin;
-    
-    // Calculate following distance (matching CPU implementation)
-    const float current_speed = sqrt(car->vel_x * car->vel_x + car->vel_y * car->vel_y);
-    const float base_following_distance = r->following_distance * current_speed;
-    const float following_distance = base_following_distance * car->following_distance_factor + safety_margin;
-    
-    // Apply collision avoidance logic
-    if (min_front_distance != INFINITY) {
-        if (min_front_distance < emergency_brake_distance) {
-            target_speed = 0.0f; // Emergency brake
-        } else if (min_front_distance < warning_distance) {
-            const float brake_factor = (min_front_distance - emergency_brake_distance) / 
-                                     (warning_distance - emergency_brake_distance);
-            target_speed *= brake_factor;
-        } else if (min_front_distance < following_distance) {
-            // Maintain following distance - match front car speed
-            target_speed = min(front_car_speed, target_speed);
+    float min_front_distance = INFINITY;
+    for (uint i = 0; i < car_count; i++) {
+        if (i == gid) continue;
+        
+        const __global Car* other = &cars[i];
+        if (other->current_lane != car->current_lane && 
+            (car->target_lane == 0 || other->current_lane != car->target_lane)) {
+            continue;
+        }
+        
+        const float to_other_x = other->pos_x - car->pos_x;
+        const float to_other_y = other->pos_y - car->pos_y;
+        const float distance = sqrt(to_other_x * to_other_x + to_other_y * to_other_y);
+        if (to_other_x * dir_x + to_other_y * dir_y > 0.0f && distance < min_front_distance) {
+            min_front_distance = distance;
+            *front_speed = sqrt(other->vel_x * other->vel_x + other->vel_y * other->vel_y);
+        }
+    }
+    return min_front_distance;
+}
+
+// Direction a grid car drives in: towards its waypoint, past it once there, and along
+// its heading when the path is done
+float2 grid_direction(const __global Car* car) {
+    if (car->waypoints_left > 0) {
+        const float2 to_waypoint = (float2)(car->waypoint_x - car->pos_x, car->waypoint_y - car->pos_y);
+        if (length(to_waypoint) > 1e-3f) {
+            return normalize(to_waypoint);
+        }
+        if (car->waypoints_left > 1) {
+            return normalize((float2)(car->after_x - car->waypoint_x, car->after_y - car->waypoint_y));
+        }
+    }
+    return (float2)(cos(car->heading), sin(car->heading));
+}
+
+// Distance along `car`'s line of travel to `other` if it sits ahead on that line within a
+// lane width and the warning distance, INFINITY otherwise
+float distance_ahead_on_grid(const __global Car* car, const __global Car* other, const __global RouteParams* r) {
+    const float2 dir = grid_direction(car);
+    const float2 to_other = (float2)(other->pos_x - car->pos_x, other->pos_y - car->pos_y);
+    const float along = dot(to_other, dir);
+    const float across = fabs(to_other.x * dir.y - to_other.y * dir.x);
+    if (along <= 0.0f || along > r->warning_distance || across > r->lane_width) {
+        return INFINITY;
+    }
+    return along;
+}
+
+// Distance to the nearest car ahead of a grid car in any lane, cars crossing its street
+// included, INFINITY if there is none
+float front_car_on_grid(
+    const __global Car* cars,
+    const uint car_count,
+    const uint gid,
+    const __global RouteParams* r,
+    float* front_speed
+) {
+    const __global Car* car = &cars[gid];
+    float min_front_distance = INFINITY;
+    for (uint i = 0; i < car_count; i++) {
+        if (i == gid) continue;
+        
+        const __global Car* other = &cars[i];
+        const float distance = distance_ahead_on_grid(car, other, r);
+        if (distance == INFINITY) continue;
+        
+        // When two cars block each other at a merge the one spawned first, earlier in the
+        // buffer, goes first
+        if (i > gid && distance_ahead_on_grid(other, car, r) != INFINITY) continue;
+        
+        if (distance < min_front_distance) {
+            min_front_distance = distance;
+            *front_speed = sqrt(other->vel_x * other->vel_x + other->vel_y * other->vel_y);
         }
     }
+    return min_front_distance;
+}
+
+// Circular motion around the ring, counter-clockwise
+void move_on_ring(__global Car* car, const __global RouteParams* r, const float target_speed, const float current_speed, const float dt) {
+    const float to_car_x = car->pos_x - r->center_x;
+    const float to_car_y = car->pos_y - r->center_y;
+    const float current_angle = atan2(to_car_y, to_car_x);
     
-    // Apply speed limits
-    target_speed = clamp(target_speed, r->min_speed, r->speed_limit);
+    // Calculate target lane radius
+    const float lane_offset = ((float)car->current_lane - 1.0f) * r->lane_width;
+    const float target_radius = r->inner_radius + r->lane_width * 0.5f + lane_offset + car->lateral_offset;
     
-    // Calculate acceleration (reuse current_speed from above)
+    // Calculate acceleration
     const float speed_diff = target_speed - current_speed;
     const float accel_mag = (speed_diff > 0.0f) ? 
         min(speed_diff / dt, car->max_accel) : 
         max(speed_diff / dt, -car->max_decel);
-    
+        
     // Calculate tangential direction
     const float tangent_angle = current_angle + M_PI_F / 2.0f;
     const float tangent_x = -sin(tangent_angle);
@@ -176,8 +323,531 @@ __kernel void update_physics(
     car->acc_x = tangent_x * accel_mag;
     car->acc_y = tangent_y * accel_mag;
 }
+
+// Straight highway lanes 1-12 and clockwise loop ramps beyond, at the target speed as on
+// the CPU. Lanes 1-3 run south on the west side, 4-6 north on the east side, 7-9 west on
+// the north side and 10-12 east on the south side.
+void move_on_cloverleaf(__global Car* car, const __global RouteParams* r, const float target_speed, const float dt) {
+    const uint lane = car->current_lane;
+    const float lane_separation = r->highway_half_width + 5.0f; // between opposite directions
+    float2 position;
+    float2 dir;
+    
+    if (lane >= 1 && lane <= 12) {
+        // Middle lane of each direction on the line, the others a lane width to either side
+        const int middle_lane = lane <= 3 ? 2 : lane <= 6 ? 5 : lane <= 9 ? 8 : 11;
+        const float lane_offset = (float)((int)lane - middle_lane) * r->lane_width + car->lateral_offset;
+        if (lane <= 3) {
+            dir = (float2)(0.0f, -1.0f);
+            position = (float2)(-lane_separation + lane_offset, car->pos_y);
+        } else if (lane <= 6) {
+            dir = (float2)(0.0f, 1.0f);
+            position = (float2)(lane_separation + lane_offset, car->pos_y);
+        } else if (lane <= 9) {
+            dir = (float2)(-1.0f, 0.0f);
+            position = (float2)(car->pos_x, lane_separation + lane_offset);
+        } else {
+            dir = (float2)(1.0f, 0.0f);
+            position = (float2)(car->pos_x, -lane_separation + lane_offset);
+        }
+        position += dir * target_speed * dt;
+    } else {
+        // Around the nearest loop ramp center
+        const float loop_offset = r->highway_half_width + r->loop_radius;
+        const float2 center = (float2)(car->pos_x >= 0.0f ? loop_offset : -loop_offset,
+                                       car->pos_y >= 0.0f ? loop_offset : -loop_offset);
+        const float2 to_car = (float2)(car->pos_x, car->pos_y) - center;
+        const float radius = max(length(to_car), 1.0f);
+        const float new_angle = atan2(to_car.y, to_car.x) - target_speed / radius * dt;
+        position = center + radius * (float2)(cos(new_angle), sin(new_angle));
+        dir = (float2)(sin(new_angle), -cos(new_angle));
+    }
+    
+    const float2 velocity = dir * target_speed;
+    car->acc_x = (velocity.x - car->vel_x) / dt;
+    car->acc_y = (velocity.y - car->vel_y) / dt;
+    car->pos_x = position.x;
+    car->pos_y = position.y;
+    car->vel_x = velocity.x;
+    car->vel_y = velocity.y;
+    car->heading = atan2(dir.y, dir.x);
+}
+
+// Along the planned path of cell centers, within the car's acceleration limits. A step
+// reaches at most the two waypoints the host uploaded, far more than a step covers.
+void move_on_grid(__global Car* car, const float target_speed, const float current_speed, const float dt) {
+    const float speed_change = clamp(target_speed - current_speed, -car->max_decel * dt, car->max_accel * dt);
+    const float new_speed = max(0.0f, current_speed + speed_change);
+    
+    const float2 start = (float2)(car->pos_x, car->pos_y);
+    float2 position = start;
+    float remaining = new_speed * dt;
+    for (uint reached = 0; reached < 2 && remaining > 0.0f && car->waypoints_left > 0; reached++) {
+        const float2 waypoint = (float2)(car->waypoint_x, car->waypoint_y);
+        const float segment = distance(waypoint, position);
+        if (segment > remaining) {
+            position += (waypoint - position) / segment * remaining;
+            remaining = 0.0f;
+        } else {
+            position = waypoint;
+            remaining -= segment;
+            car->waypoints_left -= 1;
+            car->waypoint_x = car->after_x;
+            car->waypoint_y = car->after_y;
+        }
+    }
+    
+    const float2 moved = position - start;
+    car->pos_x = position.x;
+    car->pos_y = position.y;
+    const float2 dir = length(moved) > 1e-4f ? normalize(moved) : grid_direction(car);
+    const float2 velocity = dir * new_speed;
+    car->acc_x = (velocity.x - car->vel_x) / dt;
+    car->acc_y = (velocity.y - car->vel_y) / dt;
+    car->vel_x = velocity.x;
+    car->vel_y = velocity.y;
+    car->heading = atan2(dir.y, dir.x);
+}
+
+// Philox4x32-10 counter-based generator, the same as `philox4x32` on the host, so both
+// backends draw the same numbers for a car in a step
+uint4 philox4x32(uint4 counter, uint2 key) {
+    for (int round = 0; round < 10; round++) {
+        if (round > 0) {
+            key.x += 0x9E3779B9u;
+            key.y += 0xBB67AE85u;
+        }
+        const uint hi0 = mul_hi(0xD2511F53u, counter.x);
+        const uint lo0 = 0xD2511F53u * counter.x;
+        const uint hi1 = mul_hi(0xCD9E8D57u, counter.z);
+        const uint lo1 = 0xCD9E8D57u * counter.z;
+        counter = (uint4)(hi1 ^ counter.y ^ key.x, lo1, hi0 ^ counter.w ^ key.y, lo0);
+    }
+    return counter;
+}
+
+// Uniform draw in [0, 1) from the top 24 bits of a random word
+float unit_float(const uint word) {
+    return (float)(word >> 8) / 16777216.0f;
+}
+
+// Standard normal draw from two random words, by the Box-Muller transform
+float standard_normal(const uint first, const uint second) {
+    const float radius = sqrt(-2.0f * log(unit_float(first) + 1.0f / 16777216.0f));
+    return radius * cos(2.0f * M_PI_F * unit_float(second));
+}
+
+// Whether `lane` is to the right of `other_lane` for the traffic using them
+bool is_right_of(const __global RouteParams* r, const uint lane, const uint other_lane) {
+    if (r->geometry_type == GEOMETRY_CLOVERLEAF && (other_lane <= 3 || (other_lane >= 10 && other_lane <= 12))) {
+        // Southbound and eastbound lanes are numbered right to left
+        return lane < other_lane;
+    }
+    return lane > other_lane;
+}
+
+// Lanes a car can move into from `lane`, written to `lanes`. Returns how many.
+uint adjacent_lanes(const __global RouteParams* r, const uint lane, uint* lanes) {
+    const uint candidates[2] = { lane > 0 ? lane - 1 : 0, lane + 1 };
+    uint count = 0;
+    for (uint i = 0; i < 2; i++) {
+        const uint candidate = candidates[i];
+        bool allowed;
+        if (r->geometry_type == GEOMETRY_CLOVERLEAF) {
+            // Carriageways of three lanes, never crossing into the opposite direction
+            allowed = candidate >= 1 && candidate <= 12 && lane <= 12 && (candidate - 1) / 3 == (lane - 1) / 3;
+        } else {
+            allowed = candidate >= 1 && candidate <= r->lane_count;
+        }
+        if (allowed) {
+            lanes[count++] = candidate;
+        }
+    }
+    return count;
+}
+
+// Whether `other` reaches into `lane` or is changing into it
+bool in_lane(const __global Car* other, const uint lane, const float lane_width) {
+    const float lateral_position = (float)other->current_lane + other->lateral_offset / lane_width;
+    return fabs(lateral_position - (float)lane) < 0.5f + other->width / (2.0f * lane_width) ||
+        other->target_lane == lane;
+}
+
+float rem_euclid(const float value, const float divisor) {
+    const float remainder = fmod(value, divisor);
+    return remainder < 0.0f ? remainder + divisor : remainder;
+}
+
+// Signed distance from `car` to `other` along the direction of travel
+float longitudinal_distance(const __global Car* car, const __global Car* other, const __global RouteParams* r) {
+    if (r->geometry_type == GEOMETRY_DONUT) {
+        // Arc length around the ring, traffic travels counter-clockwise
+        const float2 to_car = (float2)(car->pos_x - r->center_x, car->pos_y - r->center_y);
+        const float2 to_other = (float2)(other->pos_x - r->center_x, other->pos_y - r->center_y);
+        const float angle = rem_euclid(atan2(to_other.y, to_other.x) - atan2(to_car.y, to_car.x) + M_PI_F, 2.0f * M_PI_F) - M_PI_F;
+        return angle * length(to_car);
+    }
+    return (other->pos_x - car->pos_x) * cos(car->heading) + (other->pos_y - car->pos_y) * sin(car->heading);
+}
+
+// Bumper-to-bumper gap a car needs in the target lane, in front and behind
+float required_lane_change_gap(const __global Car* car) {
+    return LANE_CHANGE_GAP * max(car->length / REFERENCE_CAR_LENGTH, 1.0f);
+}
+
+bool is_lane_change_safe(
+    const __global Car* cars,
+    const uint car_count,
+    const uint gid,
+    const __global RouteParams* r,
+    const uint target_lane
+) {
+    const __global Car* car = &cars[gid];
+    const float2 to_car = (float2)(car->pos_x - r->center_x, car->pos_y - r->center_y);
+    const float car_angle = atan2(to_car.y, to_car.x);
+    const float required_gap = required_lane_change_gap(car);
+    
+    for (uint i = 0; i < car_count; i++) {
+        const __global Car* other = &cars[i];
+        if (i == gid || (other->behavior_flags & BEHAVIOR_ON_SHOULDER) || !in_lane(other, target_lane, r->lane_width)) {
+            continue;
+        }
+        
+        float angle_diff = fabs(atan2(other->pos_y - r->center_y, other->pos_x - r->center_x) - car_angle);
+        if (angle_diff > M_PI_F) {
+            angle_diff = 2.0f * M_PI_F - angle_diff;
+        }
+        const float gap = angle_diff * length(to_car) - (car->length + other->length) / 2.0f;
+        if (gap < required_gap) {
+            return false;
+        }
+    }
+    return true;
+}
+
+// What a car does about its lane this step
+#define DECISION_NONE 0u   // nothing decided yet, try the next rule
+#define DECISION_STAY 1u
+#define DECISION_CHANGE 2u
+#define DECISION_WAIT 3u   // has to leave its lane but waits for a gap, signalling towards it
+
+typedef struct {
+    uint kind;
+    uint lane;
+} LaneDecision;
+
+LaneDecision lane_decision(const uint kind, const uint lane) {
+    LaneDecision decision;
+    decision.kind = kind;
+    decision.lane = lane;
+    return decision;
+}
+
+// Change into the first of `lanes` with a safe gap, or wait for one in the first lane
+LaneDecision first_safe_lane(
+    const __global Car* cars,
+    const uint car_count,
+    const uint gid,
+    const __global RouteParams* r,
+    const uint* lanes,
+    const uint lane_count
+) {
+    for (uint i = 0; i < lane_count; i++) {
+        if (is_lane_change_safe(cars, car_count, gid, r, lanes[i])) {
+            return lane_decision(DECISION_CHANGE, lanes[i]);
+        }
+    }
+    return lane_count > 0 ? lane_decision(DECISION_WAIT, lanes[0]) : lane_decision(DECISION_STAY, 0);
+}
+
+// Lane change around a broken-down car, or a bus at its stop, close ahead in this lane
+LaneDecision breakdown_avoidance_decision(
+    const __global Car* cars,
+    const uint car_count,
+    const uint gid,
+    const __global RouteParams* r
+) {
+    const __global Car* car = &cars[gid];
+    int leader = -1;
+    float leader_gap = INFINITY;
+    for (uint i = 0; i < car_count; i++) {
+        const __global Car* other = &cars[i];
+        if (i == gid || (other->behavior_flags & BEHAVIOR_ON_SHOULDER) || !in_lane(other, car->current_lane, r->lane_width)) {
+            continue;
+        }
+        const float distance = longitudinal_distance(car, other, r);
+        if (distance < 0.0f || distance > NEIGHBOR_LOOKAHEAD) {
+            continue;
+        }
+        const float gap = distance - (car->length + other->length) / 2.0f;
+        if (gap < leader_gap) {
+            leader = (int)i;
+            leader_gap = gap;
+        }
+    }
+    if (leader < 0 || !(cars[leader].behavior_flags & BEHAVIOR_STOPPED) || leader_gap > BREAKDOWN_AVOIDANCE_DISTANCE) {
+        return lane_decision(DECISION_NONE, 0);
+    }
+    
+    uint lanes[2];
+    const uint lane_count = adjacent_lanes(r, car->current_lane, lanes);
+    return first_safe_lane(cars, car_count, gid, r, lanes, lane_count);
+}
+
+// Lane change one lane closer to the destination exit's lane, once the exit is near
+LaneDecision exit_lane_decision(
+    const __global Car* cars,
+    const uint car_count,
+    const uint gid,
+    const __global RouteParams* r,
+    const float simulation_time
+) {
+    const __global Car* car = &cars[gid];
+    if (r->geometry_type != GEOMETRY_DONUT || car->exit_lane == 0) {
+        return lane_decision(DECISION_NONE, 0);
+    }
+    const float2 to_car = (float2)(car->pos_x - r->center_x, car->pos_y - r->center_y);
+    const float distance = rem_euclid(car->exit_angle - atan2(to_car.y, to_car.x), 2.0f * M_PI_F) * length(to_car);
+    
+    // Each lane to cross takes a lane change and the settling time after it
+    const float lanes_to_cross = (float)abs_diff(car->current_lane, car->exit_lane);
+    const float speed = sqrt(car->vel_x * car->vel_x + car->vel_y * car->vel_y);
+    const float approach_distance = lanes_to_cross * 2.0f * r->lane_change_time * speed;
+    if (distance > max(approach_distance, EXIT_APPROACH_DISTANCE)) {
+        return lane_decision(DECISION_NONE, 0);
+    }
+    if (car->current_lane == car->exit_lane) {
+        return lane_decision(DECISION_STAY, 0);
+    }
+    
+    const uint target_lane = car->current_lane < car->exit_lane ? car->current_lane + 1 : car->current_lane - 1;
+    const float time_since_change = simulation_time - car->last_lane_change_time;
+    if (time_since_change >= r->lane_change_time && is_lane_change_safe(cars, car_count, gid, r, target_lane)) {
+        return lane_decision(DECISION_CHANGE, target_lane);
+    }
+    return lane_decision(DECISION_WAIT, target_lane);
+}
+
+// Same order of rules as the CPU's `check_lane_change_decision`, for the cars the host left
+// to the kernel
+LaneDecision lane_change_decision(
+    const __global Car* cars,
+    const uint car_count,
+    const uint gid,
+    const __global RouteParams* r,
+    const float dt,
+    const float simulation_time,
+    const uint2 behavior_key
+) {
+    const __global Car* car = &cars[gid];
+    // Grid paths are single-lane
+    if (car->target_lane != 0 || r->geometry_type == GEOMETRY_GRID) {
+        return lane_decision(DECISION_STAY, 0);
+    }
+    
+    LaneDecision decision = breakdown_avoidance_decision(cars, car_count, gid, r);
+    if (decision.kind != DECISION_NONE) {
+        return decision;
+    }
+    decision = exit_lane_decision(cars, car_count, gid, r, simulation_time);
+    if (decision.kind != DECISION_NONE) {
+        return decision;
+    }
+    
+    const float time_since_change = simulation_time - car->last_lane_change_time;
+    if (time_since_change < 60.0f / car->lane_change_frequency) {
+        return lane_decision(DECISION_STAY, 0);
+    }
+    
+    uint lanes[2];
+    const uint lane_count = adjacent_lanes(r, car->current_lane, lanes);
+    bool can_change_left = false;
+    bool can_change_right = false;
+    for (uint i = 0; i < lane_count; i++) {
+        can_change_left |= car->current_lane > 1 && lanes[i] == car->current_lane - 1;
+        can_change_right |= car->current_lane < r->lane_count && lanes[i] == car->current_lane + 1;
+    }
+    if (!can_change_left && !can_change_right) {
+        return lane_decision(DECISION_STAY, 0);
+    }
+    
+    const uint4 draws = philox4x32((uint4)(car->car_id, as_uint(simulation_time), 0, 0), behavior_key);
+    if (unit_float(draws.x) < car->lane_change_frequency / 60.0f * dt) {
+        uint target_lane;
+        if (can_change_left && can_change_right) {
+            target_lane = unit_float(draws.y) < 0.5f ? car->current_lane - 1 : car->current_lane + 1;
+        } else {
+            target_lane = can_change_left ? car->current_lane - 1 : car->current_lane + 1;
+        }
+        if (is_lane_change_safe(cars, car_count, gid, r, target_lane)) {
+            return lane_decision(DECISION_CHANGE, target_lane);
+        }
+    }
+    return lane_decision(DECISION_STAY, 0);
+}
+
+// Behavior decisions of the cars flagged BEHAVIOR_ON_DEVICE: target speed with the driver's
+// jitter, lane changes and turn signals. They go to `decisions` rather than the cars, which
+// the other work-items are still reading, and update_physics applies them.
+__kernel void update_behavior(
+    const __global Car* cars,
+    __global Decision* decisions,
+    const __global RouteParams* route,
+    const float dt,
+    const uint car_count,
+    const float simulation_time,
+    const uint2 noise_key,
+    const uint2 behavior_key,
+    const float weather_speed_factor,
+    const float visibility         // m drivers can see ahead, INFINITY when it does not limit speed
+) {
+    const uint gid = get_global_id(0);
+    if (gid >= car_count) return;
+    
+    const __global Car* car = &cars[gid];
+    const __global RouteParams* r = route;
+    __global Decision* decision = &decisions[gid];
+    decision->decided = car->behavior_flags & BEHAVIOR_ON_DEVICE;
+    if (!decision->decided) return;
+    
+    // Speed preference with some randomness, within the limits
+    float speed_noise = 1.0f;
+    if (car->speed_variance != 1.0f) {
+        const uint4 draws = philox4x32((uint4)(car->car_id, as_uint(simulation_time), 0, 0), noise_key);
+        speed_noise = 1.0f + fabs(car->speed_variance - 1.0f) * 0.1f * standard_normal(draws.x, draws.y);
+    }
+    const float speed = min(max(car->preferred_speed * car->speed_variance * speed_noise, r->min_speed), r->speed_limit);
+    // Bad weather slows everyone down, and in fog drivers keep to a speed they can stop
+    // from within sight, braking comfortably
+    decision->target_speed = min(speed * weather_speed_factor, sqrt(car->max_decel * visibility));
+    
+    const LaneDecision lane = lane_change_decision(cars, car_count, gid, r, dt, simulation_time, behavior_key);
+    decision->target_lane = lane.kind == DECISION_CHANGE ? lane.lane : car->target_lane;
+    decision->merge_intent = lane.kind == DECISION_WAIT ? lane.lane : 0;
+    decision->last_lane_change_time = lane.kind == DECISION_CHANGE ? simulation_time : car->last_lane_change_time;
+    
+    // Indicate the lane change in progress or waited for, or the exit about to be taken
+    const uint signal_lane = decision->target_lane != 0 ? decision->target_lane : decision->merge_intent;
+    decision->turn_signal = SIGNAL_NONE;
+    if (signal_lane != 0) {
+        decision->turn_signal = is_right_of(r, signal_lane, car->current_lane) ? SIGNAL_RIGHT : SIGNAL_LEFT;
+    } else if (r->geometry_type == GEOMETRY_DONUT && car->exit_lane != 0 && car->current_lane == car->exit_lane) {
+        const float2 to_car = (float2)(car->pos_x - r->center_x, car->pos_y - r->center_y);
+        const float distance = rem_euclid(car->exit_angle - atan2(to_car.y, to_car.x), 2.0f * M_PI_F) * length(to_car);
+        if (distance < EXIT_SIGNAL_DISTANCE) {
+            decision->turn_signal = car->exit_signal;
+        }
+    }
+}
+
+__kernel void update_physics(
+    __global Car* cars,
+    const __global Decision* decisions,
+    const __global RouteParams* route,
+    const float dt,
+    const uint car_count,
+    const float simulation_time
+) {
+    const uint gid = get_global_id(0);
+    if (gid >= car_count) return;
+    
+    __global Car* car = &cars[gid];
+    const __global RouteParams* r = route;
+    
+    const __global Decision* decision = &decisions[gid];
+    if (decision->decided) {
+        car->target_speed = decision->target_speed;
+        car->target_lane = decision->target_lane;
+        car->merge_intent = decision->merge_intent;
+        car->turn_signal = decision->turn_signal;
+        car->last_lane_change_time = decision->last_lane_change_time;
+    }
+    
+    // Find nearest car in front for collision avoidance
+    float front_car_speed = 0.0f;
+    float min_front_distance;
+    switch (r->geometry_type) {
+        case GEOMETRY_CLOVERLEAF:
+            min_front_distance = front_car_straight(cars, car_count, gid, &front_car_speed);
+            break;
+        case GEOMETRY_GRID:
+            min_front_distance = front_car_on_grid(cars, car_count, gid, r, &front_car_speed);
+            break;
+        default:
+            min_front_distance = front_car_on_ring(cars, car_count, gid, r, &front_car_speed);
+            break;
+    }
+    
+    // Calculate target speed based on traffic (matching CPU implementation)
+    float target_speed = car->target_speed;
+    
+    // Use route collision avoidance parameters (from config)
+    const float emergency_brake_distance = r->emergency_brake_distance;
+    const float warning_distance = r->warning_distance;
+    const float safety_margin = r->safety_margin;
+    
+    // Calculate following distance (matching CPU implementation)
+    const float current_speed = sqrt(car->vel_x * car->vel_x + car->vel_y * car->vel_y);
+    const float base_following_distance = r->following_distance * current_speed;
+    const float following_distance = base_following_distance * car->following_distance_factor + safety_margin;
+    
+    // Apply collision avoidance logic
+    if (min_front_distance != INFINITY) {
+        if (min_front_distance < emergency_brake_distance) {
+            target_speed = 0.0f; // Emergency brake
+        } else if (min_front_distance < warning_distance) {
+            const float brake_factor = (min_front_distance - emergency_brake_distance) / 
+                                     (warning_distance - emergency_brake_distance);
+            target_speed *= brake_factor;
+        } else if (min_front_distance < following_distance) {
+            // Maintain following distance - match front car speed
+            target_speed = min(front_car_speed, target_speed);
+        }
+    }
+    
+    // Apply speed limits
+    target_speed = clamp(target_speed, r->min_speed, r->speed_limit);
+    
+    switch (r->geometry_type) {
+        case GEOMETRY_CLOVERLEAF:
+            move_on_cloverleaf(car, r, target_speed, dt);
+            break;
+        case GEOMETRY_GRID:
+            move_on_grid(car, target_speed, current_speed, dt);
+            break;
+        default:
+            move_on_ring(car, r, target_speed, current_speed, dt);
+            break;
+    }
+}
+
+// Move the cars that are still on the road to the front of `out`. Each slot is the car's
+// new index, the exclusive prefix sum of the keep flags, or REMOVED if the car is gone.
+#define REMOVED 0xffffffffu
+
+__kernel void compact_cars(
+    const __global Car* cars,
+    __global Car* out,
+    const __global uint* slots,
+    const uint car_count
+) {
+    const uint gid = get_global_id(0);
+    if (gid >= car_count) return;
+    
+    const uint slot = slots[gid];
+    if (slot != REMOVED) {
+        out[slot] = cars[gid];
+    }
+}
 "#;
 
+/// Slot of a car that left the road since the last step, `REMOVED` in the kernel source
+const REMOVED_SLOT: u32 = u32::MAX;
+
+/// Unchanged cars between two changed ones that are uploaded with them rather than
+/// starting another transfer
+const DIRTY_GAP: usize = 32;
+
 impl GpuBackend {
     pub fn new(
         cars_config: CarsConfig, 
@@ -187,7 +857,7 @@ impl GpuBackend {
         // Get GPU device
         let device_ids = get_all_devices(CL_DEVICE_TYPE_GPU)
             .map_err(|e| anyhow!("Failed to get GPU devices: {}", e))?;
-        
+            
         if device_ids.is_empty() {
             return Err(anyhow!("No GPU devices found"));
         }
@@ -195,21 +865,27 @@ impl GpuBackend {
         let device = Device::new(device_ids[0]);
         let device_name = device.name().map_err(|e| anyhow!("Failed to get device name: {}", e))?;
         log::info!("Using GPU device: {}", device_name);
+        let compute_units = device.max_compute_units().map_err(|e| anyhow!("Failed to get compute units: {}", e))?;
+        let work_group_size = device.max_work_group_size().map_err(|e| anyhow!("Failed to get work group size: {}", e))?;
         
         // Create context and command queue
         let context = Context::from_device(&device)
             .map_err(|e| anyhow!("Failed to create OpenCL context: {}", e))?;
-        
+            
         let queue = CommandQueue::create_default(&context, CL_QUEUE_PROFILING_ENABLE)
             .map_err(|e| anyhow!("Failed to create command queue: {}", e))?;
-        
+            
         // Build program and kernel
         let program = Program::create_and_build_from_source(&context, PHYSICS_KERNEL_SOURCE, "")
             .map_err(|e| anyhow!("Failed to build OpenCL program: {}", e))?;
-        
+            
+        let behavior_kernel = Kernel::create(&program, "update_behavior")
+            .map_err(|e| anyhow!("Failed to create behavior kernel: {}", e))?;
         let physics_kernel = Kernel::create(&program, "update_physics")
             .map_err(|e| anyhow!("Failed to create physics kernel: {}", e))?;
-        
+        let compact_kernel = Kernel::create(&program, "compact_cars")
+            .map_err(|e| anyhow!("Failed to create compaction kernel: {}", e))?;
+            
         // Create route parameters buffer
         let route_params = Self::create_route_params(&route_config, &cars_config.collision_avoidance);
         let mut route_buffer = unsafe {
@@ -226,21 +902,71 @@ impl GpuBackend {
             queue.enqueue_write_buffer(&mut route_buffer, CL_TRUE, 0, route_bytes, &[])
         }
             .map_err(|e| anyhow!("Failed to write route data: {}", e))?;
-        
+            
         // Create traffic manager for CPU-side logic
-        let traffic_manager = TrafficManager::new(cars_config.clone(), route_config, seed);
+        let surface = route_config.route.surface.clone();
+        let exit_ramps = ExitRamps::from_route(&route_config);
+        let exits = route_config.route.exits.iter()
+            .map(|exit| (exit.id.clone(), ExitParams {
+                angle: exit.angle.to_radians(),
+                lane: exit.lane,
+                signal: if exit.is_exterior() { SIGNAL_RIGHT } else { SIGNAL_LEFT },
+            }))
+            .collect();
+        let collision_detector = CollisionDetector::new(&cars_config.collision_avoidance, &route_config.route.geometry);
         
-        let max_cars = cars_config.simulation.total_cars as usize;
+        // Decisions for plain cars are left to the behavior kernel
+        let mut traffic_manager = TrafficManager::new(cars_config.clone(), route_config, seed);
+        traffic_manager.set_device_behavior(true);
         
+        // The car arrays live as long as the backend, nothing is allocated per step
+        let max_cars = (cars_config.simulation.total_cars as usize).max(1);
+        let mut car_buffer = unsafe {
+            Buffer::create(&context, CL_MEM_READ_WRITE, max_cars, ptr::null_mut())
+                .map_err(|e| anyhow!("Failed to create car buffer: {}", e))?
+        };
+        let spare_buffer = unsafe {
+            Buffer::create(&context, CL_MEM_READ_WRITE, max_cars, ptr::null_mut())
+                .map_err(|e| anyhow!("Failed to create car buffer: {}", e))?
+        };
+        let slot_buffer = unsafe {
+            Buffer::create(&context, CL_MEM_READ_ONLY, max_cars, ptr::null_mut())
+                .map_err(|e| anyhow!("Failed to create slot buffer: {}", e))?
+        };
+        let decision_buffer = unsafe {
+            Buffer::create(&context, CL_MEM_READ_WRITE, max_cars, ptr::null_mut())
+                .map_err(|e| anyhow!("Failed to create decision buffer: {}", e))?
+        };
+        let staging = PinnedCars::new(&context, &queue, max_cars)?;
+        // The staging array mirrors the device buffer from the start
+        unsafe { queue.enqueue_write_buffer(&mut car_buffer, CL_TRUE, 0, staging.as_slice(), &[]) }
+            .map_err(|e| anyhow!("Failed to clear car buffer: {}", e))?;
+            
         Ok(Self {
-            context,
+            _context: context,
             queue,
-            program,
+            behavior_kernel,
             physics_kernel,
+            compact_kernel,
             traffic_manager,
-            car_buffer: None,
+            collision_detector,
+            car_buffer,
+            spare_buffer,
+            staging,
+            slot_buffer,
+            decision_buffer,
+            slots: Vec::with_capacity(max_cars),
+            resident: Vec::with_capacity(max_cars),
             route_buffer,
+            surface,
+            exit_ramps,
+            exits,
             max_cars,
+            observers: EventObservers::default(),
+            work_items: (compute_units as usize * work_group_size).max(1),
+            timing: None,
+            pipelined: false,
+            in_flight: None,
         })
     }
     
@@ -265,103 +991,365 @@ impl GpuBackend {
             emergency_brake_distance: collision_avoidance.emergency_brake_distance,
             warning_distance: collision_avoidance.warning_distance,
             safety_margin: collision_avoidance.safety_margin,
+            geometry_type: match geom.geometry_type.as_str() {
+                "cloverleaf" => GEOMETRY_CLOVERLEAF,
+                "grid" => GEOMETRY_GRID,
+                _ => GEOMETRY_DONUT,
+            },
+            highway_half_width: geom.highway_width.unwrap_or(40.0) / 2.0,
+            loop_radius: geom.loop_radius.unwrap_or(60.0),
+        }
+    }
+    
+    /// Match the cars the device holds against the state's cars and fill `slots` with the
+    /// new index of each device car. Removing a car moves the last car into its place and
+    /// spawned cars are appended, so device cars only ever move to lower indices; one that
+    /// moved up, after the state was replaced, counts as removed and is uploaded again.
+    /// Returns how many survive.
+    fn plan_compaction(&mut self, state: &SimulationState, count: usize) -> usize {
+        self.slots.clear();
+        let mut kept = 0;
+        for (i, id) in self.resident.iter().enumerate() {
+            let slot = if state.cars.get(i).is_some_and(|car| car.id == *id) {
+                Some(i)
+            } else {
+                state.car_position(*id)
+            };
+            match slot {
+                Some(slot) if slot <= i && slot < count => {
+                    self.slots.push(slot as u32);
+                    kept += 1;
+                }
+                _ => self.slots.push(REMOVED_SLOT),
+            }
         }
+        kept
     }
     
-    fn upload_cars_to_gpu(&mut self, state: &SimulationState) -> Result<()> {
-        if state.cars.is_empty() {
-            return Ok(());
+    /// Drop the removed cars from the device buffer and move the survivors to their new
+    /// places without a round trip: only the slots are uploaded, the kernel moves the
+    /// survivors into the spare buffer and the buffers swap. The staging array is compacted
+    /// the same way on the host so it keeps mirroring the device. Returns the slot upload and
+    /// kernel events, if any car moved.
+    fn compact_cars(&mut self, kept: usize) -> Result<Option<(Event, Event)>> {
+        // With no survivors there is nothing to move
+        let resident = self.slots.len();
+        if kept == 0 || self.slots.iter().enumerate().all(|(i, &slot)| slot == i as u32) {
+            return Ok(None);
         }
         
-        // Create or resize buffer if needed
-        let buffer_size = self.max_cars * std::mem::size_of::<GpuCar>();
-        if self.car_buffer.is_none() {
-            self.car_buffer = Some(unsafe {
-                Buffer::create(&self.context, CL_MEM_READ_WRITE, buffer_size, ptr::null_mut())
-                    .map_err(|e| anyhow!("Failed to create car buffer: {}", e))?
-            });
+        let upload = unsafe {
+            self.queue.enqueue_write_buffer(&mut self.slot_buffer, CL_FALSE, 0, &self.slots, &[])
         }
+            .map_err(|e| anyhow!("Failed to upload compaction slots: {}", e))?;
+        let kernel_event = unsafe {
+            ExecuteKernel::new(&self.compact_kernel)
+                .set_arg(&self.car_buffer)
+                .set_arg(&self.spare_buffer)
+                .set_arg(&self.slot_buffer)
+                .set_arg(&(resident as u32))
+                .set_global_work_size(resident)
+                .set_wait_event(&upload)
+                .enqueue_nd_range(&self.queue)
+                .map_err(|e| anyhow!("Failed to execute compaction kernel: {}", e))?
+        };
+        std::mem::swap(&mut self.car_buffer, &mut self.spare_buffer);
         
-        // Convert cars to GPU format
-        let mut gpu_cars = vec![GpuCar::default(); self.max_cars];
-        for (i, car) in state.cars.iter().enumerate() {
-            if i < self.max_cars {
-                gpu_cars[i] = GpuCar::from_car(car, state.time);
+        // Slots never move a car up, so the host array compacts in place front to back
+        let staged = self.staging.as_mut_slice();
+        for (i, &slot) in self.slots.iter().enumerate() {
+            if slot != REMOVED_SLOT {
+                staged[slot as usize] = staged[i];
             }
         }
-        
-        // Upload to GPU
-        if let Some(ref mut buffer) = self.car_buffer {
-            unsafe {
-                let car_bytes = std::slice::from_raw_parts(
-                    gpu_cars.as_ptr() as *const u8,
-                    gpu_cars.len() * std::mem::size_of::<GpuCar>()
-                );
-                self.queue.enqueue_write_buffer(buffer, CL_TRUE, 0, car_bytes, &[])
+        Ok(Some((upload, kernel_event)))
+    }
+    
+    /// Copy the first `count` cars into the pinned staging array and start uploading the
+    /// ranges that differ from what the device already holds: cars the host changed and
+    /// cars spawned, which fill the places of removed cars or come after the rest. Returns
+    /// the upload events.
+    fn upload_dirty_cars(&mut self, state: &SimulationState, count: usize) -> Result<Vec<Event>> {
+        let staged = self.staging.as_mut_slice();
+        let device_cars = self.traffic_manager.device_behavior_cars();
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        for (i, car) in state.cars.iter().take(count).enumerate() {
+            let on_device = device_cars.is_some_and(|cars| cars.contains(&car.id));
+            let exit = car.destination.as_ref().and_then(|destination| self.exits.get(destination));
+            let gpu_car = GpuCar::from_car(car, state.weather, &self.surface, on_device, exit);
+            if staged[i] != gpu_car {
+                staged[i] = gpu_car;
+                match ranges.last_mut() {
+                    Some((_, end)) if i - *end <= DIRTY_GAP => *end = i + 1,
+                    _ => ranges.push((i, i + 1)),
+                }
             }
-                .map_err(|e| anyhow!("Failed to upload cars to GPU: {}", e))?;
         }
         
-        Ok(())
+        // Non-blocking: the staging array is not touched again until the step's download completes
+        ranges.into_iter()
+            .map(|(first, end)| unsafe {
+                self.queue.enqueue_write_buffer(
+                    &mut self.car_buffer,
+                    CL_FALSE,
+                    first * std::mem::size_of::<GpuCar>(),
+                    &self.staging.as_slice()[first..end],
+                    &[],
+                )
+            }
+                .map_err(|e| anyhow!("Failed to upload cars to GPU: {}", e)))
+            .collect()
     }
     
-    fn download_cars_from_gpu(&mut self, state: &mut SimulationState) -> Result<()> {
-        if let Some(ref buffer) = self.car_buffer {
-            let mut gpu_cars = vec![GpuCar::default(); self.max_cars];
-            
-            unsafe {
-                let car_bytes = std::slice::from_raw_parts_mut(
-                    gpu_cars.as_mut_ptr() as *mut u8,
-                    gpu_cars.len() * std::mem::size_of::<GpuCar>()
-                );
-                self.queue.enqueue_read_buffer(buffer, CL_TRUE, 0, car_bytes, &[])
+    /// Copy the stepped cars from the staging array back into the state. Cars are matched
+    /// to the device's by id, as a pipelined step completes after the caller may have
+    /// edited the state: cars removed since are skipped and cars added since left as they are.
+    fn apply_downloaded_cars(&self, state: &mut SimulationState, ramp_motions: Vec<Option<RampMotion>>) {
+        let staged = self.staging.as_slice();
+        let time = state.time;
+        for ((i, id), ramp_motion) in self.resident.iter().enumerate().zip(ramp_motions) {
+            let pos = if state.cars.get(i).is_some_and(|car| car.id == *id) {
+                i
+            } else {
+                match state.car_position(*id) {
+                    Some(pos) => pos,
+                    None => continue,
+                }
+            };
+            let car = &mut state.cars[pos];
+            // The kernel knows nothing about crashes or breakdowns, halted cars keep their state
+            // and broken-down cars stop where they are
+            if car.crashed {
+                continue;
+            }
+            if car.breakdown.is_some() {
+                car.velocity = nalgebra::Vector2::zeros();
+                car.acceleration = nalgebra::Vector2::zeros();
+                continue;
+            }
+            if let (Some(motion), Some(ramp)) = (ramp_motion, car.exit_ramp.as_mut()) {
+                ramp.distance = motion.distance;
+                car.position = motion.position;
+                car.velocity = motion.velocity;
+                car.heading = motion.heading;
+                car.lateral_offset = motion.lateral_offset;
+                continue;
+            }
+            // Lane changes the behavior kernel started
+            let gpu_car = &staged[i];
+            if gpu_car.behavior_flags & BEHAVIOR_ON_DEVICE != 0 && gpu_car.target_lane != 0 && car.target_lane != Some(gpu_car.target_lane) {
+                state.events.push(SimulationEvent::LaneChangeStarted {
+                    car: car.id,
+                    from_lane: car.current_lane,
+                    to_lane: gpu_car.target_lane,
+                    time,
+                });
+            }
+            gpu_car.update_car(car);
+        }
+    }
+}
+
+impl Drop for GpuBackend {
+    fn drop(&mut self) {
+        // Unmap the staging array before its buffer is released
+        let unmapped = unsafe { self.queue.enqueue_unmap_mem_object(self.staging.buffer.get(), self.staging.ptr.cast(), &[]) };
+        if unmapped.is_err() || self.queue.finish().is_err() {
+            log::warn!("Failed to unmap the GPU staging buffer");
+        }
+    }
+}
+
+/// Host array of cars in page-locked memory: a buffer allocated with CL_MEM_ALLOC_HOST_PTR
+/// and mapped once for the backend's lifetime. The device transfers to and from it by DMA,
+/// without the driver copying through a bounce buffer as it does for pageable memory.
+struct PinnedCars {
+    buffer: Buffer<GpuCar>,
+    ptr: *mut GpuCar,
+    len: usize,
+}
+
+// The mapping is only used through the backend that owns it
+unsafe impl Send for PinnedCars {}
+
+impl PinnedCars {
+    fn new(context: &Context, queue: &CommandQueue, len: usize) -> Result<Self> {
+        let buffer = unsafe {
+            Buffer::<GpuCar>::create(context, CL_MEM_READ_WRITE | CL_MEM_ALLOC_HOST_PTR, len, ptr::null_mut())
+                .map_err(|e| anyhow!("Failed to create staging buffer: {}", e))?
+        };
+        let mut mapped: cl_mem = ptr::null_mut();
+        unsafe {
+            queue.enqueue_map_buffer(&buffer, CL_TRUE, CL_MAP_READ | CL_MAP_WRITE, 0, len * std::mem::size_of::<GpuCar>(), &mut mapped, &[])
+                .map_err(|e| anyhow!("Failed to map staging buffer: {}", e))?;
+        }
+        let ptr = mapped.cast::<GpuCar>();
+        // Start from the zeroed cars the device buffer is compared against
+        unsafe {
+            for i in 0..len {
+                ptr.add(i).write(GpuCar::default());
+            }
+        }
+        Ok(Self { buffer, ptr, len })
+    }
+    
+    fn as_slice(&self) -> &[GpuCar] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+    
+    fn as_mut_slice(&mut self) -> &mut [GpuCar] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+/// Device time from the start to the end of a finished command, zero if the device did
+/// not profile it
+fn event_duration(event: &Event) -> Duration {
+    match (event.profiling_command_start(), event.profiling_command_end()) {
+        (Ok(start), Ok(end)) => Duration::from_nanos(end.saturating_sub(start)),
+        _ => Duration::ZERO,
+    }
+}
+
+impl GpuBackend {
+    /// Start a step: the host work before the kernels, then the uploads, kernels and
+    /// download queued on the device without waiting for them
+    fn begin_step(&mut self, state: &mut SimulationState) -> Result<StepInFlight> {
+        // Handle traffic management on CPU (spawning, despawning, behavior decisions)
+        self.traffic_manager.update(state);
+        
+        // Apply the removals and spawns to the device buffer in place of a full upload
+        let count = state.cars.len().min(self.max_cars);
+        let kept = self.plan_compaction(state, count);
+        let compaction = self.compact_cars(kept)?;
+        self.resident.clear();
+        self.resident.extend(state.cars.iter().take(count).map(|car| car.id));
+        if count == 0 {
+            return Ok(StepInFlight { dt: state.dt, device: None });
+        }
+        
+        // Compaction, uploads, kernel and download are chained by events and run without
+        // the host waiting in between
+        let uploads = self.upload_dirty_cars(state, count)?;
+        let (noise_key, behavior_key) = self.traffic_manager.behavior_draw_keys();
+        let behavior_event = unsafe {
+            let mut kernel = ExecuteKernel::new(&self.behavior_kernel);
+            kernel
+                .set_arg(&self.car_buffer)
+                .set_arg(&self.decision_buffer)
+                .set_arg(&self.route_buffer)
+                .set_arg(&state.dt)
+                .set_arg(&(count as u32))
+                .set_arg(&state.time)
+                .set_arg(&noise_key)
+                .set_arg(&behavior_key)
+                .set_arg(&state.weather.speed_factor())
+                .set_arg(&state.weather.visibility().unwrap_or(f32::INFINITY))
+                .set_global_work_size(count);
+            for upload in &uploads {
+                kernel.set_wait_event(upload);
+            }
+            if let Some((_, compact_event)) = &compaction {
+                kernel.set_wait_event(compact_event);
             }
-                .map_err(|e| anyhow!("Failed to download cars from GPU: {}", e))?;
+            kernel.enqueue_nd_range(&self.queue)
+                .map_err(|e| anyhow!("Failed to execute behavior kernel: {}", e))?
+        };
+        let kernel_event = unsafe {
+            ExecuteKernel::new(&self.physics_kernel)
+                .set_arg(&self.car_buffer)
+                .set_arg(&self.decision_buffer)
+                .set_arg(&self.route_buffer)
+                .set_arg(&state.dt)
+                .set_arg(&(count as u32))
+                .set_arg(&state.time)
+                .set_global_work_size(count)
+                .set_wait_event(&behavior_event)
+                .enqueue_nd_range(&self.queue)
+                .map_err(|e| anyhow!("Failed to execute physics kernel: {}", e))?
+        };
+        let download = unsafe {
+            self.queue.enqueue_read_buffer(
+                &self.car_buffer,
+                CL_FALSE,
+                0,
+                &mut self.staging.as_mut_slice()[..count],
+                &[kernel_event.get()],
+            )
+        }
+            .map_err(|e| anyhow!("Failed to download cars from GPU: {}", e))?;
+        self.queue.flush()
+            .map_err(|e| anyhow!("Failed to submit GPU commands: {}", e))?;
             
-            // Update car data
-            for (i, car) in state.cars.iter_mut().enumerate() {
-                if i < self.max_cars {
-                    gpu_cars[i].update_car(car);
-                }
+        // The kernel knows nothing about off-ramps either. Exiting cars move along them on
+        // the CPU, from the states before the step, while the device works.
+        let ramp_motions: Vec<_> = state.cars.iter().take(count)
+            .map(|car| self.exit_ramps.advance(car, state, state.weather.braking_limit(car, &self.surface), state.dt))
+            .collect();
+        Ok(StepInFlight {
+            dt: state.dt,
+            device: Some(DeviceWork { count, ramp_motions, uploads, compaction, behavior_event, kernel_event, download }),
+        })
+    }
+    
+    /// Wait for the step's download and finish the step on the host
+    fn complete_step(&mut self, step: StepInFlight, state: &mut SimulationState) -> Result<()> {
+        let mut timing = GpuTiming::default();
+        if let Some(work) = step.device {
+            work.download.wait()
+                .map_err(|e| anyhow!("Failed to wait for GPU download: {}", e))?;
+            self.apply_downloaded_cars(state, work.ramp_motions);
+            
+            timing.kernel_time = event_duration(&work.behavior_event) + event_duration(&work.kernel_event);
+            timing.transfer_time = work.uploads.iter().map(event_duration).sum::<Duration>() + event_duration(&work.download);
+            if let Some((slot_upload, compact_event)) = &work.compaction {
+                timing.kernel_time += event_duration(compact_event);
+                timing.transfer_time += event_duration(slot_upload);
             }
+            timing.occupancy = (work.count as f32 / self.work_items as f32).min(1.0);
         }
         
+        // The clock moves on at the end of the physics step, as on the CPU
+        state.time += step.dt;
+        
+        // Collision detection runs on the CPU against the downloaded positions
+        self.collision_detector.update(state);
+        
+        self.timing = Some(timing);
         Ok(())
     }
+    
+    /// Drop the step left running, for a state that replaces the one it started from. The
+    /// device and the staging array still agree once its download is done.
+    fn discard_step(&mut self) {
+        if self.in_flight.take().is_some() && self.queue.finish().is_err() {
+            log::warn!("Failed to wait for the discarded GPU step");
+        }
+    }
 }
 
 impl SimulationBackend for GpuBackend {
     fn update(&mut self, state: &mut SimulationState) -> Result<()> {
-        // Handle traffic management on CPU (spawning, despawning, behavior decisions)
-        self.traffic_manager.update(state);
+        let step_start = Instant::now();
+        state.events.clear();
         
-        if !state.cars.is_empty() {
-            // Upload car data to GPU
-            self.upload_cars_to_gpu(state)?;
-            
-            // Execute physics kernel
-            if let Some(ref car_buffer) = self.car_buffer {
-                let kernel_event = unsafe {
-                    ExecuteKernel::new(&self.physics_kernel)
-                        .set_arg(car_buffer)
-                        .set_arg(&self.route_buffer)
-                        .set_arg(&state.dt)
-                        .set_arg(&(state.cars.len() as u32))
-                        .set_arg(&state.time)
-                        .set_global_work_size(state.cars.len())
-                        .enqueue_nd_range(&self.queue)
-                        .map_err(|e| anyhow!("Failed to execute physics kernel: {}", e))?
-                };
-                
-                // Wait for completion
-                kernel_event.wait()
-                    .map_err(|e| anyhow!("Failed to wait for kernel completion: {}", e))?;
-            }
-            
-            // Download updated car data
-            self.download_cars_from_gpu(state)?;
+        // Pipelined, the step left running by the last update is completed and the next one
+        // left running in turn, so the device works while the caller draws and records the
+        // state. A step still running when pipelining was turned off is completed first.
+        if let Some(step) = self.in_flight.take() {
+            self.complete_step(step, state)?;
+        }
+        let step = self.begin_step(state)?;
+        if self.pipelined {
+            self.in_flight = Some(step);
+        } else {
+            self.complete_step(step, state)?;
+        }
+        if let Some(timing) = self.timing.as_mut() {
+            timing.step_time = step_start.elapsed();
         }
         
+        self.observers.notify(&state.events);
         Ok(())
     }
     
@@ -372,11 +1360,79 @@ impl SimulationBackend for GpuBackend {
     fn supports_gpu(&self) -> bool {
         true
     }
+    
+    fn add_observer(&mut self, observer: EventObserver) {
+        self.observers.add(observer);
+    }
+    
+    fn gpu_timing(&self) -> Option<GpuTiming> {
+        self.timing
+    }
+    
+    fn gpu_memory_usage(&self) -> usize {
+        // The two car buffers, the slots, the decisions and the route; the pinned staging
+        // array is host memory
+        2 * self.max_cars * std::mem::size_of::<GpuCar>()
+            + self.max_cars * std::mem::size_of::<u32>()
+            + self.max_cars * std::mem::size_of::<GpuDecision>()
+            + std::mem::size_of::<RouteParams>()
+    }
 }
 
 impl GpuBackend {
-    pub fn spawn_manual_car(&mut self, behavior_name: &str, state: &mut SimulationState) {
-        self.traffic_manager.spawn_manual_car(behavior_name, state);
+    pub fn spawn_manual_car(&mut self, behavior_name: &str, state: &mut SimulationState) -> Result<CarId> {
+        self.traffic_manager.spawn_manual_car(behavior_name, state)
+    }
+    
+    pub fn spawn_car_at(&mut self, point: Point, behavior_name: &str, car_type: Option<&str>, state: &mut SimulationState) -> Result<CarId> {
+        self.traffic_manager.spawn_car_at(point, behavior_name, car_type, state)
+    }
+    
+    pub fn populate(&mut self, density: f32, state: &mut SimulationState) -> Result<usize> {
+        self.traffic_manager.populate(density, state)
+    }
+    
+    pub fn restore_checkpoint(&mut self, state: &SimulationState, seed: Option<u64>) {
+        self.discard_step();
+        self.traffic_manager.restore(state, seed);
+        self.collision_detector.reset();
+    }
+    
+    /// Start over with no cars, the device buffer is refilled from the next spawns
+    pub fn reset(&mut self, seed: Option<u64>) {
+        self.discard_step();
+        self.traffic_manager.reset(seed);
+        self.collision_detector.reset();
+        self.resident.clear();
+        self.timing = None;
+    }
+    
+    /// Leave each step running on the device when `update` returns and complete it in the
+    /// next update, trading a step of latency for a device that is never idle while the
+    /// caller draws. The state an update leaves is then a step behind the device: the last
+    /// completed step with the cars spawned and removed for the running one.
+    pub fn set_pipelined(&mut self, pipelined: bool) {
+        self.pipelined = pipelined;
+    }
+    
+    /// Complete the step left running on the device, if any, so the state catches up with it
+    pub fn finish_step(&mut self, state: &mut SimulationState) -> Result<()> {
+        if let Some(step) = self.in_flight.take() {
+            state.events.clear();
+            self.complete_step(step, state)?;
+            self.observers.notify(&state.events);
+        }
+        Ok(())
+    }
+    
+    /// Rebuild the OpenCL program and buffers for new configurations
+    pub fn reconfigure(&mut self, cars_config: CarsConfig, route_config: RouteConfig, state: &SimulationState, seed: Option<u64>) -> Result<()> {
+        let mut backend = Self::new(cars_config, route_config, seed)?;
+        backend.observers = std::mem::take(&mut self.observers);
+        backend.pipelined = self.pipelined;
+        *self = backend;
+        self.restore_checkpoint(state, seed);
+        Ok(())
     }
 }
 
@@ -397,10 +1453,18 @@ struct RouteParams {
     emergency_brake_distance: f32,
     warning_distance: f32,
     safety_margin: f32,
+    geometry_type: u32,
+    highway_half_width: f32,
+    loop_radius: f32,
 }
 
+// Geometry types the kernel dispatches on, matching its GEOMETRY_ defines
+const GEOMETRY_DONUT: u32 = 0;
+const GEOMETRY_CLOVERLEAF: u32 = 1;
+const GEOMETRY_GRID: u32 = 2;
+
 #[repr(C)]
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 struct GpuCar {
     pos_x: f32,
     pos_y: f32,
@@ -416,16 +1480,79 @@ struct GpuCar {
     preferred_speed: f32,
     current_lane: u32,
     target_lane: u32,
-    lane_change_progress: f32,
+    lateral_offset: f32,
     following_distance_factor: f32,
     target_speed: f32,
     reaction_time: f32,
     last_lane_change_time: f32,
-    padding: [f32; 2],
+    // Grid path: the waypoint being driven to, the one after it and how many are left
+    waypoint_x: f32,
+    waypoint_y: f32,
+    after_x: f32,
+    after_y: f32,
+    waypoints_left: u32,
+    // Behavior, decided by the behavior kernel for cars flagged `BEHAVIOR_ON_DEVICE`
+    car_id: u32,
+    behavior_flags: u32,
+    speed_variance: f32,
+    lane_change_frequency: f32,
+    exit_angle: f32,
+    exit_lane: u32,
+    exit_signal: u32,
+    merge_intent: u32,
+    turn_signal: u32,
+}
+
+// Behavior flags and turn signals, matching the kernel's defines
+const BEHAVIOR_ON_DEVICE: u32 = 1;
+const BEHAVIOR_STOPPED: u32 = 2;
+const BEHAVIOR_ON_SHOULDER: u32 = 4;
+const SIGNAL_LEFT: u32 = 1;
+const SIGNAL_RIGHT: u32 = 2;
+
+/// Behavior decided for a car by the behavior kernel and applied by the physics kernel,
+/// never seen by the host
+#[repr(C)]
+#[allow(dead_code)]
+struct GpuDecision {
+    target_speed: f32,
+    target_lane: u32,
+    merge_intent: u32,
+    turn_signal: u32,
+    last_lane_change_time: f32,
+    decided: u32,
+}
+
+/// Destination exit of a ring car, as the behavior kernel steers towards it
+#[derive(Debug, Clone, Copy)]
+struct ExitParams {
+    angle: f32, // radians
+    lane: u32,
+    signal: u32,
 }
 
 impl GpuCar {
-    fn from_car(car: &Car, _simulation_time: f32) -> Self {
+    /// The kernel knows nothing about weather, so it is folded into each car's braking and spacing.
+    /// `on_device` cars have their behavior decided by the kernel, heading for `exit` on a ring.
+    fn from_car(car: &Car, weather: Weather, surface: &RoadSurface, on_device: bool, exit: Option<&ExitParams>) -> Self {
+        let mut behavior_flags = 0;
+        if on_device {
+            behavior_flags |= BEHAVIOR_ON_DEVICE;
+        }
+        if car.breakdown.is_some() || car.is_dwelling() {
+            behavior_flags |= BEHAVIOR_STOPPED;
+        }
+        if car.is_on_shoulder() {
+            behavior_flags |= BEHAVIOR_ON_SHOULDER;
+        }
+        let (waypoint, after, waypoints_left) = match &car.grid_path {
+            Some(path) if !path.is_complete() => {
+                let waypoint = path.waypoints[path.next_waypoint];
+                let after = path.waypoints.get(path.next_waypoint + 1).copied().unwrap_or(waypoint);
+                (waypoint, after, (path.waypoints.len() - path.next_waypoint) as u32)
+            }
+            _ => (car.position, car.position, 0),
+        };
         Self {
             pos_x: car.position.x,
             pos_y: car.position.y,
@@ -437,16 +1564,33 @@ impl GpuCar {
             length: car.length,
             width: car.width,
             max_accel: car.max_acceleration,
-            max_decel: car.max_deceleration,
+            max_decel: weather.braking_limit(car, surface),
             preferred_speed: car.preferred_speed,
             current_lane: car.current_lane,
             target_lane: car.target_lane.unwrap_or(0),
-            lane_change_progress: car.lane_change_progress,
-            following_distance_factor: car.behavior.following_distance_factor,
+            lateral_offset: car.lateral_offset,
+            following_distance_factor: car.behavior.headway_factor() * weather.headway_factor(),
             target_speed: car.behavior.target_speed,
             reaction_time: car.behavior.reaction_time,
             last_lane_change_time: car.behavior.last_lane_change_time,
-            padding: [0.0; 2],
+            waypoint_x: waypoint.x,
+            waypoint_y: waypoint.y,
+            after_x: after.x,
+            after_y: after.y,
+            waypoints_left,
+            car_id: car.id.index,
+            behavior_flags,
+            speed_variance: car.behavior.speed_variance,
+            lane_change_frequency: car.behavior.lane_change_frequency,
+            exit_angle: exit.map_or(0.0, |exit| exit.angle),
+            exit_lane: exit.map_or(0, |exit| exit.lane),
+            exit_signal: exit.map_or(0, |exit| exit.signal),
+            merge_intent: car.behavior.merge_intent.unwrap_or(0),
+            turn_signal: match car.turn_signal {
+                Some(TurnSignal::Left) => SIGNAL_LEFT,
+                Some(TurnSignal::Right) => SIGNAL_RIGHT,
+                None => 0,
+            },
         }
     }
     
@@ -458,7 +1602,7 @@ impl GpuCar {
         car.acceleration.x = self.acc_x;
         car.acceleration.y = self.acc_y;
         car.heading = self.heading;
-        car.lane_change_progress = self.lane_change_progress;
+        car.lateral_offset = self.lateral_offset;
         
         // Update target lane if changed
         if self.target_lane != 0 {
@@ -470,6 +1614,18 @@ impl GpuCar {
         // Update behavior state
         car.behavior.target_speed = self.target_speed;
         car.behavior.last_lane_change_time = self.last_lane_change_time;
+        car.behavior.merge_intent = (self.merge_intent != 0).then_some(self.merge_intent);
+        car.turn_signal = match self.turn_signal {
+            SIGNAL_LEFT => Some(TurnSignal::Left),
+            SIGNAL_RIGHT => Some(TurnSignal::Right),
+            _ => None,
+        };
+        
+        // Waypoints the kernel drove through
+        if let Some(path) = &mut car.grid_path {
+            let len = path.waypoints.len();
+            path.next_waypoint = path.next_waypoint.max(len - (self.waypoints_left as usize).min(len));
+        }
     }
 }
 
@@ -1,21 +1,44 @@
-use crate::simulation::SimulationState;
+use crate::simulation::{SimulationState, CarId, Point, EventObserver, GpuTiming};
 use anyhow::Result;
 
+#[cfg(all(feature = "opencl", not(target_arch = "wasm32")))]
 pub mod gpu;
+#[cfg(not(all(feature = "opencl", not(target_arch = "wasm32"))))]
+pub mod no_gpu;
 pub mod cpu;
+pub mod world;
+pub mod divergence;
+pub mod runner;
 
 pub use cpu::*;
+pub use world::*;
+pub use divergence::*;
+pub use runner::*;
+#[cfg(all(feature = "opencl", not(target_arch = "wasm32")))]
 pub use gpu::*;
+#[cfg(not(all(feature = "opencl", not(target_arch = "wasm32"))))]
+pub use no_gpu::*;
 
 pub trait SimulationBackend {
     fn update(&mut self, state: &mut SimulationState) -> Result<()>;
     fn get_name(&self) -> &'static str;
     fn supports_gpu(&self) -> bool;
+    /// Run `observer` for every event of each following tick, after the tick is complete
+    fn add_observer(&mut self, observer: EventObserver);
+    /// Device timings of the latest step, for backends that run on a GPU
+    fn gpu_timing(&self) -> Option<GpuTiming> {
+        None
+    }
+    /// Bytes the backend holds in device buffers
+    fn gpu_memory_usage(&self) -> usize {
+        0
+    }
 }
 
 pub enum ComputeBackend {
-    Cpu(CpuBackend),
-    Gpu(GpuBackend),
+    Cpu(Box<CpuBackend>),
+    Gpu(Box<GpuBackend>),
+    World(Box<WorldBackend>),
 }
 
 impl ComputeBackend {
@@ -24,7 +47,7 @@ impl ComputeBackend {
         route_config: crate::config::RouteConfig,
         seed: Option<u64>
     ) -> Self {
-        ComputeBackend::Cpu(CpuBackend::new(cars_config, route_config, seed))
+        ComputeBackend::Cpu(Box::new(CpuBackend::new(cars_config, route_config, seed)))
     }
     
     pub fn new_gpu(
@@ -32,7 +55,16 @@ impl ComputeBackend {
         route_config: crate::config::RouteConfig,
         seed: Option<u64>
     ) -> Result<Self> {
-        Ok(ComputeBackend::Gpu(GpuBackend::new(cars_config, route_config, seed)?))
+        Ok(ComputeBackend::Gpu(Box::new(GpuBackend::new(cars_config, route_config, seed)?)))
+    }
+    
+    /// Every route of `world` on the CPU, with cars transferring between them
+    pub fn new_world(
+        cars_config: crate::config::CarsConfig,
+        world: &crate::config::World,
+        seed: Option<u64>
+    ) -> Self {
+        ComputeBackend::World(Box::new(WorldBackend::new(cars_config, world, seed)))
     }
 }
 
@@ -41,6 +73,7 @@ impl SimulationBackend for ComputeBackend {
         match self {
             ComputeBackend::Cpu(backend) => backend.update(state),
             ComputeBackend::Gpu(backend) => backend.update(state),
+            ComputeBackend::World(backend) => backend.update(state),
         }
     }
     
@@ -48,6 +81,7 @@ impl SimulationBackend for ComputeBackend {
         match self {
             ComputeBackend::Cpu(backend) => backend.get_name(),
             ComputeBackend::Gpu(backend) => backend.get_name(),
+            ComputeBackend::World(backend) => backend.get_name(),
         }
     }
     
@@ -55,20 +89,127 @@ impl SimulationBackend for ComputeBackend {
         match self {
             ComputeBackend::Cpu(backend) => backend.supports_gpu(),
             ComputeBackend::Gpu(backend) => backend.supports_gpu(),
+            ComputeBackend::World(backend) => backend.supports_gpu(),
+        }
+    }
+    
+    fn add_observer(&mut self, observer: EventObserver) {
+        match self {
+            ComputeBackend::Cpu(backend) => backend.add_observer(observer),
+            ComputeBackend::Gpu(backend) => backend.add_observer(observer),
+            ComputeBackend::World(backend) => backend.add_observer(observer),
+        }
+    }
+    
+    fn gpu_timing(&self) -> Option<GpuTiming> {
+        match self {
+            ComputeBackend::Cpu(backend) => backend.gpu_timing(),
+            ComputeBackend::Gpu(backend) => backend.gpu_timing(),
+            ComputeBackend::World(backend) => backend.gpu_timing(),
+        }
+    }
+    
+    fn gpu_memory_usage(&self) -> usize {
+        match self {
+            ComputeBackend::Cpu(backend) => backend.gpu_memory_usage(),
+            ComputeBackend::Gpu(backend) => backend.gpu_memory_usage(),
+            ComputeBackend::World(backend) => backend.gpu_memory_usage(),
         }
     }
 }
 
 impl ComputeBackend {
-    pub fn spawn_manual_car(&mut self, behavior_name: &str, state: &mut SimulationState) {
+    /// Spawn a car with `behavior_name` at the first entry with room, see
+    /// `TrafficManager::spawn_manual_car`
+    pub fn spawn_manual_car(&mut self, behavior_name: &str, state: &mut SimulationState) -> Result<CarId> {
         match self {
             ComputeBackend::Cpu(backend) => backend.spawn_manual_car(behavior_name, state),
             ComputeBackend::Gpu(backend) => backend.spawn_manual_car(behavior_name, state),
+            ComputeBackend::World(backend) => backend.spawn_manual_car(behavior_name, state),
+        }
+    }
+    
+    /// Place a car on the lane under `point`, see `TrafficManager::spawn_car_at`
+    pub fn spawn_car_at(&mut self, point: Point, behavior_name: &str, car_type: Option<&str>, state: &mut SimulationState) -> Result<CarId> {
+        match self {
+            ComputeBackend::Cpu(backend) => backend.spawn_car_at(point, behavior_name, car_type, state),
+            ComputeBackend::Gpu(backend) => backend.spawn_car_at(point, behavior_name, car_type, state),
+            ComputeBackend::World(backend) => backend.spawn_car_at(point, behavior_name, car_type, state),
         }
     }
     
-    pub fn mark_car_for_exit(&mut self, behavior_name: &str, state: &mut SimulationState) -> bool {
-        // This is handled directly in the simulation state
+    /// Fill the empty road to `density` vehicles per km in each lane, see `TrafficManager::populate`
+    pub fn populate(&mut self, density: f32, state: &mut SimulationState) -> Result<usize> {
+        match self {
+            ComputeBackend::Cpu(backend) => backend.populate(density, state),
+            ComputeBackend::Gpu(backend) => backend.populate(density, state),
+            ComputeBackend::World(backend) => backend.populate(density, state),
+        }
+    }
+    
+    /// Continue from a state loaded with `SimulationState::load`, re-seeding the backend's
+    /// random streams
+    pub fn restore_checkpoint(&mut self, state: &SimulationState, seed: Option<u64>) {
+        match self {
+            ComputeBackend::Cpu(backend) => backend.restore_checkpoint(state, seed),
+            ComputeBackend::Gpu(backend) => backend.restore_checkpoint(state, seed),
+            ComputeBackend::World(backend) => backend.restore_checkpoint(state, seed),
+        }
+    }
+    
+    /// Start a new run on the same configuration, exactly as a fresh backend created with
+    /// `seed` would. Pair it with a new `SimulationState`; observers are kept.
+    pub fn reset(&mut self, seed: Option<u64>) {
+        match self {
+            ComputeBackend::Cpu(backend) => backend.reset(seed),
+            ComputeBackend::Gpu(backend) => backend.reset(seed),
+            ComputeBackend::World(backend) => backend.reset(seed),
+        }
+    }
+    
+    /// Rebuild the backend with new configurations and continue from `state`, as when
+    /// restoring a checkpoint. Registered observers are kept.
+    pub fn reconfigure(
+        &mut self,
+        cars_config: crate::config::CarsConfig,
+        route_config: crate::config::RouteConfig,
+        state: &SimulationState,
+        seed: Option<u64>
+    ) -> Result<()> {
+        match self {
+            ComputeBackend::Cpu(backend) => {
+                backend.reconfigure(cars_config, route_config, state, seed);
+                Ok(())
+            }
+            ComputeBackend::Gpu(backend) => backend.reconfigure(cars_config, route_config, state, seed),
+            ComputeBackend::World(_) => Err(anyhow::anyhow!("Multi-route worlds cannot be reconfigured while running")),
+        }
+    }
+    
+    /// Overlap each GPU step with the frame after it, the state lagging the device by a
+    /// step (see `GpuBackend::set_pipelined`). Returns whether the backend pipelines at
+    /// all; the CPU backends step in place and ignore it.
+    pub fn set_pipelined(&mut self, pipelined: bool) -> bool {
+        match self {
+            ComputeBackend::Gpu(backend) => {
+                backend.set_pipelined(pipelined);
+                true
+            }
+            ComputeBackend::Cpu(_) | ComputeBackend::World(_) => false,
+        }
+    }
+    
+    /// Complete a pipelined step still running on the device, so `state` is up to date
+    pub fn finish_step(&mut self, state: &mut SimulationState) -> Result<()> {
+        match self {
+            ComputeBackend::Gpu(backend) => backend.finish_step(state),
+            ComputeBackend::Cpu(_) | ComputeBackend::World(_) => Ok(()),
+        }
+    }
+    
+    /// Send a car with `behavior_name` to the next exit it reaches, returning which one. The
+    /// traffic manager of either backend takes it off the road from the host state.
+    pub fn mark_car_for_exit(&mut self, behavior_name: &str, state: &mut SimulationState) -> Option<CarId> {
         state.mark_car_for_exit(behavior_name)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,80 @@
+use crate::simulation::{SimulationState, CarId, Point, EventObserver, GpuTiming};
+use crate::config::{CarsConfig, RouteConfig};
+use anyhow::{Result, anyhow};
+use std::convert::Infallible;
+use super::SimulationBackend;
+
+/// Stand-in for the OpenCL backend in builds without the `opencl` feature and on wasm32.
+/// It can never be created, so callers fall back to the CPU backend as they do when no
+/// OpenCL device is found.
+pub struct GpuBackend {
+    never: Infallible,
+}
+
+impl GpuBackend {
+    pub fn new(
+        _cars_config: CarsConfig,
+        _route_config: RouteConfig,
+        _seed: Option<u64>
+    ) -> Result<Self> {
+        Err(anyhow!("built without OpenCL support"))
+    }
+    
+    pub fn spawn_manual_car(&mut self, _behavior_name: &str, _state: &mut SimulationState) -> Result<CarId> {
+        match self.never {}
+    }
+    
+    pub fn spawn_car_at(&mut self, _point: Point, _behavior_name: &str, _car_type: Option<&str>, _state: &mut SimulationState) -> Result<CarId> {
+        match self.never {}
+    }
+    
+    pub fn populate(&mut self, _density: f32, _state: &mut SimulationState) -> Result<usize> {
+        match self.never {}
+    }
+    
+    pub fn restore_checkpoint(&mut self, _state: &SimulationState, _seed: Option<u64>) {
+        match self.never {}
+    }
+    
+    pub fn reset(&mut self, _seed: Option<u64>) {
+        match self.never {}
+    }
+    
+    pub fn set_pipelined(&mut self, _pipelined: bool) {
+        match self.never {}
+    }
+    
+    pub fn finish_step(&mut self, _state: &mut SimulationState) -> Result<()> {
+        match self.never {}
+    }
+    
+    pub fn reconfigure(&mut self, _cars_config: CarsConfig, _route_config: RouteConfig, _state: &SimulationState, _seed: Option<u64>) -> Result<()> {
+        match self.never {}
+    }
+}
+
+impl SimulationBackend for GpuBackend {
+    fn update(&mut self, _state: &mut SimulationState) -> Result<()> {
+        match self.never {}
+    }
+    
+    fn get_name(&self) -> &'static str {
+        match self.never {}
+    }
+    
+    fn supports_gpu(&self) -> bool {
+        match self.never {}
+    }
+    
+    fn add_observer(&mut self, _observer: EventObserver) {
+        match self.never {}
+    }
+    
+    fn gpu_timing(&self) -> Option<GpuTiming> {
+        match self.never {}
+    }
+    
+    fn gpu_memory_usage(&self) -> usize {
+        match self.never {}
+    }
+}
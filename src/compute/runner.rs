@@ -0,0 +1,362 @@
+use super::{ComputeBackend, SimulationBackend};
+use crate::simulation::{GpuTiming, SimulationState};
+use anyhow::Result;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use web_time::{Duration, Instant};
+
+/// Real time the simulation catches up on at once after falling behind; beyond it the
+/// simulation runs slower than asked instead
+const MAX_CATCH_UP: f32 = 0.25;
+
+/// Time an unthrottled simulation steps for between looking at its commands
+const BATCH_TIME: Duration = Duration::from_millis(5);
+
+/// Run after every step on the simulation thread, to record the states no snapshot shows
+pub type StepHook = Box<dyn FnMut(&SimulationState) -> Result<()> + Send>;
+
+type Call = Box<dyn FnOnce(&mut ComputeBackend, &mut SimulationState) -> Box<dyn FnOnce() + Send> + Send>;
+
+/// Latest of a series of values handed from one thread to another without either waiting
+/// on the other. The writer fills its own copy and swaps it in, the reader swaps the
+/// newest one out whenever it is ready for it; between the two copies they hold and the
+/// one in the middle, nothing is allocated or copied after the first round.
+pub fn triple_buffer<T>(back: T, middle: T) -> (TripleWriter<T>, TripleReader<T>) {
+    let shared = Arc::new(Mutex::new(Middle { value: middle, fresh: false }));
+    (TripleWriter { back, shared: shared.clone() }, TripleReader { shared })
+}
+
+struct Middle<T> {
+    value: T,
+    fresh: bool, // written since the reader last took it
+}
+
+pub struct TripleWriter<T> {
+    back: T,
+    shared: Arc<Mutex<Middle<T>>>,
+}
+
+impl<T> TripleWriter<T> {
+    /// The value to fill before the next `publish`, holding whatever the reader handed back
+    pub fn back_mut(&mut self) -> &mut T {
+        &mut self.back
+    }
+    
+    /// Hand the filled value over, in place of one the reader did not take yet
+    pub fn publish(&mut self) {
+        let mut middle = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+        std::mem::swap(&mut self.back, &mut middle.value);
+        middle.fresh = true;
+    }
+    
+    /// Whether the reader took the last value published
+    pub fn taken(&self) -> bool {
+        !self.shared.lock().unwrap_or_else(|e| e.into_inner()).fresh
+    }
+}
+
+pub struct TripleReader<T> {
+    shared: Arc<Mutex<Middle<T>>>,
+}
+
+impl<T> TripleReader<T> {
+    /// Swap `front` for the newest value if one was published since the last call; `front`
+    /// goes back to the writer to be filled again
+    pub fn swap_latest(&mut self, front: &mut T) -> bool {
+        let mut middle = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+        if !middle.fresh {
+            return false;
+        }
+        std::mem::swap(front, &mut middle.value);
+        middle.fresh = false;
+        true
+    }
+}
+
+/// What the simulation thread did between two snapshots
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StepReport {
+    pub steps: u64,
+    pub step_time: Duration, // wall-clock time spent stepping and running the step hooks
+    pub gpu_timing: Option<GpuTiming>, // device timings of the steps, summed
+    pub gpu_memory: usize, // bytes the backend held in device buffers as of the snapshot
+}
+
+impl StepReport {
+    /// The part of these running totals that came after `earlier`
+    fn since(&self, earlier: &StepReport) -> StepReport {
+        let gpu_timing = match (self.gpu_timing, earlier.gpu_timing) {
+            (Some(now), Some(before)) => Some(GpuTiming {
+                kernel_time: now.kernel_time.saturating_sub(before.kernel_time),
+                transfer_time: now.transfer_time.saturating_sub(before.transfer_time),
+                step_time: now.step_time.saturating_sub(before.step_time),
+                occupancy: now.occupancy,
+            }),
+            (now, _) => now,
+        };
+        StepReport {
+            steps: self.steps - earlier.steps,
+            step_time: self.step_time.saturating_sub(earlier.step_time),
+            gpu_timing: gpu_timing.filter(|_| self.steps > earlier.steps),
+            gpu_memory: self.gpu_memory,
+        }
+    }
+}
+
+struct Snapshot {
+    state: SimulationState,
+    totals: StepReport, // since the simulation thread started, so none are lost with a skipped snapshot
+}
+
+enum Command {
+    Run { paused: bool, speed: f32 },
+    Step(u32),
+    Call(Call),
+    AddHook(StepHook),
+    Stop,
+}
+
+/// Steps a compute backend on a thread of its own, paced to a multiple of real time or
+/// as fast as it goes, and hands snapshots of the state to the render loop through a
+/// triple buffer. Rendering never holds up the simulation and the simulation never holds
+/// up a frame; every step is still seen by the step hooks.
+pub struct SimulationRunner {
+    commands: Sender<Command>,
+    snapshots: TripleReader<Box<Snapshot>>,
+    front: Box<Snapshot>,
+    taken: StepReport, // totals of the snapshot taken last
+    errors: Receiver<anyhow::Error>,
+    running: (bool, f32), // paused and speed last asked for
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SimulationRunner {
+    /// Start a paused simulation of `state` on the backend `make_backend` builds. The backend
+    /// is built on the simulation thread, so it need not be sendable between threads.
+    pub fn spawn<F>(make_backend: F, state: SimulationState) -> Result<Self>
+    where
+        F: FnOnce() -> ComputeBackend + Send + 'static,
+    {
+        let empty = || Box::new(Snapshot { state: state.clone(), totals: StepReport::default() });
+        let (writer, snapshots) = triple_buffer(empty(), empty());
+        let front = empty();
+        let (commands, receiver) = mpsc::channel();
+        let (error_sender, errors) = mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name("simulation".to_string())
+            .spawn(move || {
+                let worker = Worker {
+                    backend: make_backend(),
+                    state,
+                    hooks: Vec::new(),
+                    snapshots: writer,
+                    totals: StepReport::default(),
+                    errors: error_sender,
+                };
+                worker.run(receiver);
+            })?;
+        
+        Ok(Self {
+            commands,
+            snapshots,
+            front,
+            taken: StepReport::default(),
+            errors,
+            running: (true, 1.0),
+            thread: Some(thread),
+        })
+    }
+    
+    /// Run at `speed` times real time, as fast as the backend goes for an infinite speed,
+    /// or hold still while `paused`
+    pub fn set_running(&mut self, paused: bool, speed: f32) {
+        if self.running != (paused, speed) {
+            self.running = (paused, speed);
+            let _ = self.commands.send(Command::Run { paused, speed });
+        }
+    }
+    
+    /// Take `steps` steps while paused, publishing a snapshot after the last
+    pub fn step(&self, steps: u32) {
+        let _ = self.commands.send(Command::Step(steps));
+    }
+    
+    /// Run `hook` after every following step
+    pub fn add_step_hook(&self, hook: StepHook) {
+        let _ = self.commands.send(Command::AddHook(hook));
+    }
+    
+    /// Run `f` on the backend and state between two steps and wait for its result. A
+    /// snapshot of the state it leaves is published before this returns.
+    pub fn call<F, R>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut ComputeBackend, &mut SimulationState) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (reply, result) = mpsc::sync_channel(1);
+        let call: Call = Box::new(move |backend, state| {
+            let value = f(backend, state);
+            Box::new(move || {
+                let _ = reply.send(value);
+            })
+        });
+        self.commands.send(Command::Call(call))
+            .map_err(|_| anyhow::anyhow!("the simulation thread has stopped"))?;
+        result.recv().map_err(|_| anyhow::anyhow!("the simulation thread has stopped"))
+    }
+    
+    /// Swap `state` for the newest snapshot, if one was published since the last call,
+    /// and report the steps taken to get there
+    pub fn take_latest(&mut self, state: &mut SimulationState) -> Option<StepReport> {
+        if !self.snapshots.swap_latest(&mut self.front) {
+            return None;
+        }
+        std::mem::swap(state, &mut self.front.state);
+        let report = self.front.totals.since(&self.taken);
+        self.taken = self.front.totals;
+        Some(report)
+    }
+    
+    /// Bytes the backend held in device buffers as of the latest snapshot taken
+    pub fn gpu_memory_usage(&self) -> usize {
+        self.taken.gpu_memory
+    }
+    
+    /// The first error a step or step hook failed with since the last call; the
+    /// simulation pauses on it until set running again
+    pub fn take_error(&mut self) -> Option<anyhow::Error> {
+        let error = self.errors.try_recv().ok()?;
+        self.running.0 = true;
+        Some(error)
+    }
+}
+
+impl Drop for SimulationRunner {
+    fn drop(&mut self) {
+        let _ = self.commands.send(Command::Stop);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+struct Worker {
+    backend: ComputeBackend,
+    state: SimulationState,
+    hooks: Vec<StepHook>,
+    snapshots: TripleWriter<Box<Snapshot>>,
+    totals: StepReport, // steps since the thread started
+    errors: Sender<anyhow::Error>,
+}
+
+impl Worker {
+    fn run(mut self, commands: Receiver<Command>) {
+        let (mut paused, mut speed) = (true, 1.0);
+        let mut owed = 0.0; // simulated seconds due but not stepped yet
+        let mut last_pace = Instant::now();
+        
+        loop {
+            let command = if paused {
+                match commands.recv() {
+                    Ok(command) => Some(command),
+                    Err(_) => break,
+                }
+            } else {
+                // Sleep until the next step is due, waking up for commands
+                let wait = if speed == f32::INFINITY {
+                    Duration::ZERO
+                } else {
+                    let due = (self.state.dt - owed).max(0.0) / speed;
+                    Duration::from_secs_f32((due - last_pace.elapsed().as_secs_f32()).max(0.0))
+                };
+                match commands.recv_timeout(wait) {
+                    Ok(command) => Some(command),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            };
+            
+            match command {
+                Some(Command::Run { paused: now_paused, speed: now_speed }) => {
+                    if paused && !now_paused {
+                        owed = 0.0;
+                        last_pace = Instant::now();
+                    }
+                    paused = now_paused;
+                    speed = now_speed;
+                }
+                Some(Command::Step(steps)) => {
+                    for _ in 0..steps {
+                        if !self.step() {
+                            break;
+                        }
+                    }
+                    self.publish();
+                }
+                Some(Command::Call(call)) => {
+                    let reply = call(&mut self.backend, &mut self.state);
+                    self.publish();
+                    reply();
+                }
+                Some(Command::AddHook(hook)) => self.hooks.push(hook),
+                Some(Command::Stop) => break,
+                None => {
+                    let completed = if speed == f32::INFINITY {
+                        let start = Instant::now();
+                        let mut completed = true;
+                        while completed && start.elapsed() < BATCH_TIME {
+                            completed = self.step();
+                        }
+                        completed
+                    } else {
+                        let now = Instant::now();
+                        owed += now.duration_since(last_pace).as_secs_f32().min(MAX_CATCH_UP) * speed;
+                        last_pace = now;
+                        let steps = (owed / self.state.dt).floor() as u32;
+                        owed -= steps as f32 * self.state.dt;
+                        (0..steps).all(|_| self.step())
+                    };
+                    if !completed {
+                        paused = true;
+                        self.publish();
+                    } else if self.snapshots.taken() {
+                        self.publish();
+                    }
+                }
+            }
+        }
+    }
+    
+    /// Take one step and run the hooks on it, reporting a failure to the render loop
+    fn step(&mut self) -> bool {
+        let start = Instant::now();
+        let result = self.backend.update(&mut self.state).and_then(|()| {
+            self.state.update_car_speeds();
+            self.state.active_cars = self.state.cars.len() as u32;
+            self.hooks.iter_mut().try_for_each(|hook| hook(&self.state))
+        });
+        
+        self.totals.steps += 1;
+        self.totals.step_time += start.elapsed();
+        if let Some(timing) = self.backend.gpu_timing() {
+            self.totals.gpu_timing.get_or_insert_with(GpuTiming::default).add(&timing);
+        }
+        match result {
+            Ok(()) => true,
+            Err(e) => {
+                let _ = self.errors.send(e);
+                false
+            }
+        }
+    }
+    
+    /// Copy the state into the snapshot being filled and hand it over
+    fn publish(&mut self) {
+        self.totals.gpu_memory = self.backend.gpu_memory_usage();
+        let snapshot = self.snapshots.back_mut();
+        snapshot.state.clone_from(&self.state);
+        snapshot.totals = self.totals;
+        self.snapshots.publish();
+    }
+}
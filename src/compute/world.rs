@@ -0,0 +1,398 @@
+use crate::simulation::{SimulationState, SimulationEvent, Car, CarId, Point, Vec2, StopLine, CollisionEvent, Conflict, SafetyLog, EventObserver, EventObservers};
+use crate::config::{CarsConfig, World};
+use anyhow::{Result, anyhow};
+use std::collections::BTreeMap;
+use super::{CpuBackend, SimulationBackend};
+
+/// One route of a world, simulated in its own coordinates by its own CPU backend
+struct Region {
+    id: String,
+    offset: Vec2,
+    backend: CpuBackend,
+    state: SimulationState, // cars only while the region steps, controllers' state throughout
+    trips_seen: usize,      // trips of the region already copied into the world's log
+    conflicts_seen: usize,  // conflicts of the region already copied into the world's safety log
+    traversals_seen: usize, // travel time traversals of the region already copied into the world's log
+}
+
+/// Cars leaving region `from` through `exit` carry on at `entry` of region `to`
+struct Link {
+    from: usize,
+    exit: String,
+    to: usize,
+    entry: String,
+}
+
+/// A car between routes, waiting for room at the entry it transfers to
+struct Transfer {
+    car: Car,
+    to: usize,
+    entry: String,
+}
+
+/// Counts carried over from a checkpoint, which the regions started over from
+#[derive(Default)]
+struct Carried {
+    spawned: u32,
+    collisions: u32,
+    exit_counts: BTreeMap<String, u32>,
+    safety: SafetyLog, // distributions only
+}
+
+/// Several routes simulated side by side in one world. Each route keeps its own physics,
+/// traffic manager and controllers; the host state holds the cars of every route in world
+/// coordinates, keyed by `Car::route`. Entries, exits, trips and travel time segments in
+/// the host state are named `<route id>/<entry, exit or segment id>`.
+pub struct WorldBackend {
+    regions: Vec<Region>,
+    links: Vec<Link>,
+    waiting: Vec<Transfer>,
+    carried: Carried,
+    observers: EventObservers,
+}
+
+/// Each region draws from its own random streams, the first from those of `seed`
+fn region_seed(seed: Option<u64>, index: usize) -> Option<u64> {
+    seed.map(|seed| seed.wrapping_add(index as u64))
+}
+
+impl WorldBackend {
+    pub fn new(cars_config: CarsConfig, world: &World, seed: Option<u64>) -> Self {
+        let links: Vec<Link> = world.transfers.iter().filter_map(|transfer| Some(Link {
+            from: world.region_index(&transfer.from)?,
+            exit: transfer.exit.clone(),
+            to: world.region_index(&transfer.to)?,
+            entry: transfer.entry.clone(),
+        })).collect();
+        let regions = world.regions.iter().enumerate().map(|(index, region)| {
+            let mut backend = CpuBackend::new(cars_config.clone(), region.route.clone(), region_seed(seed, index));
+            // Cars keep their ids when they change route
+            backend.set_transfer_exits(links.iter().filter(|link| link.from == index).map(|link| link.exit.clone()));
+            Region {
+                id: region.id.clone(),
+                offset: Vec2::new(region.offset[0], region.offset[1]),
+                backend,
+                state: SimulationState::new(0.0),
+                trips_seen: 0,
+                conflicts_seen: 0,
+                traversals_seen: 0,
+            }
+        }).collect();
+        
+        Self {
+            regions,
+            links,
+            waiting: Vec::new(),
+            carried: Carried::default(),
+            observers: EventObservers::default(),
+        }
+    }
+    
+    /// Cars waiting to join their next route
+    pub fn transferring(&self) -> usize {
+        self.waiting.len()
+    }
+    
+    /// Hand every region its cars from the host state, in the region's coordinates
+    fn split(&mut self, state: &mut SimulationState) {
+        let last = self.regions.len() - 1;
+        for mut car in std::mem::take(&mut state.cars) {
+            let region = &mut self.regions[car.route.min(last)];
+            car.position -= region.offset;
+            region.state.cars.push(car);
+        }
+        state.reindex_cars();
+        for region in &mut self.regions {
+            region.state.reindex_cars();
+            region.state.time = state.time;
+            region.state.dt = state.dt;
+            region.state.active_cars = region.state.cars.len() as u32;
+            region.state.signal_overrides = state.signal_overrides.clone();
+        }
+    }
+    
+    /// Take the cars back into the host state in world coordinates, with the world's
+    /// counters and the controllers' state of every region
+    fn merge(&mut self, state: &mut SimulationState) {
+        state.total_spawned = self.carried.spawned;
+        state.total_collisions = self.carried.collisions;
+        state.exit_counts = self.carried.exit_counts.clone();
+        state.signals.clear();
+        state.intersections.clear();
+        state.junctions.clear();
+        state.ramp_meters.clear();
+        state.merges.clear();
+        state.bus_stops.clear();
+        state.queues.clear();
+        state.collision_events.clear();
+        let mut safety = self.carried.safety.distributions();
+        
+        for (index, region) in self.regions.iter_mut().enumerate() {
+            let offset = region.offset;
+            for mut car in region.state.cars.drain(..) {
+                car.position += offset;
+                car.route = index;
+                state.cars.push(car);
+            }
+            region.state.reindex_cars();
+            state.total_spawned += region.state.total_spawned;
+            state.total_collisions += region.state.total_collisions;
+            for (exit, count) in &region.state.exit_counts {
+                *state.exit_counts.entry(format!("{}/{}", region.id, exit)).or_insert(0) += count;
+            }
+            
+            state.signals.extend(region.state.signals.iter().cloned().map(|mut signal| {
+                signal.position += offset;
+                match &mut signal.stop_line {
+                    StopLine::Ring { center, .. } | StopLine::Straight { center, .. } => *center += offset,
+                }
+                signal
+            }));
+            state.intersections.extend(region.state.intersections.iter().cloned().map(|mut intersection| {
+                intersection.position += offset;
+                intersection
+            }));
+            state.junctions.extend(region.state.junctions.iter().cloned().map(|mut junction| {
+                junction.position += offset;
+                junction
+            }));
+            state.ramp_meters.extend(region.state.ramp_meters.iter().cloned().map(|mut meter| {
+                meter.position += offset;
+                meter
+            }));
+            state.merges.extend(region.state.merges.iter().cloned().map(|mut merge| {
+                merge.position += offset;
+                merge.center = merge.center.map(|center| center + offset);
+                merge
+            }));
+            state.bus_stops.extend(region.state.bus_stops.iter().cloned().map(|mut stop| {
+                stop.id = format!("{}/{}", region.id, stop.id);
+                stop
+            }));
+            // Queue positions stay along the region's own road
+            state.queues.extend(region.state.queues.iter().cloned());
+            state.collision_events.extend(region.state.collision_events.iter().map(|event| shift_collision(event, offset)));
+            
+            for trip in &region.state.trips.trips()[region.trips_seen..] {
+                let mut trip = trip.clone();
+                trip.entry = format!("{}/{}", region.id, trip.entry);
+                trip.exit = format!("{}/{}", region.id, trip.exit);
+                state.trips.record(trip);
+            }
+            region.trips_seen = region.state.trips.len();
+            
+            safety.add_distributions(&region.state.safety);
+            for conflict in &region.state.safety.conflicts()[region.conflicts_seen..] {
+                state.safety.record(shift_conflict(conflict, offset));
+            }
+            region.conflicts_seen = region.state.safety.conflicts().len();
+            
+            for traversal in &region.state.travel_times.traversals()[region.traversals_seen..] {
+                let mut traversal = traversal.clone();
+                traversal.segment = format!("{}/{}", region.id, traversal.segment);
+                state.travel_times.record(traversal);
+            }
+            region.traversals_seen = region.state.travel_times.len();
+        }
+        state.safety.ttc_exposure = safety.ttc_exposure;
+        state.safety.pet_counts = safety.pet_counts;
+        state.reindex_cars();
+        state.active_cars = state.cars.len() as u32;
+        state.weather = self.regions[0].state.weather;
+    }
+    
+    /// Copy the region's events in `range` into the host state, in world terms
+    fn forward_region_events(region: &Region, range: std::ops::Range<usize>, state: &mut SimulationState) {
+        state.events.extend(region.state.events[range].iter().map(|event| match event {
+            SimulationEvent::CarSpawned { car, entry, time } => SimulationEvent::CarSpawned {
+                car: *car,
+                entry: format!("{}/{}", region.id, entry),
+                time: *time,
+            },
+            SimulationEvent::CarExited { car, exit, time } => SimulationEvent::CarExited {
+                car: *car,
+                exit: format!("{}/{}", region.id, exit),
+                time: *time,
+            },
+            SimulationEvent::CollisionDetected(collision) => SimulationEvent::CollisionDetected(shift_collision(collision, region.offset)),
+            SimulationEvent::ConflictDetected(conflict) => SimulationEvent::ConflictDetected(shift_conflict(conflict, region.offset)),
+            event => event.clone(),
+        }));
+    }
+    
+    /// Run `action` on region `index` with its cars, then take them back
+    fn on_region<T>(&mut self, index: usize, state: &mut SimulationState, action: impl FnOnce(&mut CpuBackend, &mut SimulationState) -> T) -> T {
+        self.split(state);
+        let region = &mut self.regions[index];
+        let from = region.state.events.len();
+        state.swap_car_ids(&mut region.state);
+        let result = action(&mut region.backend, &mut region.state);
+        state.swap_car_ids(&mut region.state);
+        Self::forward_region_events(region, from..region.state.events.len(), state);
+        self.merge(state);
+        result
+    }
+    
+    /// Spawn a car with `behavior_name` at the first entry with room, trying the routes in
+    /// order
+    pub fn spawn_manual_car(&mut self, behavior_name: &str, state: &mut SimulationState) -> Result<CarId> {
+        let mut result = Err(anyhow!("The world has no routes"));
+        for index in 0..self.regions.len() {
+            result = self.on_region(index, state, |backend, state| backend.spawn_manual_car(behavior_name, state));
+            if result.is_ok() {
+                break;
+            }
+        }
+        result
+    }
+    
+    /// Place a car on the lane under `point` of whichever route has one there
+    pub fn spawn_car_at(&mut self, point: Point, behavior_name: &str, car_type: Option<&str>, state: &mut SimulationState) -> Result<CarId> {
+        let mut result = Err(anyhow!("The world has no routes"));
+        for index in 0..self.regions.len() {
+            let local = point - self.regions[index].offset;
+            result = self.on_region(index, state, |backend, state| backend.spawn_car_at(local, behavior_name, car_type, state));
+            if result.is_ok() {
+                break;
+            }
+        }
+        result
+    }
+    
+    /// Fill the lanes of every route to `density` vehicles per km
+    pub fn populate(&mut self, density: f32, state: &mut SimulationState) -> Result<usize> {
+        let mut placed = 0;
+        for index in 0..self.regions.len() {
+            placed += self.on_region(index, state, |backend, state| backend.populate(density, state))?;
+        }
+        Ok(placed)
+    }
+    
+    /// Continue from a checkpoint of this world. The cars return to their routes and the
+    /// counts carry on; junction reservations, merge queues, ramp meter queues and bus stop
+    /// statistics start over.
+    pub fn restore_checkpoint(&mut self, state: &SimulationState, seed: Option<u64>) {
+        self.waiting.clear();
+        self.carried = Carried {
+            spawned: state.total_spawned,
+            collisions: state.total_collisions,
+            exit_counts: state.exit_counts.clone(),
+            safety: state.safety.distributions(),
+        };
+        let mut host = state.clone();
+        self.split(&mut host);
+        for (index, region) in self.regions.iter_mut().enumerate() {
+            let cars = std::mem::take(&mut region.state.cars);
+            region.state = SimulationState::new(state.dt);
+            region.state.cars = cars;
+            region.state.reindex_cars();
+            region.state.time = state.time;
+            region.state.active_cars = region.state.cars.len() as u32;
+            region.trips_seen = 0;
+            region.conflicts_seen = 0;
+            region.traversals_seen = 0;
+            region.backend.restore_checkpoint(&region.state, region_seed(seed, index));
+            region.state.cars.clear();
+            region.state.active_cars = 0;
+        }
+    }
+    
+    /// Start a new run on the same world, exactly as a fresh backend created with `seed` would
+    pub fn reset(&mut self, seed: Option<u64>) {
+        self.waiting.clear();
+        self.carried = Carried::default();
+        for (index, region) in self.regions.iter_mut().enumerate() {
+            region.backend.reset(region_seed(seed, index));
+            region.state = SimulationState::new(0.0);
+            region.trips_seen = 0;
+            region.conflicts_seen = 0;
+            region.traversals_seen = 0;
+        }
+    }
+}
+
+/// `collision` moved from a region's coordinates into the world's
+fn shift_collision(collision: &CollisionEvent, offset: Vec2) -> CollisionEvent {
+    CollisionEvent {
+        position_a: collision.position_a + offset,
+        position_b: collision.position_b + offset,
+        ..collision.clone()
+    }
+}
+
+/// `conflict` moved from a region's coordinates into the world's
+fn shift_conflict(conflict: &Conflict, offset: Vec2) -> Conflict {
+    Conflict {
+        position: conflict.position + offset,
+        ..conflict.clone()
+    }
+}
+
+impl SimulationBackend for WorldBackend {
+    fn update(&mut self, state: &mut SimulationState) -> Result<()> {
+        state.events.clear();
+        self.split(state);
+        
+        for index in 0..self.regions.len() {
+            let region = &mut self.regions[index];
+            // Exited cars are gone by the end of the step, remember the ones that may transfer
+            let transfers_out = self.links.iter().any(|link| link.from == index);
+            let before = if transfers_out { region.state.cars.clone() } else { Vec::new() };
+            // Every region hands out ids from the world's slots, so no two cars share one
+            state.swap_car_ids(&mut region.state);
+            let stepped = region.backend.update(&mut region.state);
+            state.swap_car_ids(&mut region.state);
+            stepped?;
+            
+            for event in &region.state.events {
+                let SimulationEvent::CarExited { car, exit, .. } = event else {
+                    continue;
+                };
+                let Some(link) = self.links.iter().find(|link| link.from == index && &link.exit == exit) else {
+                    continue;
+                };
+                if let Some(car) = before.iter().find(|before| before.id == *car) {
+                    self.waiting.push(Transfer { car: car.clone(), to: link.to, entry: link.entry.clone() });
+                }
+            }
+        }
+        
+        let stepped: Vec<usize> = self.regions.iter().map(|region| region.state.events.len()).collect();
+        
+        // Cars between routes join the next one as soon as its entry has room, in the order
+        // they left
+        let regions = &mut self.regions;
+        self.waiting.retain(|transfer| {
+            let region = &mut regions[transfer.to];
+            state.swap_car_ids(&mut region.state);
+            let admitted = region.backend.admit_car(&transfer.car, &transfer.entry, &mut region.state);
+            state.swap_car_ids(&mut region.state);
+            admitted.is_err()
+        });
+        
+        // Cars leave one route before they join the next
+        for (region, &stepped) in self.regions.iter().zip(&stepped) {
+            Self::forward_region_events(region, 0..stepped, state);
+        }
+        for (region, &stepped) in self.regions.iter().zip(&stepped) {
+            Self::forward_region_events(region, stepped..region.state.events.len(), state);
+        }
+        self.merge(state);
+        state.time = self.regions[0].state.time;
+        
+        self.observers.notify(&state.events);
+        Ok(())
+    }
+    
+    fn get_name(&self) -> &'static str {
+        "CPU (world)"
+    }
+    
+    fn supports_gpu(&self) -> bool {
+        false
+    }
+    
+    fn add_observer(&mut self, observer: EventObserver) {
+        self.observers.add(observer);
+    }
+}
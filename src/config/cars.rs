@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 use anyhow::{Result, anyhow};
 use std::collections::HashMap;
 use super::Validate;
+use super::units;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CarsConfig {
@@ -9,27 +10,97 @@ pub struct CarsConfig {
     pub car_types: Vec<CarType>,
     pub behavior: HashMap<String, DriverBehavior>,
     pub collision_avoidance: CollisionAvoidance,
+    #[serde(default)]
+    pub lane_change: LaneChangeConfig,
+    #[serde(default)]
+    pub breakdowns: BreakdownConfig,
+    #[serde(default)]
+    pub reaction: ReactionConfig,
+    #[serde(default)]
+    pub safety: SafetyConfig,
+    #[serde(default)]
+    pub electric: ElectricConfig,
+    #[serde(default)]
+    pub queues: QueueConfig,
     pub traffic_flow: TrafficFlow,
     pub random: RandomConfig,
     pub performance: PerformanceConfig,
+    #[serde(default)]
+    pub colors: ColorConfig,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SimulationParams {
     pub total_cars: u32,
     pub spawn_rate: f32,
+    #[serde(with = "units::time")]
     pub simulation_duration: f32,
+    #[serde(default)]
+    pub closed_loop: bool, // cars leaving the road re-enter at an entry, keeping the fleet at total_cars
+    #[serde(default)]
+    pub ring_cars: Option<u32>, // cars placed evenly around a donut at the start, none spawn or leave after
+    #[serde(default)]
+    pub initial: Option<InitialTraffic>, // traffic on the road at the start, instead of filling up from the entries
+}
+
+/// Traffic the road starts with: every lane filled evenly to a density, given directly or
+/// as a level of service, with the cars driving at the speed their spacing allows
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct InitialTraffic {
+    #[serde(default)]
+    pub density: Option<f32>, // vehicles per km in each lane
+    #[serde(default)]
+    pub level_of_service: Option<String>, // "A" to "F", the middle of the level's density range
+}
+
+impl InitialTraffic {
+    /// Vehicles per km in each lane the road starts with
+    pub fn density(&self) -> Option<f32> {
+        self.density.or_else(|| self.level_of_service.as_deref().and_then(level_of_service_density))
+    }
+}
+
+/// Density in vehicles per km per lane typical of a freeway level of service, the middle of
+/// its range in the Highway Capacity Manual (A up to 7, B 7-11, C 11-16, D 16-22, E 22-28,
+/// F beyond)
+pub fn level_of_service_density(level: &str) -> Option<f32> {
+    match level {
+        "A" => Some(4.0),
+        "B" => Some(9.0),
+        "C" => Some(13.5),
+        "D" => Some(19.0),
+        "E" => Some(25.0),
+        "F" => Some(35.0),
+        _ => None,
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CarType {
     pub id: String,
     pub weight: u32,
+    #[serde(with = "units::distance")]
     pub length: f32,
+    #[serde(with = "units::distance")]
     pub width: f32,
     pub max_acceleration: f32,
     pub max_deceleration: f32,
+    #[serde(with = "units::speed")]
     pub preferred_speed: f32,
+    #[serde(default)]
+    pub breakdown_probability: f32, // chance of a mechanical breakdown per minute of driving
+    #[serde(default)]
+    pub mass: Option<f32>,          // kg, with engine_power limits acceleration at speed
+    #[serde(default)]
+    pub engine_power: Option<f32>,  // kW
+    #[serde(default)]
+    pub heavy: bool,                // subject to the route's heavy vehicle lane bans
+    #[serde(default)]
+    pub bus: bool,                  // serves the route's bus stops before leaving
+    #[serde(default)]
+    pub rollover_threshold: Option<f32>, // m/s^2 sideways, tall vehicles take curves no harder
+    #[serde(default)]
+    pub battery_capacity: Option<f32>,   // kWh, makes the type an electric vehicle, needs a mass
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -39,30 +110,290 @@ pub struct DriverBehavior {
     pub following_distance_factor: f32,
     pub lane_change_frequency: f32,
     pub speed_variance: f32,
+    #[serde(with = "units::time")]
     pub reaction_time: f32,
-    pub exit_probability: f32,
+    #[serde(default)]
+    pub script: Option<String>, // Rhai script overriding decisions, needs the `scripting` feature
+    #[serde(default)]
+    pub tailgates: bool,        // closes up on slower cars ahead, flashing them
+    #[serde(default = "default_merge_courtesy")]
+    pub merge_courtesy: String, // "open" a gap for cars waiting to merge in ahead, "refuse" them by closing up, or "none"
+    #[serde(default)]
+    pub comfort_lateral_acceleration: Option<f32>, // m/s^2 sideways the driver takes curves at, unlimited if unset
+    #[serde(default)]
+    pub blind_spot_miss_probability: f32, // chance the driver overlooks a car in their blind spot when changing lanes
+}
+
+fn default_merge_courtesy() -> String {
+    "none".to_string()
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CollisionAvoidance {
+    #[serde(with = "units::distance")]
     pub safety_margin: f32,
+    #[serde(with = "units::distance")]
     pub emergency_brake_distance: f32,
+    #[serde(with = "units::distance")]
     pub warning_distance: f32,
+    #[serde(with = "units::distance")]
     pub lateral_safety_margin: f32,
+    #[serde(default = "default_crash_response")]
+    pub crash_response: String, // "none", "halt" or "remove" crashed cars
+}
+
+fn default_crash_response() -> String {
+    "none".to_string()
+}
+
+/// How drivers decide to change lanes. The default "random" model changes lanes by chance,
+/// "mobil" weighs the acceleration gain against the disadvantage imposed on other drivers.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LaneChangeConfig {
+    pub model: String,                // "random" or "mobil"
+    pub politeness: f32,              // weight of other drivers' acceleration changes (0 = selfish)
+    pub acceleration_threshold: f32,  // m/s^2 gain required before changing lanes
+    pub keep_right_bias: f32,         // m/s^2 bonus for moving right, penalty for moving left
+    pub safe_deceleration: f32,       // m/s^2 strongest braking a lane change may force on the new follower
+}
+
+impl Default for LaneChangeConfig {
+    fn default() -> Self {
+        Self {
+            model: "random".to_string(),
+            politeness: 0.3,
+            acceleration_threshold: 0.2,
+            keep_right_bias: 0.3,
+            safe_deceleration: 4.0,
+        }
+    }
+}
+
+/// What happens to a car that breaks down: it coasts to a stop in its lane, may be pulled
+/// onto the shoulder, and drives on once repaired (or is towed away from the shoulder).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BreakdownConfig {
+    #[serde(with = "units::time")]
+    pub min_duration: f32,         // seconds a broken-down car stays stopped
+    #[serde(with = "units::time")]
+    pub max_duration: f32,
+    pub shoulder_probability: f32, // chance a car stopped in the rightmost lane is pulled onto the shoulder
+    #[serde(with = "units::time")]
+    pub shoulder_delay: f32,       // seconds after the breakdown before it is pulled over
+}
+
+impl Default for BreakdownConfig {
+    fn default() -> Self {
+        Self {
+            min_duration: 60.0,
+            max_duration: 300.0,
+            shoulder_probability: 0.5,
+            shoulder_delay: 20.0,
+        }
+    }
+}
+
+/// How drivers perceive the car ahead. With `delay` on they act on it as it was their
+/// behavior's reaction_time ago, and distractions now and then make them slower still.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ReactionConfig {
+    pub delay: bool,                     // act on the car ahead after the reaction time instead of instantly
+    pub distraction_rate: f32,           // distractions per minute of driving, needs delay
+    #[serde(with = "units::time")]
+    pub distraction_min_duration: f32,   // seconds a distraction lasts
+    #[serde(with = "units::time")]
+    pub distraction_max_duration: f32,
+    #[serde(with = "units::time")]
+    pub distraction_delay: f32,          // seconds added to the reaction time while distracted
+}
+
+impl Default for ReactionConfig {
+    fn default() -> Self {
+        Self {
+            delay: false,
+            distraction_rate: 0.0,
+            distraction_min_duration: 1.0,
+            distraction_max_duration: 3.0,
+            distraction_delay: 1.5,
+        }
+    }
+}
+
+impl ReactionConfig {
+    /// Seconds before acting on what a driver with `reaction_time` sees, longer while distracted
+    pub fn delay_for(&self, reaction_time: f32, distracted: bool) -> f32 {
+        if distracted { reaction_time + self.distraction_delay } else { reaction_time }
+    }
+}
+
+/// Thresholds of the surrogate safety measures below which an encounter between two cars
+/// counts as a traffic conflict
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SafetyConfig {
+    #[serde(with = "units::time")]
+    pub ttc_threshold: f32, // seconds to collision of a follower closing in on the car ahead
+    #[serde(with = "units::time")]
+    pub pet_threshold: f32, // seconds between one car leaving a merge or crossing point and a conflicting one arriving
+}
+
+impl Default for SafetyConfig {
+    fn default() -> Self {
+        Self {
+            ttc_threshold: 1.5,
+            pet_threshold: 1.0,
+        }
+    }
+}
+
+/// What counts as a queue: at least `min_cars` cars in a row in one lane, all slower than
+/// `speed_threshold`, each within `max_gap` of the next
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct QueueConfig {
+    #[serde(with = "units::speed")]
+    pub speed_threshold: f32, // m/s
+    #[serde(with = "units::distance")]
+    pub max_gap: f32,         // meters between the centers of neighboring cars
+    pub min_cars: u32,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            speed_threshold: 5.0,
+            max_gap: 20.0,
+            min_cars: 3,
+        }
+    }
+}
+
+/// Power model and charging behavior of electric vehicles, the car types with a battery
+/// capacity. Their batteries drain with the power it takes to roll, push through the air,
+/// climb and accelerate, and braking recovers some of it.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ElectricConfig {
+    pub initial_charge_min: f32,    // share of capacity EVs spawn with, picked at random in this range
+    pub initial_charge_max: f32,
+    pub low_charge: f32,            // share of capacity below which drivers head for a charging exit
+    pub drag_area: f32,             // m^2, drag coefficient times frontal area
+    pub rolling_resistance: f32,    // rolling resistance coefficient
+    pub drivetrain_efficiency: f32, // share of battery power reaching the wheels
+    pub regen_efficiency: f32,      // share of braking power recovered into the battery
+    pub auxiliary_power: f32,       // kW drawn for lights, climate and electronics, even when stopped
+}
+
+impl Default for ElectricConfig {
+    fn default() -> Self {
+        Self {
+            initial_charge_min: 0.2,
+            initial_charge_max: 0.9,
+            low_charge: 0.15,
+            drag_area: 0.6,
+            rolling_resistance: 0.01,
+            drivetrain_efficiency: 0.9,
+            regen_efficiency: 0.6,
+            auxiliary_power: 1.0,
+        }
+    }
+}
+
+/// Car body colors. The scheme supplies a color for every behavior and car type, entries
+/// under `behaviors` and `car_types` replace single ones.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ColorConfig {
+    pub scheme: String,                       // "default" or "colorblind"
+    pub by: String,                           // color cars by "behavior" or "car_type"
+    pub behaviors: HashMap<String, [f32; 3]>, // RGB, each channel 0-1
+    pub car_types: HashMap<String, [f32; 3]>,
+}
+
+impl Default for ColorConfig {
+    fn default() -> Self {
+        Self {
+            scheme: "default".to_string(),
+            by: "behavior".to_string(),
+            behaviors: HashMap::new(),
+            car_types: HashMap::new(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct TrafficFlow {
     pub entry_intervals: Vec<EntryInterval>,
+    #[serde(default)]
+    pub demand_profiles: Vec<DemandProfile>,
+}
+
+impl TrafficFlow {
+    /// Multiplier on the spawn rate at `entry_id` at simulation `time`, 1 for entries
+    /// without a demand profile
+    pub fn demand_factor(&self, entry_id: &str, time: f32) -> f32 {
+        self.demand_profiles.iter()
+            .find(|profile| profile.entry_id == entry_id)
+            .map_or(1.0, |profile| profile.factor(time))
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct EntryInterval {
     pub entry_id: String,
+    #[serde(with = "units::time")]
     pub min_interval: f32,
+    #[serde(with = "units::time")]
     pub max_interval: f32,
 }
 
+/// Spawn demand at an entry over simulated time, as a multiplier on its spawn rate. Either
+/// `points` to interpolate between or a single smooth `peak`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DemandProfile {
+    pub entry_id: String,
+    #[serde(default)]
+    pub points: Vec<[f32; 2]>,      // (seconds, multiplier), linear between points and held past the ends
+    #[serde(default)]
+    pub peak: Option<DemandPeak>,
+}
+
+/// A rise from `base` to `factor` at `time` and back, following a cosine over `width`
+/// seconds either side of the peak
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DemandPeak {
+    #[serde(with = "units::time")]
+    pub time: f32,   // seconds
+    #[serde(with = "units::time")]
+    pub width: f32,  // seconds from base to the peak
+    pub base: f32,
+    pub factor: f32,
+}
+
+impl DemandProfile {
+    /// Spawn rate multiplier at simulation `time`
+    pub fn factor(&self, time: f32) -> f32 {
+        if let Some(peak) = &self.peak {
+            let offset = ((time - peak.time) / peak.width).abs();
+            if offset >= 1.0 {
+                return peak.base;
+            }
+            let rise = 0.5 * (1.0 + (offset * std::f32::consts::PI).cos());
+            return peak.base + (peak.factor - peak.base) * rise;
+        }
+        
+        let later = self.points.partition_point(|[point_time, _]| *point_time <= time);
+        match (later.checked_sub(1).map(|i| self.points[i]), self.points.get(later).copied()) {
+            (Some([start, from]), Some([end, to])) => from + (to - from) * (time - start) / (end - start),
+            (Some([_, factor]), None) | (None, Some([_, factor])) => factor,
+            (None, None) => 1.0,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct RandomConfig {
     pub seed: Option<u64>,
@@ -73,6 +404,20 @@ pub struct PerformanceConfig {
     pub enable_gpu_timing: bool,
     pub enable_cpu_timing: bool,
     pub timing_samples: u32,
+    /// Milliseconds of simulation and rendering a frame may take before quality is lowered, 0 for never
+    #[serde(default = "default_frame_budget_ms")]
+    pub frame_budget_ms: f32,
+    /// Megabytes of recent snapshots kept to rewind through, 0 for none
+    #[serde(default = "default_rewind_memory_mb")]
+    pub rewind_memory_mb: f32,
+}
+
+fn default_frame_budget_ms() -> f32 {
+    1000.0 / 60.0
+}
+
+fn default_rewind_memory_mb() -> f32 {
+    128.0
 }
 
 impl Validate for CarsConfig {
@@ -91,6 +436,29 @@ impl Validate for CarsConfig {
             return Err(anyhow!("Simulation duration must be positive"));
         }
         
+        if let Some(ring_cars) = sim.ring_cars {
+            if ring_cars == 0 || ring_cars > sim.total_cars {
+                return Err(anyhow!("Ring cars must be between 1 and total cars ({}), got {}", sim.total_cars, ring_cars));
+            }
+        }
+        
+        if let Some(initial) = &sim.initial {
+            if initial.density.is_some() == initial.level_of_service.is_some() {
+                return Err(anyhow!("Initial traffic needs either a density or a level of service"));
+            }
+            if let Some(level) = &initial.level_of_service {
+                if level_of_service_density(level).is_none() {
+                    return Err(anyhow!("Unknown level of service '{}', expected A to F", level));
+                }
+            }
+            if initial.density.is_some_and(|density| density <= 0.0) {
+                return Err(anyhow!("Initial traffic density must be positive"));
+            }
+            if sim.ring_cars.is_some() {
+                return Err(anyhow!("Initial traffic and ring cars cannot both be set"));
+            }
+        }
+        
         // Validate car types
         if self.car_types.is_empty() {
             return Err(anyhow!("At least one car type must be defined"));
@@ -113,6 +481,33 @@ impl Validate for CarsConfig {
             if car_type.preferred_speed <= 0.0 {
                 return Err(anyhow!("Preferred speed must be positive"));
             }
+            
+            match (car_type.mass, car_type.engine_power) {
+                (Some(mass), Some(power)) if mass <= 0.0 || power <= 0.0 => {
+                    return Err(anyhow!("Mass and engine power for '{}' must be positive", car_type.id));
+                }
+                (Some(_), None) | (None, Some(_)) => {
+                    return Err(anyhow!("Car type '{}' needs both mass and engine_power or neither", car_type.id));
+                }
+                _ => {}
+            }
+            
+            if car_type.rollover_threshold.is_some_and(|threshold| threshold <= 0.0) {
+                return Err(anyhow!("Rollover threshold for '{}' must be positive", car_type.id));
+            }
+            
+            if !(0.0..=1.0).contains(&car_type.breakdown_probability) {
+                return Err(anyhow!("Breakdown probability for '{}' must be in range [0, 1]", car_type.id));
+            }
+            
+            if let Some(capacity) = car_type.battery_capacity {
+                if !capacity.is_finite() || capacity <= 0.0 {
+                    return Err(anyhow!("Battery capacity for '{}' must be positive", car_type.id));
+                }
+                if car_type.mass.is_none() {
+                    return Err(anyhow!("Electric car type '{}' needs a mass for its power model", car_type.id));
+                }
+            }
         }
         
         // Validate behaviors
@@ -142,8 +537,20 @@ impl Validate for CarsConfig {
                 return Err(anyhow!("Reaction time for '{}' must be positive", name));
             }
             
-            if behavior.exit_probability < 0.0 || behavior.exit_probability > 1.0 {
-                return Err(anyhow!("Exit probability for '{}' must be in range [0, 1]", name));
+            if !["none", "open", "refuse"].contains(&behavior.merge_courtesy.as_str()) {
+                return Err(anyhow!("Merge courtesy for '{}' must be 'none', 'open' or 'refuse', got '{}'", name, behavior.merge_courtesy));
+            }
+            
+            if !(0.0..=1.0).contains(&behavior.blind_spot_miss_probability) {
+                return Err(anyhow!("Blind spot miss probability for '{}' must be between 0 and 1", name));
+            }
+            
+            if behavior.comfort_lateral_acceleration.is_some_and(|comfort| comfort <= 0.0) {
+                return Err(anyhow!("Comfortable lateral acceleration for '{}' must be positive", name));
+            }
+            
+            if let Some(script) = &behavior.script {
+                check_script(name, script)?;
             }
         }
         
@@ -161,12 +568,159 @@ impl Validate for CarsConfig {
             return Err(anyhow!("Emergency brake distance must be less than warning distance"));
         }
         
+        if !["none", "halt", "remove"].contains(&collision.crash_response.as_str()) {
+            return Err(anyhow!("Crash response must be 'none', 'halt' or 'remove', got '{}'", collision.crash_response));
+        }
+        
+        // Validate lane change model
+        let lane_change = &self.lane_change;
+        if lane_change.model != "random" && lane_change.model != "mobil" {
+            return Err(anyhow!("Lane change model must be 'random' or 'mobil', got '{}'", lane_change.model));
+        }
+        
+        if lane_change.politeness < 0.0 || lane_change.acceleration_threshold < 0.0 || lane_change.keep_right_bias < 0.0 {
+            return Err(anyhow!("Lane change politeness, threshold and keep-right bias must be non-negative"));
+        }
+        
+        if lane_change.safe_deceleration <= 0.0 {
+            return Err(anyhow!("Lane change safe deceleration must be positive"));
+        }
+        
+        // Validate breakdowns
+        let breakdowns = &self.breakdowns;
+        if breakdowns.min_duration <= 0.0 || breakdowns.max_duration < breakdowns.min_duration {
+            return Err(anyhow!("Breakdown durations must be positive with min_duration <= max_duration"));
+        }
+        
+        if !(0.0..=1.0).contains(&breakdowns.shoulder_probability) {
+            return Err(anyhow!("Shoulder probability must be in range [0, 1]"));
+        }
+        
+        if breakdowns.shoulder_delay < 0.0 {
+            return Err(anyhow!("Shoulder delay must be non-negative"));
+        }
+        
+        // Validate reaction delays
+        let reaction = &self.reaction;
+        if reaction.distraction_rate < 0.0 || reaction.distraction_delay < 0.0 {
+            return Err(anyhow!("Distraction rate and delay must be non-negative"));
+        }
+        
+        if reaction.distraction_min_duration <= 0.0 || reaction.distraction_max_duration < reaction.distraction_min_duration {
+            return Err(anyhow!("Distraction durations must be positive with distraction_min_duration <= distraction_max_duration"));
+        }
+        
+        // Validate safety thresholds
+        if self.safety.ttc_threshold <= 0.0 || self.safety.pet_threshold <= 0.0 {
+            return Err(anyhow!("Safety thresholds ttc_threshold and pet_threshold must be positive"));
+        }
+        
+        // Validate the electric vehicle model
+        let electric = &self.electric;
+        if !(0.0..=1.0).contains(&electric.initial_charge_min) || !(electric.initial_charge_min..=1.0).contains(&electric.initial_charge_max) {
+            return Err(anyhow!("Initial charges must be in range [0, 1] with initial_charge_min <= initial_charge_max"));
+        }
+        
+        if !(0.0..1.0).contains(&electric.low_charge) {
+            return Err(anyhow!("Low charge must be in range [0, 1)"));
+        }
+        
+        if electric.drag_area < 0.0 || electric.rolling_resistance < 0.0 || electric.auxiliary_power < 0.0 {
+            return Err(anyhow!("Drag area, rolling resistance and auxiliary power must be non-negative"));
+        }
+        
+        if electric.drivetrain_efficiency <= 0.0 || electric.drivetrain_efficiency > 1.0 || !(0.0..=1.0).contains(&electric.regen_efficiency) {
+            return Err(anyhow!("Drivetrain efficiency must be in range (0, 1] and regen efficiency in [0, 1]"));
+        }
+        
+        // Validate queue detection
+        if self.queues.speed_threshold <= 0.0 || self.queues.max_gap <= 0.0 || self.queues.min_cars < 2 {
+            return Err(anyhow!("Queue speed threshold and max gap must be positive and min_cars at least 2"));
+        }
+        
+        // Validate colors
+        let colors = &self.colors;
+        if colors.scheme != "default" && colors.scheme != "colorblind" {
+            return Err(anyhow!("Color scheme must be 'default' or 'colorblind', got '{}'", colors.scheme));
+        }
+        
+        if colors.by != "behavior" && colors.by != "car_type" {
+            return Err(anyhow!("Colors must be by 'behavior' or 'car_type', got '{}'", colors.by));
+        }
+        
+        for (name, color) in &colors.behaviors {
+            if !self.behavior.contains_key(name) {
+                return Err(anyhow!("Color given for unknown behavior '{}'", name));
+            }
+            check_color(name, color)?;
+        }
+        
+        for (id, color) in &colors.car_types {
+            if !self.car_types.iter().any(|car_type| &car_type.id == id) {
+                return Err(anyhow!("Color given for unknown car type '{}'", id));
+            }
+            check_color(id, color)?;
+        }
+        
+        // Validate demand profiles
+        for profile in &self.traffic_flow.demand_profiles {
+            match (&profile.peak, profile.points.is_empty()) {
+                (Some(peak), true) => {
+                    if peak.width <= 0.0 || peak.base < 0.0 || peak.factor < 0.0 {
+                        return Err(anyhow!("Demand peak for '{}' needs a positive width and non-negative factors", profile.entry_id));
+                    }
+                }
+                (None, false) => {
+                    if profile.points.iter().any(|[_, factor]| *factor < 0.0) {
+                        return Err(anyhow!("Demand factors for '{}' must be non-negative", profile.entry_id));
+                    }
+                    if profile.points.windows(2).any(|pair| pair[1][0] <= pair[0][0]) {
+                        return Err(anyhow!("Demand points for '{}' must be in increasing time order", profile.entry_id));
+                    }
+                }
+                _ => return Err(anyhow!("Demand profile for '{}' needs either points or a peak", profile.entry_id)),
+            }
+            
+            if self.traffic_flow.demand_profiles.iter().filter(|other| other.entry_id == profile.entry_id).count() > 1 {
+                return Err(anyhow!("Entry '{}' has more than one demand profile", profile.entry_id));
+            }
+        }
+        
         // Validate performance config
         let perf = &self.performance;
         if perf.timing_samples == 0 {
             return Err(anyhow!("Timing samples must be greater than zero"));
         }
         
+        if perf.frame_budget_ms < 0.0 {
+            return Err(anyhow!("Frame budget must be non-negative"));
+        }
+        
+        if perf.rewind_memory_mb < 0.0 {
+            return Err(anyhow!("Rewind memory must be non-negative"));
+        }
+        
         Ok(())
     }
-}
\ No newline at end of file
+}
+fn check_color(name: &str, color: &[f32; 3]) -> Result<()> {
+    if color.iter().any(|channel| !(0.0..=1.0).contains(channel)) {
+        return Err(anyhow!("Color channels for '{}' must be in range [0, 1]", name));
+    }
+    Ok(())
+}
+
+/// Check that a behavior script can be read and compiles
+#[cfg(feature = "scripting")]
+fn check_script(behavior: &str, path: &str) -> Result<()> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Cannot read script {} for '{}': {}", path, behavior, e))?;
+    rhai::Engine::new().compile(&source)
+        .map_err(|e| anyhow!("Script {} for '{}' does not compile: {}", path, behavior, e))?;
+    Ok(())
+}
+
+#[cfg(not(feature = "scripting"))]
+fn check_script(behavior: &str, path: &str) -> Result<()> {
+    Err(anyhow!("Behavior '{}' uses script {}, which needs a build with the 'scripting' feature", behavior, path))
+}
@@ -0,0 +1,45 @@
+//! Configuration files shipped with the simulator, every section explained in comments,
+//! to start new configurations from
+
+use super::RouteConfig;
+
+/// Donut highway with interior entrances and exterior exits (route.toml)
+pub const EXAMPLE_DONUT_ROUTE: &str = include_str!("../../route.toml");
+/// Cloverleaf interchange with through highways and loop ramps (route2.toml)
+pub const EXAMPLE_CLOVERLEAF_ROUTE: &str = include_str!("../../route2.toml");
+/// Grid roundabout with spawn and exit cells (route3.toml)
+pub const EXAMPLE_GRID_ROUTE: &str = include_str!("../../route3.toml");
+/// Car types, driver behaviors and spawning (cars.toml), its entry settings naming the
+/// donut's entries
+pub const EXAMPLE_CARS: &str = include_str!("../../cars.toml");
+
+/// Example route file of a geometry type: "donut", "cloverleaf" or "grid"
+pub fn example_route(geometry: &str) -> Option<&'static str> {
+    match geometry {
+        "donut" => Some(EXAMPLE_DONUT_ROUTE),
+        "cloverleaf" => Some(EXAMPLE_CLOVERLEAF_ROUTE),
+        "grid" => Some(EXAMPLE_GRID_ROUTE),
+        _ => None,
+    }
+}
+
+/// Example cars file for the example route of a geometry type, with an entry interval for
+/// each of the route's entries and the commented demand profiles naming its first two
+pub fn example_cars(geometry: &str) -> Option<String> {
+    let route: RouteConfig = toml::from_str(example_route(geometry)?).expect("the example routes parse");
+    let ids: Vec<&str> = route.route.entries.iter().map(|entry| entry.id.as_str()).collect();
+    let first = ids.first().copied().unwrap_or("entry_1");
+    let second = ids.get(1).copied().unwrap_or(first);
+    
+    // The donut's two intervals, one line each, become one line per entry of the route
+    let (head, rest) = EXAMPLE_CARS.split_once("entry_intervals = [\n").expect("the example cars set entry intervals");
+    let (intervals, tail) = rest.split_once("\n]\n").expect("the entry intervals close");
+    let template = intervals.lines().next().expect("an entry interval").trim_end_matches(',');
+    let intervals: Vec<String> = ids.iter()
+        .map(|id| template.replace("\"entry_1\"", &format!("\"{}\"", id)))
+        .collect();
+    let tail = tail
+        .replace("entry_id = \"entry_1\"", &format!("entry_id = \"{}\"", first))
+        .replace("entry_id = \"entry_2\"", &format!("entry_id = \"{}\"", second));
+    Some(format!("{}entry_intervals = [\n{}\n]\n{}", head, intervals.join(",\n"), tail))
+}
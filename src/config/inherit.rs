@@ -0,0 +1,97 @@
+//! Configuration files built on a shared base. A route or cars file may start with
+//! `base = "common.toml"` and hold only the fields it changes; everything else comes from
+//! the base file, which may have a base of its own.
+
+use anyhow::{Result, anyhow, bail};
+use std::path::{Path, PathBuf};
+
+/// Key naming the file a configuration file is built on, relative to that file
+pub const BASE_KEY: &str = "base";
+
+/// Read the configuration file at `path` with its base files under it. Tables are merged
+/// key by key; values and arrays in a file, such as the list of entries, replace the base's
+/// as a whole. A file without a base is returned as written, so parse errors still point at
+/// its lines.
+pub fn read_config_file(path: impl AsRef<Path>) -> Result<String> {
+    let path = path.as_ref();
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+    let table: toml::Table = toml::from_str(&content)
+        .map_err(|e| anyhow!("Failed to parse {}: {}", path.display(), e))?;
+    if !table.contains_key(BASE_KEY) {
+        return Ok(content);
+    }
+    let merged = resolve(path, table, &mut Vec::new())?;
+    Ok(toml::to_string(&merged)?)
+}
+
+/// The file at `path` followed by its bases, nearest first; the files a configuration is
+/// read from
+pub fn config_file_chain(path: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+    let mut chain = vec![path.as_ref().to_path_buf()];
+    loop {
+        let file = &chain[chain.len() - 1];
+        let content = std::fs::read_to_string(file)
+            .map_err(|e| anyhow!("Failed to read {}: {}", file.display(), e))?;
+        let table: toml::Table = toml::from_str(&content)
+            .map_err(|e| anyhow!("Failed to parse {}: {}", file.display(), e))?;
+        let Some(base) = base_path(file, &table)? else {
+            return Ok(chain);
+        };
+        if chain.iter().any(|seen| same_file(seen, &base)) {
+            bail!("{} is its own base through {}", base.display(), file.display());
+        }
+        chain.push(base);
+    }
+}
+
+/// `table` of the file at `path` laid over its bases. `chain` holds the files that named
+/// the one being read, to catch bases that lead back to themselves.
+fn resolve(path: &Path, mut table: toml::Table, chain: &mut Vec<PathBuf>) -> Result<toml::Table> {
+    let Some(base) = base_path(path, &table)? else {
+        return Ok(table);
+    };
+    table.remove(BASE_KEY);
+    chain.push(path.to_path_buf());
+    if chain.iter().any(|seen| same_file(seen, &base)) {
+        bail!("{} is its own base through {}", base.display(), path.display());
+    }
+    let content = std::fs::read_to_string(&base)
+        .map_err(|e| anyhow!("Failed to read {}, the base of {}: {}", base.display(), path.display(), e))?;
+    let base_table: toml::Table = toml::from_str(&content)
+        .map_err(|e| anyhow!("Failed to parse {}: {}", base.display(), e))?;
+    let mut merged = resolve(&base, base_table, chain)?;
+    merge(&mut merged, table);
+    Ok(merged)
+}
+
+/// Base file named in `table`, which was read from `path`
+fn base_path(path: &Path, table: &toml::Table) -> Result<Option<PathBuf>> {
+    match table.get(BASE_KEY) {
+        None => Ok(None),
+        Some(toml::Value::String(base)) => {
+            let directory = path.parent().unwrap_or(Path::new("."));
+            Ok(Some(directory.join(base)))
+        }
+        Some(_) => bail!("'{}' in {} must be the path of a file", BASE_KEY, path.display()),
+    }
+}
+
+/// Lay `overlay` over `base`, descending into tables both have
+fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(table)) => merge(base_table, table),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
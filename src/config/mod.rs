@@ -1,10 +1,24 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 
 pub mod route;
 pub mod cars;
+pub mod overrides;
+pub mod world;
+pub mod examples;
+pub mod units;
+pub mod inherit;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod watch;
 
 pub use route::*;
 pub use cars::*;
+pub use overrides::*;
+pub use world::*;
+pub use examples::*;
+pub use units::{parse_quantity, Dimension, UnitSystem};
+pub use inherit::{config_file_chain, read_config_file};
+#[cfg(not(target_arch = "wasm32"))]
+pub use watch::*;
 
 #[derive(Debug, Clone)]
 pub struct SimulationConfig {
@@ -14,20 +28,230 @@ pub struct SimulationConfig {
 
 impl SimulationConfig {
     pub fn load_from_files(route_path: &str, cars_path: &str) -> Result<Self> {
-        let route_content = std::fs::read_to_string(route_path)?;
-        let cars_content = std::fs::read_to_string(cars_path)?;
-        
-        let route: RouteConfig = toml::from_str(&route_content)?;
-        let cars: CarsConfig = toml::from_str(&cars_content)?;
+        Self::load_with_overrides(route_path, cars_path, &[])
+    }
+    
+    /// Load the files, laid over their bases, with `--set` style overrides applied before
+    /// validation
+    pub fn load_with_overrides(route_path: &str, cars_path: &str, overrides: &[ConfigOverride]) -> Result<Self> {
+        let route_content = read_config_file(route_path)?;
+        let cars_content = read_config_file(cars_path)?;
+        Self::load_from_strs_with_overrides(&route_content, &cars_content, overrides)
+    }
+    
+    /// Parse and validate configurations already in memory
+    pub fn load_from_strs(route_content: &str, cars_content: &str) -> Result<Self> {
+        Self::load_from_strs_with_overrides(route_content, cars_content, &[])
+    }
+    
+    pub fn load_from_strs_with_overrides(route_content: &str, cars_content: &str, overrides: &[ConfigOverride]) -> Result<Self> {
+        let (route, cars): (RouteConfig, CarsConfig) = if overrides.is_empty() {
+            // Parsed straight into the structs so errors point at lines in the files
+            (toml::from_str(route_content)?, toml::from_str(cars_content)?)
+        } else {
+            let mut route_value = toml::Value::Table(toml::from_str(route_content)?);
+            let mut cars_value = toml::Value::Table(toml::from_str(cars_content)?);
+            for config_override in overrides {
+                config_override.apply(&mut route_value, &mut cars_value)?;
+            }
+            let route: RouteConfig = route_value.try_into()?;
+            let cars: CarsConfig = cars_value.try_into()?;
+            for config_override in overrides {
+                config_override.check_known(&route, &cars)?;
+            }
+            (route, cars)
+        };
         
-        // Validate configurations
+        // Validate configurations, each file on its own and then against the other
         route.validate()?;
         cars.validate()?;
+        let config = SimulationConfig { route, cars };
+        let problems = config.cross_check();
+        let errors: Vec<String> = problems.iter()
+            .filter(|problem| !problem.warning)
+            .map(|problem| format!("  {}: {}", problem.key, problem.message))
+            .collect();
+        if !errors.is_empty() {
+            return Err(anyhow!("The route and cars configurations disagree:\n{}", errors.join("\n")));
+        }
+        for problem in &problems {
+            log::warn!("{}: {}", problem.key, problem.message);
+        }
         
-        Ok(SimulationConfig { route, cars })
+        Ok(config)
+    }
+    
+    /// Places where the route and cars files disagree, which neither file's own validation
+    /// can see; empty when the files agree
+    pub fn cross_check(&self) -> Vec<ConfigProblem> {
+        let (route, cars) = (&self.route, &self.cars);
+        let mut problems = Vec::new();
+        check_entry_settings(route, cars, &mut problems);
+        check_car_limit(route, cars, &mut problems);
+        check_lanes(route, cars, &mut problems);
+        if let Some(ring_cars) = cars.simulation.ring_cars {
+            check_ring(route, cars, ring_cars, &mut problems);
+        }
+        if let Some(initial) = &cars.simulation.initial {
+            check_initial_density(route, cars, initial, &mut problems);
+        }
+        problems
+    }
+}
+
+/// A disagreement between the route and cars files found by [`SimulationConfig::cross_check`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigProblem {
+    pub key: String, // `--set` style key of the offending value
+    pub message: String,
+    pub warning: bool, // the simulation still runs, though likely not as intended
+}
+
+impl ConfigProblem {
+    fn warning(key: String, message: String) -> Self {
+        ConfigProblem { key, message, warning: true }
+    }
+    
+    fn error(key: String, message: String) -> Self {
+        ConfigProblem { key, message, warning: false }
+    }
+}
+
+impl std::fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let level = if self.warning { "warning" } else { "error" };
+        write!(f, "{}: {}: {}", level, self.key, self.message)
+    }
+}
+
+/// Meters kept between cars placed on the road at the start, bumper to bumper
+const PLACEMENT_GAP: f32 = 2.0;
+
+/// Length of the longest car type
+fn longest_car(cars: &CarsConfig) -> f32 {
+    cars.car_types.iter().map(|car_type| car_type.length).fold(0.0, f32::max)
+}
+
+/// Entry intervals and demand profiles for entries the route does not have. Spawning falls
+/// back to the spawn rate at entries without settings, so these do no harm; one cars file
+/// serves routes with different entries.
+fn check_entry_settings(route: &RouteConfig, cars: &CarsConfig, problems: &mut Vec<ConfigProblem>) {
+    let has_entry = |id: &str| route.route.entries.iter().any(|entry| entry.id == id);
+    let flow = &cars.traffic_flow;
+    for (i, interval) in flow.entry_intervals.iter().enumerate() {
+        if !has_entry(&interval.entry_id) {
+            problems.push(ConfigProblem::warning(
+                format!("cars.traffic_flow.entry_intervals.{}.entry_id", i),
+                format!("the route has no entry '{}', the interval is ignored", interval.entry_id),
+            ));
+        }
+    }
+    for (i, profile) in flow.demand_profiles.iter().enumerate() {
+        if !has_entry(&profile.entry_id) {
+            problems.push(ConfigProblem::warning(
+                format!("cars.traffic_flow.demand_profiles.{}.entry_id", i),
+                format!("the route has no entry '{}', the profile is ignored", profile.entry_id),
+            ));
+        }
+    }
+}
+
+/// Spawn rates that hold more cars on the road than `total_cars` allows, so the limit
+/// rather than the demand sets the traffic. Estimated by Little's law with every car
+/// driving the length of the inner lane at the speed limit.
+fn check_car_limit(route: &RouteConfig, cars: &CarsConfig, problems: &mut Vec<ConfigProblem>) {
+    let sim = &cars.simulation;
+    if sim.ring_cars.is_some() {
+        return;
+    }
+    let arrivals: f32 = route.route.entries.iter()
+        .map(|entry| {
+            cars.traffic_flow.entry_intervals.iter()
+                .find(|interval| interval.entry_id == entry.id)
+                .map_or(sim.spawn_rate, |interval| 2.0 / (interval.min_interval + interval.max_interval))
+        })
+        .sum();
+    let trip_time = route.route.geometry.lane_length(1) / route.route.traffic_rules.speed_limit;
+    let on_road = arrivals * trip_time;
+    if on_road > sim.total_cars as f32 {
+        problems.push(ConfigProblem::warning(
+            "cars.simulation.total_cars".to_string(),
+            format!(
+                "the entries spawn {:.1} cars/s, enough to keep about {:.0} cars on the road, over the limit of {}; entries wait while the road is full",
+                arrivals, on_road, sim.total_cars
+            ),
+        ));
+    }
+}
+
+/// Car types too wide for the route's lanes, and heavy car types banned from every lane
+fn check_lanes(route: &RouteConfig, cars: &CarsConfig, problems: &mut Vec<ConfigProblem>) {
+    let geometry = &route.route.geometry;
+    for (i, car_type) in cars.car_types.iter().enumerate() {
+        if car_type.width > geometry.lane_width {
+            problems.push(ConfigProblem::error(
+                format!("cars.car_types.{}.width", i),
+                format!("'{}' is {:.1} m wide, wider than the route's {:.1} m lanes", car_type.id, car_type.width, geometry.lane_width),
+            ));
+        }
+    }
+    let banned = &route.route.traffic_rules.heavy_vehicle_banned_lanes;
+    if geometry.geometry_type != "grid" && (1..=geometry.lane_count).all(|lane| banned.contains(&lane)) {
+        if let Some(heavy) = cars.car_types.iter().find(|car_type| car_type.heavy && car_type.weight > 0) {
+            problems.push(ConfigProblem::error(
+                "route.traffic_rules.heavy_vehicle_banned_lanes".to_string(),
+                format!("every lane is banned to heavy car type '{}'", heavy.id),
+            ));
+        }
+    }
+}
+
+/// `ring_cars` cars of the longest type must fit around the route's ring, spread evenly
+/// over its lanes
+fn check_ring(route: &RouteConfig, cars: &CarsConfig, ring_cars: u32, problems: &mut Vec<ConfigProblem>) {
+    let key = "cars.simulation.ring_cars".to_string();
+    let geometry = &route.route.geometry;
+    if geometry.geometry_type != "donut" {
+        problems.push(ConfigProblem::error(key, format!("ring cars can only be placed on donut routes, not '{}'", geometry.geometry_type)));
+        return;
+    }
+    let longest = longest_car(cars);
+    let per_lane = ring_cars.div_ceil(geometry.lane_count);
+    let room = (geometry.lane_length(1) / (longest + PLACEMENT_GAP)) as u32;
+    if per_lane > room {
+        problems.push(ConfigProblem::error(key, format!(
+            "{} ring cars do not fit: the inner lane has room for {} cars of {:.1} m",
+            ring_cars, room, longest
+        )));
+    }
+}
+
+/// The route's lanes must fit the initial density of cars of the longest type
+fn check_initial_density(route: &RouteConfig, cars: &CarsConfig, initial: &InitialTraffic, problems: &mut Vec<ConfigProblem>) {
+    let Some(density) = initial.density() else {
+        return;
+    };
+    let key = if initial.density.is_some() {
+        "cars.simulation.initial.density"
+    } else {
+        "cars.simulation.initial.level_of_service"
+    }.to_string();
+    let geometry = &route.route.geometry;
+    if geometry.geometry_type == "grid" {
+        problems.push(ConfigProblem::error(key, "initial traffic can only be placed on donut and cloverleaf routes".to_string()));
+        return;
+    }
+    let longest = longest_car(cars);
+    let jam_density = 1000.0 / (longest + PLACEMENT_GAP);
+    if density > jam_density {
+        problems.push(ConfigProblem::error(key, format!(
+            "{:.1} veh/km does not fit: lanes hold at most {:.1} veh/km of cars of {:.1} m",
+            density, jam_density, longest
+        )));
     }
 }
 
 pub trait Validate {
     fn validate(&self) -> Result<()>;
-}
\ No newline at end of file
+}
+
@@ -0,0 +1,87 @@
+use anyhow::{Result, anyhow, bail};
+use super::{CarsConfig, RouteConfig};
+use std::str::FromStr;
+
+/// One `KEY=VALUE` override applied to the parsed configuration files before validation,
+/// e.g. `cars.simulation.spawn_rate=2.5` or `route.traffic_rules.speed_limit=33`.
+///
+/// Keys starting with `cars.` address cars.toml from its top level, keys starting with
+/// `route.` address the `[route]` table of route.toml. Array elements are addressed by
+/// index (`route.entries.0.spawn_rate`). Values are TOML, anything that does not parse as
+/// a TOML value is taken as a plain string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigOverride {
+    pub key: String,
+    pub value: toml::Value,
+}
+
+impl FromStr for ConfigOverride {
+    type Err = anyhow::Error;
+    
+    fn from_str(s: &str) -> Result<Self> {
+        let (key, value) = s.split_once('=').ok_or_else(|| anyhow!("expected KEY=VALUE, got '{}'", s))?;
+        let value = value.trim();
+        let value = match toml::from_str::<toml::Table>(&format!("value = {}", value)) {
+            Ok(mut table) => table.remove("value").unwrap_or_else(|| toml::Value::String(value.to_string())),
+            Err(_) => toml::Value::String(value.to_string()),
+        };
+        Self::new(key.trim(), value)
+    }
+}
+
+impl ConfigOverride {
+    pub fn new(key: &str, value: toml::Value) -> Result<Self> {
+        if !key.starts_with("cars.") && !key.starts_with("route.") {
+            bail!("key '{}' must start with 'cars.' or 'route.'", key);
+        }
+        if key.split('.').any(str::is_empty) {
+            bail!("key '{}' has an empty segment", key);
+        }
+        Ok(Self { key: key.to_string(), value })
+    }
+    
+    /// Set the value in whichever of the parsed files the key belongs to
+    pub(crate) fn apply(&self, route: &mut toml::Value, cars: &mut toml::Value) -> Result<()> {
+        // The route file keeps everything under [route], so its keys are used as written
+        let (mut value, path) = match self.key.strip_prefix("cars.") {
+            Some(path) => (cars, path),
+            None => (route, self.key.as_str()),
+        };
+        for segment in path.split('.') {
+            value = match value {
+                // Tables left out of the file are created, the fields in them have defaults
+                toml::Value::Table(table) => table.entry(segment.to_string())
+                    .or_insert_with(|| toml::Value::Table(toml::Table::new())),
+                toml::Value::Array(values) => {
+                    let index: usize = segment.parse()
+                        .map_err(|_| anyhow!("cannot set '{}', '{}' is not an array index", self.key, segment))?;
+                    let count = values.len();
+                    values.get_mut(index)
+                        .ok_or_else(|| anyhow!("cannot set '{}', index {} is out of range for {} elements", self.key, index, count))?
+                }
+                _ => bail!("cannot set '{}', '{}' is inside a value that is not a table", self.key, segment),
+            };
+        }
+        *value = self.value.clone();
+        Ok(())
+    }
+    
+    /// Fail if the key names no field of the deserialized configuration, which would
+    /// otherwise be dropped silently
+    pub(crate) fn check_known(&self, route: &RouteConfig, cars: &CarsConfig) -> Result<()> {
+        let (root, path) = match self.key.strip_prefix("cars.") {
+            Some(path) => (serde_json::to_value(cars)?, path),
+            None => (serde_json::to_value(route)?, self.key.as_str()),
+        };
+        let mut value = &root;
+        for segment in path.split('.') {
+            let next = match value {
+                serde_json::Value::Object(fields) => fields.get(segment),
+                serde_json::Value::Array(values) => segment.parse::<usize>().ok().and_then(|index| values.get(index)),
+                _ => None,
+            };
+            value = next.ok_or_else(|| anyhow!("unknown configuration key '{}'", self.key))?;
+        }
+        Ok(())
+    }
+}
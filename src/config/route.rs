@@ -1,12 +1,34 @@
 use serde::{Deserialize, Serialize};
 use anyhow::{Result, anyhow};
 use super::Validate;
+use super::units;
+
+/// Meters from the center of a cloverleaf to the ends of its through highways, where
+/// through traffic spawns
+pub const CLOVERLEAF_HIGHWAY_EXTENT: f32 = 250.0;
+/// Meters past the end of the road the default world bounds leave for cars to drive off
+const WORLD_BOUNDS_MARGIN: f32 = 20.0;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct RouteConfig {
     pub route: Route,
 }
 
+impl RouteConfig {
+    /// Whether both configurations describe the same road: geometry, entries and exits.
+    /// Cars driving on one can carry on driving on the other.
+    pub fn same_road(&self, other: &RouteConfig) -> bool {
+        let road = |route: &Route| serde_json::to_value((&route.geometry, &route.entries, &route.exits)).ok();
+        road(&self.route).is_some() && road(&self.route) == road(&other.route)
+    }
+    
+    /// Whether `other` lays out the same road surface, whatever its ramps and their meters
+    pub fn same_geometry(&self, other: &RouteConfig) -> bool {
+        let geometry = |route: &Route| serde_json::to_value(&route.geometry).ok();
+        geometry(&self.route).is_some() && geometry(&self.route) == geometry(&other.route)
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Route {
     pub name: String,
@@ -18,38 +40,148 @@ pub struct Route {
     pub surface: RoadSurface,
     #[serde(default)]
     pub signals: TrafficSignals,
+    #[serde(default)]
+    pub od_matrix: Vec<OdPair>,
+    #[serde(default)]
+    pub detectors: Vec<DetectorConfig>,
+    #[serde(default)]
+    pub waypoints: Vec<WaypointConfig>,
+    #[serde(default)]
+    pub travel_times: Vec<TravelTimeSegment>,
+    #[serde(default)]
+    pub weather: WeatherConfig,
+    #[serde(default)]
+    pub closures: Vec<LaneClosure>,
+    #[serde(default)]
+    pub intersections: Vec<IntersectionConfig>,
+    #[serde(default)]
+    pub turning_ratios: Vec<TurningRatio>,
+    #[serde(default)]
+    pub bus_stops: Vec<BusStop>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct RouteGeometry {
     #[serde(rename = "type")]
     pub geometry_type: String,
+    #[serde(with = "units::distance")]
     pub center_x: f32,
+    #[serde(with = "units::distance")]
     pub center_y: f32,
+    #[serde(with = "units::distance")]
     pub inner_radius: f32,
+    #[serde(with = "units::distance")]
     pub outer_radius: f32,
+    #[serde(with = "units::distance")]
     pub lane_width: f32,
     pub lane_count: u32,
     // Cloverleaf-specific fields
-    #[serde(default)]
+    #[serde(default, with = "units::distance::option")]
     pub highway_width: Option<f32>,
-    #[serde(default)]
+    #[serde(default, with = "units::distance::option")]
     pub highway_length: Option<f32>,
-    #[serde(default)]
+    #[serde(default, with = "units::distance::option")]
     pub loop_radius: Option<f32>,
-    #[serde(default)]
+    #[serde(default, with = "units::distance::option")]
     pub ramp_width: Option<f32>,
     #[serde(default)]
     pub ramp_lanes: Option<u32>,
     // Grid-specific fields
     #[serde(default)]
     pub grid: Option<Vec<Vec<String>>>,
-    #[serde(default)]
+    #[serde(default, with = "units::distance::option")]
     pub cell_size: Option<f32>,
     #[serde(default)]
     pub spawn_points: Option<Vec<GridPoint>>,
     #[serde(default)]
     pub exit_points: Option<Vec<GridPoint>>,
+    #[serde(default)]
+    pub bounds: Option<WorldBounds>, // cars outside are taken off the road (default: just past the road's ends)
+}
+
+impl RouteGeometry {
+    /// Approximate driveable length of a lane in meters, used for density calculations
+    pub fn lane_length(&self, lane: u32) -> f32 {
+        match self.geometry_type.as_str() {
+            "cloverleaf" => {
+                // Through lanes run between the spawn edges on either side of the interchange
+                2.0 * CLOVERLEAF_HIGHWAY_EXTENT
+            }
+            "grid" => {
+                let cell_size = self.cell_size.unwrap_or(20.0);
+                let road_cells = self.grid.as_ref()
+                    .map(|grid| grid.iter().flatten().filter(|cell| cell.trim() != "").count())
+                    .unwrap_or(0);
+                road_cells as f32 * cell_size
+            }
+            _ => {
+                // Donut lanes are concentric circles
+                let lane_radius = self.inner_radius + self.lane_width / 2.0 + (lane as f32 - 1.0) * self.lane_width;
+                2.0 * std::f32::consts::PI * lane_radius
+            }
+        }
+    }
+    
+    /// Area cars may drive in: the configured bounds, or a margin past the ends of the
+    /// road, the highway ends of a cloverleaf, the edges of a grid or the off-ramps of a
+    /// donut
+    pub fn world_bounds(&self) -> WorldBounds {
+        if let Some(bounds) = self.bounds {
+            return bounds;
+        }
+        let (half_width, half_height) = match self.geometry_type.as_str() {
+            "cloverleaf" => (CLOVERLEAF_HIGHWAY_EXTENT, CLOVERLEAF_HIGHWAY_EXTENT),
+            "grid" => {
+                let cell_size = self.cell_size.unwrap_or(20.0);
+                let rows = self.grid.as_ref().map_or(0, |grid| grid.len());
+                let cols = self.grid.as_ref().and_then(|grid| grid.first()).map_or(0, |row| row.len());
+                (cols as f32 * cell_size / 2.0, rows as f32 * cell_size / 2.0)
+            }
+            _ => (self.outer_radius, self.outer_radius),
+        };
+        WorldBounds {
+            min_x: self.center_x - half_width - WORLD_BOUNDS_MARGIN,
+            min_y: self.center_y - half_height - WORLD_BOUNDS_MARGIN,
+            max_x: self.center_x + half_width + WORLD_BOUNDS_MARGIN,
+            max_y: self.center_y + half_height + WORLD_BOUNDS_MARGIN,
+        }
+    }
+
+    /// Level `lane` runs at where roads cross without meeting: the north-south highway of
+    /// a cloverleaf bridges over the east-west one. `None` for lanes that meet every other
+    /// lane at grade, like the loop ramps that join the two highways.
+    pub fn lane_level(&self, lane: u32) -> Option<u32> {
+        match (self.geometry_type.as_str(), lane) {
+            ("cloverleaf", 1..=6) => Some(1),
+            ("cloverleaf", 7..=12) => Some(0),
+            _ => None,
+        }
+    }
+
+    /// Whether cars in `lane` and `other_lane` pass over one another instead of meeting
+    pub fn grade_separated(&self, lane: u32, other_lane: u32) -> bool {
+        matches!((self.lane_level(lane), self.lane_level(other_lane)), (Some(a), Some(b)) if a != b)
+    }
+}
+
+/// Rectangle of the world cars drive in, in meters. Cars that leave it have driven off
+/// the end of the road.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct WorldBounds {
+    #[serde(with = "units::distance")]
+    pub min_x: f32,
+    #[serde(with = "units::distance")]
+    pub min_y: f32,
+    #[serde(with = "units::distance")]
+    pub max_x: f32,
+    #[serde(with = "units::distance")]
+    pub max_y: f32,
+}
+
+impl WorldBounds {
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        (self.min_x..=self.max_x).contains(&x) && (self.min_y..=self.max_y).contains(&y)
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -68,10 +200,52 @@ pub struct EntryPoint {
     pub angle: f32,
     pub position: String,
     pub lane: u32,
+    #[serde(with = "units::distance")]
     pub merge_distance: f32,
     // Cloverleaf-specific fields
     #[serde(default)]
     pub loop_entry_angle: Option<f32>,
+    #[serde(default)]
+    pub metering: Option<RampMeterConfig>,
+}
+
+/// Ramp meter holding arriving cars at an entry and releasing one at a time. With a
+/// downstream detector the release rate follows the ALINEA feedback law, keeping the
+/// detector's occupancy near the target; without one cars are released at a fixed interval.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RampMeterConfig {
+    #[serde(default)]
+    pub detector: Option<String>, // id of a [[route.detectors]] entry downstream of the ramp
+    #[serde(default = "default_meter_interval", with = "units::time")]
+    pub interval: f32, // seconds between releases, the starting point of the feedback
+    #[serde(default = "default_meter_min_interval", with = "units::time")]
+    pub min_interval: f32,
+    #[serde(default = "default_meter_max_interval", with = "units::time")]
+    pub max_interval: f32,
+    #[serde(default = "default_meter_target_occupancy")]
+    pub target_occupancy: f32, // fraction of time the detector is occupied, per lane
+    #[serde(default = "default_meter_gain")]
+    pub gain: f32, // veh/h change in release rate per percentage point of occupancy error
+}
+
+fn default_meter_interval() -> f32 {
+    4.0
+}
+
+fn default_meter_min_interval() -> f32 {
+    2.0
+}
+
+fn default_meter_max_interval() -> f32 {
+    15.0
+}
+
+fn default_meter_target_occupancy() -> f32 {
+    0.2
+}
+
+fn default_meter_gain() -> f32 {
+    70.0
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -82,28 +256,364 @@ pub struct ExitPoint {
     pub angle: f32,
     pub position: String,
     pub lane: u32,
+    #[serde(with = "units::distance")]
     pub exit_distance: f32,
+    #[serde(default, with = "units::speed::option")]
+    pub ramp_speed: Option<f32>, // m/s at the end of the off-ramp (default: min_speed)
+    #[serde(default)]
+    pub weight: Option<f32>, // probability weight for destination selection (default 1)
+    #[serde(default)]
+    pub charging: bool, // leads to a charging station, electric vehicles low on charge leave here
     // Cloverleaf-specific fields
     #[serde(default)]
     pub loop_exit_angle: Option<f32>,
 }
 
+impl ExitPoint {
+    /// Exterior exits leave the ring to the outside, on the right of counter-clockwise traffic
+    pub fn is_exterior(&self) -> bool {
+        self.position != "inner"
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct TrafficRules {
+    #[serde(with = "units::speed")]
     pub speed_limit: f32,
+    #[serde(with = "units::speed")]
     pub min_speed: f32,
-    pub following_distance: f32,
+    #[serde(with = "units::time")]
+    pub following_distance: f32, // seconds of headway, despite the name
+    #[serde(with = "units::time")]
     pub lane_change_time: f32,
+    #[serde(default)]
+    pub max_lateral_acceleration: Option<f32>, // m/s^2, default crosses one lane in lane_change_time
+    #[serde(default)]
+    pub heavy_vehicle_banned_lanes: Vec<u32>, // lanes heavy car types may only use to reach an exit
+}
+
+impl TrafficRules {
+    /// Sideways acceleration limit for lane changes. Accelerating for half the lane change time
+    /// and braking for the other half covers `lane_width` in `lane_change_time`.
+    pub fn max_lateral_acceleration(&self, lane_width: f32) -> f32 {
+        self.max_lateral_acceleration
+            .unwrap_or(4.0 * lane_width / (self.lane_change_time * self.lane_change_time))
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct RoadSurface {
     pub friction_coefficient: f32,
-    pub banking_angle: f32,
+    pub banking_angle: f32, // degrees, tilts curves inwards so cars can take them faster
+    #[serde(default)]
+    pub grade: f32, // percent, uphill in the direction of travel (negative = downhill)
+    #[serde(default)]
+    pub grade_profile: Vec<GradeSection>, // stretches of a ring route with their own grade
+}
+
+impl RoadSurface {
+    /// Grade in percent at `angle` (radians) around a ring route: that of the profile
+    /// section it lies in, or the route's grade elsewhere
+    pub fn grade_at(&self, angle: f32) -> f32 {
+        self.grade_profile.iter()
+            .find(|section| section.contains(angle))
+            .map_or(self.grade, |section| section.grade)
+    }
+}
+
+/// Stretch of a ring route climbing or descending at its own grade. The stretch runs with
+/// the traffic, counter-clockwise, from `start_angle` to `end_angle`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GradeSection {
+    pub start_angle: f32, // degrees around the ring
+    pub end_angle: f32,   // degrees around the ring
+    pub grade: f32,       // percent, uphill in the direction of travel
+}
+
+impl GradeSection {
+    /// Radians from the start of the stretch to its end
+    pub fn span(&self) -> f32 {
+        (self.end_angle - self.start_angle).to_radians().rem_euclid(std::f32::consts::TAU)
+    }
+    
+    pub fn contains(&self, angle: f32) -> bool {
+        (angle - self.start_angle.to_radians()).rem_euclid(std::f32::consts::TAU) < self.span()
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
-pub struct TrafficSignals {}
+pub struct TrafficSignals {
+    #[serde(default)]
+    pub groups: Vec<SignalGroup>,
+}
+
+/// A set of signal heads that share one green/yellow/red cycle
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SignalGroup {
+    pub id: String,
+    #[serde(with = "units::time")]
+    pub green_time: f32,  // seconds
+    #[serde(with = "units::time")]
+    pub yellow_time: f32, // seconds
+    #[serde(with = "units::time")]
+    pub red_time: f32,    // seconds
+    #[serde(default, with = "units::time")]
+    pub offset: f32, // seconds into the cycle at simulation start
+    pub heads: Vec<SignalHead>,
+}
+
+impl SignalGroup {
+    pub fn cycle_time(&self) -> f32 {
+        self.green_time + self.yellow_time + self.red_time
+    }
+}
+
+/// Stop line controlled by a signal group. Ring routes (donut) place heads by `angle`,
+/// other geometries by `x`/`y` plus the `heading` of the traffic it controls.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SignalHead {
+    #[serde(default)]
+    pub angle: Option<f32>, // degrees around the ring
+    #[serde(default, with = "units::distance::option")]
+    pub x: Option<f32>,
+    #[serde(default, with = "units::distance::option")]
+    pub y: Option<f32>,
+    #[serde(default)]
+    pub heading: Option<f32>, // degrees, direction of travel of controlled traffic
+    #[serde(default, with = "units::distance::option")]
+    pub width: Option<f32>, // meters across the stop line (default: all lanes)
+    #[serde(default)]
+    pub lanes: Vec<u32>, // controlled lanes (empty = all lanes)
+}
+
+/// Lane closed over a stretch of a ring route, as for a construction zone. The stretch runs
+/// with the traffic, counter-clockwise, from `start_angle` to `end_angle`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct LaneClosure {
+    pub lane: u32,
+    pub start_angle: f32, // degrees around the ring
+    pub end_angle: f32,   // degrees around the ring
+}
+
+impl LaneClosure {
+    /// Radians from the start of the stretch to its end
+    pub fn span(&self) -> f32 {
+        (self.end_angle - self.start_angle).to_radians().rem_euclid(std::f32::consts::TAU)
+    }
+    
+    /// Radians from `angle` on to the start of the stretch, 0 within it
+    pub fn angle_ahead(&self, angle: f32) -> f32 {
+        let past_start = (angle - self.start_angle.to_radians()).rem_euclid(std::f32::consts::TAU);
+        if past_start <= self.span() {
+            0.0
+        } else {
+            std::f32::consts::TAU - past_start
+        }
+    }
+}
+
+/// Stop or yield signs at an intersection cell of a grid route. Approaches are named by
+/// the side traffic arrives from. All-way stops put a stop sign on every approach, two-way
+/// stops and yields a stop or yield sign on every approach but the `major` ones.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct IntersectionConfig {
+    pub row: usize,
+    pub col: usize,
+    pub control: String, // "all_way_stop", "two_way_stop" or "yield"
+    #[serde(default)]
+    pub major: Vec<String>, // "north", "south", "east" or "west"
+    #[serde(default = "default_critical_gap", with = "units::time")]
+    pub critical_gap: f32, // seconds drivers going straight on need before the next major car arrives
+}
+
+fn default_critical_gap() -> f32 {
+    5.0
+}
+
+/// Share of the traffic arriving at an intersection cell of a grid route from `approach`
+/// that turns left, goes through or turns right. Shares are relative to each other and
+/// movements the arriving traffic cannot make are left out.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TurningRatio {
+    pub row: usize,
+    pub col: usize,
+    pub approach: String, // "north", "south", "east" or "west", the side traffic arrives from
+    #[serde(default)]
+    pub left: f32,
+    #[serde(default)]
+    pub through: f32,
+    #[serde(default)]
+    pub right: f32,
+}
+
+/// Bus stop on a ring route, a zone of `length` meters of `lane` centered on `angle`. Buses
+/// pull up at the downstream end of the zone and dwell there before driving on. They serve
+/// the route's stops in the order listed.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BusStop {
+    pub id: String,
+    pub angle: f32, // degrees around the ring
+    pub lane: u32,
+    #[serde(default = "default_bus_stop_length", with = "units::distance")]
+    pub length: f32, // meters
+    #[serde(default = "default_dwell_time", with = "units::time")]
+    pub dwell_time: f32, // seconds buses stay stopped
+}
+
+fn default_bus_stop_length() -> f32 {
+    30.0
+}
+
+fn default_dwell_time() -> f32 {
+    20.0
+}
+
+/// Replace the lane closures in the route file at `path` with `closures`, leaving the rest
+/// of the file and its comments as they are
+pub fn save_closures(path: &std::path::Path, closures: &[LaneClosure]) -> Result<()> {
+    let text = std::fs::read_to_string(path)?;
+    let mut document: toml_edit::DocumentMut = text.parse()
+        .map_err(|e| anyhow!("Failed to parse {}: {}", path.display(), e))?;
+    let route = document.get_mut("route").and_then(|route| route.as_table_mut())
+        .ok_or_else(|| anyhow!("{} has no [route] table", path.display()))?;
+        
+    // Shortest decimals that read back as the same f32, not the f64 widening of it
+    let degrees = |angle: f32| toml_edit::value(angle.to_string().parse::<f64>().unwrap_or(angle as f64));
+    let mut tables = toml_edit::ArrayOfTables::new();
+    for closure in closures {
+        let mut table = toml_edit::Table::new();
+        table["lane"] = toml_edit::value(closure.lane as i64);
+        table["start_angle"] = degrees(closure.start_angle);
+        table["end_angle"] = degrees(closure.end_angle);
+        tables.push(table);
+    }
+    if tables.is_empty() {
+        route.remove("closures");
+    } else {
+        route.insert("closures", toml_edit::Item::ArrayOfTables(tables));
+    }
+    std::fs::write(path, document.to_string())?;
+    Ok(())
+}
+
+/// Virtual loop detector that counts cars crossing a line across the road and aggregates
+/// count, occupancy and speed over fixed intervals. Placed like a signal head.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DetectorConfig {
+    pub id: String,
+    #[serde(default)]
+    pub angle: Option<f32>, // degrees around the ring
+    #[serde(default, with = "units::distance::option")]
+    pub x: Option<f32>,
+    #[serde(default, with = "units::distance::option")]
+    pub y: Option<f32>,
+    #[serde(default)]
+    pub heading: Option<f32>, // degrees, direction of travel of counted traffic
+    #[serde(default, with = "units::distance::option")]
+    pub width: Option<f32>, // meters across the detector (default: all lanes)
+    #[serde(default)]
+    pub lanes: Vec<u32>, // counted lanes (empty = all lanes)
+    #[serde(default = "default_detector_interval", with = "units::time")]
+    pub interval: f32, // seconds per aggregated reading
+}
+
+fn default_detector_interval() -> f32 {
+    60.0
+}
+
+impl DetectorConfig {
+    /// The detector line is placed exactly like a signal head's stop line
+    pub fn placement(&self) -> SignalHead {
+        SignalHead {
+            angle: self.angle,
+            x: self.x,
+            y: self.y,
+            heading: self.heading,
+            width: self.width,
+            lanes: self.lanes.clone(),
+        }
+    }
+}
+
+/// Line across the road that travel times are measured between, placed like a loop detector
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WaypointConfig {
+    pub id: String,
+    #[serde(default)]
+    pub angle: Option<f32>, // degrees around the ring
+    #[serde(default, with = "units::distance::option")]
+    pub x: Option<f32>,
+    #[serde(default, with = "units::distance::option")]
+    pub y: Option<f32>,
+    #[serde(default)]
+    pub heading: Option<f32>, // degrees, direction of travel of timed traffic
+    #[serde(default, with = "units::distance::option")]
+    pub width: Option<f32>, // meters across the line (default: all lanes)
+    #[serde(default)]
+    pub lanes: Vec<u32>, // lanes cars are timed in (empty = all lanes)
+}
+
+impl WaypointConfig {
+    /// The waypoint line is placed exactly like a signal head's stop line
+    pub fn placement(&self) -> SignalHead {
+        SignalHead {
+            angle: self.angle,
+            x: self.x,
+            y: self.y,
+            heading: self.heading,
+            width: self.width,
+            lanes: self.lanes.clone(),
+        }
+    }
+}
+
+/// Stretch of road from one waypoint to another whose travel times are reported. A car's
+/// traversal is timed from crossing `from` to next crossing `to`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TravelTimeSegment {
+    pub id: String,
+    pub from: String, // waypoint id
+    pub to: String,   // waypoint id
+}
+
+/// Weather over the run: one condition throughout, or a schedule of changes
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WeatherConfig {
+    #[serde(default = "default_weather_condition")]
+    pub condition: String, // "dry", "wet", "ice" or "fog", until the first scheduled change
+    #[serde(default)]
+    pub schedule: Vec<WeatherChange>,
+}
+
+impl Default for WeatherConfig {
+    fn default() -> Self {
+        Self {
+            condition: default_weather_condition(),
+            schedule: Vec::new(),
+        }
+    }
+}
+
+fn default_weather_condition() -> String {
+    "dry".to_string()
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WeatherChange {
+    #[serde(with = "units::time")]
+    pub time: f32, // seconds of simulation time
+    pub condition: String,
+}
+
+/// One origin-destination matrix cell: the relative share of cars from an entry that are
+/// routed to an exit. Destinations are route exits on donut routes and grid exit points on
+/// grid routes.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OdPair {
+    pub origin: String,      // entry id
+    pub destination: String, // exit id
+    pub weight: f32,
+}
 
 impl Validate for RouteConfig {
     fn validate(&self) -> Result<()> {
@@ -171,6 +681,12 @@ impl Validate for RouteConfig {
             return Err(anyhow!("Lane width must be positive even for grid geometry"));
         }
         
+        if let Some(bounds) = &geometry.bounds {
+            if bounds.min_x >= bounds.max_x || bounds.min_y >= bounds.max_y {
+                return Err(anyhow!("World bounds must have min_x < max_x and min_y < max_y"));
+            }
+        }
+        
         // Validate entry points
         for entry in &self.route.entries {
             if entry.lane == 0 || entry.lane > geometry.lane_count {
@@ -193,6 +709,118 @@ impl Validate for RouteConfig {
             }
         }
         
+        // Validate traffic signals
+        for group in &self.route.signals.groups {
+            if group.green_time < 0.0 || group.yellow_time < 0.0 || group.red_time < 0.0 {
+                return Err(anyhow!("Signal group {} phase durations must not be negative", group.id));
+            }
+            
+            if group.cycle_time() <= 0.0 {
+                return Err(anyhow!("Signal group {} must have a positive cycle time", group.id));
+            }
+            
+            for head in &group.heads {
+                let ring_head = head.angle.is_some();
+                let positioned_head = head.x.is_some() && head.y.is_some() && head.heading.is_some();
+                if ring_head == positioned_head {
+                    return Err(anyhow!("Signal group {} heads need either an angle or x, y and heading", group.id));
+                }
+                
+                if ring_head && geometry.geometry_type != "donut" {
+                    return Err(anyhow!("Signal group {} uses angle placement, which is only supported on donut routes", group.id));
+                }
+                
+                if let Some(lane) = head.lanes.iter().find(|&&lane| lane == 0 || lane > geometry.lane_count) {
+                    return Err(anyhow!("Signal group {} lane {} is out of range (1-{})", group.id, lane, geometry.lane_count));
+                }
+            }
+        }
+        
+        // Validate loop detectors
+        for (i, detector) in self.route.detectors.iter().enumerate() {
+            if self.route.detectors[..i].iter().any(|other| other.id == detector.id) {
+                return Err(anyhow!("Detector id {} is used more than once", detector.id));
+            }
+            
+            let ring_detector = detector.angle.is_some();
+            let positioned_detector = detector.x.is_some() && detector.y.is_some() && detector.heading.is_some();
+            if ring_detector == positioned_detector {
+                return Err(anyhow!("Detector {} needs either an angle or x, y and heading", detector.id));
+            }
+            
+            if ring_detector && geometry.geometry_type != "donut" {
+                return Err(anyhow!("Detector {} uses angle placement, which is only supported on donut routes", detector.id));
+            }
+            
+            if let Some(lane) = detector.lanes.iter().find(|&&lane| lane == 0 || lane > geometry.lane_count) {
+                return Err(anyhow!("Detector {} lane {} is out of range (1-{})", detector.id, lane, geometry.lane_count));
+            }
+            
+            if !detector.interval.is_finite() || detector.interval <= 0.0 {
+                return Err(anyhow!("Detector {} aggregation interval must be positive", detector.id));
+            }
+        }
+        
+        // Validate waypoints and the travel time segments between them
+        for (i, waypoint) in self.route.waypoints.iter().enumerate() {
+            if self.route.waypoints[..i].iter().any(|other| other.id == waypoint.id) {
+                return Err(anyhow!("Waypoint id {} is used more than once", waypoint.id));
+            }
+            
+            let ring_waypoint = waypoint.angle.is_some();
+            let positioned_waypoint = waypoint.x.is_some() && waypoint.y.is_some() && waypoint.heading.is_some();
+            if ring_waypoint == positioned_waypoint {
+                return Err(anyhow!("Waypoint {} needs either an angle or x, y and heading", waypoint.id));
+            }
+            
+            if ring_waypoint && geometry.geometry_type != "donut" {
+                return Err(anyhow!("Waypoint {} uses angle placement, which is only supported on donut routes", waypoint.id));
+            }
+            
+            if let Some(lane) = waypoint.lanes.iter().find(|&&lane| lane == 0 || lane > geometry.lane_count) {
+                return Err(anyhow!("Waypoint {} lane {} is out of range (1-{})", waypoint.id, lane, geometry.lane_count));
+            }
+        }
+        
+        for (i, segment) in self.route.travel_times.iter().enumerate() {
+            if self.route.travel_times[..i].iter().any(|other| other.id == segment.id) {
+                return Err(anyhow!("Travel time segment id {} is used more than once", segment.id));
+            }
+            
+            for waypoint in [&segment.from, &segment.to] {
+                if !self.route.waypoints.iter().any(|other| &other.id == waypoint) {
+                    return Err(anyhow!("Travel time segment {} references unknown waypoint '{}'", segment.id, waypoint));
+                }
+            }
+            
+            if segment.from == segment.to {
+                return Err(anyhow!("Travel time segment {} must run between two different waypoints", segment.id));
+            }
+        }
+        
+        // Validate origin-destination matrix
+        if !self.route.od_matrix.is_empty() && geometry.geometry_type == "cloverleaf" {
+            return Err(anyhow!("Origin-destination routing is only supported on donut and grid routes"));
+        }
+        for pair in &self.route.od_matrix {
+            if !self.route.entries.iter().any(|entry| entry.id == pair.origin) {
+                return Err(anyhow!("OD matrix origin {} is not an entry id", pair.origin));
+            }
+            
+            let destination_known = if geometry.geometry_type == "grid" {
+                geometry.exit_points.iter().flatten().any(|exit| exit.id == pair.destination)
+            } else {
+                self.route.exits.iter().any(|exit| exit.id == pair.destination)
+            };
+            if !destination_known {
+                return Err(anyhow!("OD matrix destination {} is not an exit id", pair.destination));
+            }
+            
+            if !pair.weight.is_finite() || pair.weight < 0.0 {
+                return Err(anyhow!("OD matrix weight for {} -> {} must not be negative", pair.origin, pair.destination));
+            }
+        }
+        
         // Validate traffic rules
         let rules = &self.route.traffic_rules;
         if rules.speed_limit <= 0.0 || rules.min_speed <= 0.0 {
@@ -207,12 +835,187 @@ impl Validate for RouteConfig {
             return Err(anyhow!("Following distance and lane change time must be positive"));
         }
         
+        if rules.max_lateral_acceleration.is_some_and(|acceleration| acceleration <= 0.0) {
+            return Err(anyhow!("Maximum lateral acceleration must be positive"));
+        }
+        
+        for &lane in &rules.heavy_vehicle_banned_lanes {
+            if lane == 0 || lane > self.route.geometry.lane_count {
+                return Err(anyhow!("Heavy vehicle banned lane {} is out of range (1-{})", lane, self.route.geometry.lane_count));
+            }
+        }
+        
         // Validate surface properties
         let surface = &self.route.surface;
         if surface.friction_coefficient <= 0.0 || surface.friction_coefficient > 1.0 {
             return Err(anyhow!("Friction coefficient must be in range (0, 1]"));
         }
         
+        if !surface.grade.is_finite() || surface.grade.abs() > 30.0 {
+            return Err(anyhow!("Road grade must be within +/-30 percent"));
+        }
+        if !surface.banking_angle.is_finite() || !(0.0..45.0).contains(&surface.banking_angle) {
+            return Err(anyhow!("Banking angle must be at least 0 and under 45 degrees"));
+        }
+        if !surface.grade_profile.is_empty() && geometry.geometry_type != "donut" {
+            return Err(anyhow!("Grade profiles are only supported on donut routes"));
+        }
+        for section in &surface.grade_profile {
+            if !section.start_angle.is_finite() || !section.end_angle.is_finite() || section.span() == 0.0 {
+                return Err(anyhow!("Grade profile section from {} degrees needs different start and end angles", section.start_angle));
+            }
+            if !section.grade.is_finite() || section.grade.abs() > 30.0 {
+                return Err(anyhow!("Grade profile section from {} degrees must be within +/-30 percent", section.start_angle));
+            }
+        }
+        
+        // Validate exits
+        for exit in &self.route.exits {
+            // Only ring exits bend away over a ramp of that length
+            if geometry.geometry_type == "donut" && exit.exit_distance <= 0.0 {
+                return Err(anyhow!("Exit '{}' needs a positive exit distance", exit.id));
+            }
+            if exit.ramp_speed.is_some_and(|speed| speed <= 0.0) {
+                return Err(anyhow!("Exit '{}' needs a positive ramp speed", exit.id));
+            }
+            if exit.weight.is_some_and(|weight| !weight.is_finite() || weight < 0.0) {
+                return Err(anyhow!("Exit '{}' weight must not be negative", exit.id));
+            }
+            if exit.charging && geometry.geometry_type != "donut" {
+                return Err(anyhow!("Charging exits are only supported on donut routes"));
+            }
+        }
+        
+        // Validate ramp meters
+        for entry in &self.route.entries {
+            let Some(meter) = &entry.metering else {
+                continue;
+            };
+            if meter.min_interval <= 0.0 || meter.min_interval > meter.interval || meter.interval > meter.max_interval {
+                return Err(anyhow!("Ramp meter at entry '{}' needs 0 < min_interval <= interval <= max_interval", entry.id));
+            }
+            if meter.target_occupancy <= 0.0 || meter.target_occupancy >= 1.0 {
+                return Err(anyhow!("Ramp meter at entry '{}' needs a target occupancy in range (0, 1)", entry.id));
+            }
+            if meter.gain < 0.0 {
+                return Err(anyhow!("Ramp meter at entry '{}' needs a non-negative gain", entry.id));
+            }
+            if let Some(detector) = &meter.detector {
+                if !self.route.detectors.iter().any(|other| &other.id == detector) {
+                    return Err(anyhow!("Ramp meter at entry '{}' references unknown detector '{}'", entry.id, detector));
+                }
+            }
+        }
+        
+        // Validate weather
+        let weather = &self.route.weather;
+        let conditions = std::iter::once(&weather.condition)
+            .chain(weather.schedule.iter().map(|change| &change.condition));
+        for condition in conditions {
+            if !matches!(condition.as_str(), "dry" | "wet" | "ice" | "fog") {
+                return Err(anyhow!("Weather condition must be 'dry', 'wet', 'ice' or 'fog', got '{}'", condition));
+            }
+        }
+        
+        for (i, change) in weather.schedule.iter().enumerate() {
+            if !change.time.is_finite() || change.time < 0.0 {
+                return Err(anyhow!("Weather change {} must have a non-negative time", i + 1));
+            }
+            if i > 0 && change.time < weather.schedule[i - 1].time {
+                return Err(anyhow!("Weather schedule must be sorted by time"));
+            }
+        }
+        
+        // Validate lane closures
+        if !self.route.closures.is_empty() && geometry.geometry_type != "donut" {
+            return Err(anyhow!("Lane closures are only supported on donut routes"));
+        }
+        for closure in &self.route.closures {
+            if closure.lane == 0 || closure.lane > geometry.lane_count {
+                return Err(anyhow!("Lane closure lane {} is out of range (1-{})", closure.lane, geometry.lane_count));
+            }
+            if !closure.start_angle.is_finite() || !closure.end_angle.is_finite() || closure.span() == 0.0 {
+                return Err(anyhow!("Lane closure in lane {} needs different start and end angles", closure.lane));
+            }
+        }
+        
+        // Validate intersection controls
+        if !self.route.intersections.is_empty() && geometry.geometry_type != "grid" {
+            return Err(anyhow!("Intersection controls are only supported on grid routes"));
+        }
+        for (i, intersection) in self.route.intersections.iter().enumerate() {
+            let (row, col) = (intersection.row, intersection.col);
+            let cell = geometry.grid.as_ref().and_then(|grid| grid.get(row)?.get(col)).map(|cell| cell.trim());
+            let driveable = cell.is_some_and(|cell| !cell.is_empty() && cell != "o");
+            if !driveable {
+                return Err(anyhow!("Intersection at ({}, {}) is not on a road cell", row, col));
+            }
+            if self.route.intersections[..i].iter().any(|other| other.row == row && other.col == col) {
+                return Err(anyhow!("Intersection at ({}, {}) is controlled twice", row, col));
+            }
+            match intersection.control.as_str() {
+                "all_way_stop" if !intersection.major.is_empty() => {
+                    return Err(anyhow!("All-way stop at ({}, {}) has no major approaches", row, col));
+                }
+                "two_way_stop" | "yield" if intersection.major.is_empty() => {
+                    return Err(anyhow!("Intersection at ({}, {}) needs at least one major approach", row, col));
+                }
+                "all_way_stop" | "two_way_stop" | "yield" => {}
+                control => {
+                    return Err(anyhow!("Intersection control must be 'all_way_stop', 'two_way_stop' or 'yield', got '{}'", control));
+                }
+            }
+            for approach in &intersection.major {
+                if !matches!(approach.as_str(), "north" | "south" | "east" | "west") {
+                    return Err(anyhow!("Intersection approach must be 'north', 'south', 'east' or 'west', got '{}'", approach));
+                }
+            }
+            if !intersection.critical_gap.is_finite() || intersection.critical_gap <= 0.0 {
+                return Err(anyhow!("Intersection at ({}, {}) needs a positive critical gap", row, col));
+            }
+        }
+        
+        // Validate turning ratios
+        if !self.route.turning_ratios.is_empty() && geometry.geometry_type != "grid" {
+            return Err(anyhow!("Turning ratios are only supported on grid routes"));
+        }
+        for (i, ratio) in self.route.turning_ratios.iter().enumerate() {
+            let (row, col) = (ratio.row, ratio.col);
+            let cell = geometry.grid.as_ref().and_then(|grid| grid.get(row)?.get(col)).map(|cell| cell.trim());
+            if !cell.is_some_and(|cell| !cell.is_empty() && cell != "o") {
+                return Err(anyhow!("Turning ratios at ({}, {}) are not on a road cell", row, col));
+            }
+            if !matches!(ratio.approach.as_str(), "north" | "south" | "east" | "west") {
+                return Err(anyhow!("Turning ratio approach must be 'north', 'south', 'east' or 'west', got '{}'", ratio.approach));
+            }
+            if self.route.turning_ratios[..i].iter().any(|other| (other.row, other.col) == (row, col) && other.approach == ratio.approach) {
+                return Err(anyhow!("Turning ratios at ({}, {}) are given twice for the {} approach", row, col, ratio.approach));
+            }
+            let shares = [ratio.left, ratio.through, ratio.right];
+            if shares.iter().any(|share| !share.is_finite() || *share < 0.0) || shares.iter().sum::<f32>() <= 0.0 {
+                return Err(anyhow!("Turning ratios at ({}, {}) must be non-negative and not all zero", row, col));
+            }
+        }
+        
+        // Validate bus stops
+        if !self.route.bus_stops.is_empty() && geometry.geometry_type != "donut" {
+            return Err(anyhow!("Bus stops are only supported on donut routes"));
+        }
+        for (i, stop) in self.route.bus_stops.iter().enumerate() {
+            if self.route.bus_stops[..i].iter().any(|other| other.id == stop.id) {
+                return Err(anyhow!("Duplicate bus stop id {}", stop.id));
+            }
+            if stop.lane < 1 || stop.lane > geometry.lane_count {
+                return Err(anyhow!("Bus stop {} lane {} is outside lanes 1-{}", stop.id, stop.lane, geometry.lane_count));
+            }
+            if !stop.length.is_finite() || stop.length <= 0.0 {
+                return Err(anyhow!("Bus stop {} needs a positive length", stop.id));
+            }
+            if !stop.dwell_time.is_finite() || stop.dwell_time < 0.0 {
+                return Err(anyhow!("Bus stop {} dwell time must not be negative", stop.id));
+            }
+        }
+        
         Ok(())
     }
 }
\ No newline at end of file
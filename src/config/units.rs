@@ -0,0 +1,207 @@
+//! Physical quantities in the configuration files. Speeds, distances and times may be
+//! written as bare numbers in SI units (m/s, m, s) or as strings with a unit suffix, such as
+//! `"55 mph"`, `"3.5 m"` or `"2 min"`; either way they are read into SI values.
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+/// Kind of quantity a configuration value holds, and the units it may be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    Speed,
+    Distance,
+    Time,
+}
+
+impl Dimension {
+    /// Unit suffixes and the size of one of each in the SI unit, the SI unit first
+    pub fn units(self) -> &'static [(&'static str, f64)] {
+        match self {
+            Dimension::Speed => &[("m/s", 1.0), ("km/h", 1.0 / 3.6), ("kph", 1.0 / 3.6), ("mph", 0.44704), ("kn", 1852.0 / 3600.0)],
+            Dimension::Distance => &[("m", 1.0), ("km", 1000.0), ("cm", 0.01), ("ft", 0.3048), ("yd", 0.9144), ("mi", 1609.344)],
+            Dimension::Time => &[("s", 1.0), ("ms", 0.001), ("min", 60.0), ("h", 3600.0)],
+        }
+    }
+    
+    fn name(self) -> &'static str {
+        match self {
+            Dimension::Speed => "speed",
+            Dimension::Distance => "distance",
+            Dimension::Time => "time",
+        }
+    }
+}
+
+/// Read `text`, a number with an optional unit suffix, as a `dimension` in SI units
+pub fn parse_quantity(text: &str, dimension: Dimension) -> Result<f32> {
+    let text = text.trim();
+    // The unit is the trailing run of letters, so exponents stay with the number
+    let split = text.trim_end_matches(|c: char| c.is_alphabetic() || c == '/').len();
+    let (number, unit) = (text[..split].trim(), &text[split..]);
+    let value: f64 = number.parse()
+        .map_err(|_| anyhow!("'{}' is not a {}, expected a number with an optional unit such as \"10 {}\"", text, dimension.name(), dimension.units()[0].0))?;
+    if unit.is_empty() {
+        return Ok(value as f32);
+    }
+    let (_, scale) = dimension.units().iter()
+        .find(|(name, _)| *name == unit)
+        .ok_or_else(|| {
+            let known: Vec<&str> = dimension.units().iter().map(|(name, _)| *name).collect();
+            anyhow!("Unknown {} unit '{}' in '{}', expected one of {}", dimension.name(), unit, text, known.join(", "))
+        })?;
+    Ok((value * scale) as f32)
+}
+
+struct QuantityVisitor(Dimension);
+
+impl serde::de::Visitor<'_> for QuantityVisitor {
+    type Value = f32;
+    
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a {} as a number in SI units or a string with a unit", self.0.name())
+    }
+    
+    fn visit_f64<E: serde::de::Error>(self, value: f64) -> Result<f32, E> {
+        Ok(value as f32)
+    }
+    
+    fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<f32, E> {
+        Ok(value as f32)
+    }
+    
+    fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<f32, E> {
+        Ok(value as f32)
+    }
+    
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<f32, E> {
+        parse_quantity(value, self.0).map_err(E::custom)
+    }
+}
+
+/// Serde modules for `#[serde(with = "...")]` on f32 fields of one dimension, and on
+/// `Option<f32>` fields through their `option` submodule. Values are written back as SI
+/// numbers.
+macro_rules! quantity_fields {
+    ($($module:ident => $dimension:expr),* $(,)?) => {
+        $(
+            pub mod $module {
+                use serde::{Deserializer, Serializer};
+                
+                pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+                    deserializer.deserialize_any(super::QuantityVisitor($dimension))
+                }
+                
+                pub fn serialize<S: Serializer>(value: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.serialize_f32(*value)
+                }
+                
+                pub mod option {
+                    use serde::{Deserializer, Serializer};
+                    
+                    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f32>, D::Error> {
+                        super::deserialize(deserializer).map(Some)
+                    }
+                    
+                    pub fn serialize<S: Serializer>(value: &Option<f32>, serializer: S) -> Result<S::Ok, S::Error> {
+                        match value {
+                            Some(value) => serializer.serialize_some(value),
+                            None => serializer.serialize_none(),
+                        }
+                    }
+                }
+            }
+        )*
+    };
+}
+
+quantity_fields! {
+    speed => super::Dimension::Speed,
+    distance => super::Dimension::Distance,
+    time => super::Dimension::Time,
+}
+
+/// Units quantities are shown in. Configuration values are always SI underneath.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnitSystem {
+    #[default]
+    Metric,
+    Imperial,
+}
+
+impl UnitSystem {
+    pub const ALL: [UnitSystem; 2] = [UnitSystem::Metric, UnitSystem::Imperial];
+    
+    pub fn name(self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "Metric",
+            UnitSystem::Imperial => "Imperial",
+        }
+    }
+    
+    pub fn speed_unit(self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "km/h",
+            UnitSystem::Imperial => "mph",
+        }
+    }
+    
+    /// `meters_per_second` in the speed unit
+    pub fn speed(self, meters_per_second: f32) -> f32 {
+        match self {
+            UnitSystem::Metric => meters_per_second * 3.6,
+            UnitSystem::Imperial => meters_per_second / 0.44704,
+        }
+    }
+    
+    /// A value in the speed unit back in m/s
+    pub fn speed_to_si(self, speed: f32) -> f32 {
+        speed / self.speed(1.0)
+    }
+    
+    pub fn distance_unit(self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "m",
+            UnitSystem::Imperial => "ft",
+        }
+    }
+    
+    /// `meters` in the distance unit
+    pub fn distance(self, meters: f32) -> f32 {
+        match self {
+            UnitSystem::Metric => meters,
+            UnitSystem::Imperial => meters / 0.3048,
+        }
+    }
+    
+    /// A value in the distance unit back in meters
+    pub fn distance_to_si(self, distance: f32) -> f32 {
+        distance / self.distance(1.0)
+    }
+    
+    /// Length of road that densities and other per-length rates are given over
+    pub fn road_length_unit(self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "km",
+            UnitSystem::Imperial => "mi",
+        }
+    }
+    
+    /// A count per km as a count per road length unit
+    pub fn per_road_length(self, per_km: f32) -> f32 {
+        match self {
+            UnitSystem::Metric => per_km,
+            UnitSystem::Imperial => per_km * 1.609344,
+        }
+    }
+    
+    /// `meters_per_second` with one decimal and the speed unit
+    pub fn format_speed(self, meters_per_second: f32) -> String {
+        format!("{:.1} {}", self.speed(meters_per_second), self.speed_unit())
+    }
+    
+    /// `meters` with one decimal and the distance unit
+    pub fn format_distance(self, meters: f32) -> String {
+        format!("{:.1} {}", self.distance(meters), self.distance_unit())
+    }
+}
@@ -0,0 +1,96 @@
+use super::{config_file_chain, ConfigOverride, SimulationConfig};
+use anyhow::{Result, anyhow};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+/// Quiet time after the last change before reloading, editors often write a file in several steps
+const SETTLE_TIME: Duration = Duration::from_millis(250);
+
+/// Watches the route and cars files and their bases, and reloads the configuration when
+/// any of them changes
+pub struct ConfigWatcher {
+    watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    route_file: String,
+    cars_file: String,
+    overrides: Vec<ConfigOverride>, // reapplied to every reload
+    watched: Vec<PathBuf>, // absolute paths events report for the files
+    directories: Vec<PathBuf>, // directories of the files, which are what is watched
+    changed_at: Option<Instant>,
+}
+
+impl ConfigWatcher {
+    pub fn new(route_file: &str, cars_file: &str, overrides: Vec<ConfigOverride>) -> Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = Self {
+            watcher: notify::recommended_watcher(sender)?,
+            events,
+            route_file: route_file.to_string(),
+            cars_file: cars_file.to_string(),
+            overrides,
+            watched: Vec::new(),
+            directories: Vec::new(),
+            changed_at: None,
+        };
+        watcher.watch_files()?;
+        Ok(watcher)
+    }
+    
+    /// Follow the two files and the bases they name now, which an edit may have changed
+    fn watch_files(&mut self) -> Result<()> {
+        let mut files = config_file_chain(&self.route_file)?;
+        files.extend(config_file_chain(&self.cars_file)?);
+        self.watched = files.iter().map(|file| watched_path(file)).collect::<Result<_>>()?;
+        
+        // Watch the directories rather than the files, so files that editors save by
+        // replacing them are still followed afterwards
+        for path in &self.watched {
+            let directory = path.parent().ok_or_else(|| anyhow!("{} has no parent directory", path.display()))?;
+            if !self.directories.iter().any(|watched| watched == directory) {
+                self.watcher.watch(directory, RecursiveMode::NonRecursive)?;
+                self.directories.push(directory.to_path_buf());
+            }
+        }
+        Ok(())
+    }
+    
+    /// The reloaded configuration once the files changed and have settled, `None` until
+    /// then. A configuration that does not parse or validate is returned as the error.
+    pub fn poll(&mut self) -> Option<Result<SimulationConfig>> {
+        for event in self.events.try_iter() {
+            match event {
+                Ok(event) => {
+                    let modified = !matches!(event.kind, EventKind::Access(_));
+                    if modified && event.paths.iter().any(|path| self.watched.contains(path)) {
+                        self.changed_at = Some(Instant::now());
+                    }
+                }
+                Err(e) => log::warn!("Configuration watch error: {}", e),
+            }
+        }
+        
+        if self.changed_at?.elapsed() < SETTLE_TIME {
+            return None;
+        }
+        self.changed_at = None;
+        let config = SimulationConfig::load_with_overrides(&self.route_file, &self.cars_file, &self.overrides);
+        if config.is_ok() {
+            if let Err(e) = self.watch_files() {
+                log::warn!("Cannot follow the configuration bases: {}", e);
+            }
+        }
+        Some(config)
+    }
+}
+
+/// Absolute path of a configuration file as watch events report it
+fn watched_path(path: &Path) -> Result<PathBuf> {
+    let name = path.file_name().ok_or_else(|| anyhow!("{} is not a file", path.display()))?;
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    Ok(directory.canonicalize()?.join(name))
+}
@@ -0,0 +1,142 @@
+use serde::Deserialize;
+use anyhow::{Result, anyhow, bail};
+use std::collections::HashSet;
+use std::path::Path;
+use super::{read_config_file, RouteConfig, Validate};
+
+/// Several route files simulated side by side in one world, with cars leaving one route
+/// at an exit carrying on at an entry of another.
+///
+/// ```toml
+/// [[routes]]
+/// id = "ring"
+/// file = "route.toml"
+///
+/// [[routes]]
+/// id = "town"
+/// file = "route4.toml"
+/// offset = [300.0, 0.0]
+///
+/// [[transfers]]
+/// from = "ring"
+/// exit = "exit_1"
+/// to = "town"
+/// entry = "entry_west"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct WorldConfig {
+    pub routes: Vec<WorldRoute>,
+    #[serde(default)]
+    pub transfers: Vec<RouteTransfer>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WorldRoute {
+    pub id: String,
+    pub file: String, // relative to the world file
+    #[serde(default)]
+    pub offset: [f32; 2], // meters the route's own coordinates are moved by in the world
+}
+
+/// Cars leaving route `from` through `exit` join route `to` at `entry`
+#[derive(Debug, Clone, Deserialize)]
+pub struct RouteTransfer {
+    pub from: String,
+    pub exit: String,
+    pub to: String,
+    pub entry: String,
+}
+
+/// One route of a world with its configuration loaded
+#[derive(Debug, Clone)]
+pub struct WorldRegion {
+    pub id: String,
+    pub offset: [f32; 2],
+    pub route: RouteConfig,
+}
+
+/// A world file with every route it lists loaded and validated
+#[derive(Debug, Clone)]
+pub struct World {
+    pub regions: Vec<WorldRegion>,
+    pub transfers: Vec<RouteTransfer>,
+}
+
+impl WorldConfig {
+    /// Read a world file and the route files it lists, relative to the world file
+    pub fn load(path: impl AsRef<Path>) -> Result<World> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read world file {}: {}", path.display(), e))?;
+        let world: WorldConfig = toml::from_str(&content)?;
+        world.load_routes(path.parent().unwrap_or(Path::new(".")))
+    }
+    
+    /// Load the route files from `directory` and check the transfers against them
+    pub fn load_routes(&self, directory: &Path) -> Result<World> {
+        let mut regions = Vec::with_capacity(self.routes.len());
+        for world_route in &self.routes {
+            let file = directory.join(&world_route.file);
+            let content = read_config_file(&file)?;
+            let route: RouteConfig = toml::from_str(&content)
+                .map_err(|e| anyhow!("Failed to parse {}: {}", file.display(), e))?;
+            route.validate().map_err(|e| anyhow!("Route '{}': {}", world_route.id, e))?;
+            regions.push(WorldRegion {
+                id: world_route.id.clone(),
+                offset: world_route.offset,
+                route,
+            });
+        }
+        
+        let world = World { regions, transfers: self.transfers.clone() };
+        world.validate()?;
+        Ok(world)
+    }
+}
+
+impl World {
+    pub fn region_index(&self, id: &str) -> Option<usize> {
+        self.regions.iter().position(|region| region.id == id)
+    }
+}
+
+impl Validate for World {
+    fn validate(&self) -> Result<()> {
+        if self.regions.is_empty() {
+            bail!("A world needs at least one route");
+        }
+        let mut ids = HashSet::new();
+        for region in &self.regions {
+            if !ids.insert(region.id.as_str()) {
+                bail!("Duplicate world route id '{}'", region.id);
+            }
+            if region.id.contains('/') {
+                bail!("World route id '{}' cannot contain '/'", region.id);
+            }
+            if !region.offset.iter().all(|coordinate| coordinate.is_finite()) {
+                bail!("World route '{}' has a non-finite offset", region.id);
+            }
+        }
+        
+        let mut mapped_exits = HashSet::new();
+        for transfer in &self.transfers {
+            let from = self.region_index(&transfer.from)
+                .ok_or_else(|| anyhow!("Transfer from unknown route '{}'", transfer.from))?;
+            let to = self.region_index(&transfer.to)
+                .ok_or_else(|| anyhow!("Transfer to unknown route '{}'", transfer.to))?;
+            // Grid cars leave through the grid's exit points
+            let route = &self.regions[from].route.route;
+            let grid_exits = route.geometry.exit_points.iter().flatten().map(|exit| &exit.id);
+            if !route.exits.iter().map(|exit| &exit.id).chain(grid_exits).any(|exit| *exit == transfer.exit) {
+                bail!("Route '{}' has no exit '{}' to transfer from", transfer.from, transfer.exit);
+            }
+            if !self.regions[to].route.route.entries.iter().any(|entry| entry.id == transfer.entry) {
+                bail!("Route '{}' has no entry '{}' to transfer to", transfer.to, transfer.entry);
+            }
+            if !mapped_exits.insert((from, transfer.exit.as_str())) {
+                bail!("Exit '{}' of route '{}' transfers to more than one entry", transfer.exit, transfer.from);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+use crate::simulation::{Conflict, SimulationState};
+use super::{ExportFormat, create_export_writer, write_csv_row};
+use anyhow::Result;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Streams the conflicts found by the safety monitor to a CSV or JSON Lines file
+pub struct ConflictExporter {
+    writer: BufWriter<File>,
+    format: ExportFormat,
+    written: usize, // conflicts of the log already written
+    header_written: bool,
+}
+
+impl ConflictExporter {
+    pub fn create(path: impl AsRef<Path>, format: ExportFormat) -> Result<Self> {
+        Ok(Self {
+            writer: create_export_writer(path.as_ref())?,
+            format,
+            written: 0,
+            header_written: false,
+        })
+    }
+    
+    /// Write the conflicts found since the last call
+    pub fn record(&mut self, state: &SimulationState) -> Result<()> {
+        let conflicts = state.safety.conflicts();
+        // A log shorter than before belongs to a restarted simulation
+        if conflicts.len() < self.written {
+            self.written = 0;
+        }
+        for conflict in &conflicts[self.written..] {
+            self.write(conflict)?;
+        }
+        self.written = conflicts.len();
+        Ok(())
+    }
+    
+    fn write(&mut self, conflict: &Conflict) -> Result<()> {
+        match self.format {
+            ExportFormat::Csv => {
+                if !self.header_written {
+                    let header: Vec<String> = ["time", "kind", "car_a", "car_b", "value", "x", "y"]
+                        .iter()
+                        .map(|s| s.to_string())
+                        .collect();
+                    write_csv_row(&mut self.writer, &header)?;
+                    self.header_written = true;
+                }
+                
+                let row = vec![
+                    format!("{:.3}", conflict.time),
+                    conflict.kind.name().to_string(),
+                    conflict.car_a.to_string(),
+                    conflict.car_b.to_string(),
+                    format!("{:.3}", conflict.value),
+                    format!("{:.2}", conflict.position.x),
+                    format!("{:.2}", conflict.position.y),
+                ];
+                write_csv_row(&mut self.writer, &row)?;
+            }
+            ExportFormat::JsonLines => {
+                serde_json::to_writer(&mut self.writer, conflict)?;
+                writeln!(self.writer)?;
+            }
+        }
+        Ok(())
+    }
+    
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
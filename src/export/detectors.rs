@@ -0,0 +1,70 @@
+use crate::simulation::{DetectorReading, DetectorSet, SimulationState};
+use crate::config::RouteConfig;
+use super::{ExportFormat, create_export_writer, write_csv_row};
+use anyhow::Result;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Streams per-interval loop detector readings to a CSV or JSON Lines file
+pub struct DetectorExporter {
+    writer: BufWriter<File>,
+    format: ExportFormat,
+    detectors: DetectorSet,
+    header_written: bool,
+}
+
+impl DetectorExporter {
+    pub fn create(path: impl AsRef<Path>, format: ExportFormat, route: &RouteConfig) -> Result<Self> {
+        Ok(Self {
+            writer: create_export_writer(path.as_ref())?,
+            format,
+            detectors: DetectorSet::from_route(route),
+            header_written: false,
+        })
+    }
+    
+    /// Feed one simulation tick to the detectors and write the readings of intervals that ended
+    pub fn record(&mut self, state: &SimulationState) -> Result<()> {
+        for reading in self.detectors.update(state) {
+            self.write(&reading)?;
+        }
+        Ok(())
+    }
+    
+    fn write(&mut self, reading: &DetectorReading) -> Result<()> {
+        match self.format {
+            ExportFormat::Csv => {
+                if !self.header_written {
+                    let header: Vec<String> = ["detector", "start", "end", "count", "flow", "occupancy", "harmonic_mean_speed"]
+                        .iter()
+                        .map(|s| s.to_string())
+                        .collect();
+                    write_csv_row(&mut self.writer, &header)?;
+                    self.header_written = true;
+                }
+                
+                let row = vec![
+                    reading.detector.clone(),
+                    format!("{:.3}", reading.start),
+                    format!("{:.3}", reading.end),
+                    reading.count.to_string(),
+                    format!("{:.1}", reading.flow),
+                    format!("{:.4}", reading.occupancy),
+                    reading.harmonic_mean_speed.map(|speed| format!("{:.3}", speed)).unwrap_or_default(),
+                ];
+                write_csv_row(&mut self.writer, &row)?;
+            }
+            ExportFormat::JsonLines => {
+                serde_json::to_writer(&mut self.writer, reading)?;
+                writeln!(self.writer)?;
+            }
+        }
+        Ok(())
+    }
+    
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,221 @@
+use crate::simulation::SimulationState;
+use crate::config::RouteConfig;
+use super::{ExportFormat, create_export_writer, write_csv_row};
+use anyhow::Result;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Aggregate metrics for a single simulation tick
+#[derive(Debug, Clone, Serialize)]
+pub struct TickMetrics {
+    pub time: f32,
+    pub active_cars: u32,
+    pub mean_speed: f32,
+    pub spawned: u32,
+    pub exited: u32,
+    pub lane_counts: Vec<u32>,
+    pub lane_density: Vec<f32>, // vehicles per km per lane
+    pub exit_counts: Vec<u32>, // cars that left through each route exit this tick
+    pub stop_departures: Vec<u32>, // buses that pulled away from each bus stop this tick
+    pub stop_mean_dwell: Vec<f32>, // seconds buses dwelt at each bus stop, over the run so far
+}
+
+impl TickMetrics {
+    /// Collect metrics from the current state. `spawned`, `exited`, `exit_counts` and
+    /// `stop_departures` are counts for this tick, `stop_mean_dwell` covers the run so far.
+    pub fn collect(state: &SimulationState, lane_lengths: &[f32], spawned: u32, exited: u32, exit_counts: Vec<u32>, stop_departures: Vec<u32>, stop_mean_dwell: Vec<f32>) -> Self {
+        let mut lane_counts = vec![0u32; lane_lengths.len()];
+        let mut speed_sum = 0.0;
+        
+        for car in &state.cars {
+            speed_sum += car.velocity.magnitude();
+            let lane_index = car.current_lane.saturating_sub(1) as usize;
+            if let Some(count) = lane_counts.get_mut(lane_index) {
+                *count += 1;
+            }
+        }
+        
+        let mean_speed = if state.cars.is_empty() {
+            0.0
+        } else {
+            speed_sum / state.cars.len() as f32
+        };
+        
+        let lane_density = lane_counts.iter()
+            .zip(lane_lengths)
+            .map(|(&count, &length)| if length > 0.0 { count as f32 / (length / 1000.0) } else { 0.0 })
+            .collect();
+            
+        Self {
+            time: state.time,
+            active_cars: state.active_cars,
+            mean_speed,
+            spawned,
+            exited,
+            lane_counts,
+            lane_density,
+            exit_counts,
+            stop_departures,
+            stop_mean_dwell,
+        }
+    }
+}
+
+/// Turns consecutive simulation states into per-tick metrics, tracking the running totals
+/// needed for per-tick spawn and exit counts
+pub struct MetricsCollector {
+    lane_lengths: Vec<f32>,
+    exit_ids: Vec<String>,
+    last_total_spawned: u32,
+    last_total_exited: u32,
+    last_exit_counts: Vec<u32>,
+    stop_ids: Vec<String>,
+    last_stop_departures: Vec<u32>,
+}
+
+impl MetricsCollector {
+    pub fn new(route: &RouteConfig) -> Self {
+        let geometry = &route.route.geometry;
+        let lane_lengths = (1..=geometry.lane_count)
+            .map(|lane| geometry.lane_length(lane))
+            .collect();
+        let exit_ids: Vec<String> = if geometry.geometry_type == "grid" {
+            geometry.exit_points.iter().flatten().map(|exit| exit.id.clone()).collect()
+        } else {
+            route.route.exits.iter().map(|exit| exit.id.clone()).collect()
+        };
+        
+        Self {
+            lane_lengths,
+            last_exit_counts: vec![0; exit_ids.len()],
+            exit_ids,
+            last_total_spawned: 0,
+            last_total_exited: 0,
+            stop_ids: route.route.bus_stops.iter().map(|stop| stop.id.clone()).collect(),
+            last_stop_departures: vec![0; route.route.bus_stops.len()],
+        }
+    }
+    
+    /// Route exits, in the order of `TickMetrics::exit_counts`
+    pub fn exit_ids(&self) -> &[String] {
+        &self.exit_ids
+    }
+    
+    /// Route bus stops, in the order of `TickMetrics::stop_departures` and `stop_mean_dwell`
+    pub fn stop_ids(&self) -> &[String] {
+        &self.stop_ids
+    }
+    
+    pub fn lane_count(&self) -> usize {
+        self.lane_lengths.len()
+    }
+    
+    /// Metrics of the tick that produced `state`. Call once for every tick.
+    pub fn collect(&mut self, state: &SimulationState) -> TickMetrics {
+        let total_exited = state.total_spawned.saturating_sub(state.active_cars);
+        let spawned = state.total_spawned.saturating_sub(self.last_total_spawned);
+        let exited = total_exited.saturating_sub(self.last_total_exited);
+        self.last_total_spawned = state.total_spawned;
+        self.last_total_exited = total_exited;
+        
+        let mut exit_counts = Vec::with_capacity(self.exit_ids.len());
+        for (exit_id, last_count) in self.exit_ids.iter().zip(&mut self.last_exit_counts) {
+            let count = state.exit_counts.get(exit_id).copied().unwrap_or(0);
+            exit_counts.push(count.saturating_sub(*last_count));
+            *last_count = count;
+        }
+        
+        let mut stop_departures = Vec::with_capacity(self.stop_ids.len());
+        let mut stop_mean_dwell = Vec::with_capacity(self.stop_ids.len());
+        for (stop_id, last_departures) in self.stop_ids.iter().zip(&mut self.last_stop_departures) {
+            let stop = state.bus_stops.iter().find(|stop| &stop.id == stop_id);
+            let departures = stop.map_or(0, |stop| stop.departures);
+            stop_departures.push(departures.saturating_sub(*last_departures));
+            stop_mean_dwell.push(stop.map_or(0.0, |stop| stop.mean_dwell()));
+            *last_departures = departures;
+        }
+        
+        TickMetrics::collect(state, &self.lane_lengths, spawned, exited, exit_counts, stop_departures, stop_mean_dwell)
+    }
+}
+
+/// Streams per-tick aggregate metrics to a CSV or JSON Lines file
+pub struct MetricsExporter {
+    writer: BufWriter<File>,
+    format: ExportFormat,
+    collector: MetricsCollector,
+    header_written: bool,
+}
+
+impl MetricsExporter {
+    pub fn create(path: impl AsRef<Path>, format: ExportFormat, route: &RouteConfig) -> Result<Self> {
+        Ok(Self {
+            writer: create_export_writer(path.as_ref())?,
+            format,
+            collector: MetricsCollector::new(route),
+            header_written: false,
+        })
+    }
+    
+    /// Record one tick of metrics from the current simulation state
+    pub fn record(&mut self, state: &SimulationState) -> Result<()> {
+        let metrics = self.collector.collect(state);
+        self.write(&metrics)
+    }
+    
+    fn write(&mut self, metrics: &TickMetrics) -> Result<()> {
+        match self.format {
+            ExportFormat::Csv => {
+                if !self.header_written {
+                    let mut header: Vec<String> = ["time", "active_cars", "mean_speed", "spawned", "exited"]
+                        .iter()
+                        .map(|s| s.to_string())
+                        .collect();
+                    for lane in 1..=self.collector.lane_count() {
+                        header.push(format!("lane_{}_count", lane));
+                    }
+                    for lane in 1..=self.collector.lane_count() {
+                        header.push(format!("lane_{}_density", lane));
+                    }
+                    for exit_id in self.collector.exit_ids() {
+                        header.push(format!("exit_{}_count", exit_id));
+                    }
+                    for stop_id in self.collector.stop_ids() {
+                        header.push(format!("stop_{}_departures", stop_id));
+                        header.push(format!("stop_{}_mean_dwell", stop_id));
+                    }
+                    write_csv_row(&mut self.writer, &header)?;
+                    self.header_written = true;
+                }
+                
+                let mut row = vec![
+                    format!("{:.3}", metrics.time),
+                    metrics.active_cars.to_string(),
+                    format!("{:.3}", metrics.mean_speed),
+                    metrics.spawned.to_string(),
+                    metrics.exited.to_string(),
+                ];
+                row.extend(metrics.lane_counts.iter().map(|c| c.to_string()));
+                row.extend(metrics.lane_density.iter().map(|d| format!("{:.3}", d)));
+                row.extend(metrics.exit_counts.iter().map(|c| c.to_string()));
+                for (departures, dwell) in metrics.stop_departures.iter().zip(&metrics.stop_mean_dwell) {
+                    row.push(departures.to_string());
+                    row.push(format!("{:.3}", dwell));
+                }
+                write_csv_row(&mut self.writer, &row)?;
+            }
+            ExportFormat::JsonLines => {
+                serde_json::to_writer(&mut self.writer, metrics)?;
+                writeln!(self.writer)?;
+            }
+        }
+        Ok(())
+    }
+    
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use anyhow::Result;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+pub mod conflicts;
+pub mod detectors;
+pub mod fcd;
+pub mod metrics;
+pub mod noise;
+pub mod queues;
+pub mod summary;
+pub mod trips;
+pub mod travel_times;
+pub mod trajectories;
+
+pub use conflicts::*;
+pub use detectors::*;
+pub use fcd::*;
+pub use metrics::*;
+pub use noise::*;
+pub use queues::*;
+pub use summary::*;
+pub use trips::*;
+pub use travel_times::*;
+pub use trajectories::*;
+
+/// Output format for streamed records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Comma-separated values with a header row
+    Csv,
+    /// One JSON object per line
+    JsonLines,
+}
+
+/// Create a buffered writer for an export file, creating parent directories as needed
+pub fn create_export_writer(path: &Path) -> Result<BufWriter<File>> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+    Ok(BufWriter::new(File::create(path)?))
+}
+
+/// Write a single CSV row, quoting fields that contain separators
+pub fn write_csv_row<W: Write>(writer: &mut W, fields: &[String]) -> Result<()> {
+    let row: Vec<String> = fields.iter().map(|field| {
+        if field.contains(',') || field.contains('"') || field.contains('\n') {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.clone()
+        }
+    }).collect();
+    writeln!(writer, "{}", row.join(","))?;
+    Ok(())
+}
@@ -0,0 +1,196 @@
+use crate::simulation::{NoiseMap, SimulationState};
+use crate::config::CarsConfig;
+use super::{create_export_writer, write_csv_row};
+use anyhow::Result;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Value of cells no car has been in, in GeoTIFF rasters
+pub const NOISE_NODATA: f32 = -9999.0;
+
+/// File format of a noise map export, picked by the file extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoiseFormat {
+    /// One row per cell with traffic: cell center and level
+    Csv,
+    /// Single-band 32-bit float raster georeferenced in simulation meters
+    GeoTiff,
+}
+
+impl NoiseFormat {
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("tif" | "tiff") => NoiseFormat::GeoTiff,
+            _ => NoiseFormat::Csv,
+        }
+    }
+}
+
+/// Accumulates the noise map of a run and writes it when the run ends
+pub struct NoiseExporter {
+    path: PathBuf,
+    format: NoiseFormat,
+    map: NoiseMap,
+}
+
+impl NoiseExporter {
+    pub fn create(path: impl AsRef<Path>, cars_config: &CarsConfig, cell_size: f32) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        // Fail now rather than after the run if the file cannot be written
+        create_export_writer(&path)?;
+        Ok(Self {
+            format: NoiseFormat::from_path(&path),
+            path,
+            map: NoiseMap::new(cars_config, cell_size),
+        })
+    }
+    
+    pub fn record(&mut self, state: &SimulationState) {
+        self.map.record(state);
+    }
+    
+    /// Write the map as it stands
+    pub fn finish(&mut self) -> Result<()> {
+        match self.format {
+            NoiseFormat::Csv => write_noise_csv(&self.map, &self.path),
+            NoiseFormat::GeoTiff => write_noise_geotiff(&self.map, &self.path),
+        }
+    }
+}
+
+/// Write every cell with traffic as `x,y,level_db`, the cell center in meters
+pub fn write_noise_csv(map: &NoiseMap, path: &Path) -> Result<()> {
+    let mut writer = create_export_writer(path)?;
+    let header: Vec<String> = ["x", "y", "level_db"].iter().map(|s| s.to_string()).collect();
+    write_csv_row(&mut writer, &header)?;
+    for cell in map.cells() {
+        let row = vec![
+            format!("{:.2}", cell.center.x),
+            format!("{:.2}", cell.center.y),
+            format!("{:.2}", cell.level),
+        ];
+        write_csv_row(&mut writer, &row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write the map as a GeoTIFF covering the cells with traffic: one float per cell in
+/// dB(A), north-up, `NOISE_NODATA` where no car has been. The raster is tied to the
+/// simulation's own meters through a user-defined projected coordinate system, so GIS
+/// tools place it to scale but not on the globe.
+pub fn write_noise_geotiff(map: &NoiseMap, path: &Path) -> Result<()> {
+    let cells = map.cells();
+    let (min_col, max_col) = cells.iter().fold((i32::MAX, i32::MIN), |(min, max), cell| (min.min(cell.col), max.max(cell.col)));
+    let (min_row, max_row) = cells.iter().fold((i32::MAX, i32::MIN), |(min, max), cell| (min.min(cell.row), max.max(cell.row)));
+    let (width, height) = if cells.is_empty() { (1, 1) } else { ((max_col - min_col + 1) as u32, (max_row - min_row + 1) as u32) };
+    let (min_col, max_row) = if cells.is_empty() { (0, 0) } else { (min_col, max_row) };
+    
+    // Rows run from the north edge down
+    let mut pixels = vec![NOISE_NODATA; (width * height) as usize];
+    for cell in &cells {
+        let x = (cell.col - min_col) as u32;
+        let y = (max_row - cell.row) as u32;
+        pixels[(y * width + x) as usize] = cell.level;
+    }
+    
+    let cell_size = map.cell_size() as f64;
+    let origin = [min_col as f64 * cell_size, (max_row + 1) as f64 * cell_size];
+    let mut tiff = TiffWriter::default();
+    tiff.long(256, width); // ImageWidth
+    tiff.long(257, height); // ImageLength
+    tiff.shorts(258, &[32]); // BitsPerSample
+    tiff.shorts(259, &[1]); // Compression: none
+    tiff.shorts(262, &[1]); // PhotometricInterpretation: black is zero
+    tiff.long(273, 0); // StripOffsets, filled in once the layout is known
+    tiff.shorts(277, &[1]); // SamplesPerPixel
+    tiff.long(278, height); // RowsPerStrip
+    tiff.long(279, width * height * 4); // StripByteCounts
+    tiff.shorts(284, &[1]); // PlanarConfiguration: chunky
+    tiff.shorts(339, &[3]); // SampleFormat: IEEE float
+    tiff.doubles(33550, &[cell_size, cell_size, 0.0]); // ModelPixelScale
+    tiff.doubles(33922, &[0.0, 0.0, 0.0, origin[0], origin[1], 0.0]); // ModelTiepoint: top-left corner
+    tiff.shorts(34735, &[
+        1, 1, 0, 4, // GeoKeyDirectory version 1.1.0, four keys
+        1024, 0, 1, 1, // GTModelType: projected
+        1025, 0, 1, 1, // GTRasterType: pixel is area
+        3072, 0, 1, 32767, // ProjectedCSType: user-defined
+        3076, 0, 1, 9001, // ProjLinearUnits: meter
+    ]);
+    tiff.ascii(42113, &format!("{}", NOISE_NODATA)); // GDAL_NODATA
+    
+    let image: Vec<u8> = pixels.iter().flat_map(|pixel| pixel.to_le_bytes()).collect();
+    let mut writer = create_export_writer(path)?;
+    writer.write_all(&tiff.finish(&image))?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Builds a little-endian baseline TIFF with a single image file directory and one strip
+#[derive(Default)]
+struct TiffWriter {
+    entries: Vec<(u16, u16, u32, Vec<u8>)>, // tag, field type, value count, value bytes
+}
+
+impl TiffWriter {
+    const SHORT: u16 = 3;
+    const LONG: u16 = 4;
+    const ASCII: u16 = 2;
+    const DOUBLE: u16 = 12;
+    
+    fn shorts(&mut self, tag: u16, values: &[u16]) {
+        self.entries.push((tag, Self::SHORT, values.len() as u32, values.iter().flat_map(|value| value.to_le_bytes()).collect()));
+    }
+    
+    fn long(&mut self, tag: u16, value: u32) {
+        self.entries.push((tag, Self::LONG, 1, value.to_le_bytes().to_vec()));
+    }
+    
+    fn doubles(&mut self, tag: u16, values: &[f64]) {
+        self.entries.push((tag, Self::DOUBLE, values.len() as u32, values.iter().flat_map(|value| value.to_le_bytes()).collect()));
+    }
+    
+    fn ascii(&mut self, tag: u16, text: &str) {
+        let mut bytes = text.as_bytes().to_vec();
+        bytes.push(0);
+        self.entries.push((tag, Self::ASCII, bytes.len() as u32, bytes));
+    }
+    
+    /// The whole file: header, directory, values too long to fit in their entries, then
+    /// the image as the only strip
+    fn finish(mut self, image: &[u8]) -> Vec<u8> {
+        self.entries.sort_by_key(|entry| entry.0);
+        let directory_size = 2 + 12 * self.entries.len() + 4;
+        let mut extra_offset = 8 + directory_size;
+        let extra_size: usize = self.entries.iter().filter(|entry| entry.3.len() > 4).map(|entry| entry.3.len().next_multiple_of(2)).sum();
+        let image_offset = (extra_offset + extra_size) as u32;
+        
+        let mut file = Vec::with_capacity(image_offset as usize + image.len());
+        file.extend_from_slice(b"II");
+        file.extend_from_slice(&42u16.to_le_bytes());
+        file.extend_from_slice(&8u32.to_le_bytes());
+        file.extend_from_slice(&(self.entries.len() as u16).to_le_bytes());
+        let mut extra = Vec::with_capacity(extra_size);
+        for (tag, field_type, count, mut bytes) in self.entries {
+            if tag == 273 {
+                bytes = image_offset.to_le_bytes().to_vec();
+            }
+            file.extend_from_slice(&tag.to_le_bytes());
+            file.extend_from_slice(&field_type.to_le_bytes());
+            file.extend_from_slice(&count.to_le_bytes());
+            if bytes.len() > 4 {
+                file.extend_from_slice(&(extra_offset as u32).to_le_bytes());
+                extra_offset += bytes.len().next_multiple_of(2);
+                bytes.resize(bytes.len().next_multiple_of(2), 0);
+                extra.extend_from_slice(&bytes);
+            } else {
+                bytes.resize(4, 0);
+                file.extend_from_slice(&bytes);
+            }
+        }
+        file.extend_from_slice(&0u32.to_le_bytes()); // no further directories
+        file.extend_from_slice(&extra);
+        file.extend_from_slice(image);
+        file
+    }
+}
@@ -0,0 +1,94 @@
+use crate::simulation::{Queue, SimulationState};
+use super::{ExportFormat, create_export_writer, write_csv_row};
+use anyhow::Result;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Simulated seconds between the samples of the queues written
+pub const QUEUE_SAMPLE_INTERVAL: f32 = 1.0;
+
+/// One queue as it stood at `time`
+#[derive(Serialize)]
+struct QueueSample<'a> {
+    time: f32,
+    #[serde(flatten)]
+    queue: &'a Queue,
+}
+
+/// Streams the queues standing every `QUEUE_SAMPLE_INTERVAL` seconds to a CSV or JSON Lines
+/// file, a row per queue: where its head and tail are, how long it is and how fast it grows
+pub struct QueueExporter {
+    writer: BufWriter<File>,
+    format: ExportFormat,
+    next_sample: f32,
+    header_written: bool,
+}
+
+impl QueueExporter {
+    pub fn create(path: impl AsRef<Path>, format: ExportFormat) -> Result<Self> {
+        Ok(Self {
+            writer: create_export_writer(path.as_ref())?,
+            format,
+            next_sample: 0.0,
+            header_written: false,
+        })
+    }
+    
+    /// Write the queues of `state` if a sample is due
+    pub fn record(&mut self, state: &SimulationState) -> Result<()> {
+        // A restarted simulation samples from its start again
+        if state.time + QUEUE_SAMPLE_INTERVAL < self.next_sample {
+            self.next_sample = 0.0;
+        }
+        if state.time < self.next_sample {
+            return Ok(());
+        }
+        self.next_sample = (state.time / QUEUE_SAMPLE_INTERVAL).floor() * QUEUE_SAMPLE_INTERVAL + QUEUE_SAMPLE_INTERVAL;
+        for queue in &state.queues {
+            self.write(&QueueSample { time: state.time, queue })?;
+        }
+        Ok(())
+    }
+    
+    fn write(&mut self, sample: &QueueSample) -> Result<()> {
+        match self.format {
+            ExportFormat::Csv => {
+                if !self.header_written {
+                    let header: Vec<String> = ["time", "queue", "lane", "head", "tail", "length", "cars", "growth_rate", "start_time", "max_length"]
+                        .iter()
+                        .map(|s| s.to_string())
+                        .collect();
+                    write_csv_row(&mut self.writer, &header)?;
+                    self.header_written = true;
+                }
+                
+                let queue = sample.queue;
+                let row = vec![
+                    format!("{:.3}", sample.time),
+                    queue.id.to_string(),
+                    queue.lane.to_string(),
+                    format!("{:.2}", queue.head),
+                    format!("{:.2}", queue.tail),
+                    format!("{:.2}", queue.length),
+                    queue.cars.to_string(),
+                    format!("{:.3}", queue.growth_rate),
+                    format!("{:.3}", queue.start_time),
+                    format!("{:.2}", queue.max_length),
+                ];
+                write_csv_row(&mut self.writer, &row)?;
+            }
+            ExportFormat::JsonLines => {
+                serde_json::to_writer(&mut self.writer, sample)?;
+                writeln!(self.writer)?;
+            }
+        }
+        Ok(())
+    }
+    
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,406 @@
+use crate::simulation::{CarId, ConflictKind, SafetyLog, SimulationEvent, SimulationState, TravelTimeReliability, SAFETY_BIN_WIDTH};
+use crate::config::{RouteConfig, UnitSystem};
+use super::create_export_writer;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::collections::hash_map::Entry;
+use std::io::Write;
+use std::path::Path;
+
+/// Statistics of a whole run, printed when it ends and optionally written as JSON
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    pub simulated_time: f32, // seconds covered by the summary
+    pub spawned: u32,
+    pub exited: u32,
+    pub removed: u32, // towed away or despawned without reaching an exit
+    pub active_at_end: u32,
+    pub exits: BTreeMap<String, u32>, // cars that left through each exit
+    pub travel_time: TravelTimeSummary,
+    pub mean_delay: f32, // seconds, from the trip log
+    pub slow_time: f32, // vehicle-seconds spent below half the speed limit
+    pub slow_share: f32, // fraction of all vehicle-seconds
+    pub lane_changes: u32,
+    pub collisions: u32,
+    pub safety: SafetySummary,
+    pub energy: EnergySummary,
+    pub queues: QueueSummary,
+    pub travel_time_reliability: BTreeMap<String, TravelTimeReliability>, // by travel time segment
+    pub behaviors: BTreeMap<String, BehaviorSummary>,
+}
+
+/// Conflicts found by the surrogate safety measures, and the distributions of the measures
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SafetySummary {
+    pub ttc_conflicts: u32,
+    pub pet_conflicts: u32,
+    pub min_ttc: Option<f32>, // seconds, smallest of any TTC conflict
+    pub min_pet: Option<f32>,
+    pub bin_width: f32, // seconds covered by each bin of the distributions
+    pub ttc_exposure: Vec<f32>, // vehicle-seconds spent closing in at a TTC in each bin
+    pub pet_counts: Vec<u32>, // encroachments with a PET in each bin
+}
+
+/// Energy the electric vehicles drew from and recovered into their batteries
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EnergySummary {
+    pub electric_cars: u32, // electric vehicles on the road at some point of the run
+    pub distance: f32, // km driven by them
+    pub consumed: f32, // kWh drawn for driving and auxiliaries
+    pub regenerated: f32, // kWh recovered while braking
+    pub consumption: f32, // kWh per 100 km, net of what was recovered
+    pub low_charge: u32, // cars whose charge fell below the low charge threshold
+    pub charged: u32, // of those, cars that left through a charging exit
+    pub depleted: u32, // cars that ran out of charge
+}
+
+/// Queues of slow cars found on ring routes
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QueueSummary {
+    pub formed: u32,
+    pub max_length: f32, // meters, longest any queue grew
+    pub max_duration: f32, // seconds, longest any queue lasted, those still standing counted up to the end
+}
+
+/// Travel times from spawn to exit of the cars that reached an exit, in seconds
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TravelTimeSummary {
+    pub count: u32,
+    pub mean: f32,
+    pub p95: f32,
+}
+
+impl TravelTimeSummary {
+    /// Mean and 95th percentile as "mean/p95" seconds, "-" when no car finished its trip
+    pub fn mean_p95(&self) -> String {
+        if self.count == 0 {
+            return "-".to_string();
+        }
+        format!("{:.1}s/{:.1}s", self.mean, self.p95)
+    }
+}
+
+/// Statistics of the cars with one driver behavior. A collision counts for the behavior
+/// of each car involved, so these add up to twice the run's collisions.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BehaviorSummary {
+    pub spawned: u32,
+    pub exited: u32,
+    pub travel_time: TravelTimeSummary,
+    pub slow_time: f32,
+    pub slow_share: f32,
+    pub lane_changes: u32,
+    pub cars_in_collisions: u32, // cars of the behavior involved in a collision, once per collision
+}
+
+/// Running totals for one behavior while the run is in progress
+#[derive(Debug, Default)]
+struct BehaviorTotals {
+    spawned: u32,
+    travel_times: Vec<f32>,
+    slow_time: f32,
+    drive_time: f32,
+    lane_changes: u32,
+    collisions: u32,
+}
+
+/// Car being followed, remembered until it leaves the road
+#[derive(Debug)]
+struct TrackedCar {
+    behavior: String,
+    spawn_time: f32,
+    energy: Option<(f32, f32)>, // battery's consumed and regenerated kWh as of the last tick
+    low_charge: bool,
+}
+
+/// Follows every tick of a run to build its `RunSummary`
+pub struct SummaryCollector {
+    slow_speed: f32, // half the speed limit, m/s
+    start_time: f32,
+    start_spawned: u32,
+    start_active: u32,
+    start_exit_counts: BTreeMap<String, u32>,
+    cars: HashMap<CarId, TrackedCar>, // by car id
+    totals: BTreeMap<String, BehaviorTotals>, // by behavior name
+    lane_changes: u32,
+    collisions: u32,
+    start_safety: SafetyLog,
+    safety: SafetySummary,
+    chargers: Vec<String>, // ids of the charging exits
+    energy: EnergySummary,
+    queues: QueueSummary,
+}
+
+impl SummaryCollector {
+    /// Start summarizing from `state`, the state the run starts from
+    pub fn new(route: &RouteConfig, state: &SimulationState) -> Self {
+        Self {
+            slow_speed: route.route.traffic_rules.speed_limit * 0.5,
+            start_time: state.time,
+            start_spawned: state.total_spawned,
+            start_active: state.cars.len() as u32,
+            start_exit_counts: state.exit_counts.clone(),
+            cars: HashMap::new(),
+            totals: BTreeMap::new(),
+            lane_changes: 0,
+            collisions: 0,
+            start_safety: state.safety.clone(),
+            safety: SafetySummary::default(),
+            chargers: route.route.exits.iter().filter(|exit| exit.charging).map(|exit| exit.id.clone()).collect(),
+            energy: EnergySummary::default(),
+            queues: QueueSummary::default(),
+        }
+    }
+    
+    /// Take in the tick that produced `state`. Call once for every tick.
+    pub fn record(&mut self, state: &SimulationState) {
+        let start_time = self.start_time;
+        for car in &state.cars {
+            let tracked = self.cars.entry(car.id).or_insert_with(|| {
+                // Cars already on the road when the run started only count from then on
+                let energy = car.battery.as_ref().map(|battery| {
+                    self.energy.electric_cars += 1;
+                    if car.spawn_time >= start_time { (0.0, 0.0) } else { (battery.consumed, battery.regenerated) }
+                });
+                TrackedCar {
+                    behavior: state.names.behavior(car.behavior_type).to_string(),
+                    spawn_time: car.spawn_time,
+                    energy,
+                    low_charge: false,
+                }
+            });
+            if let (Some(battery), Some((consumed, regenerated))) = (&car.battery, &mut tracked.energy) {
+                self.energy.consumed += battery.consumed - *consumed;
+                self.energy.regenerated += battery.regenerated - *regenerated;
+                self.energy.distance += car.velocity.magnitude() * state.dt / 1000.0;
+                (*consumed, *regenerated) = (battery.consumed, battery.regenerated);
+            }
+            let totals = self.totals.entry(tracked.behavior.clone()).or_default();
+            totals.drive_time += state.dt;
+            if car.velocity.magnitude() < self.slow_speed {
+                totals.slow_time += state.dt;
+            }
+        }
+        
+        for event in &state.events {
+            match event {
+                SimulationEvent::CarSpawned { car, .. } => {
+                    // Cars that left again in the tick they spawned in are known from their trip
+                    if let Entry::Vacant(entry) = self.cars.entry(*car) {
+                        if let Some(tracked) = departed(state, *car) {
+                            entry.insert(tracked);
+                        }
+                    }
+                    if let Some(totals) = self.totals_for(*car) {
+                        totals.spawned += 1;
+                    }
+                }
+                SimulationEvent::CarExited { car, exit, time } => {
+                    if let Some(tracked) = self.cars.remove(car).or_else(|| departed(state, *car)) {
+                        if tracked.low_charge && self.chargers.contains(exit) {
+                            self.energy.charged += 1;
+                        }
+                        let totals = self.totals.entry(tracked.behavior).or_default();
+                        totals.travel_times.push(time - tracked.spawn_time);
+                    }
+                }
+                SimulationEvent::LaneChangeStarted { car, .. } => {
+                    self.lane_changes += 1;
+                    if let Some(totals) = self.totals_for(*car) {
+                        totals.lane_changes += 1;
+                    }
+                }
+                SimulationEvent::CollisionDetected(collision) => {
+                    self.collisions += 1;
+                    for car in [collision.car_a, collision.car_b] {
+                        if let Some(totals) = self.totals_for(car) {
+                            totals.collisions += 1;
+                        }
+                    }
+                }
+                SimulationEvent::ConflictDetected(conflict) => {
+                    let (count, min) = match conflict.kind {
+                        ConflictKind::TimeToCollision => (&mut self.safety.ttc_conflicts, &mut self.safety.min_ttc),
+                        ConflictKind::PostEncroachment => (&mut self.safety.pet_conflicts, &mut self.safety.min_pet),
+                    };
+                    *count += 1;
+                    *min = Some(min.map_or(conflict.value, |min| min.min(conflict.value)));
+                }
+                SimulationEvent::BatteryLow { car, .. } => {
+                    self.energy.low_charge += 1;
+                    if let Some(tracked) = self.cars.get_mut(car) {
+                        tracked.low_charge = true;
+                    }
+                }
+                SimulationEvent::BatteryDepleted { .. } => {
+                    self.energy.depleted += 1;
+                }
+                SimulationEvent::QueueFormed { .. } => {
+                    self.queues.formed += 1;
+                }
+                SimulationEvent::QueueDissipated { duration, max_length, .. } => {
+                    self.queues.max_length = self.queues.max_length.max(*max_length);
+                    self.queues.max_duration = self.queues.max_duration.max(*duration);
+                }
+                SimulationEvent::SignalPhaseChanged { .. } |
+                SimulationEvent::BlindSpotMiss { .. } |
+                SimulationEvent::NearMiss { .. } => {}
+            }
+        }
+        
+        // Cars towed away or despawned leave no event, forget them once a few have piled up
+        if self.cars.len() > state.cars.len() + 64 {
+            let present: std::collections::HashSet<CarId> = state.cars.iter().map(|car| car.id).collect();
+            self.cars.retain(|id, _| present.contains(id));
+        }
+    }
+    
+    fn totals_for(&mut self, car: CarId) -> Option<&mut BehaviorTotals> {
+        let tracked = self.cars.get(&car)?;
+        Some(self.totals.entry(tracked.behavior.clone()).or_default())
+    }
+    
+    /// Summary of the run up to `state`, the final state
+    pub fn finish(&self, state: &SimulationState) -> RunSummary {
+        let behaviors: BTreeMap<String, BehaviorSummary> = self.totals.iter()
+            .map(|(name, totals)| (name.clone(), BehaviorSummary {
+                spawned: totals.spawned,
+                exited: totals.travel_times.len() as u32,
+                travel_time: TravelTimeSummary::from_times(&totals.travel_times),
+                slow_time: totals.slow_time,
+                slow_share: share(totals.slow_time, totals.drive_time),
+                lane_changes: totals.lane_changes,
+                cars_in_collisions: totals.collisions,
+            }))
+            .collect();
+        let travel_times: Vec<f32> = self.totals.values().flat_map(|totals| totals.travel_times.iter().copied()).collect();
+        let slow_time: f32 = self.totals.values().map(|totals| totals.slow_time).sum();
+        let drive_time: f32 = self.totals.values().map(|totals| totals.drive_time).sum();
+        let spawned = state.total_spawned.saturating_sub(self.start_spawned);
+        let exits: BTreeMap<String, u32> = state.exit_counts.iter()
+            .map(|(exit, count)| (exit.clone(), count.saturating_sub(self.start_exit_counts.get(exit).copied().unwrap_or(0))))
+            .collect();
+        let exited = exits.values().sum();
+            
+        RunSummary {
+            simulated_time: state.time - self.start_time,
+            spawned,
+            exited,
+            removed: (self.start_active + spawned).saturating_sub(exited + state.cars.len() as u32),
+            active_at_end: state.cars.len() as u32,
+            exits,
+            travel_time: TravelTimeSummary::from_times(&travel_times),
+            mean_delay: state.trips.mean_delay(self.start_time).unwrap_or(0.0),
+            slow_time,
+            slow_share: share(slow_time, drive_time),
+            lane_changes: self.lane_changes,
+            collisions: self.collisions,
+            safety: SafetySummary {
+                bin_width: SAFETY_BIN_WIDTH,
+                ttc_exposure: state.safety.ttc_exposure.iter().zip(&self.start_safety.ttc_exposure).map(|(end, start)| end - start).collect(),
+                pet_counts: state.safety.pet_counts.iter().zip(&self.start_safety.pet_counts).map(|(end, start)| end.saturating_sub(*start)).collect(),
+                ..self.safety.clone()
+            },
+            energy: EnergySummary {
+                consumption: if self.energy.distance > 0.0 { (self.energy.consumed - self.energy.regenerated) / self.energy.distance * 100.0 } else { 0.0 },
+                ..self.energy.clone()
+            },
+            queues: state.queues.iter().fold(self.queues.clone(), |summary, queue| QueueSummary {
+                max_length: summary.max_length.max(queue.max_length),
+                max_duration: summary.max_duration.max(state.time - queue.start_time.max(self.start_time)),
+                ..summary
+            }),
+            travel_time_reliability: state.travel_times.reliability_by_segment(self.start_time),
+            behaviors,
+        }
+    }
+}
+
+/// Car that left the road through an exit in the tick that produced `state`, followed
+/// from its trip
+fn departed(state: &SimulationState, car: CarId) -> Option<TrackedCar> {
+    let exits = state.events.iter()
+        .filter(|event| matches!(event, SimulationEvent::CarExited { .. }))
+        .count();
+    state.trips.trips().iter().rev()
+        .take(exits)
+        .find(|trip| trip.car == car)
+        .map(|trip| TrackedCar {
+            behavior: trip.behavior.clone(),
+            spawn_time: trip.spawn_time,
+            energy: None,
+            low_charge: false,
+        })
+}
+
+impl TravelTimeSummary {
+    fn from_times(times: &[f32]) -> Self {
+        if times.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = times.to_vec();
+        sorted.sort_by(f32::total_cmp);
+        // Nearest-rank percentile
+        let rank = ((sorted.len() as f32 * 0.95).ceil() as usize).clamp(1, sorted.len());
+        Self {
+            count: sorted.len() as u32,
+            mean: sorted.iter().sum::<f32>() / sorted.len() as f32,
+            p95: sorted[rank - 1],
+        }
+    }
+}
+
+fn share(part: f32, total: f32) -> f32 {
+    if total > 0.0 { part / total } else { 0.0 }
+}
+
+impl RunSummary {
+    /// Print the summary for people to read, with queue lengths in `units`. The JSON file
+    /// keeps SI units.
+    pub fn print(&self, units: UnitSystem) {
+        println!("Cars spawned: {}", self.spawned);
+        println!("Cars exited: {}", self.exited);
+        for (exit_id, count) in &self.exits {
+            println!("  via {}: {}", exit_id, count);
+        }
+        println!("Cars removed without exiting: {}", self.removed);
+        println!("Active cars at end: {}", self.active_at_end);
+        println!("Travel time: {:.1}s mean, {:.1}s 95th percentile", self.travel_time.mean, self.travel_time.p95);
+        println!("Mean delay: {:.1}s", self.mean_delay);
+        println!("Below half the speed limit: {:.0} vehicle-seconds ({:.1}% of driving time)", self.slow_time, self.slow_share * 100.0);
+        println!("Lane changes: {}", self.lane_changes);
+        println!("Collisions: {}", self.collisions);
+        let seconds = |value: Option<f32>| value.map_or("-".to_string(), |value| format!("{:.2}s", value));
+        println!("Conflicts: {} TTC (min {}), {} PET (min {})",
+                 self.safety.ttc_conflicts, seconds(self.safety.min_ttc), self.safety.pet_conflicts, seconds(self.safety.min_pet));
+        if self.energy.electric_cars > 0 {
+            println!("Electric vehicles: {}, {:.1} kWh used, {:.1} kWh recovered, {:.1} kWh/100 km; {} low on charge ({} charged), {} ran flat",
+                     self.energy.electric_cars, self.energy.consumed, self.energy.regenerated, self.energy.consumption,
+                     self.energy.low_charge, self.energy.charged, self.energy.depleted);
+        }
+        if self.queues.formed > 0 {
+            println!("Queues: {} formed, longest {:.0} {}, longest lasting {:.1}s",
+                     self.queues.formed, units.distance(self.queues.max_length), units.distance_unit(), self.queues.max_duration);
+        }
+        for (segment, reliability) in &self.travel_time_reliability {
+            println!("Travel time {}: {} cars, {:.1}s mean, {:.1}s median, {:.1}s 95th percentile, buffer index {:.2}",
+                     segment, reliability.count, reliability.mean, reliability.median, reliability.p95, reliability.buffer_index);
+        }
+        println!("By behavior:");
+        for (behavior, summary) in &self.behaviors {
+            println!("  {}: {} spawned, {} exited, {} mean/p95 travel, {:.1}% slow, {} lane changes, {} cars in collisions",
+                     behavior, summary.spawned, summary.exited, summary.travel_time.mean_p95(),
+                     summary.slow_share * 100.0, summary.lane_changes, summary.cars_in_collisions);
+        }
+    }
+    
+    /// Write the summary as pretty-printed JSON
+    pub fn write_json(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut writer = create_export_writer(path.as_ref())?;
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+use crate::simulation::{SimulationState, TrajectoryBuffer, TrajectorySample};
+use crate::config::RouteGeometry;
+use super::{ExportFormat, create_export_writer, write_csv_row};
+use anyhow::Result;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Streams car trajectories sampled at a fixed interval to a CSV or JSON Lines file. The
+/// CSV uses the NGSIM column names, with metric units and simulation time.
+pub struct TrajectoryExporter {
+    writer: BufWriter<File>,
+    format: ExportFormat,
+    sampler: TrajectoryBuffer, // keeps only the latest sample of each car
+    header_written: bool,
+}
+
+impl TrajectoryExporter {
+    pub fn create(path: impl AsRef<Path>, format: ExportFormat, geometry: &RouteGeometry, interval: f32) -> Result<Self> {
+        Ok(Self {
+            writer: create_export_writer(path.as_ref())?,
+            format,
+            sampler: TrajectoryBuffer::new(geometry, interval, 0.0, usize::MAX),
+            header_written: false,
+        })
+    }
+    
+    /// Feed one simulation tick and write a sample of every car when an interval has passed
+    pub fn record(&mut self, state: &SimulationState) -> Result<()> {
+        if self.sampler.record(state) == 0 {
+            return Ok(());
+        }
+        let samples: Vec<TrajectorySample> = self.sampler.samples().iter().cloned().collect();
+        for sample in &samples {
+            self.write(sample)?;
+        }
+        Ok(())
+    }
+    
+    fn write(&mut self, sample: &TrajectorySample) -> Result<()> {
+        match self.format {
+            ExportFormat::Csv => {
+                if !self.header_written {
+                    // Local_Y is the position along the road in meters, Global_Time is milliseconds of simulation time
+                    let header: Vec<String> = [
+                        "Vehicle_ID", "Frame_ID", "Global_Time", "Local_Y", "Lane_ID", "Global_X", "Global_Y",
+                        "v_Vel", "v_Acc", "v_Length", "v_Width", "v_Class",
+                    ]
+                        .iter()
+                        .map(|s| s.to_string())
+                        .collect();
+                    write_csv_row(&mut self.writer, &header)?;
+                    self.header_written = true;
+                }
+                
+                let row = vec![
+                    sample.car.to_string(),
+                    sample.frame.to_string(),
+                    format!("{:.0}", sample.time * 1000.0),
+                    format!("{:.2}", sample.position),
+                    sample.lane.to_string(),
+                    format!("{:.2}", sample.x),
+                    format!("{:.2}", sample.y),
+                    format!("{:.3}", sample.speed),
+                    format!("{:.3}", sample.acceleration),
+                    format!("{:.2}", sample.length),
+                    format!("{:.2}", sample.width),
+                    sample.car_type.clone(),
+                ];
+                write_csv_row(&mut self.writer, &row)?;
+            }
+            ExportFormat::JsonLines => {
+                serde_json::to_writer(&mut self.writer, sample)?;
+                writeln!(self.writer)?;
+            }
+        }
+        Ok(())
+    }
+    
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use crate::simulation::{SimulationState, Traversal};
+use super::{ExportFormat, create_export_writer, write_csv_row};
+use anyhow::Result;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Streams the traversals of the route's travel time segments to a CSV or JSON Lines file
+pub struct TravelTimeExporter {
+    writer: BufWriter<File>,
+    format: ExportFormat,
+    written: usize, // traversals of the log already written
+    header_written: bool,
+}
+
+impl TravelTimeExporter {
+    pub fn create(path: impl AsRef<Path>, format: ExportFormat) -> Result<Self> {
+        Ok(Self {
+            writer: create_export_writer(path.as_ref())?,
+            format,
+            written: 0,
+            header_written: false,
+        })
+    }
+    
+    /// Write the traversals completed since the last call
+    pub fn record(&mut self, state: &SimulationState) -> Result<()> {
+        let traversals = state.travel_times.traversals();
+        // A log shorter than before belongs to a restarted simulation
+        if traversals.len() < self.written {
+            self.written = 0;
+        }
+        for traversal in &traversals[self.written..] {
+            self.write(traversal)?;
+        }
+        self.written = traversals.len();
+        Ok(())
+    }
+    
+    fn write(&mut self, traversal: &Traversal) -> Result<()> {
+        match self.format {
+            ExportFormat::Csv => {
+                if !self.header_written {
+                    let header: Vec<String> = ["segment", "car", "start_time", "end_time", "travel_time"]
+                        .iter()
+                        .map(|s| s.to_string())
+                        .collect();
+                    write_csv_row(&mut self.writer, &header)?;
+                    self.header_written = true;
+                }
+                
+                let row = vec![
+                    traversal.segment.clone(),
+                    traversal.car.to_string(),
+                    format!("{:.3}", traversal.start_time),
+                    format!("{:.3}", traversal.end_time),
+                    format!("{:.3}", traversal.travel_time),
+                ];
+                write_csv_row(&mut self.writer, &row)?;
+            }
+            ExportFormat::JsonLines => {
+                serde_json::to_writer(&mut self.writer, traversal)?;
+                writeln!(self.writer)?;
+            }
+        }
+        Ok(())
+    }
+    
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
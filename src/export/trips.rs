@@ -0,0 +1,82 @@
+use crate::simulation::{SimulationState, Trip};
+use super::{ExportFormat, create_export_writer, write_csv_row};
+use anyhow::Result;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Streams completed trips from the state's trip log to a CSV or JSON Lines file
+pub struct TripExporter {
+    writer: BufWriter<File>,
+    format: ExportFormat,
+    written: usize, // trips of the log already written
+    header_written: bool,
+}
+
+impl TripExporter {
+    pub fn create(path: impl AsRef<Path>, format: ExportFormat) -> Result<Self> {
+        Ok(Self {
+            writer: create_export_writer(path.as_ref())?,
+            format,
+            written: 0,
+            header_written: false,
+        })
+    }
+    
+    /// Write the trips completed since the last call
+    pub fn record(&mut self, state: &SimulationState) -> Result<()> {
+        let trips = state.trips.trips();
+        // A log shorter than before belongs to a restarted simulation
+        if trips.len() < self.written {
+            self.written = 0;
+        }
+        for trip in &trips[self.written..] {
+            self.write(trip)?;
+        }
+        self.written = trips.len();
+        Ok(())
+    }
+    
+    fn write(&mut self, trip: &Trip) -> Result<()> {
+        match self.format {
+            ExportFormat::Csv => {
+                if !self.header_written {
+                    let header: Vec<String> = [
+                        "car", "car_type", "behavior", "entry", "exit", "spawn_time", "exit_time",
+                        "distance", "travel_time", "free_flow_time", "delay",
+                    ]
+                        .iter()
+                        .map(|s| s.to_string())
+                        .collect();
+                    write_csv_row(&mut self.writer, &header)?;
+                    self.header_written = true;
+                }
+                
+                let row = vec![
+                    trip.car.to_string(),
+                    trip.car_type.clone(),
+                    trip.behavior.clone(),
+                    trip.entry.clone(),
+                    trip.exit.clone(),
+                    format!("{:.3}", trip.spawn_time),
+                    format!("{:.3}", trip.exit_time),
+                    format!("{:.1}", trip.distance),
+                    format!("{:.3}", trip.travel_time),
+                    format!("{:.3}", trip.free_flow_time),
+                    format!("{:.3}", trip.delay),
+                ];
+                write_csv_row(&mut self.writer, &row)?;
+            }
+            ExportFormat::JsonLines => {
+                serde_json::to_writer(&mut self.writer, trip)?;
+                writeln!(self.writer)?;
+            }
+        }
+        Ok(())
+    }
+    
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
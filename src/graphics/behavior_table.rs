@@ -0,0 +1,69 @@
+use crate::config::{CarsConfig, UnitSystem};
+use crate::simulation::{BehaviorStatistics, SimulationState};
+use crate::graphics::{to_color32, CarPalette};
+
+/// Window comparing the driver behaviors since the run started: how fast and how close
+/// their drivers drive, how often they change lanes, the delay of their trips and the
+/// collisions they were in
+pub struct BehaviorTable {
+    open: bool,
+    statistics: BehaviorStatistics,
+}
+
+impl BehaviorTable {
+    pub fn new(cars_config: &CarsConfig) -> Self {
+        Self {
+            open: false,
+            statistics: BehaviorStatistics::new(cars_config),
+        }
+    }
+    
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+    
+    /// Start over with the behaviors of a new configuration
+    pub fn set_config(&mut self, cars_config: &CarsConfig) {
+        self.statistics = BehaviorStatistics::new(cars_config);
+    }
+    
+    /// Take in one simulation tick, also while the window is closed
+    pub fn record(&mut self, state: &SimulationState) {
+        self.statistics.record(state);
+    }
+    
+    /// Behavior names take their cars' colors while cars are colored by behavior
+    pub fn show(&mut self, ctx: &egui::Context, state: &SimulationState, palette: &CarPalette, units: UnitSystem) {
+        let mut open = self.open;
+        egui::Window::new("Behaviors")
+            .open(&mut open)
+            .resizable(false)
+            .default_pos(egui::pos2(450.0, 240.0))
+            .show(ctx, |ui| {
+                ui.label("Since the run started");
+                egui::Grid::new("behavior_table").striped(true).num_columns(7).show(ui, |ui| {
+                    let speed = format!("Speed ({})", units.speed_unit());
+                    let lane_changes = format!("Lane changes/{}", units.road_length_unit());
+                    for heading in ["Behavior", "Cars", &speed, "Headway (s)", &lane_changes, "Delay (s)", "Collisions"] {
+                        ui.strong(heading);
+                    }
+                    ui.end_row();
+                    let optional = |value: Option<f32>| value.map_or("-".to_string(), |value| format!("{:.1}", value));
+                    for stats in self.statistics.behaviors(state) {
+                        let color = palette.entries().iter()
+                            .find(|entry| !palette.by_car_type() && entry.key == stats.behavior)
+                            .map_or(egui::Color32::WHITE, |entry| to_color32(entry.color));
+                        ui.colored_label(color, format!("● {}", stats.behavior));
+                        ui.label(stats.cars.to_string());
+                        ui.label(format!("{:.1}", units.speed(stats.mean_speed)));
+                        ui.label(optional(stats.mean_headway));
+                        ui.label(format!("{:.2}", units.per_road_length(stats.lane_changes_per_km)));
+                        ui.label(optional(stats.mean_delay));
+                        ui.label(stats.collisions.to_string());
+                        ui.end_row();
+                    }
+                });
+            });
+        self.open = open;
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::{anyhow, Result};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// An image read back from the GPU, RGBA with 8 bits per channel, rows top to bottom
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Screenshot {
+    /// Drop the padding at the end of rows copied out of a texture, `bytes_per_row` apart,
+    /// and reorder BGRA texels to RGBA. Alpha is made opaque; the scene has no transparency.
+    pub fn from_texture_rows(width: u32, height: u32, bytes_per_row: usize, data: &[u8], bgra: bool) -> Self {
+        let row_len = width as usize * 4;
+        let mut pixels = Vec::with_capacity(row_len * height as usize);
+        for row in data.chunks(bytes_per_row).take(height as usize) {
+            for texel in row[..row_len].chunks_exact(4) {
+                let (r, b) = if bgra { (texel[2], texel[0]) } else { (texel[0], texel[2]) };
+                pixels.extend_from_slice(&[r, texel[1], b, 255]);
+            }
+        }
+        Self { width, height, pixels }
+    }
+    
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+/// Parse a capture resolution such as `3840x2160`
+pub fn parse_capture_size(text: &str) -> std::result::Result<(u32, u32), String> {
+    let (width, height) = text.split_once(['x', 'X'])
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, got '{}'", text))?;
+    let parse = |value: &str| value.trim().parse::<u32>().ok().filter(|&v| v > 0);
+    match (parse(width), parse(height)) {
+        (Some(width), Some(height)) => Ok((width, height)),
+        _ => Err(format!("width and height must be positive integers, got '{}'", text)),
+    }
+}
+
+/// Copy `texture` into a mappable buffer at the end of `encoder`, submit it and wait for
+/// the pixels. Waiting blocks, so it is native only in practice.
+pub(crate) fn read_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    mut encoder: wgpu::CommandEncoder,
+) -> Result<Screenshot> {
+    let bgra = match texture.format() {
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+        format => return Err(anyhow!("Cannot capture {:?} textures", format)),
+    };
+    let (width, height) = (texture.width(), texture.height());
+    // Rows of a texture copy must start at multiples of 256 bytes
+    let bytes_per_row = (width * 4).div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Capture Buffer"),
+        size: bytes_per_row as u64 * height as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+    
+    let slice = buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver.recv()
+        .map_err(|_| anyhow!("Capture buffer was dropped before it was mapped"))?
+        .map_err(|e| anyhow!("Failed to map capture buffer: {}", e))?;
+        
+    let screenshot = {
+        let data = slice.get_mapped_range();
+        Screenshot::from_texture_rows(width, height, bytes_per_row as usize, &data, bgra)
+    };
+    buffer.unmap();
+    Ok(screenshot)
+}
@@ -0,0 +1,140 @@
+use crate::config::{LaneClosure, RouteConfig};
+
+/// Pixels the pointer has to move between press and release to mark a stretch of lane
+const DRAG_THRESHOLD: f32 = 4.0;
+
+/// Closes stretches of lane dragged along in the viewport, like a construction zone, and
+/// lists the closures in its window to remove them or save them into the route file.
+/// While it is open dragging on the road marks a closure instead of panning the view.
+pub struct ClosureTool {
+    open: bool,
+    closures: Vec<LaneClosure>, // the closures of the running route
+    drag: Option<((f32, f32), (f32, f32))>, // screen positions the drag started at and is at now
+    request: Option<Vec<LaneClosure>>, // closures to run with instead, once edited
+    save_request: bool,
+    outcome: Option<(String, bool)>, // what the last edit or save did, and whether it worked
+}
+
+impl ClosureTool {
+    pub fn new(route: &RouteConfig) -> Self {
+        Self {
+            open: false,
+            closures: route.route.closures.clone(),
+            drag: None,
+            request: None,
+            save_request: false,
+            outcome: None,
+        }
+    }
+    
+    /// List the closures of a changed configuration
+    pub fn set_config(&mut self, route: &RouteConfig) {
+        self.closures = route.route.closures.clone();
+    }
+    
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.drag = None;
+    }
+    
+    /// Whether dragging on the road marks closures
+    pub fn is_active(&self) -> bool {
+        self.open
+    }
+    
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+    
+    pub fn press(&mut self, position: (f32, f32)) {
+        self.drag = Some((position, position));
+    }
+    
+    pub fn drag_to(&mut self, position: (f32, f32)) {
+        if let Some((_, end)) = &mut self.drag {
+            *end = position;
+        }
+    }
+    
+    /// The screen positions a drag let go of at `position` ran between, `None` for a click
+    pub fn release(&mut self, position: (f32, f32)) -> Option<((f32, f32), (f32, f32))> {
+        let (start, _) = self.drag.take()?;
+        let moved = (position.0 - start.0).hypot(position.1 - start.1);
+        (moved > DRAG_THRESHOLD).then_some((start, position))
+    }
+    
+    /// The closures the running route has, until an edit is applied
+    pub fn closures(&self) -> &[LaneClosure] {
+        &self.closures
+    }
+    
+    /// Run with `closure` added to the current closures
+    pub fn add(&mut self, closure: LaneClosure) {
+        let mut closures = self.closures.clone();
+        closures.push(closure);
+        self.request = Some(closures);
+    }
+    
+    /// Closures to run with since they were edited, if they were
+    pub fn take_request(&mut self) -> Option<Vec<LaneClosure>> {
+        self.request.take()
+    }
+    
+    /// Whether Save was clicked since the last call
+    pub fn take_save_request(&mut self) -> bool {
+        std::mem::take(&mut self.save_request)
+    }
+    
+    /// Show what the last edit or save did
+    pub fn report(&mut self, message: impl Into<String>, succeeded: bool) {
+        self.outcome = Some((message.into(), succeeded));
+    }
+    
+    pub fn show(&mut self, ctx: &egui::Context) {
+        // Line from where the drag started to the pointer
+        if let Some(((x1, y1), (x2, y2))) = self.drag {
+            let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("closure_drag")));
+            painter.line_segment([egui::pos2(x1, y1), egui::pos2(x2, y2)], egui::Stroke::new(3.0, egui::Color32::from_rgb(255, 115, 0)));
+        }
+        
+        let mut open = self.open;
+        egui::Window::new("Lane closures")
+            .open(&mut open)
+            .resizable(false)
+            .default_pos(egui::pos2(450.0, 300.0))
+            .show(ctx, |ui| {
+                ui.label("Drag along a lane to close it");
+                let mut remove = None;
+                egui::Grid::new("lane_closures").num_columns(2).show(ui, |ui| {
+                    for (index, closure) in self.closures.iter().enumerate() {
+                        ui.label(format!("Lane {}: {:.1}° to {:.1}°", closure.lane, closure.start_angle, closure.end_angle));
+                        if ui.button("Remove").clicked() {
+                            remove = Some(index);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(index) = remove {
+                    let mut closures = self.closures.clone();
+                    closures.remove(index);
+                    self.request = Some(closures);
+                }
+                
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!self.closures.is_empty(), egui::Button::new("Clear")).clicked() {
+                        self.request = Some(Vec::new());
+                    }
+                    if ui.button("Save to route file").clicked() {
+                        self.save_request = true;
+                    }
+                });
+                if let Some((message, succeeded)) = &self.outcome {
+                    let color = if *succeeded { egui::Color32::GREEN } else { egui::Color32::YELLOW };
+                    ui.colored_label(color, message);
+                }
+            });
+        if open != self.open {
+            self.toggle();
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use crate::config::UnitSystem;
+use crate::simulation::SimulationState;
+
+/// Figures of one run compared in comparison mode, all from its state as it stands
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ComparisonMetrics {
+    pub time: f32,
+    pub active_cars: u32,
+    pub spawned: u32,
+    pub exited: u32,
+    pub throughput: f32, // exited cars per hour
+    pub mean_speed: f32, // m/s over the cars on the road
+    pub mean_delay: Option<f32>, // seconds, over the completed trips
+    pub collisions: u32,
+    pub queues: u32, // standing right now
+}
+
+impl ComparisonMetrics {
+    pub fn of(state: &SimulationState) -> Self {
+        let mean_speed = if state.cars.is_empty() {
+            0.0
+        } else {
+            state.cars.iter().map(|car| car.velocity.magnitude()).sum::<f32>() / state.cars.len() as f32
+        };
+        Self {
+            time: state.time,
+            active_cars: state.cars.len() as u32,
+            spawned: state.total_spawned,
+            exited: state.trips.len() as u32,
+            throughput: if state.time > 0.0 { state.trips.len() as f32 / state.time * 3600.0 } else { 0.0 },
+            mean_speed,
+            mean_delay: state.trips.mean_delay(0.0),
+            collisions: state.total_collisions,
+            queues: state.queues.len() as u32,
+        }
+    }
+    
+    /// Name, value and decimals shown of each figure, in table order
+    fn rows(&self, units: UnitSystem) -> [(String, Option<f32>, usize); 8] {
+        [
+            ("Active cars".to_string(), Some(self.active_cars as f32), 0),
+            ("Spawned".to_string(), Some(self.spawned as f32), 0),
+            ("Exited".to_string(), Some(self.exited as f32), 0),
+            ("Throughput (veh/h)".to_string(), Some(self.throughput), 0),
+            (format!("Mean speed ({})", units.speed_unit()), Some(units.speed(self.mean_speed)), 1),
+            ("Mean delay (s)".to_string(), self.mean_delay, 1),
+            ("Collisions".to_string(), Some(self.collisions as f32), 0),
+            ("Queues".to_string(), Some(self.queues as f32), 0),
+        ]
+    }
+}
+
+/// Labels over the two halves of the screen in comparison mode, and a window with the
+/// figures of both runs next to each other and the right run's difference from the left
+pub struct ComparisonPanel {
+    open: bool,
+    labels: [String; 2],
+}
+
+impl ComparisonPanel {
+    pub fn new(left: impl Into<String>, right: impl Into<String>) -> Self {
+        Self {
+            open: true,
+            labels: [left.into(), right.into()],
+        }
+    }
+    
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+    
+    pub fn show(&mut self, ctx: &egui::Context, left: &SimulationState, right: &SimulationState, units: UnitSystem) {
+        let screen = ctx.screen_rect();
+        let divider = screen.center().x;
+        ctx.layer_painter(egui::LayerId::background()).line_segment(
+            [egui::pos2(divider, screen.top()), egui::pos2(divider, screen.bottom())],
+            egui::Stroke::new(2.0, egui::Color32::from_gray(200)),
+        );
+        for (index, (label, left_edge)) in self.labels.iter().zip([screen.left(), divider]).enumerate() {
+            egui::Area::new(egui::Id::new(("comparison_label", index)))
+                .fixed_pos(egui::pos2(left_edge + 10.0, screen.bottom() - 30.0))
+                .interactable(false)
+                .show(ctx, |ui| {
+                    ui.label(egui::RichText::new(label).strong().color(egui::Color32::WHITE).background_color(egui::Color32::from_black_alpha(160)));
+                });
+        }
+        
+        let mut open = self.open;
+        let (left, right) = (ComparisonMetrics::of(left), ComparisonMetrics::of(right));
+        egui::Window::new("Comparison")
+            .open(&mut open)
+            .resizable(false)
+            .default_pos(egui::pos2(divider - 160.0, 60.0))
+            .show(ctx, |ui| {
+                ui.label(format!("t = {:.1}s", left.time));
+                egui::Grid::new("comparison_table").striped(true).num_columns(4).show(ui, |ui| {
+                    ui.strong("");
+                    ui.strong(&self.labels[0]);
+                    ui.strong(&self.labels[1]);
+                    ui.strong("Difference");
+                    ui.end_row();
+                    for ((name, a, precision), (_, b, _)) in left.rows(units).into_iter().zip(right.rows(units)) {
+                        let value = |value: Option<f32>| value.map_or("-".to_string(), |value| format!("{:.*}", precision, value));
+                        ui.label(name);
+                        ui.label(value(a));
+                        ui.label(value(b));
+                        ui.label(match (a, b) {
+                            (Some(a), Some(b)) => format!("{:+.*}", precision, b - a),
+                            _ => "-".to_string(),
+                        });
+                        ui.end_row();
+                    }
+                });
+            });
+        self.open = open;
+    }
+}
@@ -0,0 +1,151 @@
+use crate::config::UnitSystem;
+use crate::export::{create_export_writer, write_csv_row};
+use crate::simulation::DetectorReading;
+use anyhow::Result;
+use egui_plot::{Legend, Plot, Points};
+use std::io::Write;
+use std::path::Path;
+
+const PLOT_SIZE: f32 = 320.0;
+
+/// One detector interval placed on the fundamental diagram
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagramPoint {
+    pub detector: String,
+    pub time: f32, // end of the interval, seconds
+    pub density: f32, // vehicles per km
+    pub flow: f32, // vehicles per hour
+    pub speed: f32, // km/h, harmonic mean
+}
+
+/// Flow against density of every detector interval since the run started, the fundamental
+/// diagram of traffic flow. Unlike the time-series plots nothing scrolls out of it.
+pub struct FundamentalDiagram {
+    open: bool,
+    points: Vec<DiagramPoint>,
+    last_time: f32,
+    export_requested: bool,
+    status: Option<Result<String, String>>, // outcome of the last export
+}
+
+impl Default for FundamentalDiagram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FundamentalDiagram {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            points: Vec::new(),
+            last_time: 0.0,
+            export_requested: false,
+            status: None,
+        }
+    }
+    
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+    
+    /// Add the readings of intervals that ended at `time`. Intervals nobody passed have no
+    /// speed to derive a density from and are left out.
+    pub fn record(&mut self, time: f32, readings: &[DetectorReading]) {
+        // Time going backwards means the simulation was reset or a checkpoint was loaded
+        if time < self.last_time {
+            self.points.clear();
+        }
+        self.last_time = time;
+        
+        for reading in readings {
+            let (Some(density), Some(speed)) = (reading.density(), reading.harmonic_mean_speed) else {
+                continue;
+            };
+            self.points.push(DiagramPoint {
+                detector: reading.detector.clone(),
+                time: reading.end,
+                density,
+                flow: reading.flow,
+                speed: speed * 3.6,
+            });
+        }
+    }
+    
+    /// Points so far, oldest first
+    pub fn points(&self) -> &[DiagramPoint] {
+        &self.points
+    }
+    
+    /// Whether Export was clicked since the last call. Report the outcome with `finish_export`.
+    pub fn take_export_request(&mut self) -> bool {
+        std::mem::take(&mut self.export_requested)
+    }
+    
+    pub fn finish_export(&mut self, result: Result<String>) {
+        self.status = Some(result.map_err(|e| format!("Export failed: {}", e)));
+    }
+    
+    /// Write every point as CSV
+    pub fn write_csv(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut writer = create_export_writer(path.as_ref())?;
+        let header: Vec<String> = ["detector", "time", "density", "flow", "speed"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        write_csv_row(&mut writer, &header)?;
+        for point in &self.points {
+            let row = vec![
+                point.detector.clone(),
+                format!("{:.3}", point.time),
+                format!("{:.2}", point.density),
+                format!("{:.1}", point.flow),
+                format!("{:.2}", point.speed),
+            ];
+            write_csv_row(&mut writer, &row)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+    
+    /// Window with a scatter plot of flow against density, one color per detector
+    pub fn show<'a>(&mut self, ctx: &egui::Context, detectors: impl Iterator<Item = &'a str>, units: UnitSystem) {
+        let mut open = self.open;
+        egui::Window::new("Fundamental diagram")
+            .open(&mut open)
+            .resizable(false)
+            .default_pos(egui::pos2(450.0, 420.0))
+            .show(ctx, |ui| {
+                ui.set_width(PLOT_SIZE);
+                ui.label(format!("Flow (veh/h) against density (veh/{})", units.road_length_unit()));
+                Plot::new("fundamental_diagram")
+                    .width(PLOT_SIZE)
+                    .height(PLOT_SIZE * 0.75)
+                    .include_x(0.0)
+                    .include_y(0.0)
+                    .legend(Legend::default())
+                    .show(ui, |plot_ui| {
+                        for detector in detectors {
+                            let points: Vec<[f64; 2]> = self.points.iter()
+                                .filter(|point| point.detector == detector)
+                                .map(|point| [units.per_road_length(point.density) as f64, point.flow as f64])
+                                .collect();
+                            plot_ui.points(Points::new(points).radius(2.0).name(detector));
+                        }
+                    });
+                    
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} intervals", self.points.len()));
+                    if ui.add_enabled(!self.points.is_empty(), egui::Button::new("Export CSV")).clicked() {
+                        self.export_requested = true;
+                    }
+                });
+                match &self.status {
+                    Some(Ok(message)) => ui.colored_label(egui::Color32::GREEN, message),
+                    Some(Err(message)) => ui.colored_label(egui::Color32::RED, message),
+                    None => ui.label(""),
+                };
+            });
+        self.open = open;
+    }
+}
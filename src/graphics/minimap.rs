@@ -0,0 +1,175 @@
+use crate::config::RouteConfig;
+use crate::simulation::SimulationState;
+use super::renderer::ROAD_Z;
+use super::trajectories::speed_band_color;
+use super::{RoadMesh, Viewport};
+use nalgebra::Vector3;
+
+const MINIMAP_SIZE: f32 = 200.0; // points along the longer side
+const MINIMAP_DETAIL: f32 = 0.25; // the road is only a few pixels wide, coarse curves do
+const MARGIN: f32 = 0.05; // share of the route's extent left clear around it
+const CAR_RADIUS: f32 = 1.5;
+
+/// Maps the world rectangle a route covers into a minimap of a given size, keeping the
+/// aspect ratio. Minimap coordinates are points from the top-left, y pointing down.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MinimapTransform {
+    world_center: [f32; 2],
+    map_center: [f32; 2],
+    scale: f32, // points per meter
+}
+
+impl MinimapTransform {
+    /// Fit the world rectangle from `min` to `max` inside `size`, centered
+    pub fn fit(min: [f32; 2], max: [f32; 2], size: [f32; 2]) -> Self {
+        let extent = [(max[0] - min[0]).max(1.0), (max[1] - min[1]).max(1.0)];
+        Self {
+            world_center: [(min[0] + max[0]) / 2.0, (min[1] + max[1]) / 2.0],
+            map_center: [size[0] / 2.0, size[1] / 2.0],
+            scale: (size[0] / extent[0]).min(size[1] / extent[1]),
+        }
+    }
+    
+    pub fn to_map(&self, world: [f32; 2]) -> [f32; 2] {
+        [
+            self.map_center[0] + (world[0] - self.world_center[0]) * self.scale,
+            self.map_center[1] - (world[1] - self.world_center[1]) * self.scale,
+        ]
+    }
+    
+    pub fn to_world(&self, map: [f32; 2]) -> [f32; 2] {
+        [
+            self.world_center[0] + (map[0] - self.map_center[0]) / self.scale,
+            self.world_center[1] - (map[1] - self.map_center[1]) / self.scale,
+        ]
+    }
+}
+
+/// Overview of the whole route in a small window: the road surface, every car as a dot
+/// colored by speed and the area the camera shows. Clicking or dragging in it moves the
+/// camera there.
+pub struct Minimap {
+    open: bool,
+    road: Vec<([f32; 2], egui::Color32)>, // road surface triangles in world coordinates
+    min: [f32; 2],
+    max: [f32; 2],
+    speed_limit: f32, // m/s, for coloring
+    jump: Option<[f32; 2]>, // world position picked this frame
+}
+
+impl Minimap {
+    pub fn new(route: &RouteConfig) -> Self {
+        let mut minimap = Self {
+            open: true,
+            road: Vec::new(),
+            min: [0.0; 2],
+            max: [0.0; 2],
+            speed_limit: route.route.traffic_rules.speed_limit,
+            jump: None,
+        };
+        minimap.set_route(route);
+        minimap
+    }
+    
+    /// Show a changed route from now on
+    pub fn set_route(&mut self, route: &RouteConfig) {
+        let mesh = RoadMesh::from_route_with_detail(route, MINIMAP_DETAIL);
+        // Markings are thinner than a pixel here, only the surface is drawn. Its dark
+        // grays are brightened to stand out from the background.
+        self.road = mesh.vertices().iter()
+            .filter(|vertex| vertex.position[2] == ROAD_Z)
+            .map(|vertex| {
+                let [r, g, b] = vertex.color.map(|channel| (channel * 2.0 * 255.0).min(255.0) as u8);
+                ([vertex.position[0], vertex.position[1]], egui::Color32::from_rgb(r, g, b))
+            })
+            .collect();
+            
+        let (mut min, mut max) = ([f32::MAX; 2], [f32::MIN; 2]);
+        for (position, _) in &self.road {
+            for axis in 0..2 {
+                min[axis] = min[axis].min(position[axis]);
+                max[axis] = max[axis].max(position[axis]);
+            }
+        }
+        if self.road.is_empty() {
+            (min, max) = ([-100.0; 2], [100.0; 2]);
+        }
+        let margin = (max[0] - min[0]).max(max[1] - min[1]) * MARGIN;
+        self.min = [min[0] - margin, min[1] - margin];
+        self.max = [max[0] + margin, max[1] + margin];
+        self.speed_limit = route.route.traffic_rules.speed_limit;
+    }
+    
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+    
+    /// World rectangle the minimap covers, the route with a margin around it
+    pub fn bounds(&self) -> ([f32; 2], [f32; 2]) {
+        (self.min, self.max)
+    }
+    
+    /// Minimap size in points, `MINIMAP_SIZE` along the route's longer side
+    pub fn size(&self) -> [f32; 2] {
+        let (width, height) = (self.max[0] - self.min[0], self.max[1] - self.min[1]);
+        if width >= height {
+            [MINIMAP_SIZE, MINIMAP_SIZE * height / width]
+        } else {
+            [MINIMAP_SIZE * width / height, MINIMAP_SIZE]
+        }
+    }
+    
+    pub fn transform(&self) -> MinimapTransform {
+        MinimapTransform::fit(self.min, self.max, self.size())
+    }
+    
+    /// The world position the camera should move to, once per click
+    pub fn take_jump(&mut self) -> Option<Vector3<f32>> {
+        self.jump.take().map(|[x, y]| Vector3::new(x, y, 0.0))
+    }
+    
+    pub fn show(&mut self, ctx: &egui::Context, state: &SimulationState, viewport: &Viewport) {
+        let mut open = self.open;
+        egui::Window::new("Minimap")
+            .open(&mut open)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -15.0))
+            .show(ctx, |ui| self.draw(ui, state, viewport));
+        self.open = open;
+    }
+    
+    fn draw(&mut self, ui: &mut egui::Ui, state: &SimulationState, viewport: &Viewport) {
+        let transform = self.transform();
+        let [width, height] = self.size();
+        let (response, painter) = ui.allocate_painter(egui::vec2(width, height), egui::Sense::click_and_drag());
+        let origin = response.rect.min;
+        let to_screen = |x: f32, y: f32| {
+            let [x, y] = transform.to_map([x, y]);
+            origin + egui::vec2(x, y)
+        };
+        
+        painter.rect_filled(response.rect, 2.0, egui::Color32::from_black_alpha(200));
+        let mut road = egui::Mesh::default();
+        for &([x, y], color) in &self.road {
+            road.colored_vertex(to_screen(x, y), color);
+        }
+        road.indices = (0..road.vertices.len() as u32).collect();
+        painter.add(egui::Shape::mesh(road));
+        
+        for car in &state.cars {
+            let color = speed_band_color(car.velocity.magnitude(), self.speed_limit);
+            painter.circle_filled(to_screen(car.position.x, car.position.y), CAR_RADIUS, color);
+        }
+        
+        // The camera's view as a quad, which turns with a heading-up follow camera
+        let corners = viewport.visible_corners().map(|corner| to_screen(corner.x, corner.y));
+        painter.add(egui::Shape::closed_line(corners.to_vec(), egui::Stroke::new(1.0, egui::Color32::WHITE)));
+        
+        if response.clicked() || response.dragged() {
+            if let Some(pointer) = response.interact_pointer_pos() {
+                let local = pointer - origin;
+                self.jump = Some(transform.to_world([local.x, local.y]));
+            }
+        }
+    }
+}
@@ -4,15 +4,52 @@ use winit::{
     event_loop::EventLoop,
     window::Window,
 };
-use crate::simulation::{SimulationState, PerformanceMetrics};
+use crate::config::SimulationConfig;
+use crate::simulation::SimulationState;
 
 pub mod renderer;
 pub mod viewport;
 pub mod ui;
+pub mod road;
+pub mod plots;
+pub mod diagram;
+pub mod trajectories;
+pub mod behavior_table;
+pub mod comparison;
+pub mod settings;
+pub mod quality;
+pub mod capture;
+pub mod minimap;
+pub mod palette;
+pub mod preferences;
+pub mod spawn_tool;
+pub mod closure_tool;
+pub mod timeline;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod video;
 
 pub use renderer::*;
 pub use viewport::*;
 pub use ui::*;
+pub use road::*;
+pub use plots::*;
+pub use diagram::*;
+pub use trajectories::*;
+pub use behavior_table::*;
+pub use comparison::*;
+pub use settings::*;
+pub use quality::*;
+pub use capture::*;
+pub use minimap::*;
+pub use palette::*;
+pub use preferences::*;
+pub use spawn_tool::*;
+pub use closure_tool::*;
+pub use timeline::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use video::*;
+#[cfg(not(target_arch = "wasm32"))]
+use capture::read_texture;
 
 pub struct GraphicsSystem {
     pub window: std::sync::Arc<Window>,
@@ -22,20 +59,33 @@ pub struct GraphicsSystem {
     pub egui_ctx: egui::Context,
     pub egui_winit: egui_winit::State,
     pub egui_renderer: egui_wgpu::Renderer,
+    #[cfg(not(target_arch = "wasm32"))]
+    screenshot: Option<ScreenshotRequest>,
+}
+
+/// A screenshot to take with the next frame
+#[cfg(not(target_arch = "wasm32"))]
+struct ScreenshotRequest {
+    path: std::path::PathBuf,
+    size: Option<(u32, u32)>, // off-screen resolution, `None` for the window as shown
 }
 
 impl GraphicsSystem {
-    pub async fn new(event_loop: &EventLoop<()>, geometry_type: String) -> Result<Self> {
-        let window = std::sync::Arc::new(
-            winit::window::WindowBuilder::new()
-                .with_title("Traffic Simulator")
-                .with_inner_size(winit::dpi::LogicalSize::new(1200, 800))
-                .build(event_loop)?
-        );
+    pub async fn new(event_loop: &EventLoop<()>, config: &SimulationConfig, plot_window: f32) -> Result<Self> {
+        let builder = winit::window::WindowBuilder::new()
+            .with_title("Traffic Simulator")
+            .with_inner_size(winit::dpi::LogicalSize::new(1200, 800));
+        // In the browser the window is a canvas added to the page
+        #[cfg(target_arch = "wasm32")]
+        let builder = {
+            use winit::platform::web::WindowBuilderExtWebSys;
+            builder.with_append(true)
+        };
+        let window = std::sync::Arc::new(builder.build(event_loop)?);
         
-        let renderer = TrafficRenderer::new(window.clone(), geometry_type).await?;
+        let renderer = TrafficRenderer::new(window.clone(), &config.route, CarPalette::new(&config.cars), config.cars.simulation.total_cars).await?;
         let viewport = Viewport::new(1200.0, 800.0);
-        let ui = UiRenderer::new()?;
+        let ui = UiRenderer::new(config, plot_window)?;
         
         // Initialize egui
         let egui_ctx = egui::Context::default();
@@ -61,9 +111,39 @@ impl GraphicsSystem {
             egui_ctx,
             egui_winit,
             egui_renderer,
+            #[cfg(not(target_arch = "wasm32"))]
+            screenshot: None,
         })
     }
     
+    /// Save the next frame to `path` as a PNG. With a size only the scene is drawn, off-screen
+    /// at that resolution; without one the window is captured as shown, UI included.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn request_screenshot(&mut self, path: impl Into<std::path::PathBuf>, size: Option<(u32, u32)>) {
+        self.screenshot = Some(ScreenshotRequest { path: path.into(), size });
+    }
+    
+    /// Split the screen between two runs, `left` and `right` naming them. Both halves look
+    /// through the same camera, which is set up for half the window's width.
+    pub fn set_comparison(&mut self, left: impl Into<String>, right: impl Into<String>) {
+        self.ui.comparison = Some(ComparisonPanel::new(left, right));
+        let size = self.renderer.size;
+        self.viewport.resize(size.width as f32 / 2.0, size.height as f32);
+    }
+    
+    /// Width the camera covers, half the window while the screen is split
+    fn viewport_width(&self, width: u32) -> f32 {
+        if self.ui.comparison.is_some() { width as f32 / 2.0 } else { width as f32 }
+    }
+    
+    /// Draw and edit a configuration the simulation switched to
+    pub fn set_config(&mut self, config: &SimulationConfig) {
+        self.renderer.set_route(&config.route);
+        self.renderer.set_palette(CarPalette::new(&config.cars));
+        self.renderer.reserve_cars(config.cars.simulation.total_cars as usize);
+        self.ui.set_config(config);
+    }
+    
     pub fn handle_input(&mut self, event: &WindowEvent) -> bool {
         // Handle egui input first
         let response = self.egui_winit.on_window_event(&self.window, event);
@@ -81,7 +161,10 @@ impl GraphicsSystem {
                 true
             }
             WindowEvent::CursorMoved { position, .. } => {
-                self.viewport.handle_mouse_move(position.x as f32, position.y as f32);
+                // Both halves of a split screen show the same place
+                let half = self.viewport_width(self.renderer.size.width);
+                let x = if self.ui.comparison.is_some() { position.x as f32 % half } else { position.x as f32 };
+                self.viewport.handle_mouse_move(x, position.y as f32);
                 true
             }
             WindowEvent::KeyboardInput { event, .. } => {
@@ -90,33 +173,40 @@ impl GraphicsSystem {
             }
             WindowEvent::Resized(physical_size) => {
                 self.renderer.resize(*physical_size);
-                self.viewport.resize(physical_size.width as f32, physical_size.height as f32);
+                self.viewport.resize(self.viewport_width(physical_size.width), physical_size.height as f32);
                 true
             }
             WindowEvent::ScaleFactorChanged { .. } => {
                 let size = self.renderer.size;
                 self.renderer.resize(size);
-                self.viewport.resize(size.width as f32, size.height as f32);
+                self.viewport.resize(self.viewport_width(size.width), size.height as f32);
                 true
             }
             _ => false,
         }
     }
     
-    pub fn render(
-        &mut self, 
-        state: &SimulationState, 
-        performance: &PerformanceMetrics,
-        paused: bool,
-        simulation_speed: f32,
-        frame_count: u64,
-        route_file: &str,
-        cars_file: &str,
-        seed: Option<u64>,
-        font_size: f32
-    ) -> Result<()> {
-        // Update viewport
+    /// Draw `state`, and `comparison` next to it while the screen is split
+    pub fn render(&mut self, state: &SimulationState, comparison: Option<&SimulationState>, run: &RunStatus) -> Result<()> {
+        // Update viewport, following the selected car if there is one
+        self.viewport.track(state);
         self.viewport.update();
+        self.renderer.set_perspective(self.viewport.perspective());
+        
+        self.renderer.set_noise_overlay(self.ui.show_noise.then_some(&self.ui.noise));
+        
+        // Off-screen captures go first, they overwrite the renderer's buffers
+        #[cfg(not(target_arch = "wasm32"))]
+        let window_capture = match self.screenshot.take() {
+            Some(request) if request.size.is_some() || !self.renderer.surface_copyable() => {
+                let (width, height) = request.size.unwrap_or((self.renderer.size.width, self.renderer.size.height));
+                let view_matrix = self.viewport.view_matrix_for(width as f32, height as f32);
+                let screenshot = self.renderer.capture(state, &view_matrix, width, height);
+                save_screenshot(screenshot, &request.path);
+                None
+            }
+            request => request,
+        };
         
         // Get current texture for rendering
         let output = self.renderer.surface().get_current_texture()?;
@@ -130,14 +220,25 @@ impl GraphicsSystem {
         
         // Render the 3D scene first
         let view_matrix = self.viewport.get_view_matrix();
-        self.renderer.render_to_texture(state, &view_matrix, &view, &mut encoder)?;
+        match comparison.filter(|_| self.ui.comparison.is_some()) {
+            Some(right) => self.renderer.render_split_to_texture(state, right, &view_matrix, &view, &mut encoder)?,
+            None => self.renderer.render_to_texture(state, &view_matrix, &view, &mut encoder)?,
+        }
         
         // Prepare egui
         let raw_input = self.egui_winit.take_egui_input(&self.window);
+        self.ui.car_coloring = self.renderer.car_coloring();
+        self.ui.drawn_cars = self.renderer.drawn_cars();
         let full_output = self.egui_ctx.run(raw_input, |ctx| {
             // Render UI overlay with egui
-            self.ui.render_egui(ctx, performance, state, &self.viewport, paused, simulation_speed, frame_count, route_file, cars_file, seed, font_size);
+            self.ui.render_egui(ctx, state, &self.viewport, run);
+            if let (Some(panel), Some(right), true) = (&mut self.ui.comparison, comparison, self.ui.show_overlays) {
+                panel.show(ctx, state, right, self.ui.preferences.units());
+            }
         });
+        if let Some(position) = self.ui.minimap.take_jump() {
+            self.viewport.look_at(position);
+        }
         
         self.egui_winit.handle_platform_output(&self.window, full_output.platform_output);
         
@@ -180,7 +281,15 @@ impl GraphicsSystem {
             });
         }
         
-        // Submit commands and present
+        // Submit commands and present, reading the frame back first for a screenshot
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(request) = window_capture {
+            let screenshot = read_texture(self.renderer.device(), self.renderer.queue(), &output.texture, encoder);
+            save_screenshot(screenshot, &request.path);
+        } else {
+            self.renderer.queue().submit(std::iter::once(encoder.finish()));
+        }
+        #[cfg(target_arch = "wasm32")]
         self.renderer.queue().submit(std::iter::once(encoder.finish()));
         output.present();
         
@@ -191,4 +300,15 @@ impl GraphicsSystem {
         
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_screenshot(screenshot: Result<Screenshot>, path: &std::path::Path) {
+    match screenshot.and_then(|screenshot| {
+        screenshot.save_png(path)?;
+        Ok(screenshot)
+    }) {
+        Ok(screenshot) => log::info!("Saved {}x{} screenshot to {}", screenshot.width, screenshot.height, path.display()),
+        Err(e) => log::error!("Failed to save screenshot to {}: {}", path.display(), e),
+    }
+}
@@ -0,0 +1,185 @@
+use crate::config::CarsConfig;
+use crate::simulation::{Car, NameTable, SimulationState};
+use std::sync::Arc;
+
+/// Behavior colors and the cycle other behaviors and car types draw from, per scheme
+struct Scheme {
+    behaviors: [(&'static str, [f32; 3]); 5],
+    cycle: &'static [[f32; 3]],
+}
+
+const DEFAULT_SCHEME: Scheme = Scheme {
+    behaviors: [
+        ("aggressive", [1.0, 0.0, 0.0]), // Pure red for aggressive drivers
+        ("normal", [0.0, 0.5, 1.0]),     // Pure blue for normal drivers
+        ("cautious", [0.0, 1.0, 0.0]),   // Pure green for cautious drivers
+        ("erratic", [1.0, 0.7, 0.0]),    // Pure orange for erratic drivers
+        ("strategic", [1.0, 0.0, 1.0]),  // Pure magenta for strategic drivers
+    ],
+    cycle: &[
+        [1.0, 0.0, 0.0],
+        [0.0, 0.5, 1.0],
+        [0.0, 1.0, 0.0],
+        [1.0, 0.7, 0.0],
+        [1.0, 0.0, 1.0],
+        [0.0, 0.9, 0.9],
+        [1.0, 1.0, 0.3],
+        [0.8, 0.8, 0.8],
+    ],
+};
+
+/// Okabe-Ito colors, told apart with any of the common color vision deficiencies
+const COLORBLIND_SCHEME: Scheme = Scheme {
+    behaviors: [
+        ("aggressive", [0.835, 0.369, 0.0]), // vermillion
+        ("normal", [0.0, 0.447, 0.698]),     // blue
+        ("cautious", [0.0, 0.620, 0.451]),   // bluish green
+        ("erratic", [0.902, 0.624, 0.0]),    // orange
+        ("strategic", [0.800, 0.475, 0.655]), // reddish purple
+    ],
+    cycle: &[
+        [0.902, 0.624, 0.0],
+        [0.337, 0.706, 0.914],
+        [0.0, 0.620, 0.451],
+        [0.941, 0.894, 0.259],
+        [0.0, 0.447, 0.698],
+        [0.835, 0.369, 0.0],
+        [0.800, 0.475, 0.655],
+        [0.9, 0.9, 0.9],
+    ],
+};
+
+/// One color of the palette and what it stands for
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteEntry {
+    pub key: String,   // behavior name or car type id
+    pub label: String, // for the legend
+    pub color: [f32; 3],
+}
+
+/// Body colors of cars, resolved once from the `[colors]` section of cars.toml. The
+/// renderer and the UI legend both read from it, so they always agree.
+#[derive(Debug, Clone)]
+pub struct CarPalette {
+    by_car_type: bool,
+    entries: Vec<PaletteEntry>, // in legend order
+    names: Arc<NameTable>, // the names `colors` is indexed by
+    colors: Vec<[f32; 3]>, // by behavior or car type id, so cars are colored without comparing names
+}
+
+/// Light gray for cars whose behavior or type is not in the configuration
+const UNKNOWN_COLOR: [f32; 3] = [0.8, 0.8, 0.8];
+
+impl CarPalette {
+    pub fn new(cars: &CarsConfig) -> Self {
+        let colors = &cars.colors;
+        let scheme = if colors.scheme == "colorblind" { &COLORBLIND_SCHEME } else { &DEFAULT_SCHEME };
+        let cycle = |index: usize| scheme.cycle[index % scheme.cycle.len()];
+        
+        let entries: Vec<PaletteEntry> = if colors.by == "car_type" {
+            cars.car_types.iter().enumerate()
+                .map(|(index, car_type)| PaletteEntry {
+                    key: car_type.id.clone(),
+                    label: title_case(&car_type.id),
+                    color: colors.car_types.get(&car_type.id).copied().unwrap_or_else(|| cycle(index)),
+                })
+                .collect()
+        } else {
+            // The usual behaviors keep their places, any others follow by name
+            let mut names: Vec<&String> = cars.behavior.keys().collect();
+            let rank = |name: &str| scheme.behaviors.iter().position(|(known, _)| *known == name).unwrap_or(usize::MAX);
+            names.sort_by(|a, b| rank(a).cmp(&rank(b)).then(a.cmp(b)));
+            names.into_iter().enumerate()
+                .map(|(index, name)| {
+                    let preset = scheme.behaviors.iter().find(|(known, _)| known == name).map(|(_, color)| *color);
+                    PaletteEntry {
+                        key: name.clone(),
+                        label: title_case(name),
+                        color: colors.behaviors.get(name).copied().or(preset).unwrap_or_else(|| cycle(index)),
+                    }
+                })
+                .collect()
+        };
+        
+        let mut palette = Self {
+            by_car_type: colors.by == "car_type",
+            entries,
+            names: Arc::default(),
+            colors: Vec::new(),
+        };
+        palette.set_names(&Arc::new(NameTable::new(cars)));
+        palette
+    }
+    
+    /// Color cars by the ids of `names`, the table of the state being drawn
+    pub fn set_names(&mut self, names: &Arc<NameTable>) {
+        if Arc::ptr_eq(&self.names, names) {
+            return;
+        }
+        let len = if self.by_car_type { names.car_types.len() } else { names.behaviors.len() };
+        self.colors = vec![UNKNOWN_COLOR; len];
+        for entry in &self.entries {
+            if let Some(id) = self.entry_id(names, entry) {
+                self.colors[id] = entry.color;
+            }
+        }
+        self.names = names.clone();
+    }
+    
+    /// Id in `names` of the behavior or car type `entry` stands for
+    fn entry_id(&self, names: &NameTable, entry: &PaletteEntry) -> Option<usize> {
+        if self.by_car_type {
+            names.car_types.id(&entry.key).map(|id| id.index())
+        } else {
+            names.behaviors.id(&entry.key).map(|id| id.index())
+        }
+    }
+    
+    /// Whether cars are colored by their type rather than their driver's behavior
+    pub fn by_car_type(&self) -> bool {
+        self.by_car_type
+    }
+    
+    /// Color of `car`, whose ids are of the names last set
+    pub fn color(&self, car: &Car) -> [f32; 3] {
+        self.colors.get(self.id(car)).copied().unwrap_or(UNKNOWN_COLOR)
+    }
+    
+    fn id(&self, car: &Car) -> usize {
+        if self.by_car_type { car.car_type.index() } else { car.behavior_type.index() }
+    }
+    
+    /// Cars of `state` in each entry, in legend order
+    pub fn counts(&self, state: &SimulationState) -> Vec<usize> {
+        let len = if self.by_car_type { state.names.car_types.len() } else { state.names.behaviors.len() };
+        let mut by_id = vec![0; len];
+        for car in &state.cars {
+            if let Some(count) = by_id.get_mut(self.id(car)) {
+                *count += 1;
+            }
+        }
+        self.entries.iter()
+            .map(|entry| self.entry_id(&state.names, entry).map_or(0, |id| by_id[id]))
+            .collect()
+    }
+    
+    pub fn entries(&self) -> &[PaletteEntry] {
+        &self.entries
+    }
+}
+
+/// "sports_car" to "Sports car"
+fn title_case(name: &str) -> String {
+    let name = name.replace('_', " ");
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => name,
+    }
+}
+
+/// An RGB color with channels 0-1 for egui
+pub(crate) fn to_color32(color: [f32; 3]) -> egui::Color32 {
+    let [r, g, b] = color.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+    egui::Color32::from_rgb(r, g, b)
+}
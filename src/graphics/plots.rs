@@ -0,0 +1,175 @@
+use crate::config::{RouteConfig, UnitSystem};
+use crate::simulation::{DetectorReading, DetectorSet, SimulationState};
+use egui_plot::{Legend, Line, Plot, PlotPoints, PlotUi};
+use std::collections::VecDeque;
+
+const SAMPLE_INTERVAL: f32 = 1.0; // seconds of simulation time between plotted samples
+const PLOT_WIDTH: f32 = 392.0;
+const PLOT_HEIGHT: f32 = 90.0;
+
+/// One point of the time-series plots
+#[derive(Debug, Clone, Copy)]
+pub struct HistorySample {
+    pub time: f32,
+    pub mean_speed: f32, // m/s over all active cars
+    pub active_cars: u32,
+}
+
+/// Rolling history of network-wide measurements and loop detector readings for the
+/// time-series plots. Fed every simulation tick so detector crossings are not missed
+/// between frames.
+pub struct TrafficHistory {
+    detectors: DetectorSet,
+    window: f32, // seconds of history kept
+    samples: VecDeque<HistorySample>,
+    readings: VecDeque<DetectorReading>,
+    last_time: f32,
+    next_sample: f32,
+}
+
+impl TrafficHistory {
+    pub fn new(route: &RouteConfig, window_minutes: f32) -> Self {
+        Self {
+            detectors: DetectorSet::from_route(route),
+            window: window_minutes * 60.0,
+            samples: VecDeque::new(),
+            readings: VecDeque::new(),
+            last_time: 0.0,
+            next_sample: 0.0,
+        }
+    }
+    
+    /// Returns the detector readings of intervals that ended this tick
+    pub fn record(&mut self, state: &SimulationState) -> Vec<DetectorReading> {
+        // Time going backwards means the simulation was reset or a checkpoint was loaded
+        if state.time < self.last_time {
+            self.clear();
+        }
+        self.last_time = state.time;
+        
+        let completed = self.detectors.update(state);
+        self.readings.extend(completed.iter().cloned());
+        while self.readings.front().is_some_and(|reading| reading.end < state.time - self.window) {
+            self.readings.pop_front();
+        }
+        
+        if state.time < self.next_sample {
+            return completed;
+        }
+        self.next_sample = state.time + SAMPLE_INTERVAL;
+        
+        let mean_speed = if state.cars.is_empty() {
+            0.0
+        } else {
+            state.cars.iter().map(|car| car.velocity.magnitude()).sum::<f32>() / state.cars.len() as f32
+        };
+        
+        self.samples.push_back(HistorySample {
+            time: state.time,
+            mean_speed,
+            active_cars: state.active_cars,
+        });
+        while self.samples.front().is_some_and(|sample| sample.time < state.time - self.window) {
+            self.samples.pop_front();
+        }
+        completed
+    }
+    
+    /// Measure with the detectors of a changed route from now on
+    pub fn set_route(&mut self, route: &RouteConfig) {
+        self.detectors = DetectorSet::from_route(route);
+    }
+    
+    /// Ids of the configured detectors, in configuration order
+    pub fn detector_ids(&self) -> impl Iterator<Item = &str> {
+        self.detectors.detectors().iter().map(|detector| detector.id())
+    }
+    
+    /// Detectors notice the time jump themselves and restart their intervals
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.readings.clear();
+        self.last_time = 0.0;
+        self.next_sample = 0.0;
+    }
+    
+    /// Collapsible window in the lower-right corner with one scrolling chart per measurement
+    pub fn show(&self, ctx: &egui::Context, units: UnitSystem) {
+        let end = self.samples.back().map(|sample| sample.time).unwrap_or(0.0);
+        let start = (end - self.window).max(0.0);
+        
+        egui::Window::new("Time series")
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-15.0, -15.0))
+            .collapsible(true)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.set_width(PLOT_WIDTH);
+                
+                if self.detectors.is_empty() {
+                    ui.label("Flow: no [[route.detectors]] configured");
+                } else {
+                    ui.label("Detector flow (veh/h)");
+                    Self::plot(ui, "flow_plot", start, end, true, |plot_ui| {
+                        for detector in self.detectors.detectors() {
+                            let points: PlotPoints = self.readings.iter()
+                                .filter(|reading| reading.detector == detector.id())
+                                .map(|reading| [reading.end as f64, reading.flow as f64])
+                                .collect();
+                            plot_ui.line(Line::new(points).name(detector.id()));
+                        }
+                    });
+                    
+                    // Latest completed interval of each detector
+                    egui::Grid::new("detector_readings").striped(true).show(ui, |ui| {
+                        for header in ["Detector", "Count", "Occ", "Speed"] {
+                            ui.label(header);
+                        }
+                        ui.end_row();
+                        for detector in self.detectors.detectors() {
+                            let Some(reading) = self.readings.iter().rev().find(|reading| reading.detector == detector.id()) else {
+                                continue;
+                            };
+                            ui.label(detector.id());
+                            ui.label(reading.count.to_string());
+                            ui.label(format!("{:.0}%", reading.occupancy * 100.0));
+                            ui.label(reading.harmonic_mean_speed
+                                .map(|speed| format!("{:.0} {}", units.speed(speed), units.speed_unit()))
+                                .unwrap_or_else(|| "-".to_string()));
+                            ui.end_row();
+                        }
+                    });
+                }
+                
+                ui.label(format!("Mean speed ({})", units.speed_unit()));
+                self.plot_samples(ui, "speed_plot", start, end, |sample| units.speed(sample.mean_speed));
+                
+                ui.label("Active cars");
+                self.plot_samples(ui, "cars_plot", start, end, |sample| sample.active_cars as f32);
+            });
+    }
+    
+    fn plot_samples(&self, ui: &mut egui::Ui, id: &str, start: f32, end: f32, value: impl Fn(&HistorySample) -> f32) {
+        let points: PlotPoints = self.samples.iter()
+            .map(|sample| [sample.time as f64, value(sample) as f64])
+            .collect();
+        Self::plot(ui, id, start, end, false, |plot_ui| plot_ui.line(Line::new(points)));
+    }
+    
+    fn plot(ui: &mut egui::Ui, id: &str, start: f32, end: f32, legend: bool, build: impl FnOnce(&mut PlotUi)) {
+        // Always span the whole window so the charts scroll with simulation time instead of rescaling
+        let mut plot = Plot::new(id)
+            .height(PLOT_HEIGHT)
+            .include_x(start)
+            .include_x(end.max(start + SAMPLE_INTERVAL))
+            .include_y(0.0)
+            .link_axis("time_series", true, false)
+            .allow_drag(false)
+            .allow_zoom(false)
+            .allow_scroll(false)
+            .allow_boxed_zoom(false);
+        if legend {
+            plot = plot.legend(Legend::default());
+        }
+        plot.show(ui, build);
+    }
+}
@@ -0,0 +1,315 @@
+use crate::config::UnitSystem;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Overlays that can be hidden or moved, by id, with the names the Preferences window shows
+pub const OVERLAYS: [(&str, &str); 5] = [
+    ("status", "Status"),
+    ("controls", "Controls"),
+    ("legend", "Legend"),
+    ("velocity_graph", "Velocity distribution"),
+    ("pie_chart", "Car mix"),
+];
+
+pub const FONT_SIZE_RANGE: std::ops::RangeInclusive<f32> = 8.0..=32.0;
+pub const UI_SCALE_RANGE: std::ops::RangeInclusive<f32> = 0.5..=3.0;
+
+/// Whether an overlay is shown, folded to its title bar, and where it was dragged to
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OverlayPreference {
+    pub visible: bool,
+    pub collapsed: bool,
+    pub position: Option<[f32; 2]>, // points, of the corner the overlay is pinned by; `None` for its usual place
+}
+
+impl Default for OverlayPreference {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            collapsed: false,
+            position: None,
+        }
+    }
+}
+
+/// How the UI looks, kept between runs in `preferences.toml` in the platform config directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiPreferences {
+    pub font_size: f32,
+    pub ui_scale: f32, // egui zoom on top of the window's scale factor
+    pub units: UnitSystem, // speeds and distances are shown in
+    pub overlays: BTreeMap<String, OverlayPreference>, // only overlays changed from the defaults
+}
+
+impl Default for UiPreferences {
+    fn default() -> Self {
+        Self {
+            font_size: 14.0,
+            ui_scale: 1.0,
+            units: UnitSystem::Metric,
+            overlays: BTreeMap::new(),
+        }
+    }
+}
+
+impl UiPreferences {
+    /// `preferences.toml` in the platform's config directory, `None` where there is none,
+    /// as in browsers
+    pub fn default_path() -> Option<PathBuf> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            directories::ProjectDirs::from("", "", "traffic-sim")
+                .map(|dirs| dirs.config_dir().join("preferences.toml"))
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            None
+        }
+    }
+    
+    /// Read preferences saved at `path`, the defaults if nothing was saved yet. Sizes out of
+    /// range are brought back into it.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path)?;
+        let mut preferences: Self = toml::from_str(&text)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        preferences.font_size = preferences.font_size.clamp(*FONT_SIZE_RANGE.start(), *FONT_SIZE_RANGE.end());
+        preferences.ui_scale = preferences.ui_scale.clamp(*UI_SCALE_RANGE.start(), *UI_SCALE_RANGE.end());
+        Ok(preferences)
+    }
+    
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+    
+    pub fn overlay(&self, id: &str) -> OverlayPreference {
+        self.overlays.get(id).copied().unwrap_or_default()
+    }
+    
+    /// Keep `overlay` for `id`, dropping it when it is back to the defaults
+    pub fn set_overlay(&mut self, id: &str, overlay: OverlayPreference) {
+        if overlay == OverlayPreference::default() {
+            self.overlays.remove(id);
+        } else {
+            self.overlays.insert(id.to_string(), overlay);
+        }
+    }
+}
+
+/// Preferences window for font size, UI scale, units and the overlays, which it also lays out.
+/// Every change is saved straight away, so the next run starts the same. Values given on
+/// the command line apply on top of the saved ones and are never saved.
+pub struct PreferencesWindow {
+    open: bool,
+    preferences: UiPreferences,
+    font_size_override: Option<f32>, // from --font-size, until a size is picked here
+    units_override: Option<UnitSystem>, // from --units, until units are picked
+    path: Option<PathBuf>, // where changes are saved, `None` to keep them for this run
+    reset_layout: bool, // put the overlays back in their usual places next frame
+}
+
+impl PreferencesWindow {
+    /// Start from the preferences saved at `path`, or the defaults if they cannot be read
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let preferences = match &path {
+            Some(path) => UiPreferences::load(path).unwrap_or_else(|e| {
+                log::warn!("Using default UI preferences: {}", e);
+                UiPreferences::default()
+            }),
+            None => UiPreferences::default(),
+        };
+        Self {
+            open: false,
+            preferences,
+            font_size_override: None,
+            units_override: None,
+            path,
+            reset_layout: false,
+        }
+    }
+    
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+    
+    /// The saved preferences, without the command line's overrides
+    pub fn preferences(&self) -> &UiPreferences {
+        &self.preferences
+    }
+    
+    /// Font size to draw the UI with, the one given by --font-size if there was one
+    pub fn font_size(&self) -> f32 {
+        self.font_size_override.unwrap_or(self.preferences.font_size)
+    }
+    
+    /// Use `font_size` for this run, as given by --font-size, without saving it
+    pub fn set_font_size(&mut self, font_size: f32) {
+        self.font_size_override = Some(font_size.clamp(*FONT_SIZE_RANGE.start(), *FONT_SIZE_RANGE.end()));
+    }
+    
+    /// Units to show speeds and distances in, the ones given by --units if there were any
+    pub fn units(&self) -> UnitSystem {
+        self.units_override.unwrap_or(self.preferences.units)
+    }
+    
+    /// Show speeds and distances in `units` for this run, as given by --units, without saving it
+    pub fn set_units(&mut self, units: UnitSystem) {
+        self.units_override = Some(units);
+    }
+    
+    /// Switch to `units` and keep them for the next runs
+    pub fn choose_units(&mut self, units: UnitSystem) {
+        self.units_override = None;
+        self.preferences.units = units;
+        self.save();
+    }
+    
+    /// Show one overlay as a window that can be collapsed, dragged and closed, where it was
+    /// left last time or with its `pivot` corner at `default_pos`. Returns where it was drawn,
+    /// `None` while it is hidden.
+    pub fn overlay_window<R>(
+        &mut self,
+        ctx: &egui::Context,
+        id: &str,
+        title: &str,
+        default_pos: egui::Pos2,
+        pivot: egui::Align2,
+        add_contents: impl FnOnce(&mut egui::Ui) -> R,
+    ) -> Option<egui::Rect> {
+        let saved = self.preferences.overlay(id);
+        if !saved.visible {
+            return None;
+        }
+        let mut overlay = saved;
+        let window_id = egui::Id::new(id);
+        let collapsing_id = window_id.with("collapsing");
+        
+        // Follow collapsing done from the Preferences window, and reset it with the layout
+        let mut collapsing = egui::collapsing_header::CollapsingState::load_with_default_open(ctx, collapsing_id, !saved.collapsed);
+        if collapsing.is_open() == saved.collapsed {
+            collapsing.set_open(!saved.collapsed);
+            collapsing.store(ctx);
+        }
+        
+        // egui first lays a window out at a guessed size, keeping that on screen would move
+        // windows near the right or bottom edge away from where they were left
+        let laid_out = ctx.memory(|memory| memory.area_rect(window_id).is_some());
+        let position = saved.position.map_or(default_pos, |[x, y]| egui::pos2(x, y));
+        let window = egui::Window::new(title)
+            .id(window_id)
+            .open(&mut overlay.visible)
+            .resizable(false)
+            .default_open(!saved.collapsed)
+            .pivot(pivot)
+            .constrain(laid_out)
+            .frame(egui::Frame::window(&ctx.style()).fill(egui::Color32::from_black_alpha(160)));
+        let window = if self.reset_layout { window.current_pos(position) } else { window.default_pos(position) };
+        let rect = window.show(ctx, add_contents).map(|response| {
+            if response.response.drag_stopped() {
+                let corner = pivot.pos_in_rect(&response.response.rect);
+                overlay.position = Some([corner.x.round(), corner.y.round()]);
+            }
+            response.response.rect
+        });
+        overlay.collapsed = egui::collapsing_header::CollapsingState::load(ctx, collapsing_id)
+            .is_some_and(|state| !state.is_open());
+            
+        if overlay != saved {
+            self.preferences.set_overlay(id, overlay);
+            self.save();
+        }
+        rect
+    }
+    
+    pub fn show(&mut self, ctx: &egui::Context) {
+        // The overlays, drawn before this window, have been put back by now
+        self.reset_layout = false;
+        let before = self.preferences.clone();
+        let mut font_size_settled = false;
+        let mut open = self.open;
+        egui::Window::new("Preferences")
+            .open(&mut open)
+            .resizable(false)
+            .default_pos(egui::pos2(450.0, 60.0))
+            .show(ctx, |ui| {
+                egui::Grid::new("preferences_text").num_columns(2).show(ui, |ui| {
+                    ui.label("Font size");
+                    let mut font_size = self.font_size();
+                    let response = ui.add(egui::DragValue::new(&mut font_size).speed(0.1).range(FONT_SIZE_RANGE).suffix(" pt"));
+                    if response.changed() {
+                        self.font_size_override = None;
+                        self.preferences.font_size = font_size;
+                    }
+                    // Saved once the size is settled, not on every frame of a drag or keystroke
+                    font_size_settled = response.drag_stopped() || response.lost_focus();
+                    ui.end_row();
+                    ui.label("UI scale");
+                    ui.add(egui::Slider::new(&mut self.preferences.ui_scale, UI_SCALE_RANGE).step_by(0.05));
+                    ui.end_row();
+                    ui.label("Units");
+                    ui.horizontal(|ui| {
+                        let mut chosen = self.units();
+                        for units in UnitSystem::ALL {
+                            if ui.selectable_value(&mut chosen, units, units.name()).clicked() {
+                                self.units_override = None;
+                                self.preferences.units = chosen;
+                            }
+                        }
+                    });
+                    ui.end_row();
+                });
+                
+                ui.separator();
+                ui.label("Overlays, drag their title bars to move them");
+                egui::Grid::new("preferences_overlays").num_columns(2).show(ui, |ui| {
+                    for (id, name) in OVERLAYS {
+                        let mut overlay = self.preferences.overlay(id);
+                        ui.checkbox(&mut overlay.visible, name);
+                        ui.checkbox(&mut overlay.collapsed, "Collapsed");
+                        ui.end_row();
+                        self.preferences.set_overlay(id, overlay);
+                    }
+                });
+                
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Reset").clicked() {
+                        self.preferences = UiPreferences::default();
+                        self.font_size_override = None;
+                        self.units_override = None;
+                        self.reset_layout = true;
+                    }
+                    match &self.path {
+                        Some(path) => ui.label(format!("Saved to {}", path.display())),
+                        None => ui.label("Not saved, there is no config directory"),
+                    };
+                });
+            });
+        self.open = open;
+        
+        let others_changed = UiPreferences { font_size: before.font_size, ..self.preferences.clone() } != before;
+        if others_changed || font_size_settled {
+            self.save();
+        }
+    }
+    
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Err(e) = self.preferences.save(path) {
+            log::error!("Failed to save UI preferences: {}", e);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use crate::simulation::PerformanceTracker;
+use std::time::Duration;
+
+/// Frames in a row over the budget before dropping a level
+const DEGRADE_FRAMES: u32 = 30;
+/// Frames in a row well under the budget before raising a level again
+const RECOVER_FRAMES: u32 = 180;
+/// Share of the budget the work must stay under to recover, so levels do not flicker
+const RECOVER_FRACTION: f32 = 0.6;
+/// Physics steps run per frame at the lowest level
+const LOW_STEPS_PER_FRAME: u32 = 2;
+
+/// How much work each frame does, from everything to the least that still shows the traffic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QualityLevel {
+    Full,
+    Reduced, // UI charts are skipped
+    Low, // also a coarse road mesh and a cap on physics steps per frame
+}
+
+impl QualityLevel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            QualityLevel::Full => "Full",
+            QualityLevel::Reduced => "Reduced",
+            QualityLevel::Low => "Low",
+        }
+    }
+    
+    fn lower(self) -> Self {
+        match self {
+            QualityLevel::Full => QualityLevel::Reduced,
+            _ => QualityLevel::Low,
+        }
+    }
+    
+    fn higher(self) -> Self {
+        match self {
+            QualityLevel::Low => QualityLevel::Reduced,
+            _ => QualityLevel::Full,
+        }
+    }
+}
+
+/// Watches the time each frame spends simulating and rendering against a frame budget and
+/// lowers the quality level while it is exceeded, so a crowded road slows the simulation
+/// down smoothly instead of hitching. Quality comes back once the work fits again.
+#[derive(Debug)]
+pub struct QualityManager {
+    budget: Option<Duration>, // `None` keeps full quality
+    level: QualityLevel,
+    over_budget: u32, // frames in a row over the budget
+    under_budget: u32, // frames in a row well under it
+}
+
+impl QualityManager {
+    /// `budget_ms` of zero turns adaptive quality off
+    pub fn new(budget_ms: f32) -> Self {
+        Self {
+            budget: (budget_ms > 0.0).then(|| Duration::from_secs_f32(budget_ms / 1000.0)),
+            level: QualityLevel::Full,
+            over_budget: 0,
+            under_budget: 0,
+        }
+    }
+    
+    /// Judge the averaged simulation and render times of the latest frame. Returns whether
+    /// the level changed.
+    pub fn update(&mut self, tracker: &PerformanceTracker) -> bool {
+        self.record_frame(tracker.average_simulation_time() + tracker.average_render_time())
+    }
+    
+    /// Judge one frame that did `work`. Returns whether the level changed.
+    pub fn record_frame(&mut self, work: Duration) -> bool {
+        let Some(budget) = self.budget else {
+            return false;
+        };
+        
+        if work > budget {
+            self.over_budget += 1;
+            self.under_budget = 0;
+        } else if work.as_secs_f32() < budget.as_secs_f32() * RECOVER_FRACTION {
+            self.under_budget += 1;
+            self.over_budget = 0;
+        } else {
+            self.over_budget = 0;
+            self.under_budget = 0;
+        }
+        
+        let level = if self.over_budget >= DEGRADE_FRAMES {
+            self.level.lower()
+        } else if self.under_budget >= RECOVER_FRAMES {
+            self.level.higher()
+        } else {
+            self.level
+        };
+        if level == self.level {
+            return false;
+        }
+        log::info!("Quality {} -> {}", self.level.name(), level.name());
+        self.level = level;
+        // Give the tracker's averages time to reflect the new level before judging again
+        self.over_budget = 0;
+        self.under_budget = 0;
+        true
+    }
+    
+    pub fn level(&self) -> QualityLevel {
+        self.level
+    }
+    
+    pub fn budget(&self) -> Option<Duration> {
+        self.budget
+    }
+    
+    /// Whether the time series, resource charts and diagram windows are drawn
+    pub fn show_charts(&self) -> bool {
+        self.level == QualityLevel::Full
+    }
+    
+    /// Fraction of the usual number of segments curved road is built from
+    pub fn road_detail(&self) -> f32 {
+        if self.level == QualityLevel::Low { 0.25 } else { 1.0 }
+    }
+    
+    /// Most physics steps a frame may run, `None` for as many as real time calls for
+    pub fn max_steps_per_frame(&self) -> Option<u32> {
+        (self.level == QualityLevel::Low).then_some(LOW_STEPS_PER_FRAME)
+    }
+    
+    /// What the current level leaves out, for the status panel
+    pub fn describe(&self) -> String {
+        match self.level {
+            QualityLevel::Full => "Full".to_string(),
+            QualityLevel::Reduced => "Reduced (charts hidden)".to_string(),
+            QualityLevel::Low => format!("Low (charts hidden, coarse road, {} steps/frame)", LOW_STEPS_PER_FRAME),
+        }
+    }
+}
@@ -1,8 +1,54 @@
 use anyhow::Result;
 use wgpu::util::DeviceExt;
 use winit::window::Window;
-use crate::simulation::{SimulationState, Car};
-use nalgebra::Matrix4;
+use crate::config::{RouteConfig, RouteGeometry};
+use crate::simulation::{SimulationState, Car, CarId, Point, SignalState, SignalPhase, IntersectionSign, SignType, RampMeterState, TurnSignal, NoiseMap, Queue, road_length};
+use super::road::RoadMesh;
+use super::palette::CarPalette;
+use super::viewport::PERSPECTIVE_LAYER_SPACING;
+use nalgebra::{Matrix4, Vector2, Vector4};
+
+/// Instance slots reserved per car: its body, heading indicator and turn signal
+const INSTANCES_PER_CAR: usize = 3;
+
+/// Cars shorter than this many pixels on screen are drawn as dots
+pub const MIN_SHAPE_PIXELS: f32 = 4.0;
+/// With more cars than this in view, only the cars at least `CROWDED_MIN_SHAPE_PIXELS` long
+/// on screen keep their shapes, so huge fleets cost a dot each
+pub const DETAILED_CAR_LIMIT: usize = 4000;
+pub const CROWDED_MIN_SHAPE_PIXELS: f32 = 16.0;
+/// Smallest size dots are drawn at in pixels, so distant cars do not vanish
+const MIN_DOT_PIXELS: f32 = 2.0;
+/// Meters a car may reach past the edge of the view and still be drawn
+const CULL_MARGIN: f32 = 10.0;
+
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+// Heights the scene is layered at, bottom to top, in meters. The depth test keeps each layer
+// over the ones below whatever order they are drawn in; within a layer later draws win.
+pub(crate) const ROAD_Z: f32 = 0.0;
+pub(crate) const LANE_LINE_Z: f32 = 1.0;
+pub(crate) const MERGE_LINE_Z: f32 = 2.0;
+pub(crate) const MARKER_Z: f32 = 3.0; // entry and exit arrows
+const NOISE_Z: f32 = 3.5; // noise map cells, over the road but under the cars
+const QUEUE_Z: f32 = 3.6; // queue highlights, over the noise map
+const CAR_Z: f32 = 4.0;
+const CAR_DETAIL_Z: f32 = 5.0; // heading indicators, turn signals and headlight flashes
+const SIGNAL_Z: f32 = 6.0; // signal heads, intersection signs and ramp meters
+
+/// Meters of road each piece of a queue highlight covers, short enough to follow the curve
+const QUEUE_SEGMENT: f32 = 4.0;
+
+/// Unchanged instances between two changed ones that are uploaded anyway, so a scattering
+/// of changes goes up in a few writes rather than one per instance
+const UPLOAD_GAP: usize = 32;
+/// Color of the highlight along queues
+const QUEUE_COLOR: [f32; 3] = [1.0, 0.3, 0.1];
+
+/// Height in meters of the car boxes in the perspective view
+const CAR_HEIGHT: f32 = 1.5;
+/// Vertices in the box cars are drawn as in the perspective view, which has no bottom face
+const CAR_BOX_VERTEX_COUNT: u32 = 30;
 
 pub struct TrafficRenderer {
     surface: wgpu::Surface<'static>,
@@ -14,31 +60,153 @@ pub struct TrafficRenderer {
     // Rendering pipeline
     render_pipeline: wgpu::RenderPipeline,
     
+    // Depth attachment, resized with the surface
+    depth_texture: wgpu::Texture,
+    depth_view: wgpu::TextureView,
+    
     // Uniform buffers
     view_bind_group: wgpu::BindGroup,
     view_buffer: wgpu::Buffer,
     
     // Vertex data
     car_vertex_buffer: wgpu::Buffer,
+    car_box_vertex_buffer: wgpu::Buffer,
     road_vertex_buffer: wgpu::Buffer,
     road_vertex_count: u32,
     car_instance_buffer: wgpu::Buffer,
+    dot_instance_buffer: wgpu::Buffer,
     road_identity_instance_buffer: wgpu::Buffer,
+    scene: SceneInstances, // staged and last uploaded instances
+    
+    // Cars too small on screen for their shapes are drawn as dots by their own pipeline
+    dot_pipeline: wgpu::RenderPipeline,
+    drawn_cars: DrawnCars,
+    
+    road_detail: f32, // fraction of the usual segments the road mesh is built from
     
-    // Shader layouts
-    view_bind_group_layout: wgpu::BindGroupLayout,
+    // Draw a windshield near the front of each car so its heading is visible
+    show_heading_indicators: bool,
+    car_coloring: CarColoring,
+    palette: CarPalette,
     
-    max_cars: u32,
+    // Extrude cars into boxes and lift what sits on them, for the perspective camera
+    perspective: bool,
     
-    // Route geometry type for rendering
-    geometry_type: String,
+    // Noise map cells drawn over the road, empty while the map is hidden
+    noise_cells: Vec<CarInstance>,
+    
+    // Highlight the lanes queues stand in, along the ring of `geometry`
+    show_queues: bool,
+    geometry: RouteGeometry,
+}
+
+/// What the body color of each car shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CarColoring {
+    Palette, // behavior or car type colors from cars.toml
+    Lane,
+}
+
+impl CarColoring {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CarColoring::Palette => "palette",
+            CarColoring::Lane => "lane",
+        }
+    }
+}
+
+/// Cars drawn in the last frame at each level of detail, and those left out of view
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DrawnCars {
+    pub shapes: u32,
+    pub dots: u32,
+    pub culled: u32,
+}
+
+/// How many pixels long a car must be on screen to be drawn as its shape rather than a dot,
+/// with `visible_cars` in view
+pub fn min_shape_pixels(visible_cars: usize) -> f32 {
+    if visible_cars > DETAILED_CAR_LIMIT {
+        CROWDED_MIN_SHAPE_PIXELS
+    } else {
+        MIN_SHAPE_PIXELS
+    }
+}
+
+/// The cars in view, split into those drawn as their shapes and those drawn as dots
+pub struct CarDetail<'a> {
+    pub shapes: Vec<&'a Car>,
+    pub dots: Vec<(&'a Car, f32)>, // with the screen pixels per meter where each car is
+    pub culled: usize, // cars out of view, in neither
+}
+
+impl<'a> CarDetail<'a> {
+    /// Pick the level of detail of each of `cars` seen through `view_matrix` on a target
+    /// `width` pixels wide. No rotation is worked out here, so it stays cheap for huge fleets.
+    pub fn select(cars: &'a [Car], view_matrix: &Matrix4<f32>, width: u32) -> Self {
+        let in_view: Vec<(&Car, f32)> = cars.iter()
+            .filter_map(|car| Some((car, pixels_per_meter(car, view_matrix, width)?)))
+            .collect();
+        let culled = cars.len() - in_view.len();
+        let min_pixels = min_shape_pixels(in_view.len());
+        let (shapes, dots): (Vec<_>, Vec<_>) = in_view.into_iter()
+            .partition(|(car, pixels_per_meter)| car.length * pixels_per_meter >= min_pixels);
+        Self {
+            shapes: shapes.into_iter().map(|(car, _)| car).collect(),
+            dots,
+            culled,
+        }
+    }
+}
+
+/// Screen pixels per meter where `car` is, `None` when it is out of view
+fn pixels_per_meter(car: &Car, view_matrix: &Matrix4<f32>, width: u32) -> Option<f32> {
+    let clip = view_matrix * Vector4::new(car.position.x, car.position.y, CAR_Z, 1.0);
+    if clip.w <= 0.0 {
+        return None;
+    }
+    // Clip-space units per meter across and up the screen, divided out by the depth
+    let across = Vector2::new(view_matrix[(0, 0)], view_matrix[(0, 1)]).norm() / clip.w;
+    let up = Vector2::new(view_matrix[(1, 0)], view_matrix[(1, 1)]).norm() / clip.w;
+    let in_view = (clip.x / clip.w).abs() <= 1.0 + CULL_MARGIN * across
+        && (clip.y / clip.w).abs() <= 1.0 + CULL_MARGIN * up;
+    in_view.then_some(across * width as f32 / 2.0)
+}
+
+/// Distinct colors for lanes 1, 2, ..., repeating after eight
+const LANE_COLORS: [[f32; 3]; 8] = [
+    [0.9, 0.3, 0.3],
+    [0.3, 0.6, 1.0],
+    [0.3, 0.85, 0.35],
+    [1.0, 0.75, 0.2],
+    [0.8, 0.4, 1.0],
+    [0.2, 0.9, 0.9],
+    [1.0, 0.5, 0.8],
+    [0.7, 0.7, 0.45],
+];
+
+/// Noise map color of a cell at `level` dB(A): green when quiet through yellow to red at
+/// the loudest traffic
+pub fn noise_color(level: f32) -> [f32; 3] {
+    let t = ((level - 70.0) / 35.0).clamp(0.0, 1.0);
+    if t < 0.5 {
+        [t * 2.0, 0.8, 0.1]
+    } else {
+        [1.0, 0.8 * (2.0 - t * 2.0), 0.1]
+    }
+}
+
+/// Body color of cars in `lane` when coloring by lane
+pub fn lane_color(lane: u32) -> [f32; 3] {
+    LANE_COLORS[(lane.max(1) as usize - 1) % LANE_COLORS.len()]
 }
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-struct Vertex {
-    position: [f32; 3],
-    color: [f32; 3],
+pub(crate) struct Vertex {
+    pub(crate) position: [f32; 3],
+    pub(crate) color: [f32; 3],
 }
 
 #[repr(C)]
@@ -49,6 +217,73 @@ struct CarInstance {
     _padding: f32,
 }
 
+/// A car drawn as an unrotated square, a third the size of a `CarInstance`
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct DotInstance {
+    position: [f32; 3],
+    size: f32, // meters across
+    color: [f32; 3],
+    _padding: f32,
+}
+
+/// Everything drawn over the road, kept from one frame to the next so building the next
+/// one reuses the memory, and only what changed is worked out again and uploaded
+#[derive(Default)]
+struct SceneInstances {
+    instances: Vec<CarInstance>, // being built for this frame
+    body_count: u32, // the car bodies come first
+    dots: Vec<DotInstance>,
+    uploaded: Vec<CarInstance>, // what the car instance buffer holds
+    uploaded_dots: Vec<DotInstance>, // what the dot instance buffer holds
+    bodies: Vec<BodyPose>, // what the bodies at the start of `uploaded` were worked out from
+}
+
+/// What the transform of a car's body is made from, to tell when it must be worked out again
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BodyPose {
+    id: CarId,
+    position: Point,
+    heading: f32,
+    length: f32,
+    width: f32,
+    height: f32,
+}
+
+impl BodyPose {
+    fn of(car: &Car, height: f32) -> Self {
+        Self {
+            id: car.id,
+            position: car.position,
+            heading: car.heading,
+            length: car.length,
+            width: car.width,
+            height,
+        }
+    }
+}
+
+/// Ranges of `staged` that differ from `uploaded`, byte for byte, in order. Ranges closer
+/// than `UPLOAD_GAP` instances are joined, and everything past the end of `uploaded` is one
+/// range.
+pub fn changed_ranges<'a, T: bytemuck::Pod>(staged: &'a [T], uploaded: &'a [T]) -> impl Iterator<Item = std::ops::Range<usize>> + 'a {
+    let unchanged = |i: usize| uploaded.get(i).is_some_and(|old| bytemuck::bytes_of(old) == bytemuck::bytes_of(&staged[i]));
+    let mut next = 0;
+    std::iter::from_fn(move || {
+        let start = (next..staged.len()).find(|&i| !unchanged(i))?;
+        let mut end = start + 1;
+        let mut i = end;
+        while i < staged.len() && i - end < UPLOAD_GAP {
+            i += 1;
+            if !unchanged(i - 1) {
+                end = i;
+            }
+        }
+        next = end;
+        Some(start..end)
+    })
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 struct ViewUniforms {
@@ -115,6 +350,33 @@ impl CarInstance {
     }
 }
 
+impl DotInstance {
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<DotInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+            ],
+        }
+    }
+}
+
 const SHADER_SOURCE: &str = r#"
 struct ViewUniforms {
     view_proj: mat4x4<f32>,
@@ -159,6 +421,25 @@ fn vs_main(
     return out;
 }
 
+struct DotInput {
+    @location(5) position: vec3<f32>,
+    @location(6) size: f32,
+    @location(7) color: vec3<f32>,
+}
+
+@vertex
+fn vs_dot(
+    model: VertexInput,
+    dot: DotInput,
+) -> VertexOutput {
+    let world = vec3<f32>(dot.position.xy + model.position.xy * dot.size, dot.position.z);
+    
+    var out: VertexOutput;
+    out.color = model.color * dot.color;
+    out.clip_position = view.view_proj * vec4<f32>(world, 1.0);
+    return out;
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     return vec4<f32>(in.color, 1.0);
@@ -181,8 +462,10 @@ impl TrafficRenderer {
     pub fn surface(&self) -> &wgpu::Surface<'_> {
         &self.surface
     }
-
-    pub async fn new(window: std::sync::Arc<Window>, geometry_type: String) -> Result<Self> {
+    
+    /// The instance buffers start with room for `max_cars`, cars.toml's total, and grow if
+    /// more cars than that are ever on the road
+    pub async fn new(window: std::sync::Arc<Window>, route: &RouteConfig, palette: CarPalette, max_cars: u32) -> Result<Self> {
         let size = window.inner_size();
         
         // Create wgpu instance
@@ -205,19 +488,20 @@ impl TrafficRenderer {
             })
             .await
             .ok_or_else(|| anyhow::anyhow!("Failed to find an appropriate adapter"))?;
-        
+            
         // Request device and queue
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     required_features: wgpu::Features::empty(),
-                    required_limits: wgpu::Limits::default(),
+                    // Browsers may offer less than the native defaults
+                    required_limits: if cfg!(target_arch = "wasm32") { adapter.limits() } else { wgpu::Limits::default() },
                     label: None,
                 },
                 None,
             )
             .await?;
-        
+            
         // Configure surface
         let surface_caps = surface.get_capabilities(&adapter);
         let surface_format = surface_caps
@@ -226,18 +510,21 @@ impl TrafficRenderer {
             .copied()
             .find(|f| f.is_srgb())
             .unwrap_or(surface_caps.formats[0]);
-        
+            
+        // Screenshots copy straight out of the surface where the platform allows it
+        let copy_src = surface_caps.usages & wgpu::TextureUsages::COPY_SRC;
         let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | copy_src,
             format: surface_format,
-            width: size.width,
-            height: size.height,
+            width: size.width.max(1), // A canvas can be laid out at zero size
+            height: size.height.max(1),
             present_mode: surface_caps.present_modes[0],
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
         };
         surface.configure(&device, &config);
+        let (depth_texture, depth_view) = Self::create_depth_texture(&device, config.width, config.height);
         
         // Create shader
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
@@ -267,42 +554,8 @@ impl TrafficRenderer {
             push_constant_ranges: &[],
         });
         
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::desc(), CarInstance::desc()],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-        });
+        let render_pipeline = Self::create_pipeline(&device, &render_pipeline_layout, &shader, config.format, "Render Pipeline", "vs_main", &[Vertex::desc(), CarInstance::desc()]);
+        let dot_pipeline = Self::create_pipeline(&device, &render_pipeline_layout, &shader, config.format, "Dot Pipeline", "vs_dot", &[Vertex::desc(), DotInstance::desc()]);
         
         // Create buffers
         let view_buffer = device.create_buffer(&wgpu::BufferDescriptor {
@@ -328,22 +581,24 @@ impl TrafficRenderer {
             contents: bytemuck::cast_slice(&car_vertices),
             usage: wgpu::BufferUsages::VERTEX,
         });
+        let car_box_vertices = Self::create_car_box_vertices();
+        let car_box_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Car Box Vertex Buffer"),
+            contents: bytemuck::cast_slice(&car_box_vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
         
-        let road_vertices = Self::create_road_vertices(&geometry_type);
-        let road_vertex_count = road_vertices.len() as u32;
+        let road_mesh = RoadMesh::from_route(route);
+        let road_vertex_count = road_mesh.vertex_count() as u32;
         let road_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Road Vertex Buffer"),
-            contents: bytemuck::cast_slice(&road_vertices),
+            contents: bytemuck::cast_slice(road_mesh.vertices()),
             usage: wgpu::BufferUsages::VERTEX,
         });
         
-        let max_cars = 1000;
-        let car_instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Car Instance Buffer"),
-            size: (std::mem::size_of::<CarInstance>() * max_cars) as u64,
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+        let max_cars = max_cars.max(1) as usize;
+        let car_instance_buffer = Self::create_instance_buffer(&device, "Car Instance Buffer", std::mem::size_of::<CarInstance>() * max_cars * INSTANCES_PER_CAR);
+        let dot_instance_buffer = Self::create_instance_buffer(&device, "Dot Instance Buffer", std::mem::size_of::<DotInstance>() * max_cars);
         
         // Create identity instance buffer for road rendering (since roads don't need per-instance transforms)
         let identity_transform = Matrix4::identity();
@@ -365,28 +620,174 @@ impl TrafficRenderer {
             config,
             size,
             render_pipeline,
+            depth_texture,
+            depth_view,
             view_bind_group,
             view_buffer,
             car_vertex_buffer,
+            car_box_vertex_buffer,
             road_vertex_buffer,
             road_vertex_count,
             car_instance_buffer,
+            dot_instance_buffer,
             road_identity_instance_buffer,
-            view_bind_group_layout,
-            max_cars: max_cars as u32,
-            geometry_type,
+            scene: SceneInstances::default(),
+            dot_pipeline,
+            drawn_cars: DrawnCars::default(),
+            road_detail: 1.0,
+            show_heading_indicators: true,
+            car_coloring: CarColoring::Palette,
+            palette,
+            perspective: false,
+            noise_cells: Vec::new(),
+            show_queues: true,
+            geometry: route.route.geometry.clone(),
         })
     }
     
+    /// Bytes in the depth texture and the uniform, vertex and instance buffers. Vello and
+    /// egui allocate their own.
+    pub fn gpu_memory_usage(&self) -> usize {
+        let buffers: usize = [
+            &self.view_buffer,
+            &self.car_vertex_buffer,
+            &self.car_box_vertex_buffer,
+            &self.road_vertex_buffer,
+            &self.car_instance_buffer,
+            &self.dot_instance_buffer,
+            &self.road_identity_instance_buffer,
+        ]
+            .iter()
+            .map(|buffer| buffer.size() as usize)
+            .sum();
+        let depth = self.depth_texture.width() as usize * self.depth_texture.height() as usize * 4;
+        buffers + depth
+    }
+    
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.size = new_size;
             self.config.width = new_size.width;
             self.config.height = new_size.height;
             self.surface.configure(&self.device, &self.config);
+            (self.depth_texture, self.depth_view) = Self::create_depth_texture(&self.device, new_size.width, new_size.height);
         }
     }
     
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+        label: &str,
+        vertex_entry: &str,
+        buffers: &[wgpu::VertexBufferLayout],
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: vertex_entry,
+                buffers,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: true,
+                // Equal depths pass so later draws in the same layer cover earlier ones
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+    }
+    
+    fn create_instance_buffer(device: &wgpu::Device, label: &str, bytes: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: bytes as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+    
+    /// Replace an instance buffer too small for `count` instances of `T` with one for twice
+    /// as many. What was uploaded to the old buffer is forgotten.
+    fn reserve<T>(device: &wgpu::Device, buffer: &mut wgpu::Buffer, uploaded: &mut Vec<T>, label: &str, count: usize) {
+        let size = std::mem::size_of::<T>();
+        if (size * count) as u64 > buffer.size() {
+            log::info!("Growing {} from {} to {} instances", label, buffer.size() as usize / size, count * 2);
+            *buffer = Self::create_instance_buffer(device, label, size * count * 2);
+            uploaded.clear();
+        }
+    }
+    
+    /// Make room for `cars` cars ahead of time, as when a configuration with more is loaded
+    pub fn reserve_cars(&mut self, cars: usize) {
+        Self::reserve(&self.device, &mut self.car_instance_buffer, &mut self.scene.uploaded, "Car Instance Buffer", cars * INSTANCES_PER_CAR);
+        Self::reserve(&self.device, &mut self.dot_instance_buffer, &mut self.scene.uploaded_dots, "Dot Instance Buffer", cars);
+    }
+    
+    /// Write the ranges of `staged` that differ from `uploaded`, what `buffer` holds, and
+    /// swap the two so `uploaded` holds them from now on
+    fn upload_changed<T: bytemuck::Pod>(queue: &wgpu::Queue, buffer: &wgpu::Buffer, staged: &mut Vec<T>, uploaded: &mut Vec<T>) {
+        for range in changed_ranges(staged, uploaded) {
+            let offset = (range.start * std::mem::size_of::<T>()) as wgpu::BufferAddress;
+            queue.write_buffer(buffer, offset, bytemuck::cast_slice(&staged[range]));
+        }
+        std::mem::swap(staged, uploaded);
+    }
+    
+    fn create_depth_texture(device: &wgpu::Device, width: u32, height: u32) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Depth Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        (texture, view)
+    }
+    
+    /// Whether the surface's textures can be copied out for screenshots
+    pub fn surface_copyable(&self) -> bool {
+        self.config.usage.contains(wgpu::TextureUsages::COPY_SRC)
+    }
+    
     pub fn render_to_texture(
         &mut self, 
         state: &SimulationState, 
@@ -394,6 +795,80 @@ impl TrafficRenderer {
         target_view: &wgpu::TextureView,
         encoder: &mut wgpu::CommandEncoder
     ) -> Result<()> {
+        let width = self.size.width;
+        self.draw(state, view_matrix, target_view, None, width, None, encoder);
+        Ok(())
+    }
+    
+    /// Draw `left` into the left half of the target and `right` into the right half, both
+    /// through `view_matrix`, which should be set up for half the target's width. The left
+    /// half is submitted right away, the instance buffers are reused for the right one.
+    pub fn render_split_to_texture(
+        &mut self,
+        left: &SimulationState,
+        right: &SimulationState,
+        view_matrix: &Matrix4<f32>,
+        target_view: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder
+    ) -> Result<()> {
+        let pane_width = self.size.width / 2;
+        let mut left_encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Left Pane Encoder"),
+        });
+        self.draw(left, view_matrix, target_view, None, pane_width, Some((0, pane_width)), &mut left_encoder);
+        self.queue.submit(std::iter::once(left_encoder.finish()));
+        self.draw(right, view_matrix, target_view, None, pane_width, Some((pane_width, pane_width)), encoder);
+        Ok(())
+    }
+    
+    /// Draw the scene, without the UI, into an off-screen texture of `width` by `height`
+    /// pixels and read it back. Call it before recording the frame: it submits its own
+    /// commands and overwrites the view and instance buffers. Blocks until the GPU is done,
+    /// which browsers do not allow.
+    pub fn capture(&mut self, state: &SimulationState, view_matrix: &Matrix4<f32>, width: u32, height: u32) -> Result<super::Screenshot> {
+        let max_size = self.device.limits().max_texture_dimension_2d;
+        if width == 0 || height == 0 || width > max_size || height > max_size {
+            return Err(anyhow::anyhow!("Capture size {}x{} must be between 1 and {} pixels per side", width, height, max_size));
+        }
+        let target = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Capture Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let target_view = target.create_view(&wgpu::TextureViewDescriptor::default());
+        let (_depth_texture, depth_view) = Self::create_depth_texture(&self.device, width, height);
+        
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Capture Encoder"),
+        });
+        self.draw(state, view_matrix, &target_view, Some(&depth_view), width, None, &mut encoder);
+        super::capture::read_texture(&self.device, &self.queue, &target, encoder)
+    }
+    
+    /// Record the scene pass into `encoder`, against the surface-sized depth texture unless
+    /// another is given. `width` is the target's in pixels, for the cars' level of detail.
+    /// A pane, the left edge and width in pixels, limits drawing to that part of the
+    /// target; a pane right of the left edge draws over what is already there.
+    #[allow(clippy::too_many_arguments)]
+    fn draw(
+        &mut self,
+        state: &SimulationState,
+        view_matrix: &Matrix4<f32>,
+        target_view: &wgpu::TextureView,
+        depth_view: Option<&wgpu::TextureView>,
+        width: u32,
+        pane: Option<(u32, u32)>,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
         // Update view uniforms
         let view_proj_array: [[f32; 4]; 4] = (*view_matrix).into();
         let uniforms = ViewUniforms {
@@ -401,18 +876,18 @@ impl TrafficRenderer {
         };
         self.queue.write_buffer(&self.view_buffer, 0, bytemuck::cast_slice(&[uniforms]));
         
-        // Update car instances
-        let car_instances: Vec<CarInstance> = state.cars.iter().map(|car| {
-            self.create_car_instance(car)
-        }).collect();
-        
-        if !car_instances.is_empty() {
-            self.queue.write_buffer(
-                &self.car_instance_buffer,
-                0,
-                bytemuck::cast_slice(&car_instances),
-            );
-        }
+        // Update car and signal head instances, growing the buffers to fit and uploading
+        // what changed since the last draw
+        let mut scene = std::mem::take(&mut self.scene);
+        self.create_instances(state, view_matrix, width, &mut scene);
+        let instance_count = scene.instances.len() as u32;
+        let dot_count = scene.dots.len() as u32;
+        Self::reserve(&self.device, &mut self.car_instance_buffer, &mut scene.uploaded, "Car Instance Buffer", scene.instances.len());
+        Self::reserve(&self.device, &mut self.dot_instance_buffer, &mut scene.uploaded_dots, "Dot Instance Buffer", scene.dots.len());
+        Self::upload_changed(&self.queue, &self.car_instance_buffer, &mut scene.instances, &mut scene.uploaded);
+        Self::upload_changed(&self.queue, &self.dot_instance_buffer, &mut scene.dots, &mut scene.uploaded_dots);
+        let body_count = scene.body_count;
+        self.scene = scene;
         
         // Begin render pass
         {
@@ -422,20 +897,33 @@ impl TrafficRenderer {
                     view: target_view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
+                        load: match pane {
+                            Some((left, _)) if left > 0 => wgpu::LoadOp::Load,
+                            _ => wgpu::LoadOp::Clear(wgpu::Color {
+                                r: 0.1,
+                                g: 0.2,
+                                b: 0.3,
+                                a: 1.0,
+                            }),
+                        },
                         store: wgpu::StoreOp::Store,
                     },
                 })],
-                depth_stencil_attachment: None,
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: depth_view.unwrap_or(&self.depth_view),
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
                 occlusion_query_set: None,
                 timestamp_writes: None,
             });
             
+            if let Some((left, pane_width)) = pane {
+                render_pass.set_viewport(left as f32, 0.0, pane_width as f32, self.size.height as f32, 0.0, 1.0);
+            }
             render_pass.set_pipeline(&self.render_pipeline);
             render_pass.set_bind_group(0, &self.view_bind_group, &[]);
             
@@ -444,85 +932,30 @@ impl TrafficRenderer {
             render_pass.set_vertex_buffer(1, self.road_identity_instance_buffer.slice(..));
             render_pass.draw(0..self.road_vertex_count, 0..1);
             
-            // Render cars
-            if !state.cars.is_empty() {
+            // Render cars and signal heads
+            if instance_count > 0 {
+                self.draw_instances(&mut render_pass, body_count, instance_count);
+            }
+            
+            // Render distant cars as dots
+            if dot_count > 0 {
+                render_pass.set_pipeline(&self.dot_pipeline);
                 render_pass.set_vertex_buffer(0, self.car_vertex_buffer.slice(..));
-                render_pass.set_vertex_buffer(1, self.car_instance_buffer.slice(..));
-                render_pass.draw(0..6, 0..state.cars.len() as u32);
+                render_pass.set_vertex_buffer(1, self.dot_instance_buffer.slice(..));
+                render_pass.draw(0..6, 0..dot_count);
             }
         }
-        
-        Ok(())
     }
-
+    
     pub fn render(&mut self, state: &SimulationState, view_matrix: &Matrix4<f32>) -> Result<()> {
-        // Update view uniforms
-        let view_proj_array: [[f32; 4]; 4] = (*view_matrix).into();
-        let uniforms = ViewUniforms {
-            view_proj: view_proj_array,
-        };
-        self.queue.write_buffer(&self.view_buffer, 0, bytemuck::cast_slice(&[uniforms]));
-        
-        // Update car instances
-        let car_instances: Vec<CarInstance> = state.cars.iter().map(|car| {
-            self.create_car_instance(car)
-        }).collect();
-        
-        if !car_instances.is_empty() {
-            self.queue.write_buffer(
-                &self.car_instance_buffer,
-                0,
-                bytemuck::cast_slice(&car_instances),
-            );
-        }
-        
-        // Begin render pass
         let output = self.surface.get_current_texture()?;
         let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
         
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Render Encoder"),
         });
-        
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
-            
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, &self.view_bind_group, &[]);
-            
-            // Render road
-            render_pass.set_vertex_buffer(0, self.road_vertex_buffer.slice(..));
-            render_pass.set_vertex_buffer(1, self.road_identity_instance_buffer.slice(..));
-            render_pass.draw(0..self.road_vertex_count, 0..1);
-            
-            // Render cars
-            if !state.cars.is_empty() {
-                render_pass.set_vertex_buffer(0, self.car_vertex_buffer.slice(..));
-                render_pass.set_vertex_buffer(1, self.car_instance_buffer.slice(..));
-                render_pass.draw(0..6, 0..state.cars.len() as u32);
-            }
-            
-            // TODO: Add overlay rendering for spawn/exit indicators
-            // For now, let's test the rectangular car rendering
-        }
+        let width = self.size.width;
+        self.draw(state, view_matrix, &view, None, width, None, &mut encoder);
         
         self.queue.submit(std::iter::once(encoder.finish()));
         output.present();
@@ -530,9 +963,24 @@ impl TrafficRenderer {
         Ok(())
     }
     
+    /// Draw the car bodies as boxes in the perspective view and flat otherwise, then the rest
+    /// of the instances flat
+    fn draw_instances<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, body_count: u32, instance_count: u32) {
+        render_pass.set_vertex_buffer(1, self.car_instance_buffer.slice(..));
+        if self.perspective {
+            render_pass.set_vertex_buffer(0, self.car_box_vertex_buffer.slice(..));
+            render_pass.draw(0..CAR_BOX_VERTEX_COUNT, 0..body_count);
+            render_pass.set_vertex_buffer(0, self.car_vertex_buffer.slice(..));
+            render_pass.draw(0..6, body_count..instance_count);
+        } else {
+            render_pass.set_vertex_buffer(0, self.car_vertex_buffer.slice(..));
+            render_pass.draw(0..6, 0..instance_count);
+        }
+    }
+    
     fn create_car_vertices() -> Vec<Vertex> {
-        // Cars are perfect squares with 1:1 aspect ratio
-        // Width = Height = 1.0 unit (from -0.5 to +0.5 on both axes)
+        // Unit square (from -0.5 to +0.5 on both axes), each instance scales it
+        // to the car's length along x and width along y
         vec![
             // Square as two triangles
             // Triangle 1 (top-left, bottom-left, top-right)
@@ -546,520 +994,345 @@ impl TrafficRenderer {
         ]
     }
     
-    fn create_road_vertices(geometry_type: &str) -> Vec<Vertex> {
-        // Select road vertex generation based on geometry type from route configuration
-        match geometry_type {
-            "cloverleaf" => Self::create_cloverleaf_road_vertices(),
-            "donut" => Self::create_donut_road_vertices(),
-            _ => {
-                log::warn!("Unknown geometry type '{}', defaulting to donut", geometry_type);
-                Self::create_donut_road_vertices()
-            }
-        }
+    /// Unit box standing on the ground (from -0.5 to +0.5 across, 0 to 1 up), each instance
+    /// scales it like the square and stretches it to the car's height. The sides are shaded
+    /// darker than the roof so the boxes read as solid.
+    fn create_car_box_vertices() -> Vec<Vertex> {
+        // Corners of each face counter-clockwise seen from outside, with its shade
+        let faces: [([[f32; 3]; 4], f32); 5] = [
+            ([[-0.5, -0.5, 1.0], [0.5, -0.5, 1.0], [0.5, 0.5, 1.0], [-0.5, 0.5, 1.0]], 1.0),   // Roof
+            ([[0.5, -0.5, 0.0], [0.5, 0.5, 0.0], [0.5, 0.5, 1.0], [0.5, -0.5, 1.0]], 0.8),     // Front
+            ([[-0.5, 0.5, 0.0], [-0.5, -0.5, 0.0], [-0.5, -0.5, 1.0], [-0.5, 0.5, 1.0]], 0.6), // Back
+            ([[0.5, 0.5, 0.0], [-0.5, 0.5, 0.0], [-0.5, 0.5, 1.0], [0.5, 0.5, 1.0]], 0.7),     // Left
+            ([[-0.5, -0.5, 0.0], [0.5, -0.5, 0.0], [0.5, -0.5, 1.0], [-0.5, -0.5, 1.0]], 0.7), // Right
+        ];
+        faces.iter()
+            .flat_map(|(corners, shade)| {
+                [0, 1, 2, 0, 2, 3].map(|i| Vertex { position: corners[i], color: [*shade; 3] })
+            })
+            .collect()
     }
     
-    fn create_donut_road_vertices() -> Vec<Vertex> {
-        // Create donut-shaped highway with lane markings, entry/exit symbols
-        let mut vertices = Vec::new();
-        let segments = 64;
-        let inner_radius = 150.0;
-        let outer_radius = 200.0;
-        let lane_width = 3.5;
-        let lane_count = 3;
-        
-        // Create the road surface with individual lane visualization
-        for i in 0..segments {
-            let angle1 = (i as f32) * 2.0 * std::f32::consts::PI / (segments as f32);
-            let angle2 = ((i + 1) as f32) * 2.0 * std::f32::consts::PI / (segments as f32);
-            
-            // Render each lane individually with subtle color variations
-            for lane in 0..lane_count {
-                let lane_inner_radius = inner_radius + lane_width * lane as f32;
-                let lane_outer_radius = inner_radius + lane_width * (lane + 1) as f32;
-                
-                // Alternate lane colors for better visibility
-                let base_gray = 0.2;
-                let color_variation = if lane % 2 == 0 { 0.0 } else { 0.03 };
-                let lane_color = [
-                    base_gray + color_variation,
-                    base_gray + color_variation,
-                    base_gray + color_variation
-                ];
-                
-                // Inner edge of this lane
-                let inner1 = [lane_inner_radius * angle1.cos(), lane_inner_radius * angle1.sin(), 0.0];
-                let inner2 = [lane_inner_radius * angle2.cos(), lane_inner_radius * angle2.sin(), 0.0];
-                
-                // Outer edge of this lane
-                let outer1 = [lane_outer_radius * angle1.cos(), lane_outer_radius * angle1.sin(), 0.0];
-                let outer2 = [lane_outer_radius * angle2.cos(), lane_outer_radius * angle2.sin(), 0.0];
-                
-                // Create two triangles for each lane segment
-                vertices.push(Vertex { position: inner1, color: lane_color });
-                vertices.push(Vertex { position: outer1, color: lane_color });
-                vertices.push(Vertex { position: inner2, color: lane_color });
-                
-                vertices.push(Vertex { position: inner2, color: lane_color });
-                vertices.push(Vertex { position: outer1, color: lane_color });
-                vertices.push(Vertex { position: outer2, color: lane_color });
-            }
-        }
-        
-        // Add enhanced lane markings (white dashed lines between lanes)
-        let line_width = 0.2; // Slightly wider for better visibility
-        let dash_length = 3.0; // meters
-        let dash_spacing = 6.0; // meters
-        let white_color = [0.95, 0.95, 0.95]; // Brighter white
-        
-        for lane in 1..lane_count {
-            let lane_radius = inner_radius + lane_width * lane as f32;
-            let circumference = 2.0 * std::f32::consts::PI * lane_radius;
-            let dash_cycle = dash_length + dash_spacing;
-            let num_dashes = (circumference / dash_cycle) as usize;
-            
-            for dash in 0..num_dashes {
-                let start_angle = (dash as f32) * dash_cycle / lane_radius;
-                let end_angle = start_angle + (dash_length / lane_radius);
-                
-                let dash_segments = 8; // Segments per dash for smoothness
-                for seg in 0..dash_segments {
-                    let t1 = seg as f32 / dash_segments as f32;
-                    let t2 = (seg + 1) as f32 / dash_segments as f32;
-                    let a1 = start_angle + (end_angle - start_angle) * t1;
-                    let a2 = start_angle + (end_angle - start_angle) * t2;
-                    
-                    let inner_r = lane_radius - line_width * 0.5;
-                    let outer_r = lane_radius + line_width * 0.5;
-                    
-                    let p1 = [inner_r * a1.cos(), inner_r * a1.sin(), 0.02]; // Higher above road
-                    let p2 = [outer_r * a1.cos(), outer_r * a1.sin(), 0.02];
-                    let p3 = [inner_r * a2.cos(), inner_r * a2.sin(), 0.02];
-                    let p4 = [outer_r * a2.cos(), outer_r * a2.sin(), 0.02];
-                    
-                    // Two triangles for the dash segment
-                    vertices.push(Vertex { position: p1, color: white_color });
-                    vertices.push(Vertex { position: p2, color: white_color });
-                    vertices.push(Vertex { position: p3, color: white_color });
-                    
-                    vertices.push(Vertex { position: p3, color: white_color });
-                    vertices.push(Vertex { position: p2, color: white_color });
-                    vertices.push(Vertex { position: p4, color: white_color });
-                }
-            }
+    pub fn show_heading_indicators(&self) -> bool {
+        self.show_heading_indicators
+    }
+    
+    pub fn set_show_heading_indicators(&mut self, show: bool) {
+        self.show_heading_indicators = show;
+    }
+    
+    pub fn car_coloring(&self) -> CarColoring {
+        self.car_coloring
+    }
+    
+    pub fn set_car_coloring(&mut self, coloring: CarColoring) {
+        self.car_coloring = coloring;
+    }
+    
+    pub fn set_palette(&mut self, palette: CarPalette) {
+        self.palette = palette;
+    }
+    
+    pub fn show_queues(&self) -> bool {
+        self.show_queues
+    }
+    
+    pub fn set_show_queues(&mut self, show: bool) {
+        self.show_queues = show;
+    }
+    
+    pub fn perspective(&self) -> bool {
+        self.perspective
+    }
+    
+    /// Cars drawn in the last frame as shapes and as dots
+    pub fn drawn_cars(&self) -> DrawnCars {
+        self.drawn_cars
+    }
+    
+    /// Draw cars as boxes for the viewport's perspective camera
+    pub fn set_perspective(&mut self, perspective: bool) {
+        self.perspective = perspective;
+    }
+    
+    /// Layers added to whatever sits on top of the cars, to clear the boxes' roofs
+    fn roof_lift(&self) -> f32 {
+        if self.perspective {
+            CAR_HEIGHT / PERSPECTIVE_LAYER_SPACING
+        } else {
+            0.0
         }
-        
-        // Add merge zone indicators near entry points (yellow markings)
-        let merge_color = [0.9, 0.8, 0.2]; // Yellow for merge zones
-        let merge_zones = [0.0, 180.0]; // Degrees where entry points are located
-        
-        for &entry_angle in &merge_zones {
-            let merge_start_angle = (entry_angle - 15.0_f32).to_radians(); // 15 degrees before entry
-            let merge_end_angle = (entry_angle + 15.0_f32).to_radians(); // 15 degrees after entry
-            
-            // Add merge zone marking in lane 1 (where cars enter)
-            let merge_lane = 1;
-            let merge_radius = inner_radius + lane_width * merge_lane as f32;
-            
-            let merge_segments = 16;
-            for i in 0..merge_segments {
-                let t1 = i as f32 / merge_segments as f32;
-                let t2 = (i + 1) as f32 / merge_segments as f32;
-                let a1 = merge_start_angle + (merge_end_angle - merge_start_angle) * t1;
-                let a2 = merge_start_angle + (merge_end_angle - merge_start_angle) * t2;
-                
-                let inner_r = merge_radius - 0.3;
-                let outer_r = merge_radius + 0.3;
-                
-                let p1 = [inner_r * a1.cos(), inner_r * a1.sin(), 0.03];
-                let p2 = [outer_r * a1.cos(), outer_r * a1.sin(), 0.03];
-                let p3 = [inner_r * a2.cos(), inner_r * a2.sin(), 0.03];
-                let p4 = [outer_r * a2.cos(), outer_r * a2.sin(), 0.03];
-                
-                // Create chevron-like pattern for merge zones
-                if i % 4 < 2 { // Create dashed pattern
-                    vertices.push(Vertex { position: p1, color: merge_color });
-                    vertices.push(Vertex { position: p2, color: merge_color });
-                    vertices.push(Vertex { position: p3, color: merge_color });
-                    
-                    vertices.push(Vertex { position: p3, color: merge_color });
-                    vertices.push(Vertex { position: p2, color: merge_color });
-                    vertices.push(Vertex { position: p4, color: merge_color });
-                }
+    }
+    
+    /// Draw the cells of `noise` over the road from the next frame on, colored by level, or
+    /// none
+    pub fn set_noise_overlay(&mut self, noise: Option<&NoiseMap>) {
+        self.noise_cells.clear();
+        let Some(noise) = noise else {
+            return;
+        };
+        let size = noise.cell_size();
+        let scale = Matrix4::new_nonuniform_scaling(&nalgebra::Vector3::new(size, size, 1.0));
+        self.noise_cells.extend(noise.cells().into_iter().map(|cell| {
+            let translation = Matrix4::new_translation(&nalgebra::Vector3::new(cell.center.x, cell.center.y, NOISE_Z));
+            CarInstance {
+                transform: (translation * scale).into(),
+                color: noise_color(cell.level),
+                _padding: 0.0,
             }
-        }
-        
-        // Add solid white lines for lane boundaries
-        let solid_line_width = 0.2;
-        
-        // Inner boundary (solid white line)
-        Self::add_circular_line(&mut vertices, inner_radius, solid_line_width, white_color, 0.01, segments);
-        
-        // Outer boundary (solid white line)  
-        Self::add_circular_line(&mut vertices, outer_radius, solid_line_width, white_color, 0.01, segments);
-        
-        // Add entry points (green arrows/triangles at interior positions)
-        let entry_positions = [0.0, 180.0]; // degrees - entry_1 at 0°, entry_2 at 180°
-        let entry_color = [0.0, 0.8, 0.0]; // Bright green
-        
-        for &entry_angle in &entry_positions {
-            Self::add_entry_symbol(&mut vertices, entry_angle, inner_radius - 8.0, entry_color);
-        }
-        
-        // Add exit points (red arrows/triangles at exterior positions)  
-        let exit_positions = [90.0, 270.0]; // degrees - exit_1 at 90°, exit_2 at 270°
-        let exit_color = [0.8, 0.0, 0.0]; // Bright red
-        
-        for &exit_angle in &exit_positions {
-            Self::add_exit_symbol(&mut vertices, exit_angle, outer_radius + 8.0, exit_color);
-        }
-        
-        vertices
+        }));
     }
     
-    fn create_cloverleaf_road_vertices() -> Vec<Vertex> {
-        // Create realistic cloverleaf interchange with separated highways for right-side driving
-        let mut vertices = Vec::new();
-        
-        // Highway dimensions based on route2.toml and physics separation
-        let highway_width = 40.0;        // Width of each direction
-        let highway_extent = 300.0;      // How far highways extend from center
-        let loop_radius = 60.0;          // Radius of loop ramps
-        let lane_width = 3.5;            // Width per lane
-        let highway_half_width = highway_width / 2.0;
-        let lane_separation = highway_half_width + 5.0; // Same separation as physics
-        
-        // Highway colors
-        let highway_color = [0.2, 0.2, 0.2];      // Dark gray for main highways
-        let ramp_color = [0.25, 0.25, 0.25];     // Slightly lighter for ramps
-        let line_color = [0.8, 0.8, 0.8];        // White lane markings
-        
-        // =============================================================================
-        // SEPARATED HIGHWAYS - Right-side driving with clear separation
-        // =============================================================================
-        
-        // North-South Highway - Southbound (west side) and Northbound (east side)
-        // Southbound lanes (1-3) on west side
-        let ns_sb_left = -lane_separation - highway_half_width;
-        let ns_sb_right = -lane_separation + highway_half_width;
-        let ns_top = highway_extent;
-        let ns_bottom = -highway_extent;
-        
-        Self::add_rectangle(&mut vertices, ns_sb_left, ns_sb_right, ns_bottom, ns_top, highway_color);
-        
-        // Northbound lanes (4-6) on east side
-        let ns_nb_left = lane_separation - highway_half_width;
-        let ns_nb_right = lane_separation + highway_half_width;
-        
-        Self::add_rectangle(&mut vertices, ns_nb_left, ns_nb_right, ns_bottom, ns_top, highway_color);
-        
-        // East-West Highway - Westbound (north side) and Eastbound (south side)
-        let ew_left = -highway_extent;
-        let ew_right = highway_extent;
-        
-        // Westbound lanes (7-9) on north side
-        let ew_wb_bottom = lane_separation - highway_half_width;
-        let ew_wb_top = lane_separation + highway_half_width;
-        
-        Self::add_rectangle(&mut vertices, ew_left, ew_right, ew_wb_bottom, ew_wb_top, highway_color);
-        
-        // Eastbound lanes (10-12) on south side
-        let ew_eb_bottom = -lane_separation - highway_half_width;
-        let ew_eb_top = -lane_separation + highway_half_width;
-        
-        Self::add_rectangle(&mut vertices, ew_left, ew_right, ew_eb_bottom, ew_eb_top, highway_color);
-        
-        // =============================================================================
-        // CLOVERLEAF LOOP RAMPS - Four quarter-circle ramps in each quadrant
-        // =============================================================================
-        
-        // Calculate loop ramp positions (outside the highway intersection) 
-        // Match physics calculations for consistency
-        let loop_offset = highway_half_width + loop_radius;
-        
-        // Northeast Loop (for southbound→eastbound left turns)
-        let ne_center = (loop_offset, loop_offset);
-        Self::add_quarter_circle_ramp(&mut vertices, ne_center.0, ne_center.1, loop_radius, 180.0, ramp_color);
-        
-        // Southeast Loop (for eastbound→northbound left turns)
-        let se_center = (loop_offset, -loop_offset);
-        Self::add_quarter_circle_ramp(&mut vertices, se_center.0, se_center.1, loop_radius, 270.0, ramp_color);
-        
-        // Southwest Loop (for northbound→westbound left turns)
-        let sw_center = (-loop_offset, -loop_offset);
-        Self::add_quarter_circle_ramp(&mut vertices, sw_center.0, sw_center.1, loop_radius, 0.0, ramp_color);
-        
-        // Northwest Loop (for westbound→southbound left turns)
-        let nw_center = (-loop_offset, loop_offset);
-        Self::add_quarter_circle_ramp(&mut vertices, nw_center.0, nw_center.1, loop_radius, 90.0, ramp_color);
-        
-        // =============================================================================
-        // LANE MARKINGS - Dividing lines between lanes for separated highways
-        // =============================================================================
-        
-        let line_width = 0.2;
-        let line_z = 0.01; // Slightly above road surface
-        
-        // North-South Highway lane markings
-        // Southbound lanes (lanes 1-3) on west side
-        for lane in 1..3 { // 2 divider lines between 3 lanes
-            let x_pos = ns_sb_left + lane as f32 * lane_width;
-            Self::add_vertical_line_z(&mut vertices, ns_bottom, ns_top, x_pos, line_width, line_color, line_z);
-        }
-        
-        // Northbound lanes (lanes 4-6) on east side
-        for lane in 1..3 { // 2 divider lines between 3 lanes
-            let x_pos = ns_nb_left + lane as f32 * lane_width;
-            Self::add_vertical_line_z(&mut vertices, ns_bottom, ns_top, x_pos, line_width, line_color, line_z);
-        }
-        
-        // East-West Highway lane markings
-        // Westbound lanes (lanes 7-9) on north side
-        for lane in 1..3 { // 2 divider lines between 3 lanes
-            let y_pos = ew_wb_bottom + lane as f32 * lane_width;
-            Self::add_horizontal_line_z(&mut vertices, ew_left, ew_right, y_pos, line_width, line_color, line_z);
-        }
-        
-        // Eastbound lanes (lanes 10-12) on south side
-        for lane in 1..3 { // 2 divider lines between 3 lanes
-            let y_pos = ew_eb_bottom + lane as f32 * lane_width;
-            Self::add_horizontal_line_z(&mut vertices, ew_left, ew_right, y_pos, line_width, line_color, line_z);
-        }
-        
-        vertices
+    /// Rebuild the road mesh of `route` from `detail` times the usual segments, for this and
+    /// later routes
+    pub fn set_road_detail(&mut self, route: &RouteConfig, detail: f32) {
+        self.road_detail = detail;
+        self.set_route(route);
     }
     
-    fn add_rectangle(vertices: &mut Vec<Vertex>, left: f32, right: f32, bottom: f32, top: f32, color: [f32; 3]) {
-        // Add a rectangular road section using two triangles
-        vertices.extend_from_slice(&[
-            // First triangle
-            Vertex { position: [left, bottom, 0.0], color },
-            Vertex { position: [right, bottom, 0.0], color },
-            Vertex { position: [left, top, 0.0], color },
-            
-            // Second triangle
-            Vertex { position: [left, top, 0.0], color },
-            Vertex { position: [right, bottom, 0.0], color },
-            Vertex { position: [right, top, 0.0], color },
-        ]);
+    /// Rebuild the road mesh for a changed route
+    pub fn set_route(&mut self, route: &RouteConfig) {
+        self.geometry = route.route.geometry.clone();
+        let road_mesh = RoadMesh::from_route_with_detail(route, self.road_detail);
+        self.road_vertex_count = road_mesh.vertex_count() as u32;
+        self.road_vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Road Vertex Buffer"),
+            contents: bytemuck::cast_slice(road_mesh.vertices()),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
     }
     
-    fn add_quarter_circle_ramp(vertices: &mut Vec<Vertex>, center_x: f32, center_y: f32, radius: f32, start_angle_deg: f32, color: [f32; 3]) {
-        // Create a quarter-circle loop ramp (270° arc)
-        let segments = 20; // Number of segments for the quarter circle
-        let ramp_width = 7.0; // Width of the ramp
-        let start_angle = start_angle_deg.to_radians();
-        let arc_length = 3.0 * std::f32::consts::PI / 2.0; // 270 degrees
+    /// Car bodies, then heading indicators, noise map cells, queue highlights, turn signals and headlight
+    /// flashes, then signal heads, intersection signs and ramp meters, so later ones are
+    /// drawn on top. Cars out of view are left out,
+    /// and cars too small on screen for their shapes, or crowded out by many others, become dots.
+    /// Bodies that have not moved since `scene` was last uploaded keep their transforms.
+    fn create_instances(&mut self, state: &SimulationState, view_matrix: &Matrix4<f32>, width: u32, scene: &mut SceneInstances) {
+        self.palette.set_names(&state.names);
+        let detail = CarDetail::select(&state.cars, view_matrix, width);
+        self.drawn_cars = DrawnCars {
+            shapes: detail.shapes.len() as u32,
+            dots: detail.dots.len() as u32,
+            culled: detail.culled as u32,
+        };
         
-        for i in 0..segments {
-            let angle1 = start_angle + (i as f32) * arc_length / (segments as f32);
-            let angle2 = start_angle + ((i + 1) as f32) * arc_length / (segments as f32);
-            
-            // Inner edge of ramp
-            let inner_radius = radius - ramp_width / 2.0;
-            let inner1 = [center_x + inner_radius * angle1.cos(), center_y + inner_radius * angle1.sin(), 0.0];
-            let inner2 = [center_x + inner_radius * angle2.cos(), center_y + inner_radius * angle2.sin(), 0.0];
-            
-            // Outer edge of ramp
-            let outer_radius = radius + ramp_width / 2.0;
-            let outer1 = [center_x + outer_radius * angle1.cos(), center_y + outer_radius * angle1.sin(), 0.0];
-            let outer2 = [center_x + outer_radius * angle2.cos(), center_y + outer_radius * angle2.sin(), 0.0];
-            
-            // Create two triangles for this segment
-            vertices.extend_from_slice(&[
-                Vertex { position: inner1, color },
-                Vertex { position: outer1, color },
-                Vertex { position: inner2, color },
-                
-                Vertex { position: inner2, color },
-                Vertex { position: outer1, color },
-                Vertex { position: outer2, color },
-            ]);
+        let lift = self.roof_lift();
+        let height = self.body_height();
+        let instances = &mut scene.instances;
+        instances.clear();
+        for (i, car) in detail.shapes.iter().enumerate() {
+            let pose = BodyPose::of(car, height);
+            let transform = match (scene.bodies.get(i), scene.uploaded.get(i)) {
+                (Some(known), Some(uploaded)) if *known == pose => uploaded.transform,
+                _ => self.car_transform(car),
+            };
+            instances.push(CarInstance {
+                transform,
+                color: self.car_color(car, state.time),
+                _padding: 0.0,
+            });
+            match scene.bodies.get_mut(i) {
+                Some(known) => *known = pose,
+                None => scene.bodies.push(pose),
+            }
         }
+        scene.bodies.truncate(detail.shapes.len());
+        scene.body_count = instances.len() as u32;
+        if self.show_heading_indicators {
+            instances.extend(detail.shapes.iter().map(|car| Self::create_heading_instance(car, lift)));
+        }
+        instances.extend(self.noise_cells.iter().copied());
+        if self.show_queues {
+            instances.extend(state.queues.iter().flat_map(|queue| self.create_queue_instances(queue)));
+        }
+        instances.extend(detail.shapes.iter().filter_map(|car| Self::create_turn_signal_instance(car, state.time, lift)));
+        instances.extend(detail.shapes.iter().filter_map(|car| Self::create_headlight_flash_instance(car, state.time, lift)));
+        instances.extend(state.signals.iter().map(|signal| Self::create_signal_instance(signal, lift)));
+        instances.extend(state.intersections.iter()
+            .flat_map(|intersection| &intersection.signs)
+            .map(|sign| Self::create_sign_instance(sign, lift)));
+        instances.extend(state.ramp_meters.iter().map(|meter| Self::create_ramp_meter_instance(meter, lift)));
+        
+        scene.dots.clear();
+        scene.dots.extend(detail.dots.iter().map(|(car, pixels_per_meter)| DotInstance {
+            position: [car.position.x, car.position.y, CAR_Z],
+            size: car.width.max(MIN_DOT_PIXELS / pixels_per_meter),
+            color: self.car_color(car, state.time),
+            _padding: 0.0,
+        }));
     }
     
-    fn add_horizontal_line_z(vertices: &mut Vec<Vertex>, x1: f32, x2: f32, y: f32, width: f32, color: [f32; 3], z: f32) {
-        let half_width = width / 2.0;
-        vertices.extend_from_slice(&[
-            Vertex { position: [x1, y - half_width, z], color },
-            Vertex { position: [x2, y - half_width, z], color },
-            Vertex { position: [x1, y + half_width, z], color },
-            
-            Vertex { position: [x1, y + half_width, z], color },
-            Vertex { position: [x2, y - half_width, z], color },
-            Vertex { position: [x2, y + half_width, z], color },
-        ]);
+    /// Height the unit box of a car body is stretched to, up to the car's roof
+    fn body_height(&self) -> f32 {
+        if self.perspective { self.roof_lift() } else { 1.0 }
     }
     
-    fn add_vertical_line_z(vertices: &mut Vec<Vertex>, y1: f32, y2: f32, x: f32, width: f32, color: [f32; 3], z: f32) {
-        let half_width = width / 2.0;
-        vertices.extend_from_slice(&[
-            Vertex { position: [x - half_width, y1, z], color },
-            Vertex { position: [x + half_width, y1, z], color },
-            Vertex { position: [x - half_width, y2, z], color },
-            
-            Vertex { position: [x - half_width, y2, z], color },
-            Vertex { position: [x + half_width, y1, z], color },
-            Vertex { position: [x + half_width, y2, z], color },
-        ]);
+    fn car_transform(&self, car: &Car) -> [[f32; 4]; 4] {
+        // The unit square is stretched to the car's footprint, length along its heading, and
+        // the box up to the car's roof
+        let scale = Matrix4::new_nonuniform_scaling(&nalgebra::Vector3::new(car.length, car.width, self.body_height()));
+        let rotation = Matrix4::from_euler_angles(0.0, 0.0, car.heading);
+        let translation = Matrix4::new_translation(&nalgebra::Vector3::new(car.position.x, car.position.y, CAR_Z));
+        (translation * rotation * scale).into()
     }
     
-    fn add_loop_ramp(vertices: &mut Vec<Vertex>, center_x: f32, center_y: f32, radius: f32, color: [f32; 3]) {
-        let segments = 32;
-        let ramp_width = 10.0; // Width of the loop ramp
+    fn car_color(&self, car: &Car, time: f32) -> [f32; 3] {
+        // Color by lane, or by behavior or car type as cars.toml says
+        let color = match self.car_coloring {
+            CarColoring::Lane => lane_color(car.current_lane),
+            CarColoring::Palette => self.palette.color(car),
+        };
         
-        for i in 0..segments {
-            let angle1 = (i as f32) * 2.0 * std::f32::consts::PI / (segments as f32);
-            let angle2 = ((i + 1) as f32) * 2.0 * std::f32::consts::PI / (segments as f32);
-            
-            // Inner circle
-            let inner_radius = radius - ramp_width / 2.0;
-            let inner1 = [center_x + inner_radius * angle1.cos(), center_y + inner_radius * angle1.sin(), 0.0];
-            let inner2 = [center_x + inner_radius * angle2.cos(), center_y + inner_radius * angle2.sin(), 0.0];
-            
-            // Outer circle  
-            let outer_radius = radius + ramp_width / 2.0;
-            let outer1 = [center_x + outer_radius * angle1.cos(), center_y + outer_radius * angle1.sin(), 0.0];
-            let outer2 = [center_x + outer_radius * angle2.cos(), center_y + outer_radius * angle2.sin(), 0.0];
-            
-            // Create two triangles for this segment
-            vertices.extend_from_slice(&[
-                Vertex { position: inner1, color },
-                Vertex { position: outer1, color },
-                Vertex { position: inner2, color },
-                
-                Vertex { position: inner2, color },
-                Vertex { position: outer1, color },
-                Vertex { position: outer2, color },
-            ]);
+        // Flash white for a couple of seconds after a collision, crashed cars stay dark,
+        // broken-down cars flash their hazard lights amber and cars in a safety conflict
+        // flash red for a second after it
+        let flash_duration = 2.0;
+        let conflict_duration = 1.0;
+        match (car.last_collision_time, &car.breakdown) {
+            (Some(collision_time), _) if time - collision_time < flash_duration && ((time - collision_time) * 8.0) as i32 % 2 == 0 => [1.0, 1.0, 1.0],
+            _ if car.crashed => [0.3, 0.3, 0.3],
+            (_, Some(breakdown)) if ((time - breakdown.start_time) * 3.0) as i32 % 2 == 0 => [1.0, 0.55, 0.0],
+            (_, Some(_)) => [0.25, 0.15, 0.0],
+            _ if car.last_conflict_time.is_some_and(|conflict_time| time - conflict_time < conflict_duration && ((time - conflict_time) * 6.0) as i32 % 2 == 0) => [1.0, 0.1, 0.1],
+            _ => color,
         }
     }
     
-    fn add_horizontal_line(vertices: &mut Vec<Vertex>, x1: f32, x2: f32, y: f32, width: f32, color: [f32; 3]) {
-        let half_width = width / 2.0;
-        vertices.extend_from_slice(&[
-            Vertex { position: [x1, y - half_width, 0.01], color },
-            Vertex { position: [x2, y - half_width, 0.01], color },
-            Vertex { position: [x1, y + half_width, 0.01], color },
-            
-            Vertex { position: [x1, y + half_width, 0.01], color },
-            Vertex { position: [x2, y - half_width, 0.01], color },
-            Vertex { position: [x2, y + half_width, 0.01], color },
-        ]);
+    fn create_queue_instances(&self, queue: &Queue) -> Vec<CarInstance> {
+        // Band along the middle of the queue's lane, from behind the last car to past the
+        // front one, in short straight pieces
+        let Some(road) = road_length(&self.geometry) else {
+            return Vec::new();
+        };
+        let geometry = &self.geometry;
+        let middle_radius = road / std::f32::consts::TAU;
+        let lane_radius = geometry.inner_radius + geometry.lane_width * (queue.lane as f32 - 0.5);
+        let margin = 3.0;
+        let start = (queue.tail - margin) / middle_radius;
+        let span = (queue.length + 2.0 * margin) / middle_radius;
+        let segments = (span * lane_radius / QUEUE_SEGMENT).ceil().max(1.0) as usize;
+        let step = span / segments as f32;
+        let scale = Matrix4::new_nonuniform_scaling(&nalgebra::Vector3::new(step * lane_radius * 1.05, geometry.lane_width * 0.8, 1.0));
+        (0..segments).map(|segment| {
+            let angle = start + step * (segment as f32 + 0.5);
+            let x = geometry.center_x + lane_radius * angle.cos();
+            let y = geometry.center_y + lane_radius * angle.sin();
+            let rotation = Matrix4::from_euler_angles(0.0, 0.0, angle + std::f32::consts::FRAC_PI_2);
+            let translation = Matrix4::new_translation(&nalgebra::Vector3::new(x, y, QUEUE_Z));
+            CarInstance {
+                transform: (translation * rotation * scale).into(),
+                color: QUEUE_COLOR,
+                _padding: 0.0,
+            }
+        }).collect()
     }
     
-    fn add_vertical_line(vertices: &mut Vec<Vertex>, y1: f32, y2: f32, x: f32, width: f32, color: [f32; 3]) {
-        let half_width = width / 2.0;
-        vertices.extend_from_slice(&[
-            Vertex { position: [x - half_width, y1, 0.01], color },
-            Vertex { position: [x + half_width, y1, 0.01], color },
-            Vertex { position: [x - half_width, y2, 0.01], color },
-            
-            Vertex { position: [x - half_width, y2, 0.01], color },
-            Vertex { position: [x + half_width, y1, 0.01], color },
-            Vertex { position: [x + half_width, y2, 0.01], color },
-        ]);
+    fn create_heading_instance(car: &Car, lift: f32) -> CarInstance {
+        // Dark windshield strip across the front quarter of the car
+        let front = nalgebra::Vector2::new(car.heading.cos(), car.heading.sin()) * (car.length * 0.25);
+        let scale = Matrix4::new_nonuniform_scaling(&nalgebra::Vector3::new(car.length * 0.15, car.width * 0.8, 1.0));
+        let rotation = Matrix4::from_euler_angles(0.0, 0.0, car.heading);
+        let translation = Matrix4::new_translation(&nalgebra::Vector3::new(car.position.x + front.x, car.position.y + front.y, CAR_DETAIL_Z + lift));
+        
+        CarInstance {
+            transform: (translation * rotation * scale).into(),
+            color: [0.05, 0.05, 0.1],
+            _padding: 0.0,
+        }
     }
     
-    fn add_circular_line(vertices: &mut Vec<Vertex>, radius: f32, width: f32, color: [f32; 3], z: f32, segments: usize) {
-        for i in 0..segments {
-            let angle1 = (i as f32) * 2.0 * std::f32::consts::PI / (segments as f32);
-            let angle2 = ((i + 1) as f32) * 2.0 * std::f32::consts::PI / (segments as f32);
-            
-            let inner_r = radius - width * 0.5;
-            let outer_r = radius + width * 0.5;
-            
-            let inner1 = [inner_r * angle1.cos(), inner_r * angle1.sin(), z];
-            let inner2 = [inner_r * angle2.cos(), inner_r * angle2.sin(), z];
-            let outer1 = [outer_r * angle1.cos(), outer_r * angle1.sin(), z];
-            let outer2 = [outer_r * angle2.cos(), outer_r * angle2.sin(), z];
-            
-            // Two triangles for each segment
-            vertices.push(Vertex { position: inner1, color });
-            vertices.push(Vertex { position: outer1, color });
-            vertices.push(Vertex { position: inner2, color });
-            
-            vertices.push(Vertex { position: inner2, color });
-            vertices.push(Vertex { position: outer1, color });
-            vertices.push(Vertex { position: outer2, color });
+    fn create_turn_signal_instance(car: &Car, time: f32, lift: f32) -> Option<CarInstance> {
+        // Amber light on the front corner of the signalled side, flashing at 1.5 Hz
+        let side = match car.turn_signal? {
+            TurnSignal::Left => 1.0,
+            TurnSignal::Right => -1.0,
+        };
+        if (time * 3.0) as i32 % 2 != 0 {
+            return None;
         }
+        let forward = nalgebra::Vector2::new(car.heading.cos(), car.heading.sin());
+        let left = nalgebra::Vector2::new(-forward.y, forward.x);
+        let offset = forward * (car.length * 0.4) + left * (side * car.width * 0.5);
+        let scale = Matrix4::new_nonuniform_scaling(&nalgebra::Vector3::new(1.0, 0.8, 1.0));
+        let rotation = Matrix4::from_euler_angles(0.0, 0.0, car.heading);
+        let translation = Matrix4::new_translation(&nalgebra::Vector3::new(car.position.x + offset.x, car.position.y + offset.y, CAR_DETAIL_Z + lift));
+        
+        Some(CarInstance {
+            transform: (translation * rotation * scale).into(),
+            color: [1.0, 0.65, 0.0],
+            _padding: 0.0,
+        })
     }
     
-    fn add_entry_symbol(vertices: &mut Vec<Vertex>, angle_deg: f32, radius: f32, color: [f32; 3]) {
-        let angle_rad = angle_deg.to_radians();
-        let center_x = radius * angle_rad.cos();
-        let center_y = radius * angle_rad.sin();
-        
-        // Create much larger, simpler triangle pointing inward
-        let size = 15.0; // Much larger
-        let arrow_angle = angle_rad + std::f32::consts::PI; // Point inward
-        
-        // Arrow tip
-        let tip_x = center_x + size * arrow_angle.cos();
-        let tip_y = center_y + size * arrow_angle.sin();
-        
-        // Arrow base points - spread them wider
-        let base_angle1 = arrow_angle + 0.8; // Narrower angle
-        let base_angle2 = arrow_angle - 0.8;
-        let base_x1 = center_x + (size * 0.4) * base_angle1.cos();
-        let base_y1 = center_y + (size * 0.4) * base_angle1.sin();
-        let base_x2 = center_x + (size * 0.4) * base_angle2.cos();
-        let base_y2 = center_y + (size * 0.4) * base_angle2.sin();
+    fn create_headlight_flash_instance(car: &Car, time: f32, lift: f32) -> Option<CarInstance> {
+        // White bar across the front of a tailgating car, flashing the car ahead at 2 Hz
+        if !car.behavior.tailgating || (time * 4.0) as i32 % 2 != 0 {
+            return None;
+        }
+        let front = nalgebra::Vector2::new(car.heading.cos(), car.heading.sin()) * (car.length * 0.45);
+        let scale = Matrix4::new_nonuniform_scaling(&nalgebra::Vector3::new(0.6, car.width, 1.0));
+        let rotation = Matrix4::from_euler_angles(0.0, 0.0, car.heading);
+        let translation = Matrix4::new_translation(&nalgebra::Vector3::new(car.position.x + front.x, car.position.y + front.y, CAR_DETAIL_Z + lift));
         
-        // Create triangle for arrow - positioned higher above road
-        vertices.push(Vertex { position: [tip_x, tip_y, 0.1], color });
-        vertices.push(Vertex { position: [base_x1, base_y1, 0.1], color });
-        vertices.push(Vertex { position: [base_x2, base_y2, 0.1], color });
+        Some(CarInstance {
+            transform: (translation * rotation * scale).into(),
+            color: [1.0, 1.0, 0.9],
+            _padding: 0.0,
+        })
     }
     
-    fn add_exit_symbol(vertices: &mut Vec<Vertex>, angle_deg: f32, radius: f32, color: [f32; 3]) {
-        let angle_rad = angle_deg.to_radians();
-        let center_x = radius * angle_rad.cos();
-        let center_y = radius * angle_rad.sin();
-        
-        // Create much larger arrow pointing outward (away from the highway)
-        let size = 15.0; // Much larger
-        let arrow_angle = angle_rad; // Point outward
-        
-        // Arrow tip
-        let tip_x = center_x + size * arrow_angle.cos();
-        let tip_y = center_y + size * arrow_angle.sin();
-        
-        // Arrow base points - spread them wider
-        let base_angle1 = arrow_angle + 0.8; // Narrower angle
-        let base_angle2 = arrow_angle - 0.8;
-        let base_x1 = center_x + (size * 0.4) * base_angle1.cos();
-        let base_y1 = center_y + (size * 0.4) * base_angle1.sin();
-        let base_x2 = center_x + (size * 0.4) * base_angle2.cos();
-        let base_y2 = center_y + (size * 0.4) * base_angle2.sin();
+    fn create_signal_instance(signal: &SignalState, lift: f32) -> CarInstance {
+        // Signal heads are larger squares colored by their current phase
+        let head_size = 5.0;
+        let scale = Matrix4::new_nonuniform_scaling(&nalgebra::Vector3::new(head_size, head_size, 1.0));
+        let rotation = Matrix4::from_euler_angles(0.0, 0.0, signal.heading);
+        let translation = Matrix4::new_translation(&nalgebra::Vector3::new(signal.position.x, signal.position.y, SIGNAL_Z + lift));
+        
+        let color = match signal.phase {
+            SignalPhase::Green => [0.1, 0.9, 0.2],
+            SignalPhase::Yellow => [1.0, 0.85, 0.0],
+            SignalPhase::Red => [0.95, 0.1, 0.1],
+        };
         
-        // Create triangle for arrow - positioned higher above road
-        vertices.push(Vertex { position: [tip_x, tip_y, 0.1], color });
-        vertices.push(Vertex { position: [base_x1, base_y1, 0.1], color });
-        vertices.push(Vertex { position: [base_x2, base_y2, 0.1], color });
+        CarInstance {
+            transform: (translation * rotation * scale).into(),
+            color,
+            _padding: 0.0,
+        }
     }
     
-    fn create_car_instance(&self, car: &Car) -> CarInstance {
-        // Create transformation matrix with uniform scaling for 1:1 square cars
-        let car_size = 3.0; // Fixed size for all cars to ensure consistent 1:1 squares
-        let scale = Matrix4::new_nonuniform_scaling(&nalgebra::Vector3::new(car_size, car_size, 1.0));
-        let rotation = Matrix4::from_euler_angles(0.0, 0.0, car.heading);
-        let translation = Matrix4::new_translation(&nalgebra::Vector3::new(car.position.x, car.position.y, 0.0));
-        
-        let transform = translation * rotation * scale;
-        let transform_array: [[f32; 4]; 4] = transform.into();
+    fn create_sign_instance(sign: &IntersectionSign, lift: f32) -> CarInstance {
+        // Stop signs are red squares, yield signs white squares turned on their corner
+        let sign_size = 3.0;
+        let (angle, color) = match sign.sign {
+            SignType::Stop => (sign.heading, [0.85, 0.05, 0.05]),
+            SignType::Yield => (sign.heading + std::f32::consts::FRAC_PI_4, [0.95, 0.95, 0.95]),
+        };
+        let scale = Matrix4::new_nonuniform_scaling(&nalgebra::Vector3::new(sign_size, sign_size, 1.0));
+        let rotation = Matrix4::from_euler_angles(0.0, 0.0, angle);
+        let translation = Matrix4::new_translation(&nalgebra::Vector3::new(sign.position.x, sign.position.y, SIGNAL_Z + lift));
         
-        // Color based on driving behavior type - make colors very distinct
-        let color = match car.behavior_type.as_str() {
-            "aggressive" => [1.0, 0.0, 0.0],    // Pure red for aggressive drivers
-            "normal" => [0.0, 0.5, 1.0],        // Pure blue for normal drivers  
-            "cautious" => [0.0, 1.0, 0.0],      // Pure green for cautious drivers
-            "erratic" => [1.0, 0.7, 0.0],       // Pure orange for erratic drivers
-            "strategic" => [1.0, 0.0, 1.0],     // Pure magenta for strategic drivers
-            _ => [0.8, 0.8, 0.8],                // Light gray for unknown behavior
+        CarInstance {
+            transform: (translation * rotation * scale).into(),
+            color,
+            _padding: 0.0,
+        }
+    }
+    
+    fn create_ramp_meter_instance(meter: &RampMeterState, lift: f32) -> CarInstance {
+        // Ramp meters are smaller than signal heads and only flash green to let a car go
+        let light_size = 3.0;
+        let scale = Matrix4::new_nonuniform_scaling(&nalgebra::Vector3::new(light_size, light_size, 1.0));
+        let translation = Matrix4::new_translation(&nalgebra::Vector3::new(meter.position.x, meter.position.y, SIGNAL_Z + lift));
+        
+        let color = if meter.green {
+            [0.1, 0.9, 0.2]
+        } else {
+            [0.95, 0.1, 0.1]
         };
         
         CarInstance {
-            transform: transform_array,
+            transform: (translation * scale).into(),
             color,
             _padding: 0.0,
         }
@@ -0,0 +1,337 @@
+use crate::config::{RouteConfig, RouteGeometry};
+use crate::simulation::{grid_cell_center, cell_char, ExitRamps, Point, TrafficManager, ROUNDABOUT_CENTER};
+use super::renderer::{Vertex, ROAD_Z, LANE_LINE_Z, MERGE_LINE_Z, MARKER_Z};
+use nalgebra::{Point2, Vector2};
+use std::f32::consts::{PI, TAU};
+
+const ROAD_COLOR: [f32; 3] = [0.2, 0.2, 0.2];
+const ALT_LANE_COLOR: [f32; 3] = [0.23, 0.23, 0.23]; // Every other donut lane, for visibility
+const RAMP_COLOR: [f32; 3] = [0.25, 0.25, 0.25];
+const ISLAND_COLOR: [f32; 3] = [0.15, 0.3, 0.15];
+const LINE_COLOR: [f32; 3] = [0.95, 0.95, 0.95];
+const MERGE_COLOR: [f32; 3] = [0.9, 0.8, 0.2];
+const ENTRY_COLOR: [f32; 3] = [0.0, 0.8, 0.0];
+const EXIT_COLOR: [f32; 3] = [0.8, 0.0, 0.0];
+const CLOSURE_COLOR: [f32; 3] = [1.0, 0.45, 0.0];
+
+const LINE_WIDTH: f32 = 0.2;
+const DASH_LENGTH: f32 = 3.0;  // meters
+const DASH_SPACING: f32 = 6.0; // meters
+const MARKER_SIZE: f32 = 15.0; // meters, length of entry/exit arrows
+const MARKER_OFFSET: f32 = 8.0; // meters between the road edge and exit arrows
+const HATCH_SPACING: f32 = 3.0; // meters between stripes across closed lanes
+const HATCH_WIDTH: f32 = 0.8;   // meters
+const CONE_SPACING: f32 = 6.0;  // meters between cones along closed lanes
+const CONE_SIZE: f32 = 0.6;     // meters
+
+const RING_SEGMENTS: usize = 64;
+const CLOVERLEAF_EXTENT: f32 = 300.0; // meters from center when highway_length is not set
+const LOOP_ARC: f32 = 3.0 * PI / 2.0; // loop ramps turn 270 degrees
+
+/// Triangle list for the static road surface, markings and entry/exit arrows of a route.
+///
+/// Built from the same geometry the simulation drives on, so lane counts, radii and
+/// marker positions always follow route.toml.
+pub struct RoadMesh {
+    vertices: Vec<Vertex>,
+    detail: f32, // fraction of the usual segments curves are built from
+}
+
+impl RoadMesh {
+    pub fn from_route(route: &RouteConfig) -> Self {
+        Self::from_route_with_detail(route, 1.0)
+    }
+    
+    /// Build curves from `detail` times the usual number of segments, at least one each
+    pub fn from_route_with_detail(route: &RouteConfig, detail: f32) -> Self {
+        let mut mesh = Self { vertices: Vec::new(), detail };
+        let geometry = &route.route.geometry;
+        
+        match geometry.geometry_type.as_str() {
+            "cloverleaf" => mesh.add_cloverleaf(route),
+            "grid" => mesh.add_grid(route),
+            "donut" => mesh.add_donut(route),
+            _ => {
+                log::warn!("Unknown geometry type '{}', drawing a donut", geometry.geometry_type);
+                mesh.add_donut(route);
+            }
+        }
+        
+        // Entry arrows sit where cars appear and point the way they start driving
+        for entry in &route.route.entries {
+            let (position, heading) = TrafficManager::entry_pose(entry, geometry);
+            mesh.add_arrow(position, heading, ENTRY_COLOR);
+        }
+        
+        mesh
+    }
+    
+    pub(crate) fn vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+    
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+    
+    fn segments(&self, usual: usize) -> usize {
+        ((usual as f32 * self.detail).round() as usize).max(1)
+    }
+    
+    fn add_donut(&mut self, route: &RouteConfig) {
+        let geometry = &route.route.geometry;
+        let center = Point2::new(geometry.center_x, geometry.center_y);
+        let lane_width = geometry.lane_width;
+        let lane_count = geometry.lane_count.max(1);
+        let inner_edge = geometry.inner_radius;
+        let outer_edge = inner_edge + lane_count as f32 * lane_width;
+        let ring_segments = self.segments(RING_SEGMENTS);
+        let dash_segments = self.segments(8);
+        
+        for lane in 0..lane_count {
+            let color = if lane % 2 == 0 { ROAD_COLOR } else { ALT_LANE_COLOR };
+            let inner = inner_edge + lane as f32 * lane_width;
+            self.add_annulus_sector(center, inner, inner + lane_width, 0.0, TAU, ROAD_Z, color, ring_segments);
+        }
+        
+        // Dashed lines between lanes, solid lines along both edges
+        for lane in 1..lane_count {
+            let radius = inner_edge + lane as f32 * lane_width;
+            let dash_cycle = DASH_LENGTH + DASH_SPACING;
+            let dashes = (TAU * radius / dash_cycle) as usize;
+            for dash in 0..dashes {
+                let start = dash as f32 * dash_cycle / radius;
+                self.add_arc_line(center, radius, start, start + DASH_LENGTH / radius, LINE_WIDTH, LINE_COLOR, LANE_LINE_Z, dash_segments);
+            }
+        }
+        self.add_arc_line(center, inner_edge, 0.0, TAU, LINE_WIDTH, LINE_COLOR, LANE_LINE_Z, ring_segments);
+        self.add_arc_line(center, outer_edge, 0.0, TAU, LINE_WIDTH, LINE_COLOR, LANE_LINE_Z, ring_segments);
+        
+        // Yellow dashes along the merge lane, starting at the entry in the direction of travel
+        for entry in &route.route.entries {
+            let radius = inner_edge + (entry.lane.clamp(1, lane_count) as f32 - 0.5) * lane_width;
+            let start = entry.angle.to_radians();
+            let sweep = entry.merge_distance.max(0.0) / radius;
+            let segments = 16;
+            for i in (0..segments).filter(|i| i % 4 < 2) {
+                let a1 = start + sweep * i as f32 / segments as f32;
+                let a2 = start + sweep * (i + 1) as f32 / segments as f32;
+                self.add_arc_line(center, radius, a1, a2, 0.6, MERGE_COLOR, MERGE_LINE_Z, 1);
+            }
+        }
+        
+        // Off-ramps one lane wide, with exit arrows where cars leave at their ends
+        for ramp in ExitRamps::from_route(route).ramps() {
+            for pair in ramp.points().windows(2) {
+                // Overlap the segments slightly so the bends have no gaps
+                let along = (pair[1] - pair[0]).normalize() * (LINE_WIDTH / 2.0);
+                self.add_line(pair[0] - along, pair[1] + along, lane_width, RAMP_COLOR, ROAD_Z);
+            }
+            let (end, heading) = ramp.pose_at(ramp.length());
+            self.add_arrow(end + Vector2::new(heading.cos(), heading.sin()) * MARKER_OFFSET, heading, EXIT_COLOR);
+        }
+        
+        // Closed lanes get diagonal hatching with a row of cones along both of their edges
+        for closure in &route.route.closures {
+            let lane = closure.lane.clamp(1, lane_count);
+            let inner = inner_edge + (lane - 1) as f32 * lane_width;
+            let outer = inner + lane_width;
+            let radius = inner + lane_width / 2.0;
+            let start = closure.start_angle.to_radians();
+            let sweep = closure.span();
+            let at = |radius: f32, angle: f32| center + Vector2::new(angle.cos(), angle.sin()) * radius;
+            
+            let stripes = (sweep * radius / HATCH_SPACING) as usize;
+            for stripe in 0..stripes {
+                // Each stripe leans forward by a lane width as it crosses the lane
+                let a = start + stripe as f32 * HATCH_SPACING / radius;
+                let (lean, width) = (lane_width / radius, HATCH_WIDTH / radius);
+                if a + lean + width > start + sweep {
+                    break;
+                }
+                self.add_quad(at(inner, a), at(inner, a + width), at(outer, a + lean), at(outer, a + lean + width), MERGE_LINE_Z, CLOSURE_COLOR);
+            }
+            
+            let cones = (sweep * radius / CONE_SPACING) as usize;
+            for cone in 0..=cones {
+                let a = start + sweep * cone as f32 / cones.max(1) as f32;
+                for edge in [inner + CONE_SIZE, outer - CONE_SIZE] {
+                    let position = at(edge, a);
+                    let along = Vector2::new(-a.sin(), a.cos()) * (CONE_SIZE / 2.0);
+                    self.add_line(position - along, position + along, CONE_SIZE, CLOSURE_COLOR, MARKER_Z);
+                }
+            }
+        }
+    }
+    
+    fn add_cloverleaf(&mut self, route: &RouteConfig) {
+        let geometry = &route.route.geometry;
+        let extent = geometry.highway_length.map(|length| length / 2.0).unwrap_or(CLOVERLEAF_EXTENT);
+        let highway_half_width = geometry.highway_width.unwrap_or(40.0) / 2.0;
+        let loop_radius = geometry.loop_radius.unwrap_or(60.0);
+        let ramp_width = geometry.ramp_width.unwrap_or(7.0);
+        let loop_segments = self.segments(20);
+        let lanes = cloverleaf_lanes_per_direction(geometry);
+        let half_carriageway = lanes as f32 * geometry.lane_width / 2.0;
+        
+        // One carriageway per direction of travel, centered where physics places its middle lane
+        for &(x, y, heading) in &cloverleaf_carriageways(geometry) {
+            let along = Vector2::new(heading.cos(), heading.sin());
+            let across = Vector2::new(-along.y, along.x);
+            let middle = Point2::new(x, y);
+            let start = middle - along * extent;
+            let end = middle + along * extent;
+            self.add_quad(
+                start - across * half_carriageway, end - across * half_carriageway,
+                start + across * half_carriageway, end + across * half_carriageway,
+                ROAD_Z, ROAD_COLOR,
+            );
+            for divider in 1..lanes {
+                let offset = divider as f32 * geometry.lane_width - half_carriageway;
+                self.add_line(start + across * offset, end + across * offset, LINE_WIDTH, LINE_COLOR, LANE_LINE_Z);
+            }
+        }
+        
+        // Loop ramps in each quadrant, outside the highway intersection
+        let loop_offset = highway_half_width + loop_radius;
+        let loops = [
+            (loop_offset, loop_offset, 180.0_f32),   // Northeast
+            (loop_offset, -loop_offset, 270.0),      // Southeast
+            (-loop_offset, -loop_offset, 0.0),       // Southwest
+            (-loop_offset, loop_offset, 90.0),       // Northwest
+        ];
+        for &(x, y, start_deg) in &loops {
+            let center = Point2::new(x, y);
+            let start = start_deg.to_radians();
+            self.add_annulus_sector(center, loop_radius - ramp_width / 2.0, loop_radius + ramp_width / 2.0, start, start + LOOP_ARC, ROAD_Z, RAMP_COLOR, loop_segments);
+        }
+        
+        // Exits trigger where the exit lane crosses the exit angle around the origin
+        for exit in &route.route.exits {
+            let Some((point, heading)) = cloverleaf_lane_crossing(geometry, exit.lane, exit.angle) else {
+                log::debug!("Exit {} is never reached by lane {}, not drawing its marker", exit.id, exit.lane);
+                continue;
+            };
+            let direction = Vector2::new(heading.cos(), heading.sin());
+            self.add_arrow(point + direction * MARKER_OFFSET, heading, EXIT_COLOR);
+        }
+    }
+    
+    fn add_grid(&mut self, route: &RouteConfig) {
+        let geometry = &route.route.geometry;
+        let Some(grid) = geometry.grid.as_ref() else {
+            return;
+        };
+        let half_cell = geometry.cell_size.unwrap_or(20.0) / 2.0;
+        
+        for (row, cols) in grid.iter().enumerate() {
+            for col in 0..cols.len() {
+                let color = match cell_char(grid, row, col) {
+                    Some(ROUNDABOUT_CENTER) => ISLAND_COLOR,
+                    Some(_) => ROAD_COLOR,
+                    None => continue,
+                };
+                let c = grid_cell_center(geometry, row, col);
+                self.add_quad(
+                    Point2::new(c.x - half_cell, c.y - half_cell), Point2::new(c.x + half_cell, c.y - half_cell),
+                    Point2::new(c.x - half_cell, c.y + half_cell), Point2::new(c.x + half_cell, c.y + half_cell),
+                    ROAD_Z, color,
+                );
+            }
+        }
+        
+        // Exit arrows on the exit cells, pointing away from the grid center
+        let center = Point2::new(geometry.center_x, geometry.center_y);
+        for exit in geometry.exit_points.iter().flatten() {
+            let position = grid_cell_center(geometry, exit.row, exit.col);
+            let outward = position - center;
+            let heading = if outward.magnitude() > 1e-3 { outward.y.atan2(outward.x) } else { 0.0 };
+            self.add_arrow(position, heading, EXIT_COLOR);
+        }
+    }
+    
+    /// Ring section between two radii, swept counter-clockwise from `start` to `end` (radians)
+    #[allow(clippy::too_many_arguments)]
+    fn add_annulus_sector(&mut self, center: Point, inner: f32, outer: f32, start: f32, end: f32, z: f32, color: [f32; 3], segments: usize) {
+        for i in 0..segments {
+            let a1 = start + (end - start) * i as f32 / segments as f32;
+            let a2 = start + (end - start) * (i + 1) as f32 / segments as f32;
+            let (d1, d2) = (Vector2::new(a1.cos(), a1.sin()), Vector2::new(a2.cos(), a2.sin()));
+            self.add_quad(center + d1 * inner, center + d2 * inner, center + d1 * outer, center + d2 * outer, z, color);
+        }
+    }
+    
+    #[allow(clippy::too_many_arguments)]
+    fn add_arc_line(&mut self, center: Point, radius: f32, start: f32, end: f32, width: f32, color: [f32; 3], z: f32, segments: usize) {
+        self.add_annulus_sector(center, radius - width / 2.0, radius + width / 2.0, start, end, z, color, segments);
+    }
+    
+    fn add_line(&mut self, from: Point, to: Point, width: f32, color: [f32; 3], z: f32) {
+        let along = (to - from).normalize();
+        let across = Vector2::new(-along.y, along.x) * (width / 2.0);
+        self.add_quad(from - across, to - across, from + across, to + across, z, color);
+    }
+    
+    /// Quad from its four corners: two along one side, then the matching two on the other
+    fn add_quad(&mut self, a1: Point, a2: Point, b1: Point, b2: Point, z: f32, color: [f32; 3]) {
+        for p in [a1, b1, a2, a2, b1, b2] {
+            self.vertices.push(Vertex { position: [p.x, p.y, z], color });
+        }
+    }
+    
+    /// Triangle centered on `position` with its tip pointing along `heading` (radians)
+    fn add_arrow(&mut self, position: Point, heading: f32, color: [f32; 3]) {
+        let point = |angle: f32, length: f32| {
+            let p = position + Vector2::new(angle.cos(), angle.sin()) * length;
+            Vertex { position: [p.x, p.y, MARKER_Z], color }
+        };
+        self.vertices.push(point(heading, MARKER_SIZE / 2.0));
+        self.vertices.push(point(heading + PI - 0.6, MARKER_SIZE / 2.0));
+        self.vertices.push(point(heading + PI + 0.6, MARKER_SIZE / 2.0));
+    }
+}
+
+/// Through lanes per direction; the simulation numbers cloverleaf lanes in four equal blocks
+fn cloverleaf_lanes_per_direction(geometry: &RouteGeometry) -> u32 {
+    (geometry.lane_count / 4).max(1)
+}
+
+/// Middle-lane center line of each carriageway as (x, y, heading), in lane-block order:
+/// southbound, northbound, westbound, eastbound. Cloverleaf physics is always centered on the origin.
+fn cloverleaf_carriageways(geometry: &RouteGeometry) -> [(f32, f32, f32); 4] {
+    let separation = geometry.highway_width.unwrap_or(40.0) / 2.0 + 5.0; // Same separation as physics
+    [
+        (-separation, 0.0, -PI / 2.0),
+        (separation, 0.0, PI / 2.0),
+        (0.0, separation, PI),
+        (0.0, -separation, 0.0),
+    ]
+}
+
+/// Point where a cloverleaf through lane crosses the ray at `angle_deg` from the origin,
+/// with the lane's heading. `None` for ramp lanes or when the lane never crosses that ray.
+fn cloverleaf_lane_crossing(geometry: &RouteGeometry, lane: u32, angle_deg: f32) -> Option<(Point, f32)> {
+    let lanes = cloverleaf_lanes_per_direction(geometry);
+    let block = (lane.checked_sub(1)? / lanes) as usize;
+    let &(x, y, heading) = cloverleaf_carriageways(geometry).get(block)?;
+    
+    // Physics offsets lanes from the middle lane of their block along +x (north-south) or +y (east-west)
+    let index = (lane - 1) % lanes;
+    let offset = (index as f32 - (lanes as f32 - 1.0) / 2.0) * geometry.lane_width;
+    let along = Vector2::new(heading.cos(), heading.sin());
+    let lane_axis = if along.x.abs() > 0.5 { Vector2::new(0.0, 1.0) } else { Vector2::new(1.0, 0.0) };
+    let origin = Vector2::new(x, y) + lane_axis * offset;
+    
+    // Solve origin + along * t = ray * s for s > 0
+    let angle = angle_deg.to_radians();
+    let ray = Vector2::new(angle.cos(), angle.sin());
+    let denominator = ray.perp(&along);
+    if denominator.abs() < 1e-6 {
+        return None;
+    }
+    let s = origin.perp(&along) / denominator;
+    if s <= 0.0 {
+        return None;
+    }
+    Some((Point2::origin() + ray * s, heading))
+}
@@ -0,0 +1,196 @@
+use crate::config::{SimulationConfig, UnitSystem, Validate};
+use anyhow::Result;
+
+/// Settings window for the parts of the configuration that can change while the simulation
+/// runs: spawning, behavior weights, collision avoidance distances and speed limits. Edits
+/// are collected in a draft until Apply, when the application rebuilds the backend with them.
+pub struct SettingsEditor {
+    open: bool,
+    applied: SimulationConfig, // configuration the simulation is running with
+    draft: SimulationConfig,
+    apply_requested: bool,
+    status: Option<Result<String, String>>, // outcome of the last Apply
+}
+
+impl SettingsEditor {
+    pub fn new(config: &SimulationConfig) -> Self {
+        Self {
+            open: false,
+            applied: config.clone(),
+            draft: config.clone(),
+            apply_requested: false,
+            status: None,
+        }
+    }
+    
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+    
+    /// Start over from a configuration the simulation switched to, dropping unapplied edits
+    pub fn reset(&mut self, config: &SimulationConfig) {
+        self.applied = config.clone();
+        self.draft = config.clone();
+        self.apply_requested = false;
+        self.status = None;
+    }
+    
+    /// The edited configuration, once, after Apply was clicked and it passed validation.
+    /// Report the outcome with `finish_apply`.
+    pub fn take_apply_request(&mut self) -> Option<SimulationConfig> {
+        if !std::mem::take(&mut self.apply_requested) {
+            return None;
+        }
+        Some(self.draft.clone())
+    }
+    
+    pub fn finish_apply(&mut self, result: Result<()>, time: f32) {
+        self.status = Some(match result {
+            Ok(()) => {
+                self.applied = self.draft.clone();
+                Ok(format!("Applied at t={:.1}s", time))
+            }
+            Err(e) => Err(format!("Not applied: {}", e)),
+        });
+    }
+    
+    /// Show the window, with speeds and distances edited in `units`
+    pub fn show(&mut self, ctx: &egui::Context, units: UnitSystem) {
+        let mut open = self.open;
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .resizable(false)
+            .default_pos(egui::pos2(450.0, 60.0))
+            .show(ctx, |ui| {
+                self.show_spawning(ui);
+                self.show_behaviors(ui);
+                self.show_collision_avoidance(ui, units);
+                self.show_speed_limits(ui, units);
+                
+                ui.separator();
+                let edited = self.is_edited();
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(edited, egui::Button::new("Apply")).clicked() {
+                        match self.validate_draft() {
+                            Ok(()) => self.apply_requested = true,
+                            Err(e) => self.status = Some(Err(e.to_string())),
+                        }
+                    }
+                    if ui.add_enabled(edited, egui::Button::new("Revert")).clicked() {
+                        self.draft = self.applied.clone();
+                        self.status = None;
+                    }
+                });
+                match &self.status {
+                    Some(Err(message)) => ui.colored_label(egui::Color32::RED, message),
+                    _ if edited => ui.label("Unapplied changes"),
+                    Some(Ok(message)) => ui.colored_label(egui::Color32::GREEN, message),
+                    None => ui.label(""),
+                };
+            });
+        self.open = open;
+    }
+    
+    fn show_spawning(&mut self, ui: &mut egui::Ui) {
+        let simulation = &mut self.draft.cars.simulation;
+        ui.collapsing("Spawning", |ui| {
+            egui::Grid::new("settings_spawning").num_columns(2).show(ui, |ui| {
+                ui.label("Spawn rate");
+                ui.add(egui::DragValue::new(&mut simulation.spawn_rate).speed(0.01).range(0.01..=20.0).suffix(" cars/s"));
+                ui.end_row();
+                ui.label("Max cars");
+                ui.add(egui::DragValue::new(&mut simulation.total_cars).range(1..=10_000));
+                ui.end_row();
+            });
+        });
+    }
+    
+    fn show_behaviors(&mut self, ui: &mut egui::Ui) {
+        let behaviors = &mut self.draft.cars.behavior;
+        ui.collapsing("Behavior weights", |ui| {
+            let mut names: Vec<String> = behaviors.keys().cloned().collect();
+            names.sort();
+            egui::Grid::new("settings_behaviors").num_columns(2).show(ui, |ui| {
+                for name in &names {
+                    if let Some(behavior) = behaviors.get_mut(name) {
+                        ui.label(&behavior.name);
+                        ui.add(egui::DragValue::new(&mut behavior.weight).range(0..=100).suffix("%"));
+                        ui.end_row();
+                    }
+                }
+            });
+            let total: u32 = behaviors.values().map(|behavior| behavior.weight).sum();
+            if total != 100 {
+                ui.colored_label(egui::Color32::YELLOW, format!("Weights sum to {}, must be 100", total));
+            }
+        });
+    }
+    
+    fn show_collision_avoidance(&mut self, ui: &mut egui::Ui, units: UnitSystem) {
+        let avoidance = &mut self.draft.cars.collision_avoidance;
+        ui.collapsing("Collision avoidance", |ui| {
+            egui::Grid::new("settings_avoidance").num_columns(2).show(ui, |ui| {
+                for (label, value) in [
+                    ("Safety margin", &mut avoidance.safety_margin),
+                    ("Emergency brake distance", &mut avoidance.emergency_brake_distance),
+                    ("Warning distance", &mut avoidance.warning_distance),
+                    ("Lateral safety margin", &mut avoidance.lateral_safety_margin),
+                ] {
+                    // Edited in the display unit, stored in meters
+                    ui.label(label);
+                    let mut distance = units.distance(*value);
+                    let range = 0.0..=units.distance(200.0);
+                    if ui.add(egui::DragValue::new(&mut distance).speed(0.1).range(range).suffix(format!(" {}", units.distance_unit()))).changed() {
+                        *value = units.distance_to_si(distance);
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    }
+    
+    fn show_speed_limits(&mut self, ui: &mut egui::Ui, units: UnitSystem) {
+        let rules = &mut self.draft.route.route.traffic_rules;
+        ui.collapsing("Speed limits", |ui| {
+            egui::Grid::new("settings_speeds").num_columns(2).show(ui, |ui| {
+                // Edited in the display unit, stored in m/s
+                for (label, value) in [
+                    ("Speed limit", &mut rules.speed_limit),
+                    ("Minimum speed", &mut rules.min_speed),
+                ] {
+                    ui.label(label);
+                    let mut speed = units.speed(*value);
+                    let range = units.speed(1.0 / 3.6)..=units.speed(300.0 / 3.6);
+                    if ui.add(egui::DragValue::new(&mut speed).speed(0.5).range(range).suffix(format!(" {}", units.speed_unit()))).changed() {
+                        *value = units.speed_to_si(speed);
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    }
+    
+    fn validate_draft(&self) -> Result<()> {
+        self.draft.route.validate()?;
+        self.draft.cars.validate()
+    }
+    
+    /// Whether the draft differs from the applied configuration in any editable field
+    fn is_edited(&self) -> bool {
+        let (draft, applied) = (&self.draft, &self.applied);
+        let behaviors_edited = draft.cars.behavior.iter()
+            .any(|(name, behavior)| applied.cars.behavior.get(name).is_none_or(|applied| applied.weight != behavior.weight));
+        let (avoidance, applied_avoidance) = (&draft.cars.collision_avoidance, &applied.cars.collision_avoidance);
+        let (rules, applied_rules) = (&draft.route.route.traffic_rules, &applied.route.route.traffic_rules);
+        
+        draft.cars.simulation.spawn_rate != applied.cars.simulation.spawn_rate
+            || draft.cars.simulation.total_cars != applied.cars.simulation.total_cars
+            || behaviors_edited
+            || avoidance.safety_margin != applied_avoidance.safety_margin
+            || avoidance.emergency_brake_distance != applied_avoidance.emergency_brake_distance
+            || avoidance.warning_distance != applied_avoidance.warning_distance
+            || avoidance.lateral_safety_margin != applied_avoidance.lateral_safety_margin
+            || rules.speed_limit != applied_rules.speed_limit
+            || rules.min_speed != applied_rules.min_speed
+    }
+}
@@ -0,0 +1,127 @@
+use crate::config::CarsConfig;
+use crate::simulation::CarId;
+use anyhow::Result;
+
+/// Pixels the pointer may move between press and release for it to count as a click
+const CLICK_TOLERANCE: f32 = 4.0;
+
+/// Places cars where the road is clicked, with the behavior and car type picked in its
+/// window. Dragging still pans the view, only a click that stays put places a car.
+pub struct SpawnTool {
+    open: bool,
+    behaviors: Vec<String>,
+    car_types: Vec<String>,
+    behavior: String,
+    car_type: Option<String>, // `None` picks one by the configured weights
+    press: Option<(f32, f32)>, // screen position the left button went down at
+    outcome: Option<(String, bool)>, // what the last click did, and whether it placed a car
+}
+
+impl SpawnTool {
+    pub fn new(cars: &CarsConfig) -> Self {
+        let mut tool = Self {
+            open: false,
+            behaviors: Vec::new(),
+            car_types: Vec::new(),
+            behavior: "normal".to_string(),
+            car_type: None,
+            press: None,
+            outcome: None,
+        };
+        tool.set_config(cars);
+        tool
+    }
+    
+    /// Offer the behaviors and car types of a changed configuration, keeping the selection
+    /// where it still exists
+    pub fn set_config(&mut self, cars: &CarsConfig) {
+        self.behaviors = cars.behavior.keys().cloned().collect();
+        self.behaviors.sort();
+        self.car_types = cars.car_types.iter().map(|car_type| car_type.id.clone()).collect();
+        if !self.behaviors.contains(&self.behavior) {
+            self.behavior = self.behaviors.first().cloned().unwrap_or_default();
+        }
+        if self.car_type.as_ref().is_some_and(|id| !self.car_types.contains(id)) {
+            self.car_type = None;
+        }
+    }
+    
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.press = None;
+    }
+    
+    /// Whether clicks on the road place cars
+    pub fn is_active(&self) -> bool {
+        self.open
+    }
+    
+    /// The behavior and car type the next car gets
+    pub fn selection(&self) -> (&str, Option<&str>) {
+        (&self.behavior, self.car_type.as_deref())
+    }
+    
+    pub fn select(&mut self, behavior: &str, car_type: Option<&str>) {
+        self.behavior = behavior.to_string();
+        self.car_type = car_type.map(str::to_string);
+    }
+    
+    pub fn press(&mut self, position: (f32, f32)) {
+        self.press = Some(position);
+    }
+    
+    /// Whether letting go at `position` finishes a click rather than a drag
+    pub fn release(&mut self, position: (f32, f32)) -> bool {
+        self.press.take().is_some_and(|(x, y)| {
+            (position.0 - x).hypot(position.1 - y) <= CLICK_TOLERANCE
+        })
+    }
+    
+    /// Show what placing a car at the last click did
+    pub fn report(&mut self, result: &Result<CarId>) {
+        self.outcome = Some(match result {
+            Ok(id) => (format!("Placed car {}", id), true),
+            Err(e) => (e.to_string(), false),
+        });
+    }
+    
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let mut open = self.open;
+        egui::Window::new("Place cars")
+            .open(&mut open)
+            .resizable(false)
+            .default_pos(egui::pos2(450.0, 400.0))
+            .show(ctx, |ui| {
+                ui.label("Click the road to place a car");
+                egui::Grid::new("spawn_tool").num_columns(2).show(ui, |ui| {
+                    ui.label("Behavior");
+                    egui::ComboBox::from_id_source("spawn_behavior")
+                        .selected_text(self.behavior.as_str())
+                        .show_ui(ui, |ui| {
+                            for behavior in &self.behaviors {
+                                ui.selectable_value(&mut self.behavior, behavior.clone(), behavior.as_str());
+                            }
+                        });
+                    ui.end_row();
+                    
+                    ui.label("Car type");
+                    egui::ComboBox::from_id_source("spawn_car_type")
+                        .selected_text(self.car_type.as_deref().unwrap_or("random"))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.car_type, None, "random");
+                            for car_type in &self.car_types {
+                                ui.selectable_value(&mut self.car_type, Some(car_type.clone()), car_type.as_str());
+                            }
+                        });
+                    ui.end_row();
+                });
+                if let Some((message, placed)) = &self.outcome {
+                    let color = if *placed { egui::Color32::GREEN } else { egui::Color32::YELLOW };
+                    ui.colored_label(color, message);
+                }
+            });
+        if open != self.open {
+            self.toggle();
+        }
+    }
+}
@@ -0,0 +1,98 @@
+/// Timeline slider over the rewind buffer. Moving it shows the simulation as it was at
+/// that time, and the run carries on from there once resumed.
+pub struct RewindTimeline {
+    open: bool,
+    range: Option<(f32, f32)>, // oldest and newest snapshot times
+    snapshots: usize,
+    memory: (usize, f32), // bytes the snapshots take, and the budget in megabytes
+    time: f32, // slider position
+    scrub_request: Option<f32>,
+    resume_request: bool,
+}
+
+impl Default for RewindTimeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RewindTimeline {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            range: None,
+            snapshots: 0,
+            memory: (0, 0.0),
+            time: 0.0,
+            scrub_request: None,
+            resume_request: false,
+        }
+    }
+    
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+    
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+    
+    /// What the rewind buffer holds, for the slider's range and the memory readout
+    pub fn set_buffer(&mut self, range: Option<(f32, f32)>, snapshots: usize, bytes: usize, budget_mb: f32) {
+        self.range = range;
+        self.snapshots = snapshots;
+        self.memory = (bytes, budget_mb);
+    }
+    
+    /// Time the slider was moved to since the last call, if it was
+    pub fn take_scrub_request(&mut self) -> Option<f32> {
+        self.scrub_request.take()
+    }
+    
+    /// Whether Resume was clicked since the last call
+    pub fn take_resume_request(&mut self) -> bool {
+        std::mem::take(&mut self.resume_request)
+    }
+    
+    /// `time` is the simulation time of the state on screen
+    pub fn show(&mut self, ctx: &egui::Context, time: f32) {
+        let mut open = self.open;
+        egui::Window::new("Rewind")
+            .open(&mut open)
+            .resizable(false)
+            .default_pos(egui::pos2(450.0, 500.0))
+            .show(ctx, |ui| {
+                let Some((start, end)) = self.range else {
+                    if self.memory.1 > 0.0 {
+                        ui.label("Nothing recorded yet");
+                    } else {
+                        ui.label("Rewinding is off, set rewind_memory_mb in [performance]");
+                    }
+                    return;
+                };
+                
+                self.time = time.clamp(start, end);
+                let slider = egui::Slider::new(&mut self.time, start..=end)
+                    .suffix(" s")
+                    .fixed_decimals(1);
+                if ui.add_sized([360.0, 20.0], slider).changed() {
+                    self.scrub_request = Some(self.time);
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Resume from here").clicked() {
+                        self.resume_request = true;
+                    }
+                    ui.label(format!("{:.0} s back", end - self.time));
+                });
+                ui.label(format!(
+                    "{} snapshots, {:.1} of {:.0} MB",
+                    self.snapshots,
+                    self.memory.0 as f32 / (1024.0 * 1024.0),
+                    self.memory.1,
+                ));
+            });
+        if open != self.open {
+            self.toggle();
+        }
+    }
+}
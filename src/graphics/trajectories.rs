@@ -0,0 +1,139 @@
+use crate::config::{RouteConfig, UnitSystem};
+use crate::simulation::{road_length, CarId, SimulationState, TrajectoryBuffer, TrajectorySample};
+use egui_plot::{Line, Plot, PlotPoints};
+use std::collections::BTreeMap;
+
+const SAMPLE_INTERVAL: f32 = 0.5; // seconds of simulation time between trajectory samples
+const MAX_SAMPLES: usize = 200_000; // about 10 MB of samples
+const PLOT_WIDTH: f32 = 560.0;
+const PLOT_HEIGHT: f32 = 360.0;
+const SLOW: egui::Color32 = egui::Color32::from_rgb(220, 60, 50); // below a third of the speed limit
+const MEDIUM: egui::Color32 = egui::Color32::from_rgb(230, 180, 40);
+const FAST: egui::Color32 = egui::Color32::from_rgb(70, 180, 90);
+
+/// Time-space diagram: every car's position along the road over time. Cars held up by a
+/// stop-and-go wave flatten out together, so the waves show up as bands moving against
+/// the traffic.
+pub struct TrajectoryView {
+    open: bool,
+    buffer: TrajectoryBuffer,
+    speed_limit: f32, // m/s, for coloring
+}
+
+impl TrajectoryView {
+    /// `window` is how many seconds of trajectories are kept
+    pub fn new(route: &RouteConfig, window: f32) -> Self {
+        Self {
+            open: false,
+            buffer: TrajectoryBuffer::new(&route.route.geometry, SAMPLE_INTERVAL, window, MAX_SAMPLES),
+            speed_limit: route.route.traffic_rules.speed_limit,
+        }
+    }
+    
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+    
+    pub fn record(&mut self, state: &SimulationState) {
+        self.buffer.record(state);
+    }
+    
+    /// Follow a changed route from now on
+    pub fn set_route(&mut self, route: &RouteConfig) {
+        self.buffer.set_geometry(&route.route.geometry);
+        self.speed_limit = route.route.traffic_rules.speed_limit;
+    }
+    
+    pub fn show(&mut self, ctx: &egui::Context, units: UnitSystem) {
+        let mut open = self.open;
+        egui::Window::new("Time-space diagram")
+            .open(&mut open)
+            .resizable(false)
+            .default_pos(egui::pos2(450.0, 60.0))
+            .show(ctx, |ui| {
+                ui.label(format!("Position along the road ({}) over time (s), colored by speed", units.distance_unit()));
+                self.plot(ui, units);
+                ui.horizontal(|ui| {
+                    for (color, label) in [
+                        (SLOW, "below 1/3 of the limit"),
+                        (MEDIUM, "below 2/3"),
+                        (FAST, "faster"),
+                    ] {
+                        ui.colored_label(color, "■");
+                        ui.label(label);
+                    }
+                });
+            });
+        self.open = open;
+    }
+    
+    fn plot(&self, ui: &mut egui::Ui, units: UnitSystem) {
+        let samples = self.buffer.samples();
+        let end = samples.back().map(|sample| sample.time).unwrap_or(0.0);
+        let start = (end - self.buffer.window()).max(0.0);
+        let length = road_length(self.buffer.geometry());
+        
+        let mut tracks: BTreeMap<CarId, Vec<&TrajectorySample>> = BTreeMap::new();
+        for sample in samples {
+            tracks.entry(sample.car).or_default().push(sample);
+        }
+        
+        let mut plot = Plot::new("time_space_diagram")
+            .width(PLOT_WIDTH)
+            .height(PLOT_HEIGHT)
+            .include_x(start)
+            .include_x(end.max(start + SAMPLE_INTERVAL))
+            .include_y(0.0);
+        if let Some(length) = length {
+            plot = plot.include_y(units.distance(length));
+        }
+        plot.show(ui, |plot_ui| {
+            for track in tracks.values() {
+                for (color, points) in self.segments(track, length, units) {
+                    plot_ui.line(Line::new(PlotPoints::from(points)).color(color).width(1.0));
+                }
+            }
+        });
+    }
+    
+    /// Split one car's track into runs of the same speed band, breaking it where the
+    /// position wraps around a ring
+    fn segments(&self, track: &[&TrajectorySample], length: Option<f32>, units: UnitSystem) -> Vec<(egui::Color32, Vec<[f64; 2]>)> {
+        let mut segments = Vec::new();
+        let mut points: Vec<[f64; 2]> = Vec::new();
+        let mut current = None;
+        let mut previous: Option<&TrajectorySample> = None;
+        for sample in track {
+            let point = [sample.time as f64, units.distance(sample.position) as f64];
+            let color = self.speed_color(sample.speed);
+            let wrapped = previous.zip(length).is_some_and(|(previous, length)| previous.position - sample.position > length / 2.0);
+            if wrapped {
+                segments.extend(current.map(|color| (color, std::mem::take(&mut points))));
+            } else if current.is_some_and(|current| current != color) {
+                // Close the old run at this sample so the line stays continuous
+                points.push(point);
+                segments.extend(current.map(|color| (color, std::mem::take(&mut points))));
+            }
+            points.push(point);
+            current = Some(color);
+            previous = Some(sample);
+        }
+        segments.extend(current.map(|color| (color, points)));
+        segments
+    }
+    
+    fn speed_color(&self, speed: f32) -> egui::Color32 {
+        speed_band_color(speed, self.speed_limit)
+    }
+}
+
+/// Red, yellow or green for speeds in the lower, middle or upper third of the limit
+pub(crate) fn speed_band_color(speed: f32, speed_limit: f32) -> egui::Color32 {
+    if speed < speed_limit / 3.0 {
+        SLOW
+    } else if speed < speed_limit * 2.0 / 3.0 {
+        MEDIUM
+    } else {
+        FAST
+    }
+}
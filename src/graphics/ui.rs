@@ -1,30 +1,194 @@
-use crate::simulation::{SimulationState, PerformanceMetrics};
-use crate::graphics::Viewport;
+use crate::config::{SimulationConfig, UnitSystem};
+use crate::simulation::{ConflictKind, LaneUsage, NoiseMap, NOISE_CELL_SIZE, SimulationState, PerformanceMetrics, ResourceSample, Weather, LANE_CHANGE_WINDOW};
+use crate::graphics::{lane_color, to_color32, BehaviorTable, CarColoring, ComparisonPanel, CarPalette, ClosureTool, DrawnCars, RewindTimeline, FundamentalDiagram, Minimap, PreferencesWindow, SettingsEditor, SpawnTool, TrafficHistory, TrajectoryView, UiPreferences, Viewport};
 use anyhow::Result;
+use egui_plot::{Legend, Line, Plot, PlotPoints};
+use std::collections::VecDeque;
+
+const RESOURCE_CHART_WIDTH: f32 = 220.0;
+const RESOURCE_CHART_HEIGHT: f32 = 50.0;
+
+/// Simulation speeds the slider and Shift +/- reach, as multiples of real time
+pub const SPEED_RANGE: std::ops::RangeInclusive<f32> = 0.25..=16.0;
+/// Change in simulation speed per Shift +/- press, and the slider's resolution
+pub const SPEED_STEP: f32 = 0.25;
 
 pub struct UiRenderer {
-    // egui handles its own state, so we don't need much here
+    // egui handles its own widget state, only the plotted history and settings edits are kept here
+    history: TrafficHistory,
+    lanes: LaneUsage,
+    palette: CarPalette, // the renderer's car colors, for the legend and distribution chart
+    pub diagram: FundamentalDiagram,
+    pub trajectories: TrajectoryView,
+    pub behaviors: BehaviorTable,
+    pub comparison: Option<ComparisonPanel>, // in comparison mode only
+    pub settings: SettingsEditor,
+    pub minimap: Minimap,
+    pub preferences: PreferencesWindow,
+    pub spawn_tool: SpawnTool,
+    pub closure_tool: ClosureTool,
+    pub timeline: RewindTimeline,
+    pub noise: NoiseMap, // traffic noise since the run started, drawn over the road while shown
+    pub show_noise: bool,
+    pub show_overlays: bool, // F1 hides every panel and window
+    pub show_charts: bool, // false while the frame budget is exceeded
+    pub quality: Option<String>, // adaptive quality level, `None` when it is off
+    pub divergence: Option<(String, bool)>, // backend divergence and whether it is over tolerance, with --check-divergence
+    pub car_coloring: CarColoring, // the lane table shows while cars are colored by lane
+    pub drawn_cars: DrawnCars, // the renderer's level of detail in the last frame
+    speed_request: Option<f32>, // speed picked on the slider, for the main loop to apply
+    step_count: u32, // steps the Step button takes
+    step_request: Option<u32>, // steps asked for with the Step button, for the main loop to take
+}
+
+/// What the panels show about the run besides the simulation state
+#[derive(Clone, Copy)]
+pub struct RunStatus<'a> {
+    pub performance: &'a PerformanceMetrics,
+    pub resources: &'a VecDeque<ResourceSample>,
+    pub paused: bool,
+    pub simulation_speed: f32,
+    pub frame_count: u64,
+    pub route_file: &'a str,
+    pub cars_file: &'a str,
+    pub seed: Option<u64>,
 }
 
 impl UiRenderer {
-    pub fn new() -> Result<Self> {
-        Ok(Self {})
+    /// `plot_window` is how many minutes of history the time-series plots and the
+    /// time-space diagram show
+    pub fn new(config: &SimulationConfig, plot_window: f32) -> Result<Self> {
+        Ok(Self {
+            history: TrafficHistory::new(&config.route, plot_window),
+            lanes: LaneUsage::new(config.route.route.geometry.lane_count, LANE_CHANGE_WINDOW),
+            palette: CarPalette::new(&config.cars),
+            diagram: FundamentalDiagram::new(),
+            trajectories: TrajectoryView::new(&config.route, plot_window * 60.0),
+            behaviors: BehaviorTable::new(&config.cars),
+            comparison: None,
+            settings: SettingsEditor::new(config),
+            minimap: Minimap::new(&config.route),
+            preferences: PreferencesWindow::new(UiPreferences::default_path()),
+            spawn_tool: SpawnTool::new(&config.cars),
+            closure_tool: ClosureTool::new(&config.route),
+            timeline: RewindTimeline::new(),
+            noise: NoiseMap::new(&config.cars, NOISE_CELL_SIZE),
+            show_noise: false,
+            show_overlays: true,
+            show_charts: true,
+            quality: None,
+            divergence: None,
+            car_coloring: CarColoring::Palette,
+            drawn_cars: DrawnCars::default(),
+            speed_request: None,
+            step_count: 1,
+            step_request: None,
+        })
+    }
+    
+    /// Follow a configuration the simulation switched to
+    pub fn set_config(&mut self, config: &SimulationConfig) {
+        self.history.set_route(&config.route);
+        self.trajectories.set_route(&config.route);
+        self.behaviors.set_config(&config.cars);
+        self.minimap.set_route(&config.route);
+        self.lanes = LaneUsage::new(config.route.route.geometry.lane_count, LANE_CHANGE_WINDOW);
+        self.palette = CarPalette::new(&config.cars);
+        self.settings.reset(config);
+        self.spawn_tool.set_config(&config.cars);
+        self.closure_tool.set_config(&config.route);
+        self.noise = NoiseMap::new(&config.cars, NOISE_CELL_SIZE);
     }
     
-    pub fn render_egui(
-        &mut self,
-        ctx: &egui::Context,
-        performance: &PerformanceMetrics,
-        state: &SimulationState,
-        viewport: &Viewport,
-        paused: bool,
-        simulation_speed: f32,
-        frame_count: u64,
-        route_file: &str,
-        cars_file: &str,
-        seed: Option<u64>,
-        font_size: f32,
-    ) {
+    /// Speed picked on the slider since the last call, if it was moved
+    pub fn take_speed_request(&mut self) -> Option<f32> {
+        self.speed_request.take()
+    }
+    
+    /// Steps asked for with the Step button since the last call, if it was clicked
+    pub fn take_step_request(&mut self) -> Option<u32> {
+        self.step_request.take()
+    }
+    
+    /// Feed one simulation tick to the time-series plots and diagrams
+    pub fn record(&mut self, state: &SimulationState) {
+        let readings = self.history.record(state);
+        self.diagram.record(state.time, &readings);
+        self.trajectories.record(state);
+        self.lanes.record(state);
+        self.behaviors.record(state);
+        self.noise.record(state);
+    }
+    
+    /// Cars, mean speed and lane changes in and out of each lane, in the lanes' colors
+    fn render_lane_table(&self, ctx: &egui::Context, state: &SimulationState, units: UnitSystem) {
+        let lanes = self.lanes.lanes(state);
+        let speed_heading = format!("Speed ({})", units.speed_unit());
+        egui::Window::new("Lanes")
+            .resizable(false)
+            .default_pos(egui::pos2(450.0, 60.0))
+            .show(ctx, |ui| {
+                ui.label(format!("Lane changes per minute over the last {:.0} s", LANE_CHANGE_WINDOW));
+                egui::Grid::new("lane_table").striped(true).num_columns(5).show(ui, |ui| {
+                    for heading in ["Lane", "Cars", &speed_heading, "In/min", "Out/min"] {
+                        ui.strong(heading);
+                    }
+                    ui.end_row();
+                    for stats in &lanes {
+                        let [r, g, b] = lane_color(stats.lane).map(|channel| (channel * 255.0) as u8);
+                        ui.colored_label(egui::Color32::from_rgb(r, g, b), format!("● {}", stats.lane));
+                        ui.label(stats.cars.to_string());
+                        ui.label(format!("{:.1}", units.speed(stats.mean_speed)));
+                        ui.label(format!("{:.1}", stats.changes_in));
+                        ui.label(format!("{:.1}", stats.changes_out));
+                        ui.end_row();
+                    }
+                });
+            });
+    }
+    
+    /// Small charts of the resource history: CPU share, and process and GPU memory
+    fn render_resource_charts(ui: &mut egui::Ui, resources: &VecDeque<ResourceSample>) {
+        let Some(latest) = resources.back() else {
+            return;
+        };
+        // Scroll through the last minute instead of rescaling
+        let start = (latest.time - 60.0).max(0.0) as f64;
+        let chart = |id: &str| Plot::new(id)
+            .width(RESOURCE_CHART_WIDTH)
+            .height(RESOURCE_CHART_HEIGHT)
+            .include_x(start)
+            .include_x(start + 60.0)
+            .include_y(0.0)
+            .show_axes([false, true])
+            .allow_drag(false)
+            .allow_zoom(false)
+            .allow_scroll(false)
+            .allow_boxed_zoom(false);
+        let megabytes = |bytes: usize| bytes as f64 / (1024.0 * 1024.0);
+        
+        if latest.process.is_some() {
+            chart("cpu_chart").include_y(100.0).show(ui, |plot_ui| {
+                let cpu: PlotPoints = resources.iter()
+                    .filter_map(|sample| sample.process.map(|process| [sample.time as f64, process.cpu_utilization as f64 * 100.0]))
+                    .collect();
+                plot_ui.line(Line::new(cpu).name("CPU %"));
+            });
+        }
+        chart("memory_chart").legend(Legend::default()).show(ui, |plot_ui| {
+            let memory: PlotPoints = resources.iter()
+                .filter_map(|sample| sample.process.map(|process| [sample.time as f64, megabytes(process.memory_usage)]))
+                .collect();
+            let gpu_memory: PlotPoints = resources.iter()
+                .map(|sample| [sample.time as f64, megabytes(sample.gpu_memory)])
+                .collect();
+            plot_ui.line(Line::new(memory).name("RAM MB"));
+            plot_ui.line(Line::new(gpu_memory).name("GPU MB"));
+        });
+    }
+    
+    pub fn render_egui(&mut self, ctx: &egui::Context, state: &SimulationState, viewport: &Viewport, run: &RunStatus) {
+        let RunStatus { performance, resources, paused, simulation_speed, frame_count, route_file, cars_file, seed } = *run;
         let fps = if !performance.frame_time.is_zero() {
             1.0 / performance.frame_time.as_secs_f32()
         } else {
@@ -33,7 +197,12 @@ impl UiRenderer {
         
         let status = if paused { "PAUSED" } else { "RUNNING" };
         
-        // Configure font size for all text
+        // Weather sits behind every panel but in front of the road and cars
+        Self::render_weather(ctx, state.weather, state.time);
+        
+        // Configure font size for all text, and the scale of the whole UI
+        let font_size = self.preferences.font_size();
+        let units = self.preferences.units();
         ctx.style_mut(|style| {
             style.text_styles.insert(
                 egui::TextStyle::Body,
@@ -44,398 +213,470 @@ impl UiRenderer {
                 egui::FontId::new(font_size, egui::FontFamily::Monospace),
             );
         });
+        ctx.set_zoom_factor(self.preferences.preferences().ui_scale);
         
-        // Status overlay in the lower-left corner
-        egui::Area::new(egui::Id::new("status_overlay"))
-            .fixed_pos(egui::pos2(15.0, 15.0))
-            .show(ctx, |ui| {
-                ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
-                    // Semi-transparent background
-                    let rect = ui.available_rect_before_wrap();
-                    ui.painter().rect_filled(
-                        rect.expand(5.0),
-                        5.0,
-                        egui::Color32::from_black_alpha(160)
-                    );
-                    
-                    ui.spacing_mut().item_spacing = egui::vec2(0.0, 2.0);
-                    ui.style_mut().override_text_style = Some(egui::TextStyle::Body);
-                    
-                    // Status section
-                    ui.colored_label(
-                        if paused { egui::Color32::YELLOW } else { egui::Color32::GREEN },
-                        format!("Status: {}", status)
-                    );
-                    ui.label(format!("Cars: {}/{}", state.active_cars, state.total_spawned));
-                    ui.label(format!("Time: {:.1}s", state.time));
-                    ui.label(format!("Speed: {:.2}x", simulation_speed));
-                    ui.label(format!("FPS: {:.0}", fps));
-                    ui.label(format!("Frame: {}", frame_count));
-                    
-                    ui.add_space(10.0);
-                    
-                    // Files section
-                    ui.label(format!("Route: {}", route_file));
-                    ui.label(format!("Cars: {}", cars_file));
-                    
-                    // Seed information for reproducibility
-                    match seed {
-                        Some(s) => ui.label(format!("Seed: {}", s)),
-                        None => ui.label("Seed: random"),
-                    };
-                    
-                    ui.add_space(10.0);
-                    
-                    // Camera info
-                    ui.label(format!("Zoom: {:.2}x", viewport.get_zoom()));
-                    ui.label(format!("Pos: ({:.0}, {:.0})", 
-                               viewport.get_position().x, viewport.get_position().y));
+        // Nothing but the scene for clean captures
+        if !self.show_overlays {
+            return;
+        }
+        
+        // Status overlay in the upper-left corner
+        let mut units_choice = None;
+        let status_rect = self.preferences.overlay_window(ctx, "status", "Status", egui::pos2(15.0, 15.0), egui::Align2::LEFT_TOP, |ui| {
+            ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
+                ui.spacing_mut().item_spacing = egui::vec2(0.0, 2.0);
+                ui.style_mut().override_text_style = Some(egui::TextStyle::Body);
+                
+                // Status section
+                ui.colored_label(
+                    if paused { egui::Color32::YELLOW } else { egui::Color32::GREEN },
+                    format!("Status: {}", status)
+                );
+                ui.label(format!("Cars: {}/{}", state.active_cars, state.total_spawned));
+                ui.label(format!("Collisions: {}", state.total_collisions));
+                ui.label(format!("Conflicts: {} TTC, {} PET", state.safety.count(ConflictKind::TimeToCollision), state.safety.count(ConflictKind::PostEncroachment)));
+                let batteries: Vec<_> = state.cars.iter().filter_map(|car| car.battery.as_ref()).collect();
+                if !batteries.is_empty() {
+                    let low = batteries.iter().filter(|battery| battery.low).count();
+                    let charge = batteries.iter().map(|battery| battery.state_of_charge()).sum::<f32>() / batteries.len() as f32;
+                    ui.label(format!("EVs: {} ({:.0}% charge, {} low)", batteries.len(), charge * 100.0, low));
+                }
+                if let Some(longest) = state.queues.iter().map(|queue| queue.length).max_by(f32::total_cmp) {
+                    ui.label(format!("Queues: {} (longest {:.0} {})", state.queues.len(), units.distance(longest), units.distance_unit()));
+                }
+                ui.label(format!("Time: {:.1}s ({})", state.time, state.weather.name()));
+                if let (true, Some(loudest)) = (self.show_noise, self.noise.max_level()) {
+                    ui.label(format!("Noise: up to {:.0} dB(A)", loudest));
+                }
+                let mut speed = simulation_speed;
+                ui.horizontal(|ui| {
+                    ui.label("Speed: ");
+                    let slider = egui::Slider::new(&mut speed, SPEED_RANGE)
+                        .logarithmic(true)
+                        .step_by(SPEED_STEP as f64)
+                        .suffix("x");
+                    if ui.add(slider).changed() {
+                        self.speed_request = Some(speed);
+                    }
                 });
+                ui.horizontal(|ui| {
+                    ui.label("Units: ");
+                    for choice in UnitSystem::ALL {
+                        if ui.selectable_label(units == choice, choice.name()).clicked() && units != choice {
+                            units_choice = Some(choice);
+                        }
+                    }
+                });
+                if paused {
+                    ui.horizontal(|ui| {
+                        if ui.button("Step").clicked() {
+                            self.step_request = Some(self.step_count);
+                        }
+                        ui.add(egui::DragValue::new(&mut self.step_count).range(1..=3600).suffix(" steps"));
+                    });
+                }
+                ui.label(format!("FPS: {:.0}", fps));
+                if self.drawn_cars.dots > 0 {
+                    ui.label(format!("Drawn: {} cars, {} dots", self.drawn_cars.shapes, self.drawn_cars.dots));
+                }
+                if let Some(gpu) = &performance.gpu {
+                    ui.label(format!("GPU: {:.2}ms kernel, {:.2}ms transfer",
+                               gpu.kernel_time.as_secs_f32() * 1000.0, gpu.transfer_time.as_secs_f32() * 1000.0));
+                    ui.label(format!("GPU busy: {:.0}%, occupancy: {:.0}%",
+                               performance.gpu_utilization * 100.0, gpu.occupancy * 100.0));
+                }
+                if resources.back().is_some_and(|sample| sample.process.is_some()) {
+                    ui.label(format!("CPU: {:.0}%, memory: {}",
+                               performance.cpu_utilization * 100.0, format_bytes(performance.memory_usage)));
+                }
+                ui.label(format!("GPU memory: {}", format_bytes(performance.gpu_memory)));
+                if performance.culled_cars > 0 {
+                    ui.label(format!("Culled: {} of {} cars off screen",
+                               performance.culled_cars, performance.drawn_cars + performance.culled_cars));
+                }
+                if self.show_charts {
+                    Self::render_resource_charts(ui, resources);
+                }
+                if let Some(quality) = &self.quality {
+                    let color = if self.show_charts { egui::Color32::WHITE } else { egui::Color32::YELLOW };
+                    ui.colored_label(color, format!("Quality: {}", quality));
+                }
+                if let Some((divergence, exceeded)) = &self.divergence {
+                    let color = if *exceeded { egui::Color32::RED } else { egui::Color32::WHITE };
+                    ui.colored_label(color, divergence);
+                }
+                ui.label(format!("Frame: {}", frame_count));
+                
+                ui.add_space(10.0);
+                
+                // Files section
+                ui.label(format!("Route: {}", route_file));
+                ui.label(format!("Cars: {}", cars_file));
+                
+                // Seed information for reproducibility
+                match seed {
+                    Some(s) => ui.label(format!("Seed: {}", s)),
+                    None => ui.label("Seed: random"),
+                };
+                
+                ui.add_space(10.0);
+                
+                // Camera info
+                ui.label(format!("Zoom: {:.2}x", viewport.get_zoom()));
+                ui.label(format!("Pos: ({:.0}, {:.0}) {}", 
+                           units.distance(viewport.get_position().x), units.distance(viewport.get_position().y), units.distance_unit()));
+                if let Some(id) = viewport.follow_target() {
+                    ui.label(format!("Following: car {}", id));
+                }
             });
+        });
+        if let Some(choice) = units_choice {
+            self.preferences.choose_units(choice);
+        }
         
-        // Controls help in the lower-left corner
-        egui::Area::new(egui::Id::new("controls_overlay"))
-            .fixed_pos(egui::pos2(15.0, 280.0))
-            .show(ctx, |ui| {
-                ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
-                    // Semi-transparent background
-                    let rect = ui.available_rect_before_wrap();
-                    ui.painter().rect_filled(
-                        rect.expand(5.0),
-                        5.0,
-                        egui::Color32::from_black_alpha(160)
-                    );
-                    
-                    ui.spacing_mut().item_spacing = egui::vec2(0.0, 2.0);
-                    ui.style_mut().override_text_style = Some(egui::TextStyle::Body);
-                    
-                    ui.colored_label(egui::Color32::WHITE, "=== CONTROLS ===");
-                    ui.label("Mouse: Drag=pan, Wheel=zoom");
-                    ui.label("WASD/Arrows: Move camera");
-                    ui.label("Home: Reset view");
-                    ui.label("Space: Pause/Resume");
-                    ui.label("1-9: Speed (1x-9x)");
-                    ui.label("R: Reset simulation");
-                    ui.label("ESC: Exit");
-                    
-                    ui.add_space(10.0);
-                    
-                    ui.colored_label(egui::Color32::WHITE, "=== SPAWN CARS ===");
-                    ui.colored_label(egui::Color32::from_rgb(230, 50, 50), "A: Spawn Aggressive");
-                    ui.colored_label(egui::Color32::from_rgb(50, 150, 230), "N: Spawn Normal");
-                    ui.colored_label(egui::Color32::from_rgb(50, 200, 50), "C: Spawn Cautious");
-                    ui.colored_label(egui::Color32::from_rgb(230, 125, 25), "E: Spawn Erratic");
-                    ui.colored_label(egui::Color32::from_rgb(180, 50, 230), "S: Spawn Strategic");
-                    
-                    ui.add_space(10.0);
-                    
-                    ui.colored_label(egui::Color32::WHITE, "=== REMOVE CARS ===");
-                    ui.colored_label(egui::Color32::from_rgb(230, 50, 50), "Shift+A: Remove Aggressive");
-                    ui.colored_label(egui::Color32::from_rgb(50, 150, 230), "Shift+N: Remove Normal");
-                    ui.colored_label(egui::Color32::from_rgb(50, 200, 50), "Shift+C: Remove Cautious");
-                    ui.colored_label(egui::Color32::from_rgb(230, 125, 25), "Shift+E: Remove Erratic");
-                    ui.colored_label(egui::Color32::from_rgb(180, 50, 230), "Shift+S: Remove Strategic");
-                });
+        // Controls help below the status whatever its length
+        let controls_top = status_rect.map_or(15.0, |rect| rect.bottom() + 10.0);
+        self.preferences.overlay_window(ctx, "controls", "Controls", egui::pos2(15.0, controls_top), egui::Align2::LEFT_TOP, |ui| {
+            ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
+                ui.spacing_mut().item_spacing = egui::vec2(0.0, 2.0);
+                ui.style_mut().override_text_style = Some(egui::TextStyle::Body);
+                
+                ui.label("Mouse: Drag=pan, Wheel=zoom");
+                ui.label("WASD/Arrows: Move camera");
+                ui.label("Home: Reset view");
+                ui.label("F: Follow car (Shift+F: heading up)");
+                ui.label("H: Toggle heading indicators");
+                ui.label("M: Toggle minimap");
+                ui.label("O: Noise map");
+                ui.label("Q: Toggle queue highlights");
+                ui.label("L: Color by lane, lane table");
+                ui.label("V: Perspective (right-drag orbits)");
+                ui.label("P: Place cars by clicking");
+                ui.label("K: Close lanes by dragging");
+                ui.label("B: Rewind timeline");
+                ui.label("U: Metric/imperial units");
+                ui.label("F1: Hide all overlays");
+                ui.label("F2: Settings");
+                ui.label("F3: Fundamental diagram");
+                ui.label("F4: Time-space diagram");
+                ui.label("F6: Preferences");
+                ui.label("F7: Behavior comparison");
+                if self.comparison.is_some() {
+                    ui.label("F8: Run comparison");
+                }
+                ui.label("F12: Screenshot");
+                ui.label("Space: Pause/Resume");
+                ui.label(".: Step once while paused");
+                ui.label("1-9: Speed (1x-9x)");
+                ui.label("Shift +/-: Speed in 0.25x steps");
+                ui.label("R: Reset simulation (Shift: new seed)");
+                ui.label("ESC: Exit");
+                
+                ui.add_space(10.0);
+                
+                ui.colored_label(egui::Color32::WHITE, "=== SPAWN CARS ===");
+                ui.colored_label(egui::Color32::from_rgb(230, 50, 50), "A: Spawn Aggressive");
+                ui.colored_label(egui::Color32::from_rgb(50, 150, 230), "N: Spawn Normal");
+                ui.colored_label(egui::Color32::from_rgb(50, 200, 50), "C: Spawn Cautious");
+                ui.colored_label(egui::Color32::from_rgb(230, 125, 25), "E: Spawn Erratic");
+                ui.colored_label(egui::Color32::from_rgb(180, 50, 230), "S: Spawn Strategic");
+                
+                ui.add_space(10.0);
+                
+                ui.colored_label(egui::Color32::WHITE, "=== REMOVE CARS ===");
+                ui.colored_label(egui::Color32::from_rgb(230, 50, 50), "Shift+A: Remove Aggressive");
+                ui.colored_label(egui::Color32::from_rgb(50, 150, 230), "Shift+N: Remove Normal");
+                ui.colored_label(egui::Color32::from_rgb(50, 200, 50), "Shift+C: Remove Cautious");
+                ui.colored_label(egui::Color32::from_rgb(230, 125, 25), "Shift+E: Remove Erratic");
+                ui.colored_label(egui::Color32::from_rgb(180, 50, 230), "Shift+S: Remove Strategic");
             });
+        });
         
-        // Get behavior counts for the legend
-        let behavior_counts = state.get_behavior_counts();
-
+        // Count cars by what their color stands for, behavior or car type
+        let palette_data: Vec<(&str, usize, egui::Color32)> = self.palette.entries().iter().zip(self.palette.counts(state))
+            .map(|(entry, count)| (entry.label.as_str(), count, to_color32(entry.color)))
+            .collect();
+            
         // Color legend in the lower-left corner (20% wider)
-        egui::Area::new(egui::Id::new("legend_overlay"))
-            .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(15.0, -15.0))
-            .show(ctx, |ui| {
-                ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
-                    // Set minimum width to be 20% wider than default
-                    ui.set_min_width(240.0); // 20% wider than typical egui default (~200px)
-
-                    // Semi-transparent background
-                    let rect = ui.available_rect_before_wrap();
-                    ui.painter().rect_filled(
-                        rect.expand(5.0),
-                        5.0,
-                        egui::Color32::from_black_alpha(160)
-                    );
-                    
-                    ui.spacing_mut().item_spacing = egui::vec2(0.0, 2.0);
-                    ui.style_mut().override_text_style = Some(egui::TextStyle::Body);
-                    
-                    ui.colored_label(egui::Color32::WHITE, "=== CAR COLORS ===");
-                    ui.colored_label(egui::Color32::from_rgb(230, 50, 50),
-                        format!("● Aggressive (Red): {}", behavior_counts.get("aggressive").unwrap_or(&0)));
-                    ui.colored_label(egui::Color32::from_rgb(50, 150, 230),
-                        format!("● Normal (Blue): {}", behavior_counts.get("normal").unwrap_or(&0)));
-                    ui.colored_label(egui::Color32::from_rgb(50, 200, 50),
-                        format!("● Cautious (Green): {}", behavior_counts.get("cautious").unwrap_or(&0)));
-                    ui.colored_label(egui::Color32::from_rgb(230, 125, 25),
-                        format!("● Erratic (Orange): {}", behavior_counts.get("erratic").unwrap_or(&0)));
-                    ui.colored_label(egui::Color32::from_rgb(180, 50, 230),
-                        format!("● Strategic (Purple): {}", behavior_counts.get("strategic").unwrap_or(&0)));
-                    
-                    ui.add_space(10.0);
-                    
-                    ui.colored_label(egui::Color32::WHITE, "=== HIGHWAY SYMBOLS ===");
-                    ui.colored_label(egui::Color32::from_rgb(0, 200, 0), "▲ Entry Points");
-                    ui.colored_label(egui::Color32::from_rgb(200, 0, 0), "▲ Exit Points");
-                    ui.colored_label(egui::Color32::from_rgb(230, 200, 50), "~ Merge Zones");
-                    
-                    ui.add_space(10.0);
-                    
-                    ui.colored_label(egui::Color32::WHITE, "=== LANES ===");
-                    ui.colored_label(egui::Color32::WHITE, "Lane 1: Inner (Entry)");
-                    ui.colored_label(egui::Color32::WHITE, "Lane 2: Middle (Travel)");
-                    ui.colored_label(egui::Color32::WHITE, "Lane 3: Outer (Exit)");
-                });
+        let screen = ctx.screen_rect();
+        self.preferences.overlay_window(ctx, "legend", "Legend", egui::pos2(15.0, screen.bottom() - 15.0), egui::Align2::LEFT_BOTTOM, |ui| {
+            ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
+                // Set minimum width to be 20% wider than default
+                ui.set_min_width(240.0); // 20% wider than typical egui default (~200px)
+                
+                ui.spacing_mut().item_spacing = egui::vec2(0.0, 2.0);
+                ui.style_mut().override_text_style = Some(egui::TextStyle::Body);
+                
+                ui.colored_label(egui::Color32::WHITE, "=== CAR COLORS ===");
+                if self.car_coloring == CarColoring::Lane {
+                    ui.colored_label(egui::Color32::WHITE, "By lane, see the Lanes window (L)");
+                } else {
+                    for &(label, count, color) in &palette_data {
+                        ui.colored_label(color, format!("● {}: {}", label, count));
+                    }
+                }
+                
+                ui.add_space(10.0);
+                
+                ui.colored_label(egui::Color32::WHITE, "=== HIGHWAY SYMBOLS ===");
+                ui.colored_label(egui::Color32::from_rgb(0, 200, 0), "▲ Entry Points");
+                ui.colored_label(egui::Color32::from_rgb(200, 0, 0), "▲ Exit Points");
+                ui.colored_label(egui::Color32::from_rgb(230, 200, 50), "~ Merge Zones");
+                
+                ui.add_space(10.0);
+                
+                ui.colored_label(egui::Color32::WHITE, "=== LANES ===");
+                ui.colored_label(egui::Color32::WHITE, "Lane 1: Inner (Entry)");
+                ui.colored_label(egui::Color32::WHITE, "Lane 2: Middle (Travel)");
+                ui.colored_label(egui::Color32::WHITE, "Lane 3: Outer (Exit)");
             });
-
+        });
+        
         // Velocity distribution graph on the right side
         let velocity_distribution = state.get_velocity_distribution(16);
         let max_count = velocity_distribution.iter().cloned().max().unwrap_or(0) as f32;
-
-        // Calculate max speed for bucket labels (convert m/s to mph: m/s * 2.237)
-        let max_speed_ms = state.cars.iter()
-            .map(|car| car.velocity.magnitude())
-            .fold(0.0, f32::max);
-        let max_speed_mph = max_speed_ms * 2.237;
-        let bucket_size_mph = if max_speed_mph > 0.0 { max_speed_mph / 16.0 } else { 0.0 };
-
-        egui::Area::new(egui::Id::new("velocity_graph"))
-            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-15.0, 15.0))
-            .show(ctx, |ui| {
-                ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
-                    // Semi-transparent background
-                    let rect = egui::Rect::from_min_size(
-                        ui.cursor().min,
-                        egui::vec2(392.0, 300.0) // Another 40% wider: 280 * 1.4 = 392
-                    );
-                    ui.painter().rect_filled(
-                        rect.expand(5.0),
-                        5.0,
-                        egui::Color32::from_black_alpha(160)
-                    );
-
-                    ui.spacing_mut().item_spacing = egui::vec2(0.0, 2.0);
-                    ui.style_mut().override_text_style = Some(egui::TextStyle::Body);
-
-                    ui.colored_label(egui::Color32::WHITE, "=== VELOCITY DISTRIBUTION ===");
-                    ui.add_space(5.0);
-
-                    // Draw histogram
-                    let graph_rect = egui::Rect::from_min_size(
-                        ui.cursor().min + egui::vec2(10.0, 0.0),
-                        egui::vec2(372.0, 200.0) // Another 40% wider: 260 * 1.4 + 8 = 372
-                    );
-
-                    // Draw background for graph
-                    ui.painter().rect_filled(
-                        graph_rect,
-                        2.0,
-                        egui::Color32::from_gray(30)
-                    );
-
-                    // Draw bars
-                    let bar_width = graph_rect.width() / 16.0;
-                    for (i, &count) in velocity_distribution.iter().enumerate() {
-                        if count > 0 {
-                            let bar_height = if max_count > 0.0 {
-                                (count as f32 / max_count) * (graph_rect.height() - 20.0)
-                            } else {
-                                0.0
-                            };
-
-                            let bar_rect = egui::Rect::from_min_size(
-                                egui::pos2(
-                                    graph_rect.min.x + i as f32 * bar_width + 1.0,
-                                    graph_rect.max.y - bar_height - 10.0
-                                ),
-                                egui::vec2(bar_width - 2.0, bar_height)
-                            );
-
-                            // Color bars based on speed range
-                            let color = if i < 4 {
-                                egui::Color32::from_rgb(255, 100, 100) // Slow = red
-                            } else if i < 12 {
-                                egui::Color32::from_rgb(255, 255, 100) // Medium = yellow
-                            } else {
-                                egui::Color32::from_rgb(100, 255, 100) // Fast = green
-                            };
-
-                            ui.painter().rect_filled(bar_rect, 1.0, color);
-
-                            // Draw count label if there's room
-                            if bar_height > 15.0 {
-                                ui.painter().text(
-                                    bar_rect.center(),
-                                    egui::Align2::CENTER_CENTER,
-                                    count.to_string(),
-                                    egui::FontId::new(10.0, egui::FontFamily::Monospace),
-                                    egui::Color32::BLACK
-                                );
-                            }
-                        }
-                    }
-
-                    // Draw speed labels underneath each bucket (staggered)
-                    for i in 0..16 {
-                        let bucket_center_x = graph_rect.min.x + (i as f32 + 0.5) * bar_width;
-                        let speed_min_mph = i as f32 * bucket_size_mph;
-                        let speed_max_mph = (i + 1) as f32 * bucket_size_mph;
-
-                        // Draw middle value of the speed range
-                        let label = if bucket_size_mph > 0.0 {
-                            let middle_speed = (speed_min_mph + speed_max_mph) / 2.0;
-                            format!("{:.0}", middle_speed)
+        
+        // Bucket labels in the display speed unit
+        let max_speed = units.speed(state.aggregates().max_speed());
+        let bucket_size = if max_speed > 0.0 { max_speed / 16.0 } else { 0.0 };
+        
+        let velocity_rect = self.preferences.overlay_window(ctx, "velocity_graph", "Velocity distribution", egui::pos2(screen.right() - 15.0, 15.0), egui::Align2::RIGHT_TOP, |ui| {
+            ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
+                ui.spacing_mut().item_spacing = egui::vec2(0.0, 2.0);
+                ui.style_mut().override_text_style = Some(egui::TextStyle::Body);
+                
+                // Draw histogram
+                let graph_rect = egui::Rect::from_min_size(
+                    ui.cursor().min + egui::vec2(10.0, 0.0),
+                    egui::vec2(372.0, 200.0) // Another 40% wider: 260 * 1.4 + 8 = 372
+                );
+                
+                // Draw background for graph
+                ui.painter().rect_filled(
+                    graph_rect,
+                    2.0,
+                    egui::Color32::from_gray(30)
+                );
+                
+                // Draw bars
+                let bar_width = graph_rect.width() / 16.0;
+                for (i, &count) in velocity_distribution.iter().enumerate() {
+                    if count > 0 {
+                        let bar_height = if max_count > 0.0 {
+                            (count as f32 / max_count) * (graph_rect.height() - 20.0)
                         } else {
-                            "0".to_string()
+                            0.0
                         };
-
-                        // Stagger labels: even indices on first line, odd indices on second line
-                        let y_offset = if i % 2 == 0 { 2.0 } else { 14.0 };
-
-                        ui.painter().text(
-                            egui::pos2(bucket_center_x, graph_rect.max.y + y_offset),
-                            egui::Align2::CENTER_TOP,
-                            label,
-                            egui::FontId::new(9.0, egui::FontFamily::Monospace),
-                            egui::Color32::WHITE
+                        
+                        let bar_rect = egui::Rect::from_min_size(
+                            egui::pos2(
+                                graph_rect.min.x + i as f32 * bar_width + 1.0,
+                                graph_rect.max.y - bar_height - 10.0
+                            ),
+                            egui::vec2(bar_width - 2.0, bar_height)
                         );
+                        
+                        // Color bars based on speed range
+                        let color = if i < 4 {
+                            egui::Color32::from_rgb(255, 100, 100) // Slow = red
+                        } else if i < 12 {
+                            egui::Color32::from_rgb(255, 255, 100) // Medium = yellow
+                        } else {
+                            egui::Color32::from_rgb(100, 255, 100) // Fast = green
+                        };
+                        
+                        ui.painter().rect_filled(bar_rect, 1.0, color);
+                        
+                        // Draw count label if there's room
+                        if bar_height > 15.0 {
+                            ui.painter().text(
+                                bar_rect.center(),
+                                egui::Align2::CENTER_CENTER,
+                                count.to_string(),
+                                egui::FontId::new(10.0, egui::FontFamily::Monospace),
+                                egui::Color32::BLACK
+                            );
+                        }
                     }
-
-                    // Draw axes labels (positioned below staggered speed labels)
+                }
+                
+                // Draw speed labels underneath each bucket (staggered)
+                for i in 0..16 {
+                    let bucket_center_x = graph_rect.min.x + (i as f32 + 0.5) * bar_width;
+                    let speed_min = i as f32 * bucket_size;
+                    let speed_max = (i + 1) as f32 * bucket_size;
+                    
+                    // Draw middle value of the speed range
+                    let label = if bucket_size > 0.0 {
+                        let middle_speed = (speed_min + speed_max) / 2.0;
+                        format!("{:.0}", middle_speed)
+                    } else {
+                        "0".to_string()
+                    };
+                    
+                    // Stagger labels: even indices on first line, odd indices on second line
+                    let y_offset = if i % 2 == 0 { 2.0 } else { 14.0 };
+                    
                     ui.painter().text(
-                        egui::pos2(graph_rect.min.x, graph_rect.max.y + 28.0),
-                        egui::Align2::LEFT_TOP,
-                        "Speed (mph)",
-                        egui::FontId::new(font_size * 0.8, egui::FontFamily::Monospace),
+                        egui::pos2(bucket_center_x, graph_rect.max.y + y_offset),
+                        egui::Align2::CENTER_TOP,
+                        label,
+                        egui::FontId::new(9.0, egui::FontFamily::Monospace),
                         egui::Color32::WHITE
                     );
-
-                    // Move cursor past the graph (extra space for speed labels)
-                    ui.allocate_space(egui::vec2(392.0, 240.0));
-
-                    ui.add_space(5.0);
-                    ui.label(format!("Total cars: {}", state.active_cars));
-                    ui.label(format!("Max speed: {:.1} mph", max_speed_mph));
-                });
+                }
+                
+                // Draw axes labels (positioned below staggered speed labels)
+                ui.painter().text(
+                    egui::pos2(graph_rect.min.x, graph_rect.max.y + 28.0),
+                    egui::Align2::LEFT_TOP,
+                    format!("Speed ({})", units.speed_unit()),
+                    egui::FontId::new(font_size * 0.8, egui::FontFamily::Monospace),
+                    egui::Color32::WHITE
+                );
+                
+                // Move cursor past the graph (extra space for speed labels)
+                ui.allocate_space(egui::vec2(392.0, 240.0));
+                
+                ui.add_space(5.0);
+                ui.label(format!("Total cars: {}", state.active_cars));
+                ui.label(format!("Max speed: {:.1} {}", max_speed, units.speed_unit()));
             });
-
+        });
+        
         // Pie chart for car behavior types below the velocity graph
-        egui::Area::new(egui::Id::new("pie_chart"))
-            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-15.0, 330.0))
-            .show(ctx, |ui| {
-                ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
-                    // Semi-transparent background
-                    let rect = egui::Rect::from_min_size(
-                        ui.cursor().min,
-                        egui::vec2(280.0, 340.0)
-                    );
-                    ui.painter().rect_filled(
-                        rect.expand(5.0),
-                        5.0,
-                        egui::Color32::from_black_alpha(160)
-                    );
-
-                    ui.spacing_mut().item_spacing = egui::vec2(0.0, 2.0);
-                    ui.style_mut().override_text_style = Some(egui::TextStyle::Body);
-
-                    ui.colored_label(egui::Color32::WHITE, "=== CAR BEHAVIOR DISTRIBUTION ===");
-                    ui.add_space(5.0);
-
-                    // Draw pie chart
-                    let chart_center = egui::pos2(
-                        ui.cursor().min.x + 140.0, // Center horizontally
-                        ui.cursor().min.y + 80.0   // Position vertically
-                    );
-                    let chart_radius = 60.0;
-
-                    let total_cars = state.active_cars as f32;
-                    if total_cars > 0.0 {
-                        let mut start_angle = 0.0;
-                        let behavior_data = [
-                            ("aggressive", behavior_counts.get("aggressive").unwrap_or(&0), [230, 50, 50]),
-                            ("normal", behavior_counts.get("normal").unwrap_or(&0), [50, 150, 230]),
-                            ("cautious", behavior_counts.get("cautious").unwrap_or(&0), [50, 200, 50]),
-                            ("erratic", behavior_counts.get("erratic").unwrap_or(&0), [230, 125, 25]),
-                            ("strategic", behavior_counts.get("strategic").unwrap_or(&0), [180, 50, 230]),
-                        ];
-
-                        for (_behavior_name, &count, color_rgb) in behavior_data.iter() {
-                            if count > 0 {
-                                let slice_angle = (count as f32 / total_cars) * 2.0 * std::f32::consts::PI;
-
-                                // Draw pie slice
-                                let num_segments = (slice_angle * 20.0) as usize + 1;
-                                let mut points = vec![chart_center];
-
-                                for i in 0..=num_segments {
-                                    let angle = start_angle + (i as f32 / num_segments as f32) * slice_angle;
-                                    let x = chart_center.x + chart_radius * angle.cos();
-                                    let y = chart_center.y + chart_radius * angle.sin();
-                                    points.push(egui::pos2(x, y));
-                                }
-
-                                // Create triangle fan for the slice (no stroke to avoid focusing effect)
-                                for i in 1..points.len() - 1 {
-                                    let triangle = [points[0], points[i], points[i + 1]];
-                                    ui.painter().add(egui::epaint::Shape::convex_polygon(
-                                        triangle.to_vec(),
-                                        egui::Color32::from_rgb(color_rgb[0], color_rgb[1], color_rgb[2]),
-                                        egui::Stroke::NONE // Remove stroke to eliminate focusing effect
-                                    ));
-                                }
-
-                                // Draw label at middle of slice if slice is large enough
-                                if slice_angle > 0.2 {
-                                    let label_angle = start_angle + slice_angle / 2.0;
-                                    let label_x = chart_center.x + (chart_radius * 0.7) * label_angle.cos();
-                                    let label_y = chart_center.y + (chart_radius * 0.7) * label_angle.sin();
-
-                                    ui.painter().text(
-                                        egui::pos2(label_x, label_y),
-                                        egui::Align2::CENTER_CENTER,
-                                        count.to_string(),
-                                        egui::FontId::new(12.0, egui::FontFamily::Monospace),
-                                        egui::Color32::WHITE
-                                    );
-                                }
-
-                                start_angle += slice_angle;
+        let pie_top = velocity_rect.map_or(15.0, |rect| rect.bottom() + 10.0);
+        let title = if self.palette.by_car_type() { "Car type distribution" } else { "Car behavior distribution" };
+        self.preferences.overlay_window(ctx, "pie_chart", title, egui::pos2(screen.right() - 15.0, pie_top), egui::Align2::RIGHT_TOP, |ui| {
+            ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
+                ui.spacing_mut().item_spacing = egui::vec2(0.0, 2.0);
+                ui.style_mut().override_text_style = Some(egui::TextStyle::Body);
+                
+                // Draw pie chart
+                let chart_center = egui::pos2(
+                    ui.cursor().min.x + 140.0, // Center horizontally
+                    ui.cursor().min.y + 80.0   // Position vertically
+                );
+                let chart_radius = 60.0;
+                
+                let total_cars = state.active_cars as f32;
+                if total_cars > 0.0 {
+                    let mut start_angle = 0.0;
+                    for &(_, count, color) in &palette_data {
+                        if count > 0 {
+                            let slice_angle = (count as f32 / total_cars) * 2.0 * std::f32::consts::PI;
+                            
+                            // Draw pie slice
+                            let num_segments = (slice_angle * 20.0) as usize + 1;
+                            let mut points = vec![chart_center];
+                            
+                            for i in 0..=num_segments {
+                                let angle = start_angle + (i as f32 / num_segments as f32) * slice_angle;
+                                let x = chart_center.x + chart_radius * angle.cos();
+                                let y = chart_center.y + chart_radius * angle.sin();
+                                points.push(egui::pos2(x, y));
                             }
-                        }
-                    }
-
-                    // Always allocate space for pie chart first
-                    ui.allocate_space(egui::vec2(280.0, 130.0)); // More space for pie chart
-                    ui.add_space(10.0);
-
-                    // Draw legend below pie chart (outside the chart area)
-                    if total_cars > 0.0 {
-                        let behavior_data = [
-                            ("aggressive", behavior_counts.get("aggressive").unwrap_or(&0), [230, 50, 50]),
-                            ("normal", behavior_counts.get("normal").unwrap_or(&0), [50, 150, 230]),
-                            ("cautious", behavior_counts.get("cautious").unwrap_or(&0), [50, 200, 50]),
-                            ("erratic", behavior_counts.get("erratic").unwrap_or(&0), [230, 125, 25]),
-                            ("strategic", behavior_counts.get("strategic").unwrap_or(&0), [180, 50, 230]),
-                        ];
-
-                        for (behavior_name, &count, color_rgb) in behavior_data.iter() {
-                            if count > 0 {
-                                let percentage = (count as f32 / total_cars) * 100.0;
-                                ui.colored_label(
-                                    egui::Color32::from_rgb(color_rgb[0], color_rgb[1], color_rgb[2]),
-                                    format!("● {} {} ({:.1}%)",
-                                        count,
-                                        behavior_name.chars().next().unwrap().to_uppercase().collect::<String>() + &behavior_name[1..],
-                                        percentage
-                                    )
+                            
+                            // Create triangle fan for the slice (no stroke to avoid focusing effect)
+                            for i in 1..points.len() - 1 {
+                                let triangle = [points[0], points[i], points[i + 1]];
+                                ui.painter().add(egui::epaint::Shape::convex_polygon(
+                                    triangle.to_vec(),
+                                    color,
+                                    egui::Stroke::NONE // Remove stroke to eliminate focusing effect
+                                ));
+                            }
+                            
+                            // Draw label at middle of slice if slice is large enough
+                            if slice_angle > 0.2 {
+                                let label_angle = start_angle + slice_angle / 2.0;
+                                let label_x = chart_center.x + (chart_radius * 0.7) * label_angle.cos();
+                                let label_y = chart_center.y + (chart_radius * 0.7) * label_angle.sin();
+                                
+                                ui.painter().text(
+                                    egui::pos2(label_x, label_y),
+                                    egui::Align2::CENTER_CENTER,
+                                    count.to_string(),
+                                    egui::FontId::new(12.0, egui::FontFamily::Monospace),
+                                    egui::Color32::WHITE
                                 );
                             }
+                            
+                            start_angle += slice_angle;
                         }
-                    } else {
-                        ui.label("No cars in simulation");
                     }
-                });
+                }
+                
+                // Always allocate space for pie chart first
+                ui.allocate_space(egui::vec2(280.0, 130.0)); // More space for pie chart
+                ui.add_space(10.0);
+                
+                // Draw legend below pie chart (outside the chart area)
+                if total_cars > 0.0 {
+                    for &(label, count, color) in &palette_data {
+                        if count > 0 {
+                            let percentage = (count as f32 / total_cars) * 100.0;
+                            ui.colored_label(
+                                color,
+                                format!("● {} {} ({:.1}%)", count, label, percentage)
+                            );
+                        }
+                    }
+                } else {
+                    ui.label("No cars in simulation");
+                }
             });
+        });
+        
+        // Charts keep recording while hidden and come back complete
+        if self.show_charts {
+            // Flow, speed and car count over the last few minutes
+            self.history.show(ctx, units);
+            
+            self.diagram.show(ctx, self.history.detector_ids(), units);
+            self.trajectories.show(ctx, units);
+        }
+        self.behaviors.show(ctx, state, &self.palette, units);
+        self.settings.show(ctx, units);
+        self.preferences.show(ctx);
+        self.spawn_tool.show(ctx);
+        self.closure_tool.show(ctx);
+        self.timeline.show(ctx, state.time);
+        self.minimap.show(ctx, state, viewport);
+        if self.car_coloring == CarColoring::Lane {
+            self.render_lane_table(ctx, state, units);
+        }
+    }
+    
+    /// Tint the whole scene for the weather and draw rain streaks or snowflakes over it.
+    /// Particles move with simulation time, so they freeze while paused.
+    fn render_weather(ctx: &egui::Context, weather: Weather, time: f32) {
+        let (tint, particles) = match weather {
+            Weather::Dry => return,
+            Weather::Wet => (egui::Color32::from_rgba_unmultiplied(40, 60, 90, 50), 300),
+            Weather::Ice => (egui::Color32::from_rgba_unmultiplied(200, 220, 255, 40), 200),
+            Weather::Fog => (egui::Color32::from_rgba_unmultiplied(190, 190, 190, 140), 0),
+        };
+        
+        let painter = ctx.layer_painter(egui::LayerId::background());
+        let screen = ctx.screen_rect();
+        painter.rect_filled(screen, 0.0, tint);
+        
+        // Fixed pseudo-random column and phase per particle, falling at its own speed
+        let hash = |i: u32, salt: f32| ((i as f32 * 12.9898 + salt).sin() * 43758.547).fract().abs();
+        for i in 0..particles {
+            let x = screen.left() + hash(i, 0.0) * screen.width();
+            let fall_speed = 0.5 + hash(i, 1.0); // screen heights per second
+            let y = screen.top() + (hash(i, 2.0) + time * fall_speed).fract() * screen.height();
+            if weather == Weather::Wet {
+                painter.line_segment(
+                    [egui::pos2(x, y), egui::pos2(x - 2.0, y + 12.0)],
+                    egui::Stroke::new(1.0, egui::Color32::from_rgba_unmultiplied(170, 190, 230, 120)),
+                );
+            } else {
+                painter.circle_filled(egui::pos2(x, y), 1.5, egui::Color32::from_rgba_unmultiplied(255, 255, 255, 180));
+            }
+        }
     }
 }
 
@@ -459,6 +700,17 @@ impl TextOverlay {
     }
 }
 
+/// Bytes in the largest unit that keeps the number readable
+fn format_bytes(bytes: usize) -> String {
+    if bytes >= 1024 * 1024 * 1024 {
+        format!("{:.1} GB", bytes as f64 / (1024.0 * 1024.0 * 1024.0))
+    } else if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else {
+        format!("{:.0} KB", bytes as f64 / 1024.0)
+    }
+}
+
 // Helper function to create performance overlay text
 pub fn create_performance_overlay(
     performance: &PerformanceMetrics,
@@ -523,7 +775,7 @@ pub fn create_control_hints() -> Vec<TextOverlay> {
         TextOverlay::new("=== SIMULATION CONTROLS ===".to_string(), 10.0, 380.0),
         TextOverlay::new("Space: Pause/Resume".to_string(), 10.0, 400.0),
         TextOverlay::new("R: Reset simulation".to_string(), 10.0, 420.0),
-        TextOverlay::new("1-5: Set speed (0.25x - 4x)".to_string(), 10.0, 440.0),
+        TextOverlay::new("1-9: Set speed (1x - 9x)".to_string(), 10.0, 440.0),
         TextOverlay::new("ESC: Exit simulation".to_string(), 10.0, 460.0),
     ]
 }
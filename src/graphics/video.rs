@@ -0,0 +1,150 @@
+use super::Screenshot;
+use anyhow::{anyhow, Context, Result};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+/// Slack when comparing frame times to simulation time, well under one timestep
+const FRAME_TIME_TOLERANCE: f64 = 1e-4;
+
+/// Decides which simulation steps become video frames: one every `1 / fps` simulated
+/// seconds from the start time, however fast the simulation is playing
+#[derive(Debug, Clone)]
+pub struct VideoClock {
+    start: f64,
+    fps: f64,
+    next_frame: u64, // index of the next frame to take
+}
+
+impl VideoClock {
+    pub fn new(fps: u32, start_time: f32) -> Self {
+        Self {
+            start: start_time as f64,
+            fps: fps.max(1) as f64,
+            next_frame: 0,
+        }
+    }
+    
+    /// Number of frames whose time has come by simulation `time`. More than one when the
+    /// frame rate is above the step rate, so the same image is repeated to keep the pace.
+    pub fn frames_due(&mut self, time: f32) -> u32 {
+        let mut due = 0;
+        while self.start + self.next_frame as f64 / self.fps <= time as f64 + FRAME_TIME_TOLERANCE {
+            self.next_frame += 1;
+            due += 1;
+        }
+        due
+    }
+}
+
+/// Arguments for an ffmpeg process that reads raw RGBA frames on stdin and writes `path`.
+/// A `.gif` extension gets a palette built from the frames, anything else yuv420p, which
+/// most players expect, padded to the even dimensions that needs.
+pub fn ffmpeg_args(path: &Path, width: u32, height: u32, fps: u32) -> Vec<String> {
+    let mut args: Vec<String> = [
+        "-y", "-loglevel", "error",
+        "-f", "rawvideo", "-pix_fmt", "rgba",
+    ].iter().map(|arg| arg.to_string()).collect();
+    args.extend([
+        "-s".to_string(), format!("{}x{}", width, height),
+        "-framerate".to_string(), fps.to_string(),
+        "-i".to_string(), "-".to_string(),
+    ]);
+    
+    let gif = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("gif"));
+    if gif {
+        args.extend(["-vf", "split[a][b];[a]palettegen[p];[b][p]paletteuse"].map(String::from));
+    } else {
+        args.extend(["-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2", "-pix_fmt", "yuv420p"].map(String::from));
+    }
+    args.push(path.to_string_lossy().into_owned());
+    args
+}
+
+/// Encodes off-screen renders of the simulation into a video file through an `ffmpeg`
+/// process, one frame per `1 / fps` simulated seconds
+pub struct VideoRecorder {
+    path: PathBuf,
+    width: u32,
+    height: u32,
+    clock: VideoClock,
+    ffmpeg: Child,
+    stdin: Option<BufWriter<ChildStdin>>,
+    frames_written: u64,
+}
+
+impl VideoRecorder {
+    pub fn start(path: impl Into<PathBuf>, (width, height): (u32, u32), fps: u32, start_time: f32) -> Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut ffmpeg = Command::new("ffmpeg")
+            .args(ffmpeg_args(&path, width, height, fps))
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("Failed to start ffmpeg, which video recording needs on the PATH")?;
+        let stdin = ffmpeg.stdin.take().map(BufWriter::new);
+        
+        Ok(Self {
+            path,
+            width,
+            height,
+            clock: VideoClock::new(fps, start_time),
+            ffmpeg,
+            stdin,
+            frames_written: 0,
+        })
+    }
+    
+    /// Resolution frames must be rendered at
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+    
+    /// See [`VideoClock::frames_due`]
+    pub fn frames_due(&mut self, time: f32) -> u32 {
+        self.clock.frames_due(time)
+    }
+    
+    /// Append `frame` to the video `copies` times
+    pub fn write_frame(&mut self, frame: &Screenshot, copies: u32) -> Result<()> {
+        if (frame.width, frame.height) != (self.width, self.height) {
+            return Err(anyhow!("Frame is {}x{}, the video is {}x{}", frame.width, frame.height, self.width, self.height));
+        }
+        let stdin = self.stdin.as_mut().ok_or_else(|| anyhow!("Video is already finished"))?;
+        for _ in 0..copies {
+            stdin.write_all(&frame.pixels).context("ffmpeg stopped reading frames")?;
+            self.frames_written += 1;
+        }
+        Ok(())
+    }
+    
+    pub fn frames_written(&self) -> u64 {
+        self.frames_written
+    }
+    
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    
+    /// Close the frame stream and wait for ffmpeg to write the file
+    pub fn finish(&mut self) -> Result<()> {
+        if let Some(mut stdin) = self.stdin.take() {
+            stdin.flush()?;
+        }
+        let status = self.ffmpeg.wait()?;
+        if !status.success() {
+            return Err(anyhow!("ffmpeg exited with {}", status));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for VideoRecorder {
+    fn drop(&mut self) {
+        // Closing stdin lets ffmpeg end on its own rather than linger after we exit
+        self.stdin.take();
+        let _ = self.ffmpeg.wait();
+    }
+}
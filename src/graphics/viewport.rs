@@ -1,15 +1,39 @@
 use winit::event::{ElementState, MouseButton, MouseScrollDelta};
 use winit::keyboard::{KeyCode, PhysicalKey};
-use nalgebra::{Matrix4, Vector3};
+use nalgebra::{Matrix4, Point3, Vector3, Vector4};
+use std::f32::consts::PI;
+use crate::simulation::{CarId, SimulationState};
+
+/// Meters between draw layers in the perspective view. The layers are a meter apart for the
+/// depth test, which would float lane lines and markers visibly above the road.
+pub const PERSPECTIVE_LAYER_SPACING: f32 = 0.05;
+
+/// Vertical field of view of the perspective camera
+const PERSPECTIVE_FOV: f32 = PI / 4.0;
+
+/// Tilt from straight down the perspective view starts at, and the most it can be tilted so
+/// the horizon stays off screen
+const DEFAULT_TILT: f32 = PI / 4.0;
+const MAX_TILT: f32 = PI / 3.0;
+
+/// Radians the camera orbits or tilts per pixel of right-drag
+const ORBIT_SPEED: f32 = 0.005;
 
 pub struct Viewport {
     // Camera properties
     pub position: Vector3<f32>,
     pub zoom: f32,
     pub target: Vector3<f32>,
+    pub rotation: f32, // radians, counter-clockwise rotation of the world on screen
+    
+    // Perspective camera, orbiting the position
+    perspective: bool,
+    tilt: f32, // radians from looking straight down
+    yaw: f32, // radians, added to the rotation
     
     // Input state
     is_dragging: bool,
+    is_orbiting: bool,
     last_mouse_pos: (f32, f32),
     mouse_pos: (f32, f32),
     
@@ -21,6 +45,13 @@ pub struct Viewport {
     target_position: Vector3<f32>,
     target_zoom: f32,
     animation_speed: f32,
+    target_rotation: f32,
+    target_tilt: f32,
+    target_yaw: f32,
+    
+    // Follow camera
+    follow_target: Option<CarId>,
+    follow_rotation: bool, // keep the followed car's heading pointing up
     
     // Controls
     pan_speed: f32,
@@ -35,7 +66,12 @@ impl Viewport {
             position: Vector3::new(0.0, 0.0, 0.0),
             zoom: 1.0,
             target: Vector3::new(0.0, 0.0, 0.0),
+            rotation: 0.0,
+            perspective: false,
+            tilt: 0.0,
+            yaw: 0.0,
             is_dragging: false,
+            is_orbiting: false,
             last_mouse_pos: (0.0, 0.0),
             mouse_pos: (0.0, 0.0),
             width,
@@ -43,6 +79,11 @@ impl Viewport {
             target_position: Vector3::new(0.0, 0.0, 0.0),
             target_zoom: 1.0,
             animation_speed: 8.0,
+            target_rotation: 0.0,
+            target_tilt: 0.0,
+            target_yaw: 0.0,
+            follow_target: None,
+            follow_rotation: false,
             pan_speed: 1.0,
             zoom_speed: 0.1,
             min_zoom: 0.1,
@@ -61,6 +102,7 @@ impl Viewport {
                 match state {
                     ElementState::Pressed => {
                         self.is_dragging = true;
+                        self.set_follow_target(None);
                         self.last_mouse_pos = self.mouse_pos;
                     }
                     ElementState::Released => {
@@ -68,6 +110,10 @@ impl Viewport {
                     }
                 }
             }
+            MouseButton::Right => {
+                // Right-drag orbits and tilts the perspective camera
+                self.is_orbiting = self.perspective && state == ElementState::Pressed;
+            }
             _ => {}
         }
     }
@@ -78,11 +124,17 @@ impl Viewport {
         
         if self.is_dragging {
             let delta_x = (x - self.last_mouse_pos.0) / self.zoom;
-            let delta_y = (y - self.last_mouse_pos.1) / self.zoom;
+            let delta_y = -(y - self.last_mouse_pos.1) / self.zoom; // Flip Y axis
             
             // Convert screen coordinates to world coordinates
+            let (delta_x, delta_y) = self.unrotate(delta_x, delta_y);
             self.target_position.x -= delta_x * self.pan_speed;
-            self.target_position.y += delta_y * self.pan_speed; // Flip Y axis
+            self.target_position.y -= delta_y * self.pan_speed;
+        }
+        
+        if self.is_orbiting {
+            self.target_yaw += (x - self.last_mouse_pos.0) * ORBIT_SPEED;
+            self.target_tilt = (self.target_tilt - (y - self.last_mouse_pos.1) * ORBIT_SPEED).clamp(0.0, MAX_TILT);
         }
     }
     
@@ -106,23 +158,31 @@ impl Viewport {
             let movement_speed = 50.0 / self.zoom;
             
             if let PhysicalKey::Code(keycode) = input.physical_key {
+                // Screen-space movement direction for the arrow keys
+                let movement = match keycode {
+                    KeyCode::ArrowUp | KeyCode::KeyW => Some((0.0, movement_speed)),
+                    KeyCode::ArrowDown | KeyCode::KeyS => Some((0.0, -movement_speed)),
+                    KeyCode::ArrowLeft | KeyCode::KeyA => Some((-movement_speed, 0.0)),
+                    KeyCode::ArrowRight | KeyCode::KeyD => Some((movement_speed, 0.0)),
+                    _ => None,
+                };
+                if let Some((dx, dy)) = movement {
+                    self.set_follow_target(None);
+                    let (dx, dy) = self.unrotate(dx, dy);
+                    self.target_position.x += dx;
+                    self.target_position.y += dy;
+                }
+                
                 match keycode {
-                    KeyCode::ArrowUp | KeyCode::KeyW => {
-                        self.target_position.y += movement_speed;
-                    }
-                    KeyCode::ArrowDown | KeyCode::KeyS => {
-                        self.target_position.y -= movement_speed;
-                    }
-                    KeyCode::ArrowLeft | KeyCode::KeyA => {
-                        self.target_position.x -= movement_speed;
-                    }
-                    KeyCode::ArrowRight | KeyCode::KeyD => {
-                        self.target_position.x += movement_speed;
-                    }
                     KeyCode::Home => {
                         // Reset view to origin
+                        self.set_follow_target(None);
                         self.target_position = Vector3::new(0.0, 0.0, 0.0);
                         self.target_zoom = 1.0;
+                        if self.perspective {
+                            self.target_tilt = DEFAULT_TILT;
+                            self.target_yaw = 0.0;
+                        }
                     }
                     KeyCode::Equal | KeyCode::NumpadAdd => {
                         self.target_zoom = (self.target_zoom * 1.2).min(self.max_zoom);
@@ -136,6 +196,27 @@ impl Viewport {
         }
     }
     
+    /// Point the camera at the followed car, if any. Called every frame before `update`
+    /// so the usual smoothing applies; following stops once the car leaves the simulation.
+    pub fn track(&mut self, state: &SimulationState) {
+        let Some(id) = self.follow_target else {
+            return;
+        };
+        
+        match state.get_car(id) {
+            Some(car) => {
+                self.target_position = Vector3::new(car.position.x, car.position.y, 0.0);
+                if self.follow_rotation {
+                    self.target_rotation = PI / 2.0 - car.heading;
+                }
+            }
+            None => {
+                log::info!("Car {} left the simulation - no longer following", id);
+                self.set_follow_target(None);
+            }
+        }
+    }
+    
     pub fn update(&mut self) {
         let dt = 1.0 / 60.0; // Assume 60 FPS for smooth animation
         let interpolation_factor = 1.0 - (-self.animation_speed * dt).exp();
@@ -143,26 +224,66 @@ impl Viewport {
         // Smoothly interpolate to target position and zoom
         self.position += (self.target_position - self.position) * interpolation_factor;
         self.zoom += (self.target_zoom - self.zoom) * interpolation_factor;
+        
+        // Rotate the short way round
+        let turn = (self.target_rotation - self.rotation + PI).rem_euclid(2.0 * PI) - PI;
+        self.rotation = (self.rotation + turn * interpolation_factor).rem_euclid(2.0 * PI);
+        self.tilt += (self.target_tilt - self.tilt) * interpolation_factor;
+        self.yaw += (self.target_yaw - self.yaw) * interpolation_factor;
     }
     
     pub fn get_view_matrix(&self) -> Matrix4<f32> {
+        self.view_matrix_for(self.width, self.height)
+    }
+    
+    /// The view matrix for an image of another size, showing the same width of the world
+    pub fn view_matrix_for(&self, width: f32, height: f32) -> Matrix4<f32> {
+        if self.perspective {
+            return self.perspective_matrix_for(width, height);
+        }
+        
         // Create orthographic projection matrix
-        let aspect_ratio = self.width / self.height;
+        let aspect_ratio = width / height;
         let view_width = 400.0 / self.zoom; // Base view width
         let view_height = view_width / aspect_ratio;
         
-        let left = self.position.x - view_width / 2.0;
-        let right = self.position.x + view_width / 2.0;
-        let bottom = self.position.y - view_height / 2.0;
-        let top = self.position.y + view_height / 2.0;
+        let left = -view_width / 2.0;
+        let right = view_width / 2.0;
+        let bottom = -view_height / 2.0;
+        let top = view_height / 2.0;
         let near = -100.0;
         let far = 100.0;
         
-        // Create orthographic projection matrix
-        Matrix4::new_orthographic(left, right, bottom, top, near, far)
+        // Move the camera position to the origin, rotate about it, then project
+        let projection = gl_to_wgpu() * Matrix4::new_orthographic(left, right, bottom, top, near, far);
+        let rotation = Matrix4::from_euler_angles(0.0, 0.0, self.rotation);
+        let translation = Matrix4::new_translation(&-self.position);
+        projection * rotation * translation
     }
     
+    /// Look at the position from above and behind, the screen's up direction pointing away
+    /// from the camera. Untilted it shows the same width of the road as the top-down view.
+    fn perspective_matrix_for(&self, width: f32, height: f32) -> Matrix4<f32> {
+        let aspect_ratio = width / height;
+        let distance = 200.0 / self.zoom / ((PERSPECTIVE_FOV / 2.0).tan() * aspect_ratio);
+        
+        let heading = self.rotation + self.yaw;
+        let forward = Vector3::new(heading.sin(), heading.cos(), 0.0);
+        let target = Point3::new(self.position.x, self.position.y, 0.0);
+        let eye = target - forward * (distance * self.tilt.sin()) + Vector3::z() * (distance * self.tilt.cos());
+        
+        let projection = gl_to_wgpu() * Matrix4::new_perspective(aspect_ratio, PERSPECTIVE_FOV, distance * 0.01, distance * 20.0);
+        let view = Matrix4::look_at_rh(&eye, &target, &forward);
+        let squash = Matrix4::new_nonuniform_scaling(&Vector3::new(1.0, 1.0, PERSPECTIVE_LAYER_SPACING));
+        projection * view * squash
+    }
+    
+    /// The point on the ground under a screen position
     pub fn screen_to_world(&self, screen_x: f32, screen_y: f32) -> Vector3<f32> {
+        if self.perspective {
+            return self.ground_under(screen_x, screen_y);
+        }
+        
         let aspect_ratio = self.width / self.height;
         let view_width = 400.0 / self.zoom;
         let view_height = view_width / aspect_ratio;
@@ -173,20 +294,30 @@ impl Viewport {
         let norm_y = 1.0 - (2.0 * screen_y / self.height); // Flip Y
         
         // Convert to world coordinates
-        let world_x = self.position.x + norm_x * view_width / 2.0;
-        let world_y = self.position.y + norm_y * view_height / 2.0;
+        let (offset_x, offset_y) = self.unrotate(norm_x * view_width / 2.0, norm_y * view_height / 2.0);
+        let world_x = self.position.x + offset_x;
+        let world_y = self.position.y + offset_y;
         
         Vector3::new(world_x, world_y, 0.0)
     }
     
     pub fn world_to_screen(&self, world_pos: &Vector3<f32>) -> (f32, f32) {
+        if self.perspective {
+            let clip = self.get_view_matrix() * Vector4::new(world_pos.x, world_pos.y, world_pos.z, 1.0);
+            let (norm_x, norm_y) = (clip.x / clip.w, clip.y / clip.w);
+            return ((norm_x + 1.0) * self.width / 2.0, (1.0 - norm_y) * self.height / 2.0);
+        }
+        
         let aspect_ratio = self.width / self.height;
         let view_width = 400.0 / self.zoom;
         let view_height = view_width / aspect_ratio;
         
         // Convert world coordinates to normalized coordinates
-        let norm_x = (world_pos.x - self.position.x) / (view_width / 2.0);
-        let norm_y = (world_pos.y - self.position.y) / (view_height / 2.0);
+        let (cos, sin) = (self.rotation.cos(), self.rotation.sin());
+        let offset_x = world_pos.x - self.position.x;
+        let offset_y = world_pos.y - self.position.y;
+        let norm_x = (offset_x * cos - offset_y * sin) / (view_width / 2.0);
+        let norm_y = (offset_x * sin + offset_y * cos) / (view_height / 2.0);
         
         // Convert to screen coordinates
         let screen_x = (norm_x + 1.0) * self.width / 2.0;
@@ -195,6 +326,55 @@ impl Viewport {
         (screen_x, screen_y)
     }
     
+    /// World positions of the screen's corners, clockwise from the top-left
+    pub fn visible_corners(&self) -> [Vector3<f32>; 4] {
+        [
+            self.screen_to_world(0.0, 0.0),
+            self.screen_to_world(self.width, 0.0),
+            self.screen_to_world(self.width, self.height),
+            self.screen_to_world(0.0, self.height),
+        ]
+    }
+    
+    /// Pan smoothly to center on `position`, stopping any follow
+    pub fn look_at(&mut self, position: Vector3<f32>) {
+        self.set_follow_target(None);
+        self.target_position = position;
+    }
+    
+    /// Switch between the top-down view and the perspective camera, which starts tilted
+    pub fn set_perspective(&mut self, enabled: bool) {
+        self.perspective = enabled;
+        self.is_orbiting = false;
+        if enabled {
+            self.target_tilt = DEFAULT_TILT;
+        } else {
+            self.tilt = 0.0;
+            self.yaw = 0.0;
+            self.target_tilt = 0.0;
+            self.target_yaw = 0.0;
+        }
+    }
+    
+    pub fn perspective(&self) -> bool {
+        self.perspective
+    }
+    
+    /// Radians the perspective camera is tilted from looking straight down
+    pub fn tilt(&self) -> f32 {
+        self.tilt
+    }
+    
+    pub fn set_tilt(&mut self, tilt: f32) {
+        self.tilt = tilt.clamp(0.0, MAX_TILT);
+        self.target_tilt = self.tilt;
+    }
+    
+    /// Where the pointer last was, in pixels from the top-left
+    pub fn mouse_position(&self) -> (f32, f32) {
+        self.mouse_pos
+    }
+    
     pub fn get_zoom(&self) -> f32 {
         self.zoom
     }
@@ -212,4 +392,61 @@ impl Viewport {
         self.zoom = zoom.clamp(self.min_zoom, self.max_zoom);
         self.target_zoom = self.zoom;
     }
+    
+    pub fn set_follow_target(&mut self, target: Option<CarId>) {
+        self.follow_target = target;
+        if target.is_none() {
+            self.target_rotation = 0.0;
+        }
+    }
+    
+    pub fn follow_target(&self) -> Option<CarId> {
+        self.follow_target
+    }
+    
+    /// Keep the followed car's heading pointing up instead of north
+    pub fn set_follow_rotation(&mut self, enabled: bool) {
+        self.follow_rotation = enabled;
+        if !enabled {
+            self.target_rotation = 0.0;
+        }
+    }
+    
+    pub fn follow_rotation(&self) -> bool {
+        self.follow_rotation
+    }
+    
+    /// Where the ray through a screen position meets the ground. Rays above the horizon give
+    /// the far end of the view instead.
+    fn ground_under(&self, screen_x: f32, screen_y: f32) -> Vector3<f32> {
+        let Some(inverse) = self.get_view_matrix().try_inverse() else {
+            return self.position;
+        };
+        let norm_x = (2.0 * screen_x / self.width) - 1.0;
+        let norm_y = 1.0 - (2.0 * screen_y / self.height);
+        let near = inverse.transform_point(&Point3::new(norm_x, norm_y, 0.0));
+        let far = inverse.transform_point(&Point3::new(norm_x, norm_y, 1.0));
+        
+        let t = if near.z > far.z { (near.z / (near.z - far.z)).min(1.0) } else { 1.0 };
+        let ground = near + (far - near) * t;
+        Vector3::new(ground.x, ground.y, 0.0)
+    }
+    
+    /// Convert a screen-aligned offset into world axes
+    fn unrotate(&self, x: f32, y: f32) -> (f32, f32) {
+        let rotation = self.rotation + self.yaw;
+        let (cos, sin) = (rotation.cos(), rotation.sin());
+        (x * cos + y * sin, -x * sin + y * cos)
+    }
+}
+
+/// OpenGL projections map depth to [-1, 1]; wgpu clips to [0, 1], so it is halved and
+/// shifted, which puts higher layers nearer the camera
+fn gl_to_wgpu() -> Matrix4<f32> {
+    Matrix4::new(
+        1.0, 0.0, 0.0, 0.0,
+        0.0, 1.0, 0.0, 0.0,
+        0.0, 0.0, 0.5, 0.5,
+        0.0, 0.0, 0.0, 1.0,
+    )
 }
\ No newline at end of file
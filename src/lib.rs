@@ -2,6 +2,11 @@ pub mod config;
 pub mod simulation;
 pub mod graphics;
 pub mod compute;
+pub mod export;
+pub mod replay;
+pub mod server;
+pub mod sweep;
+pub mod scenario;
 
 pub use simulation::*;
 pub use config::*;
\ No newline at end of file
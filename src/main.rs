@@ -1,24 +1,42 @@
 use anyhow::Result;
 use log::info;
-use std::time::Instant;
-use clap::{Parser, ValueEnum};
+use web_time::Instant;
+use clap::{Parser, Subcommand, ValueEnum};
 use rand::Rng;
+use std::sync::{Arc, Mutex, MutexGuard};
 use winit::{
     event::*,
-    event_loop::EventLoop,
+    event_loop::{EventLoop, EventLoopWindowTarget},
 };
 
+#[cfg(not(target_arch = "wasm32"))]
+use traffic_sim::{config::ConfigWatcher, sweep::{self, SweepConfig}};
+#[cfg(not(target_arch = "wasm32"))]
+use traffic_sim::graphics::VideoRecorder;
 use traffic_sim::{
-    config::SimulationConfig,
-    simulation::{SimulationState, PerformanceTracker},
-    graphics::GraphicsSystem,
-    compute::{ComputeBackend, SimulationBackend},
+    config::{read_config_file, save_closures, CarsConfig, ConfigOverride, LaneClosure, SimulationConfig, UnitSystem, Validate, World, WorldConfig},
+    simulation::{closure_between, Point, NOISE_CELL_SIZE, RewindBuffer, SimulationState, PerformanceTracker},
+    graphics::{CarColoring, GraphicsSystem, QualityManager, RunStatus, UiPreferences, SPEED_RANGE, SPEED_STEP},
+    compute::{ComputeBackend, DivergenceMonitor, DivergenceTolerance, SimulationBackend, SimulationRunner},
+    export::{ConflictExporter, DetectorExporter, ExportFormat, FcdExporter, MetricsExporter, NoiseExporter, QueueExporter, SummaryCollector, TrajectoryExporter, TravelTimeExporter, TripExporter},
+    replay::{ReplayRecorder, ReplayPlayer},
+    server::{ServerCommand, TelemetryServer},
+    scenario::{load_events, EventSchedule, ScenarioBundle, BUNDLE_EXTENSION},
 };
 
+/// Fixed simulation timestep in seconds, independent of the display frame rate
+const SIMULATION_DT: f32 = 1.0 / 60.0;
+
+/// Longest real-time gap a single frame may catch up on; longer hitches slow the simulation instead
+const MAX_FRAME_TIME: f32 = 0.25;
+
 #[derive(Parser)]
 #[command(name = "traffic-sim")]
 #[command(about = "GPU-accelerated traffic simulation with interactive visualization")]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    
     /// Simulation compute backend
     #[arg(short, long, value_enum, default_value_t = Backend::Cpu)]
     backend: Backend,
@@ -27,10 +45,20 @@ struct Args {
     #[arg(short, long, default_value = "route.toml")]
     route: String,
     
+    /// World file listing several route files to simulate side by side, with cars moving
+    /// between them, instead of --route (headless only)
+    #[arg(long, value_name = "PATH", requires = "headless", conflicts_with_all = ["record", "serve", "metrics_out", "detectors_out", "trajectories_out", "fcd_out", "overrides"])]
+    world: Option<String>,
+    
     /// Cars configuration file
     #[arg(short, long, default_value = "cars.toml")]
     cars: String,
     
+    /// Scenario bundle (.tsim) with the route and cars configurations, timed events and
+    /// optionally the seed, instead of --route and --cars
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["world", "replay", "compare_route", "compare_cars", "compare_overrides"])]
+    scenario: Option<String>,
+    
     /// Random seed for reproducible simulations
     #[arg(short, long)]
     seed: Option<u64>,
@@ -39,9 +67,287 @@ struct Args {
     #[arg(short, long)]
     verbose: bool,
     
-    /// UI font size (default: 14.0)
-    #[arg(long, default_value_t = 14.0)]
-    font_size: f32,
+    /// UI font size for this run, over the one saved in the Preferences window (default: 14.0)
+    #[arg(long)]
+    font_size: Option<f32>,
+    
+    /// Units speeds and distances are shown in, over the ones saved in the Preferences window
+    #[arg(long, value_enum)]
+    units: Option<Units>,
+    
+    /// Run without a window and print summary statistics when finished
+    #[arg(long)]
+    headless: bool,
+    
+    /// Simulated seconds to run in headless mode (default: simulation_duration from the cars file)
+    #[arg(long)]
+    duration: Option<f32>,
+    
+    /// Write per-tick aggregate metrics to this file
+    #[arg(long)]
+    metrics_out: Option<String>,
+    
+    /// Format of the metrics, detector, trip and trajectory files
+    #[arg(long, value_enum, default_value_t = MetricsFormat::Csv)]
+    metrics_format: MetricsFormat,
+    
+    /// Write per-interval loop detector readings to this file
+    #[arg(long, value_name = "PATH")]
+    detectors_out: Option<String>,
+    
+    /// Run the scenario headless this many times with consecutive seeds from --seed and report
+    /// the mean and 95% confidence interval of every summary metric
+    #[arg(long, value_name = "N", requires = "headless", value_parser = clap::value_parser!(u32).range(1..),
+          conflicts_with_all = ["world", "record", "serve", "load_checkpoint", "save_checkpoint", "summary_out", "metrics_out", "detectors_out",
+                                "trips_out", "conflicts_out", "queues_out", "travel_times_out", "trajectories_out", "fcd_out", "noise_out"])]
+    replications: Option<u32>,
+    
+    /// Replications simulated at the same time (default: number of CPU cores)
+    #[arg(long, value_name = "N", requires = "replications")]
+    jobs: Option<usize>,
+    
+    /// File each replication's summary metrics are written to, one row per run; the mean and
+    /// confidence intervals go next to it in <name>_aggregate.csv
+    #[arg(long, value_name = "PATH", default_value = "replications.csv")]
+    replications_out: String,
+    
+    /// Write every completed trip (travel time, free-flow time, delay) to this file
+    #[arg(long, value_name = "PATH", conflicts_with = "replay")]
+    trips_out: Option<String>,
+    
+    /// Write every time-to-collision and post-encroachment conflict to this file
+    #[arg(long, value_name = "PATH", conflicts_with = "replay")]
+    conflicts_out: Option<String>,
+    
+    /// Write the head, tail, length and growth rate of every queue each simulated second to this file
+    #[arg(long, value_name = "PATH")]
+    queues_out: Option<String>,
+    
+    /// Write every car's travel time over each travel time segment of the route to this file
+    #[arg(long, value_name = "PATH", conflicts_with = "replay")]
+    travel_times_out: Option<String>,
+    
+    /// Write every car's position along the road at a fixed interval (NGSIM-style trajectories)
+    #[arg(long, value_name = "PATH")]
+    trajectories_out: Option<String>,
+    
+    /// Simulated seconds between trajectory samples
+    #[arg(long, value_name = "SECS", default_value_t = 0.5)]
+    trajectory_interval: f32,
+    
+    /// Write vehicle positions in SUMO floating car data (FCD) XML
+    #[arg(long, value_name = "PATH")]
+    fcd_out: Option<String>,
+    
+    /// Simulated seconds between FCD timesteps (default: every tick)
+    #[arg(long, value_name = "SECS")]
+    fcd_period: Option<f32>,
+    
+    /// Write the traffic noise map when the run ends: a GeoTIFF raster for .tif/.tiff, CSV otherwise
+    #[arg(long, value_name = "PATH", conflicts_with = "replay")]
+    noise_out: Option<String>,
+    
+    /// Meters along each side of the noise map cells
+    #[arg(long, value_name = "METERS", default_value_t = NOISE_CELL_SIZE)]
+    noise_cell: f32,
+    
+    /// Record every simulation tick to a replay file
+    #[arg(long, value_name = "PATH")]
+    record: Option<String>,
+    
+    /// Play back a recorded replay file instead of running the simulation
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["headless", "record"])]
+    replay: Option<String>,
+    
+    /// Start from a saved checkpoint instead of an empty road
+    #[arg(long, value_name = "PATH", conflicts_with = "replay")]
+    load_checkpoint: Option<String>,
+    
+    /// Save a checkpoint of the final state when the run ends
+    #[arg(long, value_name = "PATH", conflicts_with = "replay")]
+    save_checkpoint: Option<String>,
+    
+    /// Checkpoint file written by F5 and read by F9 in interactive mode
+    #[arg(long, value_name = "PATH", default_value = "checkpoint.bin")]
+    checkpoint: String,
+    
+    /// File the fundamental diagram window exports its points to
+    #[arg(long, value_name = "PATH", default_value = "fundamental_diagram.csv")]
+    diagram_out: String,
+    
+    /// Directory F12 and --screenshot-at write PNG screenshots to
+    #[arg(long, value_name = "DIR", default_value = ".")]
+    screenshot_dir: String,
+    
+    /// Render screenshots off-screen at this resolution instead of capturing the window,
+    /// e.g. 3840x2160 for figures. Off-screen captures show the scene without the UI.
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = traffic_sim::graphics::parse_capture_size)]
+    screenshot_size: Option<(u32, u32)>,
+    
+    /// Take a screenshot when the simulation reaches each of these times (seconds, comma-separated)
+    #[arg(long, value_name = "SECS", value_delimiter = ',', conflicts_with = "headless")]
+    screenshot_at: Vec<f32>,
+    
+    /// Render the road off-screen every 1/--video-fps simulated seconds and encode the frames
+    /// into this file with ffmpeg (.mp4, .webm, .gif, ...), whatever the playback speed
+    #[arg(long, value_name = "PATH", conflicts_with = "headless")]
+    record_video: Option<String>,
+    
+    /// Frames per simulated second of the recorded video
+    #[arg(long, value_name = "FPS", default_value_t = 30, value_parser = clap::value_parser!(u32).range(1..))]
+    video_fps: u32,
+    
+    /// Resolution of the recorded video
+    #[arg(long, value_name = "WIDTHxHEIGHT", default_value = "1280x720", value_parser = traffic_sim::graphics::parse_capture_size)]
+    video_size: (u32, u32),
+    
+    /// Minutes of history shown in the time-series plots
+    #[arg(long, value_name = "MINUTES", default_value_t = 5.0)]
+    plot_window: f32,
+    
+    /// Stream every tick over a WebSocket at this address (e.g. 127.0.0.1:9001) and accept
+    /// control commands. Headless runs are paced to real time while serving.
+    #[arg(long, value_name = "ADDR", conflicts_with = "replay")]
+    serve: Option<String>,
+    
+    /// Write the end-of-run statistics summary to a JSON file
+    #[arg(long, value_name = "PATH", conflicts_with = "replay")]
+    summary_out: Option<String>,
+    
+    /// Override a configuration value after the files are read, e.g.
+    /// `--set cars.simulation.spawn_rate=2.5 --set route.traffic_rules.speed_limit=33`
+    #[arg(long = "set", value_name = "KEY=VALUE", conflicts_with = "replay")]
+    overrides: Vec<ConfigOverride>,
+    
+    /// Run a donut route as a closed ring: this many cars spread evenly around it at the
+    /// start, none spawning or leaving after. Same as `--set cars.simulation.ring_cars=N`
+    #[arg(long, value_name = "N", conflicts_with = "replay")]
+    ring_cars: Option<u32>,
+    
+    /// Split the screen and run this route file next to --route, with the same seed
+    /// (comparison mode). The road must be laid out the same, ramps and meters may differ.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["headless", "replay", "load_checkpoint"])]
+    compare_route: Option<String>,
+    
+    /// Cars file of the run on the right in comparison mode (default: --cars)
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["headless", "replay", "load_checkpoint"])]
+    compare_cars: Option<String>,
+    
+    /// Override a configuration value of the run on the right only, on top of --set;
+    /// comparison mode with the same files when given alone
+    #[arg(long = "compare-set", value_name = "KEY=VALUE", conflicts_with_all = ["headless", "replay", "load_checkpoint"])]
+    compare_overrides: Vec<ConfigOverride>,
+    
+    /// Step the other compute backend in lockstep with this run, show how far the two drift
+    /// apart and pause the first time they disagree beyond --divergence-position or --divergence-velocity
+    #[arg(long, conflicts_with_all = ["headless", "replay"])]
+    check_divergence: bool,
+    
+    /// Meters a car may be from its position in the other backend's run before pausing
+    #[arg(long, value_name = "METERS", default_value_t = DivergenceTolerance::default().position, requires = "check_divergence")]
+    divergence_position: f32,
+    
+    /// Meters per second a car's velocity may differ in the other backend's run before pausing
+    #[arg(long, value_name = "M/S", default_value_t = DivergenceTolerance::default().velocity, requires = "check_divergence")]
+    divergence_velocity: f32,
+    
+    /// Step the simulation on a thread of its own and draw the newest state it reached each
+    /// frame, so slow frames never hold it up. Cars move by whole steps, without interpolation.
+    #[arg(long, conflicts_with_all = ["headless", "replay", "compare_route", "compare_cars", "compare_overrides", "check_divergence"])]
+    sim_thread: bool,
+    
+    /// Step as fast as the backend goes instead of at the simulation speed, showing the
+    /// road now and then as the frames come around
+    #[arg(long, requires = "sim_thread")]
+    max_speed: bool,
+    
+    /// Leave each GPU step running while the frame is drawn and pick up its results on the
+    /// next one, for more steps per second at the cost of showing the road a step late
+    #[arg(long, conflicts_with_all = ["headless", "replay", "check_divergence"])]
+    gpu_pipeline: bool,
+    
+    /// Do not reload the route and cars files when they change on disk
+    #[arg(long)]
+    no_watch: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run every combination of the parameters in a sweep file headless and write one
+    /// results row per run to a CSV file
+    Sweep {
+        /// Sweep definition (TOML)
+        file: String,
+        
+        /// Runs simulated at the same time (default: number of CPU cores)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+        
+        /// Results file (default: the sweep file with a .csv extension)
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<String>,
+    },
+    /// Print example configuration files or check a pair of them
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+    /// Bundle --route and --cars, laid over their bases and with the --set overrides
+    /// applied, the --seed and an events file into one .tsim scenario file
+    Pack {
+        /// Scenario file to write (.tsim is added without an extension)
+        output: String,
+        
+        /// TOML file of [[events]] to carry out during the run
+        #[arg(long, value_name = "PATH")]
+        events: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print an example route file, every section explained in comments
+    ExampleRoute {
+        /// Road layout of the example
+        #[arg(value_enum, default_value_t = ExampleGeometry::Donut)]
+        geometry: ExampleGeometry,
+    },
+    /// Print an example cars file, every section explained in comments, spawning at the
+    /// entries of the example route of a geometry
+    ExampleCars {
+        /// Road layout of the example route to spawn on
+        #[arg(value_enum, default_value_t = ExampleGeometry::Donut)]
+        geometry: ExampleGeometry,
+    },
+    /// Validate a route and cars file and check that they agree with each other
+    Check {
+        /// Route configuration file
+        route: String,
+        
+        /// Cars configuration file
+        cars: String,
+    },
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+enum ExampleGeometry {
+    /// Circular highway with interior entrances and exterior exits
+    Donut,
+    /// Two highways crossing with loop ramps
+    Cloverleaf,
+    /// Grid of roundabouts
+    Grid,
+}
+
+impl ExampleGeometry {
+    /// Geometry type of the example route
+    fn name(self) -> &'static str {
+        match self {
+            ExampleGeometry::Donut => "donut",
+            ExampleGeometry::Cloverleaf => "cloverleaf",
+            ExampleGeometry::Grid => "grid",
+        }
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -52,61 +358,280 @@ enum Backend {
     Gpu,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+enum MetricsFormat {
+    /// Comma-separated values with a header row
+    Csv,
+    /// One JSON object per line
+    Jsonl,
+}
+
+impl From<MetricsFormat> for ExportFormat {
+    fn from(format: MetricsFormat) -> Self {
+        match format {
+            MetricsFormat::Csv => ExportFormat::Csv,
+            MetricsFormat::Jsonl => ExportFormat::JsonLines,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+enum Units {
+    /// km/h and meters
+    Metric,
+    /// mph and feet
+    Imperial,
+}
+
+impl From<Units> for UnitSystem {
+    fn from(units: Units) -> Self {
+        match units {
+            Units::Metric => UnitSystem::Metric,
+            Units::Imperial => UnitSystem::Imperial,
+        }
+    }
+}
+
+/// Run shown on the right of a split screen: its own configuration, state and backend,
+/// stepped in lockstep with the main run from the same seed
+struct ComparisonRun {
+    config: SimulationConfig,
+    state: SimulationState,
+    backend: ComputeBackend,
+    previous_state: Option<SimulationState>, // state before the latest step, for interpolation
+    summary: SummaryCollector,
+}
+
+impl ComparisonRun {
+    fn new(config: SimulationConfig, backend: Backend, seed: Option<u64>) -> Self {
+        let state = SimulationState::new(SIMULATION_DT);
+        Self {
+            backend: create_compute_backend(backend, &config, seed),
+            summary: SummaryCollector::new(&config.route, &state),
+            config,
+            state,
+            previous_state: None,
+        }
+    }
+    
+    fn step(&mut self) -> Result<()> {
+        step_simulation(&mut self.backend, &mut self.state)?;
+        self.summary.record(&self.state);
+        Ok(())
+    }
+    
+    /// Start over with `seed`, as the main run does
+    fn reset(&mut self, seed: Option<u64>) {
+        self.backend.reset(seed);
+        self.state = SimulationState::new(SIMULATION_DT);
+        self.previous_state = None;
+        self.summary = SummaryCollector::new(&self.config.route, &self.state);
+    }
+}
+
+/// Where the compute backend steps: between frames on the render loop, or on a thread of
+/// its own that hands the render loop snapshots
+enum SimulationDriver {
+    Inline(Box<ComputeBackend>),
+    Threaded(SimulationRunner),
+}
+
+impl SimulationDriver {
+    fn gpu_memory_usage(&self) -> usize {
+        match self {
+            SimulationDriver::Inline(backend) => backend.gpu_memory_usage(),
+            SimulationDriver::Threaded(runner) => runner.gpu_memory_usage(),
+        }
+    }
+}
+
+/// The summary and output files, which see every step of the run. Shared with the
+/// simulation thread when there is one, which records the steps as it takes them.
+struct StepRecorders {
+    summary: SummaryCollector,
+    metrics_exporter: Option<MetricsExporter>,
+    detector_exporter: Option<DetectorExporter>,
+    trip_exporter: Option<TripExporter>,
+    conflict_exporter: Option<ConflictExporter>,
+    queue_exporter: Option<QueueExporter>,
+    travel_time_exporter: Option<TravelTimeExporter>,
+    trajectory_exporter: Option<TrajectoryExporter>,
+    fcd_exporter: Option<FcdExporter>,
+    noise_exporter: Option<NoiseExporter>,
+    replay_recorder: Option<ReplayRecorder>,
+}
+
+impl StepRecorders {
+    fn record(&mut self, state: &SimulationState) -> Result<()> {
+        self.summary.record(state);
+        if let Some(exporter) = &mut self.metrics_exporter {
+            exporter.record(state)?;
+        }
+        if let Some(exporter) = &mut self.detector_exporter {
+            exporter.record(state)?;
+        }
+        if let Some(exporter) = &mut self.trip_exporter {
+            exporter.record(state)?;
+        }
+        if let Some(exporter) = &mut self.conflict_exporter {
+            exporter.record(state)?;
+        }
+        if let Some(exporter) = &mut self.queue_exporter {
+            exporter.record(state)?;
+        }
+        if let Some(exporter) = &mut self.travel_time_exporter {
+            exporter.record(state)?;
+        }
+        if let Some(exporter) = &mut self.trajectory_exporter {
+            exporter.record(state)?;
+        }
+        if let Some(exporter) = &mut self.fcd_exporter {
+            exporter.record(state)?;
+        }
+        if let Some(exporter) = &mut self.noise_exporter {
+            exporter.record(state);
+        }
+        if let Some(recorder) = &mut self.replay_recorder {
+            recorder.record(state)?;
+        }
+        Ok(())
+    }
+    
+    /// Write out whatever the output files still buffer
+    fn flush(&mut self) {
+        if let Some(exporter) = &mut self.metrics_exporter {
+            if let Err(e) = exporter.flush() {
+                log::error!("Failed to flush metrics: {}", e);
+            }
+        }
+        if let Some(exporter) = &mut self.detector_exporter {
+            if let Err(e) = exporter.flush() {
+                log::error!("Failed to flush detector readings: {}", e);
+            }
+        }
+        if let Some(exporter) = &mut self.trip_exporter {
+            if let Err(e) = exporter.flush() {
+                log::error!("Failed to flush trips: {}", e);
+            }
+        }
+        if let Some(exporter) = &mut self.conflict_exporter {
+            if let Err(e) = exporter.flush() {
+                log::error!("Failed to flush conflicts: {}", e);
+            }
+        }
+        if let Some(exporter) = &mut self.queue_exporter {
+            if let Err(e) = exporter.flush() {
+                log::error!("Failed to flush queues: {}", e);
+            }
+        }
+        if let Some(exporter) = &mut self.travel_time_exporter {
+            if let Err(e) = exporter.flush() {
+                log::error!("Failed to flush travel times: {}", e);
+            }
+        }
+        if let Some(exporter) = &mut self.trajectory_exporter {
+            if let Err(e) = exporter.flush() {
+                log::error!("Failed to flush trajectories: {}", e);
+            }
+        }
+        if let Some(exporter) = &mut self.fcd_exporter {
+            if let Err(e) = exporter.finish() {
+                log::error!("Failed to finish FCD output: {}", e);
+            }
+        }
+        if let Some(exporter) = &mut self.noise_exporter {
+            if let Err(e) = exporter.finish() {
+                log::error!("Failed to write the noise map: {}", e);
+            }
+        }
+        if let Some(recorder) = &mut self.replay_recorder {
+            match recorder.flush() {
+                Ok(()) => info!("Recorded {} frames", recorder.frames_written()),
+                Err(e) => log::error!("Failed to flush replay: {}", e),
+            }
+        }
+    }
+}
+
+/// The recorders, even if the simulation thread panicked while recording a step
+fn lock(recorders: &Mutex<StepRecorders>) -> MutexGuard<'_, StepRecorders> {
+    recorders.lock().unwrap_or_else(|e| e.into_inner())
+}
+
 struct Application {
     graphics: GraphicsSystem,
-    simulation_state: SimulationState,
-    compute_backend: ComputeBackend,
+    config: SimulationConfig, // configuration the backend is running with
+    simulation_state: SimulationState, // the latest snapshot when the simulation has its own thread
+    driver: SimulationDriver,
+    backend_name: &'static str,
+    max_speed: bool, // step as fast as the simulation thread goes
+    comparison: Option<ComparisonRun>, // run on the right of a split screen
+    divergence_monitor: Option<DivergenceMonitor>, // other backend checked against this one
     performance_tracker: PerformanceTracker,
+    quality: QualityManager,
     paused: bool,
     last_frame_time: Instant,
+    last_update_time: Instant,
+    step_accumulator: f32, // simulated seconds owed but not yet stepped
+    previous_state: Option<SimulationState>, // state before the latest step, for interpolation
+    pending_steps: u32, // single steps asked for while paused, taken on the next update
     target_fps: f32,
     simulation_speed: f32,
     verbose: bool,
     route_file: String,
     cars_file: String,
+    scenario_file: Option<String>, // lane closures are saved into the bundle instead of the route file
     seed: Option<u64>,
     frame_count: u64,
-    font_size: f32,
     should_exit: bool,
     shift_pressed: bool,
+    recorders: Arc<Mutex<StepRecorders>>,
+    replay_player: Option<ReplayPlayer>,
+    telemetry_server: Option<TelemetryServer>,
+    scenario_events: Option<EventSchedule>,
+    summary_out: Option<String>,
+    rewind: RewindBuffer,
+    rewound: bool, // the state on screen was scrubbed back to, the run resumes from it
+    #[cfg(not(target_arch = "wasm32"))]
+    config_watcher: Option<ConfigWatcher>,
+    checkpoint_file: String,
+    save_checkpoint: Option<String>,
+    diagram_file: String,
+    screenshot_dir: std::path::PathBuf,
+    screenshot_size: Option<(u32, u32)>,
+    screenshot_times: Vec<f32>, // pending --screenshot-at times, latest first
+    #[cfg(not(target_arch = "wasm32"))]
+    video_recorder: Option<VideoRecorder>,
 }
 
 impl Application {
     async fn new(args: &Args, event_loop: Option<&EventLoop<()>>) -> Result<Self> {
-        // Initialize logging
-        env_logger::Builder::from_default_env()
-            .filter_level(if args.verbose { log::LevelFilter::Debug } else { log::LevelFilter::Info })
-            .init();
         info!("Starting Traffic Simulator");
         
-        // Load configuration
-        if args.verbose {
-            info!("Loading route configuration from: {}", &args.route);
-        }
-        let config = SimulationConfig::load_from_files(&args.route, &args.cars)?;
-        info!("Loaded configuration: {} cars max, route: {}", 
-              config.cars.simulation.total_cars, 
-              config.route.route.name);
-        
-        if args.verbose {
-            info!("Route details: {} lanes, {:.1}m inner radius, {:.1}m outer radius", 
-                  config.route.route.geometry.lane_count,
-                  config.route.route.geometry.inner_radius,
-                  config.route.route.geometry.outer_radius);
-            info!("Traffic rules: {:.1} km/h speed limit, {:.1}m following distance", 
-                  config.route.route.traffic_rules.speed_limit * 3.6,
-                  config.route.route.traffic_rules.following_distance);
-            info!("Car types loaded: {}", config.cars.car_types.len());
-            info!("Behavior patterns loaded: {}", config.cars.behavior.len());
-            info!("Spawn configuration: {} total cars, {:.1} cars/s spawn rate", 
-                  config.cars.simulation.total_cars,
-                  config.cars.simulation.spawn_rate);
-        }
+        // In replay mode the configuration comes from the replay file itself
+        let replay_player = match &args.replay {
+            Some(path) => {
+                let player = ReplayPlayer::open(path)?;
+                info!("Replaying: {}", path);
+                Some(player)
+            }
+            None => None,
+        };
+        let config = match &replay_player {
+            Some(player) => player.config().clone(),
+            None => load_config(args)?,
+        };
         
         // Initialize graphics system
-        let graphics = match event_loop {
+        let mut graphics = match event_loop {
             Some(event_loop) => {
-                let graphics = GraphicsSystem::new(event_loop, config.route.route.geometry.geometry_type.clone()).await?;
+                let mut graphics = GraphicsSystem::new(event_loop, &config, args.plot_window).await?;
+                if let Some(font_size) = args.font_size {
+                    graphics.ui.preferences.set_font_size(font_size);
+                }
+                if let Some(units) = args.units {
+                    graphics.ui.preferences.set_units(units.into());
+                }
                 info!("Graphics system initialized");
                 graphics
             }
@@ -114,50 +639,73 @@ impl Application {
         };
         
         // Initialize simulation state
-        let dt = 1.0 / 60.0; // 60 FPS simulation timestep
-        let simulation_state = SimulationState::new(dt);
-        
-        // Use seed from args, config, or generate a random one
-        let seed = args.seed.or(config.cars.random.seed).or_else(|| {
-            let random_seed = rand::thread_rng().gen::<u64>();
-            Some(random_seed)
-        });
+        let dt = SIMULATION_DT;
+        let mut simulation_state = SimulationState::new(dt);
         
-        // Initialize compute backend based on CLI argument
-        let compute_backend = match args.backend {
-            Backend::Cpu => {
-                let backend = ComputeBackend::new_cpu(
-                    config.cars.clone(),
-                    config.route.clone(),
-                    seed
-                );
-                info!("✓ CPU Backend: {}", backend.get_name());
+        let seed = match &replay_player {
+            Some(player) => player.seed(),
+            None => resolve_seed(args, &config),
+        };
+        let (driver, divergence_monitor) = if args.sim_thread {
+            let (backend, backend_config, pipelined) = (args.backend, config.clone(), args.gpu_pipeline);
+            let mut runner = SimulationRunner::spawn(move || {
+                let mut backend = create_compute_backend(backend, &backend_config, seed);
+                pipeline_gpu(&mut backend, pipelined);
                 backend
+            }, simulation_state.clone())?;
+            if let Some(path) = args.load_checkpoint.clone() {
+                runner.call(move |backend, state| -> Result<()> {
+                    *state = load_checkpoint(&path, backend, seed)?;
+                    Ok(())
+                })??;
+                runner.take_latest(&mut simulation_state);
             }
-            Backend::Gpu => {
-                match ComputeBackend::new_gpu(
-                    config.cars.clone(),
-                    config.route.clone(),
-                    seed
-                ) {
-                    Ok(backend) => {
-                        info!("✓ GPU Backend: {} (OpenCL detected and initialized)", backend.get_name());
-                        backend
-                    }
-                    Err(e) => {
-                        info!("✗ GPU Backend: OpenCL not available ({e})");
-                        info!("↳ Falling back to CPU backend");
-                        let backend = ComputeBackend::new_cpu(
-                            config.cars.clone(),
-                            config.route.clone(),
-                            seed
-                        );
-                        info!("✓ CPU Backend: {}", backend.get_name());
-                        backend
-                    }
-                }
+            (SimulationDriver::Threaded(runner), None)
+        } else {
+            let mut compute_backend = create_compute_backend(args.backend, &config, seed);
+            pipeline_gpu(&mut compute_backend, args.gpu_pipeline);
+            if let Some(path) = &args.load_checkpoint {
+                simulation_state = load_checkpoint(path, &mut compute_backend, seed)?;
+            }
+            let divergence_monitor = create_divergence_monitor(args, &config, &compute_backend, &simulation_state, seed)?;
+            (SimulationDriver::Inline(Box::new(compute_backend)), divergence_monitor)
+        };
+        let backend_name = match &driver {
+            SimulationDriver::Inline(backend) => backend.get_name(),
+            SimulationDriver::Threaded(runner) => runner.call(|backend, _| backend.get_name())?,
+        };
+        let comparison = match load_comparison_config(args, &config)? {
+            Some((comparison_config, labels)) => {
+                info!("Comparing {} (left) with {} (right)", labels[0], labels[1]);
+                graphics.set_comparison(labels[0].clone(), labels[1].clone());
+                Some(ComparisonRun::new(comparison_config, args.backend, seed))
             }
+            None => None,
         };
+        let recorders = Arc::new(Mutex::new(StepRecorders {
+            summary: SummaryCollector::new(&config.route, &simulation_state),
+            metrics_exporter: create_metrics_exporter(args, &config)?,
+            detector_exporter: create_detector_exporter(args, &config)?,
+            trip_exporter: create_trip_exporter(args)?,
+            conflict_exporter: create_conflict_exporter(args)?,
+            queue_exporter: create_queue_exporter(args)?,
+            travel_time_exporter: create_travel_time_exporter(args)?,
+            trajectory_exporter: create_trajectory_exporter(args, &config)?,
+            fcd_exporter: create_fcd_exporter(args, &config)?,
+            noise_exporter: create_noise_exporter(args, &config)?,
+            replay_recorder: create_replay_recorder(args, &config, seed)?,
+        }));
+        if let SimulationDriver::Threaded(runner) = &driver {
+            let recorders = recorders.clone();
+            runner.add_step_hook(Box::new(move |state| lock(&recorders).record(state)));
+        }
+        let telemetry_server = create_telemetry_server(args, &config)?;
+        let scenario_events = create_event_schedule(args, simulation_state.time)?;
+        let rewind = RewindBuffer::new(config.cars.performance.rewind_memory_mb);
+        #[cfg(not(target_arch = "wasm32"))]
+        let config_watcher = create_config_watcher(args);
+        #[cfg(not(target_arch = "wasm32"))]
+        let video_recorder = create_video_recorder(args, &simulation_state)?;
         
         // Initialize performance tracker
         let performance_tracker = PerformanceTracker::new(
@@ -167,7 +715,7 @@ impl Application {
         // Display startup information
         info!("=== Simulation Configuration ===");
         info!("Graphics: GPU accelerated (wgpu)");
-        info!("Compute: {}", compute_backend.get_name());
+        info!("Compute: {}{}", backend_name, if args.sim_thread { " (own thread)" } else { "" });
         info!("Route: {} ({})", config.route.route.name, config.route.route.description);
         info!("Max Cars: {}", config.cars.simulation.total_cars);
         if let Some(seed) = seed {
@@ -180,110 +728,600 @@ impl Application {
             info!("Performance tracking: {} samples", config.cars.performance.timing_samples);
         }
         
+        let quality = QualityManager::new(config.cars.performance.frame_budget_ms);
+        let mut screenshot_times = args.screenshot_at.clone();
+        screenshot_times.sort_by(|a, b| b.total_cmp(a));
+        
         Ok(Self {
             graphics,
+            config,
             simulation_state,
-            compute_backend,
+            driver,
+            backend_name,
+            max_speed: args.max_speed,
+            comparison,
+            divergence_monitor,
             performance_tracker,
+            quality,
             paused: false,
             last_frame_time: Instant::now(),
+            last_update_time: Instant::now(),
+            step_accumulator: 0.0,
+            previous_state: None,
+            pending_steps: 0,
             target_fps: 60.0,
             simulation_speed: 1.0,
             verbose: args.verbose,
-            route_file: args.route.clone(),
-            cars_file: args.cars.clone(),
+            route_file: args.replay.clone().or_else(|| args.scenario.clone()).unwrap_or_else(|| args.route.clone()),
+            cars_file: args.scenario.clone().unwrap_or_else(|| args.cars.clone()),
+            scenario_file: args.scenario.clone(),
             seed,
             frame_count: 0,
-            font_size: args.font_size,
             should_exit: false,
             shift_pressed: false,
+            recorders,
+            replay_player,
+            telemetry_server,
+            scenario_events,
+            summary_out: args.summary_out.clone(),
+            rewind,
+            rewound: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            config_watcher,
+            checkpoint_file: args.checkpoint.clone(),
+            diagram_file: args.diagram_out.clone(),
+            save_checkpoint: args.save_checkpoint.clone(),
+            screenshot_dir: args.screenshot_dir.clone().into(),
+            screenshot_size: args.screenshot_size,
+            screenshot_times,
+            #[cfg(not(target_arch = "wasm32"))]
+            video_recorder,
         })
     }
     
     fn update(&mut self) -> Result<()> {
-        if !self.paused {
-            // Update simulation
-            self.performance_tracker.start_simulation();
-            
-            // Scale timestep by simulation speed
-            let original_dt = self.simulation_state.dt;
-            self.simulation_state.dt = original_dt * self.simulation_speed;
-            
-            // Verbose logging for simulation state changes
-            let prev_car_count = self.simulation_state.active_cars as usize;
-            
-            self.compute_backend.update(&mut self.simulation_state)?;
-            
-            // Update speed history for all cars
-            self.simulation_state.update_car_speeds();
-            
-            // Update active car count and log changes
-            self.simulation_state.active_cars = self.simulation_state.cars.len() as u32;
+        if self.replay_player.is_some() {
+            if !self.paused {
+                let frames = self.simulation_speed.round().max(1.0) as u32;
+                self.advance_replay(frames)?;
+            } else if self.pending_steps > 0 {
+                let frames = std::mem::take(&mut self.pending_steps);
+                self.advance_replay(frames)?;
+            }
+            self.frame_count += 1;
+            return Ok(());
+        }
+        
+        let mut commands = self.telemetry_server.as_ref().map(|server| server.poll_commands()).unwrap_or_default();
+        if let Some(events) = &mut self.scenario_events {
+            commands.extend(events.due(self.simulation_state.time).iter().map(|event| event.command.clone()));
+        }
+        let mut changed_state = false;
+        for command in commands {
+            changed_state |= matches!(command, ServerCommand::SpawnCar { .. } | ServerCommand::SetSignalPhase { .. });
+            let (mut paused, mut speed) = (self.paused, self.simulation_speed);
+            let applied = self.with_backend(move |backend, state| {
+                apply_server_command(command, backend, state, &mut paused, &mut speed);
+                (paused, speed)
+            });
+            match applied {
+                Ok((paused, speed)) => (self.paused, self.simulation_speed) = (paused, speed),
+                Err(e) => log::error!("Failed to apply command: {}", e),
+            }
+        }
+        if changed_state {
+            self.resync_divergence_monitor();
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        self.reload_changed_config();
+        
+        if let SimulationDriver::Threaded(runner) = &mut self.driver {
+            // The simulation thread paces itself, the frame only shows how far it got
+            let error = runner.take_error();
+            if error.is_some() {
+                self.paused = true;
+            }
+            runner.set_running(self.paused, if self.max_speed { f32::INFINITY } else { self.simulation_speed });
+            let steps = std::mem::take(&mut self.pending_steps);
+            if steps > 0 {
+                runner.step(steps);
+            }
+            self.take_snapshot()?;
+            if let Some(e) = error {
+                return Err(e);
+            }
+            self.take_due_screenshots();
+            self.frame_count += 1;
+            return Ok(());
+        }
+        
+        // Accumulate real time and catch up in whole fixed steps, so trajectories are
+        // the same at any frame rate. Speed multiplies the number of steps, not dt.
+        let now = Instant::now();
+        let frame_time = now.duration_since(self.last_update_time).as_secs_f32().min(MAX_FRAME_TIME);
+        self.last_update_time = now;
+        
+        if self.paused {
+            // Show the latest step as-is rather than blending back toward the one before
+            self.step_accumulator = 0.0;
+            self.previous_state = None;
+            if let Some(comparison) = &mut self.comparison {
+                comparison.previous_state = None;
+            }
             
-            if self.verbose && self.simulation_state.cars.len() != prev_car_count {
-                if self.simulation_state.cars.len() > prev_car_count {
-                    log::debug!("Car spawned: total cars = {}", self.simulation_state.cars.len());
-                } else if self.simulation_state.cars.len() < prev_car_count {
-                    log::debug!("Car despawned: total cars = {}", self.simulation_state.cars.len());
+            // Steps asked for one at a time, to look at a situation frame by frame
+            let steps = std::mem::take(&mut self.pending_steps);
+            if steps > 0 {
+                self.performance_tracker.start_simulation();
+                for _ in 0..steps {
+                    self.step_once()?;
                 }
+                self.performance_tracker.end_simulation();
+            }
+        } else {
+            self.step_accumulator += frame_time * self.simulation_speed;
+            let dt = self.simulation_state.dt;
+            let mut steps = (self.step_accumulator / dt).floor() as u32;
+            self.step_accumulator -= steps as f32 * dt;
+            // Over the frame budget the steps beyond the cap are dropped, so the simulation
+            // runs slower than real time instead of every frame taking longer to catch up
+            if let Some(max_steps) = self.quality.max_steps_per_frame() {
+                steps = steps.min(max_steps);
             }
             
-            // Restore original timestep
-            self.simulation_state.dt = original_dt;
-            
-            self.performance_tracker.end_simulation();
+            if steps > 0 {
+                self.performance_tracker.start_simulation();
+                for step in 0..steps {
+                    // Only the last step's starting state is needed for interpolation
+                    if step + 1 == steps {
+                        copy_state(&mut self.previous_state, &self.simulation_state);
+                        if let Some(comparison) = &mut self.comparison {
+                            copy_state(&mut comparison.previous_state, &comparison.state);
+                        }
+                    }
+                    self.step_once()?;
+                    // The divergence monitor pauses on the step the backends fell apart
+                    if self.paused {
+                        break;
+                    }
+                }
+                self.performance_tracker.end_simulation();
+            }
         }
         
+        self.take_due_screenshots();
+        
         // Increment frame counter
         self.frame_count += 1;
         
         Ok(())
     }
     
+    /// One screenshot covers every requested time passed this frame
+    fn take_due_screenshots(&mut self) {
+        let mut due = false;
+        while self.screenshot_times.last().is_some_and(|&time| time <= self.simulation_state.time) {
+            self.screenshot_times.pop();
+            due = true;
+        }
+        if due {
+            self.take_screenshot();
+        }
+    }
+    
+    /// Show the newest snapshot the simulation thread handed over, if there is one, and
+    /// feed the recorders that work from what is on screen
+    fn take_snapshot(&mut self) -> Result<()> {
+        let SimulationDriver::Threaded(runner) = &mut self.driver else {
+            return Ok(());
+        };
+        let Some(report) = runner.take_latest(&mut self.simulation_state) else {
+            return Ok(());
+        };
+        self.performance_tracker.record_simulation(report.step_time);
+        if let Some(timing) = &report.gpu_timing {
+            self.performance_tracker.record_gpu(timing);
+        }
+        // Snapshots published for a change between steps show no new step
+        if report.steps > 0 {
+            self.graphics.ui.record(&self.simulation_state);
+            self.rewind.record(&self.simulation_state)?;
+            self.rewound = false;
+            if let Some(server) = &mut self.telemetry_server {
+                server.publish(&self.simulation_state)?;
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            self.record_video_frame();
+        }
+        Ok(())
+    }
+    
+    /// Run `f` on the compute backend and the live state, here or between two steps on the
+    /// simulation thread. Either way the state it leaves is the one shown from then on.
+    fn with_backend<F, R>(&mut self, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut ComputeBackend, &mut SimulationState) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        match &mut self.driver {
+            SimulationDriver::Inline(backend) => Ok(f(backend, &mut self.simulation_state)),
+            SimulationDriver::Threaded(runner) => {
+                let result = runner.call(f)?;
+                self.take_snapshot()?;
+                Ok(result)
+            }
+        }
+    }
+    
+    /// Advance the simulation by one fixed timestep and feed the recorders
+    fn step_once(&mut self) -> Result<()> {
+        // Verbose logging for simulation state changes
+        let prev_car_count = self.simulation_state.active_cars as usize;
+        
+        // The simulation thread takes and records its own steps
+        let SimulationDriver::Inline(backend) = &mut self.driver else {
+            return Ok(());
+        };
+        step_simulation(backend, &mut self.simulation_state)?;
+        if let Some(comparison) = &mut self.comparison {
+            comparison.step()?;
+        }
+        if let Some(monitor) = &mut self.divergence_monitor {
+            if monitor.step(&self.simulation_state)? {
+                log::warn!("{} backend diverged from {} at t={:.2}s: {} - pausing",
+                           monitor.backend_name(), self.backend_name, self.simulation_state.time, monitor.describe());
+                self.paused = true;
+            }
+        }
+        if let Some(timing) = backend.gpu_timing() {
+            self.performance_tracker.record_gpu(&timing);
+        }
+        self.graphics.ui.record(&self.simulation_state);
+        self.rewind.record(&self.simulation_state)?;
+        self.rewound = false;
+        lock(&self.recorders).record(&self.simulation_state)?;
+        if let Some(server) = &mut self.telemetry_server {
+            server.publish(&self.simulation_state)?;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        self.record_video_frame();
+        
+        // Log car count changes
+        if self.verbose && self.simulation_state.cars.len() != prev_car_count {
+            if self.simulation_state.cars.len() > prev_car_count {
+                log::debug!("Car spawned: total cars = {}", self.simulation_state.cars.len());
+            } else if self.simulation_state.cars.len() < prev_car_count {
+                log::debug!("Car despawned: total cars = {}", self.simulation_state.cars.len());
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Load the next `frames` recorded frames in place of running the compute backend.
+    /// Simulation speed skips frames rather than scaling the timestep.
+    fn advance_replay(&mut self, frames: u32) -> Result<()> {
+        for _ in 0..frames {
+            let Some(player) = &mut self.replay_player else {
+                return Ok(());
+            };
+            match player.next_frame()? {
+                Some(state) => {
+                    self.graphics.ui.record(&state);
+                    self.simulation_state = state;
+                    #[cfg(not(target_arch = "wasm32"))]
+                    self.record_video_frame();
+                }
+                None => {
+                    info!("Replay finished after {} frames - press R to restart", player.frames_read());
+                    self.paused = true;
+                    break;
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
     fn render(&mut self) -> Result<()> {
         self.performance_tracker.start_render();
         
         // Create performance metrics
+        self.graphics.ui.quality = self.quality.budget().map(|_| self.quality.describe());
+        self.graphics.ui.divergence = self.divergence_monitor.as_ref()
+            .map(|monitor| (format!("{}/{} divergence: {}", self.backend_name, monitor.backend_name(), monitor.describe()), monitor.exceeded()));
+        let gpu_memory = self.driver.gpu_memory_usage() + self.graphics.renderer.gpu_memory_usage();
+        self.performance_tracker.set_gpu_memory(gpu_memory);
+        let drawn = self.graphics.renderer.drawn_cars();
+        self.performance_tracker.set_car_culling((drawn.shapes + drawn.dots) as usize, drawn.culled as usize);
+        let gpu = self.performance_tracker.average_gpu();
+        let process = self.performance_tracker.latest_resources().and_then(|sample| sample.process).unwrap_or_default();
         let performance_metrics = traffic_sim::simulation::PerformanceMetrics {
             frame_time: self.performance_tracker.average_frame_time(),
             simulation_time: self.performance_tracker.average_simulation_time(),
             render_time: std::time::Duration::ZERO, // Will be updated by tracker
-            cpu_utilization: 0.0,
-            gpu_utilization: 0.0,
-            memory_usage: 0,
+            cpu_utilization: process.cpu_utilization,
+            gpu_utilization: gpu.map(|gpu| gpu.utilization()).unwrap_or(0.0),
+            gpu,
+            memory_usage: process.memory_usage,
+            gpu_memory,
+            drawn_cars: (drawn.shapes + drawn.dots) as usize,
+            culled_cars: drawn.culled as usize,
         };
         
-        self.graphics.render(
-            &self.simulation_state, 
-            &performance_metrics,
-            self.paused,
-            self.simulation_speed,
-            self.frame_count,
-            &self.route_file,
-            &self.cars_file,
-            self.seed,
-            self.font_size
-        )?;
+        // Draw cars part way between the last two steps by the time not yet simulated
+        let alpha = (self.step_accumulator / self.simulation_state.dt).clamp(0.0, 1.0);
+        let interpolated = self.previous_state.as_ref().map(|previous| self.simulation_state.interpolated(previous, alpha));
+        let comparison = self.comparison.as_ref().map(|comparison| match &comparison.previous_state {
+            Some(previous) => std::borrow::Cow::Owned(comparison.state.interpolated(previous, alpha)),
+            None => std::borrow::Cow::Borrowed(&comparison.state),
+        });
+        
+        let budget = self.config.cars.performance.rewind_memory_mb;
+        self.graphics.ui.timeline.set_buffer(self.rewind.time_range(), self.rewind.len(), self.rewind.memory_usage(), budget);
+        
+        let run = RunStatus {
+            performance: &performance_metrics,
+            resources: self.performance_tracker.resource_history(),
+            paused: self.paused,
+            simulation_speed: self.simulation_speed,
+            frame_count: self.frame_count,
+            route_file: &self.route_file,
+            cars_file: &self.cars_file,
+            seed: self.seed,
+        };
+        self.graphics.render(interpolated.as_ref().unwrap_or(&self.simulation_state), comparison.as_deref(), &run)?;
         
         self.performance_tracker.end_render();
+        if self.quality.update(&self.performance_tracker) {
+            self.apply_quality();
+        }
+        self.apply_settings();
+        self.apply_closures();
+        if let Some(speed) = self.graphics.ui.take_speed_request() {
+            self.set_simulation_speed(speed);
+        }
+        if let Some(steps) = self.graphics.ui.take_step_request() {
+            self.step_paused(steps);
+        }
+        self.apply_rewind();
+        self.export_diagram();
         
         Ok(())
     }
     
-    fn handle_input(&mut self, event: &WindowEvent) -> bool {
-        // Handle modifier state changes
-        if let WindowEvent::ModifiersChanged(modifiers) = event {
-            self.shift_pressed = modifiers.state().shift_key();
-            return false; // Let other handlers process this too
+    /// Continue the simulation from its current state with the configuration applied in
+    /// the settings window
+    fn apply_settings(&mut self) {
+        let Some(config) = self.graphics.ui.settings.take_apply_request() else {
+            return;
+        };
+        let result = if self.replay_player.is_some() {
+            Err(anyhow::anyhow!("replays keep their recorded configuration"))
+        } else {
+            let (cars, route, seed) = (config.cars.clone(), config.route.clone(), self.seed);
+            self.with_backend(move |backend, state| backend.reconfigure(cars, route, state, seed)).and_then(|result| result)
+        };
+        match &result {
+            Ok(()) => {
+                info!("Applied new settings at t={:.1}s", self.simulation_state.time);
+                self.rewind.set_budget(config.cars.performance.rewind_memory_mb);
+                self.config = config;
+                self.reconfigure_divergence_monitor();
+            }
+            Err(e) => log::error!("Failed to apply settings: {}", e),
+        }
+        self.graphics.ui.settings.finish_apply(result, self.simulation_state.time);
+    }
+    
+    /// Continue the simulation with the lane closures edited in the closure tool, and save
+    /// them into the route file once Save was clicked
+    fn apply_closures(&mut self) {
+        if let Some(closures) = self.graphics.ui.closure_tool.take_request() {
+            let count = closures.len();
+            match self.set_closures(closures) {
+                Ok(()) => {
+                    info!("Running with {} lane closures at t={:.1}s", count, self.simulation_state.time);
+                    self.graphics.ui.closure_tool.report(format!("Running with {} closures", count), true);
+                }
+                Err(e) => {
+                    log::error!("Failed to change lane closures: {}", e);
+                    self.graphics.ui.closure_tool.report(e.to_string(), false);
+                }
+            }
         }
         
-        // Handle application-specific input first (simulation controls)
-        let handled_by_app = match event {
-            WindowEvent::KeyboardInput { 
-                event: winit::event::KeyEvent {
-                    state: ElementState::Pressed,
-                    physical_key: winit::keyboard::PhysicalKey::Code(keycode),
-                    ..
+        if self.graphics.ui.closure_tool.take_save_request() {
+            let closures = &self.config.route.route.closures;
+            let saved = match &self.scenario_file {
+                Some(path) => ScenarioBundle::save_closures(path, closures),
+                None => save_closures(std::path::Path::new(&self.route_file), closures),
+            };
+            match saved {
+                Ok(()) => {
+                    info!("Saved {} lane closures to {}", closures.len(), self.route_file);
+                    self.graphics.ui.closure_tool.report(format!("Saved to {}", self.route_file), true);
+                }
+                Err(e) => {
+                    log::error!("Failed to save lane closures: {}", e);
+                    self.graphics.ui.closure_tool.report(e.to_string(), false);
+                }
+            }
+        }
+    }
+    
+    fn set_closures(&mut self, closures: Vec<LaneClosure>) -> Result<()> {
+        if self.replay_player.is_some() {
+            return Err(anyhow::anyhow!("replays keep their recorded configuration"));
+        }
+        let mut config = self.config.clone();
+        config.route.route.closures = closures;
+        config.route.validate()?;
+        let (cars, route, seed) = (config.cars.clone(), config.route.clone(), self.seed);
+        self.with_backend(move |backend, state| backend.reconfigure(cars, route, state, seed))??;
+        self.graphics.set_config(&config);
+        self.config = config;
+        self.reconfigure_divergence_monitor();
+        Ok(())
+    }
+    
+    /// Show the state the rewind timeline was moved to, and carry on from it once resumed
+    fn apply_rewind(&mut self) {
+        if self.graphics.ui.timeline.take_resume_request() {
+            self.paused = false;
+            info!("Resuming from t={:.1}s", self.simulation_state.time);
+        }
+        let Some(time) = self.graphics.ui.timeline.take_scrub_request() else {
+            return;
+        };
+        if self.replay_player.is_some() {
+            info!("Replays are rewound with R");
+            return;
+        }
+        if self.comparison.is_some() {
+            info!("Runs being compared cannot be rewound, R starts both over");
+            return;
+        }
+        
+        // The live state is the newest point to come back to
+        if !self.rewound {
+            if let Err(e) = self.rewind.capture(&self.simulation_state) {
+                log::error!("Failed to snapshot the simulation: {}", e);
+                return;
+            }
+            let recorders = lock(&self.recorders);
+            if recorders.metrics_exporter.is_some() || recorders.trip_exporter.is_some() || recorders.conflict_exporter.is_some() || recorders.queue_exporter.is_some() || recorders.travel_time_exporter.is_some() || recorders.fcd_exporter.is_some() || recorders.replay_recorder.is_some() {
+                log::warn!("Output files record the rewound stretch again once the simulation resumes");
+            }
+        }
+        match self.rewind.state_at(time) {
+            Ok(Some(rewound)) => {
+                // Paused first, so the simulation thread stays on the state rewound to
+                self.paused = true;
+                if let SimulationDriver::Threaded(runner) = &mut self.driver {
+                    runner.set_running(true, self.simulation_speed);
+                }
+                let seed = self.seed;
+                let restored = self.with_backend(move |backend, state| {
+                    backend.restore_checkpoint(&rewound, seed);
+                    *state = rewound;
+                });
+                if let Err(e) = restored {
+                    log::error!("Failed to rewind: {}", e);
+                    return;
+                }
+                self.previous_state = None;
+                self.rewound = true;
+                self.resync_divergence_monitor();
+            }
+            Ok(None) => {}
+            Err(e) => log::error!("Failed to rewind: {}", e),
+        }
+    }
+    
+    /// Write the fundamental diagram points once Export was clicked in its window
+    fn export_diagram(&mut self) {
+        let diagram = &mut self.graphics.ui.diagram;
+        if !diagram.take_export_request() {
+            return;
+        }
+        let result = diagram.write_csv(&self.diagram_file)
+            .map(|()| format!("Wrote {} points to {}", diagram.points().len(), self.diagram_file));
+        match &result {
+            Ok(message) => info!("{}", message),
+            Err(e) => log::error!("Failed to export the fundamental diagram: {}", e),
+        }
+        diagram.finish_export(result);
+    }
+    
+    /// Switch to the route and cars files once they changed on disk. Cars keep driving
+    /// unless the road itself changed, then the simulation starts over on the new road.
+    /// A configuration that fails to load or validate leaves the running one in place.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reload_changed_config(&mut self) {
+        let Some(reloaded) = self.config_watcher.as_mut().and_then(|watcher| watcher.poll()) else {
+            return;
+        };
+        let config = match reloaded {
+            Ok(config) => config,
+            Err(e) => {
+                log::error!("Keeping the running configuration, reloaded files are invalid: {}", e);
+                return;
+            }
+        };
+        
+        let same_road = config.route.same_road(&self.config.route);
+        let (cars, route, seed) = (config.cars.clone(), config.route.clone(), self.seed);
+        let recorders = self.recorders.clone();
+        let reloaded = self.with_backend(move |backend, state| -> Result<()> {
+            let mut next = if same_road {
+                state.clone()
+            } else {
+                SimulationState::new(SIMULATION_DT)
+            };
+            // Signal heads are laid out again from the new plan on the next step
+            next.signals.clear();
+            backend.reconfigure(cars, route.clone(), &next, seed)?;
+            *state = next;
+            if !same_road {
+                lock(&recorders).summary = SummaryCollector::new(&route, state);
+            }
+            Ok(())
+        });
+        if let Err(e) = reloaded.and_then(|result| result) {
+            log::error!("Failed to reload configuration: {}", e);
+            return;
+        }
+        
+        if same_road {
+            info!("Reloaded configuration at t={:.1}s", self.simulation_state.time);
+        } else {
+            info!("Reloaded configuration with a changed road, restarting the simulation");
+            self.rewind.clear();
+            self.previous_state = None;
+            self.step_accumulator = 0.0;
+            self.graphics.viewport.set_follow_target(None);
+            let recorders = lock(&self.recorders);
+            if recorders.metrics_exporter.is_some() || recorders.detector_exporter.is_some() || recorders.replay_recorder.is_some() {
+                log::warn!("Output files continue with the columns and configuration of the original road");
+            }
+        }
+        self.rewind.set_budget(config.cars.performance.rewind_memory_mb);
+        self.graphics.set_config(&config);
+        let budget_changed = config.cars.performance.frame_budget_ms != self.config.cars.performance.frame_budget_ms;
+        self.config = config;
+        self.reconfigure_divergence_monitor();
+        if budget_changed {
+            self.quality = QualityManager::new(self.config.cars.performance.frame_budget_ms);
+            self.apply_quality();
+        }
+    }
+    
+    /// Carry the quality level's decisions over to the renderer and UI
+    fn apply_quality(&mut self) {
+        self.graphics.renderer.set_road_detail(&self.config.route, self.quality.road_detail());
+        self.graphics.ui.show_charts = self.quality.show_charts();
+    }
+    
+    fn handle_input(&mut self, event: &WindowEvent) -> bool {
+        // Handle modifier state changes
+        if let WindowEvent::ModifiersChanged(modifiers) = event {
+            self.shift_pressed = modifiers.state().shift_key();
+            return false; // Let other handlers process this too
+        }
+        
+        // Keys typed into a settings field are not simulation controls
+        if matches!(event, WindowEvent::KeyboardInput { .. }) && self.graphics.egui_ctx.wants_keyboard_input() {
+            return self.graphics.handle_input(event);
+        }
+        
+        // Handle application-specific input first (simulation controls)
+        let handled_by_app = match event {
+            WindowEvent::KeyboardInput { 
+                event: winit::event::KeyEvent {
+                    state: ElementState::Pressed,
+                    physical_key: winit::keyboard::PhysicalKey::Code(keycode),
+                    ..
                 },
                 ..
             } => {
@@ -295,10 +1333,134 @@ impl Application {
                         info!("Simulation {}", if self.paused { "paused" } else { "resumed" });
                         true
                     }
+                    winit::keyboard::KeyCode::Period => {
+                        self.step_paused(1);
+                        true
+                    }
                     winit::keyboard::KeyCode::KeyR => {
-                        // Reset simulation
-                        self.simulation_state = SimulationState::new(1.0 / 60.0);
-                        info!("Simulation reset");
+                        if let Some(player) = &mut self.replay_player {
+                            // Restart playback from the first frame
+                            if let Err(e) = player.rewind() {
+                                log::error!("Failed to rewind replay: {}", e);
+                            }
+                            self.paused = false;
+                            info!("Replay restarted");
+                        } else {
+                            // Shift+R starts over on a fresh seed instead of repeating the run
+                            if self.shift_pressed {
+                                self.seed = Some(rand::thread_rng().gen::<u64>());
+                            }
+                            self.reset_simulation();
+                        }
+                        true
+                    }
+                    winit::keyboard::KeyCode::KeyF => {
+                        if self.shift_pressed {
+                            let rotate = !self.graphics.viewport.follow_rotation();
+                            self.graphics.viewport.set_follow_rotation(rotate);
+                            info!("Follow camera rotation {}", if rotate { "enabled" } else { "disabled" });
+                        } else {
+                            self.toggle_follow_camera();
+                        }
+                        true
+                    }
+                    winit::keyboard::KeyCode::KeyH => {
+                        let show = !self.graphics.renderer.show_heading_indicators();
+                        self.graphics.renderer.set_show_heading_indicators(show);
+                        info!("Heading indicators {}", if show { "shown" } else { "hidden" });
+                        true
+                    }
+                    winit::keyboard::KeyCode::KeyU => {
+                        let units = match self.graphics.ui.preferences.units() {
+                            UnitSystem::Metric => UnitSystem::Imperial,
+                            UnitSystem::Imperial => UnitSystem::Metric,
+                        };
+                        self.graphics.ui.preferences.choose_units(units);
+                        info!("Units: {}", units.name());
+                        true
+                    }
+                    winit::keyboard::KeyCode::KeyQ => {
+                        let show = !self.graphics.renderer.show_queues();
+                        self.graphics.renderer.set_show_queues(show);
+                        info!("Queue highlights {}", if show { "shown" } else { "hidden" });
+                        true
+                    }
+                    winit::keyboard::KeyCode::KeyL => {
+                        let coloring = match self.graphics.renderer.car_coloring() {
+                            CarColoring::Palette => CarColoring::Lane,
+                            CarColoring::Lane => CarColoring::Palette,
+                        };
+                        self.graphics.renderer.set_car_coloring(coloring);
+                        info!("Car colors: {}", coloring.name());
+                        true
+                    }
+                    winit::keyboard::KeyCode::KeyM => {
+                        self.graphics.ui.minimap.toggle();
+                        true
+                    }
+                    winit::keyboard::KeyCode::KeyO => {
+                        self.graphics.ui.show_noise = !self.graphics.ui.show_noise;
+                        info!("Noise map {}", if self.graphics.ui.show_noise { "shown" } else { "hidden" });
+                        true
+                    }
+                    winit::keyboard::KeyCode::KeyP => {
+                        self.graphics.ui.spawn_tool.toggle();
+                        true
+                    }
+                    winit::keyboard::KeyCode::KeyB => {
+                        self.graphics.ui.timeline.toggle();
+                        true
+                    }
+                    winit::keyboard::KeyCode::KeyK => {
+                        self.graphics.ui.closure_tool.toggle();
+                        true
+                    }
+                    winit::keyboard::KeyCode::KeyV => {
+                        let perspective = !self.graphics.viewport.perspective();
+                        self.graphics.viewport.set_perspective(perspective);
+                        info!("Camera: {}", if perspective { "perspective" } else { "top-down" });
+                        true
+                    }
+                    winit::keyboard::KeyCode::F1 => {
+                        self.graphics.ui.show_overlays = !self.graphics.ui.show_overlays;
+                        true
+                    }
+                    winit::keyboard::KeyCode::F2 => {
+                        self.graphics.ui.settings.toggle();
+                        true
+                    }
+                    winit::keyboard::KeyCode::F3 => {
+                        self.graphics.ui.diagram.toggle();
+                        true
+                    }
+                    winit::keyboard::KeyCode::F4 => {
+                        self.graphics.ui.trajectories.toggle();
+                        true
+                    }
+                    winit::keyboard::KeyCode::F5 => {
+                        self.save_checkpoint();
+                        true
+                    }
+                    winit::keyboard::KeyCode::F6 => {
+                        self.graphics.ui.preferences.toggle();
+                        true
+                    }
+                    winit::keyboard::KeyCode::F7 => {
+                        self.graphics.ui.behaviors.toggle();
+                        true
+                    }
+                    winit::keyboard::KeyCode::F8 => {
+                        if let Some(panel) = &mut self.graphics.ui.comparison {
+                            panel.toggle();
+                        }
+                        true
+                    }
+                    winit::keyboard::KeyCode::F9 => {
+                        self.load_checkpoint();
+                        true
+                    }
+                    winit::keyboard::KeyCode::F12 => {
+                        self.take_screenshot();
                         true
                     }
                     // Speed controls: 1-9 for 1x to 9x speeds
@@ -347,6 +1509,15 @@ impl Application {
                         info!("Simulation speed: 9.0x");
                         true
                     }
+                    // Shift +/- for finer steps, without Shift they zoom
+                    winit::keyboard::KeyCode::Equal if self.shift_pressed => {
+                        self.set_simulation_speed(self.simulation_speed + SPEED_STEP);
+                        true
+                    }
+                    winit::keyboard::KeyCode::Minus if self.shift_pressed => {
+                        self.set_simulation_speed(self.simulation_speed - SPEED_STEP);
+                        true
+                    }
                     winit::keyboard::KeyCode::Escape => {
                         info!("ESC pressed - exiting simulation");
                         self.should_exit = true;
@@ -396,6 +1567,39 @@ impl Application {
                     _ => false
                 }
             }
+            // With the spawn tool open a click on the road places a car, dragging still pans
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. }
+                if self.graphics.ui.spawn_tool.is_active() && !self.graphics.egui_ctx.is_pointer_over_area() => {
+                let position = self.graphics.viewport.mouse_position();
+                match state {
+                    ElementState::Pressed => self.graphics.ui.spawn_tool.press(position),
+                    ElementState::Released => {
+                        if self.graphics.ui.spawn_tool.release(position) {
+                            self.place_car(position);
+                        }
+                    }
+                }
+                false
+            }
+            // With the closure tool open dragging along a lane closes it instead of panning
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. }
+                if self.graphics.ui.closure_tool.is_active() && (self.graphics.ui.closure_tool.is_dragging() ||
+                    (*state == ElementState::Pressed && !self.graphics.egui_ctx.is_pointer_over_area())) => {
+                let position = self.graphics.viewport.mouse_position();
+                match state {
+                    ElementState::Pressed => self.graphics.ui.closure_tool.press(position),
+                    ElementState::Released => {
+                        if let Some((from, to)) = self.graphics.ui.closure_tool.release(position) {
+                            self.close_lane(from, to);
+                        }
+                    }
+                }
+                true
+            }
+            WindowEvent::CursorMoved { position, .. } if self.graphics.ui.closure_tool.is_dragging() => {
+                self.graphics.ui.closure_tool.drag_to((position.x as f32, position.y as f32));
+                false
+            }
             _ => false,
         };
         
@@ -407,42 +1611,1058 @@ impl Application {
         }
     }
     
+    /// Start over on the same configuration from the state a fresh launch with the current
+    /// seed has, so the run repeats exactly
+    fn reset_simulation(&mut self) {
+        let (seed, route) = (self.seed, self.config.route.clone());
+        let recorders = self.recorders.clone();
+        let reset = self.with_backend(move |backend, state| {
+            backend.reset(seed);
+            *state = SimulationState::new(SIMULATION_DT);
+            lock(&recorders).summary = SummaryCollector::new(&route, state);
+        });
+        if let Err(e) = reset {
+            log::error!("Failed to reset the simulation: {}", e);
+            return;
+        }
+        if let Some(comparison) = &mut self.comparison {
+            comparison.reset(self.seed);
+        }
+        if let Some(monitor) = &mut self.divergence_monitor {
+            monitor.reset(self.seed);
+        }
+        self.rewind.clear();
+        self.rewound = false;
+        self.previous_state = None;
+        self.step_accumulator = 0.0;
+        self.graphics.viewport.set_follow_target(None);
+        self.graphics.set_config(&self.config);
+        match self.seed {
+            Some(seed) => info!("Simulation reset with seed {}", seed),
+            None => info!("Simulation reset"),
+        }
+    }
+    
+    /// Keep checking for divergence after the state was changed outside a step. Both
+    /// backends continue from it as from a checkpoint, so their random streams agree again.
+    fn resync_divergence_monitor(&mut self) {
+        // Divergence is only checked with the simulation stepped inline
+        if let (Some(monitor), SimulationDriver::Inline(backend)) = (&mut self.divergence_monitor, &mut self.driver) {
+            backend.restore_checkpoint(&self.simulation_state, self.seed);
+            monitor.resync(&self.simulation_state, self.seed);
+        }
+    }
+    
+    /// Rebuild the divergence monitor's backend with the configuration the run switched to
+    fn reconfigure_divergence_monitor(&mut self) {
+        let Some(monitor) = &mut self.divergence_monitor else {
+            return;
+        };
+        if let Err(e) = monitor.reconfigure(self.config.cars.clone(), self.config.route.clone(), &self.simulation_state, self.seed) {
+            log::error!("Stopped checking for divergence, the {} backend failed to reconfigure: {}", monitor.backend_name(), e);
+            self.divergence_monitor = None;
+        }
+    }
+    
+    /// Take `steps` fixed steps on the next update, pausing first if running
+    fn step_paused(&mut self, steps: u32) {
+        if !self.paused {
+            self.paused = true;
+            info!("Simulation paused");
+        }
+        self.pending_steps += steps;
+    }
+    
+    /// Run at `speed` times real time, within the range the speed controls offer. Higher
+    /// speeds take more fixed steps per frame, the timestep stays the same.
+    fn set_simulation_speed(&mut self, speed: f32) {
+        let speed = (speed / SPEED_STEP).round() * SPEED_STEP;
+        self.simulation_speed = speed.clamp(*SPEED_RANGE.start(), *SPEED_RANGE.end());
+        info!("Simulation speed: {:.2}x", self.simulation_speed);
+    }
+    
     fn spawn_manual_car(&mut self, behavior_name: &str) {
+        if self.replay_player.is_some() {
+            info!("Cannot spawn cars while replaying");
+            return;
+        }
         info!("Manually spawning {} car", behavior_name);
-        self.compute_backend.spawn_manual_car(behavior_name, &mut self.simulation_state);
+        let behavior = behavior_name.to_string();
+        let spawned = self.with_backend(move |backend, state| backend.spawn_manual_car(&behavior, state));
+        if let Err(e) = spawned.and_then(|result| result) {
+            info!("Cannot spawn {} car: {}", behavior_name, e);
+        }
+        self.resync_divergence_monitor();
+    }
+    
+    /// Place a car of the spawn tool's behavior and car type on the lane under the screen
+    /// position
+    fn place_car(&mut self, (x, y): (f32, f32)) {
+        if self.replay_player.is_some() {
+            info!("Cannot place cars while replaying");
+            return;
+        }
+        let world = self.graphics.viewport.screen_to_world(x, y);
+        let (behavior, car_type) = self.graphics.ui.spawn_tool.selection();
+        let (behavior, car_type) = (behavior.to_string(), car_type.map(str::to_string));
+        let result = self.with_backend(move |backend, state| backend.spawn_car_at(Point::new(world.x, world.y), &behavior, car_type.as_deref(), state))
+            .and_then(|result| result);
+        if let Err(e) = &result {
+            info!("Cannot place car: {}", e);
+        }
+        self.graphics.ui.spawn_tool.report(&result);
+        self.resync_divergence_monitor();
+    }
+    
+    /// Close the stretch of lane dragged along between two screen positions
+    fn close_lane(&mut self, from: (f32, f32), to: (f32, f32)) {
+        let from = self.graphics.viewport.screen_to_world(from.0, from.1);
+        let to = self.graphics.viewport.screen_to_world(to.0, to.1);
+        let geometry = &self.config.route.route.geometry;
+        match closure_between(geometry, Point::new(from.x, from.y), Point::new(to.x, to.y)) {
+            Some(closure) => self.graphics.ui.closure_tool.add(closure),
+            None => self.graphics.ui.closure_tool.report("Drag along a lane of a ring road", false),
+        }
     }
     
     fn remove_car(&mut self, behavior_name: &str) {
+        if self.replay_player.is_some() {
+            info!("Cannot remove cars while replaying");
+            return;
+        }
         info!("Marking {} car for exit at next opportunity", behavior_name);
-        let marked = self.compute_backend.mark_car_for_exit(behavior_name, &mut self.simulation_state);
-        if marked {
-            info!("Successfully marked {} car for exit", behavior_name);
-        } else {
-            info!("No {} cars available to mark for exit", behavior_name);
+        let behavior = behavior_name.to_string();
+        let marked = self.with_backend(move |backend, state| backend.mark_car_for_exit(&behavior, state));
+        match marked {
+            Ok(Some(id)) => info!("Successfully marked {} car {} for exit", behavior_name, id),
+            Ok(None) => info!("No {} cars available to mark for exit", behavior_name),
+            Err(e) => log::error!("Failed to mark a {} car for exit: {}", behavior_name, e),
+        }
+        self.resync_divergence_monitor();
+    }
+    
+    /// Follow the car closest to the middle of the screen, or stop following
+    fn toggle_follow_camera(&mut self) {
+        let viewport = &mut self.graphics.viewport;
+        if viewport.follow_target().is_some() {
+            viewport.set_follow_target(None);
+            info!("Follow camera off");
+            return;
+        }
+        
+        let center = viewport.get_position();
+        let nearest = self.simulation_state.cars.iter().min_by(|a, b| {
+            let da = (a.position.x - center.x).powi(2) + (a.position.y - center.y).powi(2);
+            let db = (b.position.x - center.x).powi(2) + (b.position.y - center.y).powi(2);
+            da.total_cmp(&db)
+        });
+        match nearest {
+            Some(car) => {
+                viewport.set_follow_target(Some(car.id));
+                info!("Following car {}", car.id);
+            }
+            None => info!("No cars to follow"),
+        }
+    }
+    
+    fn save_checkpoint(&self) {
+        if self.replay_player.is_some() {
+            info!("Cannot save checkpoints while replaying");
+            return;
+        }
+        match self.simulation_state.save(&self.checkpoint_file) {
+            Ok(()) => info!("Saved checkpoint at t={:.1}s to {}", self.simulation_state.time, self.checkpoint_file),
+            Err(e) => log::error!("Failed to save checkpoint: {}", e),
+        }
+    }
+    
+    /// Render and encode the video frames due at the current simulation time. The video
+    /// stops, and the run carries on, if a frame cannot be rendered or written.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn record_video_frame(&mut self) {
+        let Some(video) = &mut self.video_recorder else {
+            return;
+        };
+        let copies = video.frames_due(self.simulation_state.time);
+        if copies == 0 {
+            return;
+        }
+        
+        let (width, height) = video.size();
+        let view_matrix = self.graphics.viewport.view_matrix_for(width as f32, height as f32);
+        let result = self.graphics.renderer.capture(&self.simulation_state, &view_matrix, width, height)
+            .and_then(|frame| video.write_frame(&frame, copies));
+        if let Err(e) = result {
+            log::error!("Stopping video recording: {}", e);
+            self.finish_video();
         }
     }
     
+    #[cfg(not(target_arch = "wasm32"))]
+    fn finish_video(&mut self) {
+        if let Some(mut video) = self.video_recorder.take() {
+            match video.finish() {
+                Ok(()) => info!("Wrote {} video frames to {}", video.frames_written(), video.path().display()),
+                Err(e) => log::error!("Failed to finish video {}: {}", video.path().display(), e),
+            }
+        }
+    }
+    
+    /// Capture the next frame to a numbered PNG in the screenshot directory
+    fn take_screenshot(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let path = self.screenshot_dir.join(format!("screenshot-{:06}.png", self.frame_count));
+            self.graphics.request_screenshot(path, self.screenshot_size);
+        }
+        #[cfg(target_arch = "wasm32")]
+        info!("Screenshots are not available in the browser");
+    }
+    
+    fn load_checkpoint(&mut self) {
+        if self.replay_player.is_some() {
+            info!("Cannot load checkpoints while replaying");
+            return;
+        }
+        if self.comparison.is_some() {
+            info!("Cannot load checkpoints while comparing runs");
+            return;
+        }
+        let (path, seed, route) = (self.checkpoint_file.clone(), self.seed, self.config.route.clone());
+        let recorders = self.recorders.clone();
+        let loaded = self.with_backend(move |backend, state| -> Result<()> {
+            *state = load_checkpoint(&path, backend, seed)?;
+            // The summary covers the run from the loaded state on
+            lock(&recorders).summary = SummaryCollector::new(&route, state);
+            Ok(())
+        });
+        match loaded.and_then(|result| result) {
+            Ok(()) => {
+                self.previous_state = None;
+                self.resync_divergence_monitor();
+            }
+            Err(e) => log::error!("Failed to load checkpoint: {}", e),
+        }
+    }
+    
+    /// Flush any open output files before the event loop exits
+    fn shutdown(&mut self) {
+        // Stop the simulation thread where it is and show the last step it took
+        if let SimulationDriver::Threaded(runner) = &mut self.driver {
+            runner.set_running(true, self.simulation_speed);
+            if let Err(e) = self.with_backend(|_, _| ()) {
+                log::error!("Failed to stop the simulation: {}", e);
+            }
+        }
+        
+        // A replay only shows recorded states, the summary is of runs simulated here
+        if self.replay_player.is_none() {
+            let summary = lock(&self.recorders).summary.finish(&self.simulation_state);
+            let units = self.graphics.ui.preferences.units();
+            println!("=== Run Summary ===");
+            summary.print(units);
+            if let Some(comparison) = &self.comparison {
+                println!("=== Comparison Run Summary ===");
+                comparison.summary.finish(&comparison.state).print(units);
+            }
+            if let Some(path) = &self.summary_out {
+                match summary.write_json(path) {
+                    Ok(()) => info!("Wrote run summary to {}", path),
+                    Err(e) => log::error!("Failed to write run summary: {}", e),
+                }
+            }
+        }
+        if let Some(path) = &self.save_checkpoint {
+            match self.simulation_state.save(path) {
+                Ok(()) => info!("Saved checkpoint to {}", path),
+                Err(e) => log::error!("Failed to save checkpoint: {}", e),
+            }
+        }
+        lock(&self.recorders).flush();
+        #[cfg(not(target_arch = "wasm32"))]
+        self.finish_video();
+    }
+    
     fn update_frame_timing(&mut self) {
         let now = Instant::now();
         let _delta_time = now.duration_since(self.last_frame_time);
         self.last_frame_time = now;
         
-        // Limit frame rate if needed
-        let target_frame_time = std::time::Duration::from_secs_f32(1.0 / self.target_fps);
-        let elapsed = now.elapsed();
-        if elapsed < target_frame_time {
-            std::thread::sleep(target_frame_time - elapsed);
+        // Limit frame rate if needed. Browsers pace frames themselves and cannot block.
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let target_frame_time = std::time::Duration::from_secs_f32(1.0 / self.target_fps);
+            let elapsed = now.elapsed();
+            if elapsed < target_frame_time {
+                std::thread::sleep(target_frame_time - elapsed);
+            }
         }
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+fn init_logging(verbose: bool) {
+    env_logger::Builder::from_default_env()
+        .filter_level(if verbose { log::LevelFilter::Debug } else { log::LevelFilter::Info })
+        .init();
+}
+
+/// Log to the browser console, and show panics there instead of an unexplained abort
+#[cfg(target_arch = "wasm32")]
+fn init_logging(verbose: bool) {
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+    let level = if verbose { log::Level::Debug } else { log::Level::Info };
+    if let Err(e) = console_log::init_with_level(level) {
+        eprintln!("Failed to initialize logging: {}", e);
+    }
+}
+
+/// Units given by --units, or else the ones chosen in the Preferences window. Log lines and
+/// printed summaries follow them too.
+fn display_units(args: &Args) -> UnitSystem {
+    if let Some(units) = args.units {
+        return units.into();
+    }
+    UiPreferences::default_path()
+        .and_then(|path| UiPreferences::load(&path).ok())
+        .map_or_else(UnitSystem::default, |preferences| preferences.units)
+}
+
+fn load_config(args: &Args) -> Result<SimulationConfig> {
+    if args.verbose {
+        info!("Loading route configuration from: {}", args.scenario.as_ref().unwrap_or(&args.route));
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    let config = match &args.scenario {
+        Some(path) => ScenarioBundle::load(path)?.config(&args.overrides)?,
+        None => SimulationConfig::load_with_overrides(&args.route, &args.cars, &args.overrides)?,
+    };
+    // Browsers have no file system to read from, the default configuration is built in
+    #[cfg(target_arch = "wasm32")]
+    let config = SimulationConfig::load_from_strs_with_overrides(include_str!("../route.toml"), include_str!("../cars.toml"), &args.overrides)?;
+    info!("Loaded configuration: {} cars max, route: {}", 
+          config.cars.simulation.total_cars, 
+          config.route.route.name);
+          
+    if args.verbose {
+        let units = display_units(args);
+        info!("Route details: {} lanes, {} inner radius, {} outer radius", 
+              config.route.route.geometry.lane_count,
+              units.format_distance(config.route.route.geometry.inner_radius),
+              units.format_distance(config.route.route.geometry.outer_radius));
+        info!("Traffic rules: {} speed limit, {:.1}s following time", 
+              units.format_speed(config.route.route.traffic_rules.speed_limit),
+              config.route.route.traffic_rules.following_distance);
+        info!("Car types loaded: {}", config.cars.car_types.len());
+        info!("Behavior patterns loaded: {}", config.cars.behavior.len());
+        info!("Spawn configuration: {} total cars, {:.1} cars/s spawn rate", 
+              config.cars.simulation.total_cars,
+              config.cars.simulation.spawn_rate);
+    }
+    
+    Ok(config)
+}
+
+/// Configuration of the run on the right in comparison mode, and labels for both runs,
+/// `None` unless a comparison was asked for
+fn load_comparison_config(args: &Args, config: &SimulationConfig) -> Result<Option<(SimulationConfig, [String; 2])>> {
+    if args.compare_route.is_none() && args.compare_cars.is_none() && args.compare_overrides.is_empty() {
+        return Ok(None);
+    }
+    let route = args.compare_route.as_ref().unwrap_or(&args.route);
+    let cars = args.compare_cars.as_ref().unwrap_or(&args.cars);
+    let overrides: Vec<ConfigOverride> = args.overrides.iter().chain(&args.compare_overrides).cloned().collect();
+    let comparison = SimulationConfig::load_with_overrides(route, cars, &overrides)
+        .map_err(|e| anyhow::anyhow!("Invalid comparison configuration: {}", e))?;
+    if !comparison.route.same_geometry(&config.route) {
+        return Err(anyhow::anyhow!("The compared route {} must lay out the same road as {}", route, args.route));
+    }
+    
+    let label = |route: &str, cars: &str, overrides: &[ConfigOverride]| {
+        let mut parts = vec![route.to_string(), cars.to_string()];
+        parts.extend(overrides.iter().map(|parameter| format!("{}={}", parameter.key, parameter.value)));
+        parts.join(", ")
+    };
+    let labels = [
+        label(&args.route, &args.cars, &[]),
+        label(route, cars, &args.compare_overrides),
+    ];
+    Ok(Some((comparison, labels)))
+}
+
+/// Load a world file and the cars file for a multi-route run. The first route stands in
+/// wherever a single route is needed, such as for the summary's slow speed threshold.
+fn load_world(args: &Args, path: &str) -> Result<(SimulationConfig, World)> {
+    let world = WorldConfig::load(path)?;
+    let cars: CarsConfig = toml::from_str(&read_config_file(&args.cars)?)?;
+    cars.validate()?;
+    info!("Loaded world {}: {} routes, {} transfers", path, world.regions.len(), world.transfers.len());
+    
+    let config = SimulationConfig {
+        route: world.regions[0].route.clone(),
+        cars,
+    };
+    Ok((config, world))
+}
+
+/// Use seed from args, config, or generate a random one
+fn resolve_seed(args: &Args, config: &SimulationConfig) -> Option<u64> {
+    args.seed.or(config.cars.random.seed).or_else(|| {
+        let random_seed = rand::thread_rng().gen::<u64>();
+        Some(random_seed)
+    })
+}
+
+/// Initialize compute backend based on CLI argument, falling back to CPU if OpenCL is unavailable
+fn create_compute_backend(backend: Backend, config: &SimulationConfig, seed: Option<u64>) -> ComputeBackend {
+    match backend {
+        Backend::Cpu => {
+            let backend = ComputeBackend::new_cpu(
+                config.cars.clone(),
+                config.route.clone(),
+                seed
+            );
+            info!("✓ CPU Backend: {}", backend.get_name());
+            backend
+        }
+        Backend::Gpu => {
+            match ComputeBackend::new_gpu(
+                config.cars.clone(),
+                config.route.clone(),
+                seed
+            ) {
+                Ok(backend) => {
+                    info!("✓ GPU Backend: {} (OpenCL detected and initialized)", backend.get_name());
+                    backend
+                }
+                Err(e) => {
+                    info!("✗ GPU Backend: OpenCL not available ({e})");
+                    info!("↳ Falling back to CPU backend");
+                    let backend = ComputeBackend::new_cpu(
+                        config.cars.clone(),
+                        config.route.clone(),
+                        seed
+                    );
+                    info!("✓ CPU Backend: {}", backend.get_name());
+                    backend
+                }
+            }
+        }
+    }
+}
+
+/// Pipeline the GPU backend's steps when --gpu-pipeline asks for it
+fn pipeline_gpu(backend: &mut ComputeBackend, pipelined: bool) {
+    if pipelined {
+        if backend.set_pipelined(true) {
+            info!("GPU steps pipelined: the road is shown a step behind the device");
+        } else {
+            log::warn!("--gpu-pipeline only applies to the GPU backend, {} steps in place", backend.get_name());
+        }
+    }
+}
+
+/// The backend the run does not use, set up to follow it from its first state, when
+/// --check-divergence asks for one
+fn create_divergence_monitor(
+    args: &Args,
+    config: &SimulationConfig,
+    backend: &ComputeBackend,
+    state: &SimulationState,
+    seed: Option<u64>
+) -> Result<Option<DivergenceMonitor>> {
+    if !args.check_divergence {
+        return Ok(None);
+    }
+    let mut other = if backend.supports_gpu() {
+        ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), seed)
+    } else {
+        ComputeBackend::new_gpu(config.cars.clone(), config.route.clone(), seed)
+            .map_err(|e| anyhow::anyhow!("Divergence checks need the GPU backend next to the CPU one: {}", e))?
+    };
+    // A loaded checkpoint restored the run's backend, the other one starts from it too
+    if args.load_checkpoint.is_some() {
+        other.restore_checkpoint(state, seed);
+    }
+    let tolerance = DivergenceTolerance {
+        position: args.divergence_position,
+        velocity: args.divergence_velocity,
+    };
+    info!("Checking {} against {} backend (tolerance {} m, {} m/s)", backend.get_name(), other.get_name(), tolerance.position, tolerance.velocity);
+    Ok(Some(DivergenceMonitor::new(other, state, tolerance)))
+}
+
+/// Open the metrics file requested on the command line, if any
+/// Watch the configuration files for changes, unless disabled, replaying or running a
+/// scenario bundle
+#[cfg(not(target_arch = "wasm32"))]
+fn create_config_watcher(args: &Args) -> Option<ConfigWatcher> {
+    if args.no_watch || args.replay.is_some() || args.scenario.is_some() {
+        return None;
+    }
+    match ConfigWatcher::new(&args.route, &args.cars, args.overrides.clone()) {
+        Ok(watcher) => {
+            info!("Watching {} and {} for changes", args.route, args.cars);
+            Some(watcher)
+        }
+        Err(e) => {
+            log::warn!("Not watching configuration files for changes: {}", e);
+            None
+        }
+    }
+}
+
+fn create_metrics_exporter(args: &Args, config: &SimulationConfig) -> Result<Option<MetricsExporter>> {
+    match &args.metrics_out {
+        Some(path) => {
+            let exporter = MetricsExporter::create(path, args.metrics_format.into(), &config.route)?;
+            info!("Writing metrics to: {}", path);
+            Ok(Some(exporter))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Open the detector readings file requested on the command line, if any
+fn create_detector_exporter(args: &Args, config: &SimulationConfig) -> Result<Option<DetectorExporter>> {
+    match &args.detectors_out {
+        Some(path) => {
+            if config.route.route.detectors.is_empty() {
+                log::warn!("--detectors-out given but the route has no [[route.detectors]]");
+            }
+            let exporter = DetectorExporter::create(path, args.metrics_format.into(), &config.route)?;
+            info!("Writing detector readings to: {}", path);
+            Ok(Some(exporter))
+        }
+        None => Ok(None),
+    }
+}
+
+fn create_trip_exporter(args: &Args) -> Result<Option<TripExporter>> {
+    match &args.trips_out {
+        Some(path) => {
+            let exporter = TripExporter::create(path, args.metrics_format.into())?;
+            info!("Writing trips to: {}", path);
+            Ok(Some(exporter))
+        }
+        None => Ok(None),
+    }
+}
+
+fn create_conflict_exporter(args: &Args) -> Result<Option<ConflictExporter>> {
+    match &args.conflicts_out {
+        Some(path) => {
+            let exporter = ConflictExporter::create(path, args.metrics_format.into())?;
+            info!("Writing conflicts to: {}", path);
+            Ok(Some(exporter))
+        }
+        None => Ok(None),
+    }
+}
+
+fn create_queue_exporter(args: &Args) -> Result<Option<QueueExporter>> {
+    match &args.queues_out {
+        Some(path) => {
+            let exporter = QueueExporter::create(path, args.metrics_format.into())?;
+            info!("Writing queues to: {}", path);
+            Ok(Some(exporter))
+        }
+        None => Ok(None),
+    }
+}
+
+fn create_travel_time_exporter(args: &Args) -> Result<Option<TravelTimeExporter>> {
+    match &args.travel_times_out {
+        Some(path) => {
+            let exporter = TravelTimeExporter::create(path, args.metrics_format.into())?;
+            info!("Writing travel times to: {}", path);
+            Ok(Some(exporter))
+        }
+        None => Ok(None),
+    }
+}
+
+fn create_trajectory_exporter(args: &Args, config: &SimulationConfig) -> Result<Option<TrajectoryExporter>> {
+    match &args.trajectories_out {
+        Some(path) => {
+            if args.trajectory_interval <= 0.0 {
+                anyhow::bail!("--trajectory-interval must be positive");
+            }
+            let exporter = TrajectoryExporter::create(path, args.metrics_format.into(), &config.route.route.geometry, args.trajectory_interval)?;
+            info!("Writing trajectories to: {}", path);
+            Ok(Some(exporter))
+        }
+        None => Ok(None),
+    }
+}
+
+fn create_fcd_exporter(args: &Args, config: &SimulationConfig) -> Result<Option<FcdExporter>> {
+    match &args.fcd_out {
+        Some(path) => {
+            if args.fcd_period.is_some_and(|period| period <= 0.0) {
+                anyhow::bail!("--fcd-period must be positive");
+            }
+            let exporter = FcdExporter::create(path, &config.route.route.geometry, args.fcd_period)?;
+            info!("Writing SUMO FCD to: {}", path);
+            Ok(Some(exporter))
+        }
+        None => Ok(None),
+    }
+}
+
+fn create_noise_exporter(args: &Args, config: &SimulationConfig) -> Result<Option<NoiseExporter>> {
+    match &args.noise_out {
+        Some(path) => {
+            if args.noise_cell <= 0.0 {
+                anyhow::bail!("--noise-cell must be positive");
+            }
+            let exporter = NoiseExporter::create(path, &config.cars, args.noise_cell)?;
+            info!("Writing the noise map to: {}", path);
+            Ok(Some(exporter))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Open the replay file requested on the command line, if any
+fn create_replay_recorder(args: &Args, config: &SimulationConfig, seed: Option<u64>) -> Result<Option<ReplayRecorder>> {
+    match &args.record {
+        Some(path) => {
+            let recorder = ReplayRecorder::create(path, config, seed)?;
+            info!("Recording replay to: {}", path);
+            Ok(Some(recorder))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Start encoding the video requested on the command line, if any
+#[cfg(not(target_arch = "wasm32"))]
+fn create_video_recorder(args: &Args, state: &SimulationState) -> Result<Option<VideoRecorder>> {
+    match &args.record_video {
+        Some(path) => {
+            let recorder = VideoRecorder::start(path, args.video_size, args.video_fps, state.time)?;
+            let (width, height) = args.video_size;
+            info!("Recording {}x{} video at {} fps to: {}", width, height, args.video_fps, path);
+            Ok(Some(recorder))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Start the telemetry server requested on the command line, if any
+fn create_telemetry_server(args: &Args, config: &SimulationConfig) -> Result<Option<TelemetryServer>> {
+    match &args.serve {
+        Some(address) => {
+            let server = TelemetryServer::bind(address, config)?;
+            info!("Serving telemetry on ws://{}", server.local_addr());
+            Ok(Some(server))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Schedule the events of the scenario bundle, if one was given and has any
+fn create_event_schedule(args: &Args, start_time: f32) -> Result<Option<EventSchedule>> {
+    let Some(path) = &args.scenario else {
+        return Ok(None);
+    };
+    let bundle = ScenarioBundle::load(path)?;
+    if bundle.events.is_empty() {
+        return Ok(None);
+    }
+    info!("Scenario {}: {} events", path, bundle.events.len());
+    Ok(Some(EventSchedule::new(bundle.events, start_time)))
+}
+
+/// Carry out a command from a telemetry client or a scenario event. Pausing and speed
+/// belong to whichever loop drives the simulation, so they are passed in.
+fn apply_server_command(
+    command: ServerCommand,
+    backend: &mut ComputeBackend,
+    state: &mut SimulationState,
+    paused: &mut bool,
+    speed: &mut f32
+) {
+    info!("Command at t={:.1}s: {:?}", state.time, command);
+    match command {
+        ServerCommand::Pause => *paused = true,
+        ServerCommand::Resume => *paused = false,
+        ServerCommand::SetSpeed { multiplier } => *speed = multiplier,
+        ServerCommand::SpawnCar { behavior } => {
+            if let Err(e) = backend.spawn_manual_car(&behavior, state) {
+                log::warn!("Cannot spawn {} car: {}", behavior, e);
+            }
+        }
+        ServerCommand::SetSignalPhase { group, phase } => state.set_signal_override(&group, phase),
+    }
+}
+
+/// Overwrite `copy` with `state`, reusing the memory of the copy taken before
+fn copy_state(copy: &mut Option<SimulationState>, state: &SimulationState) {
+    match copy {
+        Some(copy) => copy.clone_from(state),
+        None => *copy = Some(state.clone()),
+    }
+}
+
+/// Load a checkpoint and prepare the backend to continue from it
+fn load_checkpoint(path: &str, backend: &mut ComputeBackend, seed: Option<u64>) -> Result<SimulationState> {
+    let state = SimulationState::load(path)?;
+    backend.restore_checkpoint(&state, seed);
+    info!("Loaded checkpoint from {} (t={:.1}s, {} cars)", path, state.time, state.cars.len());
+    Ok(state)
+}
+
+/// Advance the simulation by one timestep and refresh per-car bookkeeping
+#[cfg(not(target_arch = "wasm32"))]
+fn run_sweep(file: &str, jobs: Option<usize>, output: Option<&str>) -> Result<()> {
+    let path = std::path::Path::new(file);
+    let sweep = SweepConfig::load(path)?;
+    let directory = path.parent().unwrap_or(std::path::Path::new(""));
+    let output = output.map(std::path::PathBuf::from).unwrap_or_else(|| sweep::default_output(path));
+    let jobs = jobs.unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1));
+    
+    info!("Running sweep {} on {} threads, writing {}", file, jobs, output.display());
+    let wall_start = Instant::now();
+    let results = sweep::run_sweep(&sweep, directory, &output, jobs, SIMULATION_DT)?;
+    
+    println!("=== Sweep Summary ===");
+    println!("Runs: {}", results.len());
+    println!("Wall time: {:.2}s", wall_start.elapsed().as_secs_f32());
+    println!("Results: {}", output.display());
+    Ok(())
+}
+
+/// Print an example file or report every problem in a pair of configuration files
+#[cfg(not(target_arch = "wasm32"))]
+fn run_config_command(action: &ConfigCommand) -> Result<()> {
+    use anyhow::Context;
+    
+    match action {
+        ConfigCommand::ExampleRoute { geometry } => {
+            print!("{}", traffic_sim::config::example_route(geometry.name()).expect("every geometry has an example"));
+        }
+        ConfigCommand::ExampleCars { geometry } => {
+            print!("{}", traffic_sim::config::example_cars(geometry.name()).expect("every geometry has an example"));
+        }
+        ConfigCommand::Check { route, cars } => {
+            let config = SimulationConfig::load_from_files(route, cars)
+                .with_context(|| format!("Checking {} and {}", route, cars))?;
+            // Loading fails on the errors, listing them all; the check fails on warnings too,
+            // such as settings for entries the route does not have, which a run only logs
+            let problems = config.cross_check();
+            for problem in &problems {
+                println!("{}", problem);
+            }
+            if !problems.is_empty() {
+                return Err(anyhow::anyhow!("{} and {} disagree in {} place(s)", route, cars, problems.len()));
+            }
+            println!("{} ({} geometry, {} entries, {} exits) and {} ({} car types, {} behaviors) are valid",
+                route, config.route.route.geometry.geometry_type, config.route.route.entries.len(), config.route.route.exits.len(),
+                cars, config.cars.car_types.len(), config.cars.behavior.len());
+        }
+    }
+    Ok(())
+}
+
+/// Write the --route and --cars files with the --set overrides, --seed and an events file as
+/// one scenario bundle
+fn run_pack(args: &Args, output: &str, events: Option<&str>) -> Result<()> {
+    use anyhow::Context;
+    
+    let events = match events {
+        Some(path) => load_events(path)?,
+        None => Vec::new(),
+    };
+    let bundle = ScenarioBundle::pack(&args.route, &args.cars, &args.overrides, args.seed, events)
+        .with_context(|| format!("Packing {} and {}", args.route, args.cars))?;
+    let mut output = std::path::PathBuf::from(output);
+    if output.extension().is_none() {
+        output.set_extension(BUNDLE_EXTENSION);
+    }
+    bundle.save(&output)?;
+    println!("Packed {} and {} with {} events and {} into {}",
+             args.route, args.cars, bundle.events.len(),
+             bundle.seed.map_or("no seed".to_string(), |seed| format!("seed {}", seed)), output.display());
+    Ok(())
+}
+
+/// Run the scenario once per seed on the CPU backend and print the mean and confidence
+/// interval of every summary metric
+#[cfg(not(target_arch = "wasm32"))]
+fn run_replications(args: &Args, count: usize) -> Result<()> {
+    let config = load_config(args)?;
+    if create_event_schedule(args, 0.0)?.is_some() {
+        log::warn!("Scenario events are not carried out in replications");
+    }
+    if args.backend == Backend::Gpu {
+        log::warn!("Replications run on the CPU backend");
+    }
+    let base_seed = resolve_seed(args, &config).unwrap_or_default();
+    let seeds = sweep::replication_seeds(base_seed, count);
+    let duration = args.duration.unwrap_or(config.cars.simulation.simulation_duration);
+    if duration <= 0.0 {
+        return Err(anyhow::anyhow!("Headless duration must be positive, got {}", duration));
+    }
+    let output = std::path::PathBuf::from(&args.replications_out);
+    let aggregate_output = sweep::default_aggregate_output(&output);
+    let jobs = args.jobs.unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1));
+    
+    info!("Running {} replications of {:.1}s on {} threads, seeds {} to {}", count, duration, jobs, seeds[0], seeds[count - 1]);
+    let wall_start = Instant::now();
+    let (_, estimates) = sweep::run_replications(&config, &seeds, duration, SIMULATION_DT, jobs, &output, &aggregate_output)?;
+    
+    println!("=== Replication Summary ===");
+    println!("Route: {} ({})", config.route.route.name, args.scenario.as_ref().unwrap_or(&args.route));
+    println!("Replications: {} (seeds {} to {})", count, seeds[0], seeds[count - 1]);
+    println!("Simulated time: {:.1}s each", duration);
+    println!("Wall time: {:.2}s", wall_start.elapsed().as_secs_f32());
+    println!("Mean ± 95% confidence interval:");
+    for estimate in &estimates {
+        println!("  {}: {:.3} ± {:.3}", estimate.metric, estimate.mean, estimate.half_width);
+    }
+    if count < 2 {
+        println!("(one replication has no confidence interval)");
+    }
+    println!("Per-run results: {}", output.display());
+    println!("Aggregate results: {}", aggregate_output.display());
+    Ok(())
+}
+
+fn step_simulation(backend: &mut ComputeBackend, state: &mut SimulationState) -> Result<()> {
+    backend.update(state)?;
+    
+    // Update speed history for all cars
+    state.update_car_speeds();
+    
+    // Update active car count
+    state.active_cars = state.cars.len() as u32;
+    
+    Ok(())
+}
+
+/// Run the simulation without a window or GPU surface, then print summary statistics
+fn run_headless(args: Args) -> Result<()> {
+    info!("Starting Traffic Simulator (headless)");
+    let (config, world) = match &args.world {
+        Some(path) => {
+            let (config, world) = load_world(&args, path)?;
+            (config, Some(world))
+        }
+        None => (load_config(&args)?, None),
+    };
+    let seed = resolve_seed(&args, &config);
+    let mut compute_backend = match &world {
+        Some(world) => {
+            if args.backend == Backend::Gpu {
+                log::warn!("Multi-route worlds run on the CPU backend");
+            }
+            ComputeBackend::new_world(config.cars.clone(), world, seed)
+        }
+        None => create_compute_backend(args.backend, &config, seed),
+    };
+    let mut metrics_exporter = create_metrics_exporter(&args, &config)?;
+    let mut detector_exporter = create_detector_exporter(&args, &config)?;
+    let mut trip_exporter = create_trip_exporter(&args)?;
+    let mut conflict_exporter = create_conflict_exporter(&args)?;
+    let mut queue_exporter = create_queue_exporter(&args)?;
+    let mut travel_time_exporter = create_travel_time_exporter(&args)?;
+    let mut trajectory_exporter = create_trajectory_exporter(&args, &config)?;
+    let mut fcd_exporter = create_fcd_exporter(&args, &config)?;
+    let mut noise_exporter = create_noise_exporter(&args, &config)?;
+    let mut replay_recorder = create_replay_recorder(&args, &config, seed)?;
+    let mut telemetry_server = create_telemetry_server(&args, &config)?;
+    
+    let dt = SIMULATION_DT;
+    let mut state = match &args.load_checkpoint {
+        Some(path) => load_checkpoint(path, &mut compute_backend, seed)?,
+        None => SimulationState::new(dt),
+    };
+    let mut scenario_events = create_event_schedule(&args, state.time)?;
+    let mut summary = SummaryCollector::new(&config.route, &state);
+    let start_time = state.time;
+    let duration = args.duration.unwrap_or(config.cars.simulation.simulation_duration);
+    if duration <= 0.0 {
+        return Err(anyhow::anyhow!("Headless duration must be positive, got {}", duration));
+    }
+    let steps = (duration / state.dt).ceil() as u64;
+    
+    info!("Running headless for {:.1}s of simulated time ({} steps)", duration, steps);
+    if let Some(seed) = seed {
+        info!("Random Seed: {}", seed);
+    }
+    
+    let wall_start = Instant::now();
+    let mut peak_cars = 0;
+    let mut speed_sum = 0.0f64;
+    let mut speed_samples = 0u64;
+    
+    // Served runs keep to real time, scaled by the speed command, so clients can follow along
+    let mut paused = false;
+    let mut speed = 1.0;
+    let mut step_accumulator = 0.0;
+    let mut last_pace = Instant::now();
+    
+    let mut step = 0;
+    while step < steps {
+        if let Some(server) = &telemetry_server {
+            for command in server.poll_commands() {
+                apply_server_command(command, &mut compute_backend, &mut state, &mut paused, &mut speed);
+            }
+            let now = Instant::now();
+            if !paused {
+                step_accumulator += now.duration_since(last_pace).as_secs_f32().min(MAX_FRAME_TIME) * speed;
+            }
+            last_pace = now;
+            if step_accumulator < state.dt {
+                std::thread::sleep(std::time::Duration::from_millis(1));
+                continue;
+            }
+            step_accumulator -= state.dt;
+        }
+        if let Some(events) = &mut scenario_events {
+            for event in events.due(state.time) {
+                apply_server_command(event.command.clone(), &mut compute_backend, &mut state, &mut paused, &mut speed);
+            }
+        }
+        
+        step_simulation(&mut compute_backend, &mut state)?;
+        summary.record(&state);
+        
+        if let Some(exporter) = &mut metrics_exporter {
+            exporter.record(&state)?;
+        }
+        if let Some(exporter) = &mut detector_exporter {
+            exporter.record(&state)?;
+        }
+        if let Some(exporter) = &mut trip_exporter {
+            exporter.record(&state)?;
+        }
+        if let Some(exporter) = &mut conflict_exporter {
+            exporter.record(&state)?;
+        }
+        if let Some(exporter) = &mut queue_exporter {
+            exporter.record(&state)?;
+        }
+        if let Some(exporter) = &mut travel_time_exporter {
+            exporter.record(&state)?;
+        }
+        if let Some(exporter) = &mut trajectory_exporter {
+            exporter.record(&state)?;
+        }
+        if let Some(exporter) = &mut fcd_exporter {
+            exporter.record(&state)?;
+        }
+        if let Some(exporter) = &mut noise_exporter {
+            exporter.record(&state);
+        }
+        if let Some(recorder) = &mut replay_recorder {
+            recorder.record(&state)?;
+        }
+        if let Some(server) = &mut telemetry_server {
+            server.publish(&state)?;
+        }
+        
+        peak_cars = peak_cars.max(state.active_cars);
+        for car in &state.cars {
+            speed_sum += car.velocity.magnitude() as f64;
+            speed_samples += 1;
+        }
+        
+        // Progress report every 10 simulated seconds
+        if args.verbose && step % 600 == 0 {
+            log::debug!("t={:.1}s: {} active cars, {} spawned", state.time, state.active_cars, state.total_spawned);
+        }
+        step += 1;
+    }
+    
+    let wall_time = wall_start.elapsed();
+    if let Some(exporter) = &mut metrics_exporter {
+        exporter.flush()?;
+    }
+    if let Some(exporter) = &mut detector_exporter {
+        exporter.flush()?;
+    }
+    if let Some(exporter) = &mut trip_exporter {
+        exporter.flush()?;
+    }
+    if let Some(exporter) = &mut conflict_exporter {
+        exporter.flush()?;
+    }
+    if let Some(exporter) = &mut queue_exporter {
+        exporter.flush()?;
+    }
+    if let Some(exporter) = &mut travel_time_exporter {
+        exporter.flush()?;
+    }
+    if let Some(exporter) = &mut trajectory_exporter {
+        exporter.flush()?;
+    }
+    if let Some(exporter) = &mut fcd_exporter {
+        exporter.finish()?;
+    }
+    if let Some(exporter) = &mut noise_exporter {
+        exporter.finish()?;
+    }
+    if let Some(recorder) = &mut replay_recorder {
+        recorder.flush()?;
+    }
+    if let Some(path) = &args.save_checkpoint {
+        state.save(path)?;
+        info!("Saved checkpoint to {}", path);
+    }
+    let mean_speed = if speed_samples > 0 { (speed_sum / speed_samples as f64) as f32 } else { 0.0 };
+    let mut behavior_counts = state.get_behavior_counts();
+    behavior_counts.sort_by_key(|(behavior, _)| state.names.behavior(*behavior));
+    
+    println!("=== Headless Run Summary ===");
+    match (&world, &args.world) {
+        (Some(world), Some(path)) => {
+            println!("World: {} ({} routes)", path, world.regions.len());
+            for (index, region) in world.regions.iter().enumerate() {
+                let cars = state.cars.iter().filter(|car| car.route == index).count();
+                println!("  {}: {} ({} active cars)", region.id, region.route.route.name, cars);
+            }
+        }
+        _ => println!("Route: {} ({})", config.route.route.name, args.scenario.as_ref().unwrap_or(&args.route)),
+    }
+    println!("Backend: {}", compute_backend.get_name());
+    match seed {
+        Some(s) => println!("Seed: {}", s),
+        None => println!("Seed: random"),
+    }
+    if let Some(path) = &args.load_checkpoint {
+        println!("Checkpoint: {} (resumed at {:.1}s)", path, start_time);
+    }
+    println!("Simulated time: {:.1}s ({} steps)", state.time, steps);
+    println!("Wall time: {:.2}s ({:.1}x real time)", 
+             wall_time.as_secs_f32(), 
+             (state.time - start_time) / wall_time.as_secs_f32().max(f32::EPSILON));
+    let summary = summary.finish(&state);
+    let units = display_units(&args);
+    summary.print(units);
+    println!("Peak active cars: {}", peak_cars);
+    println!("Mean speed: {}", units.format_speed(mean_speed));
+    println!("Active cars by behavior:");
+    for (behavior, count) in behavior_counts {
+        println!("  {}: {}", state.names.behavior(behavior), count);
+    }
+    if let Some(path) = &args.summary_out {
+        summary.write_json(path)?;
+        info!("Wrote run summary to {}", path);
+    }
+    
+    Ok(())
+}
+
 async fn run_simulation(args: Args) -> Result<()> {
     let event_loop = EventLoop::new()?;
     let mut app = Application::new(&args, Some(&event_loop)).await?;
     
     info!("Starting interactive mode...");
     
-    event_loop.run(move |event, control_flow| {
+    let event_handler = move |event: Event<()>, control_flow: &EventLoopWindowTarget<()>| {
         app.performance_tracker.start_frame();
         
         match event {
@@ -450,35 +2670,35 @@ async fn run_simulation(args: Args) -> Result<()> {
                 ref event,
                 window_id,
             } => {
-                if window_id == app.graphics.window.id() {
-                    if !app.handle_input(event) {
-                        match event {
-                            WindowEvent::CloseRequested => {
-                                info!("Close requested");
-                                control_flow.exit();
-                            }
-                            WindowEvent::RedrawRequested => {
-                                if let Err(e) = app.update() {
-                                    log::error!("Update error: {}", e);
-                                }
-                                
-                                if let Err(e) = app.render() {
-                                    log::error!("Render error: {}", e);
-                                }
-                            }
-                            WindowEvent::Resized(_physical_size) => {
-                                // Handled in graphics system
+                if window_id == app.graphics.window.id() && !app.handle_input(event) {
+                    match event {
+                        WindowEvent::CloseRequested => {
+                            info!("Close requested");
+                            app.shutdown();
+                            control_flow.exit();
+                        }
+                        WindowEvent::RedrawRequested => {
+                            if let Err(e) = app.update() {
+                                log::error!("Update error: {}", e);
                             }
-                            WindowEvent::ScaleFactorChanged { .. } => {
-                                // Handled in graphics system
+                            
+                            if let Err(e) = app.render() {
+                                log::error!("Render error: {}", e);
                             }
-                            _ => {}
                         }
+                        WindowEvent::Resized(_physical_size) => {
+                            // Handled in graphics system
+                        }
+                        WindowEvent::ScaleFactorChanged { .. } => {
+                            // Handled in graphics system
+                        }
+                        _ => {}
                     }
                 }
                 
                 // Check for exit flag
                 if app.should_exit {
+                    app.shutdown();
                     control_flow.exit();
                 }
             }
@@ -491,15 +2711,58 @@ async fn run_simulation(args: Args) -> Result<()> {
         }
         
         app.performance_tracker.end_frame();
-    })?;
+    };
+    
+    // The browser owns the event loop, so on the web it is handed over instead of run
+    #[cfg(not(target_arch = "wasm32"))]
+    event_loop.run(event_handler)?;
+    #[cfg(target_arch = "wasm32")]
+    {
+        use winit::platform::web::EventLoopExtWebSys;
+        event_loop.spawn(event_handler);
+    }
     Ok(())
 }
 
 
+#[cfg(not(target_arch = "wasm32"))]
 fn main() -> Result<()> {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    init_logging(args.verbose);
+    if let Some(count) = args.ring_cars {
+        args.overrides.push(format!("cars.simulation.ring_cars={}", count).parse()?);
+    }
+    
+    if let Some(Command::Sweep { file, jobs, output }) = &args.command {
+        return run_sweep(file, *jobs, output.as_deref());
+    }
+    if let Some(Command::Config { action }) = &args.command {
+        return run_config_command(action);
+    }
+    if let Some(Command::Pack { output, events }) = &args.command {
+        return run_pack(&args, output, events.as_deref());
+    }
+    if let Some(count) = args.replications {
+        return run_replications(&args, count as usize);
+    }
+    if args.headless {
+        return run_headless(args);
+    }
     
     pollster::block_on(async {
         run_simulation(args).await
     })
 }
+
+/// In the browser the simulation always runs interactively with the default options
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    let args = Args::parse_from(["traffic-sim"]);
+    init_logging(args.verbose);
+    
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Err(e) = run_simulation(args).await {
+            log::error!("Simulation failed: {}", e);
+        }
+    });
+}
@@ -0,0 +1,156 @@
+use crate::config::{SimulationConfig, CarsConfig, RouteConfig, Validate};
+use crate::simulation::SimulationState;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const REPLAY_MAGIC: &[u8; 8] = b"TSREPLAY";
+const REPLAY_VERSION: u32 = 15;
+
+/// Metadata stored at the start of a replay file.
+/// The configurations are embedded so a replay can be shared without its TOML files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ReplayHeader {
+    version: u32,
+    route_toml: String,
+    cars_toml: String,
+    seed: Option<u64>,
+}
+
+/// Writes a snapshot of the simulation state for every recorded tick
+pub struct ReplayRecorder {
+    writer: BufWriter<File>,
+    frames_written: u64,
+}
+
+impl ReplayRecorder {
+    pub fn create(path: impl AsRef<Path>, config: &SimulationConfig, seed: Option<u64>) -> Result<Self> {
+        let header = ReplayHeader {
+            version: REPLAY_VERSION,
+            route_toml: toml::to_string(&config.route)?,
+            cars_toml: toml::to_string(&config.cars)?,
+            seed,
+        };
+        
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(REPLAY_MAGIC)?;
+        write_record(&mut writer, &header)?;
+        
+        Ok(Self {
+            writer,
+            frames_written: 0,
+        })
+    }
+    
+    pub fn record(&mut self, state: &SimulationState) -> Result<()> {
+        write_record(&mut self.writer, state)?;
+        self.frames_written += 1;
+        Ok(())
+    }
+    
+    pub fn frames_written(&self) -> u64 {
+        self.frames_written
+    }
+    
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads simulation snapshots back from a replay file in recorded order
+pub struct ReplayPlayer {
+    reader: BufReader<File>,
+    config: SimulationConfig,
+    seed: Option<u64>,
+    frames_start: u64,
+    frames_read: u64,
+}
+
+impl ReplayPlayer {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut reader = BufReader::new(File::open(path)?);
+        
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != REPLAY_MAGIC {
+            return Err(anyhow!("{} is not a traffic-sim replay file", path.display()));
+        }
+        
+        let header: ReplayHeader = read_record(&mut reader)?
+            .ok_or_else(|| anyhow!("Replay file {} is missing its header", path.display()))?;
+        if header.version != REPLAY_VERSION {
+            return Err(anyhow!("Unsupported replay version {} (expected {})", header.version, REPLAY_VERSION));
+        }
+        
+        let route: RouteConfig = toml::from_str(&header.route_toml)?;
+        let cars: CarsConfig = toml::from_str(&header.cars_toml)?;
+        route.validate()?;
+        cars.validate()?;
+        
+        let frames_start = reader.stream_position()?;
+        
+        Ok(Self {
+            reader,
+            config: SimulationConfig { route, cars },
+            seed: header.seed,
+            frames_start,
+            frames_read: 0,
+        })
+    }
+    
+    /// Configuration the replay was recorded with
+    pub fn config(&self) -> &SimulationConfig {
+        &self.config
+    }
+    
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+    
+    pub fn frames_read(&self) -> u64 {
+        self.frames_read
+    }
+    
+    /// Read the next snapshot, or `None` once the end of the recording is reached
+    pub fn next_frame(&mut self) -> Result<Option<SimulationState>> {
+        let mut frame: Option<SimulationState> = read_record(&mut self.reader)?;
+        if let Some(state) = &mut frame {
+            state.reindex_cars();
+            self.frames_read += 1;
+        }
+        Ok(frame)
+    }
+    
+    /// Jump back to the first recorded frame
+    pub fn rewind(&mut self) -> Result<()> {
+        self.reader.seek(SeekFrom::Start(self.frames_start))?;
+        self.frames_read = 0;
+        Ok(())
+    }
+}
+
+/// Records are stored as a little-endian u32 length followed by the bincode payload
+fn write_record<W: Write, T: Serialize>(writer: &mut W, value: &T) -> Result<()> {
+    let bytes = bincode::serialize(value)?;
+    writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    writer.write_all(&bytes)?;
+    Ok(())
+}
+
+fn read_record<R: Read, T: for<'de> Deserialize<'de>>(reader: &mut R) -> Result<Option<T>> {
+    let mut len_bytes = [0u8; 4];
+    match reader.read_exact(&mut len_bytes) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    
+    let mut bytes = vec![0u8; u32::from_le_bytes(len_bytes) as usize];
+    reader.read_exact(&mut bytes)
+        .map_err(|e| anyhow!("Truncated replay record: {}", e))?;
+    Ok(Some(bincode::deserialize(&bytes)?))
+}
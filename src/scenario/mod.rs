@@ -0,0 +1,176 @@
+use crate::config::{read_config_file, units, ConfigOverride, LaneClosure, SimulationConfig};
+use crate::server::ServerCommand;
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Extension of scenario bundle files
+pub const BUNDLE_EXTENSION: &str = "tsim";
+
+/// One experiment in a single file to share: the route and cars configurations, the
+/// events carried out during the run and optionally the seed. The `[route]` and `[cars]`
+/// sections are written as in the two configuration files, so keys match `--set` keys.
+///
+/// ```toml
+/// seed = 42
+///
+/// [route]
+/// name = "Donut Highway"
+///
+/// [route.geometry]
+/// type = "donut"
+/// # ...
+///
+/// [cars.simulation]
+/// total_cars = 500
+/// # ...
+///
+/// [[events]]
+/// time = "2 min"
+/// command = "spawn_car"
+/// behavior = "erratic"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioBundle {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>, // used as [cars.random] seed
+    pub route: toml::Table, // the [route] table of a route file
+    pub cars: toml::Table, // a cars file from its top level
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<ScenarioEvent>,
+}
+
+/// Command carried out when the run reaches `time`, one of those telemetry clients send
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioEvent {
+    #[serde(with = "units::time")]
+    pub time: f32, // simulated seconds
+    #[serde(flatten)]
+    pub command: ServerCommand,
+}
+
+/// File of `[[events]]` to pack into a bundle
+#[derive(Debug, Deserialize)]
+struct EventsFile {
+    #[serde(default)]
+    events: Vec<ScenarioEvent>,
+}
+
+impl ScenarioBundle {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read scenario {}: {}", path.display(), e))?;
+        toml::from_str(&content).map_err(|e| anyhow!("Failed to parse scenario {}: {}", path.display(), e))
+    }
+    
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+    
+    /// Bundle the route and cars files, laid over their bases and with `overrides` applied,
+    /// with `seed` and `events`. Fails unless the bundle loads.
+    pub fn pack(route_path: &str, cars_path: &str, overrides: &[ConfigOverride], seed: Option<u64>, events: Vec<ScenarioEvent>) -> Result<Self> {
+        let mut route = toml::Value::Table(toml::from_str(&read_config_file(route_path)?)?);
+        let mut cars = toml::Value::Table(toml::from_str(&read_config_file(cars_path)?)?);
+        for config_override in overrides {
+            config_override.apply(&mut route, &mut cars)?;
+        }
+        let Some(toml::Value::Table(route)) = route.as_table_mut().and_then(|file| file.remove("route")) else {
+            bail!("{} has no [route] table", route_path);
+        };
+        let toml::Value::Table(cars) = cars else {
+            unreachable!("parsed from a table");
+        };
+        
+        let bundle = Self { seed, route, cars, events };
+        bundle.config(&[])?;
+        Ok(bundle)
+    }
+    
+    /// The bundled configuration with `overrides` applied, validated as files are, with
+    /// the events checked against it
+    pub fn config(&self, overrides: &[ConfigOverride]) -> Result<SimulationConfig> {
+        let mut cars = self.cars.clone();
+        if let Some(seed) = self.seed {
+            let seed = i64::try_from(seed).map_err(|_| anyhow!("Seed {} is too large for a scenario file", seed))?;
+            if let toml::Value::Table(random) = cars.entry("random").or_insert_with(|| toml::Value::Table(toml::Table::new())) {
+                random.insert("seed".to_string(), toml::Value::Integer(seed));
+            }
+        }
+        let route = toml::Table::from_iter([("route".to_string(), toml::Value::Table(self.route.clone()))]);
+        let config = SimulationConfig::load_from_strs_with_overrides(&toml::to_string(&route)?, &toml::to_string(&cars)?, overrides)?;
+        
+        for event in &self.events {
+            if !(event.time >= 0.0 && event.time.is_finite()) {
+                bail!("Scenario event times must be 0 or later, got {}", event.time);
+            }
+            event.command.check_against(&config)
+                .map_err(|e| anyhow!("Scenario event at {}s: {}", event.time, e))?;
+        }
+        Ok(config)
+    }
+    
+    /// Replace the lane closures in the bundle at `path` with `closures`, leaving the rest
+    /// of it as it is
+    pub fn save_closures(path: impl AsRef<Path>, closures: &[LaneClosure]) -> Result<()> {
+        let path = path.as_ref();
+        let mut bundle = Self::load(path)?;
+        if closures.is_empty() {
+            bundle.route.remove("closures");
+        } else {
+            bundle.route.insert("closures".to_string(), toml::Value::try_from(closures)?);
+        }
+        bundle.config(&[])?;
+        bundle.save(path)
+    }
+}
+
+/// Read the `[[events]]` of a TOML file
+pub fn load_events(path: impl AsRef<Path>) -> Result<Vec<ScenarioEvent>> {
+    let path = path.as_ref();
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read events {}: {}", path.display(), e))?;
+    let file: EventsFile = toml::from_str(&content)
+        .map_err(|e| anyhow!("Failed to parse events {}: {}", path.display(), e))?;
+    Ok(file.events)
+}
+
+/// Hands out a scenario's events as the run reaches their times
+#[derive(Debug, Clone, Default)]
+pub struct EventSchedule {
+    events: Vec<ScenarioEvent>, // by time
+    next: usize,
+    last_time: f32,
+}
+
+impl EventSchedule {
+    /// Schedule `events` for a run starting at `start_time`; events before it are taken as
+    /// already carried out
+    pub fn new(mut events: Vec<ScenarioEvent>, start_time: f32) -> Self {
+        events.sort_by(|a, b| a.time.total_cmp(&b.time));
+        let next = events.partition_point(|event| event.time < start_time);
+        Self { events, next, last_time: start_time }
+    }
+    
+    /// Events due by `time` and not handed out yet, in time order. Time going backwards
+    /// means the run was reset or a checkpoint loaded, and the events after it are due again.
+    pub fn due(&mut self, time: f32) -> &[ScenarioEvent] {
+        if time < self.last_time {
+            self.next = self.events.partition_point(|event| event.time < time);
+        }
+        self.last_time = time;
+        let start = self.next;
+        self.next += self.events[start..].partition_point(|event| event.time <= time);
+        &self.events[start..self.next]
+    }
+    
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
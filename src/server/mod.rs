@@ -0,0 +1,261 @@
+use crate::simulation::{CarId, SignalPhase, SimulationEvent, SimulationState};
+use crate::config::SimulationConfig;
+use crate::export::{MetricsCollector, TickMetrics};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::io::ErrorKind;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TryRecvError, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tungstenite::{Message, WebSocket};
+
+const CLIENT_QUEUE: usize = 120; // ticks buffered per client before a slow client misses some
+const POLL_INTERVAL: Duration = Duration::from_millis(5); // how long a client thread waits for commands
+const MAX_SPEED: f32 = 10.0; // simulated seconds per real second
+
+/// Command a client sends as a JSON text message, e.g. `{"command": "set_speed", "multiplier": 2.0}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ServerCommand {
+    Pause,
+    Resume,
+    /// Simulated seconds per real second
+    SetSpeed { multiplier: f32 },
+    /// Spawn a car with the named driver behavior
+    SpawnCar { behavior: String },
+    /// Hold a signal group at a phase, or return it to its timing plan with `null`
+    SetSignalPhase { group: String, phase: Option<SignalPhase> },
+}
+
+/// What commands may refer to, so bad commands are answered with an error instead of
+/// reaching the simulation
+#[derive(Debug, Clone)]
+struct CommandTargets {
+    behaviors: Vec<String>,
+    signal_groups: Vec<String>,
+}
+
+impl CommandTargets {
+    fn of(config: &SimulationConfig) -> Self {
+        Self {
+            behaviors: config.cars.behavior.keys().cloned().collect(),
+            signal_groups: config.route.route.signals.groups.iter().map(|group| group.id.clone()).collect(),
+        }
+    }
+}
+
+impl ServerCommand {
+    /// Check the command as one from a client would be, against what `config` has
+    pub fn check_against(&self, config: &SimulationConfig) -> Result<(), String> {
+        self.check(&CommandTargets::of(config))
+    }
+    
+    fn check(&self, targets: &CommandTargets) -> Result<(), String> {
+        match self {
+            ServerCommand::SetSpeed { multiplier } if !(*multiplier > 0.0 && *multiplier <= MAX_SPEED) => {
+                Err(format!("speed multiplier must be above 0 and at most {}, got {}", MAX_SPEED, multiplier))
+            }
+            ServerCommand::SpawnCar { behavior } if !targets.behaviors.contains(behavior) => {
+                Err(format!("unknown behavior '{}'", behavior))
+            }
+            ServerCommand::SetSignalPhase { group, .. } if !targets.signal_groups.contains(group) => {
+                Err(format!("unknown signal group '{}'", group))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Position and motion of one car in a tick message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CarTelemetry {
+    pub id: CarId,
+    pub x: f32,
+    pub y: f32,
+    pub heading: f32, // radians
+    pub speed: f32,   // m/s
+    pub lane: u32,
+    pub car_type: String,
+    pub behavior: String,
+}
+
+/// Current phase of one signal group in a tick message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalTelemetry {
+    pub group: String,
+    pub phase: SignalPhase,
+}
+
+/// Message sent to clients as JSON text, tagged by `type`
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage<'a> {
+    /// Sent after every simulation tick
+    Tick {
+        cars: Vec<CarTelemetry>,
+        signals: Vec<SignalTelemetry>,
+        metrics: TickMetrics,
+        events: &'a [SimulationEvent],
+    },
+    /// Sent to a client whose command was malformed or refers to something that does not exist
+    Error { message: String },
+}
+
+/// WebSocket server that streams every simulation tick to connected clients and collects
+/// the commands they send. Clients are served on background threads, so the simulation
+/// loop only publishes ticks and polls commands.
+pub struct TelemetryServer {
+    address: SocketAddr,
+    clients: Arc<Mutex<Vec<SyncSender<Arc<str>>>>>,
+    commands: Receiver<ServerCommand>,
+    metrics: MetricsCollector,
+}
+
+impl TelemetryServer {
+    /// Listen on `address` (e.g. `127.0.0.1:9001`, port 0 picks a free port)
+    pub fn bind(address: &str, config: &SimulationConfig) -> Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let (command_sender, commands) = mpsc::channel();
+        let targets = CommandTargets::of(config);
+        
+        let accepted = Arc::clone(&clients);
+        std::thread::Builder::new()
+            .name("telemetry-server".to_string())
+            .spawn(move || accept_clients(listener, accepted, command_sender, Arc::new(targets)))?;
+            
+        Ok(Self {
+            address,
+            clients,
+            commands,
+            metrics: MetricsCollector::new(&config.route),
+        })
+    }
+    
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+    
+    /// Send the tick that produced `state` to every client. Call once for every tick.
+    pub fn publish(&mut self, state: &SimulationState) -> Result<()> {
+        let metrics = self.metrics.collect(state);
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        if clients.is_empty() {
+            return Ok(());
+        }
+        
+        let mut signals: Vec<SignalTelemetry> = Vec::new();
+        for signal in &state.signals {
+            if !signals.iter().any(|known| known.group == signal.group_id) {
+                signals.push(SignalTelemetry { group: signal.group_id.clone(), phase: signal.phase });
+            }
+        }
+        let message = ServerMessage::Tick {
+            cars: state.cars.iter().map(|car| CarTelemetry {
+                id: car.id,
+                x: car.position.x,
+                y: car.position.y,
+                heading: car.heading,
+                speed: car.velocity.magnitude(),
+                lane: car.current_lane,
+                car_type: state.names.car_type(car.car_type).to_string(),
+                behavior: state.names.behavior(car.behavior_type).to_string(),
+            }).collect(),
+            signals,
+            metrics,
+            events: &state.events,
+        };
+        let text: Arc<str> = serde_json::to_string(&message)?.into();
+        
+        // A client that falls behind misses ticks rather than holding up the simulation
+        clients.retain(|client| !matches!(client.try_send(Arc::clone(&text)), Err(TrySendError::Disconnected(_))));
+        Ok(())
+    }
+    
+    /// Commands received since the last call, in the order they arrived
+    pub fn poll_commands(&self) -> Vec<ServerCommand> {
+        self.commands.try_iter().collect()
+    }
+}
+
+fn accept_clients(
+    listener: TcpListener,
+    clients: Arc<Mutex<Vec<SyncSender<Arc<str>>>>>,
+    commands: Sender<ServerCommand>,
+    targets: Arc<CommandTargets>
+) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::warn!("Telemetry server failed to accept a connection: {}", e);
+                continue;
+            }
+        };
+        let peer = stream.peer_addr().map(|addr| addr.to_string()).unwrap_or_default();
+        let (sender, ticks) = mpsc::sync_channel(CLIENT_QUEUE);
+        clients.lock().unwrap_or_else(|e| e.into_inner()).push(sender);
+        let commands = commands.clone();
+        let targets = Arc::clone(&targets);
+        let spawned = std::thread::Builder::new()
+            .name(format!("telemetry-client-{}", peer))
+            .spawn(move || {
+                match serve_client(stream, ticks, commands, &targets) {
+                    Ok(()) => log::info!("Telemetry client {} disconnected", peer),
+                    Err(e) => log::info!("Telemetry client {} dropped: {}", peer, e),
+                }
+            });
+        if let Err(e) = spawned {
+            log::warn!("Telemetry server could not start a client thread: {}", e);
+        }
+    }
+}
+
+/// Forward ticks to one client and its commands to the simulation until either side hangs up
+fn serve_client(stream: TcpStream, ticks: Receiver<Arc<str>>, commands: Sender<ServerCommand>, targets: &CommandTargets) -> Result<()> {
+    let mut socket = tungstenite::accept(stream).map_err(|e| anyhow::anyhow!("handshake failed: {}", e))?;
+    log::info!("Telemetry client connected");
+    // Reads time out so ticks keep flowing while the client is quiet
+    socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+    
+    loop {
+        loop {
+            match ticks.try_recv() {
+                Ok(text) => socket.send(Message::Text(text.to_string()))?,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    let _ = socket.close(None);
+                    return Ok(()); // Server shut down
+                }
+            }
+        }
+        
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                let command = serde_json::from_str::<ServerCommand>(&text)
+                    .map_err(|e| format!("invalid command: {}", e))
+                    .and_then(|command| command.check(targets).map(|()| command));
+                match command {
+                    Ok(command) => {
+                        if commands.send(command).is_err() {
+                            return Ok(());
+                        }
+                    }
+                    Err(message) => send_error(&mut socket, message)?,
+                }
+            }
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e)) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+fn send_error(socket: &mut WebSocket<TcpStream>, message: String) -> Result<()> {
+    let text = serde_json::to_string(&ServerMessage::Error { message })?;
+    socket.send(Message::Text(text))?;
+    Ok(())
+}
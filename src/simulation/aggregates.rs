@@ -0,0 +1,120 @@
+use super::{BehaviorId, Car};
+
+/// Width of the speed bins cars are counted in, m/s
+pub const SPEED_BIN_WIDTH: f32 = 0.5;
+/// Cars faster than the last bin are counted in it
+const MAX_SPEED_BINS: usize = 256;
+
+/// Counts over the cars on the road that the UI shows every frame, kept up to date as cars
+/// are added and removed and as their speeds are recorded at the end of a step. Reading
+/// them costs the same with twenty cars as with twenty thousand.
+///
+/// Speeds are binned `SPEED_BIN_WIDTH` apart, so distributions and the top speed drawn
+/// from the counts are as fine as one bin.
+#[derive(Debug, Clone, Default)]
+pub struct CarAggregates {
+    behaviors: Vec<usize>, // cars by behavior id
+    speed_bins: Vec<usize>, // cars by speed bin
+    car_bins: Vec<u16>, // bin each car is counted in, in the order of `cars`
+}
+
+impl CarAggregates {
+    /// Aggregates of `cars`, counted from scratch
+    pub fn from_cars(cars: &[Car]) -> Self {
+        let mut aggregates = Self::default();
+        for car in cars {
+            aggregates.add(car);
+        }
+        aggregates
+    }
+    
+    /// Count a car added at the end of the car list
+    pub(crate) fn add(&mut self, car: &Car) {
+        let behavior = car.behavior_type.index();
+        if behavior >= self.behaviors.len() {
+            self.behaviors.resize(behavior + 1, 0);
+        }
+        self.behaviors[behavior] += 1;
+        
+        let bin = speed_bin(car);
+        if bin as usize >= self.speed_bins.len() {
+            self.speed_bins.resize(bin as usize + 1, 0);
+        }
+        self.speed_bins[bin as usize] += 1;
+        self.car_bins.push(bin);
+    }
+    
+    /// Stop counting `car`, swap-removed from position `pos` of the car list
+    pub(crate) fn remove(&mut self, pos: usize, car: &Car) {
+        if let Some(count) = self.behaviors.get_mut(car.behavior_type.index()) {
+            *count = count.saturating_sub(1);
+        }
+        if pos < self.car_bins.len() {
+            let bin = self.car_bins.swap_remove(pos);
+            self.speed_bins[bin as usize] -= 1;
+        }
+    }
+    
+    /// Move the cars whose speed left their bin since the last call. `cars` must be the
+    /// list the aggregates were kept for, through `add` and `remove` or counted again
+    /// after direct edits.
+    pub(crate) fn record_speeds(&mut self, cars: &[Car]) {
+        debug_assert_eq!(self.car_bins.len(), cars.len(), "cars edited without counting them again");
+        for (car, recorded) in cars.iter().zip(&mut self.car_bins) {
+            let bin = speed_bin(car);
+            if bin == *recorded {
+                continue;
+            }
+            if bin as usize >= self.speed_bins.len() {
+                self.speed_bins.resize(bin as usize + 1, 0);
+            }
+            self.speed_bins[*recorded as usize] -= 1;
+            self.speed_bins[bin as usize] += 1;
+            *recorded = bin;
+        }
+    }
+    
+    /// Cars on the road driving with `behavior`
+    pub fn behavior_count(&self, behavior: BehaviorId) -> usize {
+        self.behaviors.get(behavior.index()).copied().unwrap_or(0)
+    }
+    
+    /// Cars on the road of each behavior that has any, in behavior id order, which is the
+    /// order of the names
+    pub fn behavior_counts(&self) -> Vec<(BehaviorId, usize)> {
+        self.behaviors.iter().enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(index, &count)| (BehaviorId::from_index(index), count))
+            .collect()
+    }
+    
+    /// Top of the highest speed bin with a car in it, m/s; 0 when no car is past the first bin
+    pub fn max_speed(&self) -> f32 {
+        match self.speed_bins.iter().rposition(|&count| count > 0) {
+            Some(0) | None => 0.0,
+            Some(bin) => (bin + 1) as f32 * SPEED_BIN_WIDTH,
+        }
+    }
+    
+    /// Cars in `num_buckets` equal speed ranges from standstill to `max_speed`
+    pub fn velocity_distribution(&self, num_buckets: usize) -> Vec<usize> {
+        let mut distribution = vec![0; num_buckets];
+        let max_speed = self.max_speed();
+        if num_buckets == 0 || max_speed == 0.0 {
+            return distribution;
+        }
+        
+        let bucket_size = max_speed / num_buckets as f32;
+        for (bin, &count) in self.speed_bins.iter().enumerate() {
+            // A bin falls in the bucket its middle speed does
+            let speed = (bin as f32 + 0.5) * SPEED_BIN_WIDTH;
+            let bucket_index = ((speed / bucket_size) as usize).min(num_buckets - 1);
+            distribution[bucket_index] += count;
+        }
+        distribution
+    }
+}
+
+fn speed_bin(car: &Car) -> u16 {
+    ((car.velocity.magnitude() / SPEED_BIN_WIDTH) as usize).min(MAX_SPEED_BINS - 1) as u16
+}
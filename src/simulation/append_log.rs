@@ -0,0 +1,64 @@
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static NEXT_ORIGIN: AtomicU64 = AtomicU64::new(0);
+
+/// Entries of a log that only ever grows, like the trips completed since the simulation
+/// started. Copying a log with `clone_from` into an earlier copy of the same log, or a
+/// copy of a copy, only copies the entries added since, so snapshots of the simulation
+/// state stay cheap however long the run has been going.
+#[derive(Debug)]
+pub struct AppendLog<T> {
+    entries: Vec<T>,
+    origin: u64, // tells this log from every other, copies included
+    copy_of: Option<u64>, // origin of the log whose first entries these are, while unchanged
+}
+
+impl<T> AppendLog<T> {
+    pub fn push(&mut self, entry: T) {
+        self.entries.push(entry);
+        self.copy_of = None;
+    }
+    
+    /// Origin of the log these entries were first written to
+    fn lineage(&self) -> u64 {
+        self.copy_of.unwrap_or(self.origin)
+    }
+}
+
+impl<T> Default for AppendLog<T> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            origin: NEXT_ORIGIN.fetch_add(1, Ordering::Relaxed),
+            copy_of: None,
+        }
+    }
+}
+
+impl<T: Clone> Clone for AppendLog<T> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+            origin: NEXT_ORIGIN.fetch_add(1, Ordering::Relaxed),
+            copy_of: Some(self.lineage()),
+        }
+    }
+    
+    fn clone_from(&mut self, source: &Self) {
+        if self.copy_of == Some(source.lineage()) && self.entries.len() <= source.entries.len() {
+            self.entries.extend_from_slice(&source.entries[self.entries.len()..]);
+        } else {
+            self.entries.clone_from(&source.entries);
+        }
+        self.copy_of = Some(source.lineage());
+    }
+}
+
+impl<T> Deref for AppendLog<T> {
+    type Target = [T];
+    
+    fn deref(&self) -> &[T] {
+        &self.entries
+    }
+}
@@ -0,0 +1,158 @@
+use super::{Car, SimulationEvent, SimulationState, GRAVITY};
+use crate::config::{CarType, ElectricConfig, ExitPoint, RouteConfig};
+use rand::Rng;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+/// kg/m^3, air at sea level and 15 degrees C
+const AIR_DENSITY: f32 = 1.225;
+
+/// Charge of an electric vehicle's battery and the energy it has moved since the car spawned
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Battery {
+    pub capacity: f32,    // kWh
+    pub charge: f32,      // kWh left
+    pub consumed: f32,    // kWh drawn for driving and auxiliaries
+    pub regenerated: f32, // kWh recovered while braking
+    pub low: bool,        // fell below the low charge threshold, the driver heads for a charger
+}
+
+impl Battery {
+    /// Share of the capacity left, 0 to 1
+    pub fn state_of_charge(&self) -> f32 {
+        self.charge / self.capacity
+    }
+    
+    pub fn is_flat(&self) -> bool {
+        self.charge <= 0.0
+    }
+}
+
+/// Power in kW an electric vehicle of `mass` kg draws from its battery driving at `speed`
+/// m/s, accelerating at `acceleration` m/s^2 up a `grade` in percent. The road load of
+/// rolling resistance, air drag and climbing plus the force accelerating the car is
+/// supplied through the drivetrain losses; when it turns negative, braking, part of it is
+/// recovered. Auxiliaries draw power regardless, so a stopped car still drains its battery.
+pub fn battery_power(mass: f32, speed: f32, acceleration: f32, grade: f32, config: &ElectricConfig) -> f32 {
+    let slope = (grade / 100.0).atan();
+    let rolling = if speed > 0.1 { config.rolling_resistance * mass * GRAVITY * slope.cos() } else { 0.0 };
+    let force = mass * acceleration
+        + rolling
+        + mass * GRAVITY * slope.sin()
+        + 0.5 * AIR_DENSITY * config.drag_area * speed * speed;
+    let wheel_power = force * speed / 1000.0;
+    let battery = if wheel_power >= 0.0 {
+        wheel_power / config.drivetrain_efficiency
+    } else {
+        wheel_power * config.regen_efficiency
+    };
+    battery + config.auxiliary_power
+}
+
+/// Drains the batteries of electric vehicles by the power they drove with in the last
+/// physics step and sends drivers low on charge to the nearest charging exit ahead. Cars
+/// whose battery runs flat break down where they are; the behavior engine strands them.
+pub struct BatteryController {
+    config: ElectricConfig,
+    route: RouteConfig,
+    chargers: Vec<ExitPoint>,
+}
+
+impl BatteryController {
+    pub fn new(config: &ElectricConfig, route: &RouteConfig) -> Self {
+        Self {
+            config: config.clone(),
+            route: route.clone(),
+            chargers: route.route.exits.iter().filter(|exit| exit.charging).cloned().collect(),
+        }
+    }
+    
+    /// Battery of a new car of `car_type`, charged to a random share within the configured
+    /// range. `None` for car types that are not electric, which draw nothing from `rng`.
+    pub fn battery_for(&self, car_type: &CarType, rng: &mut StdRng) -> Option<Battery> {
+        let capacity = car_type.battery_capacity?;
+        let share = rng.gen_range(self.config.initial_charge_min..=self.config.initial_charge_max);
+        Some(Battery {
+            capacity,
+            charge: capacity * share,
+            consumed: 0.0,
+            regenerated: 0.0,
+            low: false,
+        })
+    }
+    
+    pub fn update(&mut self, state: &mut SimulationState) {
+        let time = state.time;
+        let dt = state.dt;
+        let mut events = Vec::new();
+        for index in 0..state.cars.len() {
+            let car = &state.cars[index];
+            let (Some(battery), Some(mass)) = (&car.battery, car.mass) else {
+                continue;
+            };
+            if battery.is_flat() || car.crashed {
+                continue;
+            }
+            let speed = car.velocity.magnitude();
+            // Acceleration along the direction of travel, braking counts as negative, within
+            // what the car can do
+            let acceleration = if speed > 0.1 { car.acceleration.dot(&car.velocity) / speed } else { 0.0 };
+            let acceleration = acceleration.clamp(-car.max_deceleration, car.max_acceleration);
+            let energy = battery_power(mass, speed, acceleration, self.grade_at(car), &self.config) * dt / 3600.0;
+            let charger = self.nearest_charger(car).map(|exit| exit.id.clone());
+            
+            let car = &mut state.cars[index];
+            let Some(battery) = car.battery.as_mut() else {
+                continue;
+            };
+            if energy >= 0.0 {
+                battery.consumed += energy;
+            } else {
+                battery.regenerated -= energy;
+            }
+            battery.charge = (battery.charge - energy).clamp(0.0, battery.capacity);
+            
+            if !battery.low && battery.state_of_charge() < self.config.low_charge {
+                battery.low = true;
+                log::debug!("Car {} is low on charge, heading for {:?}", car.id, charger);
+                events.push(SimulationEvent::BatteryLow { car: car.id, charging_exit: charger.clone(), time });
+            }
+            if battery.is_flat() {
+                log::debug!("Car {} ran out of charge", car.id);
+                events.push(SimulationEvent::BatteryDepleted { car: car.id, time });
+            }
+            
+            // Low cars, also ones that came over from another route, leave at a charger
+            let heading_for_charger = car.destination.as_ref().is_some_and(|destination| self.chargers.iter().any(|exit| &exit.id == destination));
+            if battery.low && !heading_for_charger && car.exit_ramp.is_none() {
+                if let Some(charger) = charger {
+                    car.destination = Some(charger);
+                }
+            }
+        }
+        state.events.extend(events);
+    }
+    
+    /// Charging exit a car on a ring route reaches first, driving counter-clockwise
+    fn nearest_charger(&self, car: &Car) -> Option<&ExitPoint> {
+        let geometry = &self.route.route.geometry;
+        let to_car = car.position - nalgebra::Point2::new(geometry.center_x, geometry.center_y);
+        let car_angle = to_car.y.atan2(to_car.x);
+        self.chargers.iter().min_by(|a, b| {
+            let ahead = |exit: &ExitPoint| (exit.angle.to_radians() - car_angle).rem_euclid(TAU);
+            ahead(a).total_cmp(&ahead(b))
+        })
+    }
+    
+    /// Grade in percent under the car, from the ring's grade profile on donut routes
+    fn grade_at(&self, car: &Car) -> f32 {
+        let geometry = &self.route.route.geometry;
+        let surface = &self.route.route.surface;
+        if geometry.geometry_type != "donut" || surface.grade_profile.is_empty() {
+            return surface.grade;
+        }
+        let to_car = car.position - nalgebra::Point2::new(geometry.center_x, geometry.center_y);
+        surface.grade_at(to_car.y.atan2(to_car.x))
+    }
+}
@@ -1,91 +1,454 @@
-use super::{Car, SimulationState, BehaviorState};
-use crate::config::{DriverBehavior, CarsConfig, RouteConfig};
-use rand::{Rng, SeedableRng};
-use rand_distr::{Normal, Distribution};
+use super::{Car, CarId, BehaviorId, CarTypeId, NameTable, SimulationState, SimulationEvent, SpatialIndex, BehaviorState, SignalPhase, Breakdown, Weather, TurnSignal, RandomStream, PhiloxKey, GRAVITY, RESERVATION_DISTANCE, closure_ahead, splitmix64, philox4x32, unit_float, standard_normal};
+use crate::config::{DriverBehavior, CarsConfig, RouteConfig, LaneChangeConfig, BreakdownConfig, ReactionConfig};
+use rand::Rng;
 use rand::rngs::StdRng;
+use std::collections::{HashMap, HashSet};
+
+/// Distance before its destination exit at which a car starts moving to the exit lane, at
+/// least; cars further from the exit lane start earlier
+const EXIT_APPROACH_DISTANCE: f32 = 300.0;
+/// Distance before the exit it is about to take at which a car starts signalling
+const EXIT_SIGNAL_DISTANCE: f32 = 100.0;
+/// Gap to a broken-down car ahead within which drivers try to change lanes around it
+const BREAKDOWN_AVOIDANCE_DISTANCE: f32 = 60.0;
+/// How far before a closed stretch of lane cars start merging out of it, and how far ahead
+/// a lane counts as closed to cars thinking of moving into it
+const CLOSURE_MERGE_DISTANCE: f32 = 150.0;
+/// Bumper-to-bumper gap a car of up to `REFERENCE_CAR_LENGTH` needs to change into a lane;
+/// longer vehicles need proportionally more
+const LANE_CHANGE_GAP: f32 = 10.0;
+const REFERENCE_CAR_LENGTH: f32 = 5.0;
+/// Gap to a slower car ahead within which tailgating drivers close up on it
+const TAILGATE_DISTANCE: f32 = 40.0;
+/// How much slower than a tailgating driver wants to go the car ahead has to be
+const TAILGATE_SPEED_DEFICIT: f32 = 1.0;
+/// Gap to a car waiting to merge in ahead within which drivers open or refuse a gap for it
+const MERGE_COURTESY_DISTANCE: f32 = 30.0;
+/// Share of their speed drivers opening a gap for a merging car slow down to
+const GAP_OPENING_SPEED_FACTOR: f32 = 0.7;
+/// How far behind a driver's rear bumper their blind spot reaches, in the next lane
+const BLIND_SPOT_LENGTH: f32 = 8.0;
 
 #[derive(Debug, Clone)]
 struct BehaviorUpdate {
     target_speed: f32,
     target_lane: Option<u32>,
     lane_change_requested: bool,
+    turn_signal: Option<TurnSignal>,
+    merge_intent: Option<u32>,
+    tailgating: bool,
+    overlooked: Option<CarId>, // Car in the blind spot of a lane change just started
+}
+
+/// What a car does about its lane this tick
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LaneDecision {
+    Stay,
+    Change(u32),
+    /// Has to leave its lane but waits for a gap in this one, signalling towards it
+    Wait(u32),
+}
+
+/// A neighboring car and the bumper-to-bumper gap to it
+type Neighbor<'a> = Option<(&'a Car, f32)>;
+
+/// Closest cars ahead of and behind a car in one lane
+struct LaneNeighbors<'a> {
+    leader: Neighbor<'a>,
+    follower: Neighbor<'a>,
 }
 
 pub struct BehaviorEngine {
-    behaviors: Vec<(String, DriverBehavior)>,
+    behaviors: Vec<(BehaviorId, DriverBehavior)>,
+    default_behavior: Option<BehaviorId>, // "normal", for cars whose behavior is not configured
     route: RouteConfig,
-    rng: StdRng,
+    lane_change: LaneChangeConfig,
+    breakdowns: BreakdownConfig,
+    reaction: ReactionConfig,
+    breakdown_probabilities: HashMap<CarTypeId, f32>, // Chance per minute of driving
+    heavy_types: HashSet<CarTypeId>, // Car types subject to the heavy vehicle lane bans
+    max_car_length: f32,
+    min_gap: f32, // meters, standstill gap used by the MOBIL acceleration model
+    #[cfg(feature = "scripting")]
+    scripts: super::ScriptHooks,
+    behavior_key: PhiloxKey, // Lane change draws, see `draws`
+    breakdown_rng: StdRng,
+    noise_key: PhiloxKey, // Speed preference jitter, see `draws`
+    distraction_rng: StdRng,
+    mirror_seed: u64, // Which cars each driver overlooks, see `overlooks`
+    device_behaviors: HashSet<BehaviorId>, // Behaviors plain enough for the GPU kernel, see `runs_on_device`
+    on_device: Option<HashSet<CarId>>, // Cars left to the GPU kernel in the latest update, when it decides
 }
 
 impl BehaviorEngine {
-    pub fn new(cars_config: &CarsConfig, route: RouteConfig, seed: Option<u64>) -> Self {
-        let behaviors: Vec<(String, DriverBehavior)> = cars_config.behavior
-            .iter()
-            .map(|(name, behavior)| (name.clone(), behavior.clone()))
+    /// Engine for the configuration's drivers, knowing their behaviors and car types by
+    /// their ids in `names`, the table of the configuration
+    pub fn new(cars_config: &CarsConfig, names: &NameTable, route: RouteConfig, seed: Option<u64>) -> Self {
+        let behavior_id = |name: &str| names.behaviors.id(name).expect("the name table has the configuration's behaviors");
+        let car_type_id = |name: &str| names.car_types.id(name).expect("the name table has the configuration's car types");
+        let mut behaviors: Vec<(&String, &DriverBehavior)> = cars_config.behavior.iter().collect();
+        // HashMap order differs between runs; weighted picks need a stable order to be reproducible
+        behaviors.sort_by(|a, b| a.0.cmp(b.0));
+        let behaviors: Vec<(BehaviorId, DriverBehavior)> = behaviors.into_iter()
+            .map(|(name, behavior)| (behavior_id(name), behavior.clone()))
             .collect();
         
-        let rng = if let Some(seed) = seed {
-            StdRng::seed_from_u64(seed)
-        } else {
-            StdRng::from_entropy()
-        };
-        
         Self {
             behaviors,
+            default_behavior: names.behaviors.id("normal"),
+            #[cfg(feature = "scripting")]
+            scripts: super::ScriptHooks::load(cars_config, route.route.geometry.lane_count),
             route,
-            rng,
+            lane_change: cars_config.lane_change.clone(),
+            breakdowns: cars_config.breakdowns.clone(),
+            reaction: cars_config.reaction.clone(),
+            breakdown_probabilities: cars_config.car_types.iter()
+                .map(|car_type| (car_type_id(&car_type.id), car_type.breakdown_probability))
+                .collect(),
+            heavy_types: cars_config.car_types.iter()
+                .filter(|car_type| car_type.heavy)
+                .map(|car_type| car_type_id(&car_type.id))
+                .collect(),
+            max_car_length: cars_config.car_types.iter().map(|car_type| car_type.length).fold(0.0, f32::max),
+            min_gap: cars_config.collision_avoidance.safety_margin + 2.0,
+            behavior_key: RandomStream::Behavior.key(seed),
+            breakdown_rng: RandomStream::Breakdown.rng(seed),
+            noise_key: RandomStream::Noise.key(seed),
+            distraction_rng: RandomStream::Distraction.rng(seed),
+            mirror_seed: RandomStream::Mirror.rng(seed).gen(),
+            device_behaviors: cars_config.behavior.iter()
+                .filter(|(_, behavior)| {
+                    behavior.script.is_none() && !behavior.tailgates && behavior.merge_courtesy == "none" &&
+                        behavior.comfort_lateral_acceleration.is_none() && behavior.blind_spot_miss_probability <= 0.0
+                })
+                .map(|(name, _)| behavior_id(name))
+                .collect(),
+            on_device: None,
         }
     }
     
+    /// Restart the random streams, used when resuming from a checkpoint
+    pub fn reseed(&mut self, seed: Option<u64>) {
+        self.behavior_key = RandomStream::Behavior.key(seed);
+        self.breakdown_rng = RandomStream::Breakdown.rng(seed);
+        self.noise_key = RandomStream::Noise.key(seed);
+        self.distraction_rng = RandomStream::Distraction.rng(seed);
+        self.mirror_seed = RandomStream::Mirror.rng(seed).gen();
+    }
+    
+    /// Start over as if just created with `seed`. The random streams are the only state
+    /// kept between steps, the rest is configuration.
+    pub fn reset(&mut self, seed: Option<u64>) {
+        self.reseed(seed);
+    }
+    
+    /// Leave the decisions of cars on the plain behavior path to the GPU kernel, which
+    /// draws the same random numbers, see `device_cars`
+    pub fn set_device_behavior(&mut self, enabled: bool) {
+        self.on_device = enabled.then(HashSet::new);
+    }
+    
+    /// Cars whose decisions the latest update left to the GPU kernel, `None` unless it decides
+    pub fn device_cars(&self) -> Option<&HashSet<CarId>> {
+        self.on_device.as_ref()
+    }
+    
+    /// Keys of the speed jitter and lane change draws, see `draws`
+    pub fn draw_keys(&self) -> (PhiloxKey, PhiloxKey) {
+        (self.noise_key, self.behavior_key)
+    }
+    
     pub fn update(&mut self, state: &mut SimulationState) {
+        self.update_breakdowns(state);
+        self.update_distractions(state);
+        
         let mut updates = Vec::new();
+        let index = SpatialIndex::build(&state.cars, 25.0); // About the lane change safety distance
         
         // Collect behavior updates
+        let mut on_device = self.on_device.take();
+        if let Some(cars) = &mut on_device {
+            cars.clear();
+        }
         for (i, car) in state.cars.iter().enumerate() {
-            let update = self.calculate_car_behavior_update(car, state);
+            if let Some(cars) = &mut on_device {
+                if self.runs_on_device(car, state) {
+                    cars.insert(car.id);
+                    continue;
+                }
+            }
+            let update = self.calculate_car_behavior_update(car, state, &index);
             updates.push((i, update));
         }
+        self.on_device = on_device;
         
         // Apply updates
         for (i, update) in updates {
             if let Some(car) = state.cars.get_mut(i) {
                 car.behavior.target_speed = update.target_speed;
+                if let Some(to_lane) = update.target_lane.filter(|&lane| car.target_lane != Some(lane)) {
+                    state.events.push(SimulationEvent::LaneChangeStarted {
+                        car: car.id,
+                        from_lane: car.current_lane,
+                        to_lane,
+                        time: state.time,
+                    });
+                    if let Some(overlooked) = update.overlooked {
+                        state.events.push(SimulationEvent::BlindSpotMiss {
+                            car: car.id,
+                            overlooked,
+                            time: state.time,
+                        });
+                    }
+                }
                 car.target_lane = update.target_lane;
+                car.turn_signal = update.turn_signal;
+                car.behavior.merge_intent = update.merge_intent;
+                car.behavior.tailgating = update.tailgating;
                 if update.lane_change_requested {
                     car.behavior.last_lane_change_time = state.time;
-                    car.lane_change_progress = 0.0;
                 }
             }
         }
     }
     
-    fn calculate_car_behavior_update(&mut self, car: &Car, state: &SimulationState) -> BehaviorUpdate {
+    /// Break cars down at random or when their battery runs flat, pull stranded cars over
+    /// onto the shoulder and let repaired cars drive on. Cars on the shoulder or out of
+    /// charge are left for the traffic manager to tow away.
+    fn update_breakdowns(&mut self, state: &mut SimulationState) {
+        let route_rules = &self.route.route.traffic_rules;
+        let lane_width = self.route.route.geometry.lane_width;
+        let pull_over_speed = lane_width / route_rules.lane_change_time;
+        
+        for car in &mut state.cars {
+            let out_of_charge = car.is_out_of_charge();
+            let Some(breakdown) = &mut car.breakdown else {
+                // A flat battery strands the car for good, on the shoulder where there is one
+                if out_of_charge && !car.crashed && car.exit_ramp.is_none() {
+                    car.breakdown = Some(Breakdown {
+                        start_time: state.time,
+                        end_time: state.time + self.breakdowns.max_duration,
+                        pull_over_time: self.has_shoulder(car.current_lane).then_some(state.time + self.breakdowns.shoulder_delay),
+                        shoulder_offset: 0.0,
+                    });
+                    continue;
+                }
+                
+                // Cars only break down while driving straight on in a lane
+                let probability = self.breakdown_probabilities.get(&car.car_type).copied().unwrap_or(0.0);
+                if probability <= 0.0 || car.crashed || car.target_lane.is_some() || car.exit_ramp.is_some() {
+                    continue;
+                }
+                if self.breakdown_rng.gen::<f32>() < probability / 60.0 * state.dt {
+                    let duration = self.breakdown_rng.gen_range(self.breakdowns.min_duration..=self.breakdowns.max_duration);
+                    let pull_over = self.has_shoulder(car.current_lane) && self.breakdown_rng.gen::<f32>() < self.breakdowns.shoulder_probability;
+                    car.breakdown = Some(Breakdown {
+                        start_time: state.time,
+                        end_time: state.time + duration,
+                        pull_over_time: pull_over.then_some(state.time + self.breakdowns.shoulder_delay),
+                        shoulder_offset: 0.0,
+                    });
+                    log::debug!("Car {} broke down for {:.0}s", car.id, duration);
+                }
+                continue;
+            };
+            
+            if breakdown.shoulder_offset == 0.0 && state.time >= breakdown.end_time && !out_of_charge {
+                car.breakdown = None;
+                continue;
+            }
+            
+            // Once stopped, slide sideways onto the shoulder to the right of the lane
+            let pulling_over = breakdown.pull_over_time.is_some_and(|time| state.time >= time);
+            if pulling_over && breakdown.shoulder_offset < lane_width && car.velocity.magnitude() < 0.1 {
+                let step = (pull_over_speed * state.dt).min(lane_width - breakdown.shoulder_offset);
+                let right = nalgebra::Vector2::new(car.heading.sin(), -car.heading.cos());
+                car.position += right * step;
+                breakdown.shoulder_offset += step;
+            }
+        }
+    }
+    
+    /// Distract drivers at random for a while, during which they react to the car ahead
+    /// more slowly. Only matters with reaction delay on.
+    fn update_distractions(&mut self, state: &mut SimulationState) {
+        let reaction = &self.reaction;
+        if !reaction.delay || reaction.distraction_rate <= 0.0 {
+            return;
+        }
+        
+        for car in &mut state.cars {
+            if car.crashed || car.perception.is_distracted(state.time) {
+                continue;
+            }
+            if self.distraction_rng.gen::<f32>() < reaction.distraction_rate / 60.0 * state.dt {
+                let duration = self.distraction_rng.gen_range(reaction.distraction_min_duration..=reaction.distraction_max_duration);
+                car.perception.distracted_until = Some(state.time + duration);
+                log::debug!("Car {} distracted for {:.1}s", car.id, duration);
+            }
+        }
+    }
+    
+    /// Whether a car stopped in `lane` can be pulled onto a shoulder, i.e. no lane lies to its right
+    fn has_shoulder(&self, lane: u32) -> bool {
+        if self.route.route.geometry.geometry_type == "grid" {
+            return false;
+        }
+        self.adjacent_lanes(lane).into_iter().all(|other| !self.is_right_of(other, lane))
+    }
+    
+    /// Whether the GPU kernel can make `car`'s decisions this step: the speed jitter, random
+    /// lane changes, moving over for the exit and steering around stopped cars. Anything it
+    /// does not model keeps the car on the CPU: breakdowns, exit ramps, crashes, buses, lane
+    /// bans, closures, red lights ahead, MOBIL, scripts and behaviors reacting to other drivers.
+    fn runs_on_device(&self, car: &Car, state: &SimulationState) -> bool {
+        let route = &self.route.route;
+        let exit_known = route.geometry.geometry_type != "donut" || route.exits.is_empty() ||
+            car.destination.as_ref().is_some_and(|destination| route.exits.iter().any(|exit| &exit.id == destination));
+        let banned = self.heavy_types.contains(&car.car_type) && !route.traffic_rules.heavy_vehicle_banned_lanes.is_empty();
+        self.lane_change.model != "mobil" && route.closures.is_empty() && exit_known && !banned &&
+            car.breakdown.is_none() && car.exit_ramp.is_none() && !car.crashed && car.bus.is_none() &&
+            self.device_behaviors.contains(&car.behavior_type) &&
+            !self.approaching_signal(car, state)
+    }
+    
+    /// Random words for `car`'s draws from the stream of `key` in the step at `time`. They
+    /// depend on nothing else, so the GPU kernel draws the same for the car.
+    fn draws(key: PhiloxKey, car: &Car, time: f32) -> [u32; 4] {
+        philox4x32([car.id.index, time.to_bits(), 0, 0], key)
+    }
+    
+    fn calculate_car_behavior_update(&mut self, car: &Car, state: &SimulationState, index: &SpatialIndex) -> BehaviorUpdate {
+        // Broken-down cars keep their plans and never change lanes, exiting cars keep
+        // signalling until they are off the ramp
+        if car.breakdown.is_some() || car.exit_ramp.is_some() {
+            return BehaviorUpdate {
+                target_speed: car.behavior.target_speed,
+                target_lane: car.target_lane,
+                lane_change_requested: false,
+                turn_signal: car.exit_ramp.as_ref().and(car.turn_signal),
+                merge_intent: None,
+                tailgating: false,
+                overlooked: None,
+            };
+        }
+        
         let mut update = BehaviorUpdate {
-            target_speed: self.calculate_target_speed(car),
+            target_speed: self.calculate_target_speed(car, state.time, state.weather).min(self.comfortable_curve_speed(car, state.weather)),
             target_lane: car.target_lane,
             lane_change_requested: false,
+            turn_signal: None,
+            merge_intent: None,
+            tailgating: false,
+            overlooked: None,
         };
         
         // Check for lane change decisions
-        if let Some(new_target_lane) = self.check_lane_change_decision(car, state) {
-            update.target_lane = Some(new_target_lane);
-            update.lane_change_requested = true;
+        match self.check_lane_change_decision(car, state, index) {
+            LaneDecision::Change(new_target_lane) => {
+                update.target_lane = Some(new_target_lane);
+                update.lane_change_requested = true;
+                update.overlooked = self.overlooked_car(car, new_target_lane, state, index);
+            }
+            LaneDecision::Wait(lane) => update.merge_intent = Some(lane),
+            LaneDecision::Stay => {}
         }
         
-        // Check for exit decisions
-        self.check_exit_decision_for_car(car, state);
+        // React to the drivers around: tailgating slower cars, and opening or refusing
+        // gaps for cars waiting to merge in ahead
+        self.apply_interactions(car, state, index, &mut update);
+        
+        #[cfg(feature = "scripting")]
+        self.apply_script(car, state, index, &mut update);
         
+        update.turn_signal = self.turn_signal(car, update.target_lane.or(update.merge_intent));
         update
     }
     
-    fn calculate_target_speed(&mut self, car: &Car) -> f32 {
+    /// Interactions set by the driver's behavior. Drivers who tailgate close up on a slower
+    /// car ahead, keeping a shorter gap and flashing it. Cars waiting to merge in just ahead
+    /// (their intent is published in `BehaviorState::merge_intent`) get a gap opened for them
+    /// by courteous drivers, while drivers who refuse close up on the car ahead instead.
+    fn apply_interactions(&self, car: &Car, state: &SimulationState, index: &SpatialIndex, update: &mut BehaviorUpdate) {
+        let Some(behavior) = self.behavior_of(car) else {
+            return;
+        };
+        if (!behavior.tailgates && behavior.merge_courtesy == "none") || self.route.route.geometry.geometry_type == "grid" {
+            return;
+        }
+        
+        let mut lanes = vec![car.current_lane];
+        lanes.extend(self.adjacent_lanes(car.current_lane));
+        let mut neighbors = self.lane_neighbors(car, &lanes, state, index).into_iter();
+        let leader = neighbors.next().and_then(|current| current.leader);
+        let merging_ahead = neighbors.any(|adjacent| adjacent.leader.is_some_and(|(other, gap)| {
+            other.behavior.merge_intent == Some(car.current_lane) && gap < MERGE_COURTESY_DISTANCE
+        }));
+        
+        let held_up = leader.is_some_and(|(leader, gap)| {
+            gap < TAILGATE_DISTANCE && leader.velocity.magnitude() < update.target_speed - TAILGATE_SPEED_DEFICIT
+        });
+        update.tailgating = (behavior.tailgates && held_up) ||
+            (merging_ahead && behavior.merge_courtesy == "refuse" && leader.is_some());
+        if merging_ahead && behavior.merge_courtesy == "open" {
+            update.target_speed *= GAP_OPENING_SPEED_FACTOR;
+        }
+    }
+    
+    /// Let the car's behavior script override the built-in target speed and lane change.
+    /// Cars only change into an adjacent lane with a safe gap, whatever the script asks for.
+    #[cfg(feature = "scripting")]
+    fn apply_script(&mut self, car: &Car, state: &SimulationState, index: &SpatialIndex, update: &mut BehaviorUpdate) {
+        if !self.scripts.has_script(state.names.behavior(car.behavior_type)) {
+            return;
+        }
+        update.target_speed = self.scripts.target_speed(car, state, update.target_speed);
+        
+        if car.target_lane.is_some() || car.crashed {
+            return;
+        }
+        let proposed = update.target_lane.filter(|_| update.lane_change_requested);
+        let lane = self.scripts.lane_change(car, state, proposed);
+        if lane == proposed {
+            return;
+        }
+        let lane = lane.filter(|&lane| {
+            self.adjacent_lanes(car.current_lane).contains(&lane) && self.is_lane_change_safe(car, lane, state, index)
+        });
+        update.target_lane = lane;
+        update.lane_change_requested = lane.is_some();
+    }
+    
+    /// Indicator for a lane change in progress, or for the exit the car is about to take
+    fn turn_signal(&self, car: &Car, target_lane: Option<u32>) -> Option<TurnSignal> {
+        if let Some(target_lane) = target_lane {
+            return Some(if self.is_right_of(target_lane, car.current_lane) { TurnSignal::Right } else { TurnSignal::Left });
+        }
+        
+        let route_geom = &self.route.route.geometry;
+        if route_geom.geometry_type != "donut" {
+            return None;
+        }
+        let center = nalgebra::Point2::new(route_geom.center_x, route_geom.center_y);
+        let to_car = car.position - center;
+        let car_angle = to_car.y.atan2(to_car.x);
+        let exit = self.route.route.exits.iter().find(|exit| {
+            let taken = car.current_lane == exit.lane
+                && car.destination.as_ref().is_none_or(|destination| destination == &exit.id);
+            let angle_ahead = (exit.angle.to_radians() - car_angle).rem_euclid(2.0 * std::f32::consts::PI);
+            taken && angle_ahead * to_car.magnitude() < EXIT_SIGNAL_DISTANCE
+        })?;
+        Some(if exit.is_exterior() { TurnSignal::Right } else { TurnSignal::Left })
+    }
+    
+    fn calculate_target_speed(&self, car: &Car, time: f32, weather: Weather) -> f32 {
         let base_speed = car.preferred_speed;
         let variance = car.behavior.speed_variance;
         
         // Add some randomness to speed preference
         let speed_noise = if variance != 1.0 {
-            let normal = Normal::new(1.0, (variance - 1.0).abs() * 0.1).unwrap();
-            normal.sample(&mut self.rng)
+            let [first, second, ..] = Self::draws(self.noise_key, car, time);
+            1.0 + (variance - 1.0).abs() * 0.1 * standard_normal(first, second)
         } else {
             1.0
         };
@@ -94,44 +457,127 @@ impl BehaviorEngine {
         let speed_limit = self.route.route.traffic_rules.speed_limit;
         let min_speed = self.route.route.traffic_rules.min_speed;
         
-        (base_speed * variance * speed_noise)
+        let speed = (base_speed * variance * speed_noise)
             .max(min_speed)
-            .min(speed_limit)
+            .min(speed_limit);
+            
+        // Bad weather slows everyone down, below the minimum speed if need be, and in fog
+        // drivers keep to a speed they can stop from within sight
+        let braking = weather.braking_limit(car, &self.route.route.surface);
+        let weather_speed = speed * weather.speed_factor();
+        weather.sight_speed(braking).map_or(weather_speed, |sight_speed| weather_speed.min(sight_speed))
     }
     
-    fn check_lane_change_decision(&mut self, car: &Car, state: &SimulationState) -> Option<u32> {
-        // Don't change lanes if already changing
-        if car.target_lane.is_some() {
-            return None;
+    /// Speed at which the driver takes the curve they are in without feeling more sideways
+    /// acceleration than their behavior's comfort threshold, the banking taking up part of
+    /// it. Grid drivers slow in time for the bends of their path through junctions, braking
+    /// comfortably.
+    fn comfortable_curve_speed(&self, car: &Car, weather: Weather) -> f32 {
+        let Some(comfort) = self.behavior_of(car).and_then(|behavior| behavior.comfort_lateral_acceleration) else {
+            return f32::INFINITY;
+        };
+        let route_geom = &self.route.route.geometry;
+        let banking = GRAVITY * self.route.route.surface.banking_angle.to_radians().tan();
+        let speed_for = |radius: f32| (radius * (comfort + banking)).sqrt();
+        
+        match route_geom.geometry_type.as_str() {
+            "donut" => {
+                let lane_radius = route_geom.inner_radius + route_geom.lane_width * (car.current_lane as f32 - 0.5);
+                speed_for(lane_radius)
+            }
+            // Lanes past the twelve highway lanes are the loop ramps
+            "cloverleaf" if car.current_lane > 12 => speed_for(route_geom.loop_radius.unwrap_or(60.0)),
+            "grid" => {
+                let Some(path) = &car.grid_path else {
+                    return f32::INFINITY;
+                };
+                let comfortable_deceleration = weather.braking_limit(car, &self.route.route.surface) * 0.5;
+                path.bends_ahead(&car.position, RESERVATION_DISTANCE).into_iter()
+                    .map(|(distance, radius)| (speed_for(radius).powi(2) + 2.0 * comfortable_deceleration * distance).sqrt())
+                    .fold(f32::INFINITY, f32::min)
+            }
+            _ => f32::INFINITY,
+        }
+    }
+    
+    fn check_lane_change_decision(&mut self, car: &Car, state: &SimulationState, index: &SpatialIndex) -> LaneDecision {
+        // Don't change lanes if already changing, crashed or dwelling at a bus stop
+        if car.target_lane.is_some() || car.crashed || car.is_dwelling() {
+            return LaneDecision::Stay;
+        }
+        
+        // Stay in lane while approaching a light that is not green
+        if self.approaching_signal(car, state) {
+            return LaneDecision::Stay;
+        }
+        
+        let route_geom = &self.route.route.geometry;
+        let total_lanes = route_geom.lane_count;
+        
+        // Grid paths are single-lane
+        if route_geom.geometry_type == "grid" {
+            return LaneDecision::Stay;
+        }
+        
+        // Steering around a broken-down car comes before anything else
+        if let Some(decision) = self.breakdown_avoidance_decision(car, state, index) {
+            return decision;
+        }
+        
+        // Then merging out of a lane closed ahead
+        if let Some(decision) = self.closure_avoidance_decision(car, state, index) {
+            return decision;
+        }
+        
+        // Routed cars nearing their exit only move toward the exit lane, and buses toward
+        // the lane of their next stop
+        if let Some(decision) = self.exit_lane_decision(car, state, index) {
+            return decision;
+        }
+        if let Some(decision) = self.bus_stop_lane_decision(car, state, index) {
+            return decision;
+        }
+        
+        // Heavy vehicles leave lanes they are banned from
+        if self.is_banned_lane(car, car.current_lane) {
+            return self.first_safe_lane(car, self.allowed_lanes(car), state, index);
         }
         
         // Check if enough time has passed since last lane change
         let time_since_change = state.time - car.behavior.last_lane_change_time;
-        let min_change_interval = 60.0 / car.behavior.lane_change_frequency; // Convert from per-minute to seconds
+        let min_change_interval = if self.lane_change.model == "mobil" {
+            // MOBIL decides on incentives alone, just let the previous change settle
+            2.0 * self.route.route.traffic_rules.lane_change_time
+        } else {
+            60.0 / car.behavior.lane_change_frequency // Convert from per-minute to seconds
+        };
         
         if time_since_change < min_change_interval {
-            return None;
+            return LaneDecision::Stay;
         }
         
-        let route_geom = &self.route.route.geometry;
-        let total_lanes = route_geom.lane_count;
+        if self.lane_change.model == "mobil" {
+            return self.mobil_lane_change(car, state, index).map_or(LaneDecision::Stay, LaneDecision::Change);
+        }
         
         // Determine possible lane changes
-        let can_change_left = car.current_lane > 1;
-        let can_change_right = car.current_lane < total_lanes;
+        let adjacent_lanes = self.allowed_lanes(car);
+        let can_change_left = car.current_lane > 1 && adjacent_lanes.contains(&(car.current_lane - 1));
+        let can_change_right = car.current_lane < total_lanes && adjacent_lanes.contains(&(car.current_lane + 1));
         
         if !can_change_left && !can_change_right {
-            return None;
+            return LaneDecision::Stay;
         }
         
         // Calculate lane change probability based on behavior
         let base_probability = car.behavior.lane_change_frequency / 60.0; // per second
         let lane_change_chance = base_probability * state.dt;
         
-        if self.rng.gen::<f32>() < lane_change_chance {
+        let [roll, side, ..] = Self::draws(self.behavior_key, car, state.time);
+        if unit_float(roll) < lane_change_chance {
             // Decide which lane to change to
             let target_lane = if can_change_left && can_change_right {
-                if self.rng.gen_bool(0.5) {
+                if unit_float(side) < 0.5 {
                     car.current_lane - 1
                 } else {
                     car.current_lane + 1
@@ -143,24 +589,123 @@ impl BehaviorEngine {
             };
             
             // Check if lane change is safe
-            if self.is_lane_change_safe(car, target_lane, state) {
-                return Some(target_lane);
+            if self.is_lane_change_safe(car, target_lane, state, index) {
+                return LaneDecision::Change(target_lane);
             }
         }
         
-        None
+        LaneDecision::Stay
+    }
+    
+    /// Whether a light that is not green is just ahead of `car`
+    fn approaching_signal(&self, car: &Car, state: &SimulationState) -> bool {
+        let signal_lookahead = 50.0;
+        state.signals.iter().any(|signal| {
+            signal.phase != SignalPhase::Green &&
+                signal.distance_ahead(car).is_some_and(|distance| distance < signal_lookahead)
+        })
     }
     
-    fn is_lane_change_safe(&self, car: &Car, target_lane: u32, state: &SimulationState) -> bool {
+    /// Lane change that brings a car into its destination's exit lane. Returns `None` while
+    /// the exit is still far away, otherwise the decision.
+    fn exit_lane_decision(&self, car: &Car, state: &SimulationState, index: &SpatialIndex) -> Option<LaneDecision> {
+        let destination = car.destination.as_ref()?;
         let route_geom = &self.route.route.geometry;
+        if route_geom.geometry_type != "donut" {
+            return None;
+        }
+        let exit = self.route.route.exits.iter().find(|exit| &exit.id == destination)?;
+        
+        // Ring traffic travels counter-clockwise
         let center = nalgebra::Point2::new(route_geom.center_x, route_geom.center_y);
         let to_car = car.position - center;
         let car_angle = to_car.y.atan2(to_car.x);
+        let angle_ahead = (exit.angle.to_radians() - car_angle).rem_euclid(2.0 * std::f32::consts::PI);
+        self.lane_approach_decision(car, exit.lane, angle_ahead * to_car.magnitude(), state, index)
+    }
+    
+    /// Lane change that brings a bus into the lane of its next stop, which it keeps to the
+    /// whole way there. Returns `None` for other cars, otherwise the decision.
+    fn bus_stop_lane_decision(&self, car: &Car, state: &SimulationState, index: &SpatialIndex) -> Option<LaneDecision> {
+        let stop = self.route.route.bus_stops.get(car.bus.as_ref()?.next_stop)?;
+        self.lane_approach_decision(car, stop.lane, 0.0, state, index)
+    }
+    
+    /// Lane change one lane closer to `lane`, which the car has to be in `distance` ahead.
+    /// Returns `None` while that is still far away, otherwise the decision.
+    /// Each lane to cross takes a lane change and the settling time after it, so the car
+    /// starts early enough to cross them all at its current speed.
+    fn lane_approach_decision(&self, car: &Car, lane: u32, distance: f32, state: &SimulationState, index: &SpatialIndex) -> Option<LaneDecision> {
+        let lane_change_time = self.route.route.traffic_rules.lane_change_time;
+        let lanes_to_cross = car.current_lane.abs_diff(lane) as f32;
+        let approach_distance = lanes_to_cross * 2.0 * lane_change_time * car.velocity.magnitude();
+        if distance > approach_distance.max(EXIT_APPROACH_DISTANCE) {
+            return None;
+        }
         
-        let safety_distance = car.length + 10.0; // Minimum safe distance
+        if car.current_lane == lane {
+            return Some(LaneDecision::Stay);
+        }
+        let target_lane = if car.current_lane < lane {
+            car.current_lane + 1
+        } else {
+            car.current_lane - 1
+        };
         
-        for other_car in &state.cars {
-            if other_car.id == car.id || other_car.current_lane != target_lane {
+        // Let the previous change settle before the next one
+        let time_since_change = state.time - car.behavior.last_lane_change_time;
+        if time_since_change >= lane_change_time && self.is_lane_change_safe(car, target_lane, state, index) && !self.is_closed_ahead(car, target_lane) {
+            Some(LaneDecision::Change(target_lane))
+        } else {
+            Some(LaneDecision::Wait(target_lane))
+        }
+    }
+    
+    /// Lane change around a broken-down car, or a bus at its stop, in this lane. Returns
+    /// `None` when neither is close ahead, otherwise the decision.
+    fn breakdown_avoidance_decision(&self, car: &Car, state: &SimulationState, index: &SpatialIndex) -> Option<LaneDecision> {
+        let current = self.lane_neighbors(car, &[car.current_lane], state, index).into_iter().next()?;
+        let (leader, gap) = current.leader?;
+        if (leader.breakdown.is_none() && !leader.is_dwelling()) || gap > BREAKDOWN_AVOIDANCE_DISTANCE {
+            return None;
+        }
+        Some(self.first_safe_lane(car, self.allowed_lanes(car), state, index))
+    }
+    
+    /// Lane change out of a lane closed ahead. Returns `None` when no closed stretch of this
+    /// lane is close ahead, otherwise the decision.
+    fn closure_avoidance_decision(&self, car: &Car, state: &SimulationState, index: &SpatialIndex) -> Option<LaneDecision> {
+        closure_ahead(&self.route, car, car.current_lane)
+            .filter(|&distance| distance > 0.0 && distance < CLOSURE_MERGE_DISTANCE)?;
+        Some(self.first_safe_lane(car, self.allowed_lanes(car), state, index))
+    }
+    
+    /// Change into the first of `lanes` with a safe gap, or wait for one in the first lane
+    fn first_safe_lane(&self, car: &Car, lanes: Vec<u32>, state: &SimulationState, index: &SpatialIndex) -> LaneDecision {
+        match lanes.iter().find(|&&lane| self.is_lane_change_safe(car, lane, state, index)) {
+            Some(&lane) => LaneDecision::Change(lane),
+            None => lanes.first().map_or(LaneDecision::Stay, |&lane| LaneDecision::Wait(lane)),
+        }
+    }
+    
+    /// Whether `lane` is closed within merging distance ahead of `car`, or where it is now
+    fn is_closed_ahead(&self, car: &Car, lane: u32) -> bool {
+        closure_ahead(&self.route, car, lane).is_some_and(|distance| distance < CLOSURE_MERGE_DISTANCE)
+    }
+    
+    fn is_lane_change_safe(&self, car: &Car, target_lane: u32, state: &SimulationState, index: &SpatialIndex) -> bool {
+        let route_geom = &self.route.route.geometry;
+        let center = nalgebra::Point2::new(route_geom.center_x, route_geom.center_y);
+        let to_car = car.position - center;
+        let car_angle = to_car.y.atan2(to_car.x);
+        
+        let required_gap = self.required_lane_change_gap(car);
+        // Arc distance is measured on this car's radius, the target lane is one lane away
+        let search_radius = required_gap + (car.length + self.max_car_length) / 2.0 + 2.0 * route_geom.lane_width;
+        
+        for other_car in index.cars_near(&state.cars, &car.position, search_radius) {
+            let in_target_lane = other_car.occupies_lane(target_lane, route_geom.lane_width) || other_car.target_lane == Some(target_lane);
+            if other_car.id == car.id || other_car.is_on_shoulder() || !in_target_lane {
                 continue;
             }
             
@@ -174,8 +719,9 @@ impl BehaviorEngine {
             }
             
             let arc_distance = angle_diff * to_car.magnitude();
+            let gap = arc_distance - (car.length + other_car.length) / 2.0;
             
-            if arc_distance < safety_distance {
+            if gap < required_gap && !self.overlooks(car, other_car) {
                 return false;
             }
         }
@@ -183,51 +729,243 @@ impl BehaviorEngine {
         true
     }
     
-    fn check_exit_decision_for_car(&mut self, car: &Car, _state: &SimulationState) {
-        // Find nearby exits
-        let route_geom = &self.route.route.geometry;
-        let center = nalgebra::Point2::new(route_geom.center_x, route_geom.center_y);
-        let to_car = car.position - center;
-        let car_angle = to_car.y.atan2(to_car.x).to_degrees();
+    /// Whether the driver of `car` fails to see `other` in their blind spot: alongside or
+    /// just behind, with the chance their behavior's `blind_spot_miss_probability` gives.
+    /// A driver who misses a car keeps missing it for as long as it stays there.
+    fn overlooks(&self, car: &Car, other: &Car) -> bool {
+        let probability = self.behavior_of(car).map_or(0.0, |behavior| behavior.blind_spot_miss_probability);
+        if probability <= 0.0 {
+            return false;
+        }
+        let distance = self.longitudinal_distance(car, other);
+        if !(-(car.length / 2.0 + BLIND_SPOT_LENGTH)..=0.0).contains(&distance) {
+            return false;
+        }
+        let pair = ((car.id.index as u64) << 32) | other.id.index as u64;
+        let draw = (splitmix64(self.mirror_seed ^ pair) >> 40) as f32 / (1u64 << 24) as f32;
+        draw < probability
+    }
+    
+    /// Car the driver of `car` overlooked in `lane` though it is too close to change in
+    /// front of, if any
+    fn overlooked_car(&self, car: &Car, lane: u32, state: &SimulationState, index: &SpatialIndex) -> Option<CarId> {
+        let lane_width = self.route.route.geometry.lane_width;
+        let reach = car.length / 2.0 + BLIND_SPOT_LENGTH + self.max_car_length;
+        index.cars_near(&state.cars, &car.position, reach + lane_width * 2.0)
+            .filter(|other| other.id != car.id && !other.is_on_shoulder())
+            .filter(|other| other.occupies_lane(lane, lane_width) || other.target_lane == Some(lane))
+            .filter(|other| {
+                let gap = self.longitudinal_distance(car, other).abs() - (car.length + other.length) / 2.0;
+                gap < self.required_lane_change_gap(car)
+            })
+            .find(|other| self.overlooks(car, other))
+            .map(|other| other.id)
+    }
+    
+    /// MOBIL lane change decision: change to the adjacent lane with the largest acceleration
+    /// advantage once the disadvantage imposed on the old and new followers is weighed in by
+    /// politeness, as long as the new follower would not have to brake harder than the safe limit
+    fn mobil_lane_change(&self, car: &Car, state: &SimulationState, index: &SpatialIndex) -> Option<u32> {
+        let config = &self.lane_change;
+        let target_lanes = self.allowed_lanes(car);
+        let mut lanes = vec![car.current_lane];
+        lanes.extend(&target_lanes);
+        let mut neighbors = self.lane_neighbors(car, &lanes, state, index).into_iter();
+        let current = neighbors.next()?;
+        let current_acceleration = self.idm_acceleration(state.weather, car, current.leader);
         
-        // Normalize angle to 0-360 range
-        let car_angle = if car_angle < 0.0 {
-            car_angle + 360.0
-        } else {
-            car_angle
-        };
+        // The old follower gains the gap we leave behind
+        let old_follower_gain = current.follower.map(|(follower, gap_to_car)| {
+            let behind_car = self.idm_acceleration(state.weather, follower, Some((car, gap_to_car)));
+            let behind_leader = self.idm_acceleration(state.weather, follower, current.leader.map(|(leader, gap)| (leader, gap_to_car + car.length + gap)));
+            behind_leader - behind_car
+        }).unwrap_or(0.0);
         
-        for exit in &self.route.route.exits {
-            // Check if exit is nearby (within 30 degrees)
-            let angle_diff = (exit.angle - car_angle).abs();
-            let angle_diff = if angle_diff > 180.0 {
-                360.0 - angle_diff
+        let mut best_lane = None;
+        let mut best_incentive = config.acceleration_threshold;
+        
+        for (target_lane, target) in target_lanes.into_iter().zip(neighbors) {
+        
+            // There has to be physical room in the target lane, more of it for long vehicles
+            let min_gap = self.min_gap * Self::length_factor(car);
+            let gap_too_small = |neighbor: Neighbor| neighbor.is_some_and(|(other, gap)| gap < min_gap && !self.overlooks(car, other));
+            if gap_too_small(target.leader) || gap_too_small(target.follower) {
+                continue;
+            }
+            
+            // Safety criterion: the new follower must not be forced to brake too hard, unless
+            // the driver has not seen it
+            let follower = target.follower.filter(|&(follower, _)| !self.overlooks(car, follower));
+            let new_follower_gain = match follower {
+                Some((follower, gap_to_car)) => {
+                    let behind_car = self.idm_acceleration(state.weather, follower, Some((car, gap_to_car)));
+                    if behind_car < -config.safe_deceleration {
+                        continue;
+                    }
+                    let behind_leader = self.idm_acceleration(state.weather, follower, target.leader.map(|(leader, gap)| (leader, gap_to_car + car.length + gap)));
+                    behind_car - behind_leader
+                }
+                None => 0.0,
+            };
+            
+            // Incentive criterion, with a bias towards the right-hand lanes
+            let bias = if self.is_right_of(target_lane, car.current_lane) {
+                config.keep_right_bias
             } else {
-                angle_diff
+                -config.keep_right_bias
             };
+            let own_gain = self.idm_acceleration(state.weather, car, target.leader) - current_acceleration;
+            let incentive = own_gain + config.politeness * (new_follower_gain + old_follower_gain) + bias;
             
-            if angle_diff < 30.0 && car.current_lane == exit.lane {
-                // Decide whether to take the exit
-                if self.rng.gen::<f32>() < car.behavior.exit_probability {
-                    // TODO: Implement exit logic
-                    // For now, we'll just mark the car for removal
-                    // This would be handled by the traffic manager
-                }
+            if incentive > best_incentive {
+                best_incentive = incentive;
+                best_lane = Some(target_lane);
+            }
+        }
+        
+        best_lane
+    }
+    
+    /// Intelligent Driver Model acceleration of `car` following `leader` at the given gap
+    fn idm_acceleration(&self, weather: Weather, car: &Car, leader: Neighbor) -> f32 {
+        let speed = car.velocity.magnitude();
+        let desired_speed = car.behavior.target_speed.max(0.1);
+        let free_road = 1.0 - (speed / desired_speed).powi(4);
+        
+        let interaction = match leader {
+            Some((leader, gap)) => {
+                let comfortable_deceleration = weather.braking_limit(car, &self.route.route.surface) * 0.5;
+                let headway = self.route.route.traffic_rules.following_distance * weather.headway_factor() * car.behavior.headway_factor();
+                let approach_rate = speed - leader.velocity.magnitude();
+                let desired_gap = self.min_gap + (speed * headway
+                    + speed * approach_rate / (2.0 * (car.max_acceleration * comfortable_deceleration).sqrt())).max(0.0);
+                (desired_gap / gap.max(0.1)).powi(2)
+            }
+            None => 0.0,
+        };
+        
+        car.max_acceleration * (free_road - interaction)
+    }
+    
+    /// Closest cars ahead and behind `car` in each of `lanes`, including cars changing into those lanes
+    fn lane_neighbors<'a>(&self, car: &Car, lanes: &[u32], state: &'a SimulationState, index: &SpatialIndex) -> Vec<LaneNeighbors<'a>> {
+        let lookahead = 100.0; // Cars further away have no influence on the decision
+        let mut neighbors: Vec<LaneNeighbors> = lanes.iter()
+            .map(|_| LaneNeighbors { leader: None, follower: None })
+            .collect();
+            
+        for other_car in index.cars_near(&state.cars, &car.position, lookahead) {
+            if other_car.id == car.id || other_car.is_on_shoulder() {
+                continue;
+            }
+            let lane_width = self.route.route.geometry.lane_width;
+            let Some(lane_index) = lanes.iter().position(|&lane| other_car.occupies_lane(lane, lane_width) || other_car.target_lane == Some(lane)) else {
+                continue;
+            };
+            
+            let distance = self.longitudinal_distance(car, other_car);
+            if distance.abs() > lookahead {
+                continue;
+            }
+            let gap = distance.abs() - (car.length + other_car.length) / 2.0;
+            
+            let lane_neighbors = &mut neighbors[lane_index];
+            let closest = if distance >= 0.0 { &mut lane_neighbors.leader } else { &mut lane_neighbors.follower };
+            if closest.is_none_or(|(_, closest_gap)| gap < closest_gap) {
+                *closest = Some((other_car, gap));
             }
         }
+        
+        neighbors
     }
     
-    pub fn create_behavior_state(&mut self, behavior_name: &str) -> BehaviorState {
+    /// Signed distance from `car` to `other` along the direction of travel
+    fn longitudinal_distance(&self, car: &Car, other: &Car) -> f32 {
+        let route_geom = &self.route.route.geometry;
+        if route_geom.geometry_type == "donut" {
+            // Arc length around the ring, traffic travels counter-clockwise
+            let center = nalgebra::Point2::new(route_geom.center_x, route_geom.center_y);
+            let to_car = car.position - center;
+            let to_other = other.position - center;
+            let angle = (to_other.y.atan2(to_other.x) - to_car.y.atan2(to_car.x) + std::f32::consts::PI)
+                .rem_euclid(2.0 * std::f32::consts::PI) - std::f32::consts::PI;
+            angle * to_car.magnitude()
+        } else {
+            let direction = nalgebra::Vector2::new(car.heading.cos(), car.heading.sin());
+            (other.position - car.position).dot(&direction)
+        }
+    }
+    
+    /// Bumper-to-bumper gap `car` needs in the target lane, in front and behind
+    fn required_lane_change_gap(&self, car: &Car) -> f32 {
+        LANE_CHANGE_GAP * Self::length_factor(car)
+    }
+    
+    /// How many times longer than a regular car `car` is, at least 1
+    fn length_factor(car: &Car) -> f32 {
+        (car.length / REFERENCE_CAR_LENGTH).max(1.0)
+    }
+    
+    fn is_banned_lane(&self, car: &Car, lane: u32) -> bool {
+        self.heavy_types.contains(&car.car_type) &&
+            self.route.route.traffic_rules.heavy_vehicle_banned_lanes.contains(&lane)
+    }
+    
+    /// Adjacent lanes `car` may change into, leaving out lanes its type is banned from and
+    /// lanes closed just ahead
+    fn allowed_lanes(&self, car: &Car) -> Vec<u32> {
+        self.adjacent_lanes(car.current_lane)
+            .into_iter()
+            .filter(|&lane| !self.is_banned_lane(car, lane) && !self.is_closed_ahead(car, lane))
+            .collect()
+    }
+    
+    /// Lanes a car can move into from `lane`. Cloverleaf highways are split into
+    /// carriageways of three lanes and cars never cross into the opposite direction.
+    fn adjacent_lanes(&self, lane: u32) -> Vec<u32> {
+        let route_geom = &self.route.route.geometry;
+        let candidates = [lane.saturating_sub(1), lane + 1];
+        
+        if route_geom.geometry_type == "cloverleaf" {
+            let highway_lanes = 12;
+            let carriageway = |lane: u32| (lane - 1) / 3;
+            candidates.into_iter()
+                .filter(|&candidate| candidate >= 1 && candidate <= highway_lanes && lane <= highway_lanes)
+                .filter(|&candidate| carriageway(candidate) == carriageway(lane))
+                .collect()
+        } else {
+            candidates.into_iter()
+                .filter(|&candidate| candidate >= 1 && candidate <= route_geom.lane_count)
+                .collect()
+        }
+    }
+    
+    /// Whether `lane` is to the right of `other_lane` for the traffic using them
+    fn is_right_of(&self, lane: u32, other_lane: u32) -> bool {
+        let route_geom = &self.route.route.geometry;
+        if route_geom.geometry_type == "cloverleaf" {
+            // Southbound (1-3) and eastbound (10-12) lanes are numbered right to left
+            match other_lane {
+                1..=3 | 10..=12 => lane < other_lane,
+                _ => lane > other_lane,
+            }
+        } else {
+            // Counter-clockwise ring traffic has the outer lanes on its right
+            lane > other_lane
+        }
+    }
+    
+    pub fn create_behavior_state(&mut self, behavior_type: BehaviorId) -> BehaviorState {
         // Find the behavior configuration
         let behavior = self.behaviors
             .iter()
-            .find(|(name, _)| name == behavior_name)
+            .find(|(id, _)| *id == behavior_type)
             .map(|(_, behavior)| behavior.clone())
             .unwrap_or_else(|| {
                 // Default to "normal" behavior if not found
                 self.behaviors
                     .iter()
-                    .find(|(name, _)| name == "normal")
+                    .find(|(id, _)| Some(*id) == self.default_behavior)
                     .map(|(_, behavior)| behavior.clone())
                     .unwrap_or(DriverBehavior {
                         name: "default".to_string(),
@@ -236,7 +974,11 @@ impl BehaviorEngine {
                         lane_change_frequency: 0.8,
                         speed_variance: 1.0,
                         reaction_time: 1.2,
-                        exit_probability: 0.25,
+                        script: None,
+                        tailgates: false,
+                        merge_courtesy: "none".to_string(),
+                        comfort_lateral_acceleration: None,
+                        blind_spot_miss_probability: 0.0,
                     })
             });
         
@@ -245,26 +987,34 @@ impl BehaviorEngine {
             lane_change_frequency: behavior.lane_change_frequency,
             speed_variance: behavior.speed_variance,
             reaction_time: behavior.reaction_time,
-            exit_probability: behavior.exit_probability,
             last_lane_change_time: 0.0,
             target_speed: 25.0, // Will be updated by physics
+            merge_intent: None,
+            tailgating: false,
         }
     }
     
-    pub fn select_random_behavior(&mut self) -> String {
+    /// Configuration of the car's driver behavior
+    fn behavior_of(&self, car: &Car) -> Option<&DriverBehavior> {
+        self.behaviors.iter().find(|(name, _)| *name == car.behavior_type).map(|(_, behavior)| behavior)
+    }
+    
+    /// Driver behavior for a new car, drawn from the spawn stream passed in
+    pub fn select_random_behavior(&self, rng: &mut StdRng) -> BehaviorId {
         let total_weight: u32 = self.behaviors.iter().map(|(_, b)| b.weight).sum();
-        let mut random_value = self.rng.gen_range(0..total_weight);
+        let mut random_value = rng.gen_range(0..total_weight);
         
         for (name, behavior) in &self.behaviors {
             if random_value < behavior.weight {
-                return name.clone();
+                return *name;
             }
             random_value -= behavior.weight;
         }
         
         // Fallback to first behavior
         self.behaviors.first()
-            .map(|(name, _)| name.clone())
-            .unwrap_or_else(|| "normal".to_string())
+            .map(|(name, _)| *name)
+            .or(self.default_behavior)
+            .unwrap_or(BehaviorId::from_index(0))
     }
 }
\ No newline at end of file
@@ -0,0 +1,170 @@
+use super::{BehaviorId, CarId, SimulationEvent, SimulationState};
+use crate::config::CarsConfig;
+use std::collections::HashMap;
+
+/// Below this speed a follower's time headway is left out, it grows without bound as the
+/// car comes to a stop
+const HEADWAY_MIN_SPEED: f32 = 1.0;
+
+/// How the drivers of one behavior have driven since the run started
+#[derive(Debug, Clone, PartialEq)]
+pub struct BehaviorStats {
+    pub behavior: String,
+    pub cars: u32, // on the road now
+    pub mean_speed: f32, // m/s, distance driven over time on the road, 0 before any driving
+    pub mean_headway: Option<f32>, // seconds to the car ahead while following one
+    pub lane_changes_per_km: f32,
+    pub mean_delay: Option<f32>, // seconds, of the trips completed
+    pub collisions: u32, // collisions a driver of the behavior was involved in
+}
+
+/// Running totals of one behavior
+#[derive(Debug, Clone, Default)]
+struct BehaviorTotals {
+    drive_time: f32, // vehicle-seconds on the road
+    distance: f32, // meters
+    following_time: f32, // vehicle-seconds following a car ahead
+    headway_sum: f32, // time headways integrated over following_time
+    lane_changes: u32,
+    trips: u32,
+    delay: f32, // seconds summed over trips
+    collisions: u32,
+}
+
+/// Accumulates driving statistics per driver behavior over a run, for comparing the
+/// behaviors side by side. Behaviors of the configuration are listed from the start, any
+/// others once a car with them shows up.
+#[derive(Debug, Clone)]
+pub struct BehaviorStatistics {
+    configured: Vec<String>, // behaviors listed before any car has them
+    totals: Vec<Option<BehaviorTotals>>, // by behavior id, `None` for behaviors no car had yet
+    behaviors: HashMap<CarId, BehaviorId>, // behavior of each car seen, until it leaves
+    trips_seen: usize, // trips of the trip log already counted
+    last_time: f32,
+}
+
+impl BehaviorStatistics {
+    pub fn new(cars_config: &CarsConfig) -> Self {
+        Self {
+            configured: cars_config.behavior.keys().cloned().collect(),
+            totals: Vec::new(),
+            behaviors: HashMap::new(),
+            trips_seen: 0,
+            last_time: 0.0,
+        }
+    }
+    
+    /// Forget everything recorded, keeping the configured behaviors listed
+    pub fn clear(&mut self) {
+        self.totals.clear();
+        self.behaviors.clear();
+        self.trips_seen = 0;
+        self.last_time = 0.0;
+    }
+    
+    /// Take in the tick that produced `state`. Call once for every tick.
+    pub fn record(&mut self, state: &SimulationState) {
+        // A reset or loaded checkpoint starts the statistics over
+        if state.time < self.last_time || state.trips.len() < self.trips_seen {
+            self.clear();
+        }
+        self.last_time = state.time;
+        
+        for car in &state.cars {
+            self.behaviors.entry(car.id).or_insert(car.behavior_type);
+            let totals = self.totals_of(car.behavior_type);
+            let speed = car.velocity.magnitude();
+            totals.drive_time += state.dt;
+            totals.distance += speed * state.dt;
+            if let (Some((_, gap)), true) = (car.leader, speed >= HEADWAY_MIN_SPEED) {
+                totals.following_time += state.dt;
+                totals.headway_sum += gap / speed * state.dt;
+            }
+        }
+        
+        for trip in &state.trips.trips()[self.trips_seen..] {
+            let Some(behavior) = state.names.behaviors.id(&trip.behavior) else {
+                continue;
+            };
+            let totals = self.totals_of(behavior);
+            totals.trips += 1;
+            totals.delay += trip.delay;
+        }
+        self.trips_seen = state.trips.len();
+        
+        let mut exited = Vec::new();
+        for event in &state.events {
+            match event {
+                SimulationEvent::LaneChangeStarted { car, .. } => {
+                    if let Some(totals) = self.totals_for(*car) {
+                        totals.lane_changes += 1;
+                    }
+                }
+                SimulationEvent::CollisionDetected(collision) => {
+                    for car in [collision.car_a, collision.car_b] {
+                        if let Some(totals) = self.totals_for(car) {
+                            totals.collisions += 1;
+                        }
+                    }
+                }
+                SimulationEvent::CarExited { car, .. } => exited.push(*car),
+                _ => {}
+            }
+        }
+        for car in exited {
+            self.behaviors.remove(&car);
+        }
+        
+        // Cars towed away or despawned leave no event, forget them once a few have piled up
+        if self.behaviors.len() > state.cars.len() + 64 {
+            let present: std::collections::HashSet<CarId> = state.cars.iter().map(|car| car.id).collect();
+            self.behaviors.retain(|id, _| present.contains(id));
+        }
+    }
+    
+    fn totals_for(&mut self, car: CarId) -> Option<&mut BehaviorTotals> {
+        let behavior = *self.behaviors.get(&car)?;
+        self.totals.get_mut(behavior.index())?.as_mut()
+    }
+    
+    /// Totals of `behavior`, listing it if it wasn't
+    fn totals_of(&mut self, behavior: BehaviorId) -> &mut BehaviorTotals {
+        if self.totals.len() <= behavior.index() {
+            self.totals.resize(behavior.index() + 1, None);
+        }
+        self.totals[behavior.index()].get_or_insert_with(BehaviorTotals::default)
+    }
+    
+    /// Statistics of every behavior by name, with the cars on the road in `state`
+    pub fn behaviors(&self, state: &SimulationState) -> Vec<BehaviorStats> {
+        let mut cars = vec![0; self.totals.len()];
+        for car in &state.cars {
+            if let Some(count) = cars.get_mut(car.behavior_type.index()) {
+                *count += 1;
+            }
+        }
+        let mut listed: Vec<&str> = self.totals.iter().enumerate()
+            .filter(|(_, totals)| totals.is_some())
+            .map(|(index, _)| state.names.behavior(BehaviorId::from_index(index)))
+            .chain(self.configured.iter().map(String::as_str))
+            .collect();
+        listed.sort_unstable();
+        listed.dedup();
+        let unseen = BehaviorTotals::default();
+        listed.into_iter()
+            .map(|name| {
+                let index = state.names.behaviors.id(name).map(BehaviorId::index);
+                (name, index.and_then(|index| self.totals.get(index)?.as_ref()).unwrap_or(&unseen), index)
+            })
+            .map(|(name, totals, index)| BehaviorStats {
+                behavior: name.to_string(),
+                cars: index.and_then(|index| cars.get(index).copied()).unwrap_or(0),
+                mean_speed: if totals.drive_time > 0.0 { totals.distance / totals.drive_time } else { 0.0 },
+                mean_headway: (totals.following_time > 0.0).then(|| totals.headway_sum / totals.following_time),
+                lane_changes_per_km: if totals.distance > 0.0 { totals.lane_changes as f32 / (totals.distance / 1000.0) } else { 0.0 },
+                mean_delay: (totals.trips > 0).then(|| totals.delay / totals.trips as f32),
+                collisions: totals.collisions,
+            })
+            .collect()
+    }
+}
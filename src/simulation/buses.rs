@@ -0,0 +1,129 @@
+use super::{Car, SimulationState};
+use crate::config::{BusStop, CarType, RouteConfig, RouteGeometry};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+/// Below this speed a bus in its stop's zone has pulled up and starts dwelling
+const STOPPED_SPEED: f32 = 0.5;
+/// Speed at which a bus done dwelling has pulled away from the stop
+const DEPARTED_SPEED: f32 = 2.0;
+/// Meters past the end of a stop's zone within which a bus that never pulled up there
+/// gives up on the stop
+const MISSED_WINDOW: f32 = 20.0;
+
+/// Where a bus is on the route's stop list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BusState {
+    pub next_stop: usize,         // index into the route's bus stops of the stop it heads for
+    pub dwell_start: Option<f32>, // when it pulled up at that stop
+}
+
+/// Dwell statistics of one bus stop
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BusStopState {
+    pub id: String,
+    pub departures: u32,  // buses that dwelt at the stop and pulled away
+    pub missed: u32,      // buses that passed the stop without pulling up, held up in another lane
+    pub total_dwell: f32, // seconds from pulling up to pulling away, summed over departures
+    pub max_dwell: f32,
+}
+
+impl BusStopState {
+    pub fn mean_dwell(&self) -> f32 {
+        if self.departures == 0 {
+            0.0
+        } else {
+            self.total_dwell / self.departures as f32
+        }
+    }
+}
+
+/// Meters along the ring from `car` to the downstream end of `stop`'s zone, measured on
+/// the car's radius. At most the stop's length while the car is within the zone.
+pub fn bus_stop_ahead(geometry: &RouteGeometry, car: &Car, stop: &BusStop) -> f32 {
+    // Ring traffic travels counter-clockwise
+    let to_car = car.position - nalgebra::Point2::new(geometry.center_x, geometry.center_y);
+    let radius = to_car.magnitude().max(1.0);
+    let end_angle = stop.angle.to_radians() + stop.length / 2.0 / radius;
+    (end_angle - to_car.y.atan2(to_car.x)).rem_euclid(TAU) * radius
+}
+
+/// Takes buses down the route's stop list: starts their dwell once they pull up in a stop's
+/// zone, moves them on to the next stop once they pull away, and keeps per-stop dwell
+/// statistics. Physics brings buses to a stop and holds them there.
+pub struct BusController {
+    geometry: RouteGeometry,
+    stops: Vec<BusStop>,
+    statistics: Vec<BusStopState>,
+}
+
+impl BusController {
+    pub fn new(route: &RouteConfig) -> Self {
+        let stops = route.route.bus_stops.clone();
+        let statistics = stops.iter()
+            .map(|stop| BusStopState { id: stop.id.clone(), ..Default::default() })
+            .collect();
+            
+        Self {
+            geometry: route.route.geometry.clone(),
+            stops,
+            statistics,
+        }
+    }
+    
+    /// Stop list state for a new car of `car_type`, `None` unless it is a bus and the route
+    /// has stops
+    pub fn board(&self, car_type: &CarType) -> Option<BusState> {
+        (car_type.bus && !self.stops.is_empty()).then_some(BusState { next_stop: 0, dwell_start: None })
+    }
+    
+    /// Advance buses along their stop lists and publish the stop statistics into the
+    /// simulation state
+    pub fn update(&mut self, state: &mut SimulationState) {
+        if self.stops.is_empty() {
+            return;
+        }
+        
+        let time = state.time;
+        for car in &mut state.cars {
+            let Some(stop) = car.bus.as_ref().and_then(|bus| self.stops.get(bus.next_stop)) else {
+                continue;
+            };
+            let speed = car.velocity.magnitude();
+            let ahead = bus_stop_ahead(&self.geometry, car, stop);
+            let circumference = TAU * (car.position - nalgebra::Point2::new(self.geometry.center_x, self.geometry.center_y)).magnitude();
+            let Some(bus) = car.bus.as_mut() else {
+                continue;
+            };
+            let statistics = &mut self.statistics[bus.next_stop];
+            match bus.dwell_start {
+                None if ahead <= stop.length && car.current_lane == stop.lane && car.target_lane.is_none() && speed < STOPPED_SPEED => {
+                    bus.dwell_start = Some(time);
+                }
+                None if ahead > circumference - MISSED_WINDOW => {
+                    statistics.missed += 1;
+                    bus.next_stop += 1;
+                }
+                Some(start) if time - start >= stop.dwell_time && speed >= DEPARTED_SPEED => {
+                    let dwell = time - start;
+                    statistics.departures += 1;
+                    statistics.total_dwell += dwell;
+                    statistics.max_dwell = statistics.max_dwell.max(dwell);
+                    bus.next_stop += 1;
+                    bus.dwell_start = None;
+                }
+                _ => {}
+            }
+        }
+        state.bus_stops = self.statistics.clone();
+    }
+    
+    /// Carry the stop statistics over from a loaded checkpoint
+    pub fn restore(&mut self, state: &SimulationState) {
+        for statistics in &mut self.statistics {
+            if let Some(saved) = state.bus_stops.iter().find(|saved| saved.id == statistics.id) {
+                *statistics = saved.clone();
+            }
+        }
+    }
+}
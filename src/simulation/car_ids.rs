@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Handle of a car: the slot it holds and the generation of the slot when it got it. A
+/// slot is handed out again once its car has left, under the next generation, so a
+/// handle held on to after its car left finds nothing rather than the car in the slot now.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct CarId {
+    pub index: u32,
+    pub generation: u32,
+}
+
+impl CarId {
+    pub const fn new(index: u32, generation: u32) -> Self {
+        Self { index, generation }
+    }
+    
+    pub fn slot(self) -> usize {
+        self.index as usize
+    }
+}
+
+/// Cars of a slot's first generation go by the slot alone, later ones as `slot-generation`
+impl fmt::Display for CarId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.generation == 0 {
+            write!(f, "{}", self.index)
+        } else {
+            write!(f, "{}-{}", self.index, self.generation)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum SlotState {
+    Free,
+    Taken, // its car is on the road
+    Held, // its car left and comes back with the same id
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Slot {
+    generation: u32,
+    state: SlotState,
+}
+
+/// Slots the car ids are handed out from, in constant time. Slots are reused longest
+/// free first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CarIds {
+    slots: Vec<Slot>,
+    free: VecDeque<u32>, // may list slots taken again since, those are skipped
+}
+
+impl CarIds {
+    /// A new id, in a free slot or else a new one
+    pub fn allocate(&mut self) -> CarId {
+        while let Some(index) = self.free.pop_front() {
+            let slot = &mut self.slots[index as usize];
+            if slot.state == SlotState::Free {
+                slot.state = SlotState::Taken;
+                return CarId::new(index, slot.generation);
+            }
+        }
+        self.slots.push(Slot { generation: 0, state: SlotState::Taken });
+        CarId::new(self.slots.len() as u32 - 1, 0)
+    }
+    
+    /// The car of `id` left the road. Its slot is free for the next generation, unless the
+    /// car is held to come back.
+    pub fn release(&mut self, id: CarId) {
+        let Some(slot) = self.slots.get_mut(id.slot()) else {
+            return;
+        };
+        if slot.generation == id.generation && slot.state == SlotState::Taken {
+            slot.generation = slot.generation.wrapping_add(1);
+            slot.state = SlotState::Free;
+            self.free.push_back(id.index);
+        }
+    }
+    
+    /// Keep the slot of `id` for its car when it leaves, to come back with `claim`
+    pub fn hold(&mut self, id: CarId) {
+        if let Some(slot) = self.slots.get_mut(id.slot()).filter(|slot| slot.generation == id.generation) {
+            slot.state = SlotState::Held;
+        }
+    }
+    
+    /// Take the slot of `id` for a car that keeps its id: one held, or one put on the road
+    /// directly. Fails if the slot's car is on the road or the slot has moved on to a later
+    /// generation since `id`, whose car then needs a new id.
+    pub fn claim(&mut self, id: CarId) -> bool {
+        while self.slots.len() <= id.slot() {
+            self.free.push_back(self.slots.len() as u32);
+            self.slots.push(Slot { generation: 0, state: SlotState::Free });
+        }
+        let slot = &mut self.slots[id.slot()];
+        if slot.state == SlotState::Taken {
+            return slot.generation == id.generation;
+        }
+        // A slot never goes back to a generation it has handed out, or an old handle
+        // would find the slot's next car
+        slot.generation = slot.generation.max(id.generation);
+        if slot.generation != id.generation {
+            return false;
+        }
+        slot.state = SlotState::Taken;
+        true
+    }
+}
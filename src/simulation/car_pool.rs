@@ -0,0 +1,73 @@
+use super::{Car, Perception};
+
+/// Spare cars kept at most, enough for the cars that leave in a busy step
+const MAX_SPARE_CARS: usize = 1024;
+
+/// Heap buffers of a car that left the road, written over by the next car spawned
+#[derive(Debug, Default)]
+pub struct SpareCar {
+    entry: String,
+    destination: Option<String>,
+    perception: Perception,
+}
+
+impl SpareCar {
+    /// Entry id in the spare car's buffer
+    pub fn entry(&mut self, id: &str) -> String {
+        let mut entry = std::mem::take(&mut self.entry);
+        entry.clear();
+        entry.push_str(id);
+        entry
+    }
+    
+    /// Destination exit id, if any, in the spare car's buffer
+    pub fn destination(&mut self, id: Option<&str>) -> Option<String> {
+        id.map(|id| {
+            let mut destination = self.destination.take().unwrap_or_default();
+            destination.clear();
+            destination.push_str(id);
+            destination
+        })
+    }
+    
+    /// A driver who has seen nothing yet, keeping the spare car's sample buffer
+    pub fn perception(&mut self) -> Perception {
+        let mut perception = std::mem::take(&mut self.perception);
+        perception.forget();
+        perception
+    }
+}
+
+/// Free list of the cars taken off the road. Spawning writes the new car into the buffers
+/// of one that left, so a road with as many cars leaving as arriving spawns without
+/// allocating.
+#[derive(Debug, Default)]
+pub struct CarPool {
+    spares: Vec<SpareCar>,
+}
+
+impl CarPool {
+    /// Keep the buffers of a car that left the road
+    pub fn release(&mut self, car: Car) {
+        if self.spares.len() < MAX_SPARE_CARS {
+            self.spares.push(SpareCar {
+                entry: car.entry,
+                destination: car.destination,
+                perception: car.perception,
+            });
+        }
+    }
+    
+    /// Buffers for the next car spawned, empty ones when no car has left yet
+    pub fn take(&mut self) -> SpareCar {
+        self.spares.pop().unwrap_or_default()
+    }
+    
+    pub fn len(&self) -> usize {
+        self.spares.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.spares.is_empty()
+    }
+}
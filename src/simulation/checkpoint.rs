@@ -0,0 +1,45 @@
+use super::SimulationState;
+use anyhow::{Result, anyhow};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const CHECKPOINT_MAGIC: &[u8; 8] = b"TSCHKPNT";
+const CHECKPOINT_VERSION: u32 = 13;
+
+/// Checkpoints are a single snapshot of the simulation state that a run can be resumed from.
+/// Backend state that isn't part of the snapshot (RNGs, spawn timers) is
+/// rebuilt with `ComputeBackend::restore_checkpoint` after loading.
+impl SimulationState {
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(CHECKPOINT_MAGIC)?;
+        writer.write_all(&CHECKPOINT_VERSION.to_le_bytes())?;
+        bincode::serialize_into(&mut writer, self)?;
+        writer.flush()?;
+        Ok(())
+    }
+    
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut reader = BufReader::new(File::open(path)?);
+        
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != CHECKPOINT_MAGIC {
+            return Err(anyhow!("{} is not a traffic-sim checkpoint file", path.display()));
+        }
+        
+        let mut version_bytes = [0u8; 4];
+        reader.read_exact(&mut version_bytes)?;
+        let version = u32::from_le_bytes(version_bytes);
+        if version != CHECKPOINT_VERSION {
+            return Err(anyhow!("Unsupported checkpoint version {} (expected {})", version, CHECKPOINT_VERSION));
+        }
+        
+        let mut state: SimulationState = bincode::deserialize_from(&mut reader)
+            .map_err(|e| anyhow!("Corrupt checkpoint {}: {}", path.display(), e))?;
+        state.reindex_cars();
+        Ok(state)
+    }
+}
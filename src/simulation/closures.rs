@@ -0,0 +1,46 @@
+use super::{Car, Point, place_on_lane};
+use crate::config::{LaneClosure, RouteConfig, RouteGeometry};
+use std::f32::consts::{PI, TAU};
+
+/// Distance along the ring from `car` to the nearest closed stretch of `lane` ahead of it,
+/// measured on the car's radius. 0 while the car is within a closed stretch, `None` if the
+/// lane has no closures or the route is not a ring.
+pub fn closure_ahead(route: &RouteConfig, car: &Car, lane: u32) -> Option<f32> {
+    let geometry = &route.route.geometry;
+    if geometry.geometry_type != "donut" {
+        return None;
+    }
+    
+    // Ring traffic travels counter-clockwise
+    let to_car = car.position - nalgebra::Point2::new(geometry.center_x, geometry.center_y);
+    let car_angle = to_car.y.atan2(to_car.x);
+    route.route.closures.iter()
+        .filter(|closure| closure.lane == lane)
+        .map(|closure| closure.angle_ahead(car_angle) * to_car.magnitude())
+        .min_by(|a, b| a.total_cmp(b))
+}
+
+/// Closure of the lane under `from` reaching round to the angle of `to`, either way along
+/// the ring, whichever is shorter. `None` off a ring road's lanes or for a zero-length drag.
+pub fn closure_between(geometry: &RouteGeometry, from: Point, to: Point) -> Option<LaneClosure> {
+    if geometry.geometry_type != "donut" {
+        return None;
+    }
+    let lane = place_on_lane(geometry, from)?.lane;
+    
+    // Whole tenths of a degree read well in the route file
+    let center = Point::new(geometry.center_x, geometry.center_y);
+    let degrees = |point: Point| {
+        let to_point = point - center;
+        (to_point.y.atan2(to_point.x).rem_euclid(TAU).to_degrees() * 10.0).round() / 10.0 % 360.0
+    };
+    let (from_angle, to_angle) = (degrees(from), degrees(to));
+    if from_angle == to_angle {
+        return None;
+    }
+    
+    // Traffic runs counter-clockwise, closures from their upstream end
+    let counter_clockwise = (to_angle - from_angle).to_radians().rem_euclid(TAU) <= PI;
+    let (start_angle, end_angle) = if counter_clockwise { (from_angle, to_angle) } else { (to_angle, from_angle) };
+    Some(LaneClosure { lane, start_angle, end_angle })
+}
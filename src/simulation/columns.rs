@@ -0,0 +1,97 @@
+use super::{BehaviorId, Car, CarId, Point, Vec2};
+
+/// The fields the neighbor searches of a step read for every nearby car, laid out as one
+/// array per field in the order of the cars they were gathered from.
+///
+/// Scanning candidates then touches a few packed arrays instead of whole `Car`s with
+/// their paths, histories and perception buffers, and only the car a search settles on
+/// is looked up in the car list. The columns are not the cars' storage: `Vec<Car>` stays
+/// the record every part of the simulation and the renderer reads and writes, and the
+/// columns are a scratch copy taken at the start of a step, stale as soon as cars move,
+/// change lanes or are added or removed. Gathering again reuses the arrays, so steps
+/// don't allocate once the fleet has reached its size.
+#[derive(Debug, Clone, Default)]
+pub struct CarColumns {
+    pub ids: Vec<CarId>,
+    pub positions: Vec<Point>,
+    pub velocities: Vec<Vec2>,
+    pub headings: Vec<f32>,
+    pub lengths: Vec<f32>,
+    pub widths: Vec<f32>,
+    pub lanes: Vec<u32>,
+    pub lateral_offsets: Vec<f32>, // Meters from the middle of the lane, as `Car::lateral_offset`
+    pub on_shoulder: Vec<bool>,
+    pub behaviors: Vec<BehaviorId>,
+}
+
+/// Position, orientation and size of a car's body, for overlap tests
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Footprint {
+    pub position: Point,
+    pub heading: f32,
+    pub length: f32,
+    pub width: f32,
+}
+
+impl CarColumns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Columns holding `cars`
+    pub fn from_cars(cars: &[Car]) -> Self {
+        let mut columns = Self::new();
+        columns.gather(cars);
+        columns
+    }
+    
+    /// Replace the columns with the fields of `cars`
+    pub fn gather(&mut self, cars: &[Car]) {
+        self.ids.clear();
+        self.positions.clear();
+        self.velocities.clear();
+        self.headings.clear();
+        self.lengths.clear();
+        self.widths.clear();
+        self.lanes.clear();
+        self.lateral_offsets.clear();
+        self.on_shoulder.clear();
+        self.behaviors.clear();
+        
+        for car in cars {
+            self.ids.push(car.id);
+            self.positions.push(car.position);
+            self.velocities.push(car.velocity);
+            self.headings.push(car.heading);
+            self.lengths.push(car.length);
+            self.widths.push(car.width);
+            self.lanes.push(car.current_lane);
+            self.lateral_offsets.push(car.lateral_offset);
+            self.on_shoulder.push(car.is_on_shoulder());
+            self.behaviors.push(car.behavior_type);
+        }
+    }
+    
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+    
+    /// Whether any part of the body of car `i` reaches into `lane`, as `Car::occupies_lane`
+    pub fn occupies_lane(&self, i: usize, lane: u32, lane_width: f32) -> bool {
+        let lateral_position = self.lanes[i] as f32 + self.lateral_offsets[i] / lane_width;
+        (lateral_position - lane as f32).abs() < 0.5 + self.widths[i] / (2.0 * lane_width)
+    }
+    
+    pub fn footprint(&self, i: usize) -> Footprint {
+        Footprint {
+            position: self.positions[i],
+            heading: self.headings[i],
+            length: self.lengths[i],
+            width: self.widths[i],
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use super::{Approach, Car, CarId, Point, SimulationState, Turn, Vec2, grid_cell_center, grid_junctions, turning_path};
+use crate::config::RouteConfig;
+use serde::{Deserialize, Serialize};
+
+/// Meters before a junction within which cars ask to reserve their movement through it,
+/// beyond the distance they need to stop from town speeds
+pub const RESERVATION_DISTANCE: f32 = 30.0;
+
+/// Share of the half street width cars drive right of the middle of the street
+const LANE_OFFSET: f32 = 0.3;
+
+/// Movement through a junction, by the sides traffic enters and leaves it by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Movement {
+    pub from: Approach,
+    pub to: Approach,
+}
+
+impl Movement {
+    pub fn turn(self) -> Turn {
+        Turn::between(-self.from.direction(), self.to.direction())
+    }
+    
+    /// Whether cars making both movements at once would drive through each other: their
+    /// turning paths, kept to the right side of the street, cross or meet, including
+    /// leaving by the same side. Movements from the same approach never conflict, those
+    /// cars follow each other in.
+    pub fn conflicts_with(self, other: Movement) -> bool {
+        if self.from == other.from {
+            return false;
+        }
+        let (a, b) = (self.lane_path(), other.lane_path());
+        a.windows(2).any(|first| b.windows(2).any(|second| segments_meet(first[0], first[1], second[0], second[1])))
+    }
+    
+    /// Turning path through a junction of unit half size, moved right of the middle of the
+    /// street by each point's direction of travel
+    fn lane_path(self) -> Vec<Point> {
+        let path = turning_path(Point::origin(), 1.0, self.from.direction(), self.to.direction());
+        let last = path.len() - 1;
+        (0..path.len())
+            .map(|i| {
+                let heading = match i {
+                    0 => -self.from.direction(),
+                    i if i == last => self.to.direction(),
+                    i => (path[i + 1] - path[i - 1]).normalize(),
+                };
+                path[i] + Vec2::new(heading.y, -heading.x) * LANE_OFFSET
+            })
+            .collect()
+    }
+}
+
+/// Whether the segments `a0`-`a1` and `b0`-`b1` cross, touch or overlap
+fn segments_meet(a0: Point, a1: Point, b0: Point, b1: Point) -> bool {
+    const EPSILON: f32 = 1e-4;
+    let side = |from: Point, to: Point, point: Point| (to - from).perp(&(point - from));
+    let within = |from: Point, to: Point, point: Point| {
+        point.x >= from.x.min(to.x) - EPSILON && point.x <= from.x.max(to.x) + EPSILON
+            && point.y >= from.y.min(to.y) - EPSILON && point.y <= from.y.max(to.y) + EPSILON
+    };
+    let straddles = |first: f32, second: f32| (first > EPSILON && second < -EPSILON) || (first < -EPSILON && second > EPSILON);
+    
+    let (d1, d2) = (side(b0, b1, a0), side(b0, b1, a1));
+    let (d3, d4) = (side(a0, a1, b0), side(a0, a1, b1));
+    if straddles(d1, d2) && straddles(d3, d4) {
+        return true;
+    }
+    (d1.abs() <= EPSILON && within(b0, b1, a0))
+        || (d2.abs() <= EPSILON && within(b0, b1, a1))
+        || (d3.abs() <= EPSILON && within(a0, a1, b0))
+        || (d4.abs() <= EPSILON && within(a0, a1, b1))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reservation {
+    pub car: CarId,
+    pub movement: Movement,
+}
+
+/// Cars that crossed a junction by one movement
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MovementCount {
+    pub movement: Movement,
+    pub cars: u32,
+}
+
+/// Current state of one grid junction, refreshed every tick and used by physics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JunctionState {
+    pub row: usize,
+    pub col: usize,
+    pub position: Point,                 // center of the junction cell
+    pub half_size: f32,                  // meters from the center to the cell edges
+    pub reservations: Vec<Reservation>,  // cars allowed into the junction, that have yet to leave it
+    pub served: Vec<MovementCount>,      // cars that crossed by each movement since the start
+}
+
+impl JunctionState {
+    pub fn is_reserved(&self, car: CarId) -> bool {
+        self.reservations.iter().any(|reservation| reservation.car == car)
+    }
+    
+    /// Meters until the car enters the junction, zero while crossing it, if its path
+    /// crosses the junction
+    pub fn distance_ahead(&self, car: &Car) -> Option<f32> {
+        car.grid_path.as_ref()?.distance_to_cell(&car.position, &self.position, self.half_size)
+    }
+    
+    /// Movement the car makes through the junction, if its path crosses it
+    pub fn movement(&self, car: &Car) -> Option<Movement> {
+        let (entry, exit) = car.grid_path.as_ref()?.cell_crossing(&car.position, &self.position, self.half_size)?;
+        Some(Movement {
+            from: Approach::of(entry - self.position),
+            to: Approach::of(exit - self.position),
+        })
+    }
+    
+    fn count(&mut self, movement: Movement) {
+        match self.served.iter_mut().find(|count| count.movement == movement) {
+            Some(count) => count.cars += 1,
+            None => self.served.push(MovementCount { movement, cars: 1 }),
+        }
+    }
+}
+
+/// Reserves the paths through grid junctions so cars making conflicting movements never
+/// share one. Cars ask for their movement as they near a junction, nearest first, and get
+/// it when it conflicts with no movement already reserved and no nearer car still
+/// waiting for one. Cars held at a stop or yield sign ask once the sign lets them go.
+pub struct ConflictController {
+    junctions: Vec<JunctionState>,
+}
+
+impl ConflictController {
+    pub fn new(route: &RouteConfig) -> Self {
+        let geometry = &route.route.geometry;
+        let half_size = geometry.cell_size.unwrap_or(20.0) / 2.0;
+        let junctions = grid_junctions(geometry).into_iter()
+            .map(|(row, col)| JunctionState {
+                row,
+                col,
+                position: grid_cell_center(geometry, row, col),
+                half_size,
+                reservations: Vec::new(),
+                served: Vec::new(),
+            })
+            .collect();
+            
+        Self { junctions }
+    }
+    
+    /// Release the movements of cars that left their junction, reserve movements for the
+    /// cars nearing one and publish the junctions into the simulation state
+    pub fn update(&mut self, state: &mut SimulationState) {
+        if self.junctions.is_empty() {
+            return;
+        }
+        
+        let held: Vec<CarId> = state.intersections.iter().flat_map(|intersection| intersection.waiting.iter().copied()).collect();
+        for junction in &mut self.junctions {
+            let mut nearing: Vec<(&Car, f32, Movement)> = state.cars.iter()
+                .filter_map(|car| {
+                    let distance = junction.distance_ahead(car).filter(|&distance| distance <= RESERVATION_DISTANCE)?;
+                    Some((car, distance, junction.movement(car)?))
+                })
+                .collect();
+                
+            let crossed: Vec<Movement> = junction.reservations.iter()
+                .filter(|reservation| !nearing.iter().any(|(car, _, _)| car.id == reservation.car))
+                .map(|reservation| reservation.movement)
+                .collect();
+            junction.reservations.retain(|reservation| nearing.iter().any(|(car, _, _)| car.id == reservation.car));
+            for movement in crossed {
+                junction.count(movement);
+            }
+            
+            nearing.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.id.cmp(&b.0.id)));
+            let mut blocked: Vec<Movement> = Vec::new();
+            for (car, distance, movement) in nearing {
+                if junction.is_reserved(car.id) || (held.contains(&car.id) && distance > 0.0) {
+                    continue;
+                }
+                let free = junction.reservations.iter().all(|reservation| !reservation.movement.conflicts_with(movement))
+                    && blocked.iter().all(|waiting| !waiting.conflicts_with(movement));
+                // Cars already inside, placed there or loaded from an older checkpoint, carry on
+                if free || distance <= 0.0 {
+                    junction.reservations.push(Reservation { car: car.id, movement });
+                } else {
+                    blocked.push(movement);
+                }
+            }
+        }
+        state.junctions = self.junctions.clone();
+    }
+    
+    /// Carry reservations and movement counts over from a loaded checkpoint
+    pub fn restore(&mut self, state: &SimulationState) {
+        for junction in &mut self.junctions {
+            let saved = state.junctions.iter().find(|saved| (saved.row, saved.col) == (junction.row, junction.col));
+            if let Some(saved) = saved {
+                junction.reservations = saved.reservations.clone();
+                junction.served = saved.served.clone();
+            }
+        }
+    }
+}
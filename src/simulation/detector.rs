@@ -0,0 +1,177 @@
+use super::{CarId, SimulationState, StopLine};
+use crate::config::{DetectorConfig, RouteConfig};
+use serde::Serialize;
+use std::collections::HashMap;
+
+const MIN_SPOT_SPEED: f32 = 0.1; // m/s, keeps crawling cars from dominating the harmonic mean
+const MAX_STEP: f32 = 10.0;      // meters a car can move between updates; larger jumps are the ring wrapping
+
+/// Aggregated measurements of one detector over one interval
+#[derive(Debug, Clone, Serialize)]
+pub struct DetectorReading {
+    pub detector: String,
+    pub start: f32, // seconds
+    pub end: f32,   // seconds
+    pub count: u32,
+    pub flow: f32,      // vehicles per hour
+    pub occupancy: f32, // fraction of the interval the detector was covered, per lane
+    pub harmonic_mean_speed: Option<f32>, // m/s over the counted cars, `None` if none passed
+}
+
+impl DetectorReading {
+    /// Vehicles per kilometer over the counted lanes, from flow divided by the harmonic mean
+    /// (space mean) speed. `None` if no car passed.
+    pub fn density(&self) -> Option<f32> {
+        self.harmonic_mean_speed
+            .filter(|speed| *speed > 0.0)
+            .map(|speed| self.flow / (speed * 3.6))
+    }
+}
+
+/// Virtual loop detector that counts cars crossing a line across the road, the way an
+/// induction loop does, and aggregates count, occupancy and spot speeds per interval
+#[derive(Debug, Clone)]
+pub struct LoopDetector {
+    id: String,
+    stop_line: StopLine,
+    heading: f32, // radians, direction of travel of counted traffic
+    lanes: Vec<u32>, // counted lanes (empty = all lanes)
+    lane_count: u32, // lanes the detector spans, for per-lane occupancy
+    interval: f32,
+    last_offsets: HashMap<CarId, f32>, // signed distance to the line at the last update
+    last_time: Option<f32>,
+    interval_start: f32,
+    count: u32,
+    occupied_time: f32, // car-seconds spent over the line this interval
+    inverse_speed_sum: f32,
+}
+
+impl LoopDetector {
+    pub fn new(config: &DetectorConfig, route: &RouteConfig) -> Self {
+        let geometry = &route.route.geometry;
+        let (stop_line, _, heading) = StopLine::for_head(&config.placement(), geometry);
+        let lane_count = if !config.lanes.is_empty() {
+            config.lanes.len() as u32
+        } else if let Some(width) = config.width {
+            ((width / geometry.lane_width).round() as u32).max(1)
+        } else {
+            geometry.lane_count.max(1)
+        };
+        
+        Self {
+            id: config.id.clone(),
+            stop_line,
+            heading,
+            lanes: config.lanes.clone(),
+            lane_count,
+            interval: config.interval,
+            last_offsets: HashMap::new(),
+            last_time: None,
+            interval_start: 0.0,
+            count: 0,
+            occupied_time: 0.0,
+            inverse_speed_sum: 0.0,
+        }
+    }
+    
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    
+    /// Measure one simulation tick. Returns the reading of an interval that ended this tick.
+    pub fn update(&mut self, state: &SimulationState) -> Option<DetectorReading> {
+        // Intervals start with the first tick seen, which may come from a checkpoint.
+        // Time going backwards means the simulation was reset or a checkpoint was loaded.
+        if self.last_time.is_none_or(|last| state.time < last) {
+            self.reset(state.time);
+        }
+        self.last_time = Some(state.time);
+        
+        let mut offsets = HashMap::with_capacity(self.last_offsets.len());
+        for car in &state.cars {
+            if !self.lanes.is_empty() && !self.lanes.contains(&car.current_lane) {
+                continue;
+            }
+            let Some(offset) = self.stop_line.signed_distance(self.heading, car) else {
+                continue;
+            };
+            
+            // Crossed when the center moved from before the line to on or past it
+            let crossed = self.last_offsets.get(&car.id)
+                .is_some_and(|&last| last > 0.0 && offset <= 0.0 && last - offset < MAX_STEP);
+            if crossed {
+                self.count += 1;
+                self.inverse_speed_sum += 1.0 / car.velocity.magnitude().max(MIN_SPOT_SPEED);
+            }
+            if offset.abs() <= car.length / 2.0 {
+                self.occupied_time += state.dt;
+            }
+            offsets.insert(car.id, offset);
+        }
+        self.last_offsets = offsets;
+        
+        if state.time - self.interval_start < self.interval {
+            return None;
+        }
+        let reading = self.reading(state.time);
+        self.interval_start = state.time;
+        self.count = 0;
+        self.occupied_time = 0.0;
+        self.inverse_speed_sum = 0.0;
+        Some(reading)
+    }
+    
+    /// Measurements of the interval in progress up to `now`
+    pub fn reading(&self, now: f32) -> DetectorReading {
+        let duration = (now - self.interval_start).max(f32::EPSILON);
+        DetectorReading {
+            detector: self.id.clone(),
+            start: self.interval_start,
+            end: now,
+            count: self.count,
+            flow: self.count as f32 * 3600.0 / duration,
+            occupancy: (self.occupied_time / (duration * self.lane_count as f32)).min(1.0),
+            harmonic_mean_speed: (self.count > 0).then(|| self.count as f32 / self.inverse_speed_sum),
+        }
+    }
+    
+    /// Forget tracked cars and start a new interval at `time`
+    pub fn reset(&mut self, time: f32) {
+        self.last_offsets.clear();
+        self.interval_start = time;
+        self.count = 0;
+        self.occupied_time = 0.0;
+        self.inverse_speed_sum = 0.0;
+    }
+}
+
+/// All loop detectors configured for a route
+#[derive(Debug, Clone, Default)]
+pub struct DetectorSet {
+    detectors: Vec<LoopDetector>,
+}
+
+impl DetectorSet {
+    pub fn from_route(route: &RouteConfig) -> Self {
+        Self {
+            detectors: route.route.detectors.iter()
+                .map(|config| LoopDetector::new(config, route))
+                .collect(),
+        }
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.detectors.is_empty()
+    }
+    
+    pub fn detectors(&self) -> &[LoopDetector] {
+        &self.detectors
+    }
+    
+    /// Measure one simulation tick. Returns the readings of intervals that ended this tick.
+    pub fn update(&mut self, state: &SimulationState) -> Vec<DetectorReading> {
+        self.detectors.iter_mut()
+            .filter_map(|detector| detector.update(state))
+            .collect()
+    }
+}
@@ -0,0 +1,55 @@
+use super::{CarId, CollisionEvent, Conflict, SignalPhase};
+use serde::{Deserialize, Serialize};
+
+/// Something that happened during a simulation tick, for library users driving external
+/// logging or controllers. The events of the latest tick are kept in
+/// `SimulationState::events` and passed to the observers registered on the backend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SimulationEvent {
+    CarSpawned { car: CarId, entry: String, time: f32 },
+    CarExited { car: CarId, exit: String, time: f32 },
+    LaneChangeStarted { car: CarId, from_lane: u32, to_lane: u32, time: f32 },
+    CollisionDetected(CollisionEvent),
+    /// A driver started changing lanes without seeing `overlooked` in their blind spot
+    BlindSpotMiss { car: CarId, overlooked: CarId, time: f32 },
+    /// Two cars came within `gap` meters of each other, without touching, after a blind
+    /// spot miss
+    NearMiss { car_a: CarId, car_b: CarId, gap: f32, time: f32 },
+    /// Two cars came closer in time than the time-to-collision or post-encroachment
+    /// threshold allows
+    ConflictDetected(Conflict),
+    /// An electric vehicle's charge fell below the low charge threshold; the driver heads
+    /// for `charging_exit` if the route has one
+    BatteryLow { car: CarId, charging_exit: Option<String>, time: f32 },
+    /// An electric vehicle ran out of charge and broke down
+    BatteryDepleted { car: CarId, time: f32 },
+    /// Slow cars lined up into a queue in `lane`, its head `position` meters along the road
+    QueueFormed { queue: u32, lane: u32, position: f32, time: f32 },
+    /// A queue broke up after `duration` seconds, having grown to `max_length` meters
+    QueueDissipated { queue: u32, lane: u32, duration: f32, max_length: f32, time: f32 },
+    SignalPhaseChanged { group: String, phase: SignalPhase, time: f32 },
+}
+
+/// Callback run for every event, in the order the events happened
+pub type EventObserver = Box<dyn FnMut(&SimulationEvent) + Send>;
+
+/// Observers registered on a backend
+#[derive(Default)]
+pub struct EventObservers {
+    observers: Vec<EventObserver>,
+}
+
+impl EventObservers {
+    pub fn add(&mut self, observer: EventObserver) {
+        self.observers.push(observer);
+    }
+    
+    /// Pass each event to every observer
+    pub fn notify(&mut self, events: &[SimulationEvent]) {
+        for event in events {
+            for observer in &mut self.observers {
+                observer(event);
+            }
+        }
+    }
+}
@@ -0,0 +1,508 @@
+use super::{Approach, Point, RoadNetwork};
+use crate::config::{RouteGeometry, EntryPoint, GridPoint, TurningRatio};
+use nalgebra::{Point2, Vector2};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Characters in the grid layout that are not driveable
+const EMPTY_CELL: char = ' ';
+pub const ROUNDABOUT_CENTER: char = 'o';
+const SPAWN_CELL: char = 'S';
+const EXIT_CELL: char = 'X';
+/// Segments a quarter-circle turning path through a cell is made of
+const TURN_STEPS: usize = 6;
+
+/// Path a car follows through a grid route, from its spawn cell to its exit cell
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GridPath {
+    pub waypoints: Vec<Point>,
+    pub next_waypoint: usize,
+    pub exit_id: String,
+}
+
+impl GridPath {
+    pub fn is_complete(&self) -> bool {
+        self.next_waypoint >= self.waypoints.len()
+    }
+    
+    /// Direction of travel towards the next waypoint, if any remain
+    pub fn direction_from(&self, position: &Point) -> Option<Vector2<f32>> {
+        let target = self.waypoints.get(self.next_waypoint)?;
+        let to_target = target - position;
+        if to_target.magnitude() > 1e-3 {
+            Some(to_target.normalize())
+        } else {
+            self.waypoints.get(self.next_waypoint + 1)
+                .map(|next| (next - target).normalize())
+        }
+    }
+    
+    /// Advance `position` along the path by `distance` meters.
+    /// Returns the new position and the index of the next waypoint.
+    pub fn advance(&self, position: Point, distance: f32) -> (Point, usize) {
+        let mut position = position;
+        let mut remaining = distance;
+        let mut next = self.next_waypoint;
+        
+        while remaining > 0.0 && next < self.waypoints.len() {
+            let to_target = self.waypoints[next] - position;
+            let segment = to_target.magnitude();
+            if segment <= remaining {
+                position = self.waypoints[next];
+                remaining -= segment;
+                next += 1;
+            } else {
+                position += to_target / segment * remaining;
+                remaining = 0.0;
+            }
+        }
+        
+        (position, next)
+    }
+    
+    /// Bends in the remaining path within `max_distance`: the distance along the path to
+    /// each waypoint where it changes direction, and the radius of the circle through that
+    /// waypoint and its neighbours
+    pub fn bends_ahead(&self, position: &Point, max_distance: f32) -> Vec<(f32, f32)> {
+        let mut bends = Vec::new();
+        let mut distance = 0.0;
+        let mut previous = *position;
+        for index in self.next_waypoint..self.waypoints.len() {
+            let waypoint = self.waypoints[index];
+            distance += (waypoint - previous).magnitude();
+            if distance > max_distance {
+                break;
+            }
+            previous = waypoint;
+            let (Some(before), Some(after)) = (index.checked_sub(1).map(|before| self.waypoints[before]), self.waypoints.get(index + 1)) else {
+                continue;
+            };
+            let (incoming, outgoing) = (waypoint - before, after - waypoint);
+            let twice_area = incoming.perp(&outgoing).abs();
+            if twice_area > 1e-4 {
+                let radius = incoming.magnitude() * outgoing.magnitude() * (after - before).magnitude() / (2.0 * twice_area);
+                bends.push((distance, radius));
+            }
+        }
+        bends
+    }
+    
+    /// Distance along the remaining path to where it enters the cell of `half_size` meters
+    /// around `center`, zero while crossing it. `None` if the path does not cross the cell
+    /// again.
+    pub fn distance_to_cell(&self, position: &Point, center: &Point, half_size: f32) -> Option<f32> {
+        let inside = |point: &Point| (point - center).amax() <= half_size + 1e-3;
+        let ahead = &self.waypoints[self.next_waypoint.min(self.waypoints.len())..];
+        if inside(position) && ahead.first().is_some_and(inside) {
+            return Some(0.0);
+        }
+        
+        let mut travelled = 0.0;
+        let mut from = *position;
+        for waypoint in ahead {
+            travelled += (waypoint - from).magnitude();
+            if inside(waypoint) {
+                return Some(travelled);
+            }
+            from = *waypoint;
+        }
+        None
+    }
+    
+    /// Points where the path enters and leaves the cell of `half_size` meters around
+    /// `center`, for the next or current crossing of it
+    pub fn cell_crossing(&self, position: &Point, center: &Point, half_size: f32) -> Option<(Point, Point)> {
+        let inside = |point: &Point| (point - center).amax() <= half_size + 1e-3;
+        let next = self.next_waypoint.min(self.waypoints.len());
+        let first = if inside(position) && self.waypoints.get(next).is_some_and(inside) {
+            // Crossing it now, the entry is behind
+            (0..next).rev().take_while(|&index| inside(&self.waypoints[index])).last().unwrap_or(next)
+        } else {
+            (next..self.waypoints.len()).find(|&index| inside(&self.waypoints[index]))?
+        };
+        let last = (first..self.waypoints.len()).take_while(|&index| inside(&self.waypoints[index])).last()?;
+        Some((self.waypoints[first], self.waypoints[last]))
+    }
+    
+    /// Distance along the remaining path to `point`, if the point lies within
+    /// `lateral_tolerance` of the path and no further than `max_distance` ahead
+    pub fn distance_along_to(&self, position: &Point, point: &Point, lateral_tolerance: f32, max_distance: f32) -> Option<f32> {
+        let mut travelled = 0.0;
+        let mut segment_start = *position;
+        
+        for waypoint in &self.waypoints[self.next_waypoint.min(self.waypoints.len())..] {
+            let segment = waypoint - segment_start;
+            let length = segment.magnitude();
+            if length > 1e-3 {
+                let direction = segment / length;
+                let to_point = point - segment_start;
+                let along = to_point.dot(&direction);
+                if along > 0.0 && along <= length {
+                    let lateral = (to_point - direction * along).magnitude();
+                    if lateral <= lateral_tolerance {
+                        return Some(travelled + along);
+                    }
+                }
+            }
+            
+            travelled += length;
+            if travelled > max_distance {
+                break;
+            }
+            segment_start = *waypoint;
+        }
+        
+        None
+    }
+}
+
+/// Cells of a path from a spawn point, with the weight of the exit it leads to
+pub type WeightedPath = (Vec<(usize, usize)>, f32);
+
+/// Next cell the paths in a branch take, with the indices of those paths
+type Branch = (Option<(usize, usize)>, Vec<usize>);
+
+/// Road network derived from a grid route layout.
+///
+/// Every non-blank cell other than a roundabout center is driveable and connects to its
+/// orthogonal neighbors. Cells surrounding an 'o' form a one-way roundabout circulating
+/// counter-clockwise, spawn cells can only be left and exit cells can only be entered.
+/// Each cell is a node of the underlying road graph, with spawn and exit points named by id.
+/// Paths through it turn on quarter circles inside the cells where they change direction.
+#[derive(Debug, Clone)]
+pub struct GridNetwork {
+    graph: RoadNetwork,
+    cells: Vec<(usize, usize)>, // Row and column of each graph node
+    half_cell: f32,
+    spawn_points: Vec<GridPoint>,
+    exit_points: Vec<GridPoint>,
+}
+
+impl GridNetwork {
+    /// Build the network from a grid geometry. Returns `None` for non-grid geometries.
+    pub fn from_geometry(geometry: &RouteGeometry) -> Option<Self> {
+        if geometry.geometry_type != "grid" {
+            return None;
+        }
+        let grid = geometry.grid.as_ref()?;
+        
+        let mut graph = RoadNetwork::new();
+        let mut cells = Vec::new();
+        let mut cell_index = HashMap::new();
+        let mut centers = Vec::new();
+        
+        for (row, cols) in grid.iter().enumerate() {
+            for col in 0..cols.len() {
+                match cell_char(grid, row, col) {
+                    Some(ROUNDABOUT_CENTER) => centers.push(grid_cell_center(geometry, row, col)),
+                    Some(_) => {
+                        let node = graph.add_node(grid_cell_center(geometry, row, col));
+                        cell_index.insert((row, col), node);
+                        cells.push((row, col));
+                    }
+                    None => {}
+                }
+            }
+        }
+        
+        for (from, &(row, col)) in cells.iter().enumerate() {
+            if cell_char(grid, row, col) == Some(EXIT_CELL) {
+                continue;
+            }
+            
+            for (dr, dc) in [(-1i32, 0i32), (1, 0), (0, -1), (0, 1)] {
+                let neighbor = (row as i32 + dr, col as i32 + dc);
+                if neighbor.0 < 0 || neighbor.1 < 0 {
+                    continue;
+                }
+                let neighbor = (neighbor.0 as usize, neighbor.1 as usize);
+                let Some(&to) = cell_index.get(&neighbor) else {
+                    continue;
+                };
+                if cell_char(grid, neighbor.0, neighbor.1) == Some(SPAWN_CELL) {
+                    continue;
+                }
+                
+                // Moves between two cells of the same roundabout must circulate counter-clockwise
+                let wrong_way = centers.iter().any(|center| {
+                    let a = graph.position(from) - center;
+                    let b = graph.position(to) - center;
+                    let ring_distance = geometry.cell_size.unwrap_or(20.0) * 1.5;
+                    a.amax() < ring_distance && b.amax() < ring_distance && a.perp(&b) < 0.0
+                });
+                if !wrong_way {
+                    graph.add_edge(from, to);
+                }
+            }
+        }
+        
+        let named_points = geometry.spawn_points.iter().flatten().chain(geometry.exit_points.iter().flatten());
+        for point in named_points {
+            if let Some(&node) = cell_index.get(&(point.row, point.col)) {
+                graph.name_node(&point.id, node);
+            }
+        }
+        
+        Some(Self {
+            graph,
+            cells,
+            half_cell: geometry.cell_size.unwrap_or(20.0) / 2.0,
+            spawn_points: geometry.spawn_points.clone().unwrap_or_default(),
+            exit_points: geometry.exit_points.clone().unwrap_or_default(),
+        })
+    }
+    
+    pub fn spawn_points(&self) -> &[GridPoint] {
+        &self.spawn_points
+    }
+    
+    pub fn exit_points(&self) -> &[GridPoint] {
+        &self.exit_points
+    }
+    
+    /// Exit points reachable from the given spawn point
+    pub fn reachable_exits(&self, spawn: &GridPoint) -> Vec<&GridPoint> {
+        self.exit_points.iter()
+            .filter(|exit| self.shortest_path(spawn, exit).is_some())
+            .collect()
+    }
+    
+    /// Plan a path from a spawn point to an exit point, from cell center to cell center with
+    /// the turning path through each cell in between
+    pub fn plan_path(&self, spawn: &GridPoint, exit: &GridPoint) -> Option<GridPath> {
+        let nodes = self.shortest_path(spawn, exit)?;
+        let position = |index: usize| self.graph.position(nodes[index]);
+        let side = |index: usize, towards: usize| (position(towards) - position(index)).normalize();
+        
+        let mut waypoints = vec![position(0)];
+        for index in 1..nodes.len() {
+            if index + 1 < nodes.len() {
+                waypoints.extend(turning_path(position(index), self.half_cell, side(index, index - 1), side(index, index + 1)));
+            } else {
+                waypoints.push(position(index) + side(index, index - 1) * self.half_cell);
+                waypoints.push(position(index));
+            }
+        }
+        waypoints.dedup_by(|a, b| (*a - *b).magnitude() < 1e-3);
+        
+        Some(GridPath {
+            waypoints,
+            next_waypoint: 1, // Cars spawn on the first waypoint
+            exit_id: exit.id.clone(),
+        })
+    }
+    
+    /// Cells from a spawn point to an exit point along the path cars take
+    pub fn path_cells(&self, spawn: &GridPoint, exit: &GridPoint) -> Option<Vec<(usize, usize)>> {
+        Some(self.shortest_path(spawn, exit)?.into_iter().map(|node| self.cells[node]).collect())
+    }
+    
+    /// Chance of taking each of `paths` (cells from one spawn point to each exit, with the
+    /// exit's weight), so that where they split at a junction with turning ratios for the
+    /// approach, the share of cars taking each movement follows the ratios. Elsewhere paths
+    /// are chosen in proportion to the weights of the exits along them.
+    pub fn turning_shares(&self, paths: &[WeightedPath], ratios: &[TurningRatio]) -> Vec<f32> {
+        let mut shares = vec![0.0; paths.len()];
+        self.split_paths(paths, (0..paths.len()).collect(), 0, 1.0, ratios, &mut shares);
+        shares
+    }
+    
+    fn split_paths(&self, paths: &[WeightedPath], members: Vec<usize>, depth: usize, share: f32, ratios: &[TurningRatio], shares: &mut [f32]) {
+        if let [only] = members[..] {
+            shares[only] = share;
+            return;
+        }
+        
+        // Follow the paths until they split
+        let mut depth = depth;
+        let mut branches: Vec<Branch> = Vec::new();
+        loop {
+            branches.clear();
+            for &member in &members {
+                let next = paths[member].0.get(depth + 1).copied();
+                match branches.iter_mut().find(|(cell, _)| *cell == next) {
+                    Some((_, branch)) => branch.push(member),
+                    None => branches.push((next, vec![member])),
+                }
+            }
+            match branches[..] {
+                [(Some(_), _)] => depth += 1,
+                _ => break,
+            }
+        }
+        
+        let path = &paths[members[0]].0;
+        let at = path[depth];
+        let ratio = depth.checked_sub(1).and_then(|previous| {
+            let approach = Approach::of(self.center(path[previous]) - self.center(at));
+            ratios.iter().find(|ratio| (ratio.row, ratio.col) == at && ratio.approach == approach.name())
+        });
+        let weight = |(next, branch): &Branch| {
+            let exits = branch.iter().map(|&member| paths[member].1).sum::<f32>();
+            match (ratio, next) {
+                (Some(ratio), Some(next)) => {
+                    let incoming = self.center(at) - self.center(path[depth - 1]);
+                    Turn::between(incoming, self.center(*next) - self.center(at)).share(ratio)
+                }
+                _ => exits,
+            }
+        };
+        let mut weights: Vec<f32> = branches.iter().map(weight).collect();
+        if weights.iter().sum::<f32>() <= 0.0 {
+            weights = branches.iter().map(|(_, branch)| branch.iter().map(|&member| paths[member].1).sum()).collect();
+        }
+        let total: f32 = weights.iter().sum();
+        
+        for ((_, branch), weight) in branches.into_iter().zip(weights) {
+            let branch_share = if total > 0.0 { share * weight / total } else { 0.0 };
+            self.split_paths(paths, branch, depth + 1, branch_share, ratios, shares);
+        }
+    }
+    
+    fn center(&self, cell: (usize, usize)) -> Point {
+        let node = self.cells.iter().position(|&other| other == cell).expect("cell of the network");
+        self.graph.position(node)
+    }
+    
+    fn shortest_path(&self, spawn: &GridPoint, exit: &GridPoint) -> Option<Vec<usize>> {
+        let start = self.graph.node(&spawn.id)?;
+        let goal = self.graph.node(&exit.id)?;
+        self.graph.shortest_path(start, goal)
+    }
+}
+
+/// First character of a grid cell, `None` for blank cells
+pub fn cell_char(grid: &[Vec<String>], row: usize, col: usize) -> Option<char> {
+    let c = grid.get(row)?.get(col)?.chars().next().unwrap_or(EMPTY_CELL);
+    if c == EMPTY_CELL {
+        None
+    } else {
+        Some(c)
+    }
+}
+
+/// World position of a cell center. Row 0 is the northern edge and the grid is centered on the route center.
+pub fn grid_cell_center(geometry: &RouteGeometry, row: usize, col: usize) -> Point {
+    let cell_size = geometry.cell_size.unwrap_or(20.0);
+    let rows = geometry.grid.as_ref().map(|grid| grid.len()).unwrap_or(0);
+    let cols = geometry.grid.as_ref().and_then(|grid| grid.first()).map(|row| row.len()).unwrap_or(0);
+    
+    let x = geometry.center_x + (col as f32 - (cols as f32 - 1.0) / 2.0) * cell_size;
+    let y = geometry.center_y + ((rows as f32 - 1.0) / 2.0 - row as f32) * cell_size;
+    Point2::new(x, y)
+}
+
+/// Movement through an intersection relative to the direction of travel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Turn {
+    Left,
+    Through,
+    Right,
+}
+
+impl Turn {
+    /// Movement of traffic arriving in the direction `incoming` and leaving in `outgoing`
+    pub fn between(incoming: Vector2<f32>, outgoing: Vector2<f32>) -> Self {
+        let cross = incoming.perp(&outgoing);
+        if cross > 1e-3 {
+            Turn::Left
+        } else if cross < -1e-3 {
+            Turn::Right
+        } else {
+            Turn::Through
+        }
+    }
+    
+    /// This movement's share in turning ratios
+    pub fn share(self, ratio: &TurningRatio) -> f32 {
+        match self {
+            Turn::Left => ratio.left,
+            Turn::Through => ratio.through,
+            Turn::Right => ratio.right,
+        }
+    }
+}
+
+/// Waypoints through a cell for traffic entering by the side `from` and leaving by the side
+/// `to`, both unit vectors from the cell center: straight through the center, or a quarter
+/// circle around the corner between the two sides when turning
+pub fn turning_path(center: Point, half_size: f32, from: Vector2<f32>, to: Vector2<f32>) -> Vec<Point> {
+    if from.perp(&to).abs() < 1e-3 {
+        return vec![center + from * half_size, center, center + to * half_size];
+    }
+    let corner = center + (from + to) * half_size;
+    (0..=TURN_STEPS)
+        .map(|step| {
+            let angle = step as f32 / TURN_STEPS as f32 * std::f32::consts::FRAC_PI_2;
+            corner - (to * angle.cos() + from * angle.sin()) * half_size
+        })
+        .collect()
+}
+
+/// Cells where three or more streets meet, other than spawn and exit cells and cells on a
+/// roundabout, which circulating traffic keeps moving through
+pub fn grid_junctions(geometry: &RouteGeometry) -> Vec<(usize, usize)> {
+    let Some(grid) = geometry.grid.as_ref().filter(|_| geometry.geometry_type == "grid") else {
+        return Vec::new();
+    };
+    let cell = |row: i32, col: i32| if row < 0 || col < 0 { None } else { cell_char(grid, row as usize, col as usize) };
+    
+    let mut junctions = Vec::new();
+    for (row, cols) in grid.iter().enumerate() {
+        for col in 0..cols.len() {
+            let (r, c) = (row as i32, col as i32);
+            if matches!(cell(r, c), None | Some(ROUNDABOUT_CENTER) | Some(SPAWN_CELL) | Some(EXIT_CELL)) {
+                continue;
+            }
+            let streets = [(-1, 0), (1, 0), (0, -1), (0, 1)].iter()
+                .filter(|&&(dr, dc)| cell(r + dr, c + dc).is_some_and(|neighbor| neighbor != ROUNDABOUT_CENTER))
+                .count();
+            let on_roundabout = (-1..=1).any(|dr| (-1..=1).any(|dc| cell(r + dr, c + dc) == Some(ROUNDABOUT_CENTER)));
+            if streets >= 3 && !on_roundabout {
+                junctions.push((row, col));
+            }
+        }
+    }
+    junctions
+}
+
+/// Heading in degrees (0 = east, counter-clockwise) a car takes when leaving a spawn cell
+pub fn grid_spawn_heading(geometry: &RouteGeometry, spawn: &GridPoint) -> Option<f32> {
+    let grid = geometry.grid.as_ref()?;
+    let origin = grid_cell_center(geometry, spawn.row, spawn.col);
+    
+    [(-1i32, 0i32), (1, 0), (0, -1), (0, 1)].iter()
+        .filter_map(|&(dr, dc)| {
+            let row = spawn.row as i32 + dr;
+            let col = spawn.col as i32 + dc;
+            if row < 0 || col < 0 {
+                return None;
+            }
+            match cell_char(grid, row as usize, col as usize) {
+                Some(ROUNDABOUT_CENTER) | None => None,
+                Some(_) => Some(grid_cell_center(geometry, row as usize, col as usize)),
+            }
+        })
+        .map(|neighbor| {
+            let direction = neighbor - origin;
+            let degrees = direction.y.atan2(direction.x).to_degrees();
+            if degrees < 0.0 { degrees + 360.0 } else { degrees }
+        })
+        .next()
+}
+
+/// Match a route entry to the grid spawn point whose departure heading is closest to the entry angle
+pub fn grid_spawn_for_entry<'a>(geometry: &'a RouteGeometry, entry: &EntryPoint) -> Option<&'a GridPoint> {
+    geometry.spawn_points.as_ref()?
+        .iter()
+        .filter_map(|spawn| grid_spawn_heading(geometry, spawn).map(|heading| (spawn, heading)))
+        .min_by(|(_, a), (_, b)| {
+            angle_difference(*a, entry.angle).total_cmp(&angle_difference(*b, entry.angle))
+        })
+        .map(|(spawn, _)| spawn)
+}
+
+fn angle_difference(a: f32, b: f32) -> f32 {
+    let diff = (a - b).abs() % 360.0;
+    if diff > 180.0 { 360.0 - diff } else { diff }
+}
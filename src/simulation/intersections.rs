@@ -0,0 +1,338 @@
+use super::{Car, CarId, Point, SimulationState, Turn, Vec2, cell_char, grid_cell_center, ROUNDABOUT_CENTER};
+use crate::config::RouteConfig;
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+
+/// Meters before its stop line within which a car takes part at an intersection
+const APPROACH_DISTANCE: f32 = 60.0;
+/// Meters short of the stop line within which a car waits at it
+const STOP_LINE_REACH: f32 = 4.0;
+/// m/s below which a car has come to a full stop
+const STOPPED_SPEED: f32 = 0.5;
+/// Seconds within which cars stopping at the intersection count as arriving together
+const ARRIVAL_TIE: f32 = 1.0;
+/// Seconds turning left adds to the critical gap, crossing the path of both major directions
+const LEFT_TURN_GAP: f32 = 1.0;
+/// Seconds turning right takes off the critical gap, only merging with the near side
+const RIGHT_TURN_GAP: f32 = 0.5;
+
+/// Side of an intersection traffic arrives from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Approach {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Approach {
+    const ALL: [Approach; 4] = [Approach::North, Approach::South, Approach::East, Approach::West];
+    
+    pub fn name(self) -> &'static str {
+        match self {
+            Approach::North => "north",
+            Approach::South => "south",
+            Approach::East => "east",
+            Approach::West => "west",
+        }
+    }
+    
+    /// Unit vector from the intersection center towards this side
+    pub fn direction(self) -> Vec2 {
+        match self {
+            Approach::North => Vector2::new(0.0, 1.0),
+            Approach::South => Vector2::new(0.0, -1.0),
+            Approach::East => Vector2::new(1.0, 0.0),
+            Approach::West => Vector2::new(-1.0, 0.0),
+        }
+    }
+    
+    /// Side an offset from the intersection center points to
+    pub fn of(offset: Vec2) -> Self {
+        if offset.x.abs() > offset.y.abs() {
+            if offset.x > 0.0 { Approach::East } else { Approach::West }
+        } else if offset.y > 0.0 {
+            Approach::North
+        } else {
+            Approach::South
+        }
+    }
+    
+    /// Side on the right of drivers arriving from this one
+    fn to_the_right(self) -> Self {
+        match self {
+            Approach::North => Approach::West,
+            Approach::West => Approach::South,
+            Approach::South => Approach::East,
+            Approach::East => Approach::North,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SignType {
+    Stop,
+    Yield,
+}
+
+/// Sign facing the traffic of one approach
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntersectionSign {
+    pub approach: Approach,
+    pub sign: SignType,
+    pub position: Point, // beside the stop line, on the drivers' right
+    pub heading: f32,    // radians, direction of travel of the traffic it faces
+}
+
+/// Current state of one sign-controlled grid intersection, refreshed every tick and used
+/// by physics and rendering
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntersectionState {
+    pub row: usize,
+    pub col: usize,
+    pub position: Point,            // center of the intersection cell
+    pub stop_distance: f32,         // meters from the center to the stop lines
+    pub signs: Vec<IntersectionSign>,
+    pub stopped: Vec<(CarId, f32)>, // cars that came to a stop at a stop sign and when, first arrival first
+    pub cleared: Vec<CarId>,        // cars given the right of way that have yet to cross
+    pub waiting: Vec<CarId>,        // cars that have to stop at their stop line for now
+}
+
+/// A car whose path crosses the intersection, as it approaches
+struct Arrival<'a> {
+    car: &'a Car,
+    approach: Approach,
+    turn: Turn,
+    distance: f32, // meters to the stop line, zero once past it
+}
+
+impl IntersectionState {
+    fn sign(&self, approach: Approach) -> Option<SignType> {
+        self.signs.iter().find(|sign| sign.approach == approach).map(|sign| sign.sign)
+    }
+    
+    /// Meters the car has to go to its stop line, zero once past it, if its path still
+    /// crosses the intersection
+    pub fn distance_to_stop_line(&self, car: &Car) -> Option<f32> {
+        car.grid_path.as_ref()?.distance_to_cell(&car.position, &self.position, self.stop_distance)
+    }
+    
+    fn arrival<'a>(&self, car: &'a Car) -> Option<Arrival<'a>> {
+        let path = car.grid_path.as_ref()?;
+        let distance = path.distance_to_cell(&car.position, &self.position, self.stop_distance)?;
+        if distance > APPROACH_DISTANCE {
+            return None;
+        }
+        let (entry, exit) = path.cell_crossing(&car.position, &self.position, self.stop_distance)?;
+        let turn = Turn::between(self.position - entry, exit - self.position);
+        Some(Arrival { car, approach: Approach::of(entry - self.position), turn, distance })
+    }
+    
+    /// Whether any car is inside the intersection cell
+    fn occupied(&self, cars: &[Car]) -> bool {
+        cars.iter().any(|car| {
+            let offset = car.position - self.position;
+            offset.x.abs() < self.stop_distance && offset.y.abs() < self.stop_distance
+        })
+    }
+    
+    fn stopped_at(&self, id: CarId) -> Option<f32> {
+        self.stopped.iter().find(|(stopped, _)| *stopped == id).map(|&(_, time)| time)
+    }
+}
+
+struct Intersection {
+    state: IntersectionState,
+    critical_gap: f32,
+}
+
+impl Intersection {
+    /// `approaching` are the cars for which this is the next intersection on their path
+    fn update(&mut self, cars: &[Car], approaching: &[&Car], time: f32) {
+        let state = &mut self.state;
+        let arrivals: Vec<Arrival> = approaching.iter().filter_map(|car| state.arrival(car)).collect();
+        
+        // Cars that left the intersection have crossed it
+        let approaching = |id: CarId| arrivals.iter().any(|arrival| arrival.car.id == id);
+        state.stopped.retain(|&(id, _)| approaching(id));
+        state.cleared.retain(|&id| approaching(id));
+        for arrival in &arrivals {
+            let at_stop_sign = state.sign(arrival.approach) == Some(SignType::Stop)
+                && arrival.distance <= STOP_LINE_REACH
+                && arrival.car.velocity.magnitude() < STOPPED_SPEED;
+            if at_stop_sign && state.stopped_at(arrival.car.id).is_none() {
+                state.stopped.push((arrival.car.id, time));
+            }
+        }
+        
+        // One car at a time crosses from the signed approaches. Major traffic counts even
+        // while it is still crossing an earlier intersection.
+        if state.cleared.is_empty() && !state.occupied(cars) {
+            let majors: Vec<Arrival> = cars.iter()
+                .filter_map(|car| state.arrival(car))
+                .filter(|arrival| state.sign(arrival.approach).is_none())
+                .collect();
+            if let Some(id) = self.next_to_go(&arrivals, &majors) {
+                self.state.cleared.push(id);
+            }
+        }
+        
+        let state = &mut self.state;
+        state.waiting = arrivals.iter()
+            .filter(|arrival| state.sign(arrival.approach).is_some() && arrival.distance > 0.0)
+            .filter(|arrival| !state.cleared.contains(&arrival.car.id))
+            .map(|arrival| arrival.car.id)
+            .collect();
+    }
+    
+    /// The car on a signed approach to let go next. Cars at stop signs go in the order they
+    /// stopped, giving way to the car on their right when they stopped together, and cars
+    /// at yield signs nearest first. Either goes only if no car on a major approach reaches
+    /// the intersection within the critical gap for its turn after it.
+    fn next_to_go(&self, arrivals: &[Arrival], majors: &[Arrival]) -> Option<CarId> {
+        let state = &self.state;
+        let mut candidates: Vec<(&Arrival, f32)> = arrivals.iter()
+            .filter_map(|arrival| match state.sign(arrival.approach)? {
+                SignType::Stop => Some((arrival, state.stopped_at(arrival.car.id)?)),
+                SignType::Yield => {
+                    // Only the front car of each approach can go
+                    let front = arrivals.iter()
+                        .filter(|other| other.approach == arrival.approach)
+                        .all(|other| other.distance >= arrival.distance);
+                    front.then_some((arrival, f32::INFINITY))
+                }
+            })
+            .collect();
+        candidates.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.distance.total_cmp(&b.0.distance)));
+        
+        // Of cars that stopped together, the one with nobody on their right goes first
+        if let Some(&(_, first_stop)) = candidates.first().filter(|(_, stopped)| stopped.is_finite()) {
+            let tied: Vec<Approach> = candidates.iter()
+                .filter(|(_, stopped)| *stopped - first_stop < ARRIVAL_TIE)
+                .map(|(arrival, _)| arrival.approach)
+                .collect();
+            if let Some(index) = candidates.iter().position(|(arrival, stopped)| {
+                *stopped - first_stop < ARRIVAL_TIE && !tied.contains(&arrival.approach.to_the_right())
+            }) {
+                let yielded_to = candidates.remove(index);
+                candidates.insert(0, yielded_to);
+            }
+        }
+        
+        candidates.into_iter()
+            .map(|(arrival, _)| arrival)
+            .find(|arrival| {
+                let speed = arrival.car.velocity.magnitude();
+                let arrives_in = if arrival.distance <= STOP_LINE_REACH { 0.0 } else { arrival.distance / speed.max(0.1) };
+                let critical_gap = match arrival.turn {
+                    Turn::Left => self.critical_gap + LEFT_TURN_GAP,
+                    Turn::Through => self.critical_gap,
+                    Turn::Right => (self.critical_gap - RIGHT_TURN_GAP).max(0.0),
+                };
+                majors.iter().all(|major| {
+                    let major_arrives_in = major.distance.max(0.0) / major.car.velocity.magnitude().max(0.1);
+                    major_arrives_in >= arrives_in + critical_gap
+                })
+            })
+            .map(|arrival| arrival.car.id)
+    }
+}
+
+/// Stop and yield signs at the intersections of a grid route. Cars on a signed approach
+/// wait at the stop line, after coming to a full stop at stop signs, until the
+/// intersection is clear, it is their turn and there is a long enough gap in the traffic
+/// on the major approaches.
+pub struct IntersectionController {
+    intersections: Vec<Intersection>,
+}
+
+impl IntersectionController {
+    pub fn new(route: &RouteConfig) -> Self {
+        let geometry = &route.route.geometry;
+        let Some(grid) = geometry.grid.as_ref().filter(|_| geometry.geometry_type == "grid") else {
+            return Self { intersections: Vec::new() };
+        };
+        let half_cell = geometry.cell_size.unwrap_or(20.0) / 2.0;
+        
+        let intersections = route.route.intersections.iter()
+            .map(|config| {
+                let position = grid_cell_center(geometry, config.row, config.col);
+                let signs = Approach::ALL.iter()
+                    .filter(|approach| {
+                        let direction = approach.direction();
+                        let row = config.row as i32 - direction.y as i32;
+                        let col = config.col as i32 + direction.x as i32;
+                        row >= 0 && col >= 0 && cell_char(grid, row as usize, col as usize).is_some_and(|cell| cell != ROUNDABOUT_CENTER)
+                    })
+                    .filter_map(|&approach| {
+                        let sign = match config.control.as_str() {
+                            "all_way_stop" => SignType::Stop,
+                            _ if config.major.iter().any(|major| major == approach.name()) => return None,
+                            "yield" => SignType::Yield,
+                            _ => SignType::Stop,
+                        };
+                        let heading = -approach.direction();
+                        let right = Vector2::new(heading.y, -heading.x);
+                        Some(IntersectionSign {
+                            approach,
+                            sign,
+                            position: position + approach.direction() * half_cell + right * geometry.lane_width,
+                            heading: heading.y.atan2(heading.x),
+                        })
+                    })
+                    .collect();
+                    
+                Intersection {
+                    state: IntersectionState {
+                        row: config.row,
+                        col: config.col,
+                        position,
+                        stop_distance: half_cell,
+                        signs,
+                        stopped: Vec::new(),
+                        cleared: Vec::new(),
+                        waiting: Vec::new(),
+                    },
+                    critical_gap: config.critical_gap,
+                }
+            })
+            .collect();
+            
+        Self { intersections }
+    }
+    
+    /// Let cars through the intersections and publish them into the simulation state
+    pub fn update(&mut self, state: &mut SimulationState) {
+        if self.intersections.is_empty() {
+            return;
+        }
+        
+        // Cars only take part at the next intersection on their path
+        let mut approaching: Vec<Vec<&Car>> = vec![Vec::new(); self.intersections.len()];
+        for car in &state.cars {
+            let next = self.intersections.iter().enumerate()
+                .filter_map(|(index, intersection)| Some((index, intersection.state.distance_to_stop_line(car)?)))
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((index, _)) = next {
+                approaching[index].push(car);
+            }
+        }
+        for (intersection, approaching) in self.intersections.iter_mut().zip(&approaching) {
+            intersection.update(&state.cars, approaching, state.time);
+        }
+        state.intersections = self.intersections.iter().map(|intersection| intersection.state.clone()).collect();
+    }
+    
+    /// Carry who stopped when and who has been let go over from a loaded checkpoint
+    pub fn restore(&mut self, state: &SimulationState) {
+        for intersection in &mut self.intersections {
+            let saved = state.intersections.iter()
+                .find(|saved| saved.row == intersection.state.row && saved.col == intersection.state.col);
+            if let Some(saved) = saved {
+                intersection.state.stopped = saved.stopped.clone();
+                intersection.state.cleared = saved.cleared.clone();
+                intersection.state.waiting = saved.waiting.clone();
+            }
+        }
+    }
+}
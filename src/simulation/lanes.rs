@@ -0,0 +1,93 @@
+use super::{SimulationEvent, SimulationState};
+use std::collections::VecDeque;
+
+/// Seconds of lane changes the per-lane rates are averaged over
+pub const LANE_CHANGE_WINDOW: f32 = 60.0;
+
+/// How one lane is being used right now
+#[derive(Debug, Clone, PartialEq)]
+pub struct LaneStats {
+    pub lane: u32, // 1-based, as in route.toml
+    pub cars: u32,
+    pub mean_speed: f32, // m/s, 0 with no cars
+    pub changes_in: f32, // lane changes into the lane per minute
+    pub changes_out: f32, // lane changes out of it per minute
+}
+
+/// Follows lane changes over a sliding window so the lane table can show how traffic
+/// balances across lanes, alongside the counts and speeds of the current state
+#[derive(Debug, Clone)]
+pub struct LaneUsage {
+    lane_count: u32,
+    window: f32,
+    changes: VecDeque<(f32, u32, u32)>, // (time, from lane, to lane)
+    start_time: Option<f32>, // first recorded tick, rates cover less than the window until it is full
+}
+
+impl LaneUsage {
+    /// Report at least `lane_count` lanes, more if cars are seen in higher ones
+    pub fn new(lane_count: u32, window: f32) -> Self {
+        Self {
+            lane_count,
+            window,
+            changes: VecDeque::new(),
+            start_time: None,
+        }
+    }
+    
+    /// Take in the lane changes started this tick
+    pub fn record(&mut self, state: &SimulationState) {
+        // A jump back in time is a reset or a loaded checkpoint, start over
+        if self.changes.back().is_some_and(|&(time, _, _)| time > state.time) {
+            self.changes.clear();
+            self.start_time = None;
+        }
+        self.start_time.get_or_insert(state.time);
+        
+        for event in &state.events {
+            if let SimulationEvent::LaneChangeStarted { from_lane, to_lane, time, .. } = event {
+                self.changes.push_back((*time, *from_lane, *to_lane));
+            }
+        }
+        while self.changes.front().is_some_and(|&(time, _, _)| time < state.time - self.window) {
+            self.changes.pop_front();
+        }
+    }
+    
+    /// Per-lane figures for `state`, lane 1 first
+    pub fn lanes(&self, state: &SimulationState) -> Vec<LaneStats> {
+        let highest = state.cars.iter().map(|car| car.current_lane)
+            .chain(self.changes.iter().flat_map(|&(_, from, to)| [from, to]))
+            .max()
+            .unwrap_or(0);
+        let mut lanes: Vec<LaneStats> = (1..=self.lane_count.max(highest))
+            .map(|lane| LaneStats { lane, cars: 0, mean_speed: 0.0, changes_in: 0.0, changes_out: 0.0 })
+            .collect();
+        let index = |lane: u32| (lane as usize).checked_sub(1);
+        
+        for car in &state.cars {
+            if let Some(stats) = index(car.current_lane).and_then(|i| lanes.get_mut(i)) {
+                stats.cars += 1;
+                stats.mean_speed += car.velocity.magnitude();
+            }
+        }
+        
+        let elapsed = self.start_time.map_or(0.0, |start| (state.time - start).min(self.window));
+        let per_minute = if elapsed > 0.0 { 60.0 / elapsed } else { 0.0 };
+        for &(_, from, to) in &self.changes {
+            if let Some(stats) = index(from).and_then(|i| lanes.get_mut(i)) {
+                stats.changes_out += per_minute;
+            }
+            if let Some(stats) = index(to).and_then(|i| lanes.get_mut(i)) {
+                stats.changes_in += per_minute;
+            }
+        }
+        
+        for stats in &mut lanes {
+            if stats.cars > 0 {
+                stats.mean_speed /= stats.cars as f32;
+            }
+        }
+        lanes
+    }
+}
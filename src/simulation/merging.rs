@@ -0,0 +1,213 @@
+use super::{Car, CarId, NameTable, Point, SimulationState, Vec2};
+use crate::config::{CarsConfig, EntryPoint, RouteConfig};
+use serde::{Deserialize, Serialize};
+
+/// Meters either side of the merge point within which mainline cars take part in a merge
+const MERGE_ZONE_LENGTH: f32 = 100.0;
+/// Speed a car joins at with no traffic ahead to match, the usual entrance ramp speed
+const JOIN_SPEED: f32 = 15.6;
+
+/// A car waiting at an entry for a gap in the traffic, refreshed every tick. The physics
+/// engine reads it so the mainline driver yielding to the car eases off before the merge
+/// point instead of being slowed at once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeState {
+    pub entry_id: String,
+    pub lane: u32,
+    pub position: Point,        // merge point
+    pub direction: Vec2,        // direction of travel at the merge point
+    pub center: Option<Point>,  // center of the ring for ring routes, distances follow the lane round
+    pub since: f32,             // simulation time the car arrived at the entry
+    pub speed: f32,             // m/s the car will join at, matching the gap it targets
+    pub clearance: f32,         // meters kept clear ahead of the car and behind it
+    pub leader: Option<CarId>,   // mainline car the waiting car will follow
+    pub yielding: Option<CarId>, // mainline car opening the gap behind it
+}
+
+impl MergeState {
+    /// Meters `position` is past the merge point along the lane, negative before it
+    pub fn distance_past(&self, position: Point) -> f32 {
+        let Some(center) = self.center else {
+            return (position - self.position).dot(&self.direction);
+        };
+        let angle = |point: Point| (point - center).y.atan2((point - center).x);
+        let turn = (angle(position) - angle(self.position) + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI;
+        turn * (self.position - center).magnitude()
+    }
+    
+    /// Meters a driver `past` the merge point can still close up before the gap it leaves
+    /// for the waiting car gets too small, both behind the merge point and behind the car
+    /// ahead `leader_past` it. Negative once it is too close.
+    pub fn room(&self, past: f32, leader_past: Option<f32>) -> f32 {
+        let room = -past - self.clearance;
+        leader_past.map_or(room, |leader_past| room.min(leader_past - past - 2.0 * self.clearance))
+    }
+}
+
+struct MergeZone {
+    merge: MergeState,
+    lane_width: f32,
+    waiting: bool,
+    last_yielded: Option<CarId>, // mainline car that let the previous car in
+}
+
+impl MergeZone {
+    /// Meters a mainline car is past the merge point, if it is in the merge zone
+    fn offset(&self, car: &Car) -> Option<f32> {
+        if car.current_lane != self.merge.lane || car.exit_ramp.is_some() {
+            return None;
+        }
+        let past = self.merge.distance_past(car.position);
+        let beside = self.merge.center.is_some() || {
+            let offset = car.position - self.merge.position;
+            (offset - self.merge.direction * past).magnitude() < self.lane_width
+        };
+        (beside && past.abs() < MERGE_ZONE_LENGTH).then_some(past)
+    }
+}
+
+/// Whether a mainline driver with `room` meters to spare before the gap it would leave
+/// gets too small lets a waiting car in ahead of it, following their behavior's
+/// `merge_courtesy`. Courteous drivers always do and refusing drivers never do. The rest
+/// take turns zipper fashion, letting one car in unless they let the last one in, as long
+/// as the gap is already there and they can slow to the joining speed with comfortable
+/// braking.
+fn yields(car: &Car, room: f32, join_speed: f32, last_yielded: Option<CarId>, cars_config: &CarsConfig, names: &NameTable) -> bool {
+    let courtesy = cars_config.behavior.get(names.behavior(car.behavior_type)).map_or("none", |behavior| behavior.merge_courtesy.as_str());
+    match courtesy {
+        "open" => true,
+        "refuse" => false,
+        _ => {
+            let speed = car.velocity.magnitude();
+            let deceleration = (speed.powi(2) - join_speed.powi(2)).max(0.0) / (2.0 * room.max(f32::EPSILON));
+            last_yielded != Some(car.id) && room > 0.0 && deceleration <= car.max_deceleration * 0.5
+        }
+    }
+}
+
+/// Lets cars arriving at a blocked entry join the road cooperatively. The car waits for a
+/// gap, mainline drivers approaching the merge point decide one by one whether to yield
+/// or pass, and the car joins at the speed of the car ahead once the gap in front of the
+/// driver yielding to it reaches the merge point.
+pub struct MergeController {
+    zones: Vec<MergeZone>,
+}
+
+impl MergeController {
+    /// `entry_pose` gives the spawn point of an entry and the direction cars leave it in
+    pub fn new(route: &RouteConfig, entry_pose: impl Fn(&EntryPoint) -> (Point, Vec2)) -> Self {
+        let geometry = &route.route.geometry;
+        let center = (geometry.geometry_type == "donut").then(|| Point::new(geometry.center_x, geometry.center_y));
+        let zones = route.route.entries.iter()
+            .map(|entry| {
+                let (position, direction) = entry_pose(entry);
+                MergeZone {
+                    merge: MergeState {
+                        entry_id: entry.id.clone(),
+                        lane: entry.lane,
+                        position,
+                        direction: direction.try_normalize(f32::EPSILON).unwrap_or_default(),
+                        center,
+                        since: 0.0,
+                        speed: JOIN_SPEED,
+                        clearance: 0.0,
+                        leader: None,
+                        yielding: None,
+                    },
+                    lane_width: geometry.lane_width,
+                    waiting: false,
+                    last_yielded: None,
+                }
+            })
+            .collect();
+            
+        Self { zones }
+    }
+    
+    pub fn is_waiting(&self, entry_id: &str) -> bool {
+        self.zones.iter().any(|zone| zone.merge.entry_id == entry_id && zone.waiting)
+    }
+    
+    /// A car arrived at the entry and found no room to join. Returns false if another car
+    /// is already waiting there.
+    pub fn request(&mut self, entry_id: &str, time: f32) -> bool {
+        let Some(zone) = self.zones.iter_mut().find(|zone| zone.merge.entry_id == entry_id && !zone.waiting) else {
+            return false;
+        };
+        zone.waiting = true;
+        zone.merge.since = time;
+        zone.merge.yielding = None;
+        true
+    }
+    
+    /// Let the mainline traffic at each entry with a waiting car decide whether to yield,
+    /// publish the merges into the simulation state and return the entries whose car can
+    /// join now, with the speed it joins at
+    pub fn update(&mut self, state: &mut SimulationState, cars_config: &CarsConfig) -> Vec<(String, f32)> {
+        // Beyond warning distance, so neither the joining car nor the one behind it has to
+        // brake for the other
+        let clearance = cars_config.collision_avoidance.warning_distance / state.weather.friction_factor();
+        let mut ready = Vec::new();
+        for zone in self.zones.iter_mut().filter(|zone| zone.waiting) {
+            zone.merge.clearance = clearance;
+            let mut nearby: Vec<(f32, &Car)> = state.cars.iter()
+                .filter_map(|car| Some((zone.offset(car)?, car)))
+                .collect();
+            nearby.sort_by(|a, b| b.0.total_cmp(&a.0));
+            
+            // Walk upstream from the merge point until a driver yields, everyone passing
+            // becomes the car the joining car will follow
+            let committed = zone.merge.yielding.take();
+            let mut leader: Option<(f32, &Car)> = None;
+            let mut follower: Option<(f32, &Car)> = None;
+            for &(past, car) in &nearby {
+                let join_speed = leader.map_or(JOIN_SPEED, |(_, leader)| leader.velocity.magnitude());
+                let room = zone.merge.room(past, leader.map(|(leader_past, _)| leader_past));
+                let short_of_merge = -past > clearance;
+                if short_of_merge && (committed == Some(car.id) || yields(car, room, join_speed, zone.last_yielded, cars_config, &state.names)) {
+                    follower = Some((past, car));
+                    break;
+                }
+                leader = Some((past, car));
+            }
+            
+            zone.merge.speed = leader.map_or(JOIN_SPEED, |(_, leader)| leader.velocity.magnitude());
+            zone.merge.leader = leader.map(|(_, car)| car.id);
+            zone.merge.yielding = follower.map(|(_, car)| car.id);
+            
+            // The gap has reached the merge point once the car ahead is clear of it and the
+            // yielding driver can comfortably slow to the joining speed behind it
+            let clear_ahead = leader.is_none_or(|(past, _)| past >= clearance);
+            let clear_behind = follower.is_none_or(|(past, car)| {
+                let closing = (car.velocity.magnitude() - zone.merge.speed).max(0.0);
+                -past >= clearance + closing.powi(2) / (2.0 * car.max_deceleration * 0.5)
+            });
+            if clear_ahead && clear_behind {
+                zone.waiting = false;
+                zone.last_yielded = zone.merge.yielding.take();
+                zone.merge.leader = None;
+                ready.push((zone.merge.entry_id.clone(), zone.merge.speed));
+            }
+        }
+        
+        state.merges = self.zones.iter()
+            .filter(|zone| zone.waiting)
+            .map(|zone| zone.merge.clone())
+            .collect();
+        ready
+    }
+    
+    /// Carry cars waiting to merge over from a loaded checkpoint
+    pub fn restore(&mut self, state: &SimulationState) {
+        for zone in &mut self.zones {
+            let saved = state.merges.iter().find(|saved| saved.entry_id == zone.merge.entry_id);
+            zone.waiting = saved.is_some();
+            if let Some(saved) = saved {
+                zone.merge.since = saved.since;
+                zone.merge.speed = saved.speed;
+                zone.merge.leader = saved.leader;
+                zone.merge.yielding = saved.yielding;
+            }
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use super::{LoopDetector, Point, SimulationState};
+use crate::config::{EntryPoint, RampMeterConfig, RouteConfig};
+use serde::{Deserialize, Serialize};
+
+const GREEN_TIME: f32 = 1.0; // seconds the meter light stays green after letting a car go
+
+/// Current state of one ramp meter, refreshed every tick and used by rendering
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RampMeterState {
+    pub entry_id: String,
+    pub position: Point, // where the meter light is drawn
+    pub queue: u32,      // cars waiting on the ramp
+    pub interval: f32,   // seconds between releases
+    pub green: bool,
+}
+
+struct RampMeter {
+    entry_id: String,
+    config: RampMeterConfig,
+    detector: Option<LoopDetector>,
+    position: Point,
+    rate: f32, // veh/h
+    queue: u32,
+    next_release: f32, // earliest simulation time the next car may go
+    last_release: Option<f32>,
+}
+
+impl RampMeter {
+    fn interval(&self) -> f32 {
+        3600.0 / self.rate
+    }
+    
+    /// ALINEA: r(k) = r(k-1) + K_R * (o_target - o_measured), with occupancies in percent
+    fn adjust_rate(&mut self, occupancy: f32) {
+        let min_rate = 3600.0 / self.config.max_interval;
+        let max_rate = 3600.0 / self.config.min_interval;
+        let error = (self.config.target_occupancy - occupancy) * 100.0;
+        self.rate = (self.rate + self.config.gain * error).clamp(min_rate, max_rate);
+    }
+}
+
+/// Holds cars arriving at metered entries and lets them onto the road one at a time
+pub struct RampMeterController {
+    meters: Vec<RampMeter>,
+}
+
+impl RampMeterController {
+    /// `entry_position` gives the spawn point of an entry; meter lights are drawn just off it
+    pub fn new(route: &RouteConfig, entry_position: impl Fn(&EntryPoint) -> Point) -> Self {
+        let geometry = &route.route.geometry;
+        let center = Point::new(geometry.center_x, geometry.center_y);
+        
+        let meters = route.route.entries.iter()
+            .filter_map(|entry| {
+                let config = entry.metering.clone()?;
+                let detector = config.detector.as_ref()
+                    .and_then(|id| route.route.detectors.iter().find(|detector| &detector.id == id))
+                    .map(|detector| LoopDetector::new(detector, route));
+                    
+                // Beside the ramp rather than on top of the cars merging from it
+                let spawn = entry_position(entry);
+                let inward = (center - spawn).try_normalize(f32::EPSILON).unwrap_or_default();
+                
+                Some(RampMeter {
+                    entry_id: entry.id.clone(),
+                    rate: 3600.0 / config.interval,
+                    config,
+                    detector,
+                    position: spawn + inward * geometry.lane_width * 1.5,
+                    queue: 0,
+                    next_release: 0.0,
+                    last_release: None,
+                })
+            })
+            .collect();
+            
+        Self { meters }
+    }
+    
+    pub fn is_metered(&self, entry_id: &str) -> bool {
+        self.meters.iter().any(|meter| meter.entry_id == entry_id)
+    }
+    
+    /// A car arrived at a metered entry and waits on the ramp
+    pub fn enqueue(&mut self, entry_id: &str) {
+        if let Some(meter) = self.meters.iter_mut().find(|meter| meter.entry_id == entry_id) {
+            meter.queue += 1;
+        }
+    }
+    
+    /// Entries whose meter lets the car at the head of the queue go at `time`
+    pub fn ready(&self, time: f32) -> Vec<String> {
+        self.meters.iter()
+            .filter(|meter| meter.queue > 0 && time >= meter.next_release)
+            .map(|meter| meter.entry_id.clone())
+            .collect()
+    }
+    
+    /// The car at the head of the entry's queue has merged onto the road
+    pub fn release(&mut self, entry_id: &str, time: f32) {
+        if let Some(meter) = self.meters.iter_mut().find(|meter| meter.entry_id == entry_id) {
+            meter.queue = meter.queue.saturating_sub(1);
+            meter.next_release = time + meter.interval();
+            meter.last_release = Some(time);
+        }
+    }
+    
+    /// Measure downstream occupancy, adjust release rates and publish the meters into the
+    /// simulation state
+    pub fn update(&mut self, state: &mut SimulationState) {
+        if self.meters.is_empty() {
+            return;
+        }
+        
+        for meter in &mut self.meters {
+            let reading = meter.detector.as_mut().and_then(|detector| detector.update(state));
+            if let Some(reading) = reading {
+                meter.adjust_rate(reading.occupancy);
+                log::debug!("Ramp meter {}: occupancy {:.1}% -> release every {:.1}s",
+                            meter.entry_id, reading.occupancy * 100.0, meter.interval());
+            }
+        }
+        
+        state.ramp_meters = self.meters.iter()
+            .map(|meter| RampMeterState {
+                entry_id: meter.entry_id.clone(),
+                position: meter.position,
+                queue: meter.queue,
+                interval: meter.interval(),
+                green: meter.last_release.is_some_and(|release| state.time - release < GREEN_TIME),
+            })
+            .collect();
+    }
+    
+    /// Carry queues and release rates over from a loaded checkpoint
+    pub fn restore(&mut self, state: &SimulationState) {
+        for meter in &mut self.meters {
+            if let Some(saved) = state.ramp_meters.iter().find(|saved| saved.entry_id == meter.entry_id) {
+                meter.queue = saved.queue;
+                meter.rate = 3600.0 / saved.interval;
+                meter.next_release = state.time;
+                meter.last_release = None;
+            }
+        }
+    }
+}
@@ -1,21 +1,97 @@
 use nalgebra::{Vector2, Point2};
-use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use web_time::{Duration, Instant};
 
 pub mod physics;
 pub mod behavior;
 pub mod traffic;
+pub mod grid;
+pub mod network;
+pub mod signals;
+pub mod intersections;
+pub mod conflicts;
+pub mod detector;
+pub mod weather;
+pub mod metering;
+pub mod merging;
+pub mod ramp;
+pub mod events;
+pub mod random;
+pub mod trips;
+pub mod trajectory;
+pub mod resources;
+pub mod lanes;
+pub mod behavior_stats;
+pub mod placement;
+pub mod closures;
+pub mod buses;
+pub mod perception;
+pub mod safety;
+pub mod noise;
+pub mod battery;
+pub mod queues;
+pub mod travel_times;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+pub mod spatial;
+pub mod columns;
+pub mod aggregates;
+pub mod append_log;
+pub mod car_pool;
+pub mod names;
+pub mod car_ids;
+pub mod checkpoint;
+pub mod rewind;
 
 pub use physics::*;
 pub use behavior::*;
 pub use traffic::*;
+pub use grid::*;
+pub use network::*;
+pub use signals::*;
+pub use intersections::*;
+pub use conflicts::*;
+pub use detector::*;
+pub use weather::*;
+pub use metering::*;
+pub use merging::*;
+pub use ramp::*;
+pub use events::*;
+pub use random::*;
+pub use trips::*;
+pub use trajectory::*;
+pub use resources::*;
+pub use lanes::*;
+pub use behavior_stats::*;
+pub use placement::*;
+pub use closures::*;
+pub use buses::*;
+pub use perception::*;
+pub use safety::*;
+pub use noise::*;
+pub use battery::*;
+pub use queues::*;
+pub use travel_times::*;
+#[cfg(feature = "scripting")]
+pub use scripting::*;
+pub use spatial::*;
+pub use columns::*;
+pub use aggregates::*;
+pub use append_log::*;
+pub use car_pool::*;
+pub use names::*;
+pub use car_ids::*;
+pub use rewind::*;
 
 pub type Vec2 = Vector2<f32>;
 pub type Point = Point2<f32>;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct CarId(pub usize);
+/// Share of their usual gaps to the car ahead tailgating drivers keep
+pub const TAILGATE_HEADWAY_FACTOR: f32 = 0.5;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Car {
     pub id: CarId,
     pub position: Point,
@@ -27,16 +103,35 @@ pub struct Car {
     pub max_acceleration: f32,
     pub max_deceleration: f32,
     pub preferred_speed: f32,
+    pub mass: Option<f32>, // kg, with engine_power limits acceleration at speed
+    pub engine_power: Option<f32>, // kW
+    pub rollover_threshold: Option<f32>, // m/s^2 sideways acceleration it takes curves at most with
     pub current_lane: u32,
     pub target_lane: Option<u32>,
-    pub lane_change_progress: f32,
+    pub lateral_offset: f32, // Meters from the middle of current_lane, positive towards higher lane numbers
+    pub lateral_velocity: f32, // m/s, same direction as lateral_offset
     pub behavior: BehaviorState,
-    pub behavior_type: String,
-    pub car_type: String,
+    pub behavior_type: BehaviorId,
+    pub car_type: CarTypeId,
     pub speed_history: [f32; 3], // Last 3 speed measurements
     pub marked_for_exit: bool, // Car should exit at next opportunity
     pub spawn_time: f32, // Time when car was spawned
+    pub entry: String, // Entry point the car spawned at
+    pub distance_traveled: f32, // Meters driven since spawning
     pub exit_time: Option<f32>, // Time when car was marked for exit
+    pub grid_path: Option<GridPath>, // Planned path through grid routes
+    pub destination: Option<String>, // Exit id from the OD matrix (None = leave at any exit)
+    pub crashed: bool, // Halted after a collision
+    pub last_collision_time: Option<f32>, // Time of the most recent collision involving this car
+    pub last_conflict_time: Option<f32>, // Time of the most recent traffic conflict involving this car
+    pub breakdown: Option<Breakdown>, // Mechanical breakdown in progress
+    pub exit_ramp: Option<RampPosition>, // Off-ramp the car is leaving by
+    pub turn_signal: Option<TurnSignal>, // Indicator flashing for a lane change or exit
+    pub perception: Perception, // Recent glimpses of the car ahead, for delayed reactions
+    pub bus: Option<BusState>, // Stop list progress of a bus
+    pub battery: Option<Battery>, // Charge of an electric vehicle
+    pub route: usize, // Index of the world route the car drives on, 0 outside multi-route worlds
+    pub leader: Option<(CarId, f32)>, // Car ahead in its lane and the gap to it, as of the last physics step
 }
 
 impl Car {
@@ -51,26 +146,183 @@ impl Car {
     pub fn average_speed(&self) -> f32 {
         self.speed_history.iter().sum::<f32>() / 3.0
     }
+    
+    /// Whether any part of the car's body reaches into `lane`, so a car changing lanes
+    /// occupies both lanes while it straddles the line between them
+    pub fn occupies_lane(&self, lane: u32, lane_width: f32) -> bool {
+        let lateral_position = self.current_lane as f32 + self.lateral_offset / lane_width;
+        (lateral_position - lane as f32).abs() < 0.5 + self.width / (2.0 * lane_width)
+    }
+    
+    pub fn footprint(&self) -> Footprint {
+        Footprint {
+            position: self.position,
+            heading: self.heading,
+            length: self.length,
+            width: self.width,
+        }
+    }
+    
+    /// Broken down and come to rest, in its lane or on the shoulder
+    pub fn is_stranded(&self) -> bool {
+        self.breakdown.is_some() && self.velocity.magnitude() < 0.1
+    }
+    
+    /// Pulled up at a bus stop, dwelling or about to pull away
+    pub fn is_dwelling(&self) -> bool {
+        self.bus.as_ref().is_some_and(|bus| bus.dwell_start.is_some())
+    }
+    
+    /// An electric vehicle whose battery ran flat, stranded until it is towed away
+    pub fn is_out_of_charge(&self) -> bool {
+        self.battery.as_ref().is_some_and(Battery::is_flat)
+    }
+    
+    /// Moving onto or parked on the shoulder, out of the way of traffic in its lane
+    pub fn is_on_shoulder(&self) -> bool {
+        self.breakdown.as_ref().is_some_and(|breakdown| breakdown.shoulder_offset > 0.0)
+    }
 }
 
-#[derive(Debug, Clone)]
+/// A car that broke down coasts to a stop in its lane. It either drives on once repaired,
+/// or is pulled onto the shoulder and towed away at `end_time`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Breakdown {
+    pub start_time: f32,
+    pub end_time: f32,
+    pub pull_over_time: Option<f32>, // When the car is pulled onto the shoulder, if it will be
+    pub shoulder_offset: f32, // Meters moved sideways towards the shoulder so far
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TurnSignal {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BehaviorState {
     pub following_distance_factor: f32,
     pub lane_change_frequency: f32,
     pub speed_variance: f32,
     pub reaction_time: f32,
-    pub exit_probability: f32,
     pub last_lane_change_time: f32,
     pub target_speed: f32,
+    pub merge_intent: Option<u32>, // Lane the driver is waiting for a gap to merge into
+    pub tailgating: bool, // Closing up on the car ahead, with a shorter gap, and flashing it
 }
 
-#[derive(Debug, Clone)]
+impl BehaviorState {
+    /// Multiplier on the following distance, the behavior's own factor shrunk while tailgating
+    pub fn headway_factor(&self) -> f32 {
+        if self.tailgating {
+            self.following_distance_factor * TAILGATE_HEADWAY_FACTOR
+        } else {
+            self.following_distance_factor
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct SimulationState {
-    pub cars: Vec<Car>,
+    pub cars: Vec<Car>, // Added and removed through `add_car` and `remove_car`, or `reindex_cars` after editing it directly
     pub time: f32,
     pub dt: f32,
     pub total_spawned: u32,
     pub active_cars: u32,
+    pub signals: Vec<SignalState>,
+    pub intersections: Vec<IntersectionState>, // Stop and yield sign controlled grid intersections
+    pub junctions: Vec<JunctionState>, // Grid junctions and the movements reserved through them
+    pub ramp_meters: Vec<RampMeterState>,
+    pub merges: Vec<MergeState>, // Cars waiting at entries for a gap to join the road
+    pub bus_stops: Vec<BusStopState>, // Dwell statistics of the route's bus stops
+    pub weather: Weather,
+    pub total_collisions: u32,
+    pub exit_counts: std::collections::BTreeMap<String, u32>, // Cars that left through each exit
+    pub signal_overrides: std::collections::BTreeMap<String, SignalPhase>, // Groups held at a phase instead of their timing plan
+    pub collision_events: Vec<CollisionEvent>, // Collisions detected during the latest tick
+    #[serde(default)]
+    pub queues: Vec<Queue>, // Queues of slow cars found in the latest tick
+    pub names: Arc<NameTable>, // Behavior and car type names of the cars' ids, published by the traffic manager
+    #[serde(skip)]
+    pub events: Vec<SimulationEvent>, // Everything that happened during the latest tick
+    #[serde(skip)]
+    pub trips: TripLog, // Trips completed since the simulation started, not saved in checkpoints or replays
+    #[serde(skip)]
+    pub safety: SafetyLog, // Time-to-collision and post-encroachment distributions and conflicts, not saved either
+    #[serde(skip)]
+    pub travel_times: TravelTimeLog, // Traversals of the route's travel time segments, not saved either
+    car_ids: CarIds, // Slots the cars' ids are handed out from, saved so a resumed run hands out the same ids
+    #[serde(skip)]
+    car_positions: Vec<usize>, // Position in `cars` of the car of each id slot, rebuilt by `reindex_cars` after loading
+    #[serde(skip)]
+    aggregates: CarAggregates, // Counted again by `reindex_cars` after loading
+    #[serde(skip)]
+    pub car_pool: CarPool, // Buffers of the cars removed, for spawning to reuse
+}
+
+/// Snapshots of the state are taken every frame for interpolation and handed between
+/// threads, so `clone_from` reuses the allocations of the state it overwrites and copies
+/// only the newest entries of the logs that keep growing
+impl Clone for SimulationState {
+    fn clone(&self) -> Self {
+        Self {
+            cars: self.cars.clone(),
+            time: self.time,
+            dt: self.dt,
+            total_spawned: self.total_spawned,
+            active_cars: self.active_cars,
+            signals: self.signals.clone(),
+            intersections: self.intersections.clone(),
+            junctions: self.junctions.clone(),
+            ramp_meters: self.ramp_meters.clone(),
+            merges: self.merges.clone(),
+            bus_stops: self.bus_stops.clone(),
+            weather: self.weather,
+            total_collisions: self.total_collisions,
+            exit_counts: self.exit_counts.clone(),
+            signal_overrides: self.signal_overrides.clone(),
+            collision_events: self.collision_events.clone(),
+            queues: self.queues.clone(),
+            names: self.names.clone(),
+            events: self.events.clone(),
+            trips: self.trips.clone(),
+            safety: self.safety.clone(),
+            travel_times: self.travel_times.clone(),
+            car_ids: self.car_ids.clone(),
+            car_positions: self.car_positions.clone(),
+            aggregates: self.aggregates.clone(),
+            car_pool: CarPool::default(),
+        }
+    }
+    
+    fn clone_from(&mut self, source: &Self) {
+        self.cars.clone_from(&source.cars);
+        self.time = source.time;
+        self.dt = source.dt;
+        self.total_spawned = source.total_spawned;
+        self.active_cars = source.active_cars;
+        self.signals.clone_from(&source.signals);
+        self.intersections.clone_from(&source.intersections);
+        self.junctions.clone_from(&source.junctions);
+        self.ramp_meters.clone_from(&source.ramp_meters);
+        self.merges.clone_from(&source.merges);
+        self.bus_stops.clone_from(&source.bus_stops);
+        self.weather = source.weather;
+        self.total_collisions = source.total_collisions;
+        self.exit_counts.clone_from(&source.exit_counts);
+        self.signal_overrides.clone_from(&source.signal_overrides);
+        self.collision_events.clone_from(&source.collision_events);
+        self.queues.clone_from(&source.queues);
+        self.names.clone_from(&source.names);
+        self.events.clone_from(&source.events);
+        self.trips.clone_from(&source.trips);
+        self.safety.clone_from(&source.safety);
+        self.travel_times.clone_from(&source.travel_times);
+        self.car_ids.clone_from(&source.car_ids);
+        self.car_positions.clone_from(&source.car_positions);
+        self.aggregates.clone_from(&source.aggregates);
+    }
 }
 
 impl SimulationState {
@@ -81,81 +333,197 @@ impl SimulationState {
             dt,
             total_spawned: 0,
             active_cars: 0,
+            signals: Vec::new(),
+            intersections: Vec::new(),
+            junctions: Vec::new(),
+            ramp_meters: Vec::new(),
+            merges: Vec::new(),
+            bus_stops: Vec::new(),
+            weather: Weather::Dry,
+            total_collisions: 0,
+            exit_counts: std::collections::BTreeMap::new(),
+            signal_overrides: std::collections::BTreeMap::new(),
+            collision_events: Vec::new(),
+            queues: Vec::new(),
+            names: Arc::default(),
+            events: Vec::new(),
+            trips: TripLog::default(),
+            safety: SafetyLog::default(),
+            travel_times: TravelTimeLog::default(),
+            car_ids: CarIds::default(),
+            car_positions: Vec::new(),
+            aggregates: CarAggregates::default(),
+            car_pool: CarPool::default(),
+        }
+    }
+    
+    /// Put `car` on the road under a new id, which it returns
+    pub fn add_car(&mut self, mut car: Car) -> CarId {
+        car.id = self.car_ids.allocate();
+        self.insert_car(car)
+    }
+    
+    /// Put `car` back on the road under the id it had, held for it when it left. A car
+    /// whose slot went to another car meanwhile gets a new id.
+    pub fn readmit_car(&mut self, mut car: Car) -> CarId {
+        if !self.car_ids.claim(car.id) {
+            car.id = self.car_ids.allocate();
         }
+        self.insert_car(car)
     }
     
-    pub fn add_car(&mut self, car: Car) {
+    fn insert_car(&mut self, car: Car) -> CarId {
+        let id = car.id;
+        if self.car_positions.len() <= id.slot() {
+            self.car_positions.resize(id.slot() + 1, usize::MAX);
+        }
+        self.car_positions[id.slot()] = self.cars.len();
+        self.aggregates.add(&car);
         self.cars.push(car);
         self.total_spawned += 1;
         self.active_cars += 1;
+        id
+    }
+    
+    /// Keep the id of car `id` for it when it leaves the road, to come back with
+    /// `readmit_car`
+    pub fn hold_car(&mut self, id: CarId) {
+        self.car_ids.hold(id);
     }
     
+    /// Take car `id` off the road in constant time: the last car moves into its place, its
+    /// id slot is free for the next car and its buffers go to the pool for the next car
+    /// spawned
     pub fn remove_car(&mut self, id: CarId) {
-        if let Some(pos) = self.cars.iter().position(|c| c.id == id) {
-            self.cars.remove(pos);
+        if let Some(pos) = self.car_position(id) {
+            let car = self.cars.swap_remove(pos);
+            self.aggregates.remove(pos, &car);
+            self.car_positions[id.slot()] = usize::MAX;
+            if let Some(moved) = self.cars.get(pos) {
+                self.car_positions[moved.id.slot()] = pos;
+            }
+            self.car_ids.release(id);
             self.active_cars = self.active_cars.saturating_sub(1);
+            self.car_pool.release(car);
+        }
+    }
+    
+    /// Rebuild the positions and aggregates of the cars after `cars` was edited directly
+    /// or the state was loaded. Cars put on the road directly keep their ids.
+    pub fn reindex_cars(&mut self) {
+        self.car_positions.clear();
+        for (i, car) in self.cars.iter().enumerate() {
+            if self.car_positions.len() <= car.id.slot() {
+                self.car_positions.resize(car.id.slot() + 1, usize::MAX);
+            }
+            self.car_positions[car.id.slot()] = i;
+            self.car_ids.claim(car.id);
+        }
+        self.aggregates = CarAggregates::from_cars(&self.cars);
+    }
+    
+    /// Lend the slots ids are handed out from to `other` and take its own, so states that
+    /// split one fleet between them hand out ids that stay apart. Lending again swaps back.
+    pub(crate) fn swap_car_ids(&mut self, other: &mut SimulationState) {
+        std::mem::swap(&mut self.car_ids, &mut other.car_ids);
+    }
+    
+    /// Position of car `id` in `cars`
+    pub(crate) fn car_position(&self, id: CarId) -> Option<usize> {
+        let pos = *self.car_positions.get(id.slot())?;
+        self.cars.get(pos).filter(|car| car.id == id).map(|_| pos)
+    }
+    
+    /// Remove a car that left the road through `exit_id`, counting it for that exit and
+    /// logging its trip against a free-flow speed of at most `speed_limit`
+    pub fn exit_car(&mut self, id: CarId, exit_id: &str, speed_limit: f32) {
+        if let Some(car) = self.get_car(id) {
+            let trip = Trip::completed(car, &self.names, exit_id, self.time, speed_limit);
+            self.trips.record(trip);
+            self.remove_car(id);
+            *self.exit_counts.entry(exit_id.to_string()).or_insert(0) += 1;
+            self.events.push(SimulationEvent::CarExited {
+                car: id,
+                exit: exit_id.to_string(),
+                time: self.time,
+            });
+        }
+    }
+    
+    /// Hold a signal group at `phase` from the next tick on, or return it to its timing
+    /// plan with `None`
+    pub fn set_signal_override(&mut self, group_id: &str, phase: Option<SignalPhase>) {
+        match phase {
+            Some(phase) => {
+                self.signal_overrides.insert(group_id.to_string(), phase);
+            }
+            None => {
+                self.signal_overrides.remove(group_id);
+            }
         }
     }
     
     pub fn get_car(&self, id: CarId) -> Option<&Car> {
-        self.cars.iter().find(|c| c.id == id)
+        self.car_position(id).map(|pos| &self.cars[pos])
     }
     
     pub fn get_car_mut(&mut self, id: CarId) -> Option<&mut Car> {
-        self.cars.iter_mut().find(|c| c.id == id)
+        self.car_position(id).map(|pos| &mut self.cars[pos])
+    }
+    
+    /// Copy of this state with car poses blended from `previous` by `alpha` (0 = previous
+    /// tick, 1 = this tick). Used to render smoothly between fixed simulation steps.
+    pub fn interpolated(&self, previous: &SimulationState, alpha: f32) -> SimulationState {
+        let mut state = self.clone();
+        let previous_cars: std::collections::HashMap<CarId, &Car> = previous.cars
+            .iter()
+            .map(|car| (car.id, car))
+            .collect();
+            
+        for car in &mut state.cars {
+            // Cars spawned during the last tick have nothing to blend from
+            if let Some(prev) = previous_cars.get(&car.id) {
+                car.position = prev.position + (car.position - prev.position) * alpha;
+                let turn = (car.heading - prev.heading + std::f32::consts::PI)
+                    .rem_euclid(2.0 * std::f32::consts::PI) - std::f32::consts::PI;
+                car.heading = prev.heading + turn * alpha;
+            }
+        }
+        state
     }
     
     pub fn update_car_speeds(&mut self) {
         for car in &mut self.cars {
             car.update_speed_history();
         }
+        self.aggregates.record_speeds(&self.cars);
     }
     
-    pub fn get_behavior_counts(&self) -> std::collections::HashMap<String, usize> {
-        let mut counts = std::collections::HashMap::new();
-        for car in &self.cars {
-            *counts.entry(car.behavior_type.clone()).or_insert(0) += 1;
-        }
-        counts
+    /// Counts over the cars on the road, with speeds as of the last `update_car_speeds`
+    pub fn aggregates(&self) -> &CarAggregates {
+        &self.aggregates
+    }
+    
+    /// Cars on the road of each behavior that has any, in the order of their names
+    pub fn get_behavior_counts(&self) -> Vec<(BehaviorId, usize)> {
+        self.aggregates.behavior_counts()
     }
     
     pub fn get_velocity_distribution(&self, num_buckets: usize) -> Vec<usize> {
-        let mut distribution = vec![0; num_buckets];
-        
-        if self.cars.is_empty() {
-            return distribution;
-        }
-        
-        // Find max speed to determine bucket range
-        let max_speed = self.cars.iter()
-            .map(|car| car.velocity.magnitude())
-            .fold(0.0, f32::max);
-        
-        if max_speed == 0.0 {
-            return distribution;
-        }
-        
-        let bucket_size = max_speed / num_buckets as f32;
-        
-        for car in &self.cars {
-            let speed = car.velocity.magnitude();
-            let bucket_index = ((speed / bucket_size) as usize).min(num_buckets - 1);
-            distribution[bucket_index] += 1;
-        }
-        
-        distribution
+        self.aggregates.velocity_distribution(num_buckets)
     }
     
-    pub fn mark_car_for_exit(&mut self, behavior_type: &str) -> bool {
+    pub fn mark_car_for_exit(&mut self, behavior_type: &str) -> Option<CarId> {
+        let behavior_type = self.names.behaviors.id(behavior_type)?;
         // Find first car of this behavior type that's not already marked for exit
         for car in &mut self.cars {
             if car.behavior_type == behavior_type && !car.marked_for_exit {
                 car.marked_for_exit = true;
                 car.exit_time = Some(self.time);
-                return true; // Successfully marked a car
+                return Some(car.id); // Successfully marked a car
             }
         }
-        false // No car of this type found
+        None // No car of this type found
     }
 }
 
@@ -164,9 +532,13 @@ pub struct PerformanceMetrics {
     pub frame_time: Duration,
     pub simulation_time: Duration,
     pub render_time: Duration,
-    pub cpu_utilization: f32,
-    pub gpu_utilization: f32,
-    pub memory_usage: usize,
+    pub cpu_utilization: f32, // fraction of all cores' time spent in this process
+    pub gpu_utilization: f32, // fraction of the simulation steps' time the GPU was busy
+    pub gpu: Option<GpuTiming>, // kernel, transfer and occupancy figures, `None` without GPU steps
+    pub memory_usage: usize, // resident bytes of this process
+    pub gpu_memory: usize, // bytes in GPU buffers
+    pub drawn_cars: usize, // cars in view in the last frame, as shapes or dots
+    pub culled_cars: usize, // cars out of view in the last frame, not uploaded or drawn
 }
 
 impl Default for PerformanceMetrics {
@@ -177,11 +549,45 @@ impl Default for PerformanceMetrics {
             render_time: Duration::ZERO,
             cpu_utilization: 0.0,
             gpu_utilization: 0.0,
+            gpu: None,
             memory_usage: 0,
+            gpu_memory: 0,
+            drawn_cars: 0,
+            culled_cars: 0,
+        }
+    }
+}
+
+/// Device-side timings of GPU simulation steps, read from OpenCL profiling events
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GpuTiming {
+    pub kernel_time: Duration,
+    pub transfer_time: Duration, // uploading and downloading car data
+    pub step_time: Duration, // wall-clock time of the whole steps on the host
+    pub occupancy: f32, // fraction of the device's concurrent work-items the kernel launch filled
+}
+
+impl GpuTiming {
+    /// Fraction of the steps' wall-clock time the device was busy with them
+    pub fn utilization(&self) -> f32 {
+        if self.step_time.is_zero() {
+            return 0.0;
         }
+        ((self.kernel_time + self.transfer_time).as_secs_f32() / self.step_time.as_secs_f32()).min(1.0)
+    }
+    
+    /// Add the timing of another step; occupancy becomes the latest step's
+    pub fn add(&mut self, other: &GpuTiming) {
+        self.kernel_time += other.kernel_time;
+        self.transfer_time += other.transfer_time;
+        self.step_time += other.step_time;
+        self.occupancy = other.occupancy;
     }
 }
 
+/// Resource samples kept for the charts, a minute at the sample interval
+const RESOURCE_HISTORY: usize = 120;
+
 #[derive(Debug)]
 pub struct PerformanceTracker {
     samples: Vec<PerformanceMetrics>,
@@ -189,6 +595,14 @@ pub struct PerformanceTracker {
     current_frame_start: Option<Instant>,
     current_sim_start: Option<Instant>,
     current_render_start: Option<Instant>,
+    current_gpu: Option<GpuTiming>, // GPU steps of the frame in progress
+    monitor: ProcessMonitor,
+    resources: VecDeque<ResourceSample>, // one every `RESOURCE_SAMPLE_INTERVAL`, oldest first
+    gpu_memory: usize,
+    drawn_cars: usize,
+    culled_cars: usize,
+    started: Instant,
+    next_resource_sample: f32,
 }
 
 impl PerformanceTracker {
@@ -199,6 +613,14 @@ impl PerformanceTracker {
             current_frame_start: None,
             current_sim_start: None,
             current_render_start: None,
+            current_gpu: None,
+            monitor: ProcessMonitor::new(),
+            resources: VecDeque::new(),
+            gpu_memory: 0,
+            drawn_cars: 0,
+            culled_cars: 0,
+            started: Instant::now(),
+            next_resource_sample: 0.0,
         }
     }
     
@@ -212,13 +634,61 @@ impl PerformanceTracker {
     
     pub fn end_simulation(&mut self) {
         if let Some(start) = self.current_sim_start.take() {
-            let duration = start.elapsed();
-            if let Some(current) = self.samples.last_mut() {
-                current.simulation_time = duration;
-            }
+            self.record_simulation(start.elapsed());
         }
     }
     
+    /// Report time spent simulating for the frame in progress that was measured elsewhere,
+    /// as by a simulation running on its own thread
+    pub fn record_simulation(&mut self, duration: Duration) {
+        if let Some(current) = self.samples.last_mut() {
+            current.simulation_time = duration;
+        }
+    }
+    
+    /// Count a GPU step towards the frame in progress
+    pub fn record_gpu(&mut self, timing: &GpuTiming) {
+        self.current_gpu.get_or_insert_with(GpuTiming::default).add(timing);
+    }
+    
+    /// Bytes the compute backend and renderer hold in GPU buffers, reported from now on
+    pub fn set_gpu_memory(&mut self, bytes: usize) {
+        self.gpu_memory = bytes;
+    }
+    
+    /// Cars the renderer drew and culled in the last frame, reported from now on
+    pub fn set_car_culling(&mut self, drawn: usize, culled: usize) {
+        self.drawn_cars = drawn;
+        self.culled_cars = culled;
+    }
+    
+    /// Take a resource sample if an interval has passed since the last one
+    pub fn sample_resources(&mut self) {
+        let time = self.started.elapsed().as_secs_f32();
+        if time < self.next_resource_sample {
+            return;
+        }
+        self.next_resource_sample = time + RESOURCE_SAMPLE_INTERVAL;
+        
+        self.resources.push_back(ResourceSample {
+            time,
+            process: self.monitor.sample(),
+            gpu_memory: self.gpu_memory,
+        });
+        while self.resources.len() > RESOURCE_HISTORY {
+            self.resources.pop_front();
+        }
+    }
+    
+    /// Rolling resource history, oldest first
+    pub fn resource_history(&self) -> &VecDeque<ResourceSample> {
+        &self.resources
+    }
+    
+    pub fn latest_resources(&self) -> Option<&ResourceSample> {
+        self.resources.back()
+    }
+    
     pub fn start_render(&mut self) {
         self.current_render_start = Some(Instant::now());
     }
@@ -235,6 +705,9 @@ impl PerformanceTracker {
     pub fn end_frame(&mut self) {
         if let Some(start) = self.current_frame_start.take() {
             let frame_time = start.elapsed();
+            let gpu = self.current_gpu.take();
+            self.sample_resources();
+            let process = self.latest_resources().and_then(|sample| sample.process).unwrap_or_default();
             
             let metrics = PerformanceMetrics {
                 frame_time,
@@ -244,9 +717,13 @@ impl PerformanceTracker {
                 render_time: self.samples.last()
                     .map(|s| s.render_time)
                     .unwrap_or(Duration::ZERO),
-                cpu_utilization: 0.0, // TODO: Implement CPU monitoring
-                gpu_utilization: 0.0, // TODO: Implement GPU monitoring
-                memory_usage: 0,      // TODO: Implement memory monitoring
+                cpu_utilization: process.cpu_utilization,
+                gpu_utilization: gpu.map(|gpu| gpu.utilization()).unwrap_or(0.0),
+                gpu,
+                memory_usage: process.memory_usage,
+                gpu_memory: self.gpu_memory,
+                drawn_cars: self.drawn_cars,
+                culled_cars: self.culled_cars,
             };
             
             if self.samples.len() >= self.max_samples {
@@ -274,6 +751,31 @@ impl PerformanceTracker {
         total / self.samples.len() as u32
     }
     
+    pub fn average_render_time(&self) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        
+        let total: Duration = self.samples.iter().map(|s| s.render_time).sum();
+        total / self.samples.len() as u32
+    }
+    
+    /// GPU timings per frame averaged over the frames that ran GPU steps, `None` if none did
+    pub fn average_gpu(&self) -> Option<GpuTiming> {
+        let mut total: Option<GpuTiming> = None;
+        let mut frames = 0;
+        for gpu in self.samples.iter().filter_map(|s| s.gpu.as_ref()) {
+            total.get_or_insert_with(GpuTiming::default).add(gpu);
+            frames += 1;
+        }
+        total.map(|total| GpuTiming {
+            kernel_time: total.kernel_time / frames,
+            transfer_time: total.transfer_time / frames,
+            step_time: total.step_time / frames,
+            occupancy: total.occupancy,
+        })
+    }
+    
     pub fn fps(&self) -> f32 {
         let avg_frame_time = self.average_frame_time();
         if avg_frame_time.is_zero() {
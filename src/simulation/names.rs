@@ -0,0 +1,177 @@
+use crate::config::CarsConfig;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+/// A kind of name cars carry
+pub trait NameKind: Copy + Eq + Hash + Ord + fmt::Debug + 'static {}
+
+/// Driver behaviors, the keys of `[behavior]` in cars.toml
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum BehaviorNames {}
+
+/// Car types, the ids of `[[car_types]]` in cars.toml
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CarTypeNames {}
+
+impl NameKind for BehaviorNames {}
+impl NameKind for CarTypeNames {}
+
+/// A name from the configuration as a small id, so cars carry and compare two bytes
+/// instead of a string of their own. The id is the name's position in the `NameTable`
+/// of the configuration, which gives the name back.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Name<K: NameKind> {
+    id: u16,
+    kind: PhantomData<K>,
+}
+
+pub type BehaviorId = Name<BehaviorNames>;
+pub type CarTypeId = Name<CarTypeNames>;
+
+impl<K: NameKind> Name<K> {
+    /// Id of the name at `index` of its table
+    pub fn from_index(index: usize) -> Self {
+        let id = u16::try_from(index).expect("fewer than 65536 names of a kind");
+        Self { id, kind: PhantomData }
+    }
+    
+    /// Position of this name in its table, and in tables indexed by id
+    pub fn index(self) -> usize {
+        self.id as usize
+    }
+}
+
+impl<K: NameKind> fmt::Debug for Name<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.id)
+    }
+}
+
+impl<K: NameKind> Serialize for Name<K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(self.id)
+    }
+}
+
+impl<'de, K: NameKind> Deserialize<'de> for Name<K> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::from_index(u16::deserialize(deserializer)? as usize))
+    }
+}
+
+/// The names of one kind in a configuration, each known by its position. The
+/// configuration's names come first, sorted; names met later, in a checkpoint taken under
+/// another configuration, are added after them so no id changes.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Names<K: NameKind> {
+    names: Vec<String>,
+    #[serde(skip)]
+    kind: PhantomData<K>,
+}
+
+impl<K: NameKind> Names<K> {
+    pub fn new<S: Into<String>>(names: impl IntoIterator<Item = S>) -> Self {
+        let mut names: Vec<String> = names.into_iter().map(Into::into).collect();
+        names.sort();
+        names.dedup();
+        Self { names, kind: PhantomData }
+    }
+    
+    /// Id of `name`, if the table has it
+    pub fn id(&self, name: &str) -> Option<Name<K>> {
+        // Configurations have a handful of names, a scan beats hashing
+        self.names.iter().position(|known| known == name).map(Name::from_index)
+    }
+    
+    /// Name of `id`, empty for ids from another table that this one is too short for
+    pub fn name(&self, id: Name<K>) -> &str {
+        self.names.get(id.index()).map_or("", String::as_str)
+    }
+    
+    /// Every id, in id order
+    pub fn ids(&self) -> impl Iterator<Item = Name<K>> {
+        (0..self.names.len()).map(Name::from_index)
+    }
+    
+    /// Every id, in the order of their names, as lists shown to the user are
+    pub fn sorted_ids(&self) -> Vec<Name<K>> {
+        let mut ids: Vec<Name<K>> = self.ids().collect();
+        ids.sort_by(|a, b| self.name(*a).cmp(self.name(*b)));
+        ids
+    }
+    
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+    
+    fn extend(&mut self, other: &Names<K>) {
+        for name in &other.names {
+            if self.id(name).is_none() {
+                self.names.push(name.clone());
+            }
+        }
+    }
+}
+
+impl<K: NameKind> Default for Names<K> {
+    fn default() -> Self {
+        Self { names: Vec::new(), kind: PhantomData }
+    }
+}
+
+impl<K: NameKind> fmt::Debug for Names<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(&self.names).finish()
+    }
+}
+
+/// Behavior and car type names of a cars configuration. The traffic manager builds it and
+/// publishes it in the simulation state, where checkpoints and replays save it with the
+/// cars whose ids it names.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NameTable {
+    pub behaviors: Names<BehaviorNames>,
+    pub car_types: Names<CarTypeNames>,
+}
+
+impl NameTable {
+    pub fn new(cars: &CarsConfig) -> Self {
+        Self {
+            behaviors: Names::new(cars.behavior.keys().cloned()),
+            car_types: Names::new(cars.car_types.iter().map(|car_type| car_type.id.clone())),
+        }
+    }
+    
+    pub fn behavior(&self, id: BehaviorId) -> &str {
+        self.behaviors.name(id)
+    }
+    
+    pub fn car_type(&self, id: CarTypeId) -> &str {
+        self.car_types.name(id)
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.behaviors.is_empty() && self.car_types.is_empty()
+    }
+    
+    /// This table with the names of `other` that it lacks added after its own
+    pub fn including(&self, other: &NameTable) -> NameTable {
+        let mut table = self.clone();
+        table.behaviors.extend(&other.behaviors);
+        table.car_types.extend(&other.car_types);
+        table
+    }
+    
+    /// Whether every name of `other` is in this table
+    pub fn covers(&self, other: &NameTable) -> bool {
+        other.behaviors.names.iter().all(|name| self.behaviors.id(name).is_some()) &&
+            other.car_types.names.iter().all(|name| self.car_types.id(name).is_some())
+    }
+}
@@ -0,0 +1,150 @@
+use super::Point;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Directed road segment between two nodes
+#[derive(Debug, Clone)]
+pub struct NetworkEdge {
+    pub from: usize,
+    pub to: usize,
+    pub length: f32, // meters
+}
+
+/// Directed road graph used to assign routes.
+///
+/// Nodes are points cars pass through (cell centers, junctions, entries and exits) and
+/// edges are the drivable connections between them. Nodes can be given names so routes
+/// can be requested between configured entry and exit ids.
+#[derive(Debug, Clone, Default)]
+pub struct RoadNetwork {
+    positions: Vec<Point>,
+    edges: Vec<NetworkEdge>,
+    outgoing: Vec<Vec<usize>>, // node -> indices into `edges`
+    names: HashMap<String, usize>,
+}
+
+impl RoadNetwork {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn add_node(&mut self, position: Point) -> usize {
+        self.positions.push(position);
+        self.outgoing.push(Vec::new());
+        self.positions.len() - 1
+    }
+    
+    pub fn name_node(&mut self, name: &str, node: usize) {
+        self.names.insert(name.to_string(), node);
+    }
+    
+    pub fn node(&self, name: &str) -> Option<usize> {
+        self.names.get(name).copied()
+    }
+    
+    pub fn position(&self, node: usize) -> Point {
+        self.positions[node]
+    }
+    
+    pub fn node_count(&self) -> usize {
+        self.positions.len()
+    }
+    
+    /// Add a straight one-way edge, its length is the distance between the nodes
+    pub fn add_edge(&mut self, from: usize, to: usize) {
+        let length = (self.positions[to] - self.positions[from]).magnitude();
+        self.add_edge_with_length(from, to, length);
+    }
+    
+    /// Add a one-way edge whose length differs from the straight-line distance (e.g. an arc).
+    /// The length must not be shorter than the straight line or A* may miss the shortest path.
+    pub fn add_edge_with_length(&mut self, from: usize, to: usize, length: f32) {
+        self.outgoing[from].push(self.edges.len());
+        self.edges.push(NetworkEdge { from, to, length });
+    }
+    
+    /// Shortest path from `start` to `goal` as a list of nodes including both ends.
+    /// A* search with the straight-line distance as heuristic.
+    pub fn shortest_path(&self, start: usize, goal: usize) -> Option<Vec<usize>> {
+        if start >= self.node_count() || goal >= self.node_count() {
+            return None;
+        }
+        
+        let heuristic = |node: usize| (self.positions[goal] - self.positions[node]).magnitude();
+        let mut best = vec![f32::INFINITY; self.node_count()];
+        let mut previous = vec![None; self.node_count()];
+        let mut open = BinaryHeap::new();
+        best[start] = 0.0;
+        open.push(SearchNode { node: start, cost: 0.0, estimate: heuristic(start) });
+        
+        while let Some(SearchNode { node, cost, .. }) = open.pop() {
+            if node == goal {
+                let mut path = vec![goal];
+                let mut current = goal;
+                while let Some(prev) = previous[current] {
+                    path.push(prev);
+                    current = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if cost > best[node] {
+                continue; // Stale queue entry
+            }
+            
+            for &edge in &self.outgoing[node] {
+                let edge = &self.edges[edge];
+                let next_cost = cost + edge.length;
+                if next_cost < best[edge.to] {
+                    best[edge.to] = next_cost;
+                    previous[edge.to] = Some(node);
+                    open.push(SearchNode { node: edge.to, cost: next_cost, estimate: next_cost + heuristic(edge.to) });
+                }
+            }
+        }
+        
+        None
+    }
+    
+    /// Total length of a path returned by `shortest_path`
+    pub fn path_length(&self, path: &[usize]) -> f32 {
+        path.windows(2)
+            .filter_map(|pair| {
+                self.outgoing[pair[0]].iter()
+                    .map(|&edge| &self.edges[edge])
+                    .filter(|edge| edge.to == pair[1])
+                    .map(|edge| edge.length)
+                    .min_by(f32::total_cmp)
+            })
+            .sum()
+    }
+}
+
+/// Open-set entry ordered so the binary heap pops the lowest estimate first
+struct SearchNode {
+    node: usize,
+    cost: f32,
+    estimate: f32,
+}
+
+impl PartialEq for SearchNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SearchNode {}
+
+impl PartialOrd for SearchNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SearchNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Ties go to the lower node index so paths don't depend on insertion order
+        other.estimate.total_cmp(&self.estimate)
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
@@ -0,0 +1,166 @@
+use super::{Car, Point, SimulationState};
+use crate::config::CarsConfig;
+use std::collections::HashMap;
+
+/// Meters along each side of the noise map's square cells, unless chosen otherwise
+pub const NOISE_CELL_SIZE: f32 = 10.0;
+
+/// Reference speed of the emission model, km/h
+const REFERENCE_SPEED: f32 = 70.0;
+/// Lowest speed the emission model holds for, km/h; slower cars emit as if at this speed
+const MIN_MODEL_SPEED: f32 = 20.0;
+
+/// Coefficients of the emission model for one vehicle class, in dB(A) of sound power
+struct EmissionClass {
+    rolling: f32,           // tyre/road noise at the reference speed
+    rolling_slope: f32,     // per tenfold increase in speed
+    propulsion: f32,        // engine, exhaust and intake noise at the reference speed
+    propulsion_slope: f32,  // per reference speed of difference
+    acceleration: f32,      // propulsion noise added per m/s^2 of acceleration
+}
+
+const LIGHT: EmissionClass = EmissionClass {
+    rolling: 99.0,
+    rolling_slope: 30.0,
+    propulsion: 95.0,
+    propulsion_slope: 0.5,
+    acceleration: 4.4,
+};
+
+const HEAVY: EmissionClass = EmissionClass {
+    rolling: 105.5,
+    rolling_slope: 33.0,
+    propulsion: 103.0,
+    propulsion_slope: 1.0,
+    acceleration: 5.6,
+};
+
+/// A-weighted sound power level, in dB, of a vehicle driving at `speed` m/s and
+/// accelerating at `acceleration` m/s^2. A broadband simplification of the CNOSSOS-EU road
+/// source model: rolling noise grows with the logarithm of speed, propulsion noise
+/// linearly with speed and with acceleration, and the two add as energies. Stopped
+/// vehicles only idle.
+pub fn vehicle_noise_level(speed: f32, acceleration: f32, heavy: bool) -> f32 {
+    let class = if heavy { &HEAVY } else { &LIGHT };
+    let speed_kmh = (speed * 3.6).max(MIN_MODEL_SPEED);
+    let propulsion = class.propulsion
+        + class.propulsion_slope * (speed_kmh - REFERENCE_SPEED) / REFERENCE_SPEED
+        + class.acceleration * acceleration.clamp(-1.0, 2.0);
+    let mut energy = 10f32.powf(propulsion / 10.0);
+    if speed > 0.1 {
+        let rolling = class.rolling + class.rolling_slope * (speed_kmh / REFERENCE_SPEED).log10();
+        energy += 10f32.powf(rolling / 10.0);
+    }
+    10.0 * energy.log10()
+}
+
+/// One cell of the noise map
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoiseCell {
+    pub col: i32, // cells from the world origin, x then y
+    pub row: i32,
+    pub center: Point,
+    pub level: f32, // dB(A), sound power emitted in the cell averaged over the run
+}
+
+/// Noise emitted by traffic over a run, accumulated on a grid of square cells. Each tick
+/// every car adds the energy of its sound power level in the cell it is in; a cell's level
+/// is that energy averaged over the time recorded, so quiet stretches count too. Cells no
+/// car has been in are not kept.
+#[derive(Debug, Clone)]
+pub struct NoiseMap {
+    cell_size: f32,
+    heavy_types: Vec<String>, // car types emitting as heavy vehicles
+    energy: HashMap<(i32, i32), f64>, // sum of 10^(L/10) times seconds, by cell
+    duration: f32, // seconds recorded
+    last_time: f32,
+}
+
+impl NoiseMap {
+    pub fn new(cars_config: &CarsConfig, cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            heavy_types: cars_config.car_types.iter()
+                .filter(|car_type| car_type.heavy)
+                .map(|car_type| car_type.id.clone())
+                .collect(),
+            energy: HashMap::new(),
+            duration: 0.0,
+            last_time: 0.0,
+        }
+    }
+    
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+    
+    /// Seconds of simulation the map covers
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+    
+    /// Forget everything recorded
+    pub fn clear(&mut self) {
+        self.energy.clear();
+        self.duration = 0.0;
+        self.last_time = 0.0;
+    }
+    
+    /// Take in the tick that produced `state`. Call once for every tick.
+    pub fn record(&mut self, state: &SimulationState) {
+        // A reset or loaded checkpoint starts the map over
+        if state.time < self.last_time {
+            self.clear();
+        }
+        self.last_time = state.time;
+        self.duration += state.dt;
+        let heavy: Vec<bool> = state.names.car_types.ids()
+            .map(|id| self.heavy_types.iter().any(|heavy| heavy == state.names.car_type(id)))
+            .collect();
+        for car in &state.cars {
+            let level = self.car_level(car, heavy.get(car.car_type.index()).copied().unwrap_or(false));
+            let cell = self.cell_of(car.position);
+            *self.energy.entry(cell).or_insert(0.0) += 10f64.powf(level as f64 / 10.0) * state.dt as f64;
+        }
+    }
+    
+    fn car_level(&self, car: &Car, heavy: bool) -> f32 {
+        let speed = car.velocity.magnitude();
+        // Acceleration along the direction of travel, braking counts as negative
+        let acceleration = if speed > 0.1 { car.acceleration.dot(&car.velocity) / speed } else { car.acceleration.magnitude() };
+        vehicle_noise_level(speed, acceleration, heavy)
+    }
+    
+    fn cell_of(&self, position: Point) -> (i32, i32) {
+        ((position.x / self.cell_size).floor() as i32, (position.y / self.cell_size).floor() as i32)
+    }
+    
+    /// Level of the cell `position` is in, `None` if no car has been there
+    pub fn level_at(&self, position: Point) -> Option<f32> {
+        let energy = self.energy.get(&self.cell_of(position))?;
+        Some(self.level(*energy))
+    }
+    
+    fn level(&self, energy: f64) -> f32 {
+        (10.0 * (energy / self.duration.max(f32::EPSILON) as f64).log10()) as f32
+    }
+    
+    /// Every cell a car has been in, by row then column
+    pub fn cells(&self) -> Vec<NoiseCell> {
+        let mut cells: Vec<NoiseCell> = self.energy.iter()
+            .map(|(&(col, row), &energy)| NoiseCell {
+                col,
+                row,
+                center: Point::new((col as f32 + 0.5) * self.cell_size, (row as f32 + 0.5) * self.cell_size),
+                level: self.level(energy),
+            })
+            .collect();
+        cells.sort_by_key(|cell| (cell.row, cell.col));
+        cells
+    }
+    
+    /// Loudest cell's level, `None` for an empty map
+    pub fn max_level(&self) -> Option<f32> {
+        self.energy.values().copied().reduce(f64::max).map(|energy| self.level(energy))
+    }
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Simulation seconds between remembered glimpses of the car ahead
+const PERCEPTION_INTERVAL: f32 = 0.1;
+
+/// The car ahead as a driver saw it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Lead {
+    pub distance: f32, // meters
+    pub speed: f32,    // m/s
+}
+
+/// What a driver has seen of the road ahead lately. With reaction delay on, drivers act on
+/// the car ahead as it was a reaction time ago rather than as it is now, and a distracted
+/// driver takes longer still.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Perception {
+    samples: VecDeque<(f32, Option<Lead>)>, // simulation time and the car ahead then, oldest first
+    pub distracted_until: Option<f32>,      // simulation time the driver looks back at the road
+}
+
+impl Perception {
+    pub fn is_distracted(&self, time: f32) -> bool {
+        self.distracted_until.is_some_and(|until| time < until)
+    }
+    
+    /// Remember the car ahead at `time`, once a perception interval has passed since the
+    /// last glimpse, forgetting what is older than `memory` seconds
+    pub fn record(&mut self, time: f32, lead: Option<Lead>, memory: f32) {
+        let due = self.samples.back().is_none_or(|(last, _)| time - last >= PERCEPTION_INTERVAL - 1e-4);
+        if due {
+            self.samples.push_back((time, lead));
+        }
+        // Keep the newest glimpse that is at least `memory` old, it is what the slowest reaction acts on
+        while self.samples.get(1).is_some_and(|(sample_time, _)| *sample_time <= time - memory) {
+            self.samples.pop_front();
+        }
+    }
+    
+    /// The car ahead as seen `delay` seconds before `time`. Drivers newer to the road than
+    /// that act on their first glimpse, or on `current` before they had one.
+    pub fn recall(&self, time: f32, delay: f32, current: Option<Lead>) -> Option<Lead> {
+        let later = self.samples.partition_point(|(sample_time, _)| *sample_time <= time - delay);
+        match self.samples.get(later.saturating_sub(1)) {
+            Some((_, lead)) => *lead,
+            None => current,
+        }
+    }
+    
+    /// Forget everything seen, keeping the sample buffer for a new driver
+    pub fn forget(&mut self) {
+        self.samples.clear();
+        self.distracted_until = None;
+    }
+}
@@ -1,22 +1,36 @@
-use super::{Car, Vec2, Point, SimulationState};
-use crate::config::{RouteConfig, CollisionAvoidance};
+use super::{Car, CarColumns, CarId, Footprint, Lead, TAILGATE_HEADWAY_FACTOR, Vec2, Point, SimulationState, SimulationEvent, Weather, ExitRamps, SpatialIndex, SignalPhase, GridPath, RESERVATION_DISTANCE, bus_stop_ahead, closure_ahead};
+use crate::config::{RouteConfig, RouteGeometry, CollisionAvoidance, ReactionConfig};
 use nalgebra::{Point2, Vector2};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::f32::consts::PI;
 
+pub const GRAVITY: f32 = 9.81; // m/s^2
+const LANE_CENTERED_TOLERANCE: f32 = 0.05; // meters from the lane middle at which a lane change is complete
+
 pub struct PhysicsEngine {
     collision_avoidance: CollisionAvoidance,
+    reaction: ReactionConfig,
+    exit_ramps: ExitRamps,
     route: RouteConfig,
+    columns: CarColumns, // Cars as of the start of the step, for the neighbor searches
+    index: SpatialIndex, // Over `columns`
 }
 
 impl PhysicsEngine {
-    pub fn new(route: RouteConfig, collision_avoidance: CollisionAvoidance) -> Self {
+    pub fn new(route: RouteConfig, collision_avoidance: CollisionAvoidance, reaction: ReactionConfig) -> Self {
+        let index = SpatialIndex::new(collision_avoidance.warning_distance);
         Self {
             collision_avoidance,
+            reaction,
+            exit_ramps: ExitRamps::from_route(&route),
             route,
+            columns: CarColumns::new(),
+            index,
         }
     }
     
-    pub fn update(&self, state: &mut SimulationState) {
+    pub fn update(&mut self, state: &mut SimulationState) {
         let dt = state.dt;
         
         if !state.cars.is_empty() {
@@ -25,29 +39,45 @@ impl PhysicsEngine {
         
         // Update car physics in parallel-safe manner
         let mut updates = Vec::with_capacity(state.cars.len());
+        self.columns.gather(&state.cars);
+        self.index.refill(&self.columns.positions, self.collision_avoidance.warning_distance);
         
         for car in &state.cars {
             log::debug!("Car {}: pos=({:.1},{:.1}) vel=({:.1},{:.1})", 
-                        car.id.0, car.position.x, car.position.y, car.velocity.x, car.velocity.y);
+                        car.id, car.position.x, car.position.y, car.velocity.x, car.velocity.y);
             let update = self.calculate_car_update(car, state, dt);
-            updates.push((car.id, update));
+            updates.push(update);
         }
         
-        // Apply updates
-        for (car_id, update) in updates {
-            if let Some(car) = state.get_car_mut(car_id) {
-                car.position = update.position;
-                car.velocity = update.velocity;
-                car.acceleration = update.acceleration;
-                car.heading = update.heading;
-                car.lane_change_progress = update.lane_change_progress;
-                
-                if update.lane_change_progress >= 1.0 {
-                    if let Some(target_lane) = car.target_lane {
-                        car.current_lane = target_lane;
-                        car.target_lane = None;
-                        car.lane_change_progress = 0.0;
-                    }
+        // Apply updates (in the same order as state.cars, so no lookup by id is needed)
+        for (car, update) in state.cars.iter_mut().zip(updates) {
+            car.position = update.position;
+            car.velocity = update.velocity;
+            car.acceleration = update.acceleration;
+            car.heading = update.heading;
+            car.lateral_offset = update.lateral_offset;
+            car.lateral_velocity = update.lateral_velocity;
+            car.leader = update.leader;
+            
+            if self.reaction.delay {
+                let memory = self.reaction.delay_for(car.behavior.reaction_time, true);
+                car.perception.record(state.time, update.lead, memory);
+            }
+            if let (Some(next_waypoint), Some(path)) = (update.next_waypoint, car.grid_path.as_mut()) {
+                path.next_waypoint = next_waypoint;
+            }
+            if let (Some(distance), Some(ramp)) = (update.ramp_distance, car.exit_ramp.as_mut()) {
+                ramp.distance = distance;
+            }
+            
+            // The lane change is over once the car has settled in the middle of its target lane
+            if let Some(target_lane) = car.target_lane {
+                let lane_width = self.route.route.geometry.lane_width;
+                if (car.lateral_offset - Self::lane_offset(car.current_lane, target_lane, lane_width)).abs() < LANE_CENTERED_TOLERANCE {
+                    car.current_lane = target_lane;
+                    car.target_lane = None;
+                    car.lateral_offset = 0.0;
+                    car.lateral_velocity = 0.0;
                 }
             }
         }
@@ -58,9 +88,31 @@ impl PhysicsEngine {
     fn calculate_car_update(&self, car: &Car, state: &SimulationState, dt: f32) -> CarUpdate {
         let route_geom = &self.route.route.geometry;
         
+        // Crashed and broken-down cars stay where they came to rest
+        if car.crashed || car.is_stranded() {
+            return CarUpdate::hold_position(car);
+        }
+        
+        // Exiting cars follow their off-ramp instead of the road
+        if let Some(motion) = self.exit_ramps.advance(car, state, self.braking_limit(car, state), dt) {
+            return CarUpdate {
+                position: motion.position,
+                velocity: motion.velocity,
+                acceleration: if dt > 0.0 { (motion.velocity - car.velocity) / dt } else { Vector2::zeros() },
+                heading: motion.heading,
+                lateral_offset: motion.lateral_offset,
+                lateral_velocity: 0.0,
+                next_waypoint: None,
+                ramp_distance: Some(motion.distance),
+                lead: None,
+                leader: None,
+            };
+        }
+        
         match route_geom.geometry_type.as_str() {
             "donut" => self.calculate_donut_update(car, state, dt),
             "cloverleaf" => self.calculate_cloverleaf_update(car, state, dt),
+            "grid" => self.calculate_grid_update(car, state, dt),
             _ => {
                 // Default to donut behavior
                 self.calculate_donut_update(car, state, dt)
@@ -75,36 +127,29 @@ impl PhysicsEngine {
         let center = Point2::new(route_geom.center_x, route_geom.center_y);
         let to_car = car.position - center;
         let current_angle = to_car.y.atan2(to_car.x);
-        let current_radius = to_car.magnitude();
-        
-        // Calculate target lane position
-        let target_radius = self.get_target_radius(car, route_geom);
         
         // Find nearest cars for collision avoidance
         let (front_car, front_distance) = self.find_front_car(car, state);
-        let following_distance = self.calculate_following_distance(car);
+        let lead = Self::lead(front_car, front_distance);
+        let following_distance = self.calculate_following_distance(car, state.weather);
+        let braking = self.braking_limit(car, state);
         
         // Calculate desired speed based on traffic and behavior
         let mut target_speed = car.behavior.target_speed;
         
-        // Check if car is in a spawn zone and should yield
-        target_speed = self.check_spawn_zone_yielding(car, state, target_speed);
+        // Drop back for a car waiting to merge ahead
+        target_speed = Self::apply_merge_yielding(car, state, target_speed, braking, dt);
         
         // Collision avoidance
-        if let Some(distance) = front_distance {
-            if distance < self.collision_avoidance.emergency_brake_distance {
-                target_speed = 0.0; // Emergency brake
-            } else if distance < self.collision_avoidance.warning_distance {
-                let brake_factor = (distance - self.collision_avoidance.emergency_brake_distance) 
-                    / (self.collision_avoidance.warning_distance - self.collision_avoidance.emergency_brake_distance);
-                target_speed *= brake_factor;
-            } else if distance < following_distance {
-                // Maintain following distance
-                if let Some(front_car) = front_car {
-                    target_speed = front_car.velocity.magnitude().min(target_speed);
-                }
-            }
-        }
+        target_speed = self.apply_collision_avoidance(car, target_speed, self.perceived_lead(car, state.time, lead), following_distance, state.weather);
+        
+        // Stop for red lights, before closed lanes and buses at their stops
+        target_speed = self.apply_signal_control(car, state, target_speed);
+        target_speed = self.apply_lane_closures(car, state, target_speed);
+        target_speed = self.apply_bus_stop(car, state, target_speed, braking);
+        target_speed = self.apply_curve_speed(car, state, target_speed, braking);
+        target_speed = Self::apply_breakdown(car, target_speed, braking, dt);
+        target_speed = self.apply_power_limit(car, target_speed, dt);
         
         // Calculate acceleration
         let current_speed = car.velocity.magnitude();
@@ -112,58 +157,28 @@ impl PhysicsEngine {
         let _acceleration_magnitude = if speed_diff > 0.0 {
             (speed_diff / dt).min(car.max_acceleration)
         } else {
-            (speed_diff / dt).max(-car.max_deceleration)
+            (speed_diff / dt).max(-braking)
         };
         
-        // Calculate new heading (tangent to circle)
-        let tangent_angle = current_angle + PI / 2.0;
-        let heading = if car.velocity.magnitude() > 0.1 {
-            car.velocity.y.atan2(car.velocity.x)
-        } else {
-            tangent_angle
-        };
-        
-        // Update lane change progress
-        let mut lane_change_progress = car.lane_change_progress;
-        if car.target_lane.is_some() {
-            lane_change_progress += dt / self.route.route.traffic_rules.lane_change_time;
-            lane_change_progress = lane_change_progress.min(1.0);
-        }
-        
-        // Calculate position based on current and target radius
-        let _lerp_radius = if car.target_lane.is_some() {
-            let current_target_radius = self.get_lane_radius(car.current_lane, route_geom);
-            let target_target_radius = self.get_lane_radius(car.target_lane.unwrap(), route_geom);
-            current_target_radius + (target_target_radius - current_target_radius) * lane_change_progress
-        } else {
-            target_radius
-        };
+        // Drift across lanes, outwards is towards higher lane numbers
+        let (lateral_offset, lateral_velocity) = self.integrate_lateral_motion(car, dt);
+        let radius = self.get_lane_radius(car.current_lane, route_geom) + lateral_offset;
         
-        // Calculate velocity (tangential + radial components)
+        // Update position using angular motion for circular path, counter-clockwise
         let tangential_speed = target_speed;
-        // For counter-clockwise motion around the circle
-        let tangent_dir = Vector2::new(-tangent_angle.sin(), tangent_angle.cos());
-        
-        // Add radial component for lane changes
-        let radial_component = if car.target_lane.is_some() {
-            let radial_speed = (target_radius - current_radius) / self.route.route.traffic_rules.lane_change_time;
-            let radial_dir = to_car.normalize();
-            radial_dir * radial_speed
-        } else {
-            Vector2::zeros()
-        };
-        
-        let new_velocity = tangent_dir * tangential_speed + radial_component;
-        
-        // Update position using angular motion for circular path
-        let center = Point2::new(route_geom.center_x, route_geom.center_y);
-        
-        // Calculate angular velocity from tangential speed
-        let angular_velocity = tangential_speed / target_radius;
+        let angular_velocity = tangential_speed / radius;
         let new_angle = current_angle + angular_velocity * dt;
+        let radial_dir = Vector2::new(new_angle.cos(), new_angle.sin());
+        let tangent_dir = Vector2::new(-new_angle.sin(), new_angle.cos());
+        let new_position = center + radius * radial_dir;
+        let new_velocity = tangent_dir * tangential_speed + radial_dir * lateral_velocity;
         
-        // Calculate new position on the circle
-        let new_position = center + target_radius * Vector2::new(new_angle.cos(), new_angle.sin());
+        // Heading follows the velocity, so cars visibly angle across during lane changes
+        let heading = if new_velocity.magnitude() > 0.1 {
+            new_velocity.y.atan2(new_velocity.x)
+        } else {
+            tangent_dir.y.atan2(tangent_dir.x)
+        };
         
         // Calculate acceleration vector
         let acceleration = if dt > 0.0 {
@@ -177,7 +192,12 @@ impl PhysicsEngine {
             velocity: new_velocity,
             acceleration,
             heading,
-            lane_change_progress,
+            lateral_offset,
+            lateral_velocity,
+            next_waypoint: None,
+            ramp_distance: None,
+            lead,
+            leader: Self::leader(car, front_car, front_distance),
         }
     }
     
@@ -186,32 +206,39 @@ impl PhysicsEngine {
         
         // Find nearest cars for collision avoidance
         let (front_car, front_distance) = self.find_front_car_straight(car, state);
-        let following_distance = self.calculate_following_distance(car);
+        let lead = Self::lead(front_car, front_distance);
+        let following_distance = self.calculate_following_distance(car, state.weather);
+        let braking = self.braking_limit(car, state);
         
         // Calculate desired speed based on traffic and behavior with driver profile acceleration
         let mut target_speed = self.calculate_driver_profile_target_speed(car, state);
         
-        // Check if car is in a spawn zone and should yield
-        target_speed = self.check_spawn_zone_yielding(car, state, target_speed);
+        // Drop back for a car waiting to merge ahead
+        target_speed = Self::apply_merge_yielding(car, state, target_speed, braking, dt);
         
         // Collision avoidance
-        if let Some(distance) = front_distance {
-            if distance < self.collision_avoidance.emergency_brake_distance {
-                target_speed = 0.0; // Emergency brake
-            } else if distance < self.collision_avoidance.warning_distance {
-                let brake_factor = (distance - self.collision_avoidance.emergency_brake_distance) 
-                    / (self.collision_avoidance.warning_distance - self.collision_avoidance.emergency_brake_distance);
-                target_speed *= brake_factor;
-            } else if distance < following_distance {
-                // Maintain following distance
-                if let Some(front_car) = front_car {
-                    target_speed = front_car.velocity.magnitude().min(target_speed);
-                }
-            }
-        }
+        target_speed = self.apply_collision_avoidance(car, target_speed, self.perceived_lead(car, state.time, lead), following_distance, state.weather);
+        
+        // Stop for red lights
+        target_speed = self.apply_signal_control(car, state, target_speed);
+        target_speed = Self::apply_breakdown(car, target_speed, braking, dt);
+        target_speed = self.apply_power_limit(car, target_speed, dt);
         
         // Determine path type based on lane number
-        let (_path_direction, new_position, new_velocity, heading) = self.calculate_cloverleaf_path(car, target_speed, dt);
+        let (_path_direction, mut new_position, mut new_velocity, mut heading) = self.calculate_cloverleaf_path(car, car.current_lane, target_speed, dt);
+        
+        // Drift across lanes, highway lane numbers increase along +x (north-south) or +y (east-west)
+        let (lateral_offset, lateral_velocity) = self.integrate_lateral_motion(car, dt);
+        let lateral_axis = match car.current_lane {
+            1..=6 => Vector2::new(1.0, 0.0),
+            7..=12 => Vector2::new(0.0, 1.0),
+            _ => Vector2::zeros(), // Loop ramps have a single lane
+        };
+        new_position += lateral_axis * lateral_offset;
+        new_velocity += lateral_axis * lateral_velocity;
+        if new_velocity.magnitude() > 0.1 {
+            heading = new_velocity.y.atan2(new_velocity.x);
+        }
         
         // Calculate acceleration vector
         let acceleration = if dt > 0.0 {
@@ -225,10 +252,329 @@ impl PhysicsEngine {
             velocity: new_velocity,
             acceleration,
             heading,
-            lane_change_progress: car.lane_change_progress,
+            lateral_offset,
+            lateral_velocity,
+            next_waypoint: None,
+            ramp_distance: None,
+            lead,
+            leader: Self::leader(car, front_car, front_distance),
+        }
+    }
+    
+    fn calculate_grid_update(&self, car: &Car, state: &SimulationState, dt: f32) -> CarUpdate {
+        // Grid cars follow their planned path of cell centers from spawn to exit
+        let Some(path) = &car.grid_path else {
+            // No path (e.g. restored from an older snapshot) - hold position until despawned
+            return CarUpdate::hold_position(car);
+        };
+        
+        let (front_car, front_distance) = self.find_front_car_on_path(car, path, state);
+        let lead = Self::lead(front_car, front_distance);
+        let following_distance = self.calculate_following_distance(car, state.weather);
+        let braking = self.braking_limit(car, state);
+        
+        let mut target_speed = car.behavior.target_speed;
+        target_speed = Self::apply_merge_yielding(car, state, target_speed, braking, dt);
+        target_speed = self.apply_collision_avoidance(car, target_speed, self.perceived_lead(car, state.time, lead), following_distance, state.weather);
+        target_speed = self.apply_signal_control(car, state, target_speed);
+        target_speed = self.apply_intersection_control(car, state, target_speed, braking);
+        target_speed = self.apply_junction_reservations(car, state, target_speed, braking);
+        target_speed = self.apply_curve_speed(car, state, target_speed, braking);
+        target_speed = Self::apply_breakdown(car, target_speed, braking, dt);
+        target_speed = self.apply_power_limit(car, target_speed, dt);
+        
+        // Grid streets are slow enough that acceleration limits matter
+        let current_speed = car.velocity.magnitude();
+        let speed_change = (target_speed - current_speed).clamp(-braking * dt, car.max_acceleration * dt);
+        let new_speed = (current_speed + speed_change).max(0.0);
+        
+        let (new_position, next_waypoint) = path.advance(car.position, new_speed * dt);
+        
+        let moved = new_position - car.position;
+        let direction = if moved.magnitude() > 1e-4 {
+            moved.normalize()
+        } else {
+            path.direction_from(&car.position)
+                .unwrap_or_else(|| Vector2::new(car.heading.cos(), car.heading.sin()))
+        };
+        
+        let new_velocity = direction * new_speed;
+        let heading = direction.y.atan2(direction.x);
+        
+        let acceleration = if dt > 0.0 {
+            (new_velocity - car.velocity) / dt
+        } else {
+            Vector2::zeros()
+        };
+        
+        CarUpdate {
+            position: new_position,
+            velocity: new_velocity,
+            acceleration,
+            heading,
+            lateral_offset: car.lateral_offset,
+            lateral_velocity: 0.0,
+            next_waypoint: Some(next_waypoint),
+            ramp_distance: None,
+            lead,
+            leader: Self::leader(car, front_car, front_distance),
+        }
+    }
+    
+    fn find_front_car_on_path<'a>(&self, car: &Car, path: &GridPath, state: &'a SimulationState) -> (Option<&'a Car>, Option<f32>) {
+        // Any car sitting on the remaining path counts, including cars crossing it at a merge
+        let lateral_tolerance = self.route.route.geometry.lane_width;
+        let lookahead = self.collision_avoidance.warning_distance;
+        // The path scan can overshoot the lookahead by up to one cell
+        let search_radius = lookahead + self.route.route.geometry.cell_size.unwrap_or(20.0) + lateral_tolerance;
+        
+        let columns = &self.columns;
+        let mut closest_car = None;
+        let mut closest_distance = f32::INFINITY;
+        
+        for i in self.index.query(&car.position, search_radius) {
+            if columns.ids[i] == car.id || columns.on_shoulder[i] {
+                continue;
+            }
+            
+            let other_position = columns.positions[i];
+            if let Some(distance) = path.distance_along_to(&car.position, &other_position, lateral_tolerance, lookahead) {
+                // When two cars block each other at a merge the older car goes first, otherwise both would wait forever
+                let mutually_blocked = state.cars[i].grid_path.as_ref().is_some_and(|other_path| {
+                    other_path.distance_along_to(&other_position, &car.position, lateral_tolerance, lookahead).is_some()
+                });
+                if mutually_blocked && car.id < columns.ids[i] {
+                    continue;
+                }
+                
+                if distance < closest_distance {
+                    closest_distance = distance;
+                    closest_car = Some(i);
+                }
+            }
+        }
+        
+        match closest_car {
+            Some(i) => (Some(&state.cars[i]), Some(closest_distance)),
+            None => (None, None),
+        }
+    }
+    
+    fn apply_signal_control(&self, car: &Car, state: &SimulationState, target_speed: f32) -> f32 {
+        let stop_margin = self.collision_avoidance.safety_margin;
+        let braking = self.braking_limit(car, state);
+        let comfortable_deceleration = braking * 0.5;
+        let current_speed = car.velocity.magnitude();
+        
+        let mut allowed_speed = target_speed;
+        for signal in &state.signals {
+            if signal.phase == SignalPhase::Green {
+                continue;
+            }
+            let Some(distance) = signal.distance_ahead(car) else {
+                continue;
+            };
+            
+            // Moving cars too close to stop with full braking are committed and clear the intersection
+            let braking_distance = current_speed * current_speed / (2.0 * braking);
+            if current_speed > 1.0 && distance - stop_margin < braking_distance {
+                continue;
+            }
+            
+            // Highest speed from which the car can still stop comfortably before the line
+            let stopping_speed = (2.0 * comfortable_deceleration * (distance - stop_margin).max(0.0)).sqrt();
+            allowed_speed = allowed_speed.min(stopping_speed);
+        }
+        
+        allowed_speed
+    }
+    
+    /// Cars without the right of way at a sign-controlled intersection slow to stop at the
+    /// stop line, and stay there until the intersection lets them go
+    fn apply_intersection_control(&self, car: &Car, state: &SimulationState, target_speed: f32, braking: f32) -> f32 {
+        let stop_margin = self.collision_avoidance.safety_margin;
+        state.intersections.iter()
+            .filter(|intersection| intersection.waiting.contains(&car.id))
+            .filter_map(|intersection| intersection.distance_to_stop_line(car))
+            .fold(target_speed, |allowed, distance| {
+                allowed.min((2.0 * braking * 0.5 * (distance - stop_margin).max(0.0)).sqrt())
+            })
+    }
+    
+    /// Cars nearing a junction without their movement through it reserved slow to stop
+    /// before entering it
+    fn apply_junction_reservations(&self, car: &Car, state: &SimulationState, target_speed: f32, braking: f32) -> f32 {
+        let stop_margin = self.collision_avoidance.safety_margin;
+        state.junctions.iter()
+            .filter(|junction| !junction.is_reserved(car.id))
+            .filter_map(|junction| junction.distance_ahead(car))
+            .filter(|&distance| distance > 0.0 && distance <= RESERVATION_DISTANCE)
+            .fold(target_speed, |allowed, distance| {
+                allowed.min((2.0 * braking * 0.5 * (distance - stop_margin).max(0.0)).sqrt())
+            })
+    }
+    
+    /// A closed stretch of lane blocks it like a red light that never turns green. Cars
+    /// already inside one, placed there or caught when it was closed, drive on out of it.
+    fn apply_lane_closures(&self, car: &Car, state: &SimulationState, target_speed: f32) -> f32 {
+        let Some(distance) = closure_ahead(&self.route, car, car.current_lane).filter(|&distance| distance > 0.0) else {
+            return target_speed;
+        };
+        let stop_distance = distance - car.length / 2.0 - self.collision_avoidance.safety_margin;
+        let comfortable_deceleration = self.braking_limit(car, state) * 0.5;
+        target_speed.min((2.0 * comfortable_deceleration * stop_distance.max(0.0)).sqrt())
+    }
+    
+    /// Buses pull up at the downstream end of their next stop's zone when in its lane, and
+    /// stay stopped there until their dwell time is over
+    fn apply_bus_stop(&self, car: &Car, state: &SimulationState, target_speed: f32, braking: f32) -> f32 {
+        let Some(bus) = &car.bus else {
+            return target_speed;
+        };
+        let Some(stop) = self.route.route.bus_stops.get(bus.next_stop) else {
+            return target_speed;
+        };
+        match bus.dwell_start {
+            Some(start) if state.time - start < stop.dwell_time => 0.0,
+            Some(_) => target_speed,
+            None if car.current_lane == stop.lane && car.target_lane.is_none() => {
+                let stop_distance = bus_stop_ahead(&self.route.route.geometry, car, stop) - car.length / 2.0;
+                target_speed.min((2.0 * braking * 0.5 * stop_distance.max(0.0)).sqrt())
+            }
+            None => target_speed,
         }
     }
     
+    /// Cars with a mass and engine power pick up speed no faster than their engine can push
+    /// them, minus the pull of the road grade. Heavy vehicles lose speed on steep climbs,
+    /// even when already at their target speed.
+    fn apply_power_limit(&self, car: &Car, target_speed: f32, dt: f32) -> f32 {
+        let (Some(mass), Some(power)) = (car.mass, car.engine_power) else {
+            return target_speed;
+        };
+        let current_speed = car.velocity.magnitude();
+        
+        // Traction rather than power limits acceleration at walking pace
+        let power_acceleration = power * 1000.0 / (mass * current_speed.max(1.0));
+        let grade_deceleration = GRAVITY * self.grade_at(car) / 100.0;
+        let acceleration = power_acceleration.min(car.max_acceleration) - grade_deceleration;
+        target_speed.min((current_speed + acceleration * dt).max(0.0))
+    }
+    
+    /// Broken-down cars lose power and coast to a stop under comfortable braking
+    fn apply_breakdown(car: &Car, target_speed: f32, braking: f32, dt: f32) -> f32 {
+        if car.breakdown.is_none() {
+            return target_speed;
+        }
+        let coasting_speed = (car.velocity.magnitude() - braking * 0.5 * dt).max(0.0);
+        target_speed.min(coasting_speed)
+    }
+    
+    /// Hardest the car can brake in the current weather. Climbing helps the brakes and
+    /// descending works against them.
+    fn braking_limit(&self, car: &Car, state: &SimulationState) -> f32 {
+        let grade_deceleration = GRAVITY * self.grade_at(car) / 100.0;
+        (state.weather.braking_limit(car, &self.route.route.surface) + grade_deceleration).max(0.5)
+    }
+    
+    /// Grade in percent under the car, from the ring's grade profile on donut routes
+    fn grade_at(&self, car: &Car) -> f32 {
+        let route_geom = &self.route.route.geometry;
+        let surface = &self.route.route.surface;
+        if route_geom.geometry_type != "donut" || surface.grade_profile.is_empty() {
+            return surface.grade;
+        }
+        let to_car = car.position - Point2::new(route_geom.center_x, route_geom.center_y);
+        surface.grade_at(to_car.y.atan2(to_car.x))
+    }
+    
+    /// Fastest the car can take a curve of `radius` meters: side friction in the weather,
+    /// or the rollover threshold of tall vehicles if lower, plus the help of the banking
+    fn curve_speed(&self, car: &Car, radius: f32, weather: Weather) -> f32 {
+        let surface = &self.route.route.surface;
+        let mut side_friction = surface.friction_coefficient * weather.friction_factor();
+        if let Some(threshold) = car.rollover_threshold {
+            side_friction = side_friction.min(threshold / GRAVITY);
+        }
+        let bank = surface.banking_angle.to_radians().tan();
+        let denominator = 1.0 - side_friction * bank;
+        if denominator <= 0.0 {
+            return f32::INFINITY;
+        }
+        (radius * GRAVITY * (side_friction + bank) / denominator).sqrt()
+    }
+    
+    /// Cars keep to the speed their lane's curve allows on the ring, and on grid streets
+    /// slow in time for the bends of their path through junctions
+    fn apply_curve_speed(&self, car: &Car, state: &SimulationState, target_speed: f32, braking: f32) -> f32 {
+        let route_geom = &self.route.route.geometry;
+        if route_geom.geometry_type == "donut" {
+            let radius = self.get_lane_radius(car.current_lane, route_geom);
+            return target_speed.min(self.curve_speed(car, radius, state.weather));
+        }
+        let Some(path) = &car.grid_path else {
+            return target_speed;
+        };
+        path.bends_ahead(&car.position, RESERVATION_DISTANCE).into_iter()
+            .fold(target_speed, |allowed, (distance, radius)| {
+                let curve_speed = self.curve_speed(car, radius, state.weather);
+                allowed.min((curve_speed * curve_speed + 2.0 * braking * 0.5 * distance).sqrt())
+            })
+    }
+    
+    /// Braking for the car ahead starts further back when the road is slippery, as stopping
+    /// distances grow with the loss of grip, and closer in for tailgating drivers
+    fn apply_collision_avoidance(&self, car: &Car, target_speed: f32, lead: Option<Lead>, following_distance: f32, weather: Weather) -> f32 {
+        let Some(Lead { distance, speed }) = lead else {
+            return target_speed;
+        };
+        let (emergency_brake_distance, mut warning_distance) = self.braking_distances(weather);
+        if car.behavior.tailgating {
+            warning_distance = emergency_brake_distance + (warning_distance - emergency_brake_distance) * TAILGATE_HEADWAY_FACTOR;
+        }
+        
+        if distance < emergency_brake_distance {
+            0.0 // Emergency brake
+        } else if distance < warning_distance {
+            let brake_factor = (distance - emergency_brake_distance) / (warning_distance - emergency_brake_distance);
+            target_speed * brake_factor
+        } else if distance < following_distance {
+            // Maintain following distance
+            speed.min(target_speed)
+        } else {
+            target_speed
+        }
+    }
+    
+    fn lead(front_car: Option<&Car>, front_distance: Option<f32>) -> Option<Lead> {
+        Some(Lead { distance: front_distance?, speed: front_car?.velocity.magnitude() })
+    }
+    
+    /// Id of the car ahead and the bumper-to-bumper gap to it
+    fn leader(car: &Car, front_car: Option<&Car>, front_distance: Option<f32>) -> Option<(CarId, f32)> {
+        let front_car = front_car?;
+        Some((front_car.id, front_distance? - (car.length + front_car.length) / 2.0))
+    }
+    
+    /// The car ahead as the driver acts on it: as it is now, or with reaction delay on, as
+    /// it was the driver's reaction time ago (longer while distracted)
+    fn perceived_lead(&self, car: &Car, time: f32, lead: Option<Lead>) -> Option<Lead> {
+        if !self.reaction.delay {
+            return lead;
+        }
+        let delay = self.reaction.delay_for(car.behavior.reaction_time, car.perception.is_distracted(time));
+        car.perception.recall(time, delay, lead)
+    }
+    
+    /// Emergency brake and warning distances scaled to the grip left in this weather
+    fn braking_distances(&self, weather: Weather) -> (f32, f32) {
+        let stopping_factor = 1.0 / weather.friction_factor();
+        (
+            self.collision_avoidance.emergency_brake_distance * stopping_factor,
+            self.collision_avoidance.warning_distance * stopping_factor,
+        )
+    }
+    
     fn calculate_driver_profile_target_speed(&self, car: &Car, state: &SimulationState) -> f32 {
         // Base target speed from behavior system
         let base_target_speed = car.behavior.target_speed;
@@ -241,7 +587,8 @@ impl PhysicsEngine {
         
         if is_recent_spawn && spawned_at_ramp_speed {
             // Apply driver profile-based acceleration behavior for cars entering from ramps
-            let acceleration_factor = match car.behavior_type.as_str() {
+            let behavior = state.names.behavior(car.behavior_type);
+            let acceleration_factor = match behavior {
                 "aggressive" => {
                     // Aggressive drivers accelerate quickly and aim for higher speeds
                     let target_fraction = (time_since_spawn / 15.0).min(1.0); // Reach target in 15 seconds
@@ -263,7 +610,7 @@ impl PhysicsEngine {
                     let target_fraction = (time_since_spawn / 25.0).min(1.0); // Reach target in 25 seconds
                     15.6 + (base_target_speed * 0.9 - 15.6) * target_fraction.powf(1.5) // Gradual acceleration
                 }
-                "normal" | _ => {
+                _ => {
                     // Normal drivers have standard acceleration curve
                     let target_fraction = (time_since_spawn / 20.0).min(1.0); // Reach target in 20 seconds
                     15.6 + (base_target_speed - 15.6) * target_fraction // Linear acceleration
@@ -271,7 +618,7 @@ impl PhysicsEngine {
             };
             
             // Ensure we don't exceed the base target speed (unless aggressive)
-            if car.behavior_type == "aggressive" {
+            if behavior == "aggressive" {
                 acceleration_factor.min(base_target_speed * 1.15) // Allow 15% overspeed for aggressive
             } else {
                 acceleration_factor.min(base_target_speed)
@@ -282,7 +629,7 @@ impl PhysicsEngine {
         }
     }
     
-    fn calculate_cloverleaf_path(&self, car: &Car, target_speed: f32, dt: f32) -> (String, Point, Vector2<f32>, f32) {
+    fn calculate_cloverleaf_path(&self, car: &Car, lane: u32, target_speed: f32, dt: f32) -> (String, Point, Vector2<f32>, f32) {
         // Right-side driving cloverleaf lane assignments:
         // North-South Highway:
         //   Lanes 1-3:  Southbound (top to bottom) on WEST side (-x)
@@ -295,10 +642,10 @@ impl PhysicsEngine {
         let highway_half_width = route_geom.highway_width.unwrap_or(40.0) / 2.0;
         let lane_separation = highway_half_width + 5.0; // Extra separation between opposite directions
         
-        match car.current_lane {
+        match lane {
             // North-South Southbound (lanes 1-3) - West side of highway
             1..=3 => {
-                let lane_offset = ((car.current_lane as i32) - 2) as f32 * route_geom.lane_width; // -3.5, 0, 3.5
+                let lane_offset = ((lane as i32) - 2) as f32 * route_geom.lane_width; // -3.5, 0, 3.5
                 let x_pos = -lane_separation + lane_offset; // West side with lane offset
                 let y_pos = car.position.y - target_speed * dt;
                 let heading = -std::f32::consts::PI / 2.0; // Pointing south
@@ -308,7 +655,7 @@ impl PhysicsEngine {
             }
             // North-South Northbound (lanes 4-6) - East side of highway
             4..=6 => {
-                let lane_offset = ((car.current_lane as i32) - 5) as f32 * route_geom.lane_width; // -3.5, 0, 3.5
+                let lane_offset = ((lane as i32) - 5) as f32 * route_geom.lane_width; // -3.5, 0, 3.5
                 let x_pos = lane_separation + lane_offset; // East side with lane offset
                 let y_pos = car.position.y + target_speed * dt;
                 let heading = std::f32::consts::PI / 2.0; // Pointing north
@@ -318,7 +665,7 @@ impl PhysicsEngine {
             }
             // East-West Westbound (lanes 7-9) - North side of highway
             7..=9 => {
-                let lane_offset = ((car.current_lane as i32) - 8) as f32 * route_geom.lane_width; // -3.5, 0, 3.5
+                let lane_offset = ((lane as i32) - 8) as f32 * route_geom.lane_width; // -3.5, 0, 3.5
                 let y_pos = lane_separation + lane_offset; // North side with lane offset
                 let x_pos = car.position.x - target_speed * dt;
                 let heading = std::f32::consts::PI; // Pointing west
@@ -328,7 +675,7 @@ impl PhysicsEngine {
             }
             // East-West Eastbound (lanes 10-12) - South side of highway
             10..=12 => {
-                let lane_offset = ((car.current_lane as i32) - 11) as f32 * route_geom.lane_width; // -3.5, 0, 3.5
+                let lane_offset = ((lane as i32) - 11) as f32 * route_geom.lane_width; // -3.5, 0, 3.5
                 let y_pos = -lane_separation + lane_offset; // South side with lane offset
                 let x_pos = car.position.x + target_speed * dt;
                 let heading = 0.0; // Pointing east
@@ -415,42 +762,63 @@ impl PhysicsEngine {
             Vector2::new(1.0, 0.0) // Default to eastward
         };
         
-        let mut closest_car: Option<&Car> = None;
+        let columns = &self.columns;
+        let mut closest_car = None;
         let mut closest_distance = f32::INFINITY;
         
-        for other_car in &state.cars {
-            if other_car.id == car.id {
+        for i in self.index.query(&car.position, self.front_car_lookahead(car, state.weather)) {
+            if columns.ids[i] == car.id || columns.on_shoulder[i] {
                 continue;
             }
             
-            // Only consider cars in same lane
-            if other_car.current_lane != car.current_lane {
+            // Only consider cars reaching into this car's lane or target lane
+            if !self.shares_lane(car, i) {
                 continue;
             }
             
-            let to_other = other_car.position - car.position;
+            let to_other = columns.positions[i] - car.position;
             let distance = to_other.magnitude();
             
             // Check if other car is in front (dot product > 0)
             if to_other.dot(&car_direction) > 0.0 && distance < closest_distance {
                 closest_distance = distance;
-                closest_car = Some(other_car);
+                closest_car = Some(i);
             }
         }
         
-        if closest_distance == f32::INFINITY {
-            (None, None)
-        } else {
-            (closest_car, Some(closest_distance))
+        match closest_car {
+            Some(i) => (Some(&state.cars[i]), Some(closest_distance)),
+            None => (None, None),
         }
     }
     
-    fn get_target_radius(&self, car: &Car, route_geom: &crate::config::RouteGeometry) -> f32 {
-        if let Some(target_lane) = car.target_lane {
-            self.get_lane_radius(target_lane, route_geom)
+    /// Lateral offset from the middle of `lane` to the middle of `other_lane`, positive towards
+    /// higher lane numbers
+    fn lane_offset(lane: u32, other_lane: u32, lane_width: f32) -> f32 {
+        (other_lane as f32 - lane as f32) * lane_width
+    }
+    
+    /// Steer sideways towards the middle of the target lane (or back to the middle of the current
+    /// one) as fast as the lateral acceleration limit allows while still arriving without overshoot.
+    /// Returns the new lateral offset and velocity.
+    fn integrate_lateral_motion(&self, car: &Car, dt: f32) -> (f32, f32) {
+        let route_geom = &self.route.route.geometry;
+        let rules = &self.route.route.traffic_rules;
+        let max_acceleration = rules.max_lateral_acceleration(route_geom.lane_width);
+        let target_offset = car.target_lane
+            .map(|target_lane| Self::lane_offset(car.current_lane, target_lane, route_geom.lane_width))
+            .unwrap_or(0.0);
+            
+        // Fastest approach from which the car can still brake to a stop at the target
+        let error = target_offset - car.lateral_offset;
+        let desired_velocity = error.signum() * (2.0 * max_acceleration * error.abs()).sqrt();
+        let acceleration = if dt > 0.0 {
+            ((desired_velocity - car.lateral_velocity) / dt).clamp(-max_acceleration, max_acceleration)
         } else {
-            self.get_lane_radius(car.current_lane, route_geom)
-        }
+            0.0
+        };
+        let lateral_velocity = car.lateral_velocity + acceleration * dt;
+        (car.lateral_offset + lateral_velocity * dt, lateral_velocity)
     }
     
     fn get_lane_radius(&self, lane: u32, route_geom: &crate::config::RouteGeometry) -> f32 {
@@ -464,21 +832,24 @@ impl PhysicsEngine {
         let to_car = car.position - center;
         let car_angle = to_car.y.atan2(to_car.x);
         
-        let mut closest_car: Option<&Car> = None;
+        // Arc distance is measured on this car's radius, cars in the target lane sit up to a lane further out
+        let search_radius = self.front_car_lookahead(car, state.weather) + 2.0 * route_geom.lane_width;
+        
+        let columns = &self.columns;
+        let mut closest_car = None;
         let mut closest_distance = f32::INFINITY;
         
-        for other_car in &state.cars {
-            if other_car.id == car.id {
+        for i in self.index.query(&car.position, search_radius) {
+            if columns.ids[i] == car.id || columns.on_shoulder[i] {
                 continue;
             }
             
-            // Only consider cars in same lane or target lane
-            if other_car.current_lane != car.current_lane && 
-               Some(other_car.current_lane) != car.target_lane {
+            // Only consider cars reaching into this car's lane or target lane
+            if !self.shares_lane(car, i) {
                 continue;
             }
             
-            let to_other = other_car.position - center;
+            let to_other = columns.positions[i] - center;
             let other_angle = to_other.y.atan2(to_other.x);
             
             // Calculate angular distance (accounting for wrap-around)
@@ -492,94 +863,257 @@ impl PhysicsEngine {
                 let arc_distance = angle_diff * to_car.magnitude();
                 if arc_distance < closest_distance {
                     closest_distance = arc_distance;
-                    closest_car = Some(other_car);
+                    closest_car = Some(i);
                 }
             }
         }
         
-        if closest_distance == f32::INFINITY {
-            (None, None)
+        match closest_car {
+            Some(i) => (Some(&state.cars[i]), Some(closest_distance)),
+            None => (None, None),
+        }
+    }
+    
+    /// Whether the car at `other` in the columns reaches into the lane `car` is in or changing into
+    fn shares_lane(&self, car: &Car, other: usize) -> bool {
+        let lane_width = self.route.route.geometry.lane_width;
+        self.columns.occupies_lane(other, car.current_lane, lane_width) ||
+            car.target_lane.is_some_and(|target_lane| self.columns.occupies_lane(other, target_lane, lane_width))
+    }
+    
+    /// A driver yielding to a car waiting at an entry drops back until there is room for
+    /// it between them and the car ahead, easing off with comfortable braking
+    fn apply_merge_yielding(car: &Car, state: &SimulationState, target_speed: f32, braking: f32, dt: f32) -> f32 {
+        let Some(merge) = state.merges.iter().find(|merge| merge.yielding == Some(car.id)) else {
+            return target_speed;
+        };
+        let comfortable_deceleration = braking * 0.5;
+        let leader_past = merge.leader.and_then(|id| state.get_car(id)).map(|leader| merge.distance_past(leader.position));
+        let room = merge.room(merge.distance_past(car.position), leader_past);
+        let yielding_speed = if room >= 0.0 {
+            (merge.speed.powi(2) + 2.0 * comfortable_deceleration * room).sqrt()
         } else {
-            (closest_car, Some(closest_distance))
+            // Too close, fall back from the car ahead as quickly as braking comfortably allows
+            (merge.speed - (2.0 * comfortable_deceleration * -room).sqrt()).max(0.0)
+        };
+        target_speed.min(yielding_speed.max(car.velocity.magnitude() - comfortable_deceleration * dt))
+    }
+    
+    /// Cars further ahead than this never affect collision avoidance
+    fn front_car_lookahead(&self, car: &Car, weather: Weather) -> f32 {
+        self.braking_distances(weather).1.max(self.calculate_following_distance(car, weather))
+    }
+    
+    /// Drivers leave longer gaps when the road is slippery or visibility is poor
+    fn calculate_following_distance(&self, car: &Car, weather: Weather) -> f32 {
+        let base_distance = self.route.route.traffic_rules.following_distance * weather.headway_factor() * car.velocity.magnitude();
+        base_distance * car.behavior.headway_factor() + self.collision_avoidance.safety_margin
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CarUpdate {
+    position: Point,
+    velocity: Vec2,
+    acceleration: Vec2,
+    heading: f32,
+    lateral_offset: f32,
+    lateral_velocity: f32,
+    next_waypoint: Option<usize>, // Grid path progress
+    ramp_distance: Option<f32>, // Off-ramp progress
+    lead: Option<Lead>, // The car ahead as it is now
+    leader: Option<(CarId, f32)>, // Which car that is, and the gap to it
+}
+
+impl CarUpdate {
+    fn hold_position(car: &Car) -> Self {
+        Self {
+            position: car.position,
+            velocity: Vector2::zeros(),
+            acceleration: Vector2::zeros(),
+            heading: car.heading,
+            lateral_offset: car.lateral_offset,
+            lateral_velocity: 0.0,
+            next_waypoint: None,
+            ramp_distance: None,
+            lead: None,
+            leader: None,
+        }
+    }
+}
+
+/// Two cars whose bodies started overlapping during a tick
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollisionEvent {
+    pub car_a: CarId,
+    pub car_b: CarId,
+    pub position_a: Point,
+    pub position_b: Point,
+    pub relative_speed: f32, // m/s at the moment of impact
+    pub time: f32,
+}
+
+/// Gap within which two cars passing after a blind spot miss count as a near miss
+const NEAR_MISS_GAP: f32 = 1.0;
+
+/// A lane change started without seeing a car in the blind spot, followed until it is over
+struct WatchedMiss {
+    car: CarId,
+    overlooked: CarId,
+    closest: f32, // smallest gap between the two so far, meters
+}
+
+/// Detects car-to-car collisions by testing oriented bounding boxes after each physics step.
+/// A pair that stays in contact over several ticks is reported once. Lane changes started
+/// with a car overlooked in the blind spot end either in a collision or, when the two came
+/// within `NEAR_MISS_GAP` of each other, a near miss. Cars on lanes that pass over one
+/// another at different levels never collide.
+pub struct CollisionDetector {
+    crash_response: String,
+    geometry: RouteGeometry, // For the levels of the lanes
+    contacts: HashSet<(CarId, CarId)>, // Car id pairs overlapping on the previous tick
+    watched: Vec<WatchedMiss>,
+    columns: CarColumns, // Cars as of the latest check
+    index: SpatialIndex, // Over `columns`
+}
+
+impl CollisionDetector {
+    pub fn new(collision_avoidance: &CollisionAvoidance, geometry: &RouteGeometry) -> Self {
+        Self {
+            crash_response: collision_avoidance.crash_response.clone(),
+            geometry: geometry.clone(),
+            contacts: HashSet::new(),
+            watched: Vec::new(),
+            columns: CarColumns::new(),
+            index: SpatialIndex::new(1.0),
         }
     }
     
-    fn check_spawn_zone_yielding(&self, car: &Car, _state: &SimulationState, target_speed: f32) -> f32 {
-        // Check if this car is near any spawn points and should yield for incoming traffic
-        let route_geom = &self.route.route.geometry;
-        let spawn_yield_distance = 20.0; // Distance from spawn points where cars should yield
-        
-        for entry in &self.route.route.entries {
-            let entry_pos = match route_geom.geometry_type.as_str() {
-                "donut" => {
-                    let center = Point2::new(route_geom.center_x, route_geom.center_y);
-                    let angle_rad = entry.angle.to_radians();
-                    let radius = route_geom.inner_radius + (entry.lane as f32 - 1.0) * route_geom.lane_width + route_geom.lane_width / 2.0;
-                    center + Vector2::new(radius * angle_rad.cos(), radius * angle_rad.sin())
+    /// Forget contacts from a previous run, e.g. after loading a checkpoint
+    pub fn reset(&mut self) {
+        self.contacts.clear();
+        self.watched.clear();
+    }
+    
+    pub fn update(&mut self, state: &mut SimulationState) {
+        state.collision_events.clear();
+        if state.cars.len() < 2 {
+            self.contacts.clear();
+            self.watch_blind_spot_misses(state);
+            return;
+        }
+        
+        // Two boxes can only overlap if their centers are closer than the largest car diagonal
+        let columns = &mut self.columns;
+        columns.gather(&state.cars);
+        let max_diagonal = columns.lengths.iter().zip(&columns.widths)
+            .map(|(length, width)| (length * length + width * width).sqrt())
+            .fold(0.0, f32::max);
+        self.index.refill(&columns.positions, max_diagonal);
+        
+        let mut contacts = HashSet::new();
+        for i in 0..columns.len() {
+            for j in self.index.query(&columns.positions[i], max_diagonal) {
+                if j <= i || self.geometry.grade_separated(columns.lanes[i], columns.lanes[j]) {
+                    continue;
                 }
-                "cloverleaf" => {
-                    // Use the same logic as in traffic manager
-                    let highway_extent = 250.0;
-                    let lane_width = route_geom.lane_width;
-                    let highway_half_width = route_geom.highway_width.unwrap_or(40.0) / 2.0;
-                    let lane_separation = highway_half_width + 5.0;
-                    
-                    match entry.lane {
-                        1..=3 => {
-                            let lane_offset = ((entry.lane as i32) - 2) as f32 * lane_width;
-                            let x_pos = -lane_separation + lane_offset;
-                            Point2::new(x_pos, highway_extent)
-                        }
-                        4..=6 => {
-                            let lane_offset = ((entry.lane as i32) - 5) as f32 * lane_width;
-                            let x_pos = lane_separation + lane_offset;
-                            Point2::new(x_pos, -highway_extent)
-                        }
-                        7..=9 => {
-                            let lane_offset = ((entry.lane as i32) - 8) as f32 * lane_width;
-                            let y_pos = lane_separation + lane_offset;
-                            Point2::new(highway_extent, y_pos)
-                        }
-                        10..=12 => {
-                            let lane_offset = ((entry.lane as i32) - 11) as f32 * lane_width;
-                            let y_pos = -lane_separation + lane_offset;
-                            Point2::new(-highway_extent, y_pos)
-                        }
-                        _ => Point2::new(0.0, 0.0)
-                    }
+                if !oriented_boxes_overlap(&columns.footprint(i), &columns.footprint(j)) {
+                    continue;
                 }
-                _ => Point2::new(0.0, 0.0)
-            };
-            
-            let distance_to_spawn = (car.position - entry_pos).magnitude();
-            
-            // If car is close to spawn point and in same lane, apply yielding behavior
-            if distance_to_spawn < spawn_yield_distance && car.current_lane == entry.lane {
-                // Reduce speed based on proximity to spawn point
-                let proximity_factor = 1.0 - (distance_to_spawn / spawn_yield_distance).min(1.0);
-                let speed_reduction = 0.4 * proximity_factor; // Up to 40% speed reduction
-                let yielding_speed = target_speed * (1.0 - speed_reduction);
                 
-                log::debug!("Car {} yielding for spawn zone at distance {:.1}m, speed reduced from {:.1} to {:.1} m/s", 
-                           car.id.0, distance_to_spawn, target_speed, yielding_speed);
-                return yielding_speed.max(5.0); // Minimum speed of 5 m/s
+                let (id_a, id_b) = (columns.ids[i], columns.ids[j]);
+                let pair = (id_a.min(id_b), id_a.max(id_b));
+                contacts.insert(pair);
+                if !self.contacts.contains(&pair) {
+                    state.collision_events.push(CollisionEvent {
+                        car_a: id_a,
+                        car_b: id_b,
+                        position_a: columns.positions[i],
+                        position_b: columns.positions[j],
+                        relative_speed: (columns.velocities[i] - columns.velocities[j]).magnitude(),
+                        time: state.time,
+                    });
+                }
             }
         }
+        self.contacts = contacts;
+        self.watch_blind_spot_misses(state);
         
-        target_speed
+        state.total_collisions += state.collision_events.len() as u32;
+        for event in state.collision_events.clone() {
+            state.events.push(SimulationEvent::CollisionDetected(event.clone()));
+            log::debug!("Collision between car {} and car {} at {:.1} m/s", event.car_a, event.car_b, event.relative_speed);
+            self.apply_crash_response(&event, state);
+        }
     }
     
-    fn calculate_following_distance(&self, car: &Car) -> f32 {
-        let base_distance = self.route.route.traffic_rules.following_distance * car.velocity.magnitude();
-        base_distance * car.behavior.following_distance_factor + self.collision_avoidance.safety_margin
+    /// Follow the lane changes of this tick's blind spot misses, and report the ones over
+    /// whose cars came close without touching as near misses
+    fn watch_blind_spot_misses(&mut self, state: &mut SimulationState) {
+        for event in &state.events {
+            if let SimulationEvent::BlindSpotMiss { car, overlooked, .. } = event {
+                self.watched.push(WatchedMiss { car: *car, overlooked: *overlooked, closest: f32::INFINITY });
+            }
+        }
+        
+        let mut near_misses = Vec::new();
+        self.watched.retain_mut(|watched| {
+            let pair = (watched.car.min(watched.overlooked), watched.car.max(watched.overlooked));
+            if self.contacts.contains(&pair) {
+                return false; // Reported as a collision
+            }
+            let (Some(car), Some(other)) = (state.get_car(watched.car), state.get_car(watched.overlooked)) else {
+                return false;
+            };
+            watched.closest = watched.closest.min(box_separation(&car.footprint(), &other.footprint()));
+            if car.target_lane.is_some() {
+                return true;
+            }
+            if watched.closest < NEAR_MISS_GAP {
+                near_misses.push(SimulationEvent::NearMiss {
+                    car_a: watched.car,
+                    car_b: watched.overlooked,
+                    gap: watched.closest,
+                    time: state.time,
+                });
+            }
+            false
+        });
+        state.events.extend(near_misses);
+    }
+    
+    fn apply_crash_response(&self, event: &CollisionEvent, state: &mut SimulationState) {
+        for id in [event.car_a, event.car_b] {
+            if self.crash_response == "remove" {
+                state.remove_car(id);
+            } else if let Some(car) = state.get_car_mut(id) {
+                car.last_collision_time = Some(event.time);
+                if self.crash_response == "halt" {
+                    car.crashed = true;
+                    car.velocity = Vector2::zeros();
+                    car.acceleration = Vector2::zeros();
+                    car.target_lane = None;
+                }
+            }
+        }
     }
 }
 
-#[derive(Debug, Clone)]
-struct CarUpdate {
-    position: Point,
-    velocity: Vec2,
-    acceleration: Vec2,
-    heading: f32,
-    lane_change_progress: f32,
+/// Separating axis test between the footprints of two cars
+fn oriented_boxes_overlap(a: &Footprint, b: &Footprint) -> bool {
+    box_separation(a, b) < 0.0
+}
+
+/// Widest gap between the footprints of two cars along any of their axes, negative when
+/// they overlap
+fn box_separation(a: &Footprint, b: &Footprint) -> f32 {
+    let axes_a = [Vector2::new(a.heading.cos(), a.heading.sin()), Vector2::new(-a.heading.sin(), a.heading.cos())];
+    let axes_b = [Vector2::new(b.heading.cos(), b.heading.sin()), Vector2::new(-b.heading.sin(), b.heading.cos())];
+    let offset = b.position - a.position;
+    
+    axes_a.iter().chain(axes_b.iter()).map(|axis| {
+        let extent_a = a.length / 2.0 * axes_a[0].dot(axis).abs() + a.width / 2.0 * axes_a[1].dot(axis).abs();
+        let extent_b = b.length / 2.0 * axes_b[0].dot(axis).abs() + b.width / 2.0 * axes_b[1].dot(axis).abs();
+        offset.dot(axis).abs() - (extent_a + extent_b)
+    }).fold(f32::NEG_INFINITY, f32::max)
 }
\ No newline at end of file
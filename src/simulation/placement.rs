@@ -0,0 +1,95 @@
+use super::{Point, Vec2};
+use crate::config::{RouteGeometry, CLOVERLEAF_HIGHWAY_EXTENT};
+use std::f32::consts::PI;
+
+/// A spot on the center line of a lane where a car can be put down
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LanePlacement {
+    pub position: Point,
+    pub lane: u32,
+    pub heading: f32, // radians, the direction traffic in the lane drives
+}
+
+/// The lane under `point` and the nearest spot on its center line. `None` off the road,
+/// and on cloverleaf ramps and grids, where cars follow paths planned from their entry.
+pub fn place_on_lane(geometry: &RouteGeometry, point: Point) -> Option<LanePlacement> {
+    match geometry.geometry_type.as_str() {
+        "donut" => place_on_donut(geometry, point),
+        "cloverleaf" => place_on_cloverleaf(geometry, point),
+        _ => None,
+    }
+}
+
+/// The spot `distance` meters along `lane` from where its traffic starts: counter-clockwise
+/// from angle 0 around a donut, from the highway end on a cloverleaf's through lanes.
+/// `None` on grids and for lanes the route does not have.
+pub fn lane_point(geometry: &RouteGeometry, lane: u32, distance: f32) -> Option<LanePlacement> {
+    if lane == 0 || lane > geometry.lane_count {
+        return None;
+    }
+    match geometry.geometry_type.as_str() {
+        "donut" => {
+            let center = Point::new(geometry.center_x, geometry.center_y);
+            let radius = geometry.inner_radius + (lane as f32 - 0.5) * geometry.lane_width;
+            let angle = distance / radius;
+            Some(LanePlacement {
+                position: center + Vec2::new(angle.cos(), angle.sin()) * radius,
+                lane,
+                heading: angle + PI / 2.0,
+            })
+        }
+        "cloverleaf" if lane <= 12 => {
+            let separation = geometry.highway_width.unwrap_or(40.0) / 2.0 + 5.0;
+            let offset = ((lane - 1) % 3) as f32 * geometry.lane_width - geometry.lane_width;
+            let along = CLOVERLEAF_HIGHWAY_EXTENT - distance;
+            let (position, heading) = match lane {
+                1..=3 => (Point::new(-separation + offset, along), -PI / 2.0),
+                4..=6 => (Point::new(separation + offset, -along), PI / 2.0),
+                7..=9 => (Point::new(along, separation + offset), PI),
+                _ => (Point::new(-along, -separation + offset), 0.0),
+            };
+            Some(LanePlacement { position, lane, heading })
+        }
+        _ => None,
+    }
+}
+
+fn place_on_donut(geometry: &RouteGeometry, point: Point) -> Option<LanePlacement> {
+    let center = Point::new(geometry.center_x, geometry.center_y);
+    let to_point = point - center;
+    let across = to_point.magnitude() - geometry.inner_radius;
+    if across < 0.0 || across >= geometry.lane_count as f32 * geometry.lane_width {
+        return None;
+    }
+    
+    // Lanes count outwards from the inner edge, traffic runs counter-clockwise
+    let lane = (across / geometry.lane_width) as u32 + 1;
+    let radius = geometry.inner_radius + (lane as f32 - 0.5) * geometry.lane_width;
+    let angle = to_point.y.atan2(to_point.x);
+    Some(LanePlacement {
+        position: center + to_point.normalize() * radius,
+        lane,
+        heading: angle + PI / 2.0,
+    })
+}
+
+fn place_on_cloverleaf(geometry: &RouteGeometry, point: Point) -> Option<LanePlacement> {
+    // Same layout as the physics: four blocks of three lanes, either side of the origin
+    let separation = geometry.highway_width.unwrap_or(40.0) / 2.0 + 5.0;
+    (1..=12u32)
+        .filter_map(|lane| {
+            let offset = ((lane - 1) % 3) as f32 * geometry.lane_width - geometry.lane_width;
+            let (position, heading) = match lane {
+                1..=3 => (Point::new(-separation + offset, point.y), -PI / 2.0),
+                4..=6 => (Point::new(separation + offset, point.y), PI / 2.0),
+                7..=9 => (Point::new(point.x, separation + offset), PI),
+                _ => (Point::new(point.x, -separation + offset), 0.0),
+            };
+            Some((position - point).magnitude())
+                .filter(|distance| *distance < geometry.lane_width / 2.0)
+                .map(|distance| (distance, LanePlacement { position, lane, heading }))
+        })
+        .filter(|(_, placement)| placement.position.x.abs() <= CLOVERLEAF_HIGHWAY_EXTENT && placement.position.y.abs() <= CLOVERLEAF_HIGHWAY_EXTENT)
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, placement)| placement)
+}
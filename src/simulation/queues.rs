@@ -0,0 +1,178 @@
+use super::{road_length, road_position, SimulationEvent, SimulationState};
+use crate::config::{QueueConfig, RouteGeometry};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Seconds a queue may go unseen, while its cars hover around the speed threshold, before
+/// it counts as dissipated
+const QUEUE_GRACE: f32 = 2.0;
+/// Seconds the growth rate of a queue is averaged over
+const GROWTH_SMOOTHING: f32 = 10.0;
+
+/// Slow cars lined up in one lane. Positions are meters along the road as `road_position`
+/// measures them, increasing in the direction of travel, so the head is ahead of the tail
+/// and wraps around past the end of the ring.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Queue {
+    pub id: u32,
+    pub lane: u32,
+    pub head: f32, // position of the front car
+    pub tail: f32, // position of the last car
+    pub length: f32, // meters from the last car to the front car
+    pub cars: u32,
+    pub growth_rate: f32, // m/s the length changes by, averaged over the last few seconds
+    pub start_time: f32,
+    pub max_length: f32, // meters, longest the queue has been
+}
+
+/// Queue followed from tick to tick, kept for a little while after it was last seen
+struct TrackedQueue {
+    queue: Queue,
+    seen: f32, // time it was last found
+}
+
+/// Finds the queues on a ring route every tick and follows them over time: a queue keeps
+/// its id while the cars in it change, as long as it overlaps the one found the tick
+/// before. Publishes the queues found in `SimulationState::queues` and an event when a
+/// queue forms or dissipates. Other geometries have no single corridor to measure queues
+/// along, so none are found there.
+pub struct QueueDetector {
+    config: QueueConfig,
+    geometry: RouteGeometry,
+    tracked: Vec<TrackedQueue>,
+    next_id: u32,
+}
+
+impl QueueDetector {
+    pub fn new(config: &QueueConfig, geometry: &RouteGeometry) -> Self {
+        Self {
+            config: config.clone(),
+            geometry: geometry.clone(),
+            tracked: Vec::new(),
+            next_id: 0,
+        }
+    }
+    
+    pub fn update(&mut self, state: &mut SimulationState) {
+        let Some(road) = road_length(&self.geometry) else {
+            return;
+        };
+        let time = state.time;
+        let mut found = self.find(state, road);
+        // Longer queues get first pick of the queue they continue, so the main part keeps
+        // the id when one splits
+        found.sort_by(|a, b| b.length.total_cmp(&a.length));
+        
+        let mut previous: Vec<Option<TrackedQueue>> = std::mem::take(&mut self.tracked).into_iter().map(Some).collect();
+        let mut queues = Vec::with_capacity(found.len());
+        for mut queue in found {
+            let continued = previous.iter()
+                .enumerate()
+                .filter_map(|(index, tracked)| Some((index, tracked.as_ref()?)))
+                .filter(|(_, tracked)| tracked.queue.lane == queue.lane && overlaps(&tracked.queue, &queue, road, self.config.max_gap))
+                .min_by(|a, b| a.1.queue.start_time.total_cmp(&b.1.queue.start_time))
+                .map(|(index, _)| index);
+            match continued.and_then(|index| previous[index].take()) {
+                Some(tracked) => {
+                    let elapsed = (time - tracked.seen).max(state.dt).max(f32::EPSILON);
+                    let weight = (elapsed / GROWTH_SMOOTHING).min(1.0);
+                    let rate = (queue.length - tracked.queue.length) / elapsed;
+                    queue.id = tracked.queue.id;
+                    queue.start_time = tracked.queue.start_time;
+                    queue.growth_rate = tracked.queue.growth_rate + (rate - tracked.queue.growth_rate) * weight;
+                    queue.max_length = tracked.queue.max_length.max(queue.length);
+                }
+                None => {
+                    queue.id = self.next_id;
+                    self.next_id += 1;
+                    queue.start_time = time;
+                    queue.max_length = queue.length;
+                    log::debug!("Queue {} formed in lane {} at {:.0} m", queue.id, queue.lane, queue.head);
+                    state.events.push(SimulationEvent::QueueFormed { queue: queue.id, lane: queue.lane, position: queue.head, time });
+                }
+            }
+            self.tracked.push(TrackedQueue { queue: queue.clone(), seen: time });
+            queues.push(queue);
+        }
+        
+        // Queues not found again are given a moment to reappear
+        for tracked in previous.into_iter().flatten() {
+            if time - tracked.seen < QUEUE_GRACE {
+                self.tracked.push(tracked);
+                continue;
+            }
+            let queue = &tracked.queue;
+            log::debug!("Queue {} in lane {} dissipated after {:.1}s, at most {:.0} m long",
+                        queue.id, queue.lane, tracked.seen - queue.start_time, queue.max_length);
+            state.events.push(SimulationEvent::QueueDissipated {
+                queue: queue.id,
+                lane: queue.lane,
+                duration: tracked.seen - queue.start_time,
+                max_length: queue.max_length,
+                time,
+            });
+        }
+        
+        queues.sort_by_key(|queue| queue.id);
+        state.queues = queues;
+    }
+    
+    /// Runs of slow cars close behind each other in every lane, without ids or history yet
+    fn find(&self, state: &SimulationState, road: f32) -> Vec<Queue> {
+        // Position along the road and speed of every car in each lane, cars leaving by an
+        // off-ramp are no longer in the lane
+        let mut lanes: BTreeMap<u32, Vec<(f32, f32)>> = BTreeMap::new();
+        for car in state.cars.iter().filter(|car| car.exit_ramp.is_none()) {
+            lanes.entry(car.current_lane).or_default().push((road_position(car, &self.geometry), car.velocity.magnitude()));
+        }
+        
+        let mut queues = Vec::new();
+        for (lane, mut cars) in lanes {
+            cars.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let count = cars.len();
+            let slow: Vec<bool> = cars.iter().map(|(_, speed)| *speed < self.config.speed_threshold).collect();
+            let gap = |index: usize| (cars[(index + 1) % count].0 - cars[index].0).rem_euclid(road);
+            // Whether each car is queued behind the next one around the ring
+            let linked: Vec<bool> = (0..count)
+                .map(|index| count > 1 && slow[index] && slow[(index + 1) % count] && gap(index) <= self.config.max_gap)
+                .collect();
+                
+            // Start from a car not queued behind the one before it, or after the widest gap
+            // when the whole lane is one queue
+            let start = (0..count).find(|&index| !linked[(index + count - 1) % count])
+                .unwrap_or_else(|| (0..count).max_by(|&a, &b| gap(a).total_cmp(&gap(b))).map_or(0, |widest| (widest + 1) % count));
+            let mut run: Vec<usize> = Vec::new();
+            for step in 0..count {
+                let index = (start + step) % count;
+                if slow[index] {
+                    run.push(index);
+                }
+                if !linked[index] || step == count - 1 {
+                    if run.len() >= self.config.min_cars as usize {
+                        let tail = cars[run[0]].0;
+                        let head = cars[run[run.len() - 1]].0;
+                        queues.push(Queue {
+                            id: 0,
+                            lane,
+                            head,
+                            tail,
+                            length: (head - tail).rem_euclid(road),
+                            cars: run.len() as u32,
+                            growth_rate: 0.0,
+                            start_time: 0.0,
+                            max_length: 0.0,
+                        });
+                    }
+                    run.clear();
+                }
+            }
+        }
+        queues
+    }
+}
+
+/// Whether `b` covers part of the road `a` covers, or comes within `tolerance` of it
+fn overlaps(a: &Queue, b: &Queue, road: f32, tolerance: f32) -> bool {
+    (b.tail - a.tail).rem_euclid(road) <= a.length + tolerance
+        || (a.tail - b.tail).rem_euclid(road) <= b.length + tolerance
+}
@@ -0,0 +1,182 @@
+use super::{Car, Point, SimulationState, Vec2};
+use crate::config::{ExitPoint, RouteConfig};
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+
+const RAMP_CLEARANCE: f32 = 1.5; // lane widths between the road edge and the end of a ramp
+const RAMP_SAMPLES: usize = 32;  // polyline points a ramp is measured and followed along
+const RAMP_MIN_GAP: f32 = 2.0;   // meters cars keep to the car ahead on a ramp when stopped
+
+/// How far along its off-ramp a car is
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RampPosition {
+    pub exit_id: String,
+    pub distance: f32, // meters from the start of the ramp
+}
+
+/// Where a car following an off-ramp is after one step
+pub struct RampMotion {
+    pub position: Point,
+    pub velocity: Vec2,
+    pub heading: f32,
+    pub distance: f32,
+    pub lateral_offset: f32,
+}
+
+/// Off-ramp of a ring exit. It leaves the exit lane tangentially at the exit angle and
+/// bends away from the ring over the exit's `exit_distance` until it is clear of the road,
+/// where exiting cars leave the simulation.
+#[derive(Debug, Clone)]
+pub struct ExitRamp {
+    exit_id: String,
+    center: Point,
+    lane_radius: f32, // radius of the exit lane the ramp leaves
+    end_speed: f32,   // m/s exiting cars slow to by the end of the ramp
+    points: Vec<Point>,
+    distances: Vec<f32>, // length of the ramp up to each point
+}
+
+impl ExitRamp {
+    pub fn new(exit: &ExitPoint, route: &RouteConfig) -> Self {
+        let geometry = &route.route.geometry;
+        let center = Point::new(geometry.center_x, geometry.center_y);
+        let lane_radius = geometry.inner_radius + geometry.lane_width * (exit.lane as f32 - 0.5);
+        let end_radius = if exit.is_exterior() {
+            geometry.inner_radius + geometry.lane_width * (geometry.lane_count as f32 + RAMP_CLEARANCE)
+        } else {
+            geometry.inner_radius - geometry.lane_width * RAMP_CLEARANCE
+        };
+        
+        // Quadratic Bezier: tangent to the counter-clockwise exit lane at the start, ending
+        // `exit_distance` further round the ring just off the road
+        let length = exit.exit_distance.max(geometry.lane_width);
+        let start_angle = exit.angle.to_radians();
+        let end_angle = start_angle + length / lane_radius;
+        let direction = |angle: f32| Vector2::new(angle.cos(), angle.sin());
+        let start = center + direction(start_angle) * lane_radius;
+        let tangent = Vector2::new(-start_angle.sin(), start_angle.cos());
+        let control = start + tangent * (length / 2.0);
+        let end = center + direction(end_angle) * end_radius;
+        
+        let points: Vec<Point> = (0..=RAMP_SAMPLES)
+            .map(|i| {
+                let t = i as f32 / RAMP_SAMPLES as f32;
+                Point::from(start.coords * (1.0 - t).powi(2) + control.coords * 2.0 * t * (1.0 - t) + end.coords * t * t)
+            })
+            .collect();
+        let mut distances = vec![0.0];
+        for pair in points.windows(2) {
+            distances.push(distances[distances.len() - 1] + (pair[1] - pair[0]).magnitude());
+        }
+        
+        Self {
+            exit_id: exit.id.clone(),
+            center,
+            lane_radius,
+            end_speed: exit.ramp_speed.unwrap_or(route.route.traffic_rules.min_speed),
+            points,
+            distances,
+        }
+    }
+    
+    pub fn exit_id(&self) -> &str {
+        &self.exit_id
+    }
+    
+    pub fn length(&self) -> f32 {
+        self.distances[self.distances.len() - 1]
+    }
+    
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+    
+    /// Position and heading (radians) `distance` meters along the ramp
+    pub fn pose_at(&self, distance: f32) -> (Point, f32) {
+        let distance = distance.clamp(0.0, self.length());
+        let segment = self.distances.partition_point(|&d| d <= distance).clamp(1, self.points.len() - 1) - 1;
+        let (from, to) = (self.points[segment], self.points[segment + 1]);
+        let segment_length = self.distances[segment + 1] - self.distances[segment];
+        let t = if segment_length > 0.0 { (distance - self.distances[segment]) / segment_length } else { 0.0 };
+        let along = to - from;
+        (from + along * t, along.y.atan2(along.x))
+    }
+    
+    /// Meters from the middle of the exit lane, positive towards higher lane numbers like
+    /// `Car::lateral_offset`, so ring traffic sees a car until it has left the lane
+    pub fn lateral_offset(&self, position: Point) -> f32 {
+        (position - self.center).magnitude() - self.lane_radius
+    }
+    
+    /// Meters a car at `position` on the exit lane has already driven past the ramp start,
+    /// `None` if it is not within `window` meters past it
+    pub fn distance_past_start(&self, position: Point, window: f32) -> Option<f32> {
+        let to_car = position - self.center;
+        let (start, _) = self.pose_at(0.0);
+        let to_start = start - self.center;
+        let angle_past = (to_car.y.atan2(to_car.x) - to_start.y.atan2(to_start.x)).rem_euclid(std::f32::consts::TAU);
+        let distance = angle_past * self.lane_radius;
+        (distance < window).then_some(distance)
+    }
+}
+
+/// Off-ramps of the exits of a ring route. Other geometries have none, and cars leave
+/// as soon as they reach an exit there.
+#[derive(Debug, Clone, Default)]
+pub struct ExitRamps {
+    ramps: Vec<ExitRamp>,
+}
+
+impl ExitRamps {
+    pub fn from_route(route: &RouteConfig) -> Self {
+        if route.route.geometry.geometry_type != "donut" {
+            return Self::default();
+        }
+        Self {
+            ramps: route.route.exits.iter().map(|exit| ExitRamp::new(exit, route)).collect(),
+        }
+    }
+    
+    pub fn get(&self, exit_id: &str) -> Option<&ExitRamp> {
+        self.ramps.iter().find(|ramp| ramp.exit_id == exit_id)
+    }
+    
+    pub fn ramps(&self) -> &[ExitRamp] {
+        &self.ramps
+    }
+    
+    /// Move a car on an off-ramp one step along it. The car slows so it reaches the ramp's
+    /// end speed at the end, and keeps back from the car ahead on the same ramp.
+    pub fn advance(&self, car: &Car, state: &SimulationState, braking: f32, dt: f32) -> Option<RampMotion> {
+        let position = car.exit_ramp.as_ref()?;
+        let ramp = self.get(&position.exit_id)?;
+        let speed = car.velocity.magnitude();
+        
+        let remaining = (ramp.length() - position.distance).max(0.0);
+        let comfortable_deceleration = braking * 0.5;
+        let mut target_speed = car.behavior.target_speed
+            .min((ramp.end_speed.powi(2) + 2.0 * comfortable_deceleration * remaining).sqrt());
+            
+        let gap = state.cars.iter()
+            .filter_map(|other| {
+                let other_position = other.exit_ramp.as_ref()?;
+                let ahead = other_position.exit_id == position.exit_id && other_position.distance > position.distance;
+                ahead.then(|| other_position.distance - position.distance - (car.length + other.length) / 2.0)
+            })
+            .fold(f32::INFINITY, f32::min);
+        if gap.is_finite() {
+            target_speed = target_speed.min((2.0 * braking * (gap - RAMP_MIN_GAP).max(0.0)).sqrt());
+        }
+        
+        let new_speed = (speed + (target_speed - speed).clamp(-braking * dt, car.max_acceleration * dt)).max(0.0);
+        let distance = position.distance + new_speed * dt;
+        let (new_position, heading) = ramp.pose_at(distance);
+        Some(RampMotion {
+            position: new_position,
+            velocity: Vector2::new(heading.cos(), heading.sin()) * new_speed,
+            heading,
+            distance,
+            lateral_offset: ramp.lateral_offset(new_position),
+        })
+    }
+}
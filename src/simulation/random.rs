@@ -0,0 +1,112 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+/// Independent random number streams of the simulation. Each is seeded from the master
+/// seed and its own name, so drawing more or fewer numbers from one stream (say, by
+/// raising lane change frequency) leaves every other stream's sequence unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RandomStream {
+    /// Spawn intervals, car types, driver behaviors and destinations of new cars
+    Spawn,
+    /// Lane change decisions
+    Behavior,
+    /// When and how cars break down
+    Breakdown,
+    /// Random removal of long-running cars
+    Despawn,
+    /// Speed preference jitter
+    Noise,
+    /// When drivers are distracted and for how long
+    Distraction,
+    /// Which cars drivers overlook in their blind spot
+    Mirror,
+}
+
+impl RandomStream {
+    pub fn name(self) -> &'static str {
+        match self {
+            RandomStream::Spawn => "spawn",
+            RandomStream::Behavior => "behavior",
+            RandomStream::Breakdown => "breakdown",
+            RandomStream::Despawn => "despawn",
+            RandomStream::Noise => "noise",
+            RandomStream::Distraction => "distraction",
+            RandomStream::Mirror => "mirror",
+        }
+    }
+    
+    /// Generator for this stream, from entropy when there is no master seed
+    pub fn rng(self, seed: Option<u64>) -> StdRng {
+        match seed {
+            Some(seed) => StdRng::seed_from_u64(self.seed(seed)),
+            None => StdRng::from_entropy(),
+        }
+    }
+    
+    /// Child seed: the stream name hashed with FNV-1a, mixed into the master seed with
+    /// SplitMix64. Both are fixed algorithms, so child seeds are the same on every platform.
+    pub fn seed(self, master: u64) -> u64 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in self.name().bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        
+        splitmix64(master ^ hash)
+    }
+}
+
+/// SplitMix64 finalizer: scrambles `value` into a well-mixed 64-bit hash
+pub fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Key of a counter-based stream, see `philox4x32`
+pub type PhiloxKey = [u32; 2];
+
+impl RandomStream {
+    /// Key for drawing this stream with `philox4x32`, at random when there is no master seed
+    pub fn key(self, seed: Option<u64>) -> PhiloxKey {
+        let seed = seed.map_or_else(rand::random, |seed| self.seed(seed));
+        [seed as u32, (seed >> 32) as u32]
+    }
+}
+
+/// Philox4x32-10 counter-based generator (Salmon et al., "Parallel random numbers: as easy
+/// as 1, 2, 3"): four random words for each counter, with no state in between. The GPU
+/// kernel carries the same function, so both backends draw the same numbers for a car in
+/// a step by using the car and the step as the counter.
+pub fn philox4x32(counter: [u32; 4], key: PhiloxKey) -> [u32; 4] {
+    const MULTIPLIERS: [u32; 2] = [0xD251_1F53, 0xCD9E_8D57];
+    const WEYL: [u32; 2] = [0x9E37_79B9, 0xBB67_AE85];
+    let (mut counter, mut key) = (counter, key);
+    for round in 0..10 {
+        if round > 0 {
+            key = [key[0].wrapping_add(WEYL[0]), key[1].wrapping_add(WEYL[1])];
+        }
+        let product0 = MULTIPLIERS[0] as u64 * counter[0] as u64;
+        let product1 = MULTIPLIERS[1] as u64 * counter[2] as u64;
+        counter = [
+            (product1 >> 32) as u32 ^ counter[1] ^ key[0],
+            product1 as u32,
+            (product0 >> 32) as u32 ^ counter[3] ^ key[1],
+            product0 as u32,
+        ];
+    }
+    counter
+}
+
+/// Uniform draw in [0, 1) from the top 24 bits of a random word
+pub fn unit_float(word: u32) -> f32 {
+    (word >> 8) as f32 / (1u32 << 24) as f32
+}
+
+/// Standard normal draw from two random words, by the Box-Muller transform
+pub fn standard_normal(first: u32, second: u32) -> f32 {
+    // Shifted into (0, 1] so the logarithm stays finite
+    let radius = (-2.0 * (unit_float(first) + 1.0 / (1u32 << 24) as f32).ln()).sqrt();
+    radius * (2.0 * std::f32::consts::PI * unit_float(second)).cos()
+}
@@ -0,0 +1,84 @@
+use web_time::Instant;
+
+/// Seconds of wall-clock time between resource samples. Reading /proc every frame would
+/// cost more than it tells.
+pub const RESOURCE_SAMPLE_INTERVAL: f32 = 0.5;
+
+/// CPU and memory use of this process over one sample interval
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ProcessUsage {
+    pub cpu_utilization: f32, // fraction of all cores' time spent in this process
+    pub memory_usage: usize, // resident bytes
+}
+
+/// One point of the resource history
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ResourceSample {
+    pub time: f32, // seconds since the tracker was created
+    pub process: Option<ProcessUsage>, // `None` where the platform cannot tell
+    pub gpu_memory: usize, // bytes in the compute backend's and renderer's GPU buffers
+}
+
+/// Samples the CPU time and resident memory of this process from /proc. Platforms without
+/// /proc report nothing.
+#[derive(Debug)]
+pub struct ProcessMonitor {
+    last: Option<(Instant, f32)>, // wall clock and CPU seconds at the previous sample
+    cores: f32,
+}
+
+impl Default for ProcessMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProcessMonitor {
+    pub fn new() -> Self {
+        Self {
+            last: None,
+            cores: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1) as f32,
+        }
+    }
+    
+    /// Usage since the previous sample; the first sample has no interval and reports zero CPU
+    pub fn sample(&mut self) -> Option<ProcessUsage> {
+        let cpu_seconds = parse_cpu_seconds(&std::fs::read_to_string("/proc/self/stat").ok()?)?;
+        let memory_usage = parse_resident_bytes(&std::fs::read_to_string("/proc/self/status").ok()?)?;
+        
+        let now = Instant::now();
+        let cpu_utilization = match self.last {
+            Some((then, previous)) => {
+                let wall = now.duration_since(then).as_secs_f32();
+                if wall > 0.0 {
+                    ((cpu_seconds - previous) / (wall * self.cores)).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                }
+            }
+            None => 0.0,
+        };
+        self.last = Some((now, cpu_seconds));
+        Some(ProcessUsage { cpu_utilization, memory_usage })
+    }
+}
+
+/// Clock ticks per second of the times in /proc/<pid>/stat, 100 on every Linux architecture
+const CLOCK_TICKS_PER_SECOND: f32 = 100.0;
+
+/// User plus system CPU seconds from the contents of /proc/<pid>/stat
+pub fn parse_cpu_seconds(stat: &str) -> Option<f32> {
+    // The command name in parentheses may contain spaces, the fields after it do not.
+    // utime and stime are fields 14 and 15, the 12th and 13th after the name.
+    let fields: Vec<&str> = stat.get(stat.rfind(')')? + 1..)?.split_whitespace().collect();
+    let user: u64 = fields.get(11)?.parse().ok()?;
+    let system: u64 = fields.get(12)?.parse().ok()?;
+    Some((user + system) as f32 / CLOCK_TICKS_PER_SECOND)
+}
+
+/// Resident set size in bytes from the contents of /proc/<pid>/status
+pub fn parse_resident_bytes(status: &str) -> Option<usize> {
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: usize = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
@@ -0,0 +1,125 @@
+use super::SimulationState;
+use anyhow::Result;
+use std::collections::VecDeque;
+
+/// Simulation seconds between snapshots
+const SNAPSHOT_INTERVAL: f32 = 0.1;
+
+/// Recent snapshots of the simulation, oldest first, held within a memory budget so a run
+/// can be scrubbed back through and resumed from an earlier point. Snapshots are kept
+/// bincode-encoded like checkpoints, which is far smaller than the states themselves and
+/// makes the memory they take exact.
+pub struct RewindBuffer {
+    snapshots: VecDeque<(f32, Vec<u8>)>, // simulation time and encoded state
+    budget: usize, // bytes
+    bytes: usize,
+}
+
+impl RewindBuffer {
+    pub fn new(budget_mb: f32) -> Self {
+        Self {
+            snapshots: VecDeque::new(),
+            budget: Self::budget_bytes(budget_mb),
+            bytes: 0,
+        }
+    }
+    
+    fn budget_bytes(budget_mb: f32) -> usize {
+        (budget_mb.max(0.0) * 1024.0 * 1024.0) as usize
+    }
+    
+    /// Keep at most `budget_mb` megabytes of snapshots from now on, dropping the oldest
+    pub fn set_budget(&mut self, budget_mb: f32) {
+        self.budget = Self::budget_bytes(budget_mb);
+        self.evict();
+    }
+    
+    pub fn is_enabled(&self) -> bool {
+        self.budget > 0
+    }
+    
+    /// Snapshot `state` once a snapshot interval has passed since the last one. A state
+    /// from before the newest snapshot continues from an earlier point, so the snapshots
+    /// after it belong to a run that no longer happens and are dropped.
+    pub fn record(&mut self, state: &SimulationState) -> Result<()> {
+        self.drop_after(state.time);
+        let due = self.snapshots.back().is_none_or(|(time, _)| state.time - time >= SNAPSHOT_INTERVAL - 1e-4);
+        if due {
+            self.push(state)?;
+        }
+        Ok(())
+    }
+    
+    /// Snapshot `state` whenever it was taken, as the newest point to scrub back to
+    pub fn capture(&mut self, state: &SimulationState) -> Result<()> {
+        self.drop_after(state.time);
+        if self.snapshots.back().is_some_and(|(time, _)| *time == state.time) {
+            if let Some((_, bytes)) = self.snapshots.pop_back() {
+                self.bytes -= bytes.len();
+            }
+        }
+        self.push(state)
+    }
+    
+    fn push(&mut self, state: &SimulationState) -> Result<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let bytes = bincode::serialize(state)?;
+        self.bytes += bytes.len();
+        self.snapshots.push_back((state.time, bytes));
+        self.evict();
+        Ok(())
+    }
+    
+    fn drop_after(&mut self, time: f32) {
+        while self.snapshots.back().is_some_and(|(snapshot_time, _)| *snapshot_time > time) {
+            if let Some((_, bytes)) = self.snapshots.pop_back() {
+                self.bytes -= bytes.len();
+            }
+        }
+    }
+    
+    fn evict(&mut self) {
+        while self.bytes > self.budget {
+            match self.snapshots.pop_front() {
+                Some((_, bytes)) => self.bytes -= bytes.len(),
+                None => break,
+            }
+        }
+    }
+    
+    /// The latest snapshot taken at or before `time`, or the oldest one for earlier times
+    pub fn state_at(&self, time: f32) -> Result<Option<SimulationState>> {
+        let later = self.snapshots.partition_point(|(snapshot_time, _)| *snapshot_time <= time);
+        let Some((_, bytes)) = self.snapshots.get(later.saturating_sub(1)) else {
+            return Ok(None);
+        };
+        let mut state: SimulationState = bincode::deserialize(bytes)?;
+        state.reindex_cars();
+        Ok(Some(state))
+    }
+    
+    /// Simulation times of the oldest and newest snapshots
+    pub fn time_range(&self) -> Option<(f32, f32)> {
+        Some((self.snapshots.front()?.0, self.snapshots.back()?.0))
+    }
+    
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+    
+    /// Bytes the snapshots take
+    pub fn memory_usage(&self) -> usize {
+        self.bytes
+    }
+    
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.bytes = 0;
+    }
+}
@@ -0,0 +1,355 @@
+use super::{AppendLog, Car, CarId, Movement, Point, SimulationEvent, SimulationState};
+use crate::config::{RouteConfig, SafetyConfig};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Seconds covered by each bin of the TTC and PET distributions
+pub const SAFETY_BIN_WIDTH: f32 = 0.5;
+/// Bins of the distributions, measures of ten seconds or more are no interaction at all
+pub const SAFETY_BINS: usize = 20;
+
+/// Surrogate safety measure a conflict was found by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConflictKind {
+    /// Time to collision: a follower closing in on the car ahead would hit it this soon
+    #[serde(rename = "ttc")]
+    TimeToCollision,
+    /// Post-encroachment time: a car reached a merge or crossing point this soon after a
+    /// car on a conflicting path left it
+    #[serde(rename = "pet")]
+    PostEncroachment,
+}
+
+impl ConflictKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ConflictKind::TimeToCollision => "ttc",
+            ConflictKind::PostEncroachment => "pet",
+        }
+    }
+}
+
+/// Two cars that came closer in time than the safety thresholds allow. `car_a` is the
+/// follower, or the car arriving at the conflict point; `car_b` the car ahead, or the one
+/// that passed the point first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conflict {
+    pub kind: ConflictKind,
+    pub car_a: CarId,
+    pub car_b: CarId,
+    pub value: f32, // seconds: the smallest TTC of the encounter, or the PET
+    pub position: Point,
+    pub time: f32, // when the TTC rose above the threshold again, or the second car arrived
+}
+
+/// Distributions of the surrogate safety measures and every conflict since the simulation
+/// started
+#[derive(Debug)]
+pub struct SafetyLog {
+    pub ttc_exposure: Vec<f32>, // vehicle-seconds spent closing in at a TTC in each bin
+    pub pet_counts: Vec<u32>,   // encroachments with a PET in each bin
+    conflicts: AppendLog<Conflict>,
+}
+
+impl Clone for SafetyLog {
+    fn clone(&self) -> Self {
+        Self {
+            ttc_exposure: self.ttc_exposure.clone(),
+            pet_counts: self.pet_counts.clone(),
+            conflicts: self.conflicts.clone(),
+        }
+    }
+    
+    fn clone_from(&mut self, source: &Self) {
+        self.ttc_exposure.clone_from(&source.ttc_exposure);
+        self.pet_counts.clone_from(&source.pet_counts);
+        self.conflicts.clone_from(&source.conflicts);
+    }
+}
+
+impl Default for SafetyLog {
+    fn default() -> Self {
+        Self {
+            ttc_exposure: vec![0.0; SAFETY_BINS],
+            pet_counts: vec![0; SAFETY_BINS],
+            conflicts: AppendLog::default(),
+        }
+    }
+}
+
+impl SafetyLog {
+    /// Bin of the distributions `seconds` falls into, `None` past the last one
+    pub fn bin(seconds: f32) -> Option<usize> {
+        let bin = (seconds.max(0.0) / SAFETY_BIN_WIDTH) as usize;
+        (bin < SAFETY_BINS).then_some(bin)
+    }
+    
+    pub fn record(&mut self, conflict: Conflict) {
+        self.conflicts.push(conflict);
+    }
+    
+    /// Conflicts found so far, oldest first
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+    
+    pub fn count(&self, kind: ConflictKind) -> usize {
+        self.conflicts.iter().filter(|conflict| conflict.kind == kind).count()
+    }
+    
+    /// The distributions alone, without the conflicts
+    pub fn distributions(&self) -> SafetyLog {
+        SafetyLog {
+            ttc_exposure: self.ttc_exposure.clone(),
+            pet_counts: self.pet_counts.clone(),
+            conflicts: AppendLog::default(),
+        }
+    }
+    
+    /// Add the distributions of `other` to these
+    pub fn add_distributions(&mut self, other: &SafetyLog) {
+        for (exposure, other) in self.ttc_exposure.iter_mut().zip(&other.ttc_exposure) {
+            *exposure += other;
+        }
+        for (count, other) in self.pet_counts.iter_mut().zip(&other.pet_counts) {
+            *count += other;
+        }
+    }
+}
+
+/// Path a car takes through a conflict area; cars on conflicting paths pass it in turns
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stream {
+    Movement(Movement), // through a grid junction
+    Entering,           // joining the road at an entry
+    Mainline,           // driving past an entry
+}
+
+impl Stream {
+    fn conflicts_with(self, other: Stream) -> bool {
+        match (self, other) {
+            (Stream::Movement(a), Stream::Movement(b)) => a.conflicts_with(b),
+            (Stream::Entering, Stream::Mainline) | (Stream::Mainline, Stream::Entering) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Where paths merge or cross: a grid junction or the merge point of an entry
+enum AreaShape {
+    Junction { row: usize, col: usize },
+    Merge { entry: String, center: Point, radius: f32 },
+}
+
+struct ConflictArea {
+    shape: AreaShape,
+    inside: Vec<(CarId, Stream)>,
+    last_left: Vec<(CarId, Stream, f32)>, // the car of each stream that left most recently, and when
+}
+
+/// Computes the surrogate safety measures of every tick: the time to collision of each
+/// follower closing in on the car ahead, and the post-encroachment time at merge points and
+/// junctions. Encounters below the thresholds are reported as conflicts, in
+/// `SimulationState::safety` and as `ConflictDetected` events, and mark both cars.
+pub struct SafetyMonitor {
+    config: SafetyConfig,
+    merge_radius: f32,
+    closing: HashMap<(CarId, CarId), (f32, Point)>, // follower/leader pairs under the TTC threshold, smallest TTC and where
+    areas: Vec<ConflictArea>,
+}
+
+impl SafetyMonitor {
+    pub fn new(config: &SafetyConfig, route: &RouteConfig) -> Self {
+        Self {
+            config: config.clone(),
+            merge_radius: route.route.geometry.lane_width / 2.0,
+            closing: HashMap::new(),
+            areas: Vec::new(),
+        }
+    }
+    
+    /// Forget encounters in progress, e.g. after loading a checkpoint
+    pub fn reset(&mut self) {
+        self.closing.clear();
+        self.areas.clear();
+    }
+    
+    pub fn update(&mut self, state: &mut SimulationState) {
+        let mut conflicts = self.time_to_collision(state);
+        conflicts.extend(self.post_encroachment(state));
+        
+        let time = state.time;
+        for conflict in conflicts {
+            for id in [conflict.car_a, conflict.car_b] {
+                if let Some(car) = state.get_car_mut(id) {
+                    car.last_conflict_time = Some(time);
+                }
+            }
+            state.events.push(SimulationEvent::ConflictDetected(conflict.clone()));
+            state.safety.record(conflict);
+        }
+    }
+    
+    /// Time to collision of every follower faster than the car ahead. An encounter under the
+    /// threshold is one conflict, reported with its smallest TTC once it is over; both cars
+    /// are flagged for as long as it lasts.
+    fn time_to_collision(&mut self, state: &mut SimulationState) -> Vec<Conflict> {
+        let speeds: HashMap<CarId, f32> = state.cars.iter().map(|car| (car.id, car.velocity.magnitude())).collect();
+        let mut closing = HashMap::new();
+        for car in &state.cars {
+            let Some((leader, gap)) = car.leader else {
+                continue;
+            };
+            let Some(leader_speed) = speeds.get(&leader) else {
+                continue;
+            };
+            let closing_speed = car.velocity.magnitude() - leader_speed;
+            if closing_speed <= 0.0 || gap <= 0.0 {
+                continue;
+            }
+            
+            let ttc = gap / closing_speed;
+            if let Some(bin) = SafetyLog::bin(ttc) {
+                state.safety.ttc_exposure[bin] += state.dt;
+            }
+            if ttc < self.config.ttc_threshold {
+                let pair = (car.id, leader);
+                let smallest = self.closing.get(&pair).filter(|(smallest, _)| *smallest <= ttc).copied();
+                closing.insert(pair, smallest.unwrap_or((ttc, car.position)));
+            }
+        }
+        
+        let time = state.time;
+        for &(follower, leader) in closing.keys() {
+            for id in [follower, leader] {
+                if let Some(car) = state.get_car_mut(id) {
+                    car.last_conflict_time = Some(time);
+                }
+            }
+        }
+        let mut ended: Vec<_> = self.closing.drain()
+            .filter(|(pair, _)| !closing.contains_key(pair))
+            .map(|((follower, leader), (ttc, position))| Conflict {
+                kind: ConflictKind::TimeToCollision,
+                car_a: follower,
+                car_b: leader,
+                value: ttc,
+                position,
+                time,
+            })
+            .collect();
+        self.closing = closing;
+        // Report encounters in pair order, the same every run
+        ended.sort_by_key(|conflict| (conflict.car_a, conflict.car_b));
+        ended
+    }
+    
+    /// Post-encroachment time of every car arriving at a merge point or junction after a car
+    /// on a conflicting path, zero while that car is still there
+    fn post_encroachment(&mut self, state: &mut SimulationState) -> Vec<Conflict> {
+        self.discover_areas(state);
+        
+        let mut conflicts = Vec::new();
+        for area in &mut self.areas {
+            let now: Vec<(CarId, Stream, Point)> = state.cars.iter()
+                .filter_map(|car| Self::stream_in(area, car, state).map(|stream| (car.id, stream, car.position)))
+                .collect();
+                
+            for &(id, stream, position) in &now {
+                if area.inside.iter().any(|(inside, _)| *inside == id) {
+                    continue;
+                }
+                let still_there = area.inside.iter().find(|(_, other)| other.conflicts_with(stream));
+                let encroachment = match still_there {
+                    Some(&(other, _)) => Some((other, 0.0)),
+                    None => area.last_left.iter()
+                        .filter(|(other, left_stream, _)| *other != id && left_stream.conflicts_with(stream))
+                        .max_by(|a, b| a.2.total_cmp(&b.2))
+                        .map(|&(other, _, left)| (other, state.time - left)),
+                };
+                let Some((other, pet)) = encroachment else {
+                    continue;
+                };
+                if let Some(bin) = SafetyLog::bin(pet) {
+                    state.safety.pet_counts[bin] += 1;
+                }
+                if pet < self.config.pet_threshold {
+                    conflicts.push(Conflict {
+                        kind: ConflictKind::PostEncroachment,
+                        car_a: id,
+                        car_b: other,
+                        value: pet,
+                        position,
+                        time: state.time,
+                    });
+                }
+            }
+            
+            for &(id, stream) in &area.inside {
+                if !now.iter().any(|(inside, ..)| *inside == id) {
+                    area.last_left.retain(|(_, left_stream, _)| *left_stream != stream);
+                    area.last_left.push((id, stream, state.time));
+                }
+            }
+            area.inside = now.into_iter().map(|(id, stream, _)| (id, stream)).collect();
+        }
+        conflicts
+    }
+    
+    /// Junctions are known from the state, merge points from where cars join the road
+    fn discover_areas(&mut self, state: &SimulationState) {
+        for junction in &state.junctions {
+            let known = self.areas.iter().any(|area| matches!(area.shape, AreaShape::Junction { row, col } if row == junction.row && col == junction.col));
+            if !known {
+                self.areas.push(ConflictArea::new(AreaShape::Junction { row: junction.row, col: junction.col }));
+            }
+        }
+        if !state.junctions.is_empty() {
+            return;
+        }
+        for event in &state.events {
+            let SimulationEvent::CarSpawned { car, entry, .. } = event else {
+                continue;
+            };
+            let known = self.areas.iter().any(|area| matches!(&area.shape, AreaShape::Merge { entry: known, .. } if known == entry));
+            if let (false, Some(car)) = (known, state.get_car(*car)) {
+                self.areas.push(ConflictArea::new(AreaShape::Merge {
+                    entry: entry.clone(),
+                    center: car.position,
+                    radius: self.merge_radius,
+                }));
+            }
+        }
+    }
+    
+    /// Path `car` takes through the area, if it is inside it
+    fn stream_in(area: &ConflictArea, car: &Car, state: &SimulationState) -> Option<Stream> {
+        match &area.shape {
+            AreaShape::Junction { row, col } => {
+                let junction = state.junctions.iter().find(|junction| junction.row == *row && junction.col == *col)?;
+                if junction.distance_ahead(car)? > 0.0 {
+                    return None;
+                }
+                junction.movement(car).map(Stream::Movement)
+            }
+            AreaShape::Merge { entry, center, radius } => {
+                if (car.position - center).magnitude() >= *radius {
+                    return None;
+                }
+                // A car that joined here is still entering until it has driven through
+                let entering = car.entry == *entry && car.distance_traveled < 2.0 * radius;
+                Some(if entering { Stream::Entering } else { Stream::Mainline })
+            }
+        }
+    }
+}
+
+impl ConflictArea {
+    fn new(shape: AreaShape) -> Self {
+        Self {
+            shape,
+            inside: Vec::new(),
+            last_left: Vec::new(),
+        }
+    }
+}
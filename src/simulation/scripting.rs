@@ -0,0 +1,133 @@
+use super::{Car, SimulationState};
+use crate::config::CarsConfig;
+use rhai::{Dynamic, Engine, Map, Scope, AST, FLOAT, INT};
+use std::collections::HashMap;
+
+/// Operations one script call may run before it is stopped, so a runaway loop cannot
+/// freeze the simulation
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// Compiled behavior script and the hooks it defines
+struct BehaviorScript {
+    path: String,
+    ast: AST,
+    target_speed: bool,
+    lane_change: bool,
+}
+
+/// Rhai scripts referenced by driver behaviors in cars.toml. A script may define
+/// `target_speed(car, speed)` returning the speed to aim for, and `lane_change(car, lane)`
+/// returning the lane to change into or `()` to stay, each given the built-in decision.
+/// A script that fails at run time is switched off and the built-in decisions take over.
+pub struct ScriptHooks {
+    engine: Engine,
+    scripts: HashMap<String, BehaviorScript>, // Behavior name -> script
+    lane_count: u32,
+}
+
+impl ScriptHooks {
+    pub fn load(cars_config: &CarsConfig, lane_count: u32) -> Self {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        
+        let mut scripts = HashMap::new();
+        for (name, behavior) in &cars_config.behavior {
+            let Some(path) = &behavior.script else {
+                continue;
+            };
+            let ast = match std::fs::read_to_string(path).map_err(|e| e.to_string())
+                .and_then(|source| engine.compile(&source).map_err(|e| e.to_string()))
+            {
+                Ok(ast) => ast,
+                Err(e) => {
+                    log::error!("Cannot load script {} for behavior '{}': {}", path, name, e);
+                    continue;
+                }
+            };
+            let defines = |hook: &str| ast.iter_functions().any(|function| function.name == hook && function.params.len() == 2);
+            scripts.insert(name.clone(), BehaviorScript {
+                path: path.clone(),
+                target_speed: defines("target_speed"),
+                lane_change: defines("lane_change"),
+                ast,
+            });
+        }
+        
+        Self {
+            engine,
+            scripts,
+            lane_count,
+        }
+    }
+    
+    pub fn has_script(&self, behavior: &str) -> bool {
+        self.scripts.contains_key(behavior)
+    }
+    
+    /// Target speed the car's script chooses instead of `speed`, in m/s
+    pub fn target_speed(&mut self, car: &Car, state: &SimulationState, speed: f32) -> f32 {
+        let car_map = self.car_map(car, state);
+        let Some(script) = self.scripts.get_mut(state.names.behavior(car.behavior_type)).filter(|script| script.target_speed) else {
+            return speed;
+        };
+        let result = self.engine.call_fn::<Dynamic>(&mut Scope::new(), &script.ast, "target_speed", (car_map, speed as FLOAT))
+            .map_err(|e| e.to_string())
+            .and_then(|result| {
+                let value = result.as_float().or_else(|_| result.as_int().map(|value| value as FLOAT))?;
+                Ok(value as f32)
+            })
+            .and_then(|value| if value.is_finite() && value >= 0.0 { Ok(value) } else { Err(format!("speed {} is not a valid speed", value)) });
+        match result {
+            Ok(value) => value,
+            Err(e) => {
+                log::warn!("target_speed in {} failed, using built-in speeds: {}", script.path, e);
+                script.target_speed = false;
+                speed
+            }
+        }
+    }
+    
+    /// Lane the car's script wants to change into instead of `lane` (`None` = stay)
+    pub fn lane_change(&mut self, car: &Car, state: &SimulationState, lane: Option<u32>) -> Option<u32> {
+        let car_map = self.car_map(car, state);
+        let Some(script) = self.scripts.get_mut(state.names.behavior(car.behavior_type)).filter(|script| script.lane_change) else {
+            return lane;
+        };
+        let proposed = lane.map_or(Dynamic::UNIT, |lane| Dynamic::from_int(lane as INT));
+        let result = self.engine.call_fn::<Dynamic>(&mut Scope::new(), &script.ast, "lane_change", (car_map, proposed))
+            .map_err(|e| e.to_string())
+            .and_then(|result| {
+                if result.is_unit() {
+                    return Ok(None);
+                }
+                let value = result.as_int()?;
+                u32::try_from(value).ok().filter(|&lane| lane >= 1)
+                    .map(Some)
+                    .ok_or_else(|| format!("lane {} does not exist", value))
+            });
+        match result {
+            Ok(value) => value,
+            Err(e) => {
+                log::warn!("lane_change in {} failed, using built-in lane changes: {}", script.path, e);
+                script.lane_change = false;
+                lane
+            }
+        }
+    }
+    
+    /// What a script sees of a car
+    fn car_map(&self, car: &Car, state: &SimulationState) -> Map {
+        let mut map = Map::new();
+        map.insert("id".into(), Dynamic::from_int(car.id.index as INT));
+        map.insert("lane".into(), Dynamic::from_int(car.current_lane as INT));
+        map.insert("lane_count".into(), Dynamic::from_int(self.lane_count as INT));
+        map.insert("speed".into(), Dynamic::from_float(car.velocity.magnitude() as FLOAT));
+        map.insert("preferred_speed".into(), Dynamic::from_float(car.preferred_speed as FLOAT));
+        map.insert("x".into(), Dynamic::from_float(car.position.x as FLOAT));
+        map.insert("y".into(), Dynamic::from_float(car.position.y as FLOAT));
+        map.insert("heading".into(), Dynamic::from_float(car.heading as FLOAT));
+        map.insert("car_type".into(), state.names.car_type(car.car_type).into());
+        map.insert("time".into(), Dynamic::from_float(state.time as FLOAT));
+        map
+    }
+}
@@ -0,0 +1,184 @@
+use super::{Car, Point, SimulationState, SimulationEvent};
+use crate::config::{RouteConfig, RouteGeometry, SignalGroup, SignalHead};
+use nalgebra::{Point2, Vector2};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SignalPhase {
+    Green,
+    Yellow,
+    Red,
+}
+
+/// Current state of one signal head, refreshed every tick and used by physics and rendering
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalState {
+    pub group_id: String,
+    pub position: Point,   // where the signal head is drawn
+    pub heading: f32,      // radians, direction of travel of controlled traffic
+    pub phase: SignalPhase,
+    pub stop_line: StopLine,
+    pub lanes: Vec<u32>,   // controlled lanes (empty = all lanes)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum StopLine {
+    /// Stop line across every lane of a ring route at a fixed angle (radians)
+    Ring { center: Point, angle: f32 },
+    /// Straight stop line centered on `center`, perpendicular to the signal heading
+    Straight { center: Point, half_width: f32 },
+}
+
+impl SignalState {
+    fn controls_lane(&self, lane: u32) -> bool {
+        self.lanes.is_empty() || self.lanes.contains(&lane)
+    }
+    
+    /// Distance the car has to travel to reach this stop line, if it is ahead of the car
+    pub fn distance_ahead(&self, car: &Car) -> Option<f32> {
+        if !self.controls_lane(car.current_lane) {
+            return None;
+        }
+        self.stop_line.distance_ahead(self.heading, car)
+    }
+}
+
+impl StopLine {
+    /// Place the stop line of a signal head (or anything positioned like one) on the route.
+    /// Returns the line with the position the head is drawn at and the heading of the
+    /// traffic it faces, in radians.
+    pub fn for_head(head: &SignalHead, geometry: &RouteGeometry) -> (Self, Point, f32) {
+        let center = Point2::new(geometry.center_x, geometry.center_y);
+        let road_width = geometry.lane_width * geometry.lane_count as f32;
+        
+        if let Some(angle) = head.angle {
+            // Draw the head just outside the outermost lane
+            let angle = angle.to_radians();
+            let radius = geometry.inner_radius + road_width + geometry.lane_width;
+            let position = center + Vector2::new(angle.cos(), angle.sin()) * radius;
+            (StopLine::Ring { center, angle }, position, angle + PI / 2.0)
+        } else {
+            let position = Point2::new(head.x.unwrap_or(0.0), head.y.unwrap_or(0.0));
+            let half_width = head.width.unwrap_or(road_width) / 2.0;
+            (StopLine::Straight { center: position, half_width }, position, head.heading.unwrap_or(0.0).to_radians())
+        }
+    }
+    
+    /// Distance a car has to travel to reach the line, if it is ahead of the car and the car
+    /// is moving with traffic of the given heading
+    pub fn distance_ahead(&self, heading: f32, car: &Car) -> Option<f32> {
+        self.signed_distance(heading, car).filter(|&distance| distance > 0.0)
+    }
+    
+    /// Distance from the car's center to the line along the direction of travel: positive
+    /// before the line, negative once past it. `None` if the car is not in the lanes the
+    /// line spans or is not moving with traffic of the given heading.
+    pub fn signed_distance(&self, heading: f32, car: &Car) -> Option<f32> {
+        match self {
+            StopLine::Ring { center, angle } => {
+                // Ring traffic travels counter-clockwise
+                let to_car = car.position - center;
+                let car_angle = to_car.y.atan2(to_car.x);
+                let angle_ahead = (angle - car_angle).rem_euclid(2.0 * PI);
+                let angle_ahead = if angle_ahead < PI { angle_ahead } else { angle_ahead - 2.0 * PI };
+                Some(angle_ahead * to_car.magnitude())
+            }
+            StopLine::Straight { center, half_width } => {
+                let direction = Vector2::new(heading.cos(), heading.sin());
+                let car_direction = Vector2::new(car.heading.cos(), car.heading.sin());
+                if car_direction.dot(&direction) < 0.7 {
+                    return None; // Car is not travelling in the controlled direction
+                }
+                
+                let to_line = center - car.position;
+                let along = to_line.dot(&direction);
+                let lateral = (to_line - direction * along).magnitude();
+                if lateral <= *half_width {
+                    Some(along)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// Drives signal phases from the route's signal groups. Phases are a pure function of
+/// simulation time so every backend (and replays) see the same lights, unless a group is
+/// held by `SimulationState::signal_overrides`.
+pub struct SignalController {
+    groups: Vec<SignalGroup>,
+    heads: Vec<SignalState>,
+}
+
+impl SignalController {
+    pub fn new(route: &RouteConfig) -> Self {
+        let geometry = &route.route.geometry;
+        
+        let mut heads = Vec::new();
+        for group in &route.route.signals.groups {
+            for head in &group.heads {
+                let (stop_line, position, heading) = StopLine::for_head(head, geometry);
+                heads.push(SignalState {
+                    group_id: group.id.clone(),
+                    position,
+                    heading,
+                    phase: SignalPhase::Green,
+                    stop_line,
+                    lanes: head.lanes.clone(),
+                });
+            }
+        }
+        
+        Self {
+            groups: route.route.signals.groups.clone(),
+            heads,
+        }
+    }
+    
+    pub fn phase_at(group: &SignalGroup, time: f32) -> SignalPhase {
+        let cycle_time = group.cycle_time();
+        if cycle_time <= 0.0 {
+            return SignalPhase::Green;
+        }
+        
+        let t = (time + group.offset).rem_euclid(cycle_time);
+        if t < group.green_time {
+            SignalPhase::Green
+        } else if t < group.green_time + group.yellow_time {
+            SignalPhase::Yellow
+        } else {
+            SignalPhase::Red
+        }
+    }
+    
+    /// Publish current signal phases into the simulation state
+    pub fn update(&self, state: &mut SimulationState) {
+        if self.heads.is_empty() {
+            return;
+        }
+        
+        let initializing = state.signals.len() != self.heads.len();
+        if initializing {
+            state.signals = self.heads.clone();
+        }
+        
+        for group in &self.groups {
+            let phase = state.signal_overrides.get(&group.id).copied()
+                .unwrap_or_else(|| Self::phase_at(group, state.time));
+            let mut changed = false;
+            for signal in state.signals.iter_mut().filter(|signal| signal.group_id == group.id) {
+                changed |= signal.phase != phase;
+                signal.phase = phase;
+            }
+            if changed && !initializing {
+                state.events.push(SimulationEvent::SignalPhaseChanged {
+                    group: group.id.clone(),
+                    phase,
+                    time: state.time,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use super::{Car, Point};
+use std::collections::HashMap;
+
+/// Uniform grid over car positions for neighbor queries.
+///
+/// Cars are bucketed by the cell containing their position, so a radius query only
+/// has to look at the cells overlapping the search circle instead of every car.
+/// Indices refer to the car slice the index was built from and stay valid until
+/// cars are added or removed.
+#[derive(Debug, Clone)]
+pub struct SpatialIndex {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl SpatialIndex {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size: cell_size.max(1.0),
+            cells: HashMap::new(),
+        }
+    }
+    
+    pub fn build(cars: &[Car], cell_size: f32) -> Self {
+        let mut index = Self::new(cell_size);
+        for (i, car) in cars.iter().enumerate() {
+            index.insert(i, &car.position);
+        }
+        index
+    }
+    
+    /// Index `positions` in place of the cars indexed so far, keeping the cells' storage
+    /// when the cell size stays the same
+    pub fn refill(&mut self, positions: &[Point], cell_size: f32) {
+        let cell_size = cell_size.max(1.0);
+        if cell_size == self.cell_size {
+            for cell in self.cells.values_mut() {
+                cell.clear();
+            }
+        } else {
+            self.cell_size = cell_size;
+            self.cells.clear();
+        }
+        for (i, position) in positions.iter().enumerate() {
+            self.insert(i, position);
+        }
+    }
+    
+    pub fn insert(&mut self, car_index: usize, position: &Point) {
+        let cell = self.cell_of(position);
+        self.cells.entry(cell).or_default().push(car_index);
+    }
+    
+    /// Indices of cars that may lie within `radius` of `position`, in ascending order.
+    /// Candidates come from whole cells, so callers still apply their own distance checks.
+    pub fn query(&self, position: &Point, radius: f32) -> Vec<usize> {
+        let (min_x, min_y) = self.cell_of(&Point::new(position.x - radius, position.y - radius));
+        let (max_x, max_y) = self.cell_of(&Point::new(position.x + radius, position.y + radius));
+        
+        let mut candidates = Vec::new();
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                if let Some(cell) = self.cells.get(&(x, y)) {
+                    candidates.extend_from_slice(cell);
+                }
+            }
+        }
+        
+        // Keep the same order as a scan over all cars so results don't depend on the index
+        candidates.sort_unstable();
+        candidates
+    }
+    
+    /// Cars from `cars` that may lie within `radius` of `position`
+    pub fn cars_near<'a>(&self, cars: &'a [Car], position: &Point, radius: f32) -> impl Iterator<Item = &'a Car> {
+        self.query(position, radius).into_iter().filter_map(move |i| cars.get(i))
+    }
+    
+    fn cell_of(&self, position: &Point) -> (i32, i32) {
+        (
+            (position.x / self.cell_size).floor() as i32,
+            (position.y / self.cell_size).floor() as i32,
+        )
+    }
+}
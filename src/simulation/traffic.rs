@@ -1,30 +1,106 @@
-use super::{Car, CarId, SimulationState, BehaviorEngine};
-use crate::config::{CarsConfig, RouteConfig, CarType};
+use super::{Car, CarId, BehaviorId, CarTypeId, NameTable, SimulationState, SimulationEvent, SpatialIndex, BehaviorEngine, RandomStream, PhiloxKey, SignalController, IntersectionController, ConflictController, WeatherController, RampMeterController, MergeController, BusController, BatteryController, SafetyMonitor, QueueDetector, TravelTimeMonitor, ExitRamps, RampPosition, GridNetwork, GridPath, WeightedPath, grid_cell_center, grid_spawn_for_entry, grid_spawn_heading, place_on_lane, lane_point, LanePlacement, SpareCar};
+use crate::config::{CarsConfig, RouteConfig, CarType, GridPoint, InitialTraffic, WorldBounds, CLOVERLEAF_HIGHWAY_EXTENT};
+use anyhow::{anyhow, Result};
 use nalgebra::{Point2, Vector2};
-use rand::{Rng, SeedableRng};
+use rand::Rng;
 use rand::rngs::StdRng;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Radius used to match a new car's speed to nearby traffic, also the spawn index cell size
+const SPAWN_CHECK_RADIUS: f32 = 30.0;
+/// Meters past an exit within which a car in the exit lane turns off there
+const EXIT_WINDOW: f32 = 10.0;
+/// Meters kept clear between a placed car and the cars ahead and behind it
+const MIN_PLACEMENT_GAP: f32 = 2.0;
+/// Entry recorded for cars placed on the road rather than spawned at an entry
+pub const PLACED_ENTRY: &str = "placed";
 
 pub struct TrafficManager {
     car_types: Vec<CarType>,
     route: RouteConfig,
     cars_config: CarsConfig,
+    names: Arc<NameTable>, // behavior and car type names of the cars, published in the state
     behavior_engine: BehaviorEngine,
-    next_car_id: usize,
+    transfer_exits: HashSet<String>, // exits whose cars carry on along another route, keeping their ids
     spawn_timers: HashMap<String, f32>, // Entry ID -> time until next spawn
-    rng: StdRng,
+    grid_network: Option<GridNetwork>, // Road network for grid routes
+    signal_controller: SignalController,
+    intersections: IntersectionController,
+    conflicts: ConflictController,
+    weather_controller: WeatherController,
+    ramp_meters: RampMeterController,
+    merges: MergeController,
+    buses: BusController,
+    batteries: BatteryController,
+    safety: SafetyMonitor,
+    queues: QueueDetector,
+    travel_times: TravelTimeMonitor,
+    exit_ramps: ExitRamps,
+    bounds: WorldBounds, // cars outside have driven off the end of the road
+    recycling: Vec<Car>, // cars that left a closed loop, waiting to re-enter
+    start_pending: bool, // the road is yet to get the ring's or the initial traffic's cars
+    spawn_rng: StdRng,
+    despawn_rng: StdRng,
 }
 
 impl TrafficManager {
-    pub fn new(cars_config: CarsConfig, route: RouteConfig, seed: Option<u64>) -> Self {
-        let behavior_engine = BehaviorEngine::new(&cars_config, route.clone(), seed);
+    pub fn new(cars_config: CarsConfig, mut route: RouteConfig, seed: Option<u64>) -> Self {
+        // A closed ring has no way on or off, its cars are placed at the start and drive on
+        if cars_config.simulation.ring_cars.is_some() {
+            route.route.entries.clear();
+            route.route.exits.clear();
+            route.route.od_matrix.clear();
+        }
+        let names = Arc::new(NameTable::new(&cars_config));
+        let behavior_engine = BehaviorEngine::new(&cars_config, &names, route.clone(), seed);
         
-        let rng = if let Some(seed) = seed {
-            StdRng::seed_from_u64(seed)
-        } else {
-            StdRng::from_entropy()
-        };
+        let spawn_rng = RandomStream::Spawn.rng(seed);
+        let spawn_timers = Self::initial_spawn_timers(&cars_config, &route, &spawn_rng);
+        
+        let grid_network = GridNetwork::from_geometry(&route.route.geometry);
+        let signal_controller = SignalController::new(&route);
+        let intersections = IntersectionController::new(&route);
+        let conflicts = ConflictController::new(&route);
+        let weather_controller = WeatherController::new(&route);
+        let ramp_meters = RampMeterController::new(&route, |entry| Self::calculate_entry_position(entry, &route.route.geometry));
+        let merges = MergeController::new(&route, |entry| Self::calculate_entry_pose(entry, &route.route.geometry));
+        let buses = BusController::new(&route);
+        let batteries = BatteryController::new(&cars_config.electric, &route);
+        let safety = SafetyMonitor::new(&cars_config.safety, &route);
+        let queues = QueueDetector::new(&cars_config.queues, &route.route.geometry);
+        let travel_times = TravelTimeMonitor::new(&route);
         
+        Self {
+            car_types: cars_config.car_types.clone(),
+            route: route.clone(),
+            cars_config: cars_config.clone(),
+            names,
+            behavior_engine,
+            transfer_exits: HashSet::new(),
+            spawn_timers,
+            grid_network,
+            signal_controller,
+            intersections,
+            conflicts,
+            weather_controller,
+            ramp_meters,
+            merges,
+            buses,
+            batteries,
+            safety,
+            queues,
+            travel_times,
+            exit_ramps: ExitRamps::from_route(&route),
+            bounds: route.route.geometry.world_bounds(),
+            recycling: Vec::new(),
+            start_pending: true,
+            spawn_rng,
+            despawn_rng: RandomStream::Despawn.rng(seed),
+        }
+    }
+    
+    fn initial_spawn_timers(cars_config: &CarsConfig, route: &RouteConfig, rng: &StdRng) -> HashMap<String, f32> {
         // Initialize spawn timers based on spawn_rate
         let mut spawn_timers = HashMap::new();
         let base_interval = 1.0 / cars_config.simulation.spawn_rate; // Convert rate to interval
@@ -39,109 +115,280 @@ impl TrafficManager {
             spawn_timers.insert(entry.id.clone(), interval);
         }
         
-        Self {
-            car_types: cars_config.car_types.clone(),
-            route: route.clone(),
-            cars_config: cars_config.clone(),
-            behavior_engine,
-            next_car_id: 0,
-            spawn_timers,
-            rng,
+        spawn_timers
+    }
+    
+    /// Prepare to continue from a loaded checkpoint: the random streams restart from
+    /// `seed` so branches taken from the same checkpoint with the same seed evolve
+    /// identically. Car ids carry on from the slots saved in the state.
+    pub fn restore(&mut self, state: &SimulationState, seed: Option<u64>) {
+        self.spawn_rng = RandomStream::Spawn.rng(seed);
+        self.despawn_rng = RandomStream::Despawn.rng(seed);
+        self.behavior_engine.reseed(seed);
+        self.spawn_timers = Self::initial_spawn_timers(&self.cars_config, &self.route, &self.spawn_rng);
+        self.intersections.restore(state);
+        self.conflicts.restore(state);
+        self.ramp_meters.restore(state);
+        self.merges.restore(state);
+        self.buses.restore(state);
+        self.safety.reset();
+        self.queues = QueueDetector::new(&self.cars_config.queues, &self.route.route.geometry);
+        self.travel_times.reset();
+        self.recycling.clear();
+        self.start_pending = false;
+    }
+    
+    /// Start over as if just created with `seed`: fresh random streams and spawn timers,
+    /// signals, weather and ramp meters back at their first phase and no cars waiting to
+    /// merge, at intersections or holding junction reservations
+    pub fn reset(&mut self, seed: Option<u64>) {
+        self.spawn_rng = RandomStream::Spawn.rng(seed);
+        self.despawn_rng = RandomStream::Despawn.rng(seed);
+        self.behavior_engine.reset(seed);
+        self.spawn_timers = Self::initial_spawn_timers(&self.cars_config, &self.route, &self.spawn_rng);
+        self.signal_controller = SignalController::new(&self.route);
+        self.intersections = IntersectionController::new(&self.route);
+        self.conflicts = ConflictController::new(&self.route);
+        self.weather_controller = WeatherController::new(&self.route);
+        let geometry = &self.route.route.geometry;
+        self.ramp_meters = RampMeterController::new(&self.route, |entry| Self::calculate_entry_position(entry, geometry));
+        self.merges = MergeController::new(&self.route, |entry| Self::calculate_entry_pose(entry, geometry));
+        self.buses = BusController::new(&self.route);
+        self.batteries = BatteryController::new(&self.cars_config.electric, &self.route);
+        self.safety = SafetyMonitor::new(&self.cars_config.safety, &self.route);
+        self.queues = QueueDetector::new(&self.cars_config.queues, &self.route.route.geometry);
+        self.travel_times.reset();
+        self.recycling.clear();
+        self.start_pending = true;
+    }
+    
+    /// Leave the behavior decisions of plain cars to the GPU kernel, see `BehaviorEngine::set_device_behavior`
+    pub fn set_device_behavior(&mut self, enabled: bool) {
+        self.behavior_engine.set_device_behavior(enabled);
+    }
+    
+    /// Cars whose behavior decisions the latest update left to the GPU kernel
+    pub fn device_behavior_cars(&self) -> Option<&HashSet<CarId>> {
+        self.behavior_engine.device_cars()
+    }
+    
+    /// Keys of the speed jitter and lane change draws, for the GPU kernel to draw the same
+    pub fn behavior_draw_keys(&self) -> (PhiloxKey, PhiloxKey) {
+        self.behavior_engine.draw_keys()
+    }
+    
+    /// Keep the ids of the cars leaving by `exits`, which carry on along another route of
+    /// a multi-route world
+    pub fn set_transfer_exits(&mut self, exits: impl IntoIterator<Item = String>) {
+        self.transfer_exits = exits.into_iter().collect();
+    }
+    
+    /// Make the cars of `state` carry ids of this manager's names and publish them in it. A
+    /// state loaded from a checkpoint or replay brings the table its ids were taken from,
+    /// whose names this one gains if it lacks them; a state with no table yet has ids of ours.
+    fn adopt_names(&mut self, state: &mut SimulationState) {
+        if Arc::ptr_eq(&state.names, &self.names) {
+            return;
         }
+        if !state.cars.is_empty() && !state.names.is_empty() && *state.names != *self.names {
+            if !self.names.covers(&state.names) {
+                self.names = Arc::new(self.names.including(&state.names));
+            }
+            let (theirs, ours) = (&state.names, &self.names);
+            for car in &mut state.cars {
+                car.behavior_type = ours.behaviors.id(theirs.behavior(car.behavior_type)).unwrap_or(car.behavior_type);
+                car.car_type = ours.car_types.id(theirs.car_type(car.car_type)).unwrap_or(car.car_type);
+            }
+            state.names = self.names.clone();
+            state.reindex_cars();
+        }
+        state.names = self.names.clone();
+    }
+    
+    /// Id of `behavior_name` if the configuration has such a behavior
+    fn configured_behavior(&self, behavior_name: &str) -> Result<BehaviorId> {
+        self.names.behaviors.id(behavior_name)
+            .filter(|_| self.cars_config.behavior.contains_key(behavior_name))
+            .ok_or_else(|| anyhow!("Unknown behavior '{}'", behavior_name))
+    }
+    
+    fn car_type_id(&self, car_type: &CarType) -> CarTypeId {
+        self.names.car_types.id(&car_type.id).expect("the name table has the configuration's car types")
     }
     
     pub fn update(&mut self, state: &mut SimulationState) {
+        self.adopt_names(state);
+        if std::mem::take(&mut self.start_pending) {
+            self.place_start_traffic(state);
+        }
+        
+        // Distance covered in the last physics step, before any car leaves
+        for car in &mut state.cars {
+            car.distance_traveled += car.velocity.magnitude() * state.dt;
+        }
+        
+        // Electric vehicles pay for that distance from their batteries
+        self.batteries.update(state);
+        
+        // Advance traffic signal phases, intersection right of way and junction
+        // reservations, ramp meters, buses at their stops and the weather before anyone
+        // reacts to them
+        self.signal_controller.update(state);
+        self.intersections.update(state);
+        self.conflicts.update(state);
+        self.ramp_meters.update(state);
+        self.buses.update(state);
+        self.weather_controller.update(state);
+        
         // Update behavior for existing cars
         self.behavior_engine.update(state);
         
+        // Cars that left a closed loop re-enter before new cars spawn
+        self.update_recycling(state);
+        
         // Handle car spawning
         self.update_spawning(state);
         
-        // Handle car despawning (cars that have exited)
-        self.update_despawning(state);
+        // Handle car despawning (cars that have exited), a closed ring keeps all its cars
+        if self.cars_config.simulation.ring_cars.is_none() {
+            self.update_despawning(state);
+        }
+        
+        // Measure how close the cars still on the road came to each other
+        self.safety.update(state);
+        
+        // Time the cars between the route's waypoints
+        self.travel_times.update(state);
+        
+        // Find the queues the cars left on the road line up in
+        self.queues.update(state);
     }
     
     fn update_spawning(&mut self, state: &mut SimulationState) {
+        let mut index = SpatialIndex::build(&state.cars, SPAWN_CHECK_RADIUS);
+        
+        // Collect entries that need spawning
+        let entries_to_check: Vec<_> = self.route.route.entries.clone();
+        
+        // Cars already waiting at entries join once mainline traffic has opened a gap for them
+        for (entry_id, speed) in self.merges.update(state, &self.cars_config) {
+            // A car limit lowered while the car waited turns it away
+            if self.at_car_limit(state) {
+                continue;
+            }
+            if let Some(entry) = entries_to_check.iter().find(|entry| entry.id == entry_id) {
+                log::debug!("Car merging at entry {} at {:.1} m/s", entry.id, speed);
+                self.spawn_car_at_entry(entry, Some(speed), state, &mut index);
+            }
+        }
+        
         // Don't spawn if we've reached the car limit
-        if state.active_cars >= self.cars_config.simulation.total_cars {
+        if self.at_car_limit(state) {
             return;
         }
         
         let dt = state.dt;
         let mut spawn_requests = Vec::new();
         
-        // Collect entries that need spawning
-        let entries_to_check: Vec<_> = self.route.route.entries.clone();
-        
-        // Update spawn timers and collect spawn requests
-        for (entry_id, timer) in self.spawn_timers.iter_mut() {
-            *timer -= dt;
+        // Update spawn timers and collect spawn requests, in route order so seeded runs repeat
+        for entry in &entries_to_check {
+            let entry_id = &entry.id;
+            let Some(timer) = self.spawn_timers.get_mut(entry_id) else {
+                continue;
+            };
+            // The timer runs at the entry's demand, so its spawn rate follows the profile
+            // and stops while demand is zero
+            *timer -= dt * self.cars_config.traffic_flow.demand_factor(entry_id, state.time);
+            if *timer > 0.0 {
+                continue;
+            }
             
-            if *timer <= 0.0 {
-                // Try to spawn a car at this entry
-                if let Some(entry) = entries_to_check.iter().find(|e| &e.id == entry_id) {
-                    // Try natural spawning first, then force spawn if needed
-                    let natural_spawn = Self::can_spawn_at_entry_static(entry, state, &self.route.route.geometry) ||
-                                       Self::can_spawn_at_entry_permissive(entry, state, &self.route.route.geometry);
-                    
-                    // Always add to spawn requests - we'll force gaps as needed
-                    spawn_requests.push((entry_id.clone(), entry.clone(), natural_spawn));
-                    
-                    // Reset timer with random interval
-                    let base_interval = 1.0 / self.cars_config.simulation.spawn_rate;
-                    let entry_interval = self.cars_config.traffic_flow.entry_intervals
-                        .iter()
-                        .find(|ei| &ei.entry_id == entry_id);
-                    
-                    *timer = if let Some(interval) = entry_interval {
-                        self.rng.gen_range(interval.min_interval..=interval.max_interval)
-                    } else {
-                        base_interval // Use spawn_rate as default
-                    };
-                }
+            if self.ramp_meters.is_metered(entry_id) {
+                // Metered cars wait on the ramp until the meter lets them go
+                self.ramp_meters.enqueue(entry_id);
+            } else {
+                let natural_spawn = self.can_spawn_naturally(entry, state, &index);
+                
+                // Always add to spawn requests - blocked cars wait to merge
+                spawn_requests.push((entry.clone(), natural_spawn, false));
+            }
+            
+            // Reset timer with random interval
+            let base_interval = 1.0 / self.cars_config.simulation.spawn_rate;
+            let entry_interval = self.cars_config.traffic_flow.entry_intervals
+                .iter()
+                .find(|ei| &ei.entry_id == entry_id);
+            
+            let next_spawn = if let Some(interval) = entry_interval {
+                self.spawn_rng.gen_range(interval.min_interval..=interval.max_interval)
+            } else {
+                base_interval // Use spawn_rate as default
+            };
+            self.spawn_timers.insert(entry_id.clone(), next_spawn);
+        }
+        
+        // Ramp meters release the car at the head of their queue
+        for entry_id in self.ramp_meters.ready(state.time) {
+            if let Some(entry) = entries_to_check.iter().find(|entry| entry.id == entry_id) {
+                let natural_spawn = self.can_spawn_naturally(entry, state, &index);
+                spawn_requests.push((entry.clone(), natural_spawn, true));
             }
         }
         
-        // Process spawn requests and force gaps if needed
-        for (_entry_id, entry, natural_spawn) in spawn_requests {
-            if !natural_spawn {
-                // Need to force a gap before spawning
-                if !Self::force_spawn_gap(&entry, state, &self.route.route.geometry) {
-                    log::debug!("Could not force spawn gap at entry {}, skipping spawn", entry.id);
+        // Process spawn requests, cars that find no room or a car already waiting wait at
+        // the entry to merge
+        for (entry, natural_spawn, metered) in spawn_requests {
+            if !natural_spawn || self.merges.is_waiting(&entry.id) {
+                // Grid spawn cells are single-lane streets and metered cars wait on the ramp
+                // until the merge point clears instead
+                if self.grid_network.is_some() || metered {
                     continue;
                 }
+                
+                if !self.merges.request(&entry.id, state.time) {
+                    log::debug!("A car is already waiting to merge at entry {}, skipping spawn", entry.id);
+                }
+                continue;
+            }
+            self.spawn_car_at_entry(&entry, None, state, &mut index);
+            if metered {
+                self.ramp_meters.release(&entry.id, state.time);
             }
-            self.spawn_car_at_entry(&entry, state);
         }
     }
     
-    fn can_spawn_at_entry_static(
-        entry: &crate::config::EntryPoint, 
-        state: &SimulationState, 
-        route_geom: &crate::config::RouteGeometry
-    ) -> bool {
-        // Calculate entry position based on geometry type
-        let entry_pos = Self::calculate_entry_position(entry, route_geom);
-        
-        // Check if there's space at the entry point
-        let min_spawn_distance = 5.0; // Minimum distance from other cars (further reduced to allow spawning in traffic)
+    /// Whether a car can join the road at the entry without waiting for a gap
+    fn can_spawn_naturally(&self, entry: &crate::config::EntryPoint, state: &SimulationState, index: &SpatialIndex) -> bool {
+        if self.grid_network.is_some() {
+            Self::can_spawn_at_grid_entry(entry, state, index, &self.route.route.geometry, &self.cars_config)
+        } else {
+            self.has_gap_at_entry(entry, state)
+        }
+    }
+    
+    /// Whether every car in or moving into the entry's lane is at least a car length plus
+    /// the distance it covers in the route's following time away from the spawn point.
+    /// Cars that find less room wait for the merge controller to open a gap.
+    fn has_gap_at_entry(&self, entry: &crate::config::EntryPoint, state: &SimulationState) -> bool {
+        let entry_pos = Self::calculate_entry_position(entry, &self.route.route.geometry);
+        let following_time = self.route.route.traffic_rules.following_distance;
+        let new_car_length = self.longest_car();
         
-        for car in &state.cars {
+        for car in state.cars.iter().filter(|car| car.current_lane == entry.lane || car.target_lane == Some(entry.lane)) {
             let distance = (car.position - entry_pos).magnitude();
-            if distance < min_spawn_distance {
-                log::debug!("Cannot spawn at entry {} - car too close ({:.1}m < {:.1}m)", entry.id, distance, min_spawn_distance);
+            let gap = (car.length + new_car_length) / 2.0 + MIN_PLACEMENT_GAP + car.velocity.magnitude() * following_time;
+            if distance < gap {
+                log::debug!("Cannot spawn at entry {} - car {} too close ({:.1}m < {:.1}m)", entry.id, car.id, distance, gap);
                 return false;
             }
         }
-        
-        log::debug!("Can spawn at entry {} - no blocking cars", entry.id);
-        
         true
     }
     
     fn can_spawn_at_entry_permissive(
         entry: &crate::config::EntryPoint, 
-        state: &SimulationState, 
+        state: &SimulationState,
+        index: &SpatialIndex, 
         route_geom: &crate::config::RouteGeometry
     ) -> bool {
         // Calculate entry position based on geometry type  
@@ -150,7 +397,7 @@ impl TrafficManager {
         // Very permissive distance check - only prevent spawning if cars are extremely close
         let min_spawn_distance = 2.0; // Only 2 meters - allows spawning in tight traffic
         
-        for car in &state.cars {
+        for car in index.cars_near(&state.cars, &entry_pos, min_spawn_distance) {
             let distance = (car.position - entry_pos).magnitude();
             if distance < min_spawn_distance {
                 log::debug!("Cannot spawn at entry {} - car extremely close ({:.1}m < {:.1}m)", entry.id, distance, min_spawn_distance);
@@ -163,100 +410,49 @@ impl TrafficManager {
         true
     }
     
-    fn force_spawn_gap(
+    fn can_spawn_at_grid_entry(
         entry: &crate::config::EntryPoint,
-        state: &mut SimulationState,
-        route_geom: &crate::config::RouteGeometry
+        state: &SimulationState,
+        index: &SpatialIndex,
+        route_geom: &crate::config::RouteGeometry,
+        cars_config: &CarsConfig
     ) -> bool {
         let entry_pos = Self::calculate_entry_position(entry, route_geom);
         
-        // Find cars within the force gap zone
-        let force_gap_distance = 15.0; // meters - distance within which we'll force cars to slow down
-        let minimum_spawn_distance = 3.0; // meters - absolute minimum distance for spawning
+        // The previous car must be far enough along that a new car won't emergency brake behind it
+        let min_spawn_distance = cars_config.collision_avoidance.emergency_brake_distance;
         
-        let mut cars_to_slow = Vec::new();
-        let mut closest_distance = f32::INFINITY;
-        
-        for car in &state.cars {
+        for car in index.cars_near(&state.cars, &entry_pos, min_spawn_distance) {
             let distance = (car.position - entry_pos).magnitude();
-            
-            if distance < minimum_spawn_distance {
-                // Too close even for forced spawning
-                log::debug!("Cannot force spawn at entry {} - car too close even for forced spawning ({:.1}m < {:.1}m)", entry.id, distance, minimum_spawn_distance);
+            if distance < min_spawn_distance {
+                log::debug!("Cannot spawn at grid entry {} - spawn cell occupied ({:.1}m < {:.1}m)", entry.id, distance, min_spawn_distance);
                 return false;
             }
-            
-            if distance < force_gap_distance {
-                cars_to_slow.push(car.id);
-                closest_distance = closest_distance.min(distance);
-            }
-        }
-        
-        if cars_to_slow.is_empty() {
-            // No cars nearby, can spawn freely
-            log::debug!("Force spawn at entry {} - no cars to slow down", entry.id);
-            return true;
         }
         
-        let num_cars_to_slow = cars_to_slow.len();
-        let dt = state.dt; // Store dt value before borrowing state
-        
-        // Force nearby cars to slow down to create a gap
-        for car_id in &cars_to_slow {
-            if let Some(car) = state.get_car_mut(*car_id) {
-                // Calculate how much to slow down based on distance to spawn point
-                let car_distance = (car.position - entry_pos).magnitude();
-                let slowdown_factor = 1.0 - (car_distance / force_gap_distance).min(1.0);
-                
-                // Reduce target speed to create gap - more reduction for closer cars
-                let new_target_speed = car.behavior.target_speed * (0.3 + 0.7 * (1.0 - slowdown_factor));
-                car.behavior.target_speed = new_target_speed.max(2.0); // Minimum speed of 2 m/s
-                
-                // Apply immediate deceleration for very close cars
-                if car_distance < force_gap_distance * 0.6 {
-                    let deceleration_factor = 0.7; // 70% of max deceleration
-                    let max_decel = car.max_deceleration * deceleration_factor;
-                    let current_speed = car.velocity.magnitude();
-                    let decel_velocity_change = max_decel * dt;
-                    
-                    if current_speed > decel_velocity_change {
-                        let new_speed = current_speed - decel_velocity_change;
-                        car.velocity = car.velocity.normalize() * new_speed;
-                    } else {
-                        car.velocity = car.velocity.normalize() * 1.0; // Minimum movement
-                    }
-                    
-                    log::debug!("Applied immediate deceleration to car {} at distance {:.1}m from spawn point", car_id.0, car_distance);
-                }
-                
-                log::debug!("Forced car {} to slow down: speed {:.1} -> {:.1} m/s (distance: {:.1}m)", 
-                           car_id.0, car.behavior.target_speed / (0.3 + 0.7 * (1.0 - slowdown_factor)), car.behavior.target_speed, car_distance);
-            }
-        }
-        
-        log::info!("Force spawning at entry {} - slowed down {} cars (closest: {:.1}m)", entry.id, num_cars_to_slow, closest_distance);
         true
     }
     
-    fn spawn_car_at_entry(&mut self, entry: &crate::config::EntryPoint, state: &mut SimulationState) {
-        let car_type_id = {
-            let total_weight: u32 = self.car_types.iter().map(|ct| ct.weight).sum();
-            let mut random_value = self.rng.gen_range(0..total_weight);
-            
-            let mut selected_type_id = self.car_types[0].id.clone();
-            for car_type in &self.car_types {
-                if random_value < car_type.weight {
-                    selected_type_id = car_type.id.clone();
-                    break;
-                }
-                random_value -= car_type.weight;
-            }
-            selected_type_id
+    /// Spawn a car at the entry, at `speed` if it is merging into a gap or else matching
+    /// nearby traffic
+    fn spawn_car_at_entry(&mut self, entry: &crate::config::EntryPoint, speed: Option<f32>, state: &mut SimulationState, index: &mut SpatialIndex) {
+        let car_type = self.pick_car_type();
+        
+        // Grid cars need a path to an exit before they can enter
+        let grid_path = self.plan_grid_path(entry);
+        if self.grid_network.is_some() && grid_path.is_none() {
+            log::warn!("No grid path from entry {} to any exit, skipping spawn", entry.id);
+            return;
+        }
+        // The new car goes into the buffers of one that left
+        let mut spare = state.car_pool.take();
+        let destination = match &grid_path {
+            Some(path) => spare.destination(Some(&path.exit_id)),
+            None => self.select_destination(entry, &mut spare),
         };
         
-        let car_type = self.car_types.iter().find(|ct| ct.id == car_type_id).unwrap().clone();
-        let behavior_name = self.behavior_engine.select_random_behavior();
-        let behavior_state = self.behavior_engine.create_behavior_state(&behavior_name);
+        let behavior_name = self.behavior_engine.select_random_behavior(&mut self.spawn_rng);
+        let behavior_state = self.behavior_engine.create_behavior_state(behavior_name);
         
         let route_geom = &self.route.route.geometry;
         
@@ -270,27 +466,40 @@ impl TrafficManager {
         let mut initial_speed = 15.6; // 35 mph in m/s (35 / 2.237 = 15.6) - entrance ramp speed
         
         // Check nearby cars and adjust spawn speed to match traffic flow
-        let check_radius = 30.0; // meters
-        let mut nearby_speeds = Vec::new();
+        let check_radius = SPAWN_CHECK_RADIUS;
+        let (mut speed_sum, mut nearby) = (0.0, 0);
         
-        for car in &state.cars {
+        for car in index.cars_near(&state.cars, &position, check_radius) {
             let distance = (car.position - position).magnitude();
             if distance < check_radius {
-                nearby_speeds.push(car.velocity.magnitude());
+                speed_sum += car.velocity.magnitude();
+                nearby += 1;
             }
         }
         
-        if !nearby_speeds.is_empty() {
+        if nearby > 0 {
             // Match average speed of nearby traffic, but ensure minimum reasonable speed
-            let avg_speed = nearby_speeds.iter().sum::<f32>() / nearby_speeds.len() as f32;
-            initial_speed = avg_speed.max(10.0).min(35.0); // Between 10-35 m/s (36-126 km/h)
-            log::debug!("Adaptive spawn speed: {:.1} m/s based on {} nearby cars", initial_speed, nearby_speeds.len());
+            let avg_speed = speed_sum / nearby as f32;
+            initial_speed = avg_speed.clamp(10.0, 35.0); // Between 10-35 m/s (36-126 km/h)
+            log::debug!("Adaptive spawn speed: {:.1} m/s based on {} nearby cars", initial_speed, nearby);
+        }
+        
+        // Grid streets are slow - never enter above the speed limit
+        if grid_path.is_some() {
+            initial_speed = initial_speed.min(self.route.route.traffic_rules.speed_limit);
+        }
+        
+        // Merging cars match the gap they were waiting for
+        if let Some(speed) = speed {
+            initial_speed = speed;
         }
         
         // Scale initial velocity by adaptive speed
         let velocity = initial_velocity.normalize() * initial_speed;
+        let car_type = &self.car_types[car_type];
+        let battery = self.batteries.battery_for(car_type, &mut self.spawn_rng);
         let car = Car {
-            id: CarId(self.next_car_id),
+            id: CarId::default(),
             position,
             velocity,
             acceleration: Vector2::zeros(),
@@ -300,55 +509,79 @@ impl TrafficManager {
             max_acceleration: car_type.max_acceleration,
             max_deceleration: car_type.max_deceleration,
             preferred_speed: car_type.preferred_speed,
+            mass: car_type.mass,
+            engine_power: car_type.engine_power,
+            rollover_threshold: car_type.rollover_threshold,
             current_lane: entry.lane,
             target_lane: None,
-            lane_change_progress: 0.0,
+            lateral_offset: 0.0,
+            lateral_velocity: 0.0,
             behavior: behavior_state,
             behavior_type: behavior_name,
-            car_type: car_type.id.clone(),
+            car_type: self.car_type_id(car_type),
             speed_history: [initial_speed, initial_speed, initial_speed],
             marked_for_exit: false,
             spawn_time: state.time,
+            entry: spare.entry(&entry.id),
+            distance_traveled: 0.0,
             exit_time: None,
+            grid_path,
+            destination,
+            crashed: false,
+            last_collision_time: None,
+            last_conflict_time: None,
+            breakdown: None,
+            exit_ramp: None,
+            turn_signal: None,
+            perception: spare.perception(),
+            bus: self.buses.board(car_type),
+            battery,
+            route: 0,
+            leader: None,
         };
         
-        state.add_car(car);
-        self.next_car_id += 1;
+        index.insert(state.cars.len(), &car.position);
+        let id = state.add_car(car);
+        state.events.push(SimulationEvent::CarSpawned {
+            car: id,
+            entry: entry.id.clone(),
+            time: state.time,
+        });
     }
     
-    pub fn spawn_manual_car(&mut self, behavior_name: &str, state: &mut SimulationState) {
-        // Find an available entry point
-        let entry = if let Some(entry) = self.route.route.entries.first() {
-            entry.clone()
-        } else {
-            log::warn!("No entry points available for manual car spawn");
-            return;
-        };
-        
-        // For manual spawning, be more permissive - allow spawning with closer cars
-        if !Self::can_spawn_at_entry_permissive(&entry, state, &self.route.route.geometry) {
-            log::debug!("Cannot spawn manual car - entry severely congested");
-            return;
+    /// Spawn a car with `behavior_name` at the first entry with room for it, as the
+    /// keyboard and telemetry controls do
+    pub fn spawn_manual_car(&mut self, behavior_name: &str, state: &mut SimulationState) -> Result<CarId> {
+        self.adopt_names(state);
+        let behavior_type = self.configured_behavior(behavior_name)?;
+        if self.route.route.entries.is_empty() {
+            return Err(anyhow!("No entry points available"));
         }
         
-        // Select a random car type
-        let car_type_id = {
-            let total_weight: u32 = self.car_types.iter().map(|ct| ct.weight).sum();
-            let mut random_value = self.rng.gen_range(0..total_weight);
+        // For manual spawning, be more permissive - allow spawning with closer cars
+        let index = SpatialIndex::build(&state.cars, SPAWN_CHECK_RADIUS);
+        let entry = self.route.route.entries.iter()
+            .find(|entry| if self.grid_network.is_some() {
+                Self::can_spawn_at_grid_entry(entry, state, &index, &self.route.route.geometry, &self.cars_config)
+            } else {
+                Self::can_spawn_at_entry_permissive(entry, state, &index, &self.route.route.geometry)
+            })
+            .cloned()
+            .ok_or_else(|| anyhow!("Every entry is severely congested"))?;
             
-            let mut selected_type_id = self.car_types[0].id.clone();
-            for car_type in &self.car_types {
-                if random_value < car_type.weight {
-                    selected_type_id = car_type.id.clone();
-                    break;
-                }
-                random_value -= car_type.weight;
-            }
-            selected_type_id
+        let car_type = self.random_car_type();
+        
+        let grid_path = self.plan_grid_path(&entry);
+        if self.grid_network.is_some() && grid_path.is_none() {
+            return Err(anyhow!("No grid path from entry {} to any exit", entry.id));
+        }
+        let mut spare = state.car_pool.take();
+        let destination = match &grid_path {
+            Some(path) => spare.destination(Some(&path.exit_id)),
+            None => self.select_destination(&entry, &mut spare),
         };
         
-        let car_type = self.car_types.iter().find(|ct| ct.id == car_type_id).unwrap().clone();
-        let behavior_state = self.behavior_engine.create_behavior_state(behavior_name);
+        let behavior_state = self.behavior_engine.create_behavior_state(behavior_type);
         
         let route_geom = &self.route.route.geometry;
         
@@ -365,7 +598,7 @@ impl TrafficManager {
         let check_radius = 25.0; // meters - smaller radius for manual spawning
         let mut nearby_speeds = Vec::new();
         
-        for car in &state.cars {
+        for car in index.cars_near(&state.cars, &position, check_radius) {
             let distance = (car.position - position).magnitude();
             if distance < check_radius {
                 nearby_speeds.push(car.velocity.magnitude());
@@ -375,15 +608,20 @@ impl TrafficManager {
         if !nearby_speeds.is_empty() {
             // For manual spawning, be more conservative - use min of nearby speeds
             let min_speed = nearby_speeds.iter().copied().fold(f32::INFINITY, f32::min);
-            initial_speed = min_speed.max(5.0).min(30.0); // Between 5-30 m/s to avoid collisions
+            initial_speed = min_speed.clamp(5.0, 30.0); // Between 5-30 m/s to avoid collisions
             log::debug!("Manual spawn speed: {:.1} m/s based on {} nearby cars (conservative)", initial_speed, nearby_speeds.len());
         }
         
+        if grid_path.is_some() {
+            initial_speed = initial_speed.min(self.route.route.traffic_rules.speed_limit);
+        }
+        
         // Scale initial velocity by conservative speed
         let velocity = initial_velocity.normalize() * initial_speed;
         
+        let battery = self.batteries.battery_for(&car_type, &mut self.spawn_rng);
         let car = Car {
-            id: CarId(self.next_car_id),
+            id: CarId::default(),
             position,
             velocity,
             acceleration: Vector2::zeros(),
@@ -393,50 +631,564 @@ impl TrafficManager {
             max_acceleration: car_type.max_acceleration,
             max_deceleration: car_type.max_deceleration,
             preferred_speed: car_type.preferred_speed,
+            mass: car_type.mass,
+            engine_power: car_type.engine_power,
+            rollover_threshold: car_type.rollover_threshold,
             current_lane: entry.lane,
             target_lane: None,
-            lane_change_progress: 0.0,
+            lateral_offset: 0.0,
+            lateral_velocity: 0.0,
             behavior: behavior_state,
-            behavior_type: behavior_name.to_string(),
-            car_type: car_type.id.clone(),
+            behavior_type,
+            car_type: self.car_type_id(&car_type),
             speed_history: [initial_speed, initial_speed, initial_speed],
             marked_for_exit: false,
             spawn_time: state.time,
+            entry: spare.entry(&entry.id),
+            distance_traveled: 0.0,
             exit_time: None,
+            grid_path,
+            destination,
+            crashed: false,
+            last_collision_time: None,
+            last_conflict_time: None,
+            breakdown: None,
+            exit_ramp: None,
+            turn_signal: None,
+            perception: spare.perception(),
+            bus: self.buses.board(&car_type),
+            battery,
+            route: 0,
+            leader: None,
         };
         
-        state.add_car(car);
-        self.next_car_id += 1;
+        let id = state.add_car(car);
+        state.events.push(SimulationEvent::CarSpawned {
+            car: id,
+            entry: entry.id.clone(),
+            time: state.time,
+        });
         
-        log::info!("Manually spawned {} car (ID: {})", behavior_name, self.next_car_id - 1);
+        log::info!("Manually spawned {} car (ID: {})", behavior_name, id);
+        Ok(id)
+    }
+    
+    /// Put a car with `behavior_name`, of `car_type` or one picked by weight, on the center
+    /// line of the lane under `point`, driving at the speed of the traffic around it
+    pub fn spawn_car_at(&mut self, point: Point2<f32>, behavior_name: &str, car_type: Option<&str>, state: &mut SimulationState) -> Result<CarId> {
+        let placement = place_on_lane(&self.route.route.geometry, point)
+            .ok_or_else(|| anyhow!("({:.0}, {:.0}) is not on a lane cars can be placed in", point.x, point.y))?;
+        self.adopt_names(state);
+        let behavior_type = self.configured_behavior(behavior_name)?;
+        let car_type = match car_type {
+            Some(id) => self.car_types.iter().find(|ct| ct.id == id).cloned()
+                .ok_or_else(|| anyhow!("Unknown car type '{}'", id))?,
+            None => self.random_car_type(),
+        };
+        
+        // Keep clear of the cars ahead and behind in the lane, and drive as fast as they do
+        let route_geom = &self.route.route.geometry;
+        let index = SpatialIndex::build(&state.cars, SPAWN_CHECK_RADIUS);
+        let mut nearby_speeds = Vec::new();
+        for car in index.cars_near(&state.cars, &placement.position, SPAWN_CHECK_RADIUS) {
+            if !car.occupies_lane(placement.lane, route_geom.lane_width) {
+                continue;
+            }
+            let distance = (car.position - placement.position).magnitude();
+            if distance < (car.length + car_type.length) / 2.0 + MIN_PLACEMENT_GAP {
+                return Err(anyhow!("Too close to car {} in lane {}", car.id, placement.lane));
+            }
+            if distance < SPAWN_CHECK_RADIUS {
+                nearby_speeds.push(car.velocity.magnitude());
+            }
+        }
+        let speed_limit = self.route.route.traffic_rules.speed_limit;
+        let initial_speed = if nearby_speeds.is_empty() {
+            speed_limit.min(car_type.preferred_speed)
+        } else {
+            nearby_speeds.iter().sum::<f32>() / nearby_speeds.len() as f32
+        };
+        
+        let id = self.place_car(placement, behavior_type, &car_type, initial_speed, state);
+        log::info!("Placed {} {} car (ID: {}) in lane {}", behavior_name, car_type.id, id, placement.lane);
+        Ok(id)
+    }
+    
+    /// Put a new car down at `placement`, driving at `speed`
+    fn place_car(&mut self, placement: LanePlacement, behavior_type: BehaviorId, car_type: &CarType, speed: f32, state: &mut SimulationState) -> CarId {
+        // Ring routes send the car to an exit like any other
+        let route_geom = &self.route.route.geometry;
+        let mut spare = state.car_pool.take();
+        let destination = if route_geom.geometry_type == "donut" {
+            let options: Vec<(&str, f32)> = self.route.route.exits.iter()
+                .map(|exit| (exit.id.as_str(), exit.weight.unwrap_or(1.0)))
+                .collect();
+            spare.destination(Self::pick_weighted(&mut self.spawn_rng, &options).copied())
+        } else {
+            None
+        };
+        
+        let direction = Vector2::new(placement.heading.cos(), placement.heading.sin());
+        let battery = self.batteries.battery_for(car_type, &mut self.spawn_rng);
+        let car = Car {
+            id: CarId::default(),
+            position: placement.position,
+            velocity: direction * speed,
+            acceleration: Vector2::zeros(),
+            heading: placement.heading,
+            length: car_type.length,
+            width: car_type.width,
+            max_acceleration: car_type.max_acceleration,
+            max_deceleration: car_type.max_deceleration,
+            preferred_speed: car_type.preferred_speed,
+            mass: car_type.mass,
+            engine_power: car_type.engine_power,
+            rollover_threshold: car_type.rollover_threshold,
+            current_lane: placement.lane,
+            target_lane: None,
+            lateral_offset: 0.0,
+            lateral_velocity: 0.0,
+            behavior: self.behavior_engine.create_behavior_state(behavior_type),
+            behavior_type,
+            car_type: self.car_type_id(car_type),
+            speed_history: [speed, speed, speed],
+            marked_for_exit: false,
+            spawn_time: state.time,
+            entry: spare.entry(PLACED_ENTRY),
+            distance_traveled: 0.0,
+            exit_time: None,
+            grid_path: None,
+            destination,
+            crashed: false,
+            last_collision_time: None,
+            last_conflict_time: None,
+            breakdown: None,
+            exit_ramp: None,
+            turn_signal: None,
+            perception: spare.perception(),
+            bus: self.buses.board(car_type),
+            battery,
+            route: 0,
+            leader: None,
+        };
+        
+        let id = state.add_car(car);
+        state.events.push(SimulationEvent::CarSpawned {
+            car: id,
+            entry: PLACED_ENTRY.to_string(),
+            time: state.time,
+        });
+        id
+    }
+        
+    /// Put down the cars the configuration starts the road with, a closed ring's or the
+    /// initial traffic
+    fn place_start_traffic(&mut self, state: &mut SimulationState) {
+        let simulation = &self.cars_config.simulation;
+        let density = simulation.initial.as_ref().and_then(InitialTraffic::density);
+        if let Some(count) = simulation.ring_cars {
+            self.place_ring_cars(count, state);
+        } else if let Some(density) = density {
+            if let Err(e) = self.populate(density, state) {
+                log::warn!("No initial traffic placed: {}", e);
+            }
+        }
+    }
+    
+    /// Fill a closed ring before its first step: `count` cars spread evenly over the lanes
+    /// and evenly around each lane
+    fn place_ring_cars(&mut self, count: u32, state: &mut SimulationState) {
+        let lanes = self.route.route.geometry.lane_count.max(1);
+        let per_lane: Vec<u32> = (1..=lanes)
+            .map(|lane| count / lanes + u32::from(lane <= count % lanes))
+            .collect();
+        let placed = self.place_evenly(&per_lane, state);
+        log::info!("Placed {} cars evenly around the ring", placed);
+    }
+    
+    /// Fill every lane evenly to `density` vehicles per km, up to the car limit, instead of
+    /// waiting for the entries to fill the road. For roads with no cars on them yet, on
+    /// donuts and cloverleafs; returns how many cars were placed.
+    pub fn populate(&mut self, density: f32, state: &mut SimulationState) -> Result<usize> {
+        self.adopt_names(state);
+        if !state.cars.is_empty() {
+            return Err(anyhow!("The road already has cars on it"));
+        }
+        let geometry = &self.route.route.geometry;
+        if lane_point(geometry, 1, 0.0).is_none() {
+            return Err(anyhow!("Cars can only be placed evenly on donut and cloverleaf routes"));
+        }
+        let longest = self.longest_car();
+        let jam_density = 1000.0 / (longest + MIN_PLACEMENT_GAP);
+        if density <= 0.0 || density > jam_density {
+            return Err(anyhow!("Density must be above 0 and at most {:.1} veh/km for cars of {:.1} m", jam_density, longest));
+        }
+        
+        let mut per_lane: Vec<u32> = (1..=geometry.lane_count)
+            .map(|lane| (geometry.lane_length(lane) * density / 1000.0).round() as u32)
+            .collect();
+        // Lanes give up cars alike to stay within the car limit
+        let (total, limit) = (per_lane.iter().sum::<u32>(), self.cars_config.simulation.total_cars);
+        if total > limit {
+            for count in &mut per_lane {
+                *count = (*count as u64 * limit as u64 / total as u64) as u32;
+            }
+        }
+        let placed = self.place_evenly(&per_lane, state);
+        log::info!("Placed {} cars at {:.1} veh/km per lane", placed, density);
+        Ok(placed)
+    }
+    
+    /// Spread `per_lane[i]` cars evenly along lane `i + 1`, the cars of each lane driving at
+    /// the speed that keeps the route's following time to the car ahead
+    fn place_evenly(&mut self, per_lane: &[u32], state: &mut SimulationState) -> usize {
+        let geometry = self.route.route.geometry.clone();
+        let rules = &self.route.route.traffic_rules;
+        let (following_time, speed_limit) = (rules.following_distance, rules.speed_limit);
+        let longest = self.longest_car();
+        
+        let mut placed = 0;
+        for (index, &count) in per_lane.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let lane = index as u32 + 1;
+            let spacing = geometry.lane_length(lane) / count as f32;
+            let speed = ((spacing - longest) / following_time).clamp(0.0, speed_limit);
+            // Each lane starts a little further along, so cars side by side are rare
+            let stagger = index as f32 / per_lane.len() as f32;
+            for slot in 0..count {
+                let Some(placement) = lane_point(&geometry, lane, (slot as f32 + stagger) * spacing) else {
+                    continue;
+                };
+                let behavior = self.behavior_engine.select_random_behavior(&mut self.spawn_rng);
+                let car_type = self.random_car_type();
+                self.place_car(placement, behavior, &car_type, speed, state);
+                placed += 1;
+            }
+        }
+        placed
+    }
+    
+    /// Length of the longest car type, the one placed cars leave room for
+    fn longest_car(&self) -> f32 {
+        self.car_types.iter().map(|car_type| car_type.length).fold(0.0, f32::max)
+    }
+    
+    /// Bring `car`, which left another route of a multi-route world, onto this road at
+    /// `entry_id` once the entry has room. It keeps its id, type, driver and speed, and
+    /// starts a new trip from the entry.
+    pub fn admit_car(&mut self, car: &Car, entry_id: &str, state: &mut SimulationState) -> Result<CarId> {
+        let entry = self.route.route.entries.iter()
+            .find(|entry| entry.id == entry_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Unknown entry '{}'", entry_id))?;
+        let index = SpatialIndex::build(&state.cars, SPAWN_CHECK_RADIUS);
+        if !self.can_spawn_naturally(&entry, state, &index) {
+            return Err(anyhow!("No room at entry {}", entry.id));
+        }
+        
+        let grid_path = self.plan_grid_path(&entry);
+        if self.grid_network.is_some() && grid_path.is_none() {
+            return Err(anyhow!("No grid path from entry {} to any exit", entry.id));
+        }
+        let mut spare = state.car_pool.take();
+        let destination = match &grid_path {
+            Some(path) => spare.destination(Some(&path.exit_id)),
+            None => self.select_destination(&entry, &mut spare),
+        };
+        
+        let route_geom = &self.route.route.geometry;
+        let position = Self::calculate_entry_position(&entry, route_geom);
+        let (initial_velocity, heading) = Self::calculate_entry_velocity(&entry, route_geom, &position);
+        let mut initial_speed = car.velocity.magnitude().max(5.0);
+        if grid_path.is_some() {
+            initial_speed = initial_speed.min(self.route.route.traffic_rules.speed_limit);
+        }
+        let bus = self.car_types.iter()
+            .find(|car_type| self.names.car_type(car.car_type) == car_type.id)
+            .and_then(|car_type| self.buses.board(car_type));
+            
+        let admitted = Car {
+            position,
+            velocity: initial_velocity.normalize() * initial_speed,
+            acceleration: Vector2::zeros(),
+            heading,
+            current_lane: entry.lane,
+            target_lane: None,
+            lateral_offset: 0.0,
+            lateral_velocity: 0.0,
+            speed_history: [initial_speed, initial_speed, initial_speed],
+            marked_for_exit: false,
+            spawn_time: state.time,
+            entry: spare.entry(&entry.id),
+            distance_traveled: 0.0,
+            exit_time: None,
+            grid_path,
+            destination,
+            exit_ramp: None,
+            turn_signal: None,
+            perception: spare.perception(),
+            bus,
+            ..car.clone()
+        };
+        
+        let id = state.readmit_car(admitted);
+        state.events.push(SimulationEvent::CarSpawned {
+            car: id,
+            entry: entry.id.clone(),
+            time: state.time,
+        });
+        Ok(id)
+    }
+    
+    /// A car type picked by the configured weights
+    fn random_car_type(&mut self) -> CarType {
+        let index = self.pick_car_type();
+        self.car_types[index].clone()
+    }
+    
+    /// Index of a car type picked by the configured weights
+    fn pick_car_type(&mut self) -> usize {
+        let total_weight: u32 = self.car_types.iter().map(|ct| ct.weight).sum();
+        let mut random_value = self.spawn_rng.gen_range(0..total_weight);
+        
+        for (index, car_type) in self.car_types.iter().enumerate() {
+            if random_value < car_type.weight {
+                return index;
+            }
+            random_value -= car_type.weight;
+        }
+        0
+    }
+    
+    /// Pick an exit reachable from the entry's spawn cell and plan a path to it. Exits are
+    /// weighted by the route's OD matrix when it has rows for this entry, otherwise by exit
+    /// point weight, and split between movements by the route's turning ratios where their
+    /// paths part at an intersection that has them. Returns `None` for non-grid routes.
+    fn plan_grid_path(&mut self, entry: &crate::config::EntryPoint) -> Option<GridPath> {
+        let network = self.grid_network.as_ref()?;
+        let spawn = grid_spawn_for_entry(&self.route.route.geometry, entry)?;
+        
+        let exits = network.reachable_exits(spawn);
+        let has_od_rows = self.route.route.od_matrix.iter().any(|pair| pair.origin == entry.id);
+        let options: Vec<(&GridPoint, f32)> = exits.into_iter()
+            .filter_map(|exit| {
+                if has_od_rows {
+                    self.od_weight(&entry.id, &exit.id).map(|weight| (exit, weight))
+                } else {
+                    Some((exit, exit.weight.unwrap_or(1.0)))
+                }
+            })
+            .collect();
+        let options = if self.route.route.turning_ratios.is_empty() {
+            options
+        } else {
+            let (exits, paths): (Vec<&GridPoint>, Vec<WeightedPath>) = options.iter()
+                .filter_map(|&(exit, weight)| Some((exit, (network.path_cells(spawn, exit)?, weight))))
+                .unzip();
+            let shares = network.turning_shares(&paths, &self.route.route.turning_ratios);
+            exits.into_iter().zip(shares).collect()
+        };
+        
+        let selected = *Self::pick_weighted(&mut self.spawn_rng, &options)?;
+        network.plan_path(spawn, selected)
+    }
+    
+    /// Destination exit for a car entering at `entry` on a ring route, in the spare car's
+    /// buffer, sampled from the route's OD matrix when it has rows for the entry, otherwise
+    /// by exit weight. `None` on other routes, or when no exit has a positive weight; such
+    /// cars leave at the first exit they reach in the exit lane.
+    fn select_destination(&mut self, entry: &crate::config::EntryPoint, spare: &mut SpareCar) -> Option<String> {
+        if self.route.route.geometry.geometry_type != "donut" {
+            return None;
+        }
+        let mut options: Vec<(&str, f32)> = self.route.route.od_matrix.iter()
+            .filter(|pair| pair.origin == entry.id)
+            .map(|pair| (pair.destination.as_str(), pair.weight))
+            .collect();
+        if options.is_empty() {
+            options = self.route.route.exits.iter()
+                .map(|exit| (exit.id.as_str(), exit.weight.unwrap_or(1.0)))
+                .collect();
+        }
+        
+        spare.destination(Self::pick_weighted(&mut self.spawn_rng, &options).copied())
+    }
+    
+    /// Combined OD matrix weight for an origin/destination pair, if the matrix lists it
+    fn od_weight(&self, origin: &str, destination: &str) -> Option<f32> {
+        let mut pairs = self.route.route.od_matrix.iter()
+            .filter(|pair| pair.origin == origin && pair.destination == destination)
+            .peekable();
+        pairs.peek()?;
+        Some(pairs.map(|pair| pair.weight).sum())
+    }
+    
+    /// Weighted random choice; `None` if there are no options with positive weight
+    fn pick_weighted<'a, T>(rng: &mut StdRng, options: &'a [(T, f32)]) -> Option<&'a T> {
+        let total_weight: f32 = options.iter().map(|(_, weight)| weight).sum();
+        if options.is_empty() || total_weight <= 0.0 {
+            return None;
+        }
+        
+        let mut random_value = rng.gen_range(0.0..total_weight);
+        for (option, weight) in options {
+            if random_value < *weight {
+                return Some(option);
+            }
+            random_value -= weight;
+        }
+        options.last().map(|(option, _)| option)
+    }
+    
+    /// Whether the road holds as many cars as it may, counting those waiting to re-enter
+    /// a closed loop
+    fn at_car_limit(&self, state: &SimulationState) -> bool {
+        state.active_cars + self.recycling.len() as u32 >= self.cars_config.simulation.total_cars
+    }
+    
+    /// Admit the cars waiting to re-enter a closed loop at their entries, one per entry
+    /// each step. Cars whose entry has no room wait for the next step.
+    fn update_recycling(&mut self, state: &mut SimulationState) {
+        if self.recycling.is_empty() {
+            return;
+        }
+        let mut entries_used = HashSet::new();
+        for car in std::mem::take(&mut self.recycling) {
+            let Some(entry_id) = self.reentry(&car) else {
+                continue;
+            };
+            if !entries_used.insert(entry_id.clone()) || self.admit_car(&car, &entry_id, state).is_err() {
+                self.recycling.push(car);
+            }
+        }
+    }
+    
+    /// Entry a recycled car re-enters at: the one it first spawned at, or the route's
+    /// first entry for cars placed on the road
+    fn reentry(&self, car: &Car) -> Option<String> {
+        let entries = &self.route.route.entries;
+        entries.iter()
+            .find(|entry| entry.id == car.entry)
+            .or(entries.first())
+            .map(|entry| entry.id.clone())
+    }
+    
+    /// Queue car `id` to re-enter the road if it is leaving a closed loop. The driver comes
+    /// back in the same car, repaired and recharged, and keeps its id.
+    fn recycle(&mut self, id: CarId, state: &mut SimulationState) {
+        if !self.cars_config.simulation.closed_loop {
+            return;
+        }
+        if let Some(car) = state.get_car(id) {
+            let mut car = car.clone();
+            car.crashed = false;
+            car.breakdown = None;
+            car.last_collision_time = None;
+            car.last_conflict_time = None;
+            car.leader = None;
+            if let Some(battery) = &mut car.battery {
+                battery.charge = battery.capacity;
+                battery.low = false;
+            }
+            self.recycling.push(car);
+            state.hold_car(id);
+        }
+    }
+    
+    /// Exit of the lane a car drove off the end of the road in, for routes whose through
+    /// lanes end at the edge of the world
+    fn route_end_exit(&self, car: &Car) -> Option<&crate::config::ExitPoint> {
+        self.route.route.exits.iter()
+            .find(|exit| exit.exit_type == "through" && exit.lane == car.current_lane)
     }
     
     fn update_despawning(&mut self, state: &mut SimulationState) {
-        let mut cars_to_remove = Vec::new();
+        // Each car leaves once: by the exit it reached, or taken off the road (no exit)
+        let mut departures: Vec<(CarId, Option<String>)> = Vec::new();
+        let mut cars_leaving = Vec::new();
         
         for car in &state.cars {
-            // Check if car should exit at nearby exit points
-            if self.should_car_exit(car) {
-                cars_to_remove.push(car.id);
+            // Cars that drove off the end of the road leave by their lane's exit, or are taken off
+            if !self.bounds.contains(car.position.x, car.position.y) {
+                departures.push((car.id, self.route_end_exit(car).map(|exit| exit.id.clone())));
+                continue;
             }
             
-            // Remove cars that have been in simulation too long (prevent buildup)
-            if state.time > 600.0 { // 10 minutes
-                if self.rng.gen::<f32>() < 0.001 { // 0.1% chance per frame to despawn
-                    cars_to_remove.push(car.id);
+            let mut exit_id = None;
+            match &car.exit_ramp {
+                // Exiting cars leave the simulation at the end of their off-ramp
+                Some(ramp) if self.exit_ramps.get(&ramp.exit_id).is_none_or(|exit_ramp| ramp.distance >= exit_ramp.length()) => {
+                    exit_id = Some(ramp.exit_id.clone());
+                }
+                Some(_) => {}
+                // Check if car should exit at nearby exit points, broken-down cars cannot drive off
+                None if car.breakdown.is_none() => {
+                    if let Some(grid_path) = &car.grid_path {
+                        // Grid cars leave once they reach the exit cell at the end of their path
+                        if grid_path.is_complete() {
+                            exit_id = Some(grid_path.exit_id.clone());
+                        }
+                    } else if let Some(exit) = self.exit_reached(car) {
+                        // Turn onto the exit's off-ramp where there is one
+                        let ramp = self.exit_ramps.get(&exit.id)
+                            .and_then(|ramp| ramp.distance_past_start(car.position, EXIT_WINDOW).map(|distance| (ramp, distance)));
+                        match ramp {
+                            Some((ramp, distance)) => cars_leaving.push((car.id, RampPosition {
+                                exit_id: ramp.exit_id().to_string(),
+                                distance,
+                            })),
+                            None => exit_id = Some(exit.id.clone()),
+                        }
+                    }
                 }
+                None => {}
+            }
+            
+            // Cars on the shoulder, or out of charge, are towed away once their breakdown is over
+            let towed = (car.is_on_shoulder() || car.is_out_of_charge()) && car.breakdown.as_ref().is_some_and(|breakdown| state.time >= breakdown.end_time);
+            
+            // Remove cars that have been in simulation too long (prevent buildup), a
+            // closed loop keeps its fleet
+            let expired = state.time > 600.0 && !self.cars_config.simulation.closed_loop // 10 minutes
+                && self.despawn_rng.gen::<f32>() < 0.001; // 0.1% chance per frame to despawn
+            
+            if exit_id.is_some() || towed || expired {
+                departures.push((car.id, exit_id));
             }
         }
         
-        for car_id in cars_to_remove {
-            state.remove_car(car_id);
+        for (car_id, ramp) in cars_leaving {
+            if let Some(car) = state.get_car_mut(car_id) {
+                car.exit_ramp = Some(ramp);
+                car.target_lane = None;
+                car.lateral_velocity = 0.0;
+            }
+        }
+        
+        for (car_id, exit_id) in departures {
+            self.recycle(car_id, state);
+            if exit_id.as_ref().is_some_and(|exit_id| self.transfer_exits.contains(exit_id)) {
+                state.hold_car(car_id);
+            }
+            match exit_id {
+                Some(exit_id) => state.exit_car(car_id, &exit_id, self.route.route.traffic_rules.speed_limit),
+                None => state.remove_car(car_id),
+            }
         }
     }
     
-    fn should_car_exit(&self, car: &Car) -> bool {
+    /// Exit of a ring the car has just reached in the exit lane, if it leaves there. Exits
+    /// of other geometries are at the road's ends, where cars leave by `route_end_exit`.
+    fn exit_reached(&self, car: &Car) -> Option<&crate::config::ExitPoint> {
         let route_geom = &self.route.route.geometry;
+        if route_geom.geometry_type != "donut" {
+            return None;
+        }
         let center = Point2::new(route_geom.center_x, route_geom.center_y);
         let to_car = car.position - center;
+        let radius = to_car.magnitude().max(1.0);
         let car_angle = to_car.y.atan2(to_car.x).to_degrees();
         
         // Normalize angle to 0-360 range
@@ -446,33 +1198,49 @@ impl TrafficManager {
             car_angle
         };
         
-        for exit in &self.route.route.exits {
-            // Check if car is near an exit
-            let angle_diff = (exit.angle - car_angle).abs();
-            let angle_diff = if angle_diff > 180.0 {
-                360.0 - angle_diff
-            } else {
-                angle_diff
-            };
-            
-            // Car is near exit and in correct lane
-            if angle_diff < 5.0 && car.current_lane == exit.lane {
-                // Priority exit for cars marked for removal
-                if car.marked_for_exit {
-                    return true;
-                }
-                // Use behavior's exit probability for normal cars
-                return true; // For simplicity, always exit when near
-            }
+        // Buses only leave once they have served their last stop
+        let serving_stops = car.bus.as_ref().is_some_and(|bus| bus.next_stop < self.route.route.bus_stops.len());
+        if serving_stops && !car.marked_for_exit {
+            return None;
         }
         
-        false
+        self.route.route.exits.iter().find(|exit| {
+            // Routed cars only leave at their destination unless they were marked for removal
+            let wrong_exit = car.destination.as_ref().is_some_and(|destination| destination != &exit.id);
+            if wrong_exit && !car.marked_for_exit {
+                return false;
+            }
+            
+            // Car has just passed the exit in the exit lane
+            let angle_past = (car_angle - exit.angle).rem_euclid(360.0);
+            angle_past.to_radians() * radius < EXIT_WINDOW && car.current_lane == exit.lane
+        })
+    }
+    
+    /// Where cars entering at `entry` appear and the heading (radians) they start with
+    pub fn entry_pose(entry: &crate::config::EntryPoint, route_geom: &crate::config::RouteGeometry) -> (Point2<f32>, f32) {
+        let position = Self::calculate_entry_position(entry, route_geom);
+        let (_, heading) = Self::calculate_entry_velocity(entry, route_geom, &position);
+        (position, heading)
+    }
+    
+    /// Spawn point of an entry and the direction of the lane cars join there
+    fn calculate_entry_pose(entry: &crate::config::EntryPoint, route_geom: &crate::config::RouteGeometry) -> (Point2<f32>, Vector2<f32>) {
+        let position = Self::calculate_entry_position(entry, route_geom);
+        let (direction, _) = if route_geom.geometry_type == "cloverleaf" {
+            // Cars off a loop ramp join the highway lane at the merge point and follow it
+            Self::cloverleaf_lane_velocity(entry.lane)
+        } else {
+            Self::calculate_entry_velocity(entry, route_geom, &position)
+        };
+        (position, direction)
     }
     
     fn calculate_entry_position(entry: &crate::config::EntryPoint, route_geom: &crate::config::RouteGeometry) -> Point2<f32> {
         match route_geom.geometry_type.as_str() {
             "cloverleaf" => Self::calculate_cloverleaf_entry_position(entry, route_geom),
             "donut" => Self::calculate_donut_entry_position(entry, route_geom),
+            "grid" => Self::calculate_grid_entry_position(entry, route_geom),
             _ => {
                 log::warn!("Unknown geometry type '{}', using donut spawn logic", route_geom.geometry_type);
                 Self::calculate_donut_entry_position(entry, route_geom)
@@ -490,13 +1258,33 @@ impl TrafficManager {
         )
     }
     
+    fn calculate_grid_entry_position(entry: &crate::config::EntryPoint, route_geom: &crate::config::RouteGeometry) -> Point2<f32> {
+        match grid_spawn_for_entry(route_geom, entry) {
+            Some(spawn) => grid_cell_center(route_geom, spawn.row, spawn.col),
+            None => {
+                log::warn!("No grid spawn point matches entry {}, spawning at center", entry.id);
+                Point2::new(route_geom.center_x, route_geom.center_y)
+            }
+        }
+    }
+    
     fn calculate_cloverleaf_entry_position(entry: &crate::config::EntryPoint, route_geom: &crate::config::RouteGeometry) -> Point2<f32> {
-        // Check if this is a loop ramp entry based on entry type
+        // Loop ramp cars join their merge lane level with the end of the loop ramp, where
+        // the lane keeping puts them, so the spawn checks look where they really are
         if entry.entry_type == "loop_ramp" {
-            // Spawn cars on entrance ramps (loop ramps) for realistic cloverleaf behavior
-            return Self::calculate_loop_ramp_entry_position(entry, route_geom);
+            let ramp = Self::calculate_loop_ramp_entry_position(entry, route_geom);
+            let lane = Self::cloverleaf_lane_start(entry.lane, route_geom);
+            return match entry.lane {
+                1..=6 => Point2::new(lane.x, ramp.y),
+                _ => Point2::new(ramp.x, lane.y),
+            };
         }
         
+        Self::cloverleaf_lane_start(entry.lane, route_geom)
+    }
+    
+    /// Where a cloverleaf through lane starts at the edge of the highway
+    fn cloverleaf_lane_start(lane: u32, route_geom: &crate::config::RouteGeometry) -> Point2<f32> {
         // For through traffic, spawn at highway edges with proper right-side driving
         // Right-side driving lane assignments:
         // North-South Highway:
@@ -506,39 +1294,39 @@ impl TrafficManager {
         //   Lanes 7-9:  Westbound on NORTH side - spawn at east edge
         //   Lanes 10-12: Eastbound on SOUTH side - spawn at west edge
         
-        let highway_extent = 250.0; // How far from center to spawn
+        let highway_extent = CLOVERLEAF_HIGHWAY_EXTENT; // How far from center to spawn
         let lane_width = route_geom.lane_width;
         let highway_half_width = route_geom.highway_width.unwrap_or(40.0) / 2.0;
         let lane_separation = highway_half_width + 5.0; // Same separation as physics
         
-        match entry.lane {
+        match lane {
             // North-South Southbound (lanes 1-3) - spawn at north edge, west side
             1..=3 => {
-                let lane_offset = ((entry.lane as i32) - 2) as f32 * lane_width; // -3.5, 0, 3.5
+                let lane_offset = ((lane as i32) - 2) as f32 * lane_width; // -3.5, 0, 3.5
                 let x_pos = -lane_separation + lane_offset; // West side
                 Point2::new(x_pos, highway_extent) // North edge
             }
             // North-South Northbound (lanes 4-6) - spawn at south edge, east side
             4..=6 => {
-                let lane_offset = ((entry.lane as i32) - 5) as f32 * lane_width; // -3.5, 0, 3.5
+                let lane_offset = ((lane as i32) - 5) as f32 * lane_width; // -3.5, 0, 3.5
                 let x_pos = lane_separation + lane_offset; // East side
                 Point2::new(x_pos, -highway_extent) // South edge
             }
             // East-West Westbound (lanes 7-9) - spawn at east edge, north side
             7..=9 => {
-                let lane_offset = ((entry.lane as i32) - 8) as f32 * lane_width; // -3.5, 0, 3.5
+                let lane_offset = ((lane as i32) - 8) as f32 * lane_width; // -3.5, 0, 3.5
                 let y_pos = lane_separation + lane_offset; // North side
                 Point2::new(highway_extent, y_pos) // East edge
             }
             // East-West Eastbound (lanes 10-12) - spawn at west edge, south side
             10..=12 => {
-                let lane_offset = ((entry.lane as i32) - 11) as f32 * lane_width; // -3.5, 0, 3.5
+                let lane_offset = ((lane as i32) - 11) as f32 * lane_width; // -3.5, 0, 3.5
                 let y_pos = -lane_separation + lane_offset; // South side
                 Point2::new(-highway_extent, y_pos) // West edge
             }
             // Invalid lane - spawn at center
             _ => {
-                log::warn!("Invalid lane {} for cloverleaf, spawning at center", entry.lane);
+                log::warn!("Invalid lane {} for cloverleaf, spawning at center", lane);
                 Point2::new(0.0, 0.0)
             }
         }
@@ -586,6 +1374,7 @@ impl TrafficManager {
         match route_geom.geometry_type.as_str() {
             "cloverleaf" => Self::calculate_cloverleaf_entry_velocity(entry),
             "donut" => Self::calculate_donut_entry_velocity(entry),
+            "grid" => Self::calculate_grid_entry_velocity(entry, route_geom),
             _ => {
                 log::warn!("Unknown geometry type '{}', using donut velocity logic", route_geom.geometry_type);
                 Self::calculate_donut_entry_velocity(entry)
@@ -604,14 +1393,27 @@ impl TrafficManager {
         (velocity, tangent_angle)
     }
     
+    fn calculate_grid_entry_velocity(entry: &crate::config::EntryPoint, route_geom: &crate::config::RouteGeometry) -> (Vector2<f32>, f32) {
+        // Head towards the first road cell next to the spawn cell
+        let heading = grid_spawn_for_entry(route_geom, entry)
+            .and_then(|spawn| grid_spawn_heading(route_geom, spawn))
+            .unwrap_or(entry.angle)
+            .to_radians();
+        (Vector2::new(heading.cos(), heading.sin()), heading)
+    }
+    
     fn calculate_cloverleaf_entry_velocity(entry: &crate::config::EntryPoint) -> (Vector2<f32>, f32) {
         // Check if this is a loop ramp entry - cars start on ramps heading toward merge point
         if entry.entry_type == "loop_ramp" {
             return Self::calculate_loop_ramp_entry_velocity(entry);
         }
         
-        // For through traffic, calculate velocity based on lane assignments
-        match entry.lane {
+        Self::cloverleaf_lane_velocity(entry.lane)
+    }
+    
+    /// Direction of travel and heading along a cloverleaf through lane
+    fn cloverleaf_lane_velocity(lane: u32) -> (Vector2<f32>, f32) {
+        match lane {
             // North-South Southbound (lanes 1-3) - heading south  
             1..=3 => (Vector2::new(0.0, -1.0), -std::f32::consts::PI / 2.0),
             // North-South Northbound (lanes 4-6) - heading north
@@ -622,7 +1424,7 @@ impl TrafficManager {
             10..=12 => (Vector2::new(1.0, 0.0), 0.0),
             // Invalid lane - default east
             _ => {
-                log::warn!("Invalid lane {} for cloverleaf velocity, defaulting to east", entry.lane);
+                log::warn!("Invalid lane {} for cloverleaf velocity, defaulting to east", lane);
                 (Vector2::new(1.0, 0.0), 0.0)
             }
         }
@@ -0,0 +1,143 @@
+use super::{Car, CarId, NameTable, SimulationState};
+use crate::config::RouteGeometry;
+use serde::{Serialize, Serializer};
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+/// Where one car was at one moment, in the style of an NGSIM trajectory record
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TrajectorySample {
+    #[serde(serialize_with = "car_label")]
+    pub car: CarId, // written as the id is shown
+    pub frame: u64, // sample number, time divided by the sample interval
+    pub time: f32, // seconds
+    pub position: f32, // meters along the road, see `road_position`
+    pub lane: u32,
+    pub x: f32,
+    pub y: f32,
+    pub speed: f32, // m/s
+    pub acceleration: f32, // m/s², along the heading
+    pub length: f32,
+    pub width: f32,
+    pub car_type: String,
+}
+
+impl TrajectorySample {
+    pub fn of(car: &Car, names: &NameTable, time: f32, frame: u64, geometry: &RouteGeometry) -> Self {
+        let heading = nalgebra::Vector2::new(car.heading.cos(), car.heading.sin());
+        Self {
+            car: car.id,
+            frame,
+            time,
+            position: road_position(car, geometry),
+            lane: car.current_lane,
+            x: car.position.x,
+            y: car.position.y,
+            speed: car.velocity.magnitude(),
+            acceleration: car.acceleration.dot(&heading),
+            length: car.length,
+            width: car.width,
+            car_type: names.car_type(car.car_type).to_string(),
+        }
+    }
+}
+
+/// Trajectory files name a car as `CarId` displays it
+fn car_label<S: Serializer>(id: &CarId, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(id)
+}
+
+/// Arc-length coordinate of a car. On a donut it is the distance counter-clockwise from
+/// the positive x axis along the middle of the road, wrapping at `road_length`. Other
+/// geometries have no single corridor, so it is the distance the car drove since spawning.
+pub fn road_position(car: &Car, geometry: &RouteGeometry) -> f32 {
+    if geometry.geometry_type != "donut" {
+        return car.distance_traveled;
+    }
+    let angle = (car.position.y - geometry.center_y).atan2(car.position.x - geometry.center_x);
+    angle.rem_euclid(2.0 * PI) * middle_radius(geometry)
+}
+
+/// Length of the road the positions wrap around at, `None` unless it is a ring
+pub fn road_length(geometry: &RouteGeometry) -> Option<f32> {
+    (geometry.geometry_type == "donut").then(|| 2.0 * PI * middle_radius(geometry))
+}
+
+fn middle_radius(geometry: &RouteGeometry) -> f32 {
+    geometry.inner_radius + geometry.lane_width * geometry.lane_count as f32 / 2.0
+}
+
+/// Recent trajectories of every car, sampled at a fixed interval of simulation time.
+/// Memory stays bounded: samples older than the window are dropped, and so are the
+/// oldest ones whenever more than `max_samples` are held.
+pub struct TrajectoryBuffer {
+    geometry: RouteGeometry,
+    interval: f32, // seconds between samples
+    window: f32, // seconds of history kept
+    max_samples: usize,
+    samples: VecDeque<TrajectorySample>,
+    last_time: f32,
+    next_sample: f32,
+}
+
+impl TrajectoryBuffer {
+    pub fn new(geometry: &RouteGeometry, interval: f32, window: f32, max_samples: usize) -> Self {
+        Self {
+            geometry: geometry.clone(),
+            interval,
+            window,
+            max_samples,
+            samples: VecDeque::new(),
+            last_time: 0.0,
+            next_sample: 0.0,
+        }
+    }
+    
+    /// Sample every car if an interval has passed since the last sample. Returns how many
+    /// samples were added.
+    pub fn record(&mut self, state: &SimulationState) -> usize {
+        // Time going backwards means the simulation was reset or a checkpoint was loaded
+        if state.time < self.last_time {
+            self.clear();
+        }
+        self.last_time = state.time;
+        
+        if state.time < self.next_sample {
+            return 0;
+        }
+        let frame = (state.time / self.interval).round() as u64;
+        self.next_sample = (frame + 1) as f32 * self.interval;
+        
+        self.samples.extend(state.cars.iter().map(|car| TrajectorySample::of(car, &state.names, state.time, frame, &self.geometry)));
+        while self.samples.front().is_some_and(|sample| sample.time < state.time - self.window) {
+            self.samples.pop_front();
+        }
+        let excess = self.samples.len().saturating_sub(self.max_samples);
+        self.samples.drain(..excess);
+        state.cars.len()
+    }
+    
+    /// Samples held, oldest first
+    pub fn samples(&self) -> &VecDeque<TrajectorySample> {
+        &self.samples
+    }
+    
+    /// Follow the geometry of a changed route from now on
+    pub fn set_geometry(&mut self, geometry: &RouteGeometry) {
+        self.geometry = geometry.clone();
+    }
+    
+    pub fn geometry(&self) -> &RouteGeometry {
+        &self.geometry
+    }
+    
+    pub fn window(&self) -> f32 {
+        self.window
+    }
+    
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.last_time = 0.0;
+        self.next_sample = 0.0;
+    }
+}
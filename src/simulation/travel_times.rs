@@ -0,0 +1,218 @@
+use super::{AppendLog, CarId, SimulationState, StopLine};
+use crate::config::RouteConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+const MAX_STEP: f32 = 10.0; // meters a car can move between updates; larger jumps are the ring wrapping
+
+/// One car's drive over a travel time segment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Traversal {
+    pub segment: String,
+    pub car: CarId,
+    pub start_time: f32, // crossed the segment's first waypoint
+    pub end_time: f32,   // crossed its second waypoint
+    pub travel_time: f32, // seconds
+}
+
+/// How dependable the travel time over a segment is, in seconds. The buffer index is the
+/// share of the mean a driver has to add to arrive on time in 95% of trips.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TravelTimeReliability {
+    pub count: u32,
+    pub mean: f32,
+    pub median: f32,
+    pub p95: f32,
+    pub buffer_index: f32, // (p95 - mean) / mean
+}
+
+impl TravelTimeReliability {
+    /// Statistics of `times`, `None` if there are none
+    pub fn from_times(times: &[f32]) -> Option<Self> {
+        if times.is_empty() {
+            return None;
+        }
+        let mut sorted = times.to_vec();
+        sorted.sort_by(f32::total_cmp);
+        let count = sorted.len();
+        let mean = sorted.iter().sum::<f32>() / count as f32;
+        let median = if count.is_multiple_of(2) { (sorted[count / 2 - 1] + sorted[count / 2]) / 2.0 } else { sorted[count / 2] };
+        // Nearest-rank percentile
+        let rank = ((count as f32 * 0.95).ceil() as usize).clamp(1, count);
+        let p95 = sorted[rank - 1];
+        Some(Self {
+            count: count as u32,
+            mean,
+            median,
+            p95,
+            buffer_index: if mean > 0.0 { (p95 - mean) / mean } else { 0.0 },
+        })
+    }
+}
+
+/// Traversals of the route's travel time segments since the simulation started
+#[derive(Debug, Default)]
+pub struct TravelTimeLog {
+    traversals: AppendLog<Traversal>,
+}
+
+impl Clone for TravelTimeLog {
+    fn clone(&self) -> Self {
+        Self { traversals: self.traversals.clone() }
+    }
+    
+    fn clone_from(&mut self, source: &Self) {
+        self.traversals.clone_from(&source.traversals);
+    }
+}
+
+impl TravelTimeLog {
+    pub fn record(&mut self, traversal: Traversal) {
+        self.traversals.push(traversal);
+    }
+    
+    /// Completed traversals, oldest first
+    pub fn traversals(&self) -> &[Traversal] {
+        &self.traversals
+    }
+    
+    pub fn len(&self) -> usize {
+        self.traversals.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.traversals.is_empty()
+    }
+    
+    /// Reliability of `segment` over the traversals that ended at or after `since`
+    pub fn reliability(&self, segment: &str, since: f32) -> Option<TravelTimeReliability> {
+        let times: Vec<f32> = self.traversals.iter()
+            .filter(|traversal| traversal.segment == segment && traversal.end_time >= since)
+            .map(|traversal| traversal.travel_time)
+            .collect();
+        TravelTimeReliability::from_times(&times)
+    }
+    
+    /// Reliability of every segment traversed at or after `since`, by segment id
+    pub fn reliability_by_segment(&self, since: f32) -> BTreeMap<String, TravelTimeReliability> {
+        let mut times: BTreeMap<String, Vec<f32>> = BTreeMap::new();
+        for traversal in self.traversals.iter().filter(|traversal| traversal.end_time >= since) {
+            times.entry(traversal.segment.clone()).or_default().push(traversal.travel_time);
+        }
+        times.into_iter()
+            .filter_map(|(segment, times)| Some((segment, TravelTimeReliability::from_times(&times)?)))
+            .collect()
+    }
+}
+
+/// Line across the road a waypoint marks, with the cars' signed distances to it as of the
+/// last update
+struct WaypointLine {
+    stop_line: StopLine,
+    heading: f32, // radians, direction of travel of timed traffic
+    lanes: Vec<u32>, // timed lanes (empty = all lanes)
+    last_offsets: HashMap<CarId, f32>,
+}
+
+/// Times cars between the route's waypoints, crossing their lines the way a loop detector
+/// counts them, and logs every traversal of a travel time segment in
+/// `SimulationState::travel_times`
+pub struct TravelTimeMonitor {
+    waypoints: Vec<WaypointLine>,
+    segments: Vec<(String, usize, usize)>, // id and the indices of its first and second waypoint
+    started: HashMap<(CarId, usize), f32>, // when each car crossed the first waypoint of a segment it is on
+    last_time: Option<f32>,
+}
+
+impl TravelTimeMonitor {
+    pub fn new(route: &RouteConfig) -> Self {
+        let waypoints = &route.route.waypoints;
+        let index_of = |id: &str| waypoints.iter().position(|waypoint| waypoint.id == id);
+        Self {
+            waypoints: waypoints.iter().map(|waypoint| {
+                let (stop_line, _, heading) = StopLine::for_head(&waypoint.placement(), &route.route.geometry);
+                WaypointLine {
+                    stop_line,
+                    heading,
+                    lanes: waypoint.lanes.clone(),
+                    last_offsets: HashMap::new(),
+                }
+            }).collect(),
+            segments: route.route.travel_times.iter()
+                .filter_map(|segment| Some((segment.id.clone(), index_of(&segment.from)?, index_of(&segment.to)?)))
+                .collect(),
+            started: HashMap::new(),
+            last_time: None,
+        }
+    }
+    
+    pub fn update(&mut self, state: &mut SimulationState) {
+        if self.segments.is_empty() {
+            return;
+        }
+        // Time going backwards means the simulation was reset or a checkpoint was loaded
+        if self.last_time.is_some_and(|last| state.time < last) {
+            self.reset();
+        }
+        self.last_time = Some(state.time);
+        
+        // Cars whose center moved from before a waypoint's line to on or past it
+        let mut crossings = Vec::new();
+        for (index, waypoint) in self.waypoints.iter_mut().enumerate() {
+            let mut offsets = HashMap::with_capacity(waypoint.last_offsets.len());
+            for car in &state.cars {
+                if !waypoint.lanes.is_empty() && !waypoint.lanes.contains(&car.current_lane) {
+                    continue;
+                }
+                let Some(offset) = waypoint.stop_line.signed_distance(waypoint.heading, car) else {
+                    continue;
+                };
+                let crossed = waypoint.last_offsets.get(&car.id)
+                    .is_some_and(|&last| last > 0.0 && offset <= 0.0 && last - offset < MAX_STEP);
+                if crossed {
+                    crossings.push((car.id, index));
+                }
+                offsets.insert(car.id, offset);
+            }
+            waypoint.last_offsets = offsets;
+        }
+        
+        // Segments end before others start, so a car is timed over segments back to back
+        for (car, waypoint) in crossings {
+            for (segment, (id, _, to)) in self.segments.iter().enumerate() {
+                if *to != waypoint {
+                    continue;
+                }
+                if let Some(start_time) = self.started.remove(&(car, segment)) {
+                    state.travel_times.record(Traversal {
+                        segment: id.clone(),
+                        car,
+                        start_time,
+                        end_time: state.time,
+                        travel_time: state.time - start_time,
+                    });
+                }
+            }
+            for (segment, (_, from, _)) in self.segments.iter().enumerate() {
+                if *from == waypoint {
+                    self.started.insert((car, segment), state.time);
+                }
+            }
+        }
+        
+        // Cars that left the road mid-segment are no longer timed
+        if self.started.len() > state.cars.len() * self.segments.len() + 64 {
+            let present: HashSet<CarId> = state.cars.iter().map(|car| car.id).collect();
+            self.started.retain(|(car, _), _| present.contains(car));
+        }
+    }
+    
+    /// Forget the cars being timed
+    pub fn reset(&mut self) {
+        for waypoint in &mut self.waypoints {
+            waypoint.last_offsets.clear();
+        }
+        self.started.clear();
+        self.last_time = None;
+    }
+}
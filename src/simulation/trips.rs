@@ -0,0 +1,88 @@
+use super::{AppendLog, Car, CarId, NameTable};
+use serde::{Deserialize, Serialize};
+
+/// Journey of one car from its entry to the exit it left by
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Trip {
+    pub car: CarId,
+    pub car_type: String,
+    pub behavior: String,
+    pub entry: String,
+    pub exit: String,
+    pub spawn_time: f32,
+    pub exit_time: f32,
+    pub distance: f32, // meters driven
+    pub travel_time: f32, // seconds
+    pub free_flow_time: f32, // seconds to drive the same distance at the free-flow speed
+    pub delay: f32, // travel time beyond the free-flow time, never negative
+}
+
+impl Trip {
+    /// Trip of `car`, whose ids are of `names`, ending at `exit` at `time`. The free-flow
+    /// speed is the car's preferred speed capped at `speed_limit`.
+    pub fn completed(car: &Car, names: &NameTable, exit: &str, time: f32, speed_limit: f32) -> Self {
+        let free_flow_speed = car.preferred_speed.min(speed_limit);
+        let travel_time = time - car.spawn_time;
+        let free_flow_time = if free_flow_speed > 0.0 { car.distance_traveled / free_flow_speed } else { 0.0 };
+        Self {
+            car: car.id,
+            car_type: names.car_type(car.car_type).to_string(),
+            behavior: names.behavior(car.behavior_type).to_string(),
+            entry: car.entry.clone(),
+            exit: exit.to_string(),
+            spawn_time: car.spawn_time,
+            exit_time: time,
+            distance: car.distance_traveled,
+            travel_time,
+            free_flow_time,
+            delay: (travel_time - free_flow_time).max(0.0),
+        }
+    }
+}
+
+/// Every trip completed since the simulation started, kept after the cars are gone
+#[derive(Debug, Default)]
+pub struct TripLog {
+    trips: AppendLog<Trip>,
+}
+
+impl Clone for TripLog {
+    fn clone(&self) -> Self {
+        Self { trips: self.trips.clone() }
+    }
+    
+    fn clone_from(&mut self, source: &Self) {
+        self.trips.clone_from(&source.trips);
+    }
+}
+
+impl TripLog {
+    pub fn record(&mut self, trip: Trip) {
+        self.trips.push(trip);
+    }
+    
+    /// Completed trips, oldest first
+    pub fn trips(&self) -> &[Trip] {
+        &self.trips
+    }
+    
+    pub fn len(&self) -> usize {
+        self.trips.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.trips.is_empty()
+    }
+    
+    /// Mean delay of the trips that ended at or after `since`, `None` if there are none
+    pub fn mean_delay(&self, since: f32) -> Option<f32> {
+        let delays: Vec<f32> = self.trips.iter()
+            .filter(|trip| trip.exit_time >= since)
+            .map(|trip| trip.delay)
+            .collect();
+        if delays.is_empty() {
+            return None;
+        }
+        Some(delays.iter().sum::<f32>() / delays.len() as f32)
+    }
+}
@@ -0,0 +1,117 @@
+use super::{Car, SimulationState, GRAVITY};
+use crate::config::{RoadSurface, RouteConfig};
+use serde::{Deserialize, Serialize};
+
+/// Road and visibility conditions that degrade braking, spacing and speed choice
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Weather {
+    #[default]
+    Dry,
+    Wet,
+    Ice,
+    Fog,
+}
+
+impl Weather {
+    /// Condition named in the route config (validated to be one of these)
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "wet" => Weather::Wet,
+            "ice" => Weather::Ice,
+            "fog" => Weather::Fog,
+            _ => Weather::Dry,
+        }
+    }
+    
+    pub fn name(self) -> &'static str {
+        match self {
+            Weather::Dry => "dry",
+            Weather::Wet => "wet",
+            Weather::Ice => "ice",
+            Weather::Fog => "fog",
+        }
+    }
+    
+    /// Share of the dry road's grip left for braking
+    pub fn friction_factor(self) -> f32 {
+        match self {
+            Weather::Dry | Weather::Fog => 1.0,
+            Weather::Wet => 0.7,
+            Weather::Ice => 0.2,
+        }
+    }
+    
+    /// Multiplier on the time gap drivers keep to the car ahead
+    pub fn headway_factor(self) -> f32 {
+        match self {
+            Weather::Dry => 1.0,
+            Weather::Wet | Weather::Fog => 1.5,
+            Weather::Ice => 2.5,
+        }
+    }
+    
+    /// Multiplier on the speed drivers choose
+    pub fn speed_factor(self) -> f32 {
+        match self {
+            Weather::Dry => 1.0,
+            Weather::Wet => 0.9,
+            Weather::Fog => 0.8,
+            Weather::Ice => 0.6,
+        }
+    }
+    
+    /// Meters drivers can see ahead, `None` when sight does not limit speed
+    pub fn visibility(self) -> Option<f32> {
+        match self {
+            Weather::Fog => Some(60.0),
+            _ => None,
+        }
+    }
+    
+    /// Hardest the car can brake: limited by its brakes or by the tires' grip on the surface
+    pub fn braking_limit(self, car: &Car, surface: &RoadSurface) -> f32 {
+        car.max_deceleration.min(surface.friction_coefficient * self.friction_factor() * GRAVITY)
+    }
+    
+    /// Highest speed from which a car braking comfortably, at half of `braking`, still stops
+    /// within sight
+    pub fn sight_speed(self, braking: f32) -> Option<f32> {
+        self.visibility().map(|visibility| (braking * visibility).sqrt())
+    }
+}
+
+/// Switches the weather as scheduled by the route. Like signal phases, the weather is a pure
+/// function of simulation time.
+pub struct WeatherController {
+    initial: Weather,
+    schedule: Vec<(f32, Weather)>, // (start time, weather), sorted by time
+}
+
+impl WeatherController {
+    pub fn new(route: &RouteConfig) -> Self {
+        let weather = &route.route.weather;
+        Self {
+            initial: Weather::from_name(&weather.condition),
+            schedule: weather.schedule.iter()
+                .map(|change| (change.time, Weather::from_name(&change.condition)))
+                .collect(),
+        }
+    }
+    
+    pub fn weather_at(&self, time: f32) -> Weather {
+        self.schedule.iter()
+            .rev()
+            .find(|(start, _)| *start <= time)
+            .map(|&(_, weather)| weather)
+            .unwrap_or(self.initial)
+    }
+    
+    /// Publish the current weather into the simulation state
+    pub fn update(&self, state: &mut SimulationState) {
+        let weather = self.weather_at(state.time);
+        if weather != state.weather {
+            log::info!("Weather changed to {} at {:.1}s", weather.name(), state.time);
+            state.weather = weather;
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use crate::config::{read_config_file, ConfigOverride, SimulationConfig};
+use crate::compute::{ComputeBackend, SimulationBackend};
+use crate::export::{create_export_writer, write_csv_row};
+use crate::simulation::SimulationState;
+use anyhow::{Result, anyhow, bail};
+use rand::Rng;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+
+pub mod replications;
+
+pub use replications::*;
+
+/// Experiment definition read from a sweep file. Every combination of parameter values,
+/// variants and seeds is one headless run.
+///
+/// ```toml
+/// route = "route.toml"
+/// cars = "cars.toml"
+/// duration = 600.0
+/// seeds = [1, 2, 3]
+///
+/// [parameters]
+/// "cars.simulation.spawn_rate" = [1.0, 2.0, 4.0]
+///
+/// [[variants]]
+/// name = "calm"
+/// set = { "cars.behavior.aggressive.weight" = 5, "cars.behavior.cautious.weight" = 30 }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct SweepConfig {
+    #[serde(default = "default_route")]
+    pub route: String, // relative to the sweep file
+    #[serde(default = "default_cars")]
+    pub cars: String,
+    #[serde(default)]
+    pub duration: Option<f32>, // simulated seconds per run, default simulation_duration from the cars file
+    #[serde(default)]
+    pub seeds: Vec<u64>, // default one random seed
+    #[serde(default)]
+    pub parameters: BTreeMap<String, Vec<toml::Value>>, // override key -> values to try
+    #[serde(default)]
+    pub variants: Vec<SweepVariant>,
+}
+
+/// Overrides that only make sense together, such as behavior weights that must still
+/// sum to 100, tried as one more dimension of the sweep
+#[derive(Debug, Clone, Deserialize)]
+pub struct SweepVariant {
+    pub name: String,
+    #[serde(default)]
+    pub set: BTreeMap<String, toml::Value>,
+}
+
+fn default_route() -> String {
+    "route.toml".to_string()
+}
+
+fn default_cars() -> String {
+    "cars.toml".to_string()
+}
+
+/// One combination of the sweep, with its configuration loaded and validated
+#[derive(Debug, Clone)]
+pub struct SweepRun {
+    pub index: usize,
+    pub parameters: Vec<ConfigOverride>,
+    pub variant: Option<String>,
+    pub seed: u64,
+    pub config: SimulationConfig,
+}
+
+/// Outcome of one run, one row of the results file
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunResult {
+    pub spawned: u32,
+    pub exited: u32,
+    pub throughput: f32, // exited cars per hour
+    pub mean_delay: f32, // seconds, mean trip delay of the cars that exited
+    pub mean_speed: f32, // m/s, over all cars and ticks
+    pub collisions: u32,
+}
+
+impl SweepConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read sweep file {}: {}", path.display(), e))?;
+        let sweep: SweepConfig = toml::from_str(&content)?;
+        if sweep.parameters.values().any(Vec::is_empty) {
+            bail!("Every swept parameter needs at least one value");
+        }
+        if let Some(duration) = sweep.duration {
+            if duration <= 0.0 {
+                bail!("Sweep duration must be positive, got {}", duration);
+            }
+        }
+        Ok(sweep)
+    }
+    
+    /// Every combination to run, in order. Configuration files are read relative to
+    /// `directory`, and each combination is validated before anything runs.
+    pub fn runs(&self, directory: &Path) -> Result<Vec<SweepRun>> {
+        let route_content = read_config_file(directory.join(&self.route))?;
+        let cars_content = read_config_file(directory.join(&self.cars))?;
+        
+        // Cartesian product of the parameter values, the first key in sorted order varying slowest
+        let mut combinations: Vec<Vec<ConfigOverride>> = vec![Vec::new()];
+        for (key, values) in &self.parameters {
+            let mut expanded = Vec::with_capacity(combinations.len() * values.len());
+            for combination in &combinations {
+                for value in values {
+                    let mut combination = combination.clone();
+                    combination.push(ConfigOverride::new(key, value.clone())?);
+                    expanded.push(combination);
+                }
+            }
+            combinations = expanded;
+        }
+        let variants: Vec<Option<&SweepVariant>> = if self.variants.is_empty() {
+            vec![None]
+        } else {
+            self.variants.iter().map(Some).collect()
+        };
+        let seeds = if self.seeds.is_empty() {
+            vec![rand::thread_rng().gen()]
+        } else {
+            self.seeds.clone()
+        };
+        
+        let mut runs = Vec::new();
+        for parameters in &combinations {
+            for variant in &variants {
+                let mut overrides = parameters.clone();
+                if let Some(variant) = variant {
+                    for (key, value) in &variant.set {
+                        overrides.push(ConfigOverride::new(key, value.clone())?);
+                    }
+                }
+                let config = SimulationConfig::load_from_strs_with_overrides(&route_content, &cars_content, &overrides)
+                    .map_err(|e| anyhow!("Sweep run {} ({}) is invalid: {}", runs.len() + 1, describe(parameters, *variant), e))?;
+                for &seed in &seeds {
+                    runs.push(SweepRun {
+                        index: runs.len(),
+                        parameters: parameters.clone(),
+                        variant: variant.map(|variant| variant.name.clone()),
+                        seed,
+                        config: config.clone(),
+                    });
+                }
+            }
+        }
+        Ok(runs)
+    }
+}
+
+fn describe(parameters: &[ConfigOverride], variant: Option<&SweepVariant>) -> String {
+    let mut parts: Vec<String> = parameters.iter()
+        .map(|parameter| format!("{}={}", parameter.key, parameter.value))
+        .collect();
+    if let Some(variant) = variant {
+        parts.push(format!("variant {}", variant.name));
+    }
+    parts.join(", ")
+}
+
+impl SweepRun {
+    /// Simulate the run on the CPU backend for `duration` seconds in steps of `dt`
+    pub fn execute(&self, duration: f32, dt: f32) -> Result<RunResult> {
+        let mut backend = ComputeBackend::new_cpu(self.config.cars.clone(), self.config.route.clone(), Some(self.seed));
+        let mut state = SimulationState::new(dt);
+        let steps = (duration / dt).ceil() as u64;
+        
+        let mut speed_sum = 0.0f64;
+        let mut speed_samples = 0u64;
+        
+        for _ in 0..steps {
+            backend.update(&mut state)?;
+            state.update_car_speeds();
+            state.active_cars = state.cars.len() as u32;
+            for car in &state.cars {
+                speed_sum += car.velocity.magnitude() as f64;
+                speed_samples += 1;
+            }
+        }
+        
+        let exited = state.trips.len() as u32;
+        
+        Ok(RunResult {
+            spawned: state.total_spawned,
+            exited,
+            throughput: exited as f32 / state.time.max(f32::EPSILON) * 3600.0,
+            mean_delay: state.trips.mean_delay(0.0).unwrap_or(0.0),
+            mean_speed: if speed_samples > 0 { (speed_sum / speed_samples as f64) as f32 } else { 0.0 },
+            collisions: state.total_collisions,
+        })
+    }
+}
+
+/// Run every combination of `sweep` on `jobs` threads and write one CSV row per run to
+/// `output` as runs finish. Rows carry the run number, so they can be sorted back into order.
+pub fn run_sweep(sweep: &SweepConfig, directory: &Path, output: &Path, jobs: usize, dt: f32) -> Result<Vec<(SweepRun, RunResult)>> {
+    let runs = sweep.runs(directory)?;
+    let keys: Vec<String> = sweep.parameters.keys().cloned().collect();
+    let mut writer = create_export_writer(output)?;
+    let mut header = vec!["run".to_string()];
+    header.extend(keys.iter().cloned());
+    if !sweep.variants.is_empty() {
+        header.push("variant".to_string());
+    }
+    header.extend(["seed", "duration", "spawned", "exited", "throughput_per_hour", "mean_delay", "mean_speed", "collisions"]
+        .iter()
+        .map(|s| s.to_string()));
+    write_csv_row(&mut writer, &header)?;
+    writer.flush()?;
+    
+    let next = AtomicUsize::new(0);
+    let (sender, results) = mpsc::channel();
+    let mut finished: Vec<(SweepRun, RunResult)> = Vec::with_capacity(runs.len());
+    let outcome = std::thread::scope(|scope| -> Result<()> {
+        for _ in 0..jobs.clamp(1, runs.len().max(1)) {
+            let sender = sender.clone();
+            let (runs, next) = (&runs, &next);
+            scope.spawn(move || {
+                while let Some(run) = runs.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let duration = sweep.duration.unwrap_or(run.config.cars.simulation.simulation_duration);
+                    let result = run.execute(duration, dt);
+                    if sender.send((run.index, duration, result)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+        
+        for (index, duration, result) in results {
+            let run = &runs[index];
+            let result = result.map_err(|e| {
+                // Stop handing out runs, the sweep has failed
+                next.store(runs.len(), Ordering::Relaxed);
+                anyhow!("Sweep run {} failed: {}", index + 1, e)
+            })?;
+            let mut row = vec![(index + 1).to_string()];
+            row.extend(run.parameters.iter().map(|parameter| csv_value(&parameter.value)));
+            if let Some(variant) = &run.variant {
+                row.push(variant.clone());
+            }
+            row.extend([
+                run.seed.to_string(),
+                format!("{:.1}", duration),
+                result.spawned.to_string(),
+                result.exited.to_string(),
+                format!("{:.1}", result.throughput),
+                format!("{:.3}", result.mean_delay),
+                format!("{:.3}", result.mean_speed),
+                result.collisions.to_string(),
+            ]);
+            write_csv_row(&mut writer, &row)?;
+            writer.flush()?;
+            log::info!("Sweep run {}/{} done: {} exited, {} collisions", finished.len() + 1, runs.len(), result.exited, result.collisions);
+            finished.push((run.clone(), result));
+        }
+        Ok(())
+    });
+    outcome?;
+    
+    finished.sort_by_key(|(run, _)| run.index);
+    Ok(finished)
+}
+
+/// Value as written in a results cell, strings without their TOML quotes
+fn csv_value(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Default results file, next to the sweep file with a `.csv` extension
+pub fn default_output(sweep_file: &Path) -> PathBuf {
+    sweep_file.with_extension("csv")
+}
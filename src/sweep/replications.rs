@@ -0,0 +1,214 @@
+use crate::config::SimulationConfig;
+use crate::compute::{ComputeBackend, SimulationBackend};
+use crate::export::{create_export_writer, write_csv_row, RunSummary, SummaryCollector};
+use crate::simulation::SimulationState;
+use anyhow::{Result, anyhow};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+
+/// Two-sided 97.5% quantiles of Student's t distribution for 1 to 30 degrees of freedom
+const T_975: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228,
+    2.201, 2.179, 2.160, 2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086,
+    2.080, 2.074, 2.069, 2.064, 2.060, 2.056, 2.052, 2.048, 2.045, 2.042,
+];
+
+/// One replication of a scenario: the seed it ran with and its run summary
+#[derive(Debug, Clone)]
+pub struct Replication {
+    pub index: usize,
+    pub seed: u64,
+    pub summary: RunSummary,
+}
+
+/// Mean of one summary metric over the replications, with its 95% confidence interval
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricEstimate {
+    pub metric: &'static str,
+    pub runs: usize,
+    pub mean: f64,
+    pub std_dev: f64, // sample standard deviation between the replications
+    pub half_width: f64, // the interval is mean ± half_width, 0 for a single run
+}
+
+impl MetricEstimate {
+    /// Estimate from one value of the metric per replication
+    pub fn from_samples(metric: &'static str, samples: &[f64]) -> Self {
+        let runs = samples.len();
+        let mean = if runs > 0 { samples.iter().sum::<f64>() / runs as f64 } else { 0.0 };
+        let std_dev = if runs > 1 {
+            (samples.iter().map(|sample| (sample - mean).powi(2)).sum::<f64>() / (runs - 1) as f64).sqrt()
+        } else {
+            0.0
+        };
+        let half_width = if runs > 1 { t_critical_95(runs - 1) * std_dev / (runs as f64).sqrt() } else { 0.0 };
+        Self { metric, runs, mean, std_dev, half_width }
+    }
+    
+    pub fn low(&self) -> f64 {
+        self.mean - self.half_width
+    }
+    
+    pub fn high(&self) -> f64 {
+        self.mean + self.half_width
+    }
+}
+
+/// Multiplier of the standard error for a two-sided 95% confidence interval with
+/// `degrees_of_freedom`; the normal quantile beyond the table
+pub fn t_critical_95(degrees_of_freedom: usize) -> f64 {
+    match degrees_of_freedom {
+        0 => f64::INFINITY,
+        df if df <= T_975.len() => T_975[df - 1],
+        df if df <= 60 => 2.000 + (60 - df) as f64 / 30.0 * 0.042,
+        df if df <= 120 => 1.980 + (120 - df) as f64 / 60.0 * 0.020,
+        _ => 1.960,
+    }
+}
+
+/// Run summary metrics that replications are compared on, in column order
+pub const SUMMARY_METRICS: [&str; 14] = [
+    "spawned", "exited", "removed", "throughput_per_hour", "mean_travel_time", "p95_travel_time", "mean_delay",
+    "slow_share", "lane_changes", "collisions", "ttc_conflicts", "pet_conflicts", "queues_formed", "max_queue_length",
+];
+
+/// Values of `SUMMARY_METRICS` in `summary`
+pub fn summary_metrics(summary: &RunSummary) -> [f64; SUMMARY_METRICS.len()] {
+    let throughput = if summary.simulated_time > 0.0 { summary.exited as f64 / summary.simulated_time as f64 * 3600.0 } else { 0.0 };
+    [
+        summary.spawned as f64,
+        summary.exited as f64,
+        summary.removed as f64,
+        throughput,
+        summary.travel_time.mean as f64,
+        summary.travel_time.p95 as f64,
+        summary.mean_delay as f64,
+        summary.slow_share as f64,
+        summary.lane_changes as f64,
+        summary.collisions as f64,
+        summary.safety.ttc_conflicts as f64,
+        summary.safety.pet_conflicts as f64,
+        summary.queues.formed as f64,
+        summary.queues.max_length as f64,
+    ]
+}
+
+/// Seeds of `count` replications, consecutive from `base`
+pub fn replication_seeds(base: u64, count: usize) -> Vec<u64> {
+    (0..count as u64).map(|offset| base.wrapping_add(offset)).collect()
+}
+
+/// Mean and confidence interval of every summary metric over `replications`
+pub fn aggregate(replications: &[Replication]) -> Vec<MetricEstimate> {
+    if replications.is_empty() {
+        return Vec::new();
+    }
+    let rows: Vec<_> = replications.iter().map(|replication| summary_metrics(&replication.summary)).collect();
+    SUMMARY_METRICS.iter().enumerate().map(|(column, metric)| {
+        let samples: Vec<f64> = rows.iter().map(|row| row[column]).collect();
+        MetricEstimate::from_samples(metric, &samples)
+    }).collect()
+}
+
+/// Simulate `config` on the CPU backend with `seed` for `duration` seconds in steps of `dt`
+pub fn run_replication(config: &SimulationConfig, seed: u64, duration: f32, dt: f32) -> Result<RunSummary> {
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(seed));
+    let mut state = SimulationState::new(dt);
+    let mut collector = SummaryCollector::new(&config.route, &state);
+    let steps = (duration / dt).ceil() as u64;
+    for _ in 0..steps {
+        backend.update(&mut state)?;
+        state.update_car_speeds();
+        state.active_cars = state.cars.len() as u32;
+        collector.record(&state);
+    }
+    Ok(collector.finish(&state))
+}
+
+/// Run `config` once per seed on `jobs` threads, writing one CSV row per replication to
+/// `output` as they finish and the mean and confidence interval of every metric to
+/// `aggregate_output` once all are done
+pub fn run_replications(
+    config: &SimulationConfig,
+    seeds: &[u64],
+    duration: f32,
+    dt: f32,
+    jobs: usize,
+    output: &Path,
+    aggregate_output: &Path,
+) -> Result<(Vec<Replication>, Vec<MetricEstimate>)> {
+    let mut writer = create_export_writer(output)?;
+    let mut header = vec!["run".to_string(), "seed".to_string()];
+    header.extend(SUMMARY_METRICS.iter().map(|metric| metric.to_string()));
+    write_csv_row(&mut writer, &header)?;
+    writer.flush()?;
+    
+    let next = AtomicUsize::new(0);
+    let (sender, results) = mpsc::channel();
+    let mut finished: Vec<Replication> = Vec::with_capacity(seeds.len());
+    let outcome = std::thread::scope(|scope| -> Result<()> {
+        for _ in 0..jobs.clamp(1, seeds.len().max(1)) {
+            let sender = sender.clone();
+            let next = &next;
+            scope.spawn(move || {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(&seed) = seeds.get(index) else {
+                        break;
+                    };
+                    if sender.send((index, seed, run_replication(config, seed, duration, dt))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+        
+        for (index, seed, summary) in results {
+            let summary = summary.map_err(|e| {
+                // Stop handing out runs, the replications have failed
+                next.store(seeds.len(), Ordering::Relaxed);
+                anyhow!("Replication {} (seed {}) failed: {}", index + 1, seed, e)
+            })?;
+            let mut row = vec![(index + 1).to_string(), seed.to_string()];
+            row.extend(summary_metrics(&summary).iter().map(|value| format!("{:.3}", value)));
+            write_csv_row(&mut writer, &row)?;
+            writer.flush()?;
+            log::info!("Replication {}/{} done: {} exited, {} collisions", finished.len() + 1, seeds.len(), summary.exited, summary.collisions);
+            finished.push(Replication { index, seed, summary });
+        }
+        Ok(())
+    });
+    outcome?;
+    finished.sort_by_key(|replication| replication.index);
+    
+    let estimates = aggregate(&finished);
+    let mut writer = create_export_writer(aggregate_output)?;
+    let header: Vec<String> = ["metric", "runs", "mean", "std_dev", "ci_low", "ci_high", "half_width"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    write_csv_row(&mut writer, &header)?;
+    for estimate in &estimates {
+        write_csv_row(&mut writer, &[
+            estimate.metric.to_string(),
+            estimate.runs.to_string(),
+            format!("{:.3}", estimate.mean),
+            format!("{:.3}", estimate.std_dev),
+            format!("{:.3}", estimate.low()),
+            format!("{:.3}", estimate.high()),
+            format!("{:.3}", estimate.half_width),
+        ])?;
+    }
+    writer.flush()?;
+    Ok((finished, estimates))
+}
+
+/// Aggregate results file next to the per-run file, `replications.csv` ->
+/// `replications_aggregate.csv`
+pub fn default_aggregate_output(output: &Path) -> PathBuf {
+    let stem = output.file_stem().map_or("replications".into(), |stem| stem.to_string_lossy());
+    output.with_file_name(format!("{}_aggregate.csv", stem))
+}
@@ -0,0 +1,80 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    graphics::{QualityLevel, QualityManager, RoadMesh},
+};
+use anyhow::Result;
+use std::time::Duration;
+
+const BUDGET_MS: f32 = 10.0;
+
+fn frames(quality: &mut QualityManager, count: usize, work_ms: u64) -> usize {
+    (0..count).filter(|_| quality.record_frame(Duration::from_millis(work_ms))).count()
+}
+
+/// Test that sustained overruns lower the quality one level at a time and short spikes do not
+#[test]
+fn test_degrades_when_over_budget() {
+    let mut quality = QualityManager::new(BUDGET_MS);
+    assert_eq!(quality.level(), QualityLevel::Full);
+    assert!(quality.show_charts());
+    
+    // A few slow frames in between normal ones are tolerated
+    for _ in 0..10 {
+        frames(&mut quality, 20, 15);
+        frames(&mut quality, 1, 8);
+    }
+    assert_eq!(quality.level(), QualityLevel::Full);
+    
+    assert_eq!(frames(&mut quality, 30, 15), 1);
+    assert_eq!(quality.level(), QualityLevel::Reduced);
+    assert!(!quality.show_charts());
+    assert_eq!(quality.max_steps_per_frame(), None);
+    
+    frames(&mut quality, 30, 15);
+    assert_eq!(quality.level(), QualityLevel::Low);
+    assert!(quality.road_detail() < 1.0);
+    assert!(quality.max_steps_per_frame().is_some());
+    
+    // There is nothing lower
+    assert_eq!(frames(&mut quality, 100, 15), 0);
+    assert_eq!(quality.level(), QualityLevel::Low);
+}
+
+/// Test that quality only comes back after a long stretch well under the budget
+#[test]
+fn test_recovers_when_under_budget() {
+    let mut quality = QualityManager::new(BUDGET_MS);
+    frames(&mut quality, 60, 15);
+    assert_eq!(quality.level(), QualityLevel::Low);
+    
+    // Just under the budget is not enough headroom
+    assert_eq!(frames(&mut quality, 500, 9), 0);
+    
+    frames(&mut quality, 180, 2);
+    assert_eq!(quality.level(), QualityLevel::Reduced);
+    frames(&mut quality, 180, 2);
+    assert_eq!(quality.level(), QualityLevel::Full);
+    assert!(quality.show_charts());
+    assert_eq!(quality.road_detail(), 1.0);
+}
+
+/// Test that a zero budget keeps full quality
+#[test]
+fn test_zero_budget_disables() {
+    let mut quality = QualityManager::new(0.0);
+    assert_eq!(quality.budget(), None);
+    assert_eq!(frames(&mut quality, 1000, 1000), 0);
+    assert_eq!(quality.level(), QualityLevel::Full);
+}
+
+/// Test that a lower road detail builds the same road from fewer triangles
+#[test]
+fn test_road_detail_reduces_mesh() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let full = RoadMesh::from_route(&config.route);
+    let coarse = RoadMesh::from_route_with_detail(&config.route, 0.25);
+    assert!(coarse.vertex_count() > 0);
+    assert!(coarse.vertex_count() < full.vertex_count());
+    assert_eq!(RoadMesh::from_route_with_detail(&config.route, 1.0).vertex_count(), full.vertex_count());
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::{BehaviorStatistics, SimulationEvent, SimulationState},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+use std::collections::HashMap;
+
+/// Test that the statistics list every configured behavior, split the trip log and the
+/// collisions by behavior, and come out plausible for a run on the default route
+#[test]
+fn test_behavior_statistics() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(5));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut statistics = BehaviorStatistics::new(&config.cars);
+    let mut collisions = 0;
+    while state.time < 90.0 {
+        backend.update(&mut state)?;
+        statistics.record(&state);
+        collisions += state.events.iter().filter(|event| matches!(event, SimulationEvent::CollisionDetected(_))).count() as u32;
+    }
+    
+    let behaviors = statistics.behaviors(&state);
+    for name in config.cars.behavior.keys() {
+        assert!(behaviors.iter().any(|stats| &stats.behavior == name), "behavior {} is missing", name);
+    }
+    assert_eq!(behaviors.iter().map(|stats| stats.cars as usize).sum::<usize>(), state.cars.len());
+    assert_eq!(behaviors.iter().map(|stats| stats.collisions).sum::<u32>(), 2 * collisions);
+    
+    let mut delays: HashMap<&str, (f32, u32)> = HashMap::new();
+    for trip in state.trips.trips() {
+        let entry = delays.entry(trip.behavior.as_str()).or_default();
+        entry.0 += trip.delay;
+        entry.1 += 1;
+    }
+    for stats in &behaviors {
+        match delays.get(stats.behavior.as_str()) {
+            Some(&(delay, trips)) => {
+                let mean_delay = stats.mean_delay.expect("behavior with trips has a delay");
+                assert!((mean_delay - delay / trips as f32).abs() < 1e-3);
+            }
+            None => assert_eq!(stats.mean_delay, None),
+        }
+        assert!(stats.lane_changes_per_km >= 0.0);
+        if let Some(headway) = stats.mean_headway {
+            assert!(headway > 0.0, "{} follows {} s behind", stats.behavior, headway);
+        }
+    }
+    assert!(behaviors.iter().any(|stats| stats.mean_speed > 5.0 && stats.mean_headway.is_some()));
+    Ok(())
+}
+
+/// Test that the statistics start over when the simulation goes back in time
+#[test]
+fn test_behavior_statistics_reset() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(5));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut statistics = BehaviorStatistics::new(&config.cars);
+    while state.time < 20.0 {
+        backend.update(&mut state)?;
+        statistics.record(&state);
+    }
+    assert!(statistics.behaviors(&state).iter().any(|stats| stats.mean_speed > 0.0));
+    
+    state = SimulationState::new(1.0 / 60.0);
+    statistics.record(&state);
+    for stats in statistics.behaviors(&state) {
+        assert_eq!((stats.cars, stats.mean_speed, stats.collisions), (0, 0.0, 0));
+        assert_eq!((stats.mean_headway, stats.mean_delay), (None, None));
+    }
+    Ok(())
+}
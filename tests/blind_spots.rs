@@ -0,0 +1,88 @@
+use traffic_sim::{
+    config::{SimulationConfig, Validate},
+    simulation::{CarId, SimulationEvent, SimulationState},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+
+/// Every event of the first `seconds` of a run
+fn run_events(config: &SimulationConfig, seconds: f32) -> Result<Vec<SimulationEvent>> {
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(21));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut events = Vec::new();
+    while state.time < seconds {
+        backend.update(&mut state)?;
+        events.extend(state.events.iter().cloned());
+    }
+    Ok(events)
+}
+
+/// Test that drivers who never check their blind spot change lanes into cars beside them,
+/// and that each such lane change is reported and ends in a collision or a near miss
+/// between the two cars, or passes off safely
+#[test]
+fn test_blind_spot_misses() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    config.cars.collision_avoidance.crash_response = "none".to_string();
+    for behavior in config.cars.behavior.values_mut() {
+        behavior.blind_spot_miss_probability = 1.0;
+        behavior.lane_change_frequency *= 4.0;
+    }
+    let events = run_events(&config, 120.0)?;
+    
+    let misses: Vec<(CarId, CarId, f32)> = events.iter().filter_map(|event| match event {
+        SimulationEvent::BlindSpotMiss { car, overlooked, time } => Some((*car, *overlooked, *time)),
+        _ => None,
+    }).collect();
+    assert!(!misses.is_empty(), "no driver overlooked a car");
+    
+    let mut outcomes = 0;
+    for event in &events {
+        let (pair, time) = match event {
+            SimulationEvent::NearMiss { car_a, car_b, gap, time } => {
+                assert!((0.0..1.0).contains(gap), "near miss at {} m", gap);
+                ((*car_a, *car_b), *time)
+            }
+            SimulationEvent::CollisionDetected(collision) => ((collision.car_a, collision.car_b), collision.time),
+            _ => continue,
+        };
+        let after_miss = misses.iter().any(|&(car, overlooked, missed)| {
+            missed <= time && (pair == (car, overlooked) || pair == (overlooked, car))
+        });
+        if matches!(event, SimulationEvent::NearMiss { .. }) {
+            assert!(after_miss, "near miss between cars {:?} without a blind spot miss", pair);
+        }
+        outcomes += after_miss as usize;
+    }
+    assert!(outcomes > 0, "{} blind spot misses and not one collision or near miss", misses.len());
+    Ok(())
+}
+
+/// Test that drivers who always check their mirrors never overlook a car
+#[test]
+fn test_attentive_drivers_never_miss() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    for behavior in config.cars.behavior.values_mut() {
+        behavior.blind_spot_miss_probability = 0.0;
+    }
+    let events = run_events(&config, 60.0)?;
+    assert!(events.iter().any(|event| matches!(event, SimulationEvent::LaneChangeStarted { .. })));
+    assert!(!events.iter().any(|event| matches!(event, SimulationEvent::BlindSpotMiss { .. } | SimulationEvent::NearMiss { .. })));
+    Ok(())
+}
+
+/// Test that blind spot miss probabilities must be between 0 and 1
+#[test]
+fn test_blind_spot_validation() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let check = |probability: f32| {
+        let mut cars = config.cars.clone();
+        cars.behavior.get_mut("erratic").expect("erratic behavior").blind_spot_miss_probability = probability;
+        cars.validate()
+    };
+    assert!(check(0.0).is_ok());
+    assert!(check(1.0).is_ok());
+    assert!(check(-0.1).is_err());
+    assert!(check(1.5).is_err());
+    Ok(())
+}
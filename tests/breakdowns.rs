@@ -0,0 +1,69 @@
+mod common;
+
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::{SimulationState, Breakdown, CarId},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+use common::first_car_spawned;
+
+/// Run the CPU backend until a car has spawned, then break that car down
+fn broken_down_car(pull_over: bool) -> Result<(ComputeBackend, SimulationState, CarId)> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let (backend, mut state) = first_car_spawned(&config, 11)?;
+    
+    let car = &mut state.cars[0];
+    car.breakdown = Some(Breakdown {
+        start_time: state.time,
+        end_time: state.time + 20.0,
+        pull_over_time: pull_over.then_some(state.time + 5.0),
+        shoulder_offset: 0.0,
+    });
+    let id = car.id;
+    Ok((backend, state, id))
+}
+
+fn run_for(backend: &mut ComputeBackend, state: &mut SimulationState, seconds: f32) -> Result<()> {
+    let end = state.time + seconds;
+    while state.time < end {
+        backend.update(state)?;
+    }
+    Ok(())
+}
+
+/// Test that a broken-down car coasts to a stop in its lane and drives on once repaired
+#[test]
+fn test_breakdown_stops_and_recovers() -> Result<()> {
+    let (mut backend, mut state, id) = broken_down_car(false)?;
+    
+    run_for(&mut backend, &mut state, 10.0)?;
+    let car = state.get_car(id).expect("broken-down car stays in the simulation");
+    assert!(car.is_stranded());
+    assert!(!car.is_on_shoulder());
+    
+    run_for(&mut backend, &mut state, 12.0)?;
+    let car = state.get_car(id).expect("repaired car is still driving");
+    assert!(car.breakdown.is_none());
+    assert!(car.velocity.magnitude() > 0.0);
+    
+    Ok(())
+}
+
+/// Test that a car pulled onto the shoulder moves one lane sideways and is towed away
+#[test]
+fn test_breakdown_pulled_onto_shoulder() -> Result<()> {
+    let (mut backend, mut state, id) = broken_down_car(true)?;
+    let lane_width = SimulationConfig::load_from_files("route.toml", "cars.toml")?.route.route.geometry.lane_width;
+    
+    run_for(&mut backend, &mut state, 15.0)?;
+    let car = state.get_car(id).expect("car waits on the shoulder");
+    assert!(car.is_on_shoulder());
+    let offset = car.breakdown.as_ref().map(|breakdown| breakdown.shoulder_offset).unwrap_or(0.0);
+    assert!((offset - lane_width).abs() < 1e-3, "moved {} of {}", offset, lane_width);
+    
+    run_for(&mut backend, &mut state, 6.0)?;
+    assert!(state.get_car(id).is_none());
+    
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use traffic_sim::{
+    config::{BusStop, SimulationConfig, Validate},
+    simulation::{CarId, SimulationEvent, SimulationState, bus_stop_ahead},
+    compute::{ComputeBackend, SimulationBackend},
+    export::{ExportFormat, MetricsExporter},
+};
+use anyhow::Result;
+use std::collections::HashMap;
+
+fn stop(id: &str, angle: f32) -> BusStop {
+    BusStop { id: id.to_string(), angle, lane: 6, length: 30.0, dwell_time: 15.0 }
+}
+
+/// The highway ring with two bus stops in the outer lane and buses among the traffic
+fn bus_line() -> Result<SimulationConfig> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    config.route.route.bus_stops = vec![stop("north", 80.0), stop("south", 260.0)];
+    let mut bus = config.cars.car_types.iter().find(|car_type| car_type.heavy).expect("heavy car type").clone();
+    bus.id = "bus".to_string();
+    bus.weight = 15;
+    bus.bus = true;
+    config.cars.car_types.push(bus);
+    config.cars.simulation.spawn_rate = 2.0;
+    Ok(config)
+}
+
+/// Test that buses pull up in their stop's zone, dwell there for the dwell time and serve
+/// every stop before leaving, and that traffic queues behind them
+#[test]
+fn test_bus_stops() -> Result<()> {
+    let config = bus_line()?;
+    let stops = &config.route.route.bus_stops;
+    let geometry = &config.route.route.geometry;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(3));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    
+    let mut stops_left: HashMap<CarId, usize> = HashMap::new();
+    let mut held_up = false;
+    while state.time < 400.0 {
+        backend.update(&mut state)?;
+        for event in &state.events {
+            if let SimulationEvent::CarExited { car, .. } = event {
+                assert!(stops_left.get(car).is_none_or(|&left| left == 0), "bus {:?} left with stops to serve", car);
+            }
+        }
+        
+        stops_left.clear();
+        for bus in state.cars.iter().filter(|car| car.bus.is_some()) {
+            let progress = bus.bus.as_ref().expect("bus");
+            stops_left.insert(bus.id, stops.len().saturating_sub(progress.next_stop));
+            let Some(start) = progress.dwell_start else {
+                continue;
+            };
+            let stop = &stops[progress.next_stop];
+            assert_eq!(bus.current_lane, stop.lane);
+            assert!(bus_stop_ahead(geometry, bus, stop) <= stop.length, "bus dwelling outside the stop zone");
+            if state.time - start < stop.dwell_time {
+                assert!(bus.velocity.magnitude() < 0.5, "bus left before its dwell time was up");
+            }
+            
+            // Someone is stuck right behind the bus in its lane
+            held_up |= state.cars.iter().any(|other| {
+                let gap = bus_stop_ahead(geometry, other, stop) - bus_stop_ahead(geometry, bus, stop);
+                other.id != bus.id && other.current_lane == bus.current_lane && gap > 0.0 && gap < 30.0
+                    && other.velocity.magnitude() < 1.0
+            });
+        }
+    }
+    
+    for (stop, statistics) in stops.iter().zip(&state.bus_stops) {
+        assert_eq!(stop.id, statistics.id);
+        assert!(statistics.departures > 0, "no bus served stop {}", stop.id);
+        assert!(statistics.mean_dwell() >= stop.dwell_time);
+        assert!(statistics.max_dwell >= statistics.mean_dwell());
+    }
+    assert!(held_up, "no traffic queued behind a bus at its stop");
+    Ok(())
+}
+
+/// Test that the metrics export has departure and dwell columns for every stop
+#[test]
+fn test_bus_stop_metrics() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("traffic-sim-bus-metrics-{}.csv", std::process::id()));
+    let config = bus_line()?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(3));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut exporter = MetricsExporter::create(&path, ExportFormat::Csv, &config.route)?;
+    while state.time < 200.0 {
+        backend.update(&mut state)?;
+        exporter.record(&state)?;
+    }
+    exporter.flush()?;
+    
+    let csv = std::fs::read_to_string(&path)?;
+    let mut lines = csv.lines();
+    let header: Vec<&str> = lines.next().expect("header").split(',').collect();
+    let column = |name: &str| header.iter().position(|&column| column == name).expect(name);
+    let (departures, dwell) = (column("stop_north_departures"), column("stop_north_mean_dwell"));
+    column("stop_south_departures");
+    column("stop_south_mean_dwell");
+    
+    let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+    let total: u32 = rows.iter().map(|row| row[departures].parse::<u32>().expect("count")).sum();
+    assert_eq!(total, state.bus_stops[0].departures);
+    let last_dwell: f32 = rows.last().expect("rows")[dwell].parse()?;
+    assert!((last_dwell - state.bus_stops[0].mean_dwell()).abs() < 1e-2);
+    
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+/// Test that bus stops are checked against the route
+#[test]
+fn test_bus_stop_validation() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let check = |stops: Vec<BusStop>| {
+        let mut route = config.route.clone();
+        route.route.bus_stops = stops;
+        route.validate()
+    };
+    assert!(check(vec![stop("north", 80.0), stop("south", 260.0)]).is_ok());
+    assert!(check(vec![stop("north", 80.0), stop("north", 260.0)]).is_err());
+    assert!(check(vec![BusStop { lane: 7, ..stop("north", 80.0) }]).is_err());
+    assert!(check(vec![BusStop { lane: 0, ..stop("north", 80.0) }]).is_err());
+    assert!(check(vec![BusStop { length: 0.0, ..stop("north", 80.0) }]).is_err());
+    assert!(check(vec![BusStop { dwell_time: -1.0, ..stop("north", 80.0) }]).is_err());
+    
+    let grid = SimulationConfig::load_from_files("route4.toml", "cars.toml")?;
+    let mut route = grid.route.clone();
+    route.route.bus_stops = vec![stop("north", 80.0)];
+    assert!(route.validate().is_err());
+    Ok(())
+}
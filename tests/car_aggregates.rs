@@ -0,0 +1,72 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::{SimulationState, SimulationEvent, CarAggregates, SPEED_BIN_WIDTH},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+
+/// Check the kept aggregates against counting the cars again
+fn assert_counted(state: &SimulationState) {
+    let counted = CarAggregates::from_cars(&state.cars);
+    let aggregates = state.aggregates();
+    for behavior in state.names.behaviors.ids() {
+        let cars = state.cars.iter().filter(|car| car.behavior_type == behavior).count();
+        assert_eq!(aggregates.behavior_count(behavior), cars, "{} cars", state.names.behavior(behavior));
+    }
+    assert_eq!(aggregates.max_speed(), counted.max_speed());
+    assert_eq!(aggregates.velocity_distribution(16), counted.velocity_distribution(16));
+    assert_eq!(aggregates.velocity_distribution(16).iter().sum::<usize>(),
+               if aggregates.max_speed() > 0.0 { state.cars.len() } else { 0 });
+}
+
+/// Test that the aggregates follow cars spawning, exiting and changing speed, and that
+/// the top speed is the fastest car's to within a bin
+#[test]
+fn test_aggregates_follow_the_road() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(6));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut exited = 0;
+    while state.time < 90.0 {
+        backend.update(&mut state)?;
+        state.update_car_speeds();
+        exited += state.events.iter().filter(|event| matches!(event, SimulationEvent::CarExited { .. })).count();
+        assert_counted(&state);
+        
+        let fastest = state.cars.iter().map(|car| car.velocity.magnitude()).fold(0.0, f32::max);
+        if fastest >= SPEED_BIN_WIDTH {
+            let max_speed = state.aggregates().max_speed();
+            assert!(max_speed >= fastest && max_speed <= fastest + SPEED_BIN_WIDTH, "{} for {}", max_speed, fastest);
+        }
+    }
+    assert!(exited > 0, "some cars should leave the road in 90s");
+    
+    for id in state.cars.iter().step_by(2).map(|car| car.id).collect::<Vec<_>>() {
+        state.remove_car(id);
+    }
+    assert_counted(&state);
+    Ok(())
+}
+
+/// Test that the aggregates are counted again after direct edits and loading
+#[test]
+fn test_aggregates_after_reindex() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(8));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    while state.cars.len() < 10 {
+        backend.update(&mut state)?;
+        state.update_car_speeds();
+    }
+    
+    state.cars.truncate(4);
+    state.reindex_cars();
+    assert_counted(&state);
+    
+    let mut loaded: SimulationState = serde_json::from_str(&serde_json::to_string(&state)?)?;
+    assert_eq!(loaded.get_behavior_counts(), Vec::new());
+    loaded.reindex_cars();
+    assert_eq!(loaded.get_behavior_counts(), state.get_behavior_counts());
+    assert_eq!(loaded.get_velocity_distribution(8), state.get_velocity_distribution(8));
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use traffic_sim::{
+    config::{SimulationConfig, Validate},
+    graphics::CarPalette,
+    simulation::SimulationState,
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+use std::collections::HashSet;
+
+fn distinct(palette: &CarPalette) -> bool {
+    let colors: HashSet<[u32; 3]> = palette.entries().iter()
+        .map(|entry| entry.color.map(f32::to_bits))
+        .collect();
+    colors.len() == palette.entries().len()
+}
+
+/// Test that both schemes give every behavior its own color, the usual behaviors first
+#[test]
+fn test_schemes_color_every_behavior() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let default = CarPalette::new(&config.cars);
+    let keys: Vec<&str> = default.entries().iter().map(|entry| entry.key.as_str()).collect();
+    assert_eq!(keys, ["aggressive", "normal", "cautious", "erratic", "strategic"]);
+    assert_eq!(default.entries()[0].label, "Aggressive");
+    assert_eq!(default.entries()[0].color, [1.0, 0.0, 0.0]);
+    assert!(distinct(&default));
+    
+    config.cars.colors.scheme = "colorblind".to_string();
+    config.cars.validate()?;
+    let colorblind = CarPalette::new(&config.cars);
+    assert!(distinct(&colorblind));
+    assert_ne!(colorblind.entries()[0].color, default.entries()[0].color);
+    Ok(())
+}
+
+/// Test that coloring by car type follows the car types' order and single overrides win
+#[test]
+fn test_car_type_colors_and_overrides() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    config.cars.colors.by = "car_type".to_string();
+    config.cars.colors.car_types.insert("truck".to_string(), [0.5, 0.5, 0.5]);
+    config.cars.validate()?;
+    
+    let palette = CarPalette::new(&config.cars);
+    assert!(palette.by_car_type());
+    let ids: Vec<&str> = config.cars.car_types.iter().map(|car_type| car_type.id.as_str()).collect();
+    let keys: Vec<&str> = palette.entries().iter().map(|entry| entry.key.as_str()).collect();
+    assert_eq!(keys, ids);
+    let truck = palette.entries().iter().find(|entry| entry.key == "truck").expect("truck entry");
+    assert_eq!(truck.color, [0.5, 0.5, 0.5]);
+    assert_eq!(palette.entries().iter().find(|entry| entry.key == "sports_car").map(|entry| entry.label.as_str()), Some("Sports car"));
+    
+    // Cars get the color of their own entry
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(4));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    while state.time < 5.0 {
+        backend.update(&mut state)?;
+    }
+    assert!(!state.cars.is_empty());
+    for car in &state.cars {
+        let entry = palette.entries().iter().find(|entry| state.names.car_type(car.car_type) == entry.key).expect("entry for every car type");
+        assert_eq!(palette.color(car), entry.color);
+    }
+    Ok(())
+}
+
+/// Test that unknown schemes, names and out-of-range channels are rejected
+#[test]
+fn test_color_validation() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    
+    let mut cars = config.cars.clone();
+    cars.colors.scheme = "neon".to_string();
+    assert!(cars.validate().is_err());
+    
+    let mut cars = config.cars.clone();
+    cars.colors.by = "lane".to_string();
+    assert!(cars.validate().is_err());
+    
+    let mut cars = config.cars.clone();
+    cars.colors.behaviors.insert("reckless".to_string(), [1.0, 0.0, 0.0]);
+    assert!(cars.validate().is_err());
+    
+    let mut cars = config.cars.clone();
+    cars.colors.behaviors.insert("normal".to_string(), [0.0, 1.5, 0.0]);
+    assert!(cars.validate().is_err());
+    Ok(())
+}
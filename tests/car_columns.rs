@@ -0,0 +1,60 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::{SimulationState, CarColumns, SpatialIndex},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+
+/// Test that the columns hold the cars' fields in car order, agree with the cars on lane
+/// occupancy, and follow the cars as they come and go
+#[test]
+fn test_columns_follow_cars() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let lane_width = config.route.route.geometry.lane_width;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(3));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    while state.cars.len() < 20 {
+        backend.update(&mut state)?;
+    }
+    
+    let mut columns = CarColumns::from_cars(&state.cars);
+    assert_eq!(columns.len(), state.cars.len());
+    for (i, car) in state.cars.iter().enumerate() {
+        assert_eq!(columns.ids[i], car.id);
+        assert_eq!(columns.positions[i], car.position);
+        assert_eq!(columns.footprint(i), car.footprint());
+        assert_eq!(columns.behaviors[i], car.behavior_type);
+        for lane in 1..=config.route.route.geometry.lane_count {
+            assert_eq!(columns.occupies_lane(i, lane, lane_width), car.occupies_lane(lane, lane_width));
+        }
+    }
+    
+    state.cars.remove(0);
+    columns.gather(&state.cars);
+    assert_eq!(columns.len(), state.cars.len());
+    assert_eq!(columns.ids[0], state.cars[0].id);
+    assert_eq!(columns.behaviors[0], state.cars[0].behavior_type);
+    Ok(())
+}
+
+/// Test that refilling an index gives the same neighbors as building a new one, also
+/// when the cell size changes
+#[test]
+fn test_spatial_index_refill() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(5));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut index = SpatialIndex::new(25.0);
+    let mut columns = CarColumns::new();
+    while state.time < 30.0 {
+        backend.update(&mut state)?;
+        let cell_size = if state.cars.len().is_multiple_of(2) { 25.0 } else { 40.0 };
+        columns.gather(&state.cars);
+        index.refill(&columns.positions, cell_size);
+        let built = SpatialIndex::build(&state.cars, cell_size);
+        for car in &state.cars {
+            assert_eq!(index.query(&car.position, 30.0), built.query(&car.position, 30.0));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use traffic_sim::{
+    config::SimulationConfig,
+    graphics::{changed_ranges, min_shape_pixels, CarDetail, Viewport, CROWDED_MIN_SHAPE_PIXELS, DETAILED_CAR_LIMIT, MIN_SHAPE_PIXELS},
+    simulation::{Car, CarId},
+};
+use anyhow::Result;
+use common::template_car;
+use nalgebra::Point2;
+
+/// `count` copies of a spawned car on a square grid `spacing` meters apart, centered on the origin
+fn fleet(count: usize, spacing: f32) -> Result<Vec<Car>> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let template = template_car(&config)?;
+    
+    let side = (count as f32).sqrt().ceil() as usize;
+    let offset = (side - 1) as f32 * spacing / 2.0;
+    Ok((0..count).map(|i| {
+        let mut car = template.clone();
+        car.id = CarId::new(i as u32, 0);
+        car.position = Point2::new((i % side) as f32 * spacing - offset, (i / side) as f32 * spacing - offset);
+        car
+    }).collect())
+}
+
+/// Test that the shape threshold rises only once many cars are in view
+#[test]
+fn test_min_shape_pixels() {
+    assert_eq!(min_shape_pixels(0), MIN_SHAPE_PIXELS);
+    assert_eq!(min_shape_pixels(DETAILED_CAR_LIMIT), MIN_SHAPE_PIXELS);
+    assert_eq!(min_shape_pixels(DETAILED_CAR_LIMIT + 1), CROWDED_MIN_SHAPE_PIXELS);
+}
+
+/// Test that cars keep their shapes zoomed in, turn into dots zoomed out and drop out of view
+#[test]
+fn test_detail_follows_zoom() -> Result<()> {
+    let mut cars = fleet(100, 10.0)?;
+    cars[0].position = Point2::new(5000.0, 0.0);
+    let mut viewport = Viewport::new(1200.0, 800.0);
+    
+    // 3 pixels per meter at zoom 1
+    let detail = CarDetail::select(&cars, &viewport.get_view_matrix(), 1200);
+    assert_eq!(detail.shapes.len(), 99);
+    assert!(detail.dots.is_empty());
+    
+    viewport.set_zoom(0.1);
+    let detail = CarDetail::select(&cars, &viewport.get_view_matrix(), 1200);
+    assert!(detail.shapes.is_empty());
+    assert_eq!(detail.dots.len(), 99);
+    for (_, pixels_per_meter) in &detail.dots {
+        assert!((pixels_per_meter - 0.3).abs() < 1e-3);
+    }
+    
+    // A larger capture keeps the shapes at the same zoom
+    let detail = CarDetail::select(&cars, &viewport.get_view_matrix(), 12000);
+    assert_eq!(detail.shapes.len(), 99);
+    Ok(())
+}
+
+/// Test that 50k cars in view are drawn as dots unless zoomed in on a few
+#[test]
+fn test_crowded_view_uses_dots() -> Result<()> {
+    let cars = fleet(50_000, 1.5)?;
+    let mut viewport = Viewport::new(1200.0, 800.0);
+    
+    let detail = CarDetail::select(&cars, &viewport.get_view_matrix(), 1200);
+    assert!(detail.shapes.is_empty());
+    assert!(detail.dots.len() > DETAILED_CAR_LIMIT);
+    
+    viewport.set_zoom(10.0);
+    let detail = CarDetail::select(&cars, &viewport.get_view_matrix(), 1200);
+    assert!(!detail.shapes.is_empty() && detail.shapes.len() <= DETAILED_CAR_LIMIT);
+    assert!(detail.dots.is_empty());
+    Ok(())
+}
+
+/// Test that the perspective camera turns the far cars into dots first
+#[test]
+fn test_perspective_detail_by_distance() -> Result<()> {
+    let cars = fleet(2500, 20.0)?;
+    let mut viewport = Viewport::new(1200.0, 800.0);
+    viewport.set_zoom(0.35);
+    viewport.set_perspective(true);
+    viewport.set_tilt(std::f32::consts::PI / 3.0);
+    
+    let detail = CarDetail::select(&cars, &viewport.get_view_matrix(), 1200);
+    assert!(!detail.shapes.is_empty() && !detail.dots.is_empty());
+    let nearest_dot = detail.dots.iter().map(|(car, _)| car.position.y).fold(f32::MAX, f32::min);
+    let farthest_shape = detail.shapes.iter().map(|car| car.position.y).fold(f32::MIN, f32::max);
+    assert!(nearest_dot > farthest_shape - 1.0);
+    Ok(())
+}
+
+/// Test that zooming into a corner of the fleet culls the rest and every car is accounted for
+#[test]
+fn test_zoomed_in_corner_culls() -> Result<()> {
+    let cars = fleet(10_000, 10.0)?;
+    let mut viewport = Viewport::new(1200.0, 800.0);
+    
+    let detail = CarDetail::select(&cars, &viewport.get_view_matrix(), 1200);
+    assert_eq!(detail.shapes.len() + detail.dots.len() + detail.culled, cars.len());
+    let culled_at_zoom_1 = detail.culled;
+    
+    viewport.set_position(nalgebra::Vector3::new(-480.0, -480.0, 0.0));
+    viewport.set_zoom(8.0);
+    let detail = CarDetail::select(&cars, &viewport.get_view_matrix(), 1200);
+    assert!(detail.culled > culled_at_zoom_1);
+    assert!(detail.shapes.len() < 100);
+    assert_eq!(detail.shapes.len() + detail.dots.len() + detail.culled, cars.len());
+    
+    // Cars just past the edge of the view are still drawn, as they may reach into it
+    let view_right = viewport.screen_to_world(1200.0, 400.0).x;
+    assert!(detail.shapes.iter().any(|car| car.position.x > view_right));
+    Ok(())
+}
+
+/// Test that only the instances that changed since the last upload are written again,
+/// nearby changes together
+#[test]
+fn test_changed_ranges() {
+    let uploaded: Vec<[f32; 2]> = (0..200).map(|i| [i as f32, 0.0]).collect();
+    let ranges = |staged: &[[f32; 2]], uploaded: &[[f32; 2]]| changed_ranges(staged, uploaded).collect::<Vec<_>>();
+    
+    assert_eq!(ranges(&uploaded, &uploaded), vec![]);
+    assert_eq!(ranges(&uploaded, &[]), vec![0..200]);
+    assert_eq!(ranges(&uploaded[..150], &uploaded), vec![]);
+    
+    let mut staged = uploaded.clone();
+    staged[10][1] = 1.0;
+    staged[20][1] = 1.0;
+    staged[120][1] = 1.0;
+    assert_eq!(ranges(&staged, &uploaded), vec![10..21, 120..121]);
+    
+    staged.extend([[0.0, 1.0]; 5]);
+    staged[199][1] = 1.0;
+    assert_eq!(ranges(&staged, &uploaded), vec![10..21, 120..121, 199..205]);
+}
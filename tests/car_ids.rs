@@ -0,0 +1,126 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::{SimulationState, CarId, CarIds},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+
+/// Run until `count` cars are on the road
+fn state_with_cars(config: &SimulationConfig, count: usize) -> Result<SimulationState> {
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(9));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    while state.cars.len() < count {
+        backend.update(&mut state)?;
+    }
+    Ok(state)
+}
+
+/// Test that cars are found by id as others are removed around them, and that ids of
+/// removed cars find nothing
+#[test]
+fn test_lookup_after_removal() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut state = state_with_cars(&config, 12)?;
+    let ids: Vec<CarId> = state.cars.iter().map(|car| car.id).collect();
+    
+    for &id in ids.iter().step_by(3) {
+        state.remove_car(id);
+    }
+    assert_eq!(state.cars.len(), ids.len() - ids.len().div_ceil(3));
+    for (i, &id) in ids.iter().enumerate() {
+        let found = state.get_car(id).map(|car| car.id);
+        assert_eq!(found, (i % 3 != 0).then_some(id));
+    }
+    
+    let last = *ids.last().expect("cars");
+    state.get_car_mut(last).expect("last car").marked_for_exit = true;
+    assert!(state.cars.iter().any(|car| car.id == last && car.marked_for_exit));
+    Ok(())
+}
+
+/// Test that lookups stay right once the car list is reindexed after direct edits or
+/// loading
+#[test]
+fn test_lookup_after_direct_edits() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut state = state_with_cars(&config, 8)?;
+    
+    state.cars.reverse();
+    let moved = state.cars.remove(2);
+    state.reindex_cars();
+    for car in &state.cars {
+        assert_eq!(state.get_car(car.id).map(|found| found.id), Some(car.id));
+    }
+    assert!(state.get_car(moved.id).is_none());
+    
+    state.cars.push(moved.clone());
+    state.reindex_cars();
+    assert_eq!(state.get_car(moved.id).map(|car| car.spawn_time), Some(moved.spawn_time));
+    
+    let mut loaded: SimulationState = serde_json::from_str(&serde_json::to_string(&state)?)?;
+    loaded.reindex_cars();
+    for car in state.cars.clone() {
+        assert!(loaded.get_car_mut(car.id).is_some());
+        loaded.remove_car(car.id);
+    }
+    assert!(loaded.cars.is_empty());
+    Ok(())
+}
+
+/// Test that a removed car's slot goes to the next car under a new generation, so the
+/// old id finds nothing while the new one finds the new car
+#[test]
+fn test_reused_slot_gets_new_generation() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut state = state_with_cars(&config, 4)?;
+    let template = state.cars[0].clone();
+    let old = state.cars[1].id;
+    
+    state.remove_car(old);
+    let new = state.add_car(template);
+    assert_eq!(new.index, old.index);
+    assert_ne!(new.generation, old.generation);
+    assert!(state.get_car(old).is_none());
+    assert_eq!(state.get_car(new).map(|car| car.id), Some(new));
+    
+    // Removing by the stale id leaves the new car on the road
+    state.remove_car(old);
+    assert!(state.get_car(new).is_some());
+    assert_eq!(state.cars.len(), 4);
+    Ok(())
+}
+
+/// Test that an old id claimed after its slot was reused does not take the slot back to
+/// its generation, so neither old handle finds the slot's next car
+#[test]
+fn test_claim_old_id_after_reuse() -> Result<()> {
+    let mut ids = CarIds::default();
+    let first = ids.allocate();
+    ids.release(first);
+    let second = ids.allocate();
+    assert_eq!((second.index, second.generation), (first.index, first.generation + 1));
+    ids.release(second);
+    assert!(!ids.claim(first));
+    let third = ids.allocate();
+    assert_eq!(third.index, first.index);
+    assert!(third.generation > second.generation);
+    
+    // Nor does a car put back on the road directly under an id whose slot moved on
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut state = state_with_cars(&config, 4)?;
+    let template = state.cars[0].clone();
+    let old_car = state.cars[1].clone();
+    state.remove_car(old_car.id);
+    let reused = state.add_car(template.clone());
+    assert_eq!(reused.index, old_car.id.index);
+    state.remove_car(reused);
+    
+    state.cars.push(old_car.clone());
+    state.reindex_cars();
+    assert!(state.get_car(reused).is_none());
+    let next = state.add_car(template);
+    assert_eq!(next.index, old_car.id.index);
+    assert!(next.generation > reused.generation);
+    assert!(state.get_car(reused).is_none());
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    graphics::SpawnTool,
+    simulation::{place_on_lane, Point, SimulationEvent, SimulationState, PLACED_ENTRY},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+use std::f32::consts::PI;
+
+/// Test that points snap to the middle of the lane under them, and off-road points are refused
+#[test]
+fn test_place_on_lane() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let geometry = &config.route.route.geometry;
+    
+    // Lane 2 of the donut runs from 153.5 to 157 m out, traffic counter-clockwise
+    let placement = place_on_lane(geometry, Point::new(0.0, 154.0)).expect("on the road");
+    assert_eq!(placement.lane, 2);
+    assert!((placement.position - Point::new(0.0, 155.25)).magnitude() < 1e-3);
+    assert!((placement.heading - PI).abs() < 1e-3);
+    assert!(place_on_lane(geometry, Point::new(0.0, 140.0)).is_none());
+    assert!(place_on_lane(geometry, Point::new(0.0, 172.0)).is_none());
+    
+    // Cloverleaf through lanes, but not the ramps
+    let mut geometry = config.route.route.geometry.clone();
+    geometry.geometry_type = "cloverleaf".to_string();
+    geometry.highway_width = Some(40.0);
+    let geometry = &geometry;
+    let placement = place_on_lane(geometry, Point::new(22.0, 100.0)).expect("northbound lane");
+    assert_eq!(placement.lane, 4);
+    assert_eq!(placement.position, Point::new(21.5, 100.0));
+    assert_eq!(placement.heading, PI / 2.0);
+    assert!(place_on_lane(geometry, Point::new(60.0, 60.0)).is_none());
+    
+    // Grid cars need a path from their entry
+    let mut geometry = config.route.route.geometry.clone();
+    geometry.geometry_type = "grid".to_string();
+    assert!(place_on_lane(&geometry, Point::new(0.0, 155.0)).is_none());
+    Ok(())
+}
+
+/// Test that placed cars get the chosen behavior and type, match the lane's traffic,
+/// and are refused on top of another car
+#[test]
+fn test_spawn_car_at() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(5));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    
+    let id = backend.spawn_car_at(Point::new(0.0, -160.0), "cautious", Some("truck"), &mut state)?;
+    let car = state.cars.iter().find(|car| car.id == id).expect("placed car");
+    assert_eq!((state.names.behavior(car.behavior_type), state.names.car_type(car.car_type), car.current_lane), ("cautious", "truck", 3));
+    assert_eq!(car.entry, PLACED_ENTRY);
+    let speed = car.velocity.magnitude();
+    assert!(speed > 0.0 && speed <= config.route.route.traffic_rules.speed_limit);
+    assert!(matches!(state.events.last(), Some(SimulationEvent::CarSpawned { car, .. }) if *car == id));
+    
+    // Right behind the truck is taken, the next lane over is not
+    assert!(backend.spawn_car_at(Point::new(4.0, -160.0), "normal", None, &mut state).is_err());
+    let neighbor = backend.spawn_car_at(Point::new(0.0, -163.0), "normal", None, &mut state)?;
+    assert_eq!(state.cars.iter().find(|car| car.id == neighbor).map(|car| car.current_lane), Some(4));
+    
+    // A car placed behind others in the lane takes up their speed
+    let behind = backend.spawn_car_at(Point::new(15.0, -160.0), "normal", None, &mut state)?;
+    let car = state.cars.iter().find(|car| car.id == behind).expect("placed car");
+    assert!((car.velocity.magnitude() - speed).abs() < 1e-3);
+    
+    assert!(backend.spawn_car_at(Point::new(0.0, 0.0), "normal", None, &mut state).is_err());
+    assert!(backend.spawn_car_at(Point::new(0.0, 160.0), "reckless", None, &mut state).is_err());
+    assert!(backend.spawn_car_at(Point::new(0.0, 160.0), "normal", Some("bus"), &mut state).is_err());
+    
+    // Placed cars drive on like any other
+    let cars = state.cars.len();
+    for _ in 0..60 {
+        backend.update(&mut state)?;
+    }
+    assert!(state.cars.len() >= cars);
+    assert!(state.cars.iter().any(|car| car.id == id && car.distance_traveled > 0.0));
+    Ok(())
+}
+
+/// Test that only a press and release in the same spot counts as a click
+#[test]
+fn test_spawn_tool_clicks() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut tool = SpawnTool::new(&config.cars);
+    assert!(!tool.is_active());
+    tool.toggle();
+    assert!(tool.is_active());
+    assert_eq!(tool.selection(), ("normal", None));
+    
+    tool.press((100.0, 100.0));
+    assert!(tool.release((102.0, 101.0)));
+    tool.press((100.0, 100.0));
+    assert!(!tool.release((140.0, 100.0)));
+    assert!(!tool.release((140.0, 100.0)));
+    
+    // A selection the configuration no longer has falls back
+    tool.select("erratic", Some("truck"));
+    let mut cars = config.cars.clone();
+    cars.car_types.retain(|car_type| car_type.id != "truck");
+    tool.set_config(&cars);
+    assert_eq!(tool.selection(), ("erratic", None));
+    Ok(())
+}
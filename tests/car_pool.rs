@@ -0,0 +1,87 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::{SimulationState, SimulationEvent, CarPool, CarAggregates},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+
+/// Test that removing a car moves the last car into its place, keeps the lookups and
+/// aggregates right and keeps the removed car's buffers
+#[test]
+fn test_removal_swaps_in_last_car() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(14));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    while state.cars.len() < 8 {
+        backend.update(&mut state)?;
+        state.update_car_speeds();
+    }
+    let pooled = state.car_pool.len();
+    let (removed, last) = (state.cars[2].id, state.cars[state.cars.len() - 1].id);
+    state.remove_car(removed);
+    
+    assert_eq!(state.cars[2].id, last);
+    assert_eq!(state.cars.len(), 7);
+    assert_eq!(state.car_pool.len(), pooled + 1);
+    assert!(state.get_car(removed).is_none());
+    for car in &state.cars {
+        assert_eq!(state.get_car(car.id).map(|found| found.id), Some(car.id));
+    }
+    let counted = CarAggregates::from_cars(&state.cars);
+    assert_eq!(state.get_behavior_counts(), counted.behavior_counts());
+    assert_eq!(state.get_velocity_distribution(16), counted.velocity_distribution(16));
+    Ok(())
+}
+
+/// Test that a spawned car gets the buffers of one that left, emptied of what it held
+#[test]
+fn test_spare_cars_are_reused() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(2));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    while state.cars.len() < 3 {
+        backend.update(&mut state)?;
+    }
+    let mut car = state.cars[0].clone();
+    car.perception.distracted_until = Some(100.0);
+    car.destination = Some("a long way from the entry".to_string());
+    let capacity = car.destination.as_ref().map_or(0, String::capacity);
+    
+    let mut pool = CarPool::default();
+    pool.release(car);
+    let mut spare = pool.take();
+    assert!(pool.is_empty());
+    let destination = spare.destination(Some("east")).expect("a destination");
+    assert_eq!(destination, "east");
+    assert_eq!(destination.capacity(), capacity);
+    assert_eq!(spare.entry("north"), "north");
+    assert_eq!(spare.perception().distracted_until, None);
+    assert_eq!(pool.take().entry("south"), "south");
+    Ok(())
+}
+
+/// Test that a busy road keeps running on pooled cars: spawns after the first cars left
+/// take their buffers, and ids and lookups stay right through the swaps
+#[test]
+fn test_steady_traffic_uses_the_pool() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(6));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let (mut exited, mut spawned_after) = (0, 0);
+    while state.time < 120.0 {
+        backend.update(&mut state)?;
+        for event in &state.events {
+            match event {
+                SimulationEvent::CarExited { .. } => exited += 1,
+                SimulationEvent::CarSpawned { .. } if exited > 0 => spawned_after += 1,
+                _ => {}
+            }
+        }
+    }
+    assert!(exited > 0 && spawned_after > 0, "{} exits, {} spawns after them", exited, spawned_after);
+    assert!(state.car_pool.len() <= exited);
+    for car in &state.cars {
+        assert_eq!(state.get_car(car.id).map(|found| found.id), Some(car.id));
+    }
+    Ok(())
+}
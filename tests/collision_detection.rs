@@ -0,0 +1,109 @@
+mod common;
+
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::{SimulationState, CollisionDetector, CarId, Point},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+use common::{place_car, template_car};
+
+/// Test that overlapping cars produce one event per contact and separated cars none
+#[test]
+fn test_collision_events() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let template = template_car(&config)?;
+    let mut detector = CollisionDetector::new(&config.cars.collision_avoidance, &config.route.route.geometry);
+    
+    let mut state = SimulationState::new(1.0 / 60.0);
+    state.add_car(place_car(&template, 0, Point::new(0.0, 0.0), 0.0, 20.0));
+    state.add_car(place_car(&template, 1, Point::new(template.length * 0.8, 0.0), 0.0, 5.0));
+    state.add_car(place_car(&template, 2, Point::new(100.0, 0.0), 0.0, 20.0));
+    // Rotated 90 degrees, its box only reaches half a car width along the x axis
+    state.add_car(place_car(&template, 3, Point::new(100.0 + template.length / 2.0 + template.width / 2.0 + 0.1, 0.0), std::f32::consts::FRAC_PI_2, 0.0));
+    
+    detector.update(&mut state);
+    assert_eq!(state.total_collisions, 1);
+    assert_eq!(state.collision_events.len(), 1);
+    let event = &state.collision_events[0];
+    assert_eq!((event.car_a, event.car_b), (CarId::new(0, 0), CarId::new(1, 0)));
+    assert!((event.relative_speed - 15.0).abs() < 1e-3);
+    assert!(state.cars[0].last_collision_time.is_some());
+    assert!(!state.cars[0].crashed);
+    
+    // A contact that persists is not reported again
+    detector.update(&mut state);
+    assert_eq!(state.total_collisions, 1);
+    assert!(state.collision_events.is_empty());
+    
+    Ok(())
+}
+
+/// Test the configurable crash responses
+#[test]
+fn test_crash_responses() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let template = template_car(&config)?;
+    
+    config.cars.collision_avoidance.crash_response = "halt".to_string();
+    let mut detector = CollisionDetector::new(&config.cars.collision_avoidance, &config.route.route.geometry);
+    let mut state = SimulationState::new(1.0 / 60.0);
+    state.add_car(place_car(&template, 0, Point::new(0.0, 0.0), 0.0, 20.0));
+    state.add_car(place_car(&template, 1, Point::new(1.0, 0.0), 0.0, 5.0));
+    detector.update(&mut state);
+    assert!(state.cars.iter().all(|car| car.crashed && car.velocity.magnitude() == 0.0));
+    
+    config.cars.collision_avoidance.crash_response = "remove".to_string();
+    let mut detector = CollisionDetector::new(&config.cars.collision_avoidance, &config.route.route.geometry);
+    let mut state = SimulationState::new(1.0 / 60.0);
+    state.add_car(place_car(&template, 0, Point::new(0.0, 0.0), 0.0, 20.0));
+    state.add_car(place_car(&template, 1, Point::new(1.0, 0.0), 0.0, 5.0));
+    state.add_car(place_car(&template, 2, Point::new(50.0, 0.0), 0.0, 20.0));
+    detector.update(&mut state);
+    assert_eq!(state.total_collisions, 1);
+    assert_eq!(state.cars.len(), 1);
+    assert_eq!(state.cars[0].id, CarId::new(2, 0));
+    
+    Ok(())
+}
+
+/// Test that the crossing highways of a cloverleaf pass over one another, while cars on
+/// the same highway still collide
+#[test]
+fn test_cloverleaf_bridge() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route2.toml", "cars.toml")?;
+    let template = template_car(&config)?;
+    let mut detector = CollisionDetector::new(&config.cars.collision_avoidance, &config.route.route.geometry);
+    
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut southbound = place_car(&template, 0, Point::new(0.0, 0.0), -std::f32::consts::FRAC_PI_2, 20.0);
+    southbound.current_lane = 2;
+    let mut westbound = place_car(&template, 1, Point::new(0.0, 0.0), std::f32::consts::PI, 20.0);
+    westbound.current_lane = 8;
+    let mut below = place_car(&template, 2, Point::new(0.0, 1.0), std::f32::consts::PI, 5.0);
+    below.current_lane = 8;
+    state.add_car(southbound);
+    state.add_car(westbound);
+    state.add_car(below);
+    detector.update(&mut state);
+    assert_eq!(state.total_collisions, 1);
+    let event = &state.collision_events[0];
+    assert_eq!((event.car_a.min(event.car_b), event.car_a.max(event.car_b)), (CarId::new(1, 0), CarId::new(2, 0)));
+    
+    // Nor do they meet in traffic
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(1));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let geometry = &config.route.route.geometry;
+    for _ in 0..(60.0 / state.dt) as usize {
+        backend.update(&mut state)?;
+        for event in &state.collision_events {
+            let (Some(a), Some(b)) = (state.get_car(event.car_a), state.get_car(event.car_b)) else {
+                continue;
+            };
+            assert!(!geometry.grade_separated(a.current_lane, b.current_lane),
+                "cars in lanes {} and {} collided across the bridge", a.current_lane, b.current_lane);
+        }
+    }
+    
+    Ok(())
+}
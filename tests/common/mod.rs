@@ -0,0 +1,52 @@
+//! Helpers shared by the integration tests. Each test crate uses some of them.
+#![allow(dead_code)]
+
+use traffic_sim::{
+    config::{ConfigOverride, SimulationConfig},
+    simulation::{Car, CarId, Point, SimulationState},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+use nalgebra::Vector2;
+
+/// `route` and the shipped cars.toml with `overrides` applied, as `--set` applies them
+pub fn load(route: &str, overrides: &[&str]) -> Result<SimulationConfig> {
+    let overrides = overrides.iter()
+        .map(|text| text.parse::<ConfigOverride>())
+        .collect::<Result<Vec<_>>>()?;
+    SimulationConfig::load_with_overrides(route, "cars.toml", &overrides)
+}
+
+/// A CPU backend for `config` seeded with `seed`, run until its first car has spawned,
+/// and the state with that car
+pub fn first_car_spawned(config: &SimulationConfig, seed: u64) -> Result<(ComputeBackend, SimulationState)> {
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(seed));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    while state.cars.is_empty() {
+        backend.update(&mut state)?;
+    }
+    Ok((backend, state))
+}
+
+/// Spawn a single car through the normal traffic path to use as a template
+pub fn template_car(config: &SimulationConfig) -> Result<Car> {
+    let (_, state) = first_car_spawned(config, 7)?;
+    Ok(state.cars[0].clone())
+}
+
+/// A copy of `template` numbered `id` at `position`, driving along `heading` at `speed`
+pub fn place_car(template: &Car, id: usize, position: Point, heading: f32, speed: f32) -> Car {
+    let mut car = template.clone();
+    car.id = CarId::new(id as u32, 0);
+    car.position = position;
+    car.heading = heading;
+    car.velocity = Vector2::new(heading.cos(), heading.sin()) * speed;
+    car
+}
+
+/// Point `radius` meters from the origin, the center of the shipped ring, at `degrees`
+/// counter-clockwise from the x axis
+pub fn ring_point(radius: f32, degrees: f32) -> Point {
+    let angle = degrees.to_radians();
+    Point::new(radius * angle.cos(), radius * angle.sin())
+}
@@ -0,0 +1,72 @@
+use traffic_sim::{
+    config::{SimulationConfig, RampMeterConfig},
+    simulation::SimulationState,
+    compute::{ComputeBackend, SimulationBackend},
+    graphics::ComparisonMetrics,
+};
+use anyhow::Result;
+
+/// Test that two runs from the same seed and configuration stay identical step for step,
+/// so any difference between compared runs comes from their configurations
+#[test]
+fn test_same_seed_runs_match() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut left = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(11));
+    let mut right = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(11));
+    let mut left_state = SimulationState::new(1.0 / 60.0);
+    let mut right_state = SimulationState::new(1.0 / 60.0);
+    while left_state.time < 30.0 {
+        left.update(&mut left_state)?;
+        right.update(&mut right_state)?;
+    }
+
+    let metrics = ComparisonMetrics::of(&left_state);
+    assert_eq!(metrics, ComparisonMetrics::of(&right_state));
+    assert!(metrics.spawned > 0);
+    assert_eq!(metrics.active_cars as usize, left_state.cars.len());
+    Ok(())
+}
+
+/// Test that metering the ramps of the compared run holds back cars the baseline lets on
+#[test]
+fn test_metered_comparison_spawns_fewer_cars() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut metered = config.clone();
+    for entry in &mut metered.route.route.entries {
+        entry.metering = Some(RampMeterConfig {
+            detector: None,
+            interval: 10.0,
+            min_interval: 10.0,
+            max_interval: 10.0,
+            target_occupancy: 0.2,
+            gain: 70.0,
+        });
+    }
+    assert!(metered.route.same_geometry(&config.route));
+    assert!(!metered.route.same_road(&config.route));
+
+    let mut baseline = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(11));
+    let mut compared = ComputeBackend::new_cpu(metered.cars.clone(), metered.route.clone(), Some(11));
+    let mut baseline_state = SimulationState::new(1.0 / 60.0);
+    let mut compared_state = SimulationState::new(1.0 / 60.0);
+    while baseline_state.time < 40.0 {
+        baseline.update(&mut baseline_state)?;
+        compared.update(&mut compared_state)?;
+    }
+
+    let (baseline, compared) = (ComparisonMetrics::of(&baseline_state), ComparisonMetrics::of(&compared_state));
+    assert!(compared.spawned < baseline.spawned, "{} metered against {} spawned", compared.spawned, baseline.spawned);
+    assert_eq!(baseline.time, compared.time);
+    Ok(())
+}
+
+/// Test that routes laying out a different road are told apart
+#[test]
+fn test_different_geometry_is_detected() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut wider = config.clone();
+    wider.route.route.geometry.lane_count += 1;
+    assert!(config.route.same_geometry(&config.route));
+    assert!(!wider.route.same_geometry(&config.route));
+    Ok(())
+}
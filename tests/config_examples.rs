@@ -0,0 +1,77 @@
+use traffic_sim::config::{example_cars, example_route, ConfigOverride, EXAMPLE_CARS, SimulationConfig};
+use anyhow::Result;
+
+/// Test that every example route loads, validates and runs with the example cars made for
+/// it, the two agreeing everywhere
+#[test]
+fn test_examples_are_valid() -> Result<()> {
+    for geometry in ["donut", "cloverleaf", "grid"] {
+        let route = example_route(geometry).expect("an example route");
+        let cars = example_cars(geometry).expect("example cars");
+        let config = SimulationConfig::load_from_strs(route, &cars)?;
+        assert_eq!(config.route.route.geometry.geometry_type, geometry);
+        let problems = config.cross_check();
+        assert!(problems.is_empty(), "{}: {:?}", geometry, problems);
+        
+        // One interval per entry, and the commented profiles name entries of the route too
+        let entries: Vec<&str> = config.route.route.entries.iter().map(|entry| entry.id.as_str()).collect();
+        let intervals: Vec<&str> = config.cars.traffic_flow.entry_intervals.iter().map(|interval| interval.entry_id.as_str()).collect();
+        assert_eq!(intervals, entries, "{}", geometry);
+        let profiles = cars.replace("# [[traffic_flow.demand_profiles]]", "[[traffic_flow.demand_profiles]]")
+            .replace("# entry_id", "entry_id")
+            .replace("# points", "points")
+            .replace("# peak", "peak");
+        let config = SimulationConfig::load_from_strs(route, &profiles)?;
+        assert_eq!(config.cars.traffic_flow.demand_profiles.len(), 2, "{}", geometry);
+        assert!(config.cross_check().is_empty(), "{}: {:?}", geometry, config.cross_check());
+    }
+    assert_eq!(example_cars("donut").as_deref(), Some(EXAMPLE_CARS));
+    assert!(example_route("triangle").is_none());
+    assert!(example_cars("triangle").is_none());
+    Ok(())
+}
+
+/// Test that the check names the keys of settings for entries the route does not have and
+/// of car types that do not fit the lanes
+#[test]
+fn test_cross_check_names_keys() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    config.cars.traffic_flow.entry_intervals[1].entry_id = "nowhere".to_string();
+    let problems = config.cross_check();
+    assert_eq!(problems.len(), 1, "{:?}", problems);
+    assert_eq!(problems[0].key, "cars.traffic_flow.entry_intervals.1.entry_id");
+    assert!(problems[0].warning && problems[0].message.contains("nowhere"));
+    
+    config.route.route.geometry.lane_width = 2.2;
+    let errors: Vec<_> = config.cross_check().into_iter().filter(|problem| !problem.warning).collect();
+    let truck = config.cars.car_types.iter().position(|car_type| car_type.id == "truck").expect("a truck");
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert_eq!(errors[0].key, format!("cars.car_types.{}.width", truck));
+    
+    config.route.route.geometry.lane_width = 3.5;
+    config.route.route.traffic_rules.heavy_vehicle_banned_lanes = (1..=config.route.route.geometry.lane_count).collect();
+    let errors: Vec<_> = config.cross_check().into_iter().filter(|problem| !problem.warning).collect();
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert_eq!(errors[0].key, "route.traffic_rules.heavy_vehicle_banned_lanes");
+    Ok(())
+}
+
+/// Test that loading fails on every disagreement at once, naming each offending key, and
+/// loads with warnings only
+#[test]
+fn test_load_checks_files_together() -> Result<()> {
+    let overrides = ["route.geometry.lane_width=2.2", "route.traffic_rules.heavy_vehicle_banned_lanes=[1, 2, 3, 4, 5, 6]"]
+        .map(|text| text.parse::<ConfigOverride>().unwrap());
+    let error = SimulationConfig::load_with_overrides("route.toml", "cars.toml", &overrides)
+        .expect_err("trucks fit no lane").to_string();
+    assert!(error.contains("cars.car_types.2.width"), "{}", error);
+    assert!(error.contains("route.traffic_rules.heavy_vehicle_banned_lanes"), "{}", error);
+    
+    let overrides = ["cars.simulation.total_cars=50".parse::<ConfigOverride>()?];
+    let config = SimulationConfig::load_with_overrides("route.toml", "cars.toml", &overrides)?;
+    let problems = config.cross_check();
+    assert_eq!(problems.len(), 1, "{:?}", problems);
+    assert_eq!(problems[0].key, "cars.simulation.total_cars");
+    assert!(problems[0].warning);
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use traffic_sim::config::{config_file_chain, SimulationConfig};
+use anyhow::Result;
+use std::path::PathBuf;
+
+/// Directory for one test's files, next to copies of the shipped configurations
+fn scenario_directory(name: &str) -> Result<PathBuf> {
+    let directory = std::env::temp_dir().join(format!("traffic-sim-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(directory.join("common"))?;
+    std::fs::copy("route.toml", directory.join("common/route.toml"))?;
+    std::fs::copy("cars.toml", directory.join("common/cars.toml"))?;
+    Ok(directory)
+}
+
+/// Test that a file with a base changes only the fields it sets, merging tables and
+/// replacing arrays, through a chain of bases relative to each file
+#[test]
+fn test_files_override_their_base() -> Result<()> {
+    let directory = scenario_directory("inherit")?;
+    std::fs::write(directory.join("fast.toml"), r#"
+base = "common/route.toml"
+
+[route.traffic_rules]
+speed_limit = "130 km/h"
+"#)?;
+    std::fs::write(directory.join("fast_one_entry.toml"), r#"
+base = "fast.toml"
+
+[route]
+name = "One entry"
+
+[[route.entries]]
+id = "north"
+type = "interior"
+angle = 90.0
+position = "inner"
+lane = 1
+merge_distance = 40.0
+"#)?;
+    std::fs::write(directory.join("busy.toml"), "base = \"common/cars.toml\"\n\n[simulation]\ntotal_cars = 500\n")?;
+    
+    let shipped = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let path = |name: &str| directory.join(name).to_string_lossy().into_owned();
+    let config = SimulationConfig::load_from_files(&path("fast_one_entry.toml"), &path("busy.toml"))?;
+    let route = &config.route.route;
+    assert_eq!(route.name, "One entry");
+    assert!((route.traffic_rules.speed_limit - 36.111).abs() < 1e-2);
+    assert_eq!(route.traffic_rules.following_distance, shipped.route.route.traffic_rules.following_distance);
+    assert_eq!(route.geometry.lane_count, shipped.route.route.geometry.lane_count);
+    assert_eq!(route.entries.len(), 1, "arrays replace the base's");
+    assert_eq!(route.exits.len(), shipped.route.route.exits.len());
+    assert_eq!(config.cars.simulation.total_cars, 500);
+    assert_eq!(config.cars.simulation.spawn_rate, shipped.cars.simulation.spawn_rate);
+    assert_eq!(config.cars.car_types.len(), shipped.cars.car_types.len());
+    
+    let chain = config_file_chain(path("fast_one_entry.toml"))?;
+    assert_eq!(chain, [directory.join("fast_one_entry.toml"), directory.join("fast.toml"), directory.join("common/route.toml")]);
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
+
+/// Test that missing bases and bases leading back to the file fail the load, naming the files
+#[test]
+fn test_bad_bases() -> Result<()> {
+    let directory = scenario_directory("bad-base")?;
+    let path = |name: &str| directory.join(name).to_string_lossy().into_owned();
+    let cars = path("common/cars.toml");
+    
+    std::fs::write(directory.join("missing.toml"), "base = \"nowhere.toml\"\n")?;
+    let error = SimulationConfig::load_from_files(&path("missing.toml"), &cars).expect_err("the base does not exist");
+    assert!(error.to_string().contains("nowhere.toml"), "{}", error);
+    
+    std::fs::write(directory.join("a.toml"), "base = \"b.toml\"\n")?;
+    std::fs::write(directory.join("b.toml"), "base = \"a.toml\"\n")?;
+    let error = SimulationConfig::load_from_files(&path("a.toml"), &cars).expect_err("a loop of bases");
+    assert!(error.to_string().contains("is its own base"), "{}", error);
+    assert!(config_file_chain(path("a.toml")).is_err());
+    
+    std::fs::write(directory.join("number.toml"), "base = 3\n")?;
+    assert!(SimulationConfig::load_from_files(&path("number.toml"), &cars).is_err());
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
@@ -0,0 +1,32 @@
+mod common;
+
+use traffic_sim::config::ConfigOverride;
+use anyhow::Result;
+use common::load;
+
+/// Test that overrides replace values in either file before validation
+#[test]
+fn test_overrides_apply_to_both_files() -> Result<()> {
+    let config = load("route.toml", &[
+        "cars.simulation.spawn_rate=2.5",
+        "route.traffic_rules.speed_limit=33",
+        "route.entries.1.angle=45.0",
+        "route.name=Sweep run",
+    ])?;
+    assert_eq!(config.cars.simulation.spawn_rate, 2.5);
+    assert_eq!(config.route.route.traffic_rules.speed_limit, 33.0);
+    assert_eq!(config.route.route.entries[1].angle, 45.0);
+    assert_eq!(config.route.route.name, "Sweep run");
+
+    Ok(())
+}
+
+/// Test that misspelled keys, bad indices and invalid values are refused
+#[test]
+fn test_bad_overrides_are_errors() {
+    assert!("simulation.spawn_rate=2.5".parse::<ConfigOverride>().is_err());
+    assert!("cars.simulation.spawn_rate".parse::<ConfigOverride>().is_err());
+    assert!(load("route.toml", &["cars.simulation.spawn_rat=2.5"]).is_err());
+    assert!(load("route.toml", &["route.entries.7.angle=45.0"]).is_err());
+    assert!(load("route.toml", &["cars.simulation.spawn_rate=-1"]).is_err());
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use traffic_sim::config::{parse_quantity, Dimension, UnitSystem};
+use anyhow::Result;
+use common::load;
+
+/// Test that quantities with a unit are read in SI units and bare numbers are taken as SI
+#[test]
+fn test_parse_quantities() -> Result<()> {
+    let close = |value: f32, expected: f32| (value - expected).abs() < 1e-3 * expected.abs().max(1.0);
+    assert!(close(parse_quantity("55 mph", Dimension::Speed)?, 24.587));
+    assert!(close(parse_quantity("100km/h", Dimension::Speed)?, 27.778));
+    assert!(close(parse_quantity("3.5 m", Dimension::Distance)?, 3.5));
+    assert!(close(parse_quantity("1 mi", Dimension::Distance)?, 1609.344));
+    assert!(close(parse_quantity("1.5e3 ft", Dimension::Distance)?, 457.2));
+    assert!(close(parse_quantity("2 min", Dimension::Time)?, 120.0));
+    assert!(close(parse_quantity(" 12.5 ", Dimension::Time)?, 12.5));
+    
+    assert!(parse_quantity("55 mpg", Dimension::Speed).is_err());
+    assert!(parse_quantity("3 s", Dimension::Distance).is_err(), "a time is not a distance");
+    assert!(parse_quantity("fast", Dimension::Speed).is_err());
+    Ok(())
+}
+
+/// Test that configuration values may carry units, optional ones too, and that a wrong
+/// unit fails the load naming it
+#[test]
+fn test_config_values_with_units() -> Result<()> {
+    let config = load("route.toml", &[
+        "route.traffic_rules.speed_limit=\"100 km/h\"",
+        "route.geometry.lane_width=\"12 ft\"",
+        "route.exits.0.ramp_speed=\"30 mph\"",
+        "cars.simulation.simulation_duration=\"5 min\"",
+    ])?;
+    let route = &config.route.route;
+    assert!((route.traffic_rules.speed_limit - 27.778).abs() < 1e-2);
+    assert!((route.geometry.lane_width - 3.6576).abs() < 1e-3);
+    assert!(route.exits[0].ramp_speed.is_some_and(|speed| (speed - 13.411).abs() < 1e-2));
+    assert_eq!(config.cars.simulation.simulation_duration, 300.0);
+    
+    let error = load("route.toml", &["route.traffic_rules.speed_limit=\"100 km\""]).expect_err("a distance is not a speed");
+    assert!(format!("{:#}", error).contains("Unknown speed unit 'km'"), "{:#}", error);
+    Ok(())
+}
+
+/// Test that display units convert both ways, and per-length rates to the road length unit
+#[test]
+fn test_unit_systems() {
+    assert_eq!(UnitSystem::Metric.format_speed(10.0), "36.0 km/h");
+    assert_eq!(UnitSystem::Imperial.format_speed(24.587), "55.0 mph");
+    assert_eq!(UnitSystem::Imperial.format_distance(3.048), "10.0 ft");
+    assert_eq!(UnitSystem::Metric.per_road_length(20.0), 20.0);
+    assert!((UnitSystem::Imperial.per_road_length(20.0) - 32.187).abs() < 1e-3, "cars per mile");
+    for units in UnitSystem::ALL {
+        assert!((units.speed_to_si(units.speed(27.8)) - 27.8).abs() < 1e-4);
+        assert!((units.distance_to_si(units.distance(3.5)) - 3.5).abs() < 1e-5);
+    }
+}
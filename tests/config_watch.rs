@@ -0,0 +1,59 @@
+use traffic_sim::config::{ConfigWatcher, SimulationConfig};
+use anyhow::Result;
+use std::time::{Duration, Instant};
+
+/// Poll the watcher until it reports a reload
+fn wait_for_reload(watcher: &mut ConfigWatcher) -> Result<SimulationConfig> {
+    let start = Instant::now();
+    loop {
+        if let Some(reloaded) = watcher.poll() {
+            return reloaded;
+        }
+        assert!(start.elapsed() < Duration::from_secs(10), "change not noticed");
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// Test that edits to a watched file are reloaded and that an invalid file is
+/// reported rather than loaded
+#[test]
+fn test_watcher_reloads_changed_files() -> Result<()> {
+    let directory = std::env::temp_dir().join(format!("traffic-sim-watch-{}", std::process::id()));
+    std::fs::create_dir_all(&directory)?;
+    let route_file = directory.join("route.toml");
+    let cars_file = directory.join("cars.toml");
+    std::fs::copy("route.toml", &route_file)?;
+    let cars = std::fs::read_to_string("cars.toml")?;
+    std::fs::write(&cars_file, &cars)?;
+    
+    let mut watcher = ConfigWatcher::new(route_file.to_str().unwrap(), cars_file.to_str().unwrap(), Vec::new())?;
+    assert!(watcher.poll().is_none());
+    
+    std::fs::write(&cars_file, cars.replace("spawn_rate = 50.0", "spawn_rate = 2.5"))?;
+    let config = wait_for_reload(&mut watcher)?;
+    assert_eq!(config.cars.simulation.spawn_rate, 2.5);
+    
+    std::fs::write(&cars_file, "[simulation\nspawn_rate = ")?;
+    assert!(wait_for_reload(&mut watcher).is_err());
+    
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
+
+/// Test that only geometry, entry and exit changes count as a different road
+#[test]
+fn test_same_road() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut edited = config.route.clone();
+    edited.route.traffic_rules.speed_limit += 5.0;
+    assert!(config.route.same_road(&edited));
+    
+    edited.route.geometry.lane_count += 1;
+    assert!(!config.route.same_road(&edited));
+    
+    let mut edited = config.route.clone();
+    edited.route.exits.pop();
+    assert!(!config.route.same_road(&edited));
+    
+    Ok(())
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use traffic_sim::{
+    config::{DemandProfile, SimulationConfig},
+    simulation::{CarId, SimulationEvent, SimulationState},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+use common::ring_point;
+
+fn degrees(state: &SimulationState, id: CarId) -> f32 {
+    let car = state.get_car(id).expect("car on the ring");
+    car.position.y.atan2(car.position.x).to_degrees()
+}
+
+struct Merge {
+    yielded: bool,     // the follower yielded to the waiting car
+    ahead: bool,       // the car joined ahead of the follower
+    join_speed: f32,   // speed the waiting car planned to join at
+    leader_speed: f32, // speed of the car it joined behind
+    hardest_slowdown: f32, // largest drop in the follower's speed in one step, m/s
+}
+
+/// A car arrives at entry 1 while a cautious driver is passing it, with a faster driver
+/// with `merge_courtesy` catching up behind, and waits to merge
+fn merge(merge_courtesy: &str) -> Result<Merge> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    config.cars.behavior.get_mut("aggressive").expect("aggressive behavior").merge_courtesy = merge_courtesy.to_string();
+    config.cars.traffic_flow.demand_profiles = vec![
+        DemandProfile { entry_id: "entry_1".to_string(), points: vec![[0.0, 100.0], [0.1, 0.0]], peak: None },
+        DemandProfile { entry_id: "entry_2".to_string(), points: vec![[0.0, 0.0]], peak: None },
+    ];
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(7));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    
+    let passing = backend.spawn_car_at(ring_point(151.75, 0.3), "cautious", Some("sedan"), &mut state)?;
+    let follower = backend.spawn_car_at(ring_point(151.75, -40.0), "aggressive", Some("sedan"), &mut state)?;
+    for id in [passing, follower] {
+        state.get_car_mut(id).expect("placed car").destination = None;
+    }
+    
+    // Drivers settle on their own speeds in the first step, the car arrives then too
+    backend.update(&mut state)?;
+    let mut yielded = false;
+    let mut join_speed = 0.0;
+    let mut leader_speed = 0.0;
+    let mut hardest_slowdown: f32 = 0.0;
+    while state.time < 30.0 {
+        let speed = state.get_car(follower).expect("follower").velocity.magnitude();
+        if let Some(merge) = state.merges.first() {
+            join_speed = merge.speed;
+            leader_speed = state.get_car(passing).expect("passing car").velocity.magnitude();
+        }
+        backend.update(&mut state)?;
+        hardest_slowdown = hardest_slowdown.max(speed - state.get_car(follower).expect("follower").velocity.magnitude());
+        yielded |= state.merges.iter().any(|merge| merge.yielding == Some(follower));
+        
+        let joined = state.events.iter().find_map(|event| match event {
+            SimulationEvent::CarSpawned { car, .. } => Some(*car),
+            _ => None,
+        });
+        if let Some(joined) = joined {
+            let ahead = (degrees(&state, joined) - degrees(&state, follower)).rem_euclid(360.0) < 180.0;
+            return Ok(Merge {
+                yielded,
+                ahead,
+                join_speed,
+                leader_speed,
+                hardest_slowdown,
+            });
+        }
+    }
+    panic!("the waiting car never joined");
+}
+
+/// Test that a courteous driver eases off to let a waiting car in ahead of it, which joins
+/// at the speed of the car it follows, and a refusing driver goes first
+#[test]
+fn test_cooperative_merge() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let sedan = config.cars.car_types.iter().find(|car_type| car_type.id == "sedan").expect("sedan");
+    
+    let courteous = merge("open")?;
+    assert!(courteous.yielded);
+    assert!(courteous.ahead);
+    assert!((courteous.join_speed - courteous.leader_speed).abs() < 0.5);
+    assert!(courteous.hardest_slowdown <= sedan.max_deceleration / 60.0 + 1e-3,
+            "follower slowed by {:.2} m/s in one step", courteous.hardest_slowdown);
+            
+    let refusing = merge("refuse")?;
+    assert!(!refusing.yielded);
+    assert!(!refusing.ahead);
+    Ok(())
+}
+
+/// Test that cars arriving on a busy cloverleaf are never put down on top of or just
+/// behind a car in their lane: they join with room to spare or wait to merge
+#[test]
+fn test_spawns_keep_their_distance() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route2.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(1));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut waited = false;
+    for _ in 0..(60.0 / state.dt) as usize {
+        backend.update(&mut state)?;
+        waited |= !state.merges.is_empty();
+        for event in &state.collision_events {
+            let (Some(a), Some(b)) = (state.get_car(event.car_a), state.get_car(event.car_b)) else {
+                continue;
+            };
+            let newest = a.spawn_time.max(b.spawn_time);
+            assert!(a.current_lane != b.current_lane || state.time - newest > 2.0,
+                "cars {} and {} in lane {} collided {:.1}s after spawning", a.id, b.id, a.current_lane, state.time - newest);
+        }
+    }
+    assert!(waited, "no car had to wait to merge");
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use traffic_sim::{
+    config::{SimulationConfig, Validate},
+    simulation::SimulationState,
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+
+/// Test that drivers aim for no more than their comfortable speed round their lane of the
+/// ring, and that the ones with a low comfort threshold end up slower
+#[test]
+fn test_comfortable_curve_speed_on_ring() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    config.cars.behavior.get_mut("cautious").expect("cautious behavior").comfort_lateral_acceleration = Some(1.0);
+    let geometry = config.route.route.geometry.clone();
+    let banking = 9.81 * config.route.route.surface.banking_angle.to_radians().tan();
+    let comfort = |behavior: &str| config.cars.behavior[behavior].comfort_lateral_acceleration;
+    
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(9));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let (mut cautious, mut others) = (Vec::new(), Vec::new());
+    while state.time < 90.0 {
+        backend.update(&mut state)?;
+        // Cars only just spawned have yet to choose a speed, exiting ones keep theirs
+        let driving = state.cars.iter().filter(|car| car.breakdown.is_none() && car.exit_ramp.is_none() && state.time - car.spawn_time > 1.0);
+        for car in driving {
+            let Some(comfort) = comfort(state.names.behavior(car.behavior_type)) else {
+                continue;
+            };
+            let radius = geometry.inner_radius + geometry.lane_width * (car.current_lane as f32 - 0.5);
+            let comfortable_speed = (radius * (comfort + banking)).sqrt();
+            assert!(car.behavior.target_speed <= comfortable_speed + 1e-3,
+                    "{} car {} aims for {} m/s in a lane comfortable up to {} m/s",
+                    state.names.behavior(car.behavior_type), car.id, car.behavior.target_speed, comfortable_speed);
+            if state.names.behavior(car.behavior_type) == "cautious" {
+                cautious.push(car.behavior.target_speed);
+            } else {
+                others.push(car.behavior.target_speed);
+            }
+        }
+    }
+    
+    assert!(!cautious.is_empty() && !others.is_empty());
+    let cautious = cautious.iter().sum::<f32>() / cautious.len() as f32;
+    let others = others.iter().sum::<f32>() / others.len() as f32;
+    assert!(cautious < 15.5 && others > cautious + 5.0, "cautious drivers aim for {} m/s, others {} m/s", cautious, others);
+    Ok(())
+}
+
+/// Test that comfort thresholds must be positive
+#[test]
+fn test_comfort_validation() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let check = |comfort: Option<f32>| {
+        let mut cars = config.cars.clone();
+        cars.behavior.get_mut("normal").expect("normal behavior").comfort_lateral_acceleration = comfort;
+        cars.validate()
+    };
+    assert!(check(Some(2.5)).is_ok());
+    assert!(check(None).is_ok());
+    assert!(check(Some(0.0)).is_err());
+    assert!(check(Some(-1.0)).is_err());
+    Ok(())
+}
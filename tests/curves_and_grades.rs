@@ -0,0 +1,112 @@
+use traffic_sim::{
+    config::{GradeSection, SimulationConfig, Validate},
+    simulation::SimulationState,
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+
+const GRAVITY: f32 = 9.81;
+
+/// Highest speed any car reaches in the first `duration` seconds
+fn top_speed(config: &SimulationConfig, duration: f32) -> Result<f32> {
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(3));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut top: f32 = 0.0;
+    while state.time < duration {
+        backend.update(&mut state)?;
+        top = state.cars.iter().map(|car| car.velocity.magnitude()).fold(top, f32::max);
+    }
+    Ok(top)
+}
+
+/// Test that on a slippery flat ring no car goes round its lane faster than side friction
+/// allows, and that banking the ring lets traffic go faster
+#[test]
+fn test_curve_speed_and_banking() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    config.route.route.surface.friction_coefficient = 0.2;
+    config.route.route.surface.banking_angle = 0.0;
+    let geometry = config.route.route.geometry.clone();
+    
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(3));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    while state.time < 60.0 {
+        backend.update(&mut state)?;
+        for car in state.cars.iter().filter(|car| car.target_lane.is_none() && car.exit_ramp.is_none()) {
+            let radius = geometry.inner_radius + geometry.lane_width * (car.current_lane as f32 - 0.5);
+            let curve_speed = (radius * GRAVITY * 0.2).sqrt();
+            // Cars just done changing lanes may still drift sideways onto the lane middle, and
+            // cars on an off-ramp have left the lane's curve
+            assert!(car.velocity.magnitude() <= curve_speed + 0.5,
+                    "car {} at {} m/s in a curve allowing {} m/s", car.id, car.velocity.magnitude(), curve_speed);
+        }
+    }
+    
+    let flat = top_speed(&config, 60.0)?;
+    config.route.route.surface.banking_angle = 20.0;
+    let banked = top_speed(&config, 60.0)?;
+    assert!(banked > flat + 1.0, "banked ring {} m/s, flat ring {} m/s", banked, flat);
+    Ok(())
+}
+
+/// Test that power-limited trucks lose speed on a steep climb of the ring's grade profile
+#[test]
+fn test_trucks_slow_on_climb() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    config.route.route.surface.grade_profile = vec![GradeSection { start_angle: 90.0, end_angle: 270.0, grade: 12.0 }];
+    let center = nalgebra::Point2::new(config.route.route.geometry.center_x, config.route.route.geometry.center_y);
+    
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(3));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let (mut climbing, mut level) = (Vec::new(), Vec::new());
+    while state.time < 240.0 {
+        backend.update(&mut state)?;
+        if state.time < 60.0 {
+            continue;
+        }
+        for truck in state.cars.iter().filter(|car| state.names.car_type(car.car_type) == "truck") {
+            let to_truck = truck.position - center;
+            let angle = to_truck.y.atan2(to_truck.x).to_degrees().rem_euclid(360.0);
+            // Trucks have lost most of their speed by the second half of the climb
+            if (180.0..270.0).contains(&angle) {
+                climbing.push(truck.velocity.magnitude());
+            } else if !(90.0..270.0).contains(&angle) {
+                level.push(truck.velocity.magnitude());
+            }
+        }
+    }
+    
+    assert!(!climbing.is_empty() && !level.is_empty(), "no trucks on the ring");
+    let climbing = climbing.iter().sum::<f32>() / climbing.len() as f32;
+    let level = level.iter().sum::<f32>() / level.len() as f32;
+    assert!(climbing < level - 2.0, "trucks at {} m/s climbing, {} m/s on the level", climbing, level);
+    Ok(())
+}
+
+/// Test that banking and grade profiles are checked against the route
+#[test]
+fn test_curve_and_grade_validation() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let check = |banking_angle: f32, sections: Vec<GradeSection>| {
+        let mut route = config.route.clone();
+        route.route.surface.banking_angle = banking_angle;
+        route.route.surface.grade_profile = sections;
+        route.validate()
+    };
+    let section = |start_angle: f32, end_angle: f32, grade: f32| GradeSection { start_angle, end_angle, grade };
+    assert!(check(2.0, vec![section(90.0, 180.0, 5.0), section(300.0, 30.0, -4.0)]).is_ok());
+    assert!(check(45.0, Vec::new()).is_err());
+    assert!(check(-1.0, Vec::new()).is_err());
+    assert!(check(2.0, vec![section(90.0, 90.0, 5.0)]).is_err());
+    assert!(check(2.0, vec![section(90.0, 180.0, 31.0)]).is_err());
+    
+    let grid = SimulationConfig::load_from_files("route4.toml", "cars.toml")?;
+    let mut route = grid.route.clone();
+    route.route.surface.grade_profile = vec![section(90.0, 180.0, 5.0)];
+    assert!(route.validate().is_err());
+    
+    let mut cars = config.cars.clone();
+    cars.car_types[0].rollover_threshold = Some(0.0);
+    assert!(cars.validate().is_err());
+    Ok(())
+}
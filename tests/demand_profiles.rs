@@ -0,0 +1,77 @@
+use traffic_sim::{
+    config::{DemandPeak, DemandProfile, SimulationConfig, Validate},
+    simulation::{SimulationEvent, SimulationState},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+use std::collections::HashMap;
+
+fn points(entry_id: &str, points: &[[f32; 2]]) -> DemandProfile {
+    DemandProfile { entry_id: entry_id.to_string(), points: points.to_vec(), peak: None }
+}
+
+/// Test that profiles interpolate between points, hold past the ends and follow a peak
+#[test]
+fn test_demand_factor() -> Result<()> {
+    let profile = points("entry_1", &[[0.0, 0.2], [600.0, 1.0], [1200.0, 0.4]]);
+    assert_eq!(profile.factor(-10.0), 0.2);
+    assert!((profile.factor(300.0) - 0.6).abs() < 1e-5);
+    assert!((profile.factor(900.0) - 0.7).abs() < 1e-5);
+    assert_eq!(profile.factor(5000.0), 0.4);
+    
+    let peak = DemandProfile {
+        entry_id: "entry_2".to_string(),
+        points: Vec::new(),
+        peak: Some(DemandPeak { time: 900.0, width: 600.0, base: 0.2, factor: 1.0 }),
+    };
+    assert_eq!(peak.factor(0.0), 0.2);
+    assert!((peak.factor(900.0) - 1.0).abs() < 1e-5);
+    assert!((peak.factor(600.0) - 0.6).abs() < 1e-5);
+    assert!((peak.factor(1200.0) - 0.6).abs() < 1e-5);
+    assert_eq!(peak.factor(1500.0), 0.2);
+    
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    assert_eq!(config.cars.traffic_flow.demand_factor("entry_1", 100.0), 1.0);
+    config.cars.traffic_flow.demand_profiles = vec![profile, peak.clone()];
+    assert!(config.cars.validate().is_ok());
+    assert_eq!(config.cars.traffic_flow.demand_factor("entry_2", 0.0), 0.2);
+    
+    config.cars.traffic_flow.demand_profiles = vec![points("entry_1", &[[600.0, 1.0], [0.0, 0.2]])];
+    assert!(config.cars.validate().is_err());
+    config.cars.traffic_flow.demand_profiles = vec![DemandProfile { points: vec![[0.0, 1.0]], ..peak.clone() }];
+    assert!(config.cars.validate().is_err());
+    config.cars.traffic_flow.demand_profiles = vec![peak.clone(), peak];
+    assert!(config.cars.validate().is_err());
+    Ok(())
+}
+
+/// Cars spawned at each entry while running up to `until`; events only last a step
+fn spawns_until(backend: &mut ComputeBackend, state: &mut SimulationState, until: f32) -> Result<HashMap<String, usize>> {
+    let mut spawns = HashMap::new();
+    while state.time < until {
+        backend.update(state)?;
+        for event in &state.events {
+            if let SimulationEvent::CarSpawned { entry, .. } = event {
+                *spawns.entry(entry.clone()).or_insert(0) += 1;
+            }
+        }
+    }
+    Ok(spawns)
+}
+
+/// Test that an entry spawns nothing while its demand is zero and starts once it rises
+#[test]
+fn test_spawning_follows_demand() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    config.cars.traffic_flow.demand_profiles = vec![points("entry_1", &[[0.0, 0.0], [20.0, 0.0], [21.0, 1.0]])];
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(5));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    
+    let spawns = spawns_until(&mut backend, &mut state, 20.0)?;
+    assert!(!spawns.contains_key("entry_1"));
+    assert!(spawns.contains_key("entry_2"));
+    
+    let spawns = spawns_until(&mut backend, &mut state, 40.0)?;
+    assert!(spawns.contains_key("entry_1"));
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::SimulationState,
+    compute::{ComputeBackend, Divergence, DivergenceMonitor, DivergenceTolerance, SimulationBackend},
+};
+use anyhow::Result;
+
+fn step(backend: &mut ComputeBackend, state: &mut SimulationState) -> Result<()> {
+    backend.update(state)?;
+    state.update_car_speeds();
+    state.active_cars = state.cars.len() as u32;
+    Ok(())
+}
+
+/// Test that a backend checked against another of its kind never diverges, including
+/// after cars were added outside a step and both were resynchronized
+#[test]
+fn test_identical_backends_agree() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let seed = Some(21);
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), seed);
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let other = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), seed);
+    let mut monitor = DivergenceMonitor::new(other, &state, DivergenceTolerance::default());
+
+    while state.time < 20.0 {
+        step(&mut backend, &mut state)?;
+        assert!(!monitor.step(&state)?, "diverged at t={:.2}s: {}", state.time, monitor.describe());
+    }
+    assert!(!state.cars.is_empty());
+
+    let behavior = config.cars.behavior.keys().next().expect("a behavior").clone();
+    backend.spawn_manual_car(&behavior, &mut state)?;
+    backend.restore_checkpoint(&state, seed);
+    monitor.resync(&state, seed);
+    while state.time < 30.0 {
+        step(&mut backend, &mut state)?;
+        assert!(!monitor.step(&state)?, "diverged after resync at t={:.2}s: {}", state.time, monitor.describe());
+    }
+    assert_eq!(*monitor.peak(), Divergence { time: state.time, ..Divergence::default() });
+    Ok(())
+}
+
+/// Test that a car out of place or missing from one run is reported and goes over tolerance
+#[test]
+fn test_divergence_between_states() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(4));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    while state.cars.len() < 2 {
+        step(&mut backend, &mut state)?;
+    }
+    let tolerance = DivergenceTolerance::default();
+    assert!(!Divergence::between(&state, &state).exceeds(&tolerance));
+
+    let mut moved = state.clone();
+    moved.cars[1].position.x += 0.5;
+    moved.cars[1].velocity.y -= 0.05;
+    let divergence = Divergence::between(&state, &moved);
+    assert!((divergence.max_position - 0.5).abs() < 1e-4);
+    assert!((divergence.max_velocity - 0.05).abs() < 1e-4);
+    assert_eq!(divergence.worst_car, Some(state.cars[1].id));
+    assert!(divergence.exceeds(&tolerance));
+
+    let mut missing = state.clone();
+    missing.cars.pop();
+    let divergence = Divergence::between(&state, &missing);
+    assert_eq!(divergence.unmatched_cars, 1);
+    assert!(divergence.exceeds(&tolerance));
+    Ok(())
+}
+
+/// Test the CPU backend against the GPU one over a longer stretch than the consistency
+/// test, skipped without an OpenCL device
+#[test]
+fn test_gpu_follows_cpu() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let seed = Some(12345);
+    let gpu = match ComputeBackend::new_gpu(config.cars.clone(), config.route.clone(), seed) {
+        Ok(backend) => backend,
+        Err(e) => {
+            println!("Skipping GPU divergence test: {}", e);
+            return Ok(());
+        }
+    };
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), seed);
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let tolerance = DivergenceTolerance { position: 1.0, velocity: 1.0 };
+    let mut monitor = DivergenceMonitor::new(gpu, &state, tolerance);
+    while state.time < 15.0 {
+        step(&mut backend, &mut state)?;
+        monitor.step(&state)?;
+    }
+    assert!(!monitor.exceeded(), "GPU diverged: {}", monitor.describe());
+    Ok(())
+}
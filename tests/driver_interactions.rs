@@ -0,0 +1,103 @@
+mod common;
+
+use traffic_sim::{
+    config::{DemandProfile, LaneClosure, SimulationConfig},
+    simulation::{CarId, SimulationState, TurnSignal},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+use common::ring_point;
+
+/// The default configuration with no traffic spawning and drivers who only change lanes
+/// when they have to
+fn quiet_config() -> Result<SimulationConfig> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    config.cars.traffic_flow.demand_profiles = config.route.route.entries.iter()
+        .map(|entry| DemandProfile { entry_id: entry.id.clone(), points: vec![[0.0, 0.0]], peak: None })
+        .collect();
+    for behavior in config.cars.behavior.values_mut() {
+        behavior.lane_change_frequency = 0.0;
+    }
+    Ok(config)
+}
+
+/// Put a car on the ring that stays in its lane rather than heading for an exit
+fn place(backend: &mut ComputeBackend, state: &mut SimulationState, radius: f32, degrees: f32, behavior: &str) -> Result<CarId> {
+    let id = backend.spawn_car_at(ring_point(radius, degrees), behavior, Some("sedan"), state)?;
+    state.get_car_mut(id).expect("placed car").destination = None;
+    Ok(id)
+}
+
+fn arc_distance(state: &SimulationState, behind: CarId, ahead: CarId) -> f32 {
+    let angle = |id| {
+        let car = state.get_car(id).expect("car on the ring");
+        car.position.y.atan2(car.position.x)
+    };
+    (angle(ahead) - angle(behind)).rem_euclid(std::f32::consts::TAU) * 155.25
+}
+
+/// Distance an aggressive driver settles at behind a slower car, and whether it tailgated
+fn following_gap(tailgates: bool) -> Result<(f32, bool)> {
+    let mut config = quiet_config()?;
+    config.cars.behavior.get_mut("aggressive").expect("aggressive behavior").tailgates = tailgates;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(6));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    
+    let leader = place(&mut backend, &mut state, 155.25, 60.0, "cautious")?;
+    let follower = place(&mut backend, &mut state, 155.25, 50.0, "aggressive")?;
+    let mut tailgated = false;
+    for _ in 0..1800 {
+        backend.update(&mut state)?;
+        tailgated |= state.get_car(follower).expect("follower").behavior.tailgating;
+    }
+    Ok((arc_distance(&state, follower, leader), tailgated))
+}
+
+/// Test that tailgating drivers close up on a slower car ahead and flash it
+#[test]
+fn test_tailgating() -> Result<()> {
+    let (usual, tailgated) = following_gap(false)?;
+    assert!(!tailgated);
+    let (close, tailgated) = following_gap(true)?;
+    assert!(tailgated);
+    assert!(close < usual - 5.0, "tailgating gap {:.1} m, usual {:.1} m", close, usual);
+    Ok(())
+}
+
+/// Seconds until a car whose lane is closed ahead merges in beside a driver with
+/// `merge_courtesy`, and the slowest that driver went meanwhile
+fn merge_time(merge_courtesy: &str) -> Result<(Option<f32>, f32)> {
+    let mut config = quiet_config()?;
+    config.route.route.closures.push(LaneClosure { lane: 1, start_angle: 100.0, end_angle: 130.0 });
+    config.cars.behavior.get_mut("cautious").expect("cautious behavior").merge_courtesy = merge_courtesy.to_string();
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(6));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    
+    let merging = place(&mut backend, &mut state, 151.75, 40.0, "cautious")?;
+    let beside = place(&mut backend, &mut state, 155.25, 39.0, "cautious")?;
+    let mut slowest = f32::INFINITY;
+    let mut signalled = false;
+    while state.time < 20.0 {
+        backend.update(&mut state)?;
+        let car = state.get_car(merging).expect("merging car");
+        if car.target_lane == Some(2) || car.current_lane == 2 {
+            assert!(signalled, "merged without signalling its intent first");
+            return Ok((Some(state.time), slowest));
+        }
+        signalled |= car.behavior.merge_intent == Some(2) && car.turn_signal == Some(TurnSignal::Right);
+        slowest = slowest.min(state.get_car(beside).expect("car beside").velocity.magnitude());
+    }
+    Ok((None, slowest))
+}
+
+/// Test that a car waiting to merge signals its intent, and a courteous driver beside it
+/// slows down to open a gap for it
+#[test]
+fn test_gap_opening() -> Result<()> {
+    let (courteous, slowest) = merge_time("open")?;
+    let (indifferent, usual_slowest) = merge_time("none")?;
+    let courteous = courteous.expect("merged beside a courteous driver");
+    assert!(slowest < usual_slowest - 2.0);
+    assert!(indifferent.is_none_or(|indifferent| courteous < indifferent));
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use traffic_sim::{
+    config::{SimulationConfig, Validate},
+    simulation::{battery_power, SimulationEvent, SimulationState},
+    compute::{ComputeBackend, SimulationBackend},
+    export::SummaryCollector,
+};
+use anyhow::Result;
+use std::collections::HashSet;
+
+/// The default configuration with every car an electric sedan of `capacity` kWh and exit_2
+/// leading to a charging station
+fn electric_config(capacity: f32) -> Result<SimulationConfig> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut ev = config.cars.car_types.iter().find(|car_type| car_type.id == "sedan").expect("sedan car type").clone();
+    ev.id = "ev".to_string();
+    ev.weight = 100;
+    ev.breakdown_probability = 0.0;
+    ev.battery_capacity = Some(capacity);
+    config.cars.car_types = vec![ev];
+    config.route.route.exits.iter_mut().find(|exit| exit.id == "exit_2").expect("exit_2").charging = true;
+    Ok(config)
+}
+
+/// Test that the power model draws more for speed and climbs, only the auxiliaries when
+/// stopped, and recovers energy braking
+#[test]
+fn test_battery_power() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let electric = &config.cars.electric;
+    let power = |speed: f32, acceleration: f32, grade: f32| battery_power(1800.0, speed, acceleration, grade, electric);
+    
+    assert!((power(0.0, 0.0, 0.0) - electric.auxiliary_power).abs() < 1e-4);
+    let cruising = power(25.0, 0.0, 0.0);
+    assert!((8.0..30.0).contains(&cruising), "cruising at 90 km/h takes {} kW", cruising);
+    assert!(power(30.0, 0.0, 0.0) > cruising);
+    assert!(power(25.0, 0.0, 5.0) > cruising + 10.0);
+    assert!(power(25.0, 1.0, 0.0) > cruising);
+    assert!(power(25.0, -3.0, 0.0) < 0.0, "braking hard recovers energy");
+    Ok(())
+}
+
+/// Test that electric vehicles drain their batteries, that drivers low on charge leave at
+/// the charging exit, and that the summary adds up their energy
+#[test]
+fn test_low_charge_heads_for_charger() -> Result<()> {
+    let mut config = electric_config(1.0)?;
+    config.cars.electric.initial_charge_min = 0.3;
+    config.cars.electric.initial_charge_max = 0.5;
+    config.cars.electric.low_charge = 0.2;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(17));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut collector = SummaryCollector::new(&config.route, &state);
+    let mut low = 0;
+    let mut routed = HashSet::new(); // cars that ran low before turning off
+    let mut charged = 0;
+    while state.time < 120.0 {
+        backend.update(&mut state)?;
+        collector.record(&state);
+        for car in &state.cars {
+            let battery = car.battery.as_ref().expect("every car is electric");
+            assert!((0.0..=battery.capacity).contains(&battery.charge));
+            if battery.low && car.breakdown.is_none() && car.exit_ramp.is_none() {
+                assert_eq!(car.destination.as_deref(), Some("exit_2"), "car {} low on charge is not heading for the charger", car.id);
+            }
+        }
+        for event in &state.events {
+            match event {
+                SimulationEvent::BatteryLow { car, charging_exit, .. } => {
+                    assert_eq!(charging_exit.as_deref(), Some("exit_2"));
+                    low += 1;
+                    if state.get_car(*car).is_some_and(|car| car.exit_ramp.is_none()) {
+                        routed.insert(*car);
+                    }
+                }
+                SimulationEvent::CarExited { car, exit, .. } if routed.contains(car) => {
+                    assert_eq!(exit, "exit_2", "car {} low on charge left at {}", car, exit);
+                    charged += 1;
+                }
+                _ => {}
+            }
+        }
+    }
+    assert!(charged > 0, "{} cars ran low, none reached the charger", low);
+    
+    let energy = collector.finish(&state).energy;
+    assert_eq!(energy.electric_cars, state.total_spawned);
+    assert_eq!(energy.low_charge, low);
+    assert!(energy.charged >= charged);
+    assert!(energy.consumed > energy.regenerated && energy.regenerated >= 0.0);
+    assert!((5.0..50.0).contains(&energy.consumption), "{} kWh/100 km", energy.consumption);
+    Ok(())
+}
+
+/// Test that a car whose battery runs flat breaks down and is towed away
+#[test]
+fn test_flat_battery_strands_car() -> Result<()> {
+    let mut config = electric_config(0.02)?;
+    config.cars.electric.low_charge = 0.0;
+    config.cars.breakdowns.min_duration = 5.0;
+    config.cars.breakdowns.max_duration = 10.0;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(3));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut depleted = Vec::new();
+    while state.time < 60.0 {
+        backend.update(&mut state)?;
+        for event in &state.events {
+            if let SimulationEvent::BatteryDepleted { car, time } = event {
+                depleted.push((*car, *time));
+            }
+        }
+        for &(id, time) in &depleted {
+            let Some(car) = state.get_car(id) else {
+                continue;
+            };
+            assert!(car.is_out_of_charge());
+            assert!(car.breakdown.is_some() || car.exit_ramp.is_some(), "car {} ran flat and drives on", id);
+            assert!(state.time < time + 12.0, "car {} out of charge was never towed", id);
+        }
+    }
+    assert!(!depleted.is_empty(), "no battery ran flat");
+    Ok(())
+}
+
+/// Test that electric car types need a mass and that charging exits and charge shares are checked
+#[test]
+fn test_electric_validation() -> Result<()> {
+    let config = electric_config(60.0)?;
+    assert!(config.cars.validate().is_ok() && config.route.validate().is_ok());
+    
+    let mut no_mass = config.clone();
+    no_mass.cars.car_types[0].mass = None;
+    no_mass.cars.car_types[0].engine_power = None;
+    assert!(no_mass.cars.validate().is_err());
+    
+    let mut no_capacity = config.clone();
+    no_capacity.cars.car_types[0].battery_capacity = Some(0.0);
+    assert!(no_capacity.cars.validate().is_err());
+    
+    let mut charges = config.clone();
+    charges.cars.electric.initial_charge_min = 0.8;
+    charges.cars.electric.initial_charge_max = 0.5;
+    assert!(charges.cars.validate().is_err());
+    
+    let mut low = config.clone();
+    low.cars.electric.low_charge = 1.0;
+    assert!(low.cars.validate().is_err());
+    
+    let mut grid = SimulationConfig::load_from_files("route4.toml", "cars.toml")?;
+    grid.route.route.exits[0].charging = true;
+    assert!(grid.route.validate().is_err());
+    Ok(())
+}
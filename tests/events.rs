@@ -0,0 +1,73 @@
+use traffic_sim::{
+    config::{SimulationConfig, SignalGroup, SignalHead},
+    simulation::{SimulationState, SimulationEvent},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+use std::sync::mpsc;
+
+/// Test that observers registered on a backend receive the events of every tick in order,
+/// matching the spawns, exits and signal changes the simulation made
+#[test]
+fn test_observers_receive_events() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    config.route.route.signals.groups.push(SignalGroup {
+        id: "north".to_string(),
+        green_time: 10.0,
+        yellow_time: 3.0,
+        red_time: 7.0,
+        offset: 0.0,
+        heads: vec![SignalHead {
+            angle: Some(90.0),
+            x: None,
+            y: None,
+            heading: None,
+            width: None,
+            lanes: Vec::new(),
+        }],
+    });
+    
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(3));
+    let (sender, receiver) = mpsc::channel();
+    backend.add_observer(Box::new(move |event| {
+        let _ = sender.send(event.clone());
+    }));
+    
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut tick_events = 0;
+    while state.time < 125.0 {
+        backend.update(&mut state)?;
+        tick_events += state.events.len();
+    }
+    let events: Vec<SimulationEvent> = receiver.try_iter().collect();
+    assert_eq!(events.len(), tick_events);
+    
+    let count = |matches: fn(&SimulationEvent) -> bool| events.iter().filter(|event| matches(event)).count() as u32;
+    assert_eq!(count(|event| matches!(event, SimulationEvent::CarSpawned { .. })), state.total_spawned);
+    assert_eq!(count(|event| matches!(event, SimulationEvent::CarExited { .. })), state.exit_counts.values().sum::<u32>());
+    assert_eq!(count(|event| matches!(event, SimulationEvent::CollisionDetected(_))), state.total_collisions);
+    assert!(count(|event| matches!(event, SimulationEvent::LaneChangeStarted { .. })) > 0);
+    
+    // Six full 20 s cycles of yellow, red and green again
+    assert_eq!(count(|event| matches!(event, SimulationEvent::SignalPhaseChanged { .. })), 18);
+    assert!(events.windows(2).all(|pair| event_time(&pair[0]) <= event_time(&pair[1])));
+    
+    Ok(())
+}
+
+fn event_time(event: &SimulationEvent) -> f32 {
+    match event {
+        SimulationEvent::CarSpawned { time, .. }
+        | SimulationEvent::CarExited { time, .. }
+        | SimulationEvent::LaneChangeStarted { time, .. }
+        | SimulationEvent::SignalPhaseChanged { time, .. }
+        | SimulationEvent::BlindSpotMiss { time, .. }
+        | SimulationEvent::NearMiss { time, .. }
+        | SimulationEvent::BatteryLow { time, .. }
+        | SimulationEvent::BatteryDepleted { time, .. }
+        | SimulationEvent::QueueFormed { time, .. }
+        | SimulationEvent::QueueDissipated { time, .. } => *time,
+        SimulationEvent::CollisionDetected(collision) => collision.time,
+        SimulationEvent::ConflictDetected(conflict) => conflict.time,
+    }
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::{ExitRamps, TurnSignal},
+    compute::SimulationBackend,
+};
+use anyhow::Result;
+use common::first_car_spawned;
+
+/// Test that a car heading for an exit signals, leaves the ring onto the off-ramp, moves
+/// along it without jumping, slows down to the ramp speed and is removed at the ramp end
+#[test]
+fn test_car_follows_exit_ramp() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let exit = config.route.route.exits[0].clone();
+    let ramp_speed = exit.ramp_speed.unwrap_or(config.route.route.traffic_rules.min_speed);
+    let ramps = ExitRamps::from_route(&config.route);
+    let ramp_length = ramps.get(&exit.id).expect("donut exits have ramps").length();
+    
+    let (mut backend, mut state) = first_car_spawned(&config, 5)?;
+    let id = state.cars[0].id;
+    state.get_car_mut(id).expect("car just spawned").destination = Some(exit.id.clone());
+    
+    let mut signalled = false;
+    let mut on_ramp = false;
+    let mut last_distance = 0.0;
+    let mut last_speed = f32::MAX;
+    let mut largest_step: f32 = 0.0;
+    let start_time = state.time;
+    loop {
+        let previous_position = state.get_car(id).expect("car is still driving").position;
+        backend.update(&mut state)?;
+        let Some(car) = state.get_car(id) else {
+            break;
+        };
+        largest_step = largest_step.max((car.position - previous_position).magnitude());
+        signalled |= car.turn_signal == Some(TurnSignal::Right);
+        if let Some(ramp) = &car.exit_ramp {
+            assert_eq!(ramp.exit_id, exit.id);
+            on_ramp = true;
+            last_distance = ramp.distance;
+            last_speed = car.velocity.magnitude();
+        }
+        assert!(state.time - start_time < 300.0, "car never left the ring");
+    }
+    
+    assert!(signalled);
+    assert!(on_ramp, "car left without using the ramp");
+    assert!(last_distance > ramp_length - 2.0, "removed {} m into a {} m ramp", last_distance, ramp_length);
+    assert!(last_speed < ramp_speed + 1.0, "left the ramp at {} m/s", last_speed);
+    assert!(largest_step < 1.0, "jumped {} m in one tick", largest_step);
+    
+    Ok(())
+}
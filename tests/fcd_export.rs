@@ -0,0 +1,94 @@
+mod common;
+
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::SimulationState,
+    compute::{ComputeBackend, SimulationBackend},
+    export::FcdExporter,
+};
+use anyhow::Result;
+use common::first_car_spawned;
+
+/// Test that the output is a closed fcd-export document with one timestep per tick and
+/// one vehicle element per car in it
+#[test]
+fn test_fcd_document() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("traffic-sim-fcd-{}.xml", std::process::id()));
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(5));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut exporter = FcdExporter::create(&path, &config.route.route.geometry, None)?;
+    let mut vehicles = 0;
+    for _ in 0..120 {
+        backend.update(&mut state)?;
+        exporter.record(&state)?;
+        vehicles += state.cars.len();
+    }
+    exporter.finish()?;
+    // Nothing is written after the document is closed
+    exporter.record(&state)?;
+    exporter.finish()?;
+    
+    let xml = std::fs::read_to_string(&path)?;
+    assert!(xml.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+    assert!(xml.trim_end().ends_with("</fcd-export>"));
+    assert_eq!(xml.matches("<fcd-export").count(), 1);
+    assert_eq!(xml.matches("<timestep ").count(), 120);
+    assert_eq!(xml.matches("</timestep>").count(), 120);
+    assert_eq!(xml.matches("<vehicle ").count(), vehicles);
+    
+    let vehicle = xml.lines().find(|line| line.trim_start().starts_with("<vehicle ")).expect("a car was written");
+    for attribute in ["id=", "x=", "y=", "angle=", "type=", "speed=", "pos=", "lane=\"road_"] {
+        assert!(vehicle.contains(attribute), "{} missing from {}", attribute, vehicle);
+    }
+    
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+/// Test that a period thins out the timesteps
+#[test]
+fn test_fcd_period() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("traffic-sim-fcd-period-{}.xml", std::process::id()));
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(5));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut exporter = FcdExporter::create(&path, &config.route.route.geometry, Some(1.0))?;
+    for _ in 0..(5.5 / state.dt) as usize {
+        backend.update(&mut state)?;
+        exporter.record(&state)?;
+    }
+    exporter.finish()?;
+    
+    let xml = std::fs::read_to_string(&path)?;
+    assert_eq!(xml.matches("<timestep ").count(), 6);
+    
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+/// Test that headings convert to SUMO's clockwise-from-north angles
+#[test]
+fn test_angles_are_navigational() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("traffic-sim-fcd-angle-{}.xml", std::process::id()));
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let (_, mut state) = first_car_spawned(&config, 5)?;
+    state.cars.truncate(1);
+    state.cars[0].heading = std::f32::consts::FRAC_PI_2; // facing +y, north
+    
+    let mut exporter = FcdExporter::create(&path, &config.route.route.geometry, None)?;
+    exporter.record(&state)?;
+    state.cars[0].heading = 0.0; // facing +x, east
+    state.time += state.dt;
+    exporter.record(&state)?;
+    exporter.finish()?;
+    
+    let xml = std::fs::read_to_string(&path)?;
+    let angles: Vec<&str> = xml.split("angle=\"").skip(1)
+        .map(|rest| rest.split('"').next().unwrap_or_default())
+        .collect();
+    assert_eq!(angles, vec!["0.00", "90.00"]);
+    
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use traffic_sim::{
+    config::{SimulationConfig, DetectorConfig},
+    simulation::{SimulationState, LoopDetector, Car, CarId, Point},
+};
+use anyhow::Result;
+use common::{place_car, template_car};
+
+/// Put car `id` at `x` on the x axis heading along `heading`, replacing it if there
+fn set_car(state: &mut SimulationState, template: &Car, id: usize, x: f32, heading: f32) {
+    let car = place_car(template, id, Point::new(x, 0.0), heading, 20.0);
+    match state.get_car_mut(CarId::new(id as u32, 0)) {
+        Some(placed) => *placed = car,
+        None => {
+            state.add_car(car);
+        }
+    }
+}
+
+/// Test that a car is counted once when it drives over the detector line, that a car
+/// that turns away before reaching it is not counted, and that the interval reading
+/// reports the crossing
+#[test]
+fn test_detector_counts_crossings() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let template = template_car(&config)?;
+    let detector_config = DetectorConfig {
+        id: "test".to_string(),
+        angle: None,
+        x: Some(50.0),
+        y: Some(0.0),
+        heading: Some(0.0),
+        width: Some(10.0),
+        lanes: Vec::new(),
+        interval: 1.0,
+    };
+    let mut detector = LoopDetector::new(&detector_config, &config.route);
+    let mut state = SimulationState::new(1.0 / 60.0);
+    
+    set_car(&mut state, &template, 0, 49.9, 0.0);
+    set_car(&mut state, &template, 1, 20.0, 0.0);
+    assert!(detector.update(&state).is_none());
+    
+    // Car 0 drives over the line, car 1 turns north far before reaching it
+    state.time += state.dt;
+    set_car(&mut state, &template, 0, 50.2, 0.0);
+    set_car(&mut state, &template, 1, 20.0, std::f32::consts::FRAC_PI_2);
+    assert!(detector.update(&state).is_none());
+    assert_eq!(detector.reading(state.time).count, 1);
+    
+    // A car past the line is not counted again, and the interval closes with one crossing
+    state.time = 1.0;
+    set_car(&mut state, &template, 0, 50.5, 0.0);
+    let reading = detector.update(&state).expect("interval has ended");
+    assert_eq!(reading.detector, "test");
+    assert_eq!(reading.count, 1);
+    assert!((reading.flow - 3600.0).abs() < 1.0);
+    let speed = reading.harmonic_mean_speed.expect("one car was counted");
+    assert!((speed - 20.0).abs() < 0.01, "harmonic mean speed {}", speed);
+    assert_eq!(detector.reading(state.time).count, 0);
+    
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use traffic_sim::{
+    graphics::FundamentalDiagram,
+    simulation::DetectorReading,
+};
+use anyhow::Result;
+
+fn reading(detector: &str, end: f32, flow: f32, speed: Option<f32>) -> DetectorReading {
+    DetectorReading {
+        detector: detector.to_string(),
+        start: end - 60.0,
+        end,
+        count: (flow / 60.0) as u32,
+        flow,
+        occupancy: 0.1,
+        harmonic_mean_speed: speed,
+    }
+}
+
+/// Test that density is flow over space mean speed and that empty intervals are left out
+#[test]
+fn test_points_from_readings() {
+    // 1800 veh/h at 20 m/s (72 km/h) is 25 veh/km
+    let full = reading("a", 60.0, 1800.0, Some(20.0));
+    assert!((full.density().unwrap() - 25.0).abs() < 1e-3);
+    let empty = reading("b", 60.0, 0.0, None);
+    assert_eq!(empty.density(), None);
+    
+    let mut diagram = FundamentalDiagram::new();
+    diagram.record(60.0, &[full, empty]);
+    diagram.record(120.0, &[reading("a", 120.0, 900.0, Some(5.0))]);
+    assert_eq!(diagram.points().len(), 2);
+    assert!((diagram.points()[0].speed - 72.0).abs() < 1e-3);
+    assert!((diagram.points()[1].density - 50.0).abs() < 1e-3);
+    
+    // Points stay for the whole run but not across a reset
+    diagram.record(30.0, &[]);
+    assert!(diagram.points().is_empty());
+}
+
+/// Test that the points export as CSV with a header row
+#[test]
+fn test_export_csv() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("traffic-sim-diagram-{}.csv", std::process::id()));
+    let mut diagram = FundamentalDiagram::new();
+    diagram.record(60.0, &[reading("ring_45", 60.0, 1800.0, Some(20.0))]);
+    diagram.write_csv(&path)?;
+    
+    let csv = std::fs::read_to_string(&path)?;
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines, vec!["detector,time,density,flow,speed", "ring_45,60.000,25.00,1800.0,72.00"]);
+    
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
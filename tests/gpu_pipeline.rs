@@ -0,0 +1,81 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::SimulationState,
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+
+/// Test that the CPU backend has nothing to pipeline and keeps stepping in place
+#[test]
+fn test_cpu_backend_ignores_pipelining() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars, config.route, Some(3));
+    assert!(!backend.set_pipelined(true));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    backend.update(&mut state)?;
+    assert_eq!(state.time, state.dt);
+    backend.finish_step(&mut state)?;
+    assert_eq!(state.time, state.dt);
+    Ok(())
+}
+
+/// Test that pipelined GPU steps leave the state a step behind and, once the last one is
+/// finished, end exactly where waiting for every step does
+#[test]
+fn test_pipelined_gpu_matches_lockstep() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut lockstep = match ComputeBackend::new_gpu(config.cars.clone(), config.route.clone(), Some(21)) {
+        Ok(backend) => backend,
+        Err(e) => {
+            println!("Skipping GPU pipeline test: {}", e);
+            return Ok(());
+        }
+    };
+    let mut pipelined = ComputeBackend::new_gpu(config.cars.clone(), config.route.clone(), Some(21))?;
+    assert!(pipelined.set_pipelined(true));
+    
+    let dt = 1.0 / 60.0;
+    let mut expected = SimulationState::new(dt);
+    let mut state = SimulationState::new(dt);
+    for _ in 0..600 {
+        let before = expected.time;
+        lockstep.update(&mut expected)?;
+        pipelined.update(&mut state)?;
+        assert_eq!(state.time, before, "the state should be a step behind");
+    }
+    pipelined.finish_step(&mut state)?;
+    assert_eq!(state.time, expected.time);
+    assert_eq!(state.total_spawned, expected.total_spawned);
+    assert!(!state.cars.is_empty());
+    for (car, other) in state.cars.iter().zip(&expected.cars) {
+        assert_eq!((car.id, car.position, car.velocity), (other.id, other.position, other.velocity));
+    }
+    Ok(())
+}
+
+/// Test that cars removed while a step is running are not brought back by its results
+#[test]
+fn test_pipelined_gpu_follows_removals() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = match ComputeBackend::new_gpu(config.cars.clone(), config.route.clone(), Some(4)) {
+        Ok(backend) => backend,
+        Err(e) => {
+            println!("Skipping GPU pipeline test: {}", e);
+            return Ok(());
+        }
+    };
+    backend.set_pipelined(true);
+    let mut state = SimulationState::new(1.0 / 60.0);
+    while state.cars.len() < 6 {
+        backend.update(&mut state)?;
+    }
+    let removed = state.cars[1].id;
+    state.remove_car(removed);
+    for _ in 0..60 {
+        backend.update(&mut state)?;
+        assert!(state.get_car(removed).is_none());
+    }
+    backend.finish_step(&mut state)?;
+    assert!(state.get_car(removed).is_none());
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::{GpuTiming, PerformanceTracker, SimulationState},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+use std::time::Duration;
+
+fn step(kernel_ms: u64, transfer_ms: u64, step_ms: u64, occupancy: f32) -> GpuTiming {
+    GpuTiming {
+        kernel_time: Duration::from_millis(kernel_ms),
+        transfer_time: Duration::from_millis(transfer_ms),
+        step_time: Duration::from_millis(step_ms),
+        occupancy,
+    }
+}
+
+/// Test that utilization is the busy share of the step time
+#[test]
+fn test_utilization() {
+    assert_eq!(step(2, 1, 6, 0.5).utilization(), 0.5);
+    assert_eq!(step(0, 0, 0, 0.0).utilization(), 0.0);
+    assert_eq!(step(5, 5, 5, 0.0).utilization(), 1.0);
+}
+
+/// Test that the tracker adds up the GPU steps of each frame and averages over frames
+#[test]
+fn test_tracker_averages_gpu_frames() {
+    let mut tracker = PerformanceTracker::new(10);
+    assert_eq!(tracker.average_gpu(), None);
+    
+    // Two steps in the first frame, one in the second
+    tracker.start_frame();
+    tracker.record_gpu(&step(1, 1, 4, 0.25));
+    tracker.record_gpu(&step(1, 1, 4, 0.5));
+    tracker.end_frame();
+    tracker.start_frame();
+    tracker.record_gpu(&step(2, 0, 8, 0.5));
+    tracker.end_frame();
+    
+    let average = tracker.average_gpu().expect("frames ran GPU steps");
+    assert_eq!(average.kernel_time, Duration::from_millis(2));
+    assert_eq!(average.transfer_time, Duration::from_millis(1));
+    assert_eq!(average.step_time, Duration::from_millis(8));
+    assert_eq!(average.occupancy, 0.5);
+    assert!((average.utilization() - 0.375).abs() < 1e-6);
+}
+
+/// Test that the CPU backend reports no GPU timings
+#[test]
+fn test_cpu_backend_has_no_gpu_timing() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars, config.route, Some(1));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    backend.update(&mut state)?;
+    assert_eq!(backend.gpu_timing(), None);
+    Ok(())
+}
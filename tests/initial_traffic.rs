@@ -0,0 +1,75 @@
+mod common;
+
+use traffic_sim::{
+    config::level_of_service_density,
+    simulation::SimulationState,
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+use common::load;
+
+/// Test that populating a donut fills each lane to the density with evenly spaced cars,
+/// all cars of a lane at one speed, and only while the road is empty
+#[test]
+fn test_populate_donut() -> Result<()> {
+    let config = load("route.toml", &[])?;
+    let geometry = config.route.route.geometry.clone();
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(5));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let placed = backend.populate(15.0, &mut state)?;
+    assert_eq!(placed, state.cars.len());
+    
+    for lane in 1..=geometry.lane_count {
+        let cars: Vec<_> = state.cars.iter().filter(|car| car.current_lane == lane).collect();
+        let expected = (geometry.lane_length(lane) * 15.0 / 1000.0).round() as usize;
+        assert_eq!(cars.len(), expected, "lane {}", lane);
+        
+        let mut angles: Vec<f32> = cars.iter()
+            .map(|car| (car.position.y - geometry.center_y).atan2(car.position.x - geometry.center_x))
+            .collect();
+        angles.sort_by(f32::total_cmp);
+        let even = std::f32::consts::TAU / expected as f32;
+        for pair in angles.windows(2) {
+            assert!((pair[1] - pair[0] - even).abs() < 0.01, "lane {} cars {} rad apart", lane, pair[1] - pair[0]);
+        }
+        let speed = cars[0].velocity.magnitude();
+        assert!(speed > 0.0 && cars.iter().all(|car| (car.velocity.magnitude() - speed).abs() < 1e-3));
+    }
+    assert!(backend.populate(15.0, &mut state).is_err(), "the road already has cars");
+    Ok(())
+}
+
+/// Test that a level of service in the configuration fills the road before the first step,
+/// through lanes of a cloverleaf included, and that the car limit caps it
+#[test]
+fn test_initial_traffic_from_config() -> Result<()> {
+    let config = load("route2.toml", &["cars.simulation.initial.level_of_service=\"C\""])?;
+    let density = level_of_service_density("C").expect("a level");
+    let bounds = config.route.route.geometry.world_bounds();
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(2));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    backend.update(&mut state)?;
+    let per_lane = (config.route.route.geometry.lane_length(1) * density / 1000.0).round() as usize;
+    assert!(state.cars.len() >= 12 * per_lane, "{} cars for {} per lane", state.cars.len(), per_lane);
+    assert!(state.cars.iter().all(|car| bounds.contains(car.position.x, car.position.y)));
+    
+    let config = load("route.toml", &["cars.simulation.initial.density=40.0", "cars.simulation.total_cars=50"])?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(2));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    backend.update(&mut state)?;
+    assert!(state.cars.len() <= 50 && state.cars.len() > 40, "{} cars", state.cars.len());
+    Ok(())
+}
+
+/// Test that initial traffic needs exactly one known density on a route it can be placed on
+#[test]
+fn test_initial_traffic_checks() -> Result<()> {
+    assert!(load("route.toml", &["cars.simulation.initial.density=10.0"]).is_ok());
+    assert!(load("route.toml", &["cars.simulation.initial.density=10.0", "cars.simulation.initial.level_of_service=\"B\""]).is_err());
+    assert!(load("route.toml", &["cars.simulation.initial.level_of_service=\"G\""]).is_err());
+    assert!(load("route.toml", &["cars.simulation.initial.density=0.0"]).is_err());
+    assert!(load("route.toml", &["cars.simulation.initial.density=500.0"]).is_err(), "denser than a jam");
+    assert!(load("route3.toml", &["cars.simulation.initial.density=10.0"]).is_err(), "grids plan paths from the entries");
+    assert!(load("route.toml", &["cars.simulation.initial.density=10.0", "cars.simulation.ring_cars=20"]).is_err());
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use traffic_sim::{
+    config::{IntersectionConfig, SimulationConfig, Validate},
+    simulation::{Approach, Car, CarId, IntersectionState, SignType, SimulationState},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+use std::collections::HashMap;
+
+/// Side of the intersection a car arrives from
+fn approach(intersection: &IntersectionState, car: &Car) -> Approach {
+    let offset = car.position - intersection.position;
+    if offset.x.abs() > offset.y.abs() {
+        if offset.x > 0.0 { Approach::East } else { Approach::West }
+    } else if offset.y > 0.0 {
+        Approach::North
+    } else {
+        Approach::South
+    }
+}
+
+fn sign(intersection: &IntersectionState, approach: Approach) -> Option<SignType> {
+    intersection.signs.iter().find(|sign| sign.approach == approach).map(|sign| sign.sign)
+}
+
+/// Run the town grid at town traffic levels, calling `check` with each intersection every
+/// time it lets a car go
+fn run_town(seconds: f32, mut check: impl FnMut(&SimulationState, &IntersectionState, CarId)) -> Result<usize> {
+    let mut config = SimulationConfig::load_from_files("route4.toml", "cars.toml")?;
+    config.cars.simulation.spawn_rate = 1.0;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(9));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    
+    let mut let_go = 0;
+    let mut cleared: HashMap<(usize, usize), Vec<CarId>> = HashMap::new();
+    while state.time < seconds {
+        backend.update(&mut state)?;
+        for intersection in &state.intersections {
+            let before = cleared.entry((intersection.row, intersection.col)).or_default();
+            for &id in intersection.cleared.iter().filter(|id| !before.contains(id)) {
+                check(&state, intersection, id);
+                let_go += 1;
+            }
+            *before = intersection.cleared.clone();
+        }
+    }
+    Ok(let_go)
+}
+
+/// Test that cars at stop signs come to a full stop before going, one at a time, and that
+/// all-way stops let cars go in the order they stopped
+#[test]
+fn test_stop_signs() -> Result<()> {
+    let let_go = run_town(120.0, |state, intersection, id| {
+        let car = state.get_car(id).expect("car let go");
+        if sign(intersection, approach(intersection, car)) != Some(SignType::Stop) {
+            return;
+        }
+        let stopped_at = intersection.stopped.iter()
+            .find(|(stopped, _)| *stopped == id)
+            .map(|&(_, time)| time)
+            .expect("let go without stopping");
+        assert_eq!(intersection.cleared.len(), 1, "two cars let go at once");
+        
+        // Nobody that stopped well before it is still waiting at an all-way stop
+        if intersection.signs.iter().all(|sign| sign.sign == SignType::Stop) {
+            assert!(intersection.stopped.iter()
+                .filter(|(other, _)| intersection.waiting.contains(other))
+                .all(|&(_, time)| time > stopped_at - 1.0));
+        }
+    })?;
+    assert!(let_go > 10, "only {} cars let through", let_go);
+    Ok(())
+}
+
+/// Test that drivers at a yield sign only go with a gap of at least the critical gap ahead
+/// of the major traffic
+#[test]
+fn test_yield_gap_acceptance() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route4.toml", "cars.toml")?;
+    let yield_sign = config.route.route.intersections.iter().find(|intersection| intersection.control == "yield").expect("yield intersection");
+    let critical_gap = yield_sign.critical_gap;
+    
+    let mut yielded = 0;
+    run_town(120.0, |state, intersection, id| {
+        let car = state.get_car(id).expect("car let go");
+        if sign(intersection, approach(intersection, car)) != Some(SignType::Yield) {
+            return;
+        }
+        yielded += 1;
+        
+        // Right turns accept half a second less
+        for other in &state.cars {
+            let Some(distance) = intersection.distance_to_stop_line(other).filter(|&distance| distance > 0.0) else {
+                continue;
+            };
+            if sign(intersection, approach(intersection, other)).is_none() {
+                let arrives_in = distance / other.velocity.magnitude().max(0.1);
+                assert!(arrives_in >= critical_gap - 0.5, "went with a major car {:.1}s away", arrives_in);
+            }
+        }
+    })?;
+    assert!(yielded > 0);
+    Ok(())
+}
+
+/// Test that intersection controls are checked against the route
+#[test]
+fn test_intersection_validation() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route4.toml", "cars.toml")?;
+    let check = |intersection: IntersectionConfig| {
+        let mut route = config.route.clone();
+        route.route.intersections = vec![intersection];
+        route.validate()
+    };
+    let stop = IntersectionConfig { row: 2, col: 2, control: "all_way_stop".to_string(), major: Vec::new(), critical_gap: 5.0 };
+    assert!(check(stop.clone()).is_ok());
+    assert!(check(IntersectionConfig { row: 1, col: 1, ..stop.clone() }).is_err());
+    assert!(check(IntersectionConfig { control: "roundabout".to_string(), ..stop.clone() }).is_err());
+    assert!(check(IntersectionConfig { major: vec!["north".to_string()], ..stop.clone() }).is_err());
+    assert!(check(IntersectionConfig { control: "yield".to_string(), ..stop.clone() }).is_err());
+    assert!(check(IntersectionConfig { control: "yield".to_string(), major: vec!["up".to_string()], ..stop.clone() }).is_err());
+    assert!(check(IntersectionConfig { control: "two_way_stop".to_string(), major: vec!["west".to_string()], ..stop.clone() }).is_ok());
+    assert!(check(IntersectionConfig { critical_gap: 0.0, ..stop.clone() }).is_err());
+    
+    let ring = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut route = ring.route.clone();
+    route.route.intersections = vec![stop];
+    assert!(route.validate().is_err());
+    Ok(())
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use traffic_sim::{
+    config::SimulationConfig,
+    compute::SimulationBackend,
+};
+use anyhow::Result;
+use common::first_car_spawned;
+
+/// Test that a lane change moves the car sideways continuously, straddling both lanes on
+/// the way, and completes in about the configured lane change time
+#[test]
+fn test_lane_change_is_continuous() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let lane_width = config.route.route.geometry.lane_width;
+    let lane_change_time = config.route.route.traffic_rules.lane_change_time;
+    
+    let (mut backend, mut state) = first_car_spawned(&config, 3)?;
+    let id = state.cars[0].id;
+    let start_lane = state.cars[0].current_lane;
+    let target_lane = if start_lane > 1 { start_lane - 1 } else { start_lane + 1 };
+    state.get_car_mut(id).expect("car just spawned").target_lane = Some(target_lane);
+    let start_time = state.time;
+    
+    let mut straddled = false;
+    let mut largest_step: f32 = 0.0;
+    loop {
+        let previous_offset = state.get_car(id).expect("car is still driving").lateral_offset;
+        backend.update(&mut state)?;
+        let car = state.get_car(id).expect("car is still driving");
+        if car.target_lane.is_none() {
+            break;
+        }
+        largest_step = largest_step.max((car.lateral_offset - previous_offset).abs());
+        straddled |= car.occupies_lane(start_lane, lane_width) && car.occupies_lane(target_lane, lane_width);
+        assert!(state.time - start_time < 2.0 * lane_change_time, "lane change did not finish");
+    }
+    
+    let car = state.get_car(id).expect("car is still driving");
+    assert_eq!(car.current_lane, target_lane);
+    assert_eq!(car.lateral_offset, 0.0);
+    assert!(straddled);
+    assert!(largest_step < lane_width / 20.0, "jumped {} m in one tick", largest_step);
+    assert!(state.time - start_time > 0.8 * lane_change_time);
+    
+    Ok(())
+}
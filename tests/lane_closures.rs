@@ -0,0 +1,94 @@
+mod common;
+
+use traffic_sim::{
+    config::{save_closures, LaneClosure, SimulationConfig, Validate},
+    graphics::RoadMesh,
+    simulation::{closure_between, SimulationState},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+use common::ring_point;
+
+/// Test that drags become closures of the lane they start on, ordered with traffic, and
+/// that closures are checked against the route
+#[test]
+fn test_closure_between() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let geometry = &config.route.route.geometry;
+    
+    // Lane 2 is 155.25 m out, traffic runs counter-clockwise
+    let closure = closure_between(geometry, ring_point(155.0, 30.0), ring_point(155.0, 60.0)).expect("on lane 2");
+    assert_eq!(closure, LaneClosure { lane: 2, start_angle: 30.0, end_angle: 60.0 });
+    let reversed = closure_between(geometry, ring_point(155.0, 60.0), ring_point(170.0, 30.0)).expect("on lane 2");
+    assert_eq!(reversed, closure);
+    
+    // Across angle 0 the shorter way round
+    let closure = closure_between(geometry, ring_point(152.0, 10.0), ring_point(152.0, -20.0)).expect("on lane 1");
+    assert_eq!(closure, LaneClosure { lane: 1, start_angle: 340.0, end_angle: 10.0 });
+    assert!((closure.span().to_degrees() - 30.0).abs() < 1e-3);
+    assert_eq!(closure.angle_ahead(0.0), 0.0);
+    assert!(closure.angle_ahead(20f32.to_radians()) > 0.0);
+    
+    assert!(closure_between(geometry, ring_point(100.0, 10.0), ring_point(152.0, 40.0)).is_none());
+    assert!(closure_between(geometry, ring_point(152.0, 10.0), ring_point(160.0, 10.0)).is_none());
+    
+    let mut route = config.route.clone();
+    route.route.closures.push(LaneClosure { lane: 7, start_angle: 0.0, end_angle: 10.0 });
+    assert!(route.validate().is_err());
+    route.route.closures[0] = LaneClosure { lane: 3, start_angle: 10.0, end_angle: 370.0 };
+    assert!(route.validate().is_err());
+    route.route.closures[0].end_angle = 20.0;
+    assert!(route.validate().is_ok());
+    route.route.geometry.geometry_type = "grid".to_string();
+    assert!(route.validate().is_err());
+    Ok(())
+}
+
+/// Test that no car drives into a closed stretch of lane, and a car heading for it merges out
+#[test]
+fn test_cars_avoid_closures() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let closure = LaneClosure { lane: 1, start_angle: 90.0, end_angle: 120.0 };
+    config.route.route.closures.push(closure.clone());
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(9));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    
+    let id = backend.spawn_car_at(ring_point(151.75, 30.0), "normal", Some("sedan"), &mut state)?;
+    for _ in 0..1800 {
+        backend.update(&mut state)?;
+        for car in state.cars.iter().filter(|car| car.current_lane == 1) {
+            let angle = car.position.y.atan2(car.position.x);
+            assert!(closure.angle_ahead(angle) > 0.0, "car {} drove into the closure at t={:.1}", car.id, state.time);
+        }
+    }
+    let car = state.cars.iter().find(|car| car.id == id).expect("placed car still on the ring");
+    assert_ne!(car.current_lane, 1);
+    Ok(())
+}
+
+/// Test that closures are saved into the route file, keeping its comments, and the road
+/// shows them
+#[test]
+fn test_save_closures() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("traffic-sim-closures-{}.toml", std::process::id()));
+    std::fs::copy("route.toml", &path)?;
+    let closures = vec![
+        LaneClosure { lane: 1, start_angle: 90.0, end_angle: 120.5 },
+        LaneClosure { lane: 4, start_angle: 350.0, end_angle: 10.0 },
+    ];
+    save_closures(&path, &closures)?;
+    let text = std::fs::read_to_string(&path)?;
+    assert!(text.contains("# Entry points (interior - cars entering the highway)"));
+    assert!(text.contains("end_angle = 120.5"));
+    let config = SimulationConfig::load_from_files(path.to_str().unwrap(), "cars.toml")?;
+    assert_eq!(config.route.route.closures, closures);
+    
+    let open = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    assert!(RoadMesh::from_route(&config.route).vertex_count() > RoadMesh::from_route(&open.route).vertex_count());
+    
+    save_closures(&path, &[])?;
+    let config = SimulationConfig::load_from_files(path.to_str().unwrap(), "cars.toml")?;
+    std::fs::remove_file(&path)?;
+    assert!(config.route.route.closures.is_empty());
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::{CarId, LaneUsage, SimulationEvent, SimulationState},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+
+fn lane_change(state: &mut SimulationState, from_lane: u32, to_lane: u32) {
+    state.events.push(SimulationEvent::LaneChangeStarted { car: CarId::new(1, 0), from_lane, to_lane, time: state.time });
+}
+
+/// Test that lane change rates are per minute over the window and old changes drop out
+#[test]
+fn test_lane_change_rates() {
+    let mut usage = LaneUsage::new(3, 60.0);
+    let mut state = SimulationState::new(1.0);
+    
+    // Three changes from lane 1 to 2 and one from 2 to 3 in the first 30 seconds
+    for second in 0..=30 {
+        state.time = second as f32;
+        state.events.clear();
+        match second {
+            5 | 10 | 15 => lane_change(&mut state, 1, 2),
+            20 => lane_change(&mut state, 2, 3),
+            _ => {}
+        }
+        usage.record(&state);
+    }
+    let lanes = usage.lanes(&state);
+    assert_eq!(lanes.len(), 3);
+    assert_eq!(lanes[0].changes_out, 6.0);
+    assert_eq!(lanes[1].changes_in, 6.0);
+    assert_eq!(lanes[1].changes_out, 2.0);
+    assert_eq!(lanes[2].changes_in, 2.0);
+    assert_eq!(lanes[0].cars, 0);
+    assert_eq!(lanes[0].mean_speed, 0.0);
+    
+    // A minute later only the change at 20 s is left
+    state.events.clear();
+    state.time = 78.0;
+    usage.record(&state);
+    let lanes = usage.lanes(&state);
+    assert_eq!(lanes[0].changes_out, 0.0);
+    assert_eq!(lanes[2].changes_in, 1.0);
+    
+    // Going back in time, as after a reset, starts over
+    state.time = 0.0;
+    usage.record(&state);
+    assert!(usage.lanes(&state).iter().all(|lane| lane.changes_in == 0.0 && lane.changes_out == 0.0));
+}
+
+/// Test that the lane table accounts for every car and every lane change of a real run
+#[test]
+fn test_lane_usage_matches_run() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(3));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut usage = LaneUsage::new(config.route.route.geometry.lane_count, 60.0);
+    let mut changes = 0;
+    while state.time < 45.0 {
+        backend.update(&mut state)?;
+        usage.record(&state);
+        changes += state.events.iter().filter(|event| matches!(event, SimulationEvent::LaneChangeStarted { .. })).count();
+    }
+    
+    let lanes = usage.lanes(&state);
+    assert!(lanes.len() >= config.route.route.geometry.lane_count as usize);
+    assert_eq!(lanes.iter().map(|lane| lane.cars as usize).sum::<usize>(), state.cars.len());
+    for lane in lanes.iter().filter(|lane| lane.cars > 0) {
+        let speeds = state.cars.iter().filter(|car| car.current_lane == lane.lane).map(|car| car.velocity.magnitude());
+        let mean = speeds.clone().sum::<f32>() / speeds.count() as f32;
+        assert!((lane.mean_speed - mean).abs() < 1e-3);
+    }
+    
+    // Every change leaves one lane and enters another, and the run is shorter than the window
+    let minutes = (state.time - 1.0 / 60.0) / 60.0;
+    let total_in: f32 = lanes.iter().map(|lane| lane.changes_in).sum();
+    let total_out: f32 = lanes.iter().map(|lane| lane.changes_out).sum();
+    assert!((total_in - total_out).abs() < 1e-2);
+    assert!((total_in * minutes - changes as f32).abs() < 0.1, "{} changes, {} per minute", changes, total_in);
+    Ok(())
+}
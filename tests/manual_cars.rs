@@ -0,0 +1,50 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::{SimulationEvent, SimulationState},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+
+/// Test that the spawn hotkeys' backend calls add a car of each behavior, and the removal
+/// hotkeys' mark one car of the behavior at a time
+#[test]
+fn test_spawn_and_remove_by_behavior() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(3));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    
+    for behavior in ["aggressive", "normal", "cautious", "erratic", "strategic"] {
+        // Regular spawns can be sitting on both entries for a moment
+        let mut spawned = backend.spawn_manual_car(behavior, &mut state);
+        for _ in 0..600 {
+            if spawned.is_ok() {
+                break;
+            }
+            backend.update(&mut state)?;
+            spawned = backend.spawn_manual_car(behavior, &mut state);
+        }
+        let id = spawned?;
+        let car = state.cars.iter().find(|car| car.id == id).expect("spawned car");
+        assert_eq!(state.names.behavior(car.behavior_type), behavior);
+        assert!(matches!(state.events.last(), Some(SimulationEvent::CarSpawned { car, .. }) if *car == id));
+        for _ in 0..120 {
+            backend.update(&mut state)?;
+        }
+    }
+    assert!(backend.spawn_manual_car("reckless", &mut state).is_err());
+    
+    let manual = state.cars.iter().find(|car| state.names.behavior(car.behavior_type) == "cautious").map(|car| car.id);
+    let marked = backend.mark_car_for_exit("cautious", &mut state);
+    assert!(marked.is_some());
+    assert_eq!(marked, manual);
+    let car = state.cars.iter().find(|car| Some(car.id) == marked).expect("marked car");
+    assert!(car.marked_for_exit && car.exit_time == Some(state.time));
+    
+    // Every cautious car ends up marked, then there is nothing left to mark
+    let cautious = state.cars.iter().filter(|car| state.names.behavior(car.behavior_type) == "cautious").count();
+    for _ in 1..cautious {
+        assert!(backend.mark_car_for_exit("cautious", &mut state).is_some());
+    }
+    assert_eq!(backend.mark_car_for_exit("cautious", &mut state), None);
+    Ok(())
+}
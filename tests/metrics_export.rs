@@ -0,0 +1,82 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::SimulationState,
+    compute::{ComputeBackend, SimulationBackend},
+    export::{ExportFormat, MetricsExporter},
+};
+use anyhow::Result;
+
+const TICKS: usize = 120;
+
+/// Run the highway ring for `TICKS` ticks, recording each into a metrics file at `path`
+fn export_metrics(path: &std::path::Path, format: ExportFormat) -> Result<(SimulationConfig, SimulationState)> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(5));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut exporter = MetricsExporter::create(path, format, &config.route)?;
+    for _ in 0..TICKS {
+        backend.update(&mut state)?;
+        exporter.record(&state)?;
+    }
+    exporter.flush()?;
+    Ok((config, state))
+}
+
+/// Test that the CSV has a header naming every lane and exit column and one row per tick,
+/// whose per-tick spawn counts add up to the cars spawned
+#[test]
+fn test_metrics_csv() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("traffic-sim-metrics-{}.csv", std::process::id()));
+    let (config, state) = export_metrics(&path, ExportFormat::Csv)?;
+    let lane_count = config.route.route.geometry.lane_count as usize;
+    
+    let csv = std::fs::read_to_string(&path)?;
+    let mut lines = csv.lines();
+    let header: Vec<&str> = lines.next().expect("header").split(',').collect();
+    assert_eq!(header[..5], ["time", "active_cars", "mean_speed", "spawned", "exited"]);
+    assert_eq!(header.len(), 5 + 2 * lane_count + config.route.route.exits.len());
+    assert_eq!(header[5], "lane_1_count");
+    assert_eq!(header[5 + lane_count], "lane_1_density");
+    assert!(header.contains(&"exit_exit_1_count"));
+    
+    let rows: Vec<Vec<f32>> = lines
+        .map(|line| line.split(',').map(|field| field.parse().expect("number")).collect())
+        .collect();
+    assert_eq!(rows.len(), TICKS);
+    assert!(rows.iter().all(|row| row.len() == header.len()));
+    assert!(rows.windows(2).all(|pair| pair[1][0] > pair[0][0]), "times increase");
+    let spawned: f32 = rows.iter().map(|row| row[3]).sum();
+    assert_eq!(spawned as u32, state.total_spawned);
+    
+    let last = rows.last().expect("rows");
+    assert_eq!(last[1] as u32, state.active_cars);
+    let on_lanes: f32 = last[5..5 + lane_count].iter().sum();
+    assert_eq!(on_lanes as usize, state.cars.len());
+    
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+/// Test that JSON Lines output has one object per tick with the same figures as the state
+#[test]
+fn test_metrics_json_lines() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("traffic-sim-metrics-{}.jsonl", std::process::id()));
+    let (config, state) = export_metrics(&path, ExportFormat::JsonLines)?;
+    
+    let jsonl = std::fs::read_to_string(&path)?;
+    let ticks: Vec<serde_json::Value> = jsonl.lines().map(serde_json::from_str).collect::<Result<_, _>>()?;
+    assert_eq!(ticks.len(), TICKS);
+    let spawned: u64 = ticks.iter().map(|tick| tick["spawned"].as_u64().expect("count")).sum();
+    assert_eq!(spawned, state.total_spawned as u64);
+    
+    let last = ticks.last().expect("ticks");
+    assert!((last["time"].as_f64().expect("time") - state.time as f64).abs() < 1e-4);
+    assert_eq!(last["active_cars"], state.active_cars);
+    let lane_counts = last["lane_counts"].as_array().expect("lane counts");
+    assert_eq!(lane_counts.len(), config.route.route.geometry.lane_count as usize);
+    assert_eq!(last["lane_density"].as_array().expect("densities").len(), lane_counts.len());
+    assert_eq!(last["exit_counts"].as_array().expect("exit counts").len(), config.route.route.exits.len());
+    
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    graphics::{Minimap, MinimapTransform, Viewport},
+};
+use anyhow::Result;
+use nalgebra::Vector3;
+
+/// Test that the world rectangle fills the minimap on its longer side, centered on the other
+#[test]
+fn test_transform_fits_and_round_trips() {
+    let transform = MinimapTransform::fit([-200.0, -50.0], [200.0, 50.0], [200.0, 100.0]);
+    assert_eq!(transform.to_map([-200.0, 50.0]), [0.0, 25.0]);
+    assert_eq!(transform.to_map([200.0, -50.0]), [200.0, 75.0]);
+    // North is up
+    assert!(transform.to_map([0.0, 10.0])[1] < transform.to_map([0.0, 0.0])[1]);
+    
+    let world = transform.to_world(transform.to_map([123.0, -17.0]));
+    assert!((world[0] - 123.0).abs() < 1e-3 && (world[1] + 17.0).abs() < 1e-3);
+}
+
+/// Test that the minimap covers the whole road and keeps its shape
+#[test]
+fn test_minimap_covers_route() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let minimap = Minimap::new(&config.route);
+    let (min, max) = minimap.bounds();
+    assert!(min[0] < max[0] && min[1] < max[1]);
+    
+    // The donut's outer edge, which ring vertices sit on at angle 0 and 180 degrees
+    let geometry = &config.route.route.geometry;
+    let radius = geometry.inner_radius + geometry.lane_count as f32 * geometry.lane_width;
+    assert!(min[0] < -radius && max[0] > radius);
+    
+    let size = minimap.size();
+    let aspect = (max[0] - min[0]) / (max[1] - min[1]);
+    assert!((size[0] / size[1] - aspect).abs() < 1e-3);
+    Ok(())
+}
+
+/// Test that the viewport reports the world area on screen and pans to a picked point
+#[test]
+fn test_viewport_corners_and_look_at() {
+    let mut viewport = Viewport::new(1200.0, 800.0);
+    let corners = viewport.visible_corners();
+    // 400 m across at zoom 1, in the window's aspect ratio
+    assert!((corners[0].x + 200.0).abs() < 1e-3 && (corners[0].y - 400.0 / 3.0).abs() < 1e-3);
+    assert!((corners[2].x - 200.0).abs() < 1e-3 && (corners[2].y + 400.0 / 3.0).abs() < 1e-3);
+    
+    viewport.look_at(Vector3::new(300.0, -100.0, 0.0));
+    for _ in 0..120 {
+        viewport.update();
+    }
+    assert!((viewport.position - Vector3::new(300.0, -100.0, 0.0)).norm() < 1.0);
+}
@@ -0,0 +1,115 @@
+use traffic_sim::{
+    config::{RouteTransfer, SimulationConfig, WorldConfig, WorldRoute},
+    simulation::{CarId, Point, SimulationEvent, SimulationState},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+fn world_route(id: &str, file: &str, offset: [f32; 2]) -> WorldRoute {
+    WorldRoute { id: id.to_string(), file: file.to_string(), offset }
+}
+
+fn transfer(from: &str, exit: &str, to: &str, entry: &str) -> RouteTransfer {
+    RouteTransfer { from: from.to_string(), exit: exit.to_string(), to: to.to_string(), entry: entry.to_string() }
+}
+
+/// Test that the ring and the town of world.toml run side by side, each car on its own
+/// route in world coordinates, and that cars leaving through a mapped exit carry on at the
+/// entry of the other route with the same id
+#[test]
+fn test_world_transfers() -> Result<()> {
+    let world = WorldConfig::load("world.toml")?;
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let ring_edge = world.regions[0].route.route.geometry.outer_radius;
+    let town_center = Point::new(world.regions[1].offset[0], world.regions[1].offset[1]);
+    let mut backend = ComputeBackend::new_world(config.cars.clone(), &world, Some(4));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    
+    let mut left_town: HashMap<CarId, f32> = HashMap::new();
+    let mut transferred = 0;
+    let mut both_routes = false;
+    while state.time < 240.0 {
+        backend.update(&mut state)?;
+        
+        let ids: HashSet<CarId> = state.cars.iter().map(|car| car.id).collect();
+        assert_eq!(ids.len(), state.cars.len(), "two cars share an id");
+        assert_eq!(state.active_cars as usize, state.cars.len());
+        for car in &state.cars {
+            match car.route {
+                // Cars leaving by an off-ramp are still a little outside the ring
+                0 => assert!(car.position.coords.magnitude() < ring_edge + 60.0, "ring car {} at {:?}", car.id, car.position),
+                1 => assert!((car.position - town_center).magnitude() < 110.0, "town car {} at {:?}", car.id, car.position),
+                route => panic!("car {} on unknown route {}", car.id, route),
+            }
+        }
+        both_routes |= state.cars.iter().any(|car| car.route == 0) && state.cars.iter().any(|car| car.route == 1);
+        
+        for event in &state.events {
+            match event {
+                SimulationEvent::CarExited { car, exit, time } if exit == "town/west_exit" => {
+                    left_town.insert(*car, *time);
+                }
+                SimulationEvent::CarSpawned { car, entry, time } if entry == "ring/entry_1" => {
+                    if let Some(left) = left_town.remove(car) {
+                        assert!(*time >= left);
+                        let joined = state.get_car(*car).expect("transferred car");
+                        assert_eq!(joined.route, 0);
+                        transferred += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    
+    assert!(both_routes, "the routes never had cars at the same time");
+    assert!(transferred > 0, "no car drove from the town onto the ring");
+    assert!(state.exit_counts.keys().all(|exit| exit.starts_with("ring/") || exit.starts_with("town/")));
+    assert!(state.trips.trips().iter().any(|trip| trip.entry == "ring/entry_1"));
+    Ok(())
+}
+
+/// Test that a world of one route at the origin runs exactly as the route on its own
+#[test]
+fn test_single_route_world_matches_route() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let world = WorldConfig { routes: vec![world_route("ring", "route.toml", [0.0, 0.0])], transfers: Vec::new() }
+        .load_routes(Path::new("."))?;
+    let mut world_backend = ComputeBackend::new_world(config.cars.clone(), &world, Some(7));
+    let mut route_backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(7));
+    let mut world_state = SimulationState::new(1.0 / 60.0);
+    let mut route_state = SimulationState::new(1.0 / 60.0);
+    
+    while route_state.time < 60.0 {
+        world_backend.update(&mut world_state)?;
+        route_backend.update(&mut route_state)?;
+    }
+    assert_eq!(world_state.total_spawned, route_state.total_spawned);
+    assert_eq!(world_state.cars.len(), route_state.cars.len());
+    for (world_car, route_car) in world_state.cars.iter().zip(&route_state.cars) {
+        assert_eq!(world_car.id, route_car.id);
+        assert_eq!(world_car.position, route_car.position);
+    }
+    Ok(())
+}
+
+/// Test that worlds are checked against the routes they list
+#[test]
+fn test_world_validation() -> Result<()> {
+    let check = |transfers: Vec<RouteTransfer>, second: &str| {
+        let routes = vec![world_route("ring", "route.toml", [0.0, 0.0]), world_route(second, "route4.toml", [300.0, 0.0])];
+        WorldConfig { routes, transfers }.load_routes(Path::new("."))
+    };
+    assert!(check(vec![transfer("ring", "exit_1", "town", "entry_west"), transfer("town", "west_exit", "ring", "entry_1")], "town").is_ok());
+    assert!(check(Vec::new(), "ring").is_err());
+    assert!(check(Vec::new(), "town/grid").is_err());
+    assert!(check(vec![transfer("ring", "exit_1", "city", "entry_west")], "town").is_err());
+    assert!(check(vec![transfer("ring", "exit_9", "town", "entry_west")], "town").is_err());
+    assert!(check(vec![transfer("ring", "exit_1", "town", "entry_9")], "town").is_err());
+    assert!(check(vec![transfer("ring", "exit_1", "town", "entry_west"), transfer("ring", "exit_1", "town", "entry_north")], "town").is_err());
+    assert!(WorldConfig { routes: Vec::new(), transfers: Vec::new() }.load_routes(Path::new(".")).is_err());
+    assert!(WorldConfig { routes: vec![world_route("ring", "missing.toml", [0.0, 0.0])], transfers: Vec::new() }.load_routes(Path::new(".")).is_err());
+    Ok(())
+}
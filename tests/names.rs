@@ -0,0 +1,65 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::{SimulationState, NameTable, Names, BehaviorNames, Car},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+
+/// Test that a table knows its names by their sorted position, does not find names it
+/// lacks, and keeps every id when names of another table are added to it
+#[test]
+fn test_ids_and_lookup() -> Result<()> {
+    let names: Names<BehaviorNames> = Names::new(["normal", "aggressive", "cautious", "normal"]);
+    assert_eq!(names.len(), 3);
+    let ids: Vec<_> = names.ids().collect();
+    assert_eq!(ids.iter().map(|&id| names.name(id)).collect::<Vec<_>>(), ["aggressive", "cautious", "normal"]);
+    assert_eq!(names.id("cautious"), Some(ids[1]));
+    assert_eq!(names.id("never_configured"), None);
+    
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let table = NameTable::new(&config.cars);
+    let mut other_cars = config.cars.clone();
+    let behavior = other_cars.behavior.values().next().unwrap().clone();
+    other_cars.behavior.insert("aaa_names_test".to_string(), behavior);
+    let other = NameTable::new(&other_cars);
+    assert!(other.covers(&table));
+    assert!(!table.covers(&other));
+    
+    let merged = table.including(&other);
+    assert!(merged.covers(&other));
+    for id in table.behaviors.ids() {
+        assert_eq!(merged.behavior(id), table.behavior(id));
+    }
+    assert_eq!(merged.behaviors.name(merged.behaviors.id("aaa_names_test").unwrap()), "aaa_names_test");
+    Ok(())
+}
+
+/// Test that cars carry the behaviors and types of the configuration, are saved with
+/// their ids alongside the table naming them, and are counted by behavior
+#[test]
+fn test_cars_carry_configured_names() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(4));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    while state.cars.len() < 15 {
+        backend.update(&mut state)?;
+    }
+    
+    assert_eq!(*state.names, NameTable::new(&config.cars));
+    for car in &state.cars {
+        assert!(config.cars.behavior.contains_key(state.names.behavior(car.behavior_type)));
+        assert!(config.cars.car_types.iter().any(|car_type| state.names.car_type(car.car_type) == car_type.id));
+        
+        let saved = serde_json::to_value(car)?;
+        let loaded: Car = serde_json::from_value(saved)?;
+        assert_eq!((loaded.behavior_type, loaded.car_type), (car.behavior_type, car.car_type));
+    }
+    
+    let counts = state.get_behavior_counts();
+    assert_eq!(counts.iter().map(|(_, count)| count).sum::<usize>(), state.cars.len());
+    for (behavior, count) in counts {
+        assert!(count > 0);
+        assert_eq!(state.cars.iter().filter(|car| car.behavior_type == behavior).count(), count);
+    }
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    export::{write_noise_csv, write_noise_geotiff, NOISE_NODATA},
+    simulation::{vehicle_noise_level, NoiseMap, SimulationState},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+use std::collections::HashSet;
+
+/// Noise map of the first `seconds` of a run on the ring, and the cells cars were seen in
+fn ring_noise(seconds: f32) -> Result<(NoiseMap, HashSet<(i32, i32)>)> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(11));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut noise = NoiseMap::new(&config.cars, 10.0);
+    let mut visited = HashSet::new();
+    while state.time < seconds {
+        backend.update(&mut state)?;
+        noise.record(&state);
+        visited.extend(state.cars.iter().map(|car| ((car.position.x / 10.0).floor() as i32, (car.position.y / 10.0).floor() as i32)));
+    }
+    Ok((noise, visited))
+}
+
+/// Test that vehicles are louder the faster they go, when accelerating and when heavy
+#[test]
+fn test_vehicle_noise_level() {
+    let cruising = vehicle_noise_level(25.0, 0.0, false);
+    assert!(vehicle_noise_level(30.0, 0.0, false) > cruising);
+    assert!(vehicle_noise_level(25.0, 1.5, false) > cruising);
+    assert!(vehicle_noise_level(25.0, 0.0, true) > cruising + 5.0);
+    assert!(vehicle_noise_level(0.0, 0.0, false) < vehicle_noise_level(5.0, 0.0, false));
+    assert!((90.0..115.0).contains(&cruising), "car at 90 km/h emits {} dB(A)", cruising);
+}
+
+/// Test that the noise map has a level for exactly the cells cars drove through
+#[test]
+fn test_noise_map_follows_traffic() -> Result<()> {
+    let (noise, visited) = ring_noise(60.0)?;
+    assert!((noise.duration() - 60.0).abs() < 0.1);
+    
+    let cells = noise.cells();
+    assert!(cells.len() > 50, "only {} cells with traffic", cells.len());
+    let mapped: HashSet<(i32, i32)> = cells.iter().map(|cell| (cell.col, cell.row)).collect();
+    assert_eq!(mapped, visited);
+    for cell in &cells {
+        assert!((40.0..120.0).contains(&cell.level), "cell at {:?} is at {} dB(A)", cell.center, cell.level);
+        assert_eq!(noise.level_at(cell.center), Some(cell.level));
+    }
+    let quiet = nalgebra::Point2::new(1.0e4, 1.0e4);
+    assert_eq!(noise.level_at(quiet), None);
+    Ok(())
+}
+
+/// Test that the noise map is written as CSV rows and as a GeoTIFF raster of the cells
+#[test]
+fn test_noise_map_export() -> Result<()> {
+    let (noise, _) = ring_noise(20.0)?;
+    let cells = noise.cells();
+    
+    let csv_path = std::env::temp_dir().join(format!("traffic-sim-noise-{}.csv", std::process::id()));
+    write_noise_csv(&noise, &csv_path)?;
+    let csv = std::fs::read_to_string(&csv_path)?;
+    std::fs::remove_file(&csv_path)?;
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("x,y,level_db"));
+    assert_eq!(lines.count(), cells.len());
+    
+    let tiff_path = std::env::temp_dir().join(format!("traffic-sim-noise-{}.tif", std::process::id()));
+    write_noise_geotiff(&noise, &tiff_path)?;
+    let tiff = std::fs::read(&tiff_path)?;
+    std::fs::remove_file(&tiff_path)?;
+    assert_eq!(&tiff[..4], b"II*\0");
+    
+    let u16_at = |offset: usize| u16::from_le_bytes([tiff[offset], tiff[offset + 1]]);
+    let u32_at = |offset: usize| u32::from_le_bytes([tiff[offset], tiff[offset + 1], tiff[offset + 2], tiff[offset + 3]]);
+    let directory = u32_at(4) as usize;
+    let tag = |wanted: u16| (0..u16_at(directory) as usize)
+        .map(|entry| directory + 2 + entry * 12)
+        .find(|&entry| u16_at(entry) == wanted)
+        .map(|entry| u32_at(entry + 8));
+    let (width, height) = (tag(256).expect("width") as usize, tag(257).expect("height") as usize);
+    let strip = tag(273).expect("strip offset") as usize;
+    assert!(tag(33922).is_some() && tag(34735).is_some(), "no georeferencing");
+    assert_eq!(tiff.len(), strip + width * height * 4);
+    
+    let pixels: Vec<f32> = tiff[strip..].chunks(4).map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).collect();
+    assert_eq!(pixels.iter().filter(|&&pixel| pixel != NOISE_NODATA).count(), cells.len());
+    let loudest = pixels.iter().copied().fold(f32::MIN, f32::max);
+    assert_eq!(Some(loudest), noise.max_level());
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use traffic_sim::{
+    config::{SimulationConfig, OdPair},
+    simulation::{SimulationState, RoadNetwork},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+use nalgebra::Point2;
+
+/// Test that route search prefers the shorter of two paths and respects edge direction
+#[test]
+fn test_shortest_path() {
+    let mut network = RoadNetwork::new();
+    let start = network.add_node(Point2::new(0.0, 0.0));
+    let detour = network.add_node(Point2::new(50.0, 80.0));
+    let direct = network.add_node(Point2::new(50.0, 5.0));
+    let goal = network.add_node(Point2::new(100.0, 0.0));
+    network.add_edge(start, detour);
+    network.add_edge(detour, goal);
+    network.add_edge(start, direct);
+    network.add_edge(direct, goal);
+    
+    assert_eq!(network.shortest_path(start, goal), Some(vec![start, direct, goal]));
+    assert_eq!(network.shortest_path(goal, start), None);
+}
+
+/// Test that donut cars are routed by the OD matrix and leave at their destination
+#[test]
+fn test_od_matrix_destinations() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    for entry in ["entry_1", "entry_2"] {
+        config.route.route.od_matrix.push(OdPair {
+            origin: entry.to_string(),
+            destination: "exit_2".to_string(),
+            weight: 1.0,
+        });
+    }
+    
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(7));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    for _ in 0..(120 * 60) {
+        backend.update(&mut state)?;
+        state.active_cars = state.cars.len() as u32;
+        assert!(state.cars.iter().all(|car| car.destination.as_deref() == Some("exit_2")));
+    }
+    
+    assert!(state.total_spawned > state.active_cars, "no routed car reached its exit");
+    
+    Ok(())
+}
+
+/// Test that cars from entries without OD matrix rows head for exits by exit weight and
+/// are counted at the exit they leave through
+#[test]
+fn test_exit_weights_assign_destinations() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    for car_type in &mut config.cars.car_types {
+        car_type.breakdown_probability = 0.0;
+    }
+    for exit in &mut config.route.route.exits {
+        exit.weight = Some(if exit.id == "exit_1" { 1.0 } else { 0.0 });
+    }
+    
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(7));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    for _ in 0..(120 * 60) {
+        backend.update(&mut state)?;
+        assert!(state.cars.iter().all(|car| car.destination.as_deref() == Some("exit_1")));
+    }
+    
+    assert!(state.exit_counts.get("exit_1").is_some_and(|&count| count > 0), "no car reached its exit");
+    assert_eq!(state.exit_counts.len(), 1);
+    
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use traffic_sim::graphics::{Viewport, PERSPECTIVE_LAYER_SPACING};
+use nalgebra::{Vector3, Vector4};
+use winit::event::{ElementState, MouseButton};
+use std::f32::consts::PI;
+
+fn settle(viewport: &mut Viewport) {
+    for _ in 0..240 {
+        viewport.update();
+    }
+}
+
+/// Clip-space depth of a world point, 0 at the near plane and 1 at the far one
+fn depth(viewport: &Viewport, point: Vector3<f32>) -> f32 {
+    let clip = viewport.get_view_matrix() * Vector4::new(point.x, point.y, point.z, 1.0);
+    clip.z / clip.w
+}
+
+/// Test that the untilted perspective camera shows the same ground as the top-down view
+#[test]
+fn test_untilted_matches_top_down() {
+    let mut viewport = Viewport::new(1200.0, 800.0);
+    let top_down = viewport.visible_corners();
+    
+    viewport.set_perspective(true);
+    viewport.set_tilt(0.0);
+    for (corner, expected) in viewport.visible_corners().iter().zip(top_down.iter()) {
+        assert!((corner - expected).norm() < 0.1, "{:?} vs {:?}", corner, expected);
+    }
+}
+
+/// Test that the tilted view looks ahead, round-trips screen points and keeps layers in order
+#[test]
+fn test_tilted_view() {
+    let mut viewport = Viewport::new(1200.0, 800.0);
+    viewport.set_position(Vector3::new(50.0, 20.0, 0.0));
+    viewport.set_perspective(true);
+    settle(&mut viewport);
+    assert!((viewport.tilt() - PI / 4.0).abs() < 1e-3);
+    
+    // The screen center stays on the position, the top of the screen shows more road further away
+    assert!((viewport.screen_to_world(600.0, 400.0) - Vector3::new(50.0, 20.0, 0.0)).norm() < 0.5);
+    let [top_left, top_right, bottom_right, bottom_left] = viewport.visible_corners();
+    assert!((top_right - top_left).norm() > (bottom_right - bottom_left).norm());
+    assert!(top_left.y > bottom_left.y);
+    
+    let point = Vector3::new(80.0, 60.0, 0.0);
+    let (x, y) = viewport.world_to_screen(&point);
+    assert!((viewport.screen_to_world(x, y) - point).norm() < 0.5);
+    
+    // Every point in view has a depth in range, a car roof is nearer than the road under it
+    let road = depth(&viewport, point);
+    let roof = depth(&viewport, point + Vector3::new(0.0, 0.0, 4.0 + 1.5 / PERSPECTIVE_LAYER_SPACING));
+    assert!((0.0..1.0).contains(&road) && (0.0..1.0).contains(&roof));
+    assert!(roof < road);
+}
+
+/// Test that right-drag orbits and tilts within limits, and only with the perspective camera
+#[test]
+fn test_right_drag_orbits() {
+    let mut viewport = Viewport::new(1200.0, 800.0);
+    viewport.handle_mouse_move(600.0, 400.0);
+    viewport.handle_mouse_input(ElementState::Pressed, MouseButton::Right);
+    viewport.handle_mouse_move(700.0, 300.0);
+    viewport.handle_mouse_input(ElementState::Released, MouseButton::Right);
+    settle(&mut viewport);
+    assert_eq!(viewport.tilt(), 0.0);
+    
+    viewport.set_perspective(true);
+    viewport.set_tilt(0.0);
+    let before = viewport.screen_to_world(600.0, 0.0);
+    viewport.handle_mouse_move(600.0, 400.0);
+    viewport.handle_mouse_input(ElementState::Pressed, MouseButton::Right);
+    viewport.handle_mouse_move(700.0, 200.0);
+    settle(&mut viewport);
+    assert!((viewport.tilt() - 200.0 * 0.005).abs() < 1e-3);
+    let after = viewport.screen_to_world(600.0, 0.0);
+    assert!((after - before).norm() > 10.0);
+    
+    // Dragging far up stops short of the horizon
+    viewport.handle_mouse_move(700.0, -5000.0);
+    viewport.handle_mouse_input(ElementState::Released, MouseButton::Right);
+    settle(&mut viewport);
+    assert!((viewport.tilt() - PI / 3.0).abs() < 1e-3);
+    
+    // Back to top-down drops the tilt and the orbit
+    let perspective_corners = viewport.visible_corners();
+    viewport.set_perspective(false);
+    assert_eq!(viewport.tilt(), 0.0);
+    let corners = viewport.visible_corners();
+    assert!((corners[0].x + 200.0).abs() < 1e-3 && (corners[0].y - 400.0 / 3.0).abs() < 1e-3);
+    assert_ne!(corners, perspective_corners);
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::{Car, Point, QueueDetector, SimulationEvent, SimulationState, road_length},
+    compute::{ComputeBackend, SimulationBackend},
+    export::{ExportFormat, QueueExporter, SummaryCollector},
+};
+use anyhow::Result;
+use common::{place_car, template_car};
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+/// A car in `lane` of the ring at `position` meters along the road, driving at `speed`
+fn ring_car(config: &SimulationConfig, template: &Car, id: usize, lane: u32, position: f32, speed: f32) -> Car {
+    let geometry = &config.route.route.geometry;
+    let angle = position / road_length(geometry).expect("route.toml is a ring") * TAU;
+    let radius = geometry.inner_radius + geometry.lane_width * (lane as f32 - 0.5);
+    let center = Point::new(geometry.center_x + radius * angle.cos(), geometry.center_y + radius * angle.sin());
+    let mut car = place_car(template, id, center, angle + FRAC_PI_2, speed);
+    car.current_lane = lane;
+    car.exit_ramp = None;
+    car
+}
+
+fn formed(state: &SimulationState) -> Vec<u32> {
+    state.events.iter().filter_map(|event| match event {
+        SimulationEvent::QueueFormed { queue, .. } => Some(*queue),
+        _ => None,
+    }).collect()
+}
+
+/// Test that queues are runs of enough slow cars close together in one lane, also across
+/// the start of the ring, that they keep their ids as they grow and dissipate once gone
+#[test]
+fn test_queue_detection() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let template = template_car(&config)?;
+    let road = road_length(&config.route.route.geometry).expect("route.toml is a ring");
+    let mut detector = QueueDetector::new(&config.cars.queues, &config.route.route.geometry);
+    let mut state = SimulationState::new(1.0);
+    
+    let mut cars = vec![
+        // Four slow cars around the start of the ring
+        (1, road - 15.0, 2.0),
+        (1, road - 5.0, 1.0),
+        (1, 5.0, 0.0),
+        (1, 15.0, 3.0),
+        // Two slow cars, then a gap too wide to close the queue, then three more
+        (1, 100.0, 1.0),
+        (1, 110.0, 1.0),
+        (1, 150.0, 1.0),
+        (1, 160.0, 1.0),
+        (1, 170.0, 1.0),
+        // Close together but moving
+        (1, 300.0, 20.0),
+        (1, 310.0, 20.0),
+        (1, 320.0, 20.0),
+        // Slow but alone in their lane
+        (2, 10.0, 0.0),
+        (2, 20.0, 0.0),
+    ];
+    let place = |cars: &[(u32, f32, f32)]| -> Vec<Car> {
+        cars.iter().enumerate().map(|(id, &(lane, position, speed))| ring_car(&config, &template, id, lane, position, speed)).collect()
+    };
+    state.cars = place(&cars);
+    detector.update(&mut state);
+    assert_eq!(state.queues.len(), 2, "{:?}", state.queues);
+    assert_eq!(formed(&state).len(), 2);
+    let around_start = state.queues.iter().find(|queue| queue.cars == 4).expect("queue across the start of the ring");
+    assert_eq!(around_start.lane, 1);
+    assert!((around_start.tail - (road - 15.0)).abs() < 0.1 && (around_start.head - 15.0).abs() < 0.1);
+    assert!((around_start.length - 30.0).abs() < 0.1);
+    let (first_id, second_id) = (around_start.id, state.queues.iter().find(|queue| queue.cars == 3).expect("queue of three").id);
+    
+    // A car joins the tail of the first queue
+    cars.push((1, road - 25.0, 0.0));
+    state.events.clear();
+    state.time = 1.0;
+    state.cars = place(&cars);
+    detector.update(&mut state);
+    assert!(formed(&state).is_empty());
+    let grown = state.queues.iter().find(|queue| queue.id == first_id).expect("queue kept its id");
+    assert_eq!(grown.cars, 5);
+    assert!((grown.length - 40.0).abs() < 0.1);
+    assert!(grown.growth_rate > 0.0);
+    assert_eq!(grown.start_time, 0.0);
+    
+    // Both queues clear; they are only dissipated after a grace period
+    state.cars.clear();
+    state.events.clear();
+    state.time = 2.0;
+    detector.update(&mut state);
+    assert!(state.queues.is_empty() && state.events.is_empty());
+    state.time = 4.0;
+    detector.update(&mut state);
+    let mut dissipated: Vec<(u32, f32)> = state.events.iter().filter_map(|event| match event {
+        SimulationEvent::QueueDissipated { queue, max_length, .. } => Some((*queue, *max_length)),
+        _ => None,
+    }).collect();
+    dissipated.sort_by_key(|(queue, _)| *queue);
+    assert_eq!(dissipated.iter().map(|(queue, _)| *queue).collect::<Vec<_>>(), {
+        let mut ids = vec![first_id, second_id];
+        ids.sort();
+        ids
+    });
+    assert!(dissipated.iter().any(|&(queue, max_length)| queue == first_id && (max_length - 40.0).abs() < 0.1));
+    Ok(())
+}
+
+/// Test that queues found in a run hold together, are summarized and exported
+#[test]
+fn test_queues_in_run() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    // The default traffic flows freely; count any pair of cars below highway speed
+    // within a few seconds of each other, so the run has queues to find
+    config.cars.queues.speed_threshold = 28.0;
+    config.cars.queues.max_gap = 80.0;
+    config.cars.queues.min_cars = 2;
+    let road = road_length(&config.route.route.geometry).expect("route.toml is a ring");
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(11));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut collector = SummaryCollector::new(&config.route, &state);
+    let path = std::env::temp_dir().join(format!("traffic-sim-queues-{}.csv", std::process::id()));
+    let mut exporter = QueueExporter::create(&path, ExportFormat::Csv)?;
+    let mut formed_count = 0;
+    while state.time < 60.0 {
+        backend.update(&mut state)?;
+        collector.record(&state);
+        exporter.record(&state)?;
+        formed_count += formed(&state).len() as u32;
+        for queue in &state.queues {
+            assert!(queue.cars >= config.cars.queues.min_cars);
+            assert!(queue.length >= 0.0 && queue.length < road);
+            assert!(queue.max_length >= queue.length);
+            assert!((queue.head - queue.tail - queue.length).rem_euclid(road) < 0.1);
+        }
+        let mut ids: Vec<u32> = state.queues.iter().map(|queue| queue.id).collect();
+        ids.dedup();
+        assert_eq!(ids.len(), state.queues.len(), "queue ids repeat");
+    }
+    exporter.flush()?;
+    assert!(formed_count > 0, "no queue formed");
+    
+    let summary = collector.finish(&state).queues;
+    assert_eq!(summary.formed, formed_count);
+    assert!(summary.max_length > 0.0 && summary.max_duration > 0.0);
+    
+    let contents = std::fs::read_to_string(&path)?;
+    std::fs::remove_file(&path)?;
+    let mut lines = contents.lines();
+    assert_eq!(lines.next(), Some("time,queue,lane,head,tail,length,cars,growth_rate,start_time,max_length"));
+    let rows: Vec<&str> = lines.collect();
+    assert!(!rows.is_empty());
+    assert!(rows.iter().all(|row| row.split(',').count() == 10));
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use traffic_sim::{
+    config::{SimulationConfig, RampMeterConfig},
+    simulation::SimulationState,
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+
+/// Test that fixed-rate ramp meters let at most one car per interval onto the road and
+/// queue the rest
+#[test]
+fn test_fixed_rate_meter_limits_entry_flow() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let interval = 5.0;
+    for entry in &mut config.route.route.entries {
+        entry.metering = Some(RampMeterConfig {
+            detector: None,
+            interval,
+            min_interval: interval,
+            max_interval: interval,
+            target_occupancy: 0.2,
+            gain: 70.0,
+        });
+    }
+    let entries = config.route.route.entries.len() as u32;
+    
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(8));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let duration = 30.0;
+    while state.time < duration {
+        backend.update(&mut state)?;
+    }
+    
+    let releases_per_entry = (duration / interval) as u32 + 1;
+    assert!(state.total_spawned > 0);
+    assert!(state.total_spawned <= entries * releases_per_entry, "{} cars spawned", state.total_spawned);
+    assert_eq!(state.ramp_meters.len(), entries as usize);
+    assert!(state.ramp_meters.iter().all(|meter| meter.queue > 0));
+    
+    Ok(())
+}
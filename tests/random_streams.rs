@@ -0,0 +1,96 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::{CarId, RandomStream, SimulationState, philox4x32, unit_float, standard_normal},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+
+/// Id, car type, behavior and destination of a spawned car
+type SpawnedCar = (CarId, String, String, Option<String>);
+
+/// Every car spawned in the first `seconds`
+fn spawned_cars(config: &SimulationConfig, seconds: f32) -> Result<Vec<SpawnedCar>> {
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(11));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut spawned: Vec<SpawnedCar> = Vec::new();
+    while state.time < seconds {
+        backend.update(&mut state)?;
+        for car in &state.cars {
+            if !spawned.iter().any(|(id, ..)| *id == car.id) {
+                spawned.push((car.id, state.names.car_type(car.car_type).to_string(), state.names.behavior(car.behavior_type).to_string(), car.destination.clone()));
+            }
+        }
+    }
+    Ok(spawned)
+}
+
+/// Test that drawing more lane change decisions leaves the spawn stream untouched, so
+/// the same cars enter the road with the same types, behaviors and destinations. Entries
+/// spawn seconds apart, as a blocked entry would hold its car back and change the order.
+#[test]
+fn test_behavior_changes_do_not_perturb_spawning() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    for interval in &mut config.cars.traffic_flow.entry_intervals {
+        interval.min_interval = 1.0;
+        interval.max_interval = 2.0;
+    }
+    let mut busier = config.clone();
+    for behavior in busier.cars.behavior.values_mut() {
+        behavior.lane_change_frequency *= 4.0;
+    }
+    
+    let original = spawned_cars(&config, 20.0)?;
+    let changed = spawned_cars(&busier, 20.0)?;
+    let shared = original.len().min(changed.len());
+    assert!(shared > 10);
+    assert_eq!(original[..shared], changed[..shared]);
+    
+    Ok(())
+}
+
+/// Test that child seeds are stable and differ between streams
+#[test]
+fn test_stream_seeds() {
+    let streams = [RandomStream::Spawn, RandomStream::Behavior, RandomStream::Breakdown, RandomStream::Despawn, RandomStream::Noise];
+    for (i, a) in streams.iter().enumerate() {
+        assert_eq!(a.seed(42), a.seed(42));
+        assert_ne!(a.seed(42), a.seed(43));
+        for b in &streams[i + 1..] {
+            assert_ne!(a.seed(42), b.seed(42));
+        }
+    }
+}
+
+/// Test the counter-based generator against the Philox4x32-10 known answers, which the
+/// OpenCL kernel has to reproduce for its draws to match the CPU
+#[test]
+fn test_philox_known_answers() {
+    assert_eq!(philox4x32([0; 4], [0; 2]), [0x6627e8d5, 0xe169c58d, 0xbc57ac4c, 0x9b00dbd8]);
+    assert_eq!(philox4x32([u32::MAX; 4], [u32::MAX; 2]), [0x408f276d, 0x41c83b0e, 0xa20bc7c6, 0x6d5451fd]);
+    assert_eq!(
+        philox4x32([0x243f6a88, 0x85a308d3, 0x13198a2e, 0x03707344], [0xa4093822, 0x299f31d0]),
+        [0xd16cfe09, 0x94fdcceb, 0x5001e420, 0x24126ea1]
+    );
+    assert_eq!(RandomStream::Noise.key(Some(42)), RandomStream::Noise.key(Some(42)));
+    assert_ne!(RandomStream::Noise.key(Some(42)), RandomStream::Behavior.key(Some(42)));
+}
+
+/// Test that the draws turned into floats are uniform and standard normal
+#[test]
+fn test_draw_distributions() {
+    assert_eq!(unit_float(0), 0.0);
+    assert!(unit_float(u32::MAX) < 1.0);
+    assert!((standard_normal(0x80000000, 0) - 1.1774099).abs() < 1e-5);
+    
+    let key = RandomStream::Noise.key(Some(7));
+    let samples: Vec<f32> = (0..20_000u32)
+        .map(|i| {
+            let [a, b, ..] = philox4x32([i, 0, 0, 0], key);
+            standard_normal(a, b)
+        })
+        .collect();
+    let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+    let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / samples.len() as f32;
+    assert!(mean.abs() < 0.05, "mean {}", mean);
+    assert!((variance - 1.0).abs() < 0.05, "variance {}", variance);
+}
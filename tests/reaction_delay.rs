@@ -0,0 +1,91 @@
+mod common;
+
+use traffic_sim::{
+    config::{DemandProfile, SimulationConfig},
+    simulation::{Lead, Perception, SimulationState},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+use common::ring_point;
+
+/// Test that drivers recall the car ahead as it was the delay ago, and their first glimpse
+/// of it until they have been driving that long
+#[test]
+fn test_perception_recall() {
+    let mut perception = Perception::default();
+    let lead = |distance| Some(Lead { distance, speed: 20.0 });
+    assert_eq!(perception.recall(0.0, 1.0, lead(50.0)), lead(50.0));
+    perception.record(0.0, lead(100.0), 1.5);
+    assert_eq!(perception.recall(0.5, 1.0, lead(50.0)), lead(100.0));
+    
+    for step in 0..=120 {
+        let time = step as f32 / 60.0;
+        perception.record(time, lead(100.0 - time), 1.5);
+    }
+    let recalled = perception.recall(2.0, 1.0, lead(0.0)).expect("a car ahead");
+    assert!((recalled.distance - 99.0).abs() < 0.11);
+    assert_eq!(perception.recall(2.0, 0.0, None), lead(98.0));
+    assert_eq!(perception.recall(2.0, 5.0, lead(0.0)), perception.recall(2.0, 1.5, lead(0.0)));
+    
+    perception.distracted_until = Some(3.0);
+    assert!(perception.is_distracted(2.5));
+    assert!(!perception.is_distracted(3.0));
+}
+
+/// Seconds after the car ahead halts before a cautious follower `gap_degrees` behind it,
+/// far enough back to be matching its speed, slows down
+fn response_time(delay: bool, gap_degrees: f32) -> Result<f32> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    config.cars.reaction.delay = delay;
+    config.cars.traffic_flow.demand_profiles = config.route.route.entries.iter()
+        .map(|entry| DemandProfile { entry_id: entry.id.clone(), points: vec![[0.0, 0.0]], peak: None })
+        .collect();
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(4));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    
+    let leader = backend.spawn_car_at(ring_point(155.25, 20.0 + gap_degrees), "cautious", Some("sedan"), &mut state)?;
+    let follower = backend.spawn_car_at(ring_point(155.25, 20.0), "cautious", Some("sedan"), &mut state)?;
+    for _ in 0..60 {
+        backend.update(&mut state)?;
+    }
+    let speed = state.get_car(follower).expect("follower").velocity.magnitude();
+    state.get_car_mut(leader).expect("leader").crashed = true;
+    let halted = state.time;
+    
+    while state.time < halted + 10.0 {
+        backend.update(&mut state)?;
+        if state.get_car(follower).expect("follower").velocity.magnitude() < 0.9 * speed {
+            return Ok(state.time - halted);
+        }
+    }
+    panic!("follower never slowed down");
+}
+
+/// Test that with reaction delay on a follower brakes a reaction time later than without,
+/// and that distractions only happen with it on
+#[test]
+fn test_delayed_braking() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let reaction_time = config.cars.behavior["cautious"].reaction_time;
+    
+    let instant = response_time(false, 22.0)?;
+    let delayed = response_time(true, 22.0)?;
+    assert!(instant < 0.1, "instant response took {:.2}s", instant);
+    assert!((delayed - instant - reaction_time).abs() < 0.15, "delayed response took {:.2}s", delayed);
+    
+    let mut config = config;
+    config.cars.reaction.distraction_rate = 600.0;
+    let distracted = |delay: bool| -> Result<usize> {
+        let mut config = config.clone();
+        config.cars.reaction.delay = delay;
+        let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(4));
+        let mut state = SimulationState::new(1.0 / 60.0);
+        for _ in 0..300 {
+            backend.update(&mut state)?;
+        }
+        Ok(state.cars.iter().filter(|car| car.perception.is_distracted(state.time)).count())
+    };
+    assert!(distracted(true)? > 0);
+    assert_eq!(distracted(false)?, 0);
+    Ok(())
+}
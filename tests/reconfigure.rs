@@ -0,0 +1,51 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::{SimulationState, SimulationEvent},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+use std::collections::HashSet;
+use std::sync::mpsc;
+
+/// Test that a backend rebuilt with new settings carries on from the running state: a car
+/// limit below the current count stops spawning, car ids keep counting up and observers
+/// stay registered
+#[test]
+fn test_reconfigure_continues_running_simulation() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(5));
+    let (sender, receiver) = mpsc::channel();
+    backend.add_observer(Box::new(move |event| {
+        let _ = sender.send(event.clone());
+    }));
+    
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut ids = HashSet::new();
+    while state.time < 30.0 {
+        backend.update(&mut state)?;
+        ids.extend(state.cars.iter().map(|car| car.id));
+    }
+    let cars_before = state.cars.len() as u32;
+    assert!(cars_before > 2);
+    
+    let mut cars = config.cars.clone();
+    cars.simulation.total_cars = 1;
+    backend.reconfigure(cars, config.route.clone(), &state, Some(5))?;
+    receiver.try_iter().count();
+    
+    let mut events_after = 0;
+    while state.time < 60.0 {
+        backend.update(&mut state)?;
+        ids.extend(state.cars.iter().map(|car| car.id));
+        for event in receiver.try_iter() {
+            assert!(!matches!(event, SimulationEvent::CarSpawned { .. }), "spawned over the car limit");
+            events_after += 1;
+        }
+    }
+    
+    assert!(state.cars.len() as u32 <= cars_before);
+    assert!(events_after > 0, "observer saw no events after reconfiguring");
+    assert_eq!(ids.len() as u32, state.total_spawned, "car ids were reused");
+    
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use traffic_sim::graphics::Viewport;
+use nalgebra::Vector4;
+
+fn depth(viewport: &Viewport, z: f32) -> f32 {
+    let clip = viewport.get_view_matrix() * Vector4::new(10.0, -20.0, z, 1.0);
+    clip.z / clip.w
+}
+
+/// Test that the projection maps the scene's heights into wgpu's [0, 1] depth range
+#[test]
+fn test_depth_range() {
+    let viewport = Viewport::new(1200.0, 800.0);
+    assert!((depth(&viewport, 100.0) - 0.0).abs() < 1e-6);
+    assert!((depth(&viewport, -100.0) - 1.0).abs() < 1e-6);
+    
+    // Every layer from the road surface to the signal heads is inside the clip volume
+    for z in 0..=6 {
+        let d = depth(&viewport, z as f32);
+        assert!((0.0..=1.0).contains(&d), "height {} has depth {}", z, d);
+    }
+}
+
+/// Test that higher layers are nearer the camera at any zoom, so they pass the depth test
+/// over lower ones
+#[test]
+fn test_higher_layers_are_nearer() {
+    let mut viewport = Viewport::new(1200.0, 800.0);
+    for zoom in [0.05, 1.0, 40.0] {
+        viewport.set_zoom(zoom);
+        for z in 0..6 {
+            assert!(depth(&viewport, z as f32 + 1.0) < depth(&viewport, z as f32));
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::SimulationState,
+    compute::{ComputeBackend, SimulationBackend},
+    replay::{ReplayRecorder, ReplayPlayer},
+};
+use anyhow::Result;
+
+/// Test that a recorded run plays back frame-for-frame with the same configuration
+#[test]
+fn test_replay_roundtrip() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let seed = Some(4242u64);
+    let path = std::env::temp_dir().join(format!("traffic-sim-roundtrip-{}.replay", std::process::id()));
+    
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), seed);
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut recorded = Vec::new();
+    
+    let mut recorder = ReplayRecorder::create(&path, &config, seed)?;
+    for _ in 0..180 {
+        backend.update(&mut state)?;
+        recorder.record(&state)?;
+        recorded.push(state.clone());
+    }
+    recorder.flush()?;
+    drop(recorder);
+    
+    let mut player = ReplayPlayer::open(&path)?;
+    assert_eq!(player.seed(), seed);
+    assert_eq!(player.config().route.route.name, config.route.route.name);
+    assert_eq!(player.config().cars.simulation.total_cars, config.cars.simulation.total_cars);
+    
+    for expected in &recorded {
+        let frame = player.next_frame()?.expect("replay ended early");
+        assert_eq!(frame.time, expected.time);
+        assert_eq!(frame.total_spawned, expected.total_spawned);
+        assert_eq!(frame.cars.len(), expected.cars.len());
+        for (a, b) in frame.cars.iter().zip(&expected.cars) {
+            assert_eq!(a.id, b.id);
+            assert_eq!(a.position, b.position);
+            assert_eq!(a.behavior_type, b.behavior_type);
+        }
+    }
+    assert!(player.next_frame()?.is_none());
+    
+    // Rewinding starts playback over from the first frame
+    player.rewind()?;
+    let first = player.next_frame()?.expect("replay is empty after rewind");
+    assert_eq!(first.time, recorded[0].time);
+    
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    sweep::{MetricEstimate, SUMMARY_METRICS, default_aggregate_output, replication_seeds, run_replication, run_replications, summary_metrics, t_critical_95},
+};
+use anyhow::Result;
+
+/// Test the mean, sample standard deviation and t-based 95% confidence interval
+#[test]
+fn test_confidence_interval() {
+    assert_eq!(t_critical_95(1), 12.706);
+    assert_eq!(t_critical_95(9), 2.262);
+    assert!(t_critical_95(45) > t_critical_95(60) && t_critical_95(60) > t_critical_95(1000));
+    assert_eq!(t_critical_95(1000), 1.96);
+    
+    let estimate = MetricEstimate::from_samples("delay", &[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+    assert_eq!(estimate.runs, 8);
+    assert!((estimate.mean - 5.0).abs() < 1e-9);
+    let std_dev = (32.0f64 / 7.0).sqrt();
+    assert!((estimate.std_dev - std_dev).abs() < 1e-9);
+    assert!((estimate.half_width - 2.365 * std_dev / 8.0f64.sqrt()).abs() < 1e-9);
+    assert!((estimate.high() - estimate.low() - 2.0 * estimate.half_width).abs() < 1e-9);
+    
+    let single = MetricEstimate::from_samples("delay", &[3.0]);
+    assert_eq!((single.mean, single.half_width), (3.0, 0.0));
+}
+
+/// Test that every seed runs once, in parallel giving the same results as on its own, and
+/// that the per-run and aggregate files are written
+#[test]
+fn test_replications() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let directory = std::env::temp_dir().join(format!("traffic-sim-replications-{}", std::process::id()));
+    let output = directory.join("replications.csv");
+    let aggregate_output = default_aggregate_output(&output);
+    assert_eq!(aggregate_output, directory.join("replications_aggregate.csv"));
+    
+    let seeds = replication_seeds(40, 3);
+    assert_eq!(seeds, vec![40, 41, 42]);
+    let (replications, estimates) = run_replications(&config, &seeds, 10.0, 1.0 / 60.0, 2, &output, &aggregate_output)?;
+    assert_eq!(replications.iter().map(|replication| (replication.index, replication.seed)).collect::<Vec<_>>(), vec![(0, 40), (1, 41), (2, 42)]);
+    assert_eq!(summary_metrics(&run_replication(&config, 41, 10.0, 1.0 / 60.0)?), summary_metrics(&replications[1].summary));
+    
+    assert_eq!(estimates.len(), SUMMARY_METRICS.len());
+    let spawned: Vec<f64> = replications.iter().map(|replication| replication.summary.spawned as f64).collect();
+    assert_eq!(estimates[0], MetricEstimate::from_samples("spawned", &spawned));
+    assert!(estimates[0].mean > 0.0);
+    
+    let per_run = std::fs::read_to_string(&output)?;
+    let lines: Vec<&str> = per_run.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].starts_with("run,seed,spawned,exited"));
+    let aggregate = std::fs::read_to_string(&aggregate_output)?;
+    let lines: Vec<&str> = aggregate.lines().collect();
+    assert_eq!(lines[0], "metric,runs,mean,std_dev,ci_low,ci_high,half_width");
+    assert_eq!(lines.len(), SUMMARY_METRICS.len() + 1);
+    assert!(lines[1].starts_with("spawned,3,"));
+    
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::{CarId, SimulationState},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+
+/// Id, position, speed and lane of a car
+type CarSnapshot = (CarId, [f32; 2], f32, u32);
+
+/// Every car after running `seconds` from a fresh state
+fn run(backend: &mut ComputeBackend, seconds: f32) -> Result<Vec<CarSnapshot>> {
+    let mut state = SimulationState::new(1.0 / 60.0);
+    while state.time < seconds {
+        backend.update(&mut state)?;
+    }
+    Ok(state.cars.iter()
+        .map(|car| (car.id, [car.position.x, car.position.y], car.velocity.magnitude(), car.current_lane))
+        .collect())
+}
+
+/// Test that a reset backend repeats the run of a freshly created one with the same seed,
+/// and a different seed gives a different run
+#[test]
+fn test_reset_repeats_fresh_run() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let new_backend = |seed| ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(seed));
+    let fresh = run(&mut new_backend(21), 30.0)?;
+    assert!(fresh.len() > 10);
+    
+    let mut backend = new_backend(21);
+    run(&mut backend, 45.0)?;
+    backend.reset(Some(21));
+    assert_eq!(run(&mut backend, 30.0)?, fresh);
+    
+    backend.reset(Some(22));
+    assert_ne!(run(&mut backend, 30.0)?, fresh);
+    Ok(())
+}
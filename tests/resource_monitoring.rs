@@ -0,0 +1,58 @@
+use traffic_sim::simulation::{parse_cpu_seconds, parse_resident_bytes, PerformanceTracker, ProcessMonitor};
+
+/// Test that utime and stime are read after the command name, even one with spaces
+#[test]
+fn test_parse_cpu_seconds() {
+    let stat = "1234 (traffic sim) S 1 1234 1234 0 -1 4194560 2000 0 0 0 250 50 0 0 20 0 8 0 100 1000000 5000";
+    assert_eq!(parse_cpu_seconds(stat), Some(3.0));
+    assert_eq!(parse_cpu_seconds("1234 (truncated) S 1"), None);
+    assert_eq!(parse_cpu_seconds(""), None);
+}
+
+/// Test that the resident set size is read from the status file in bytes
+#[test]
+fn test_parse_resident_bytes() {
+    let status = "Name:\ttraffic-sim\nVmPeak:\t  900000 kB\nVmRSS:\t  204800 kB\nThreads:\t8\n";
+    assert_eq!(parse_resident_bytes(status), Some(200 * 1024 * 1024));
+    assert_eq!(parse_resident_bytes("Name:\ttraffic-sim\n"), None);
+}
+
+/// Test that this process reports its memory and a CPU share within bounds
+#[cfg(target_os = "linux")]
+#[test]
+fn test_process_monitor() {
+    let mut monitor = ProcessMonitor::new();
+    let first = monitor.sample().expect("/proc is readable");
+    assert!(first.memory_usage > 0);
+    assert_eq!(first.cpu_utilization, 0.0);
+    
+    // Keep a core busy so the next sample sees CPU time pass
+    let start = std::time::Instant::now();
+    let mut x = 0u64;
+    while start.elapsed() < std::time::Duration::from_millis(50) {
+        x = std::hint::black_box(x.wrapping_mul(31).wrapping_add(7));
+    }
+    let second = monitor.sample().expect("/proc is readable");
+    assert!((0.0..=1.0).contains(&second.cpu_utilization));
+}
+
+/// Test that frames feed a resource history sampled at most once per interval
+#[test]
+fn test_tracker_samples_resources() {
+    let mut tracker = PerformanceTracker::new(10);
+    assert!(tracker.resource_history().is_empty());
+    
+    tracker.set_gpu_memory(4096);
+    tracker.start_frame();
+    tracker.end_frame();
+    // A second frame right after falls within the same sample interval
+    tracker.start_frame();
+    tracker.end_frame();
+    
+    assert_eq!(tracker.resource_history().len(), 1);
+    let sample = tracker.latest_resources().expect("the first frame takes a sample");
+    assert_eq!(sample.gpu_memory, 4096);
+    if cfg!(target_os = "linux") {
+        assert!(sample.process.expect("/proc is readable").memory_usage > 0);
+    }
+}
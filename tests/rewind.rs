@@ -0,0 +1,58 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::{RewindBuffer, SimulationState},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+
+/// Test that snapshots are taken every tenth of a second, within the memory budget, and
+/// that continuing from an earlier one drops the snapshots after it
+#[test]
+fn test_rewind_buffer() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(4));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut rewind = RewindBuffer::new(64.0);
+    assert!(rewind.time_range().is_none());
+    
+    while state.time < 20.0 {
+        backend.update(&mut state)?;
+        rewind.record(&state)?;
+    }
+    let (start, end) = rewind.time_range().expect("snapshots");
+    assert!(start < 0.1 && end > 19.8);
+    assert!((rewind.len() as f32 - 200.0).abs() <= 2.0);
+    
+    // The snapshot at or before the asked time, with the cars as they were
+    let past = rewind.state_at(10.05)?.expect("snapshot");
+    assert!(past.time <= 10.05 && past.time > 9.9);
+    assert!(past.cars.len() <= state.cars.len());
+    assert_eq!(rewind.state_at(-1.0)?.map(|state| state.time), Some(start));
+    
+    // Resuming from the past rewrites the future
+    let mut resumed = past.clone();
+    backend.restore_checkpoint(&resumed, Some(4));
+    backend.update(&mut resumed)?;
+    rewind.record(&resumed)?;
+    assert!(rewind.time_range().expect("snapshots").1 <= resumed.time);
+    
+    // Over budget the oldest snapshots go first
+    let before = rewind.len();
+    rewind.set_budget(rewind.memory_usage() as f32 / (2.0 * 1024.0 * 1024.0));
+    assert!(rewind.len() < before && !rewind.is_empty());
+    assert!(rewind.time_range().expect("snapshots").0 > start);
+    
+    // The live state can be captured at any time, once
+    let mut live = resumed.clone();
+    backend.update(&mut live)?;
+    rewind.capture(&live)?;
+    let count = rewind.len();
+    rewind.capture(&live)?;
+    assert_eq!(rewind.len(), count);
+    assert_eq!(rewind.time_range().map(|(_, end)| end), Some(live.time));
+    
+    let mut off = RewindBuffer::new(0.0);
+    off.record(&state)?;
+    assert!(off.is_empty() && !off.is_enabled());
+    Ok(())
+}
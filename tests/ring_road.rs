@@ -0,0 +1,87 @@
+use traffic_sim::{
+    config::{ConfigOverride, SimulationConfig},
+    simulation::{SimulationState, SimulationEvent},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+
+fn ring_config(cars: u32) -> Result<SimulationConfig> {
+    let overrides = [format!("cars.simulation.ring_cars={}", cars).parse::<ConfigOverride>()?];
+    SimulationConfig::load_with_overrides("route.toml", "cars.toml", &overrides)
+}
+
+/// Test that a closed ring starts with its cars spread evenly over the lanes and around
+/// each lane, all at one speed and heading nowhere, and starts over the same way on reset
+#[test]
+fn test_ring_cars_placed_evenly() -> Result<()> {
+    let config = ring_config(60)?;
+    let geometry = config.route.route.geometry.clone();
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(4));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    backend.update(&mut state)?;
+    assert_eq!(state.cars.len(), 60);
+    let spawned = state.events.iter().filter(|event| matches!(event, SimulationEvent::CarSpawned { .. })).count();
+    assert_eq!(spawned, 60);
+    
+    for lane in 1..=geometry.lane_count {
+        let mut angles: Vec<f32> = state.cars.iter()
+            .filter(|car| car.current_lane == lane)
+            .map(|car| (car.position.y - geometry.center_y).atan2(car.position.x - geometry.center_x))
+            .collect();
+        assert_eq!(angles.len(), 10, "lane {}", lane);
+        angles.sort_by(f32::total_cmp);
+        let even = std::f32::consts::TAU / 10.0;
+        for pair in angles.windows(2) {
+            assert!((pair[1] - pair[0] - even).abs() < 0.01, "lane {} cars {} rad apart", lane, pair[1] - pair[0]);
+        }
+    }
+    assert!(state.cars.iter().all(|car| car.destination.is_none()));
+    let speeds: Vec<f32> = state.cars.iter().map(|car| car.speed_history[0]).collect();
+    assert!(speeds.iter().all(|speed| *speed == speeds[0] && *speed > 0.0), "{:?}", speeds);
+    
+    backend.reset(Some(4));
+    let mut again = SimulationState::new(1.0 / 60.0);
+    backend.update(&mut again)?;
+    assert_eq!(again.cars.len(), 60);
+    for (car, other) in again.cars.iter().zip(&state.cars) {
+        assert_eq!((car.id, car.position), (other.id, other.position));
+    }
+    Ok(())
+}
+
+/// Test that a closed ring keeps every car it started with, past the time the despawn
+/// lottery would start taking them
+#[test]
+fn test_ring_conserves_cars() -> Result<()> {
+    let config = ring_config(48)?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(9));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    backend.update(&mut state)?;
+    let mut ids: Vec<_> = state.cars.iter().map(|car| car.id).collect();
+    ids.sort();
+    
+    while state.time < 660.0 {
+        backend.update(&mut state)?;
+        assert!(!state.events.iter().any(|event| matches!(event, SimulationEvent::CarSpawned { .. } | SimulationEvent::CarExited { .. })));
+        assert_eq!(state.cars.len(), 48);
+    }
+    let mut remaining: Vec<_> = state.cars.iter().map(|car| car.id).collect();
+    remaining.sort();
+    assert_eq!(remaining, ids);
+    assert_eq!(state.total_spawned, 48);
+    Ok(())
+}
+
+/// Test that rings are only placed on donuts, within the car limit and with room for
+/// every car
+#[test]
+fn test_ring_config_checks() -> Result<()> {
+    assert!(ring_config(0).is_err());
+    assert!(ring_config(100_000).is_err(), "more cars than fit around the ring");
+    let limited = ["cars.simulation.total_cars=10", "cars.simulation.ring_cars=20"]
+        .map(|text| text.parse::<ConfigOverride>().unwrap());
+    assert!(SimulationConfig::load_with_overrides("route.toml", "cars.toml", &limited).is_err());
+    let ring = ["cars.simulation.ring_cars=20".parse::<ConfigOverride>()?];
+    assert!(SimulationConfig::load_with_overrides("route2.toml", "cars.toml", &ring).is_err());
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use traffic_sim::{
+    config::{SimulationConfig, WorldBounds},
+    simulation::SimulationState,
+    compute::{ComputeBackend, SimulationBackend},
+    export::{SummaryCollector, TravelTimeSummary},
+};
+use anyhow::Result;
+
+/// Test that the run summary agrees with the final state and that the per-behavior
+/// breakdowns add up to the totals
+#[test]
+fn test_summary_totals() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(5));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut collector = SummaryCollector::new(&config.route, &state);
+    while state.time < 90.0 {
+        backend.update(&mut state)?;
+        state.active_cars = state.cars.len() as u32;
+        collector.record(&state);
+    }
+    
+    let summary = collector.finish(&state);
+    assert_eq!(summary.spawned, state.total_spawned);
+    assert_eq!(summary.exited, state.exit_counts.values().sum::<u32>());
+    assert_eq!(summary.spawned, summary.exited + summary.removed + summary.active_at_end);
+    assert!(summary.exited > 0);
+    assert!(summary.travel_time.p95 >= summary.travel_time.mean * 0.5);
+    assert!((0.0..=1.0).contains(&summary.slow_share));
+    
+    let behaviors = summary.behaviors.values();
+    assert_eq!(behaviors.clone().map(|behavior| behavior.spawned).sum::<u32>(), summary.spawned);
+    assert_eq!(behaviors.clone().map(|behavior| behavior.exited).sum::<u32>(), summary.exited);
+    assert_eq!(behaviors.clone().map(|behavior| behavior.lane_changes).sum::<u32>(), summary.lane_changes);
+    assert_eq!(behaviors.map(|behavior| behavior.cars_in_collisions).sum::<u32>(), summary.collisions * 2);
+    
+    let path = std::env::temp_dir().join(format!("traffic-sim-summary-{}.json", std::process::id()));
+    summary.write_json(&path)?;
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+    assert_eq!(json["spawned"], summary.spawned);
+    assert!(json["behaviors"]["normal"]["travel_time"]["p95"].is_number());
+    std::fs::remove_file(&path)?;
+    
+    Ok(())
+}
+
+/// Test that cloverleaf cars are all accounted for, also those that leave in the tick
+/// they spawn in, and that the exit counts agree with the cars that exited
+#[test]
+fn test_cloverleaf_accounting() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route2.toml", "cars.toml")?;
+    // The highway ends lie outside the world, so through traffic leaves as it spawns
+    config.route.route.geometry.bounds = Some(WorldBounds { min_x: -150.0, min_y: -150.0, max_x: 150.0, max_y: 150.0 });
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(5));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut collector = SummaryCollector::new(&config.route, &state);
+    while state.time < 20.0 {
+        backend.update(&mut state)?;
+        state.active_cars = state.cars.len() as u32;
+        collector.record(&state);
+    }
+    
+    let summary = collector.finish(&state);
+    assert!(summary.exited > 0);
+    assert_eq!(summary.spawned, summary.exited + summary.removed + summary.active_at_end);
+    assert_eq!(summary.exited, summary.exits.values().sum::<u32>());
+    assert_eq!(summary.exited, summary.travel_time.count);
+    
+    let behaviors = summary.behaviors.values();
+    assert_eq!(behaviors.clone().map(|behavior| behavior.spawned).sum::<u32>(), summary.spawned);
+    assert_eq!(behaviors.map(|behavior| behavior.exited).sum::<u32>(), summary.exited);
+    Ok(())
+}
+
+/// Test that a behavior none of whose cars finished a trip shows no travel times instead
+/// of zeros
+#[test]
+fn test_behavior_without_exits() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(5));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut collector = SummaryCollector::new(&config.route, &state);
+    while state.total_spawned == 0 {
+        backend.update(&mut state)?;
+        collector.record(&state);
+    }
+    
+    let summary = collector.finish(&state);
+    let (_, behavior) = summary.behaviors.iter().find(|(_, behavior)| behavior.spawned > 0).expect("a car spawned");
+    assert_eq!(behavior.exited, 0);
+    assert_eq!(behavior.travel_time.mean_p95(), "-");
+    
+    let finished = TravelTimeSummary { count: 2, mean: 30.0, p95: 41.3 };
+    assert_eq!(finished.mean_p95(), "30.0s/41.3s");
+    
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use traffic_sim::{
+    config::{SimulationConfig, Validate},
+    simulation::{CarId, Conflict, ConflictKind, SimulationEvent, SimulationState},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+
+/// The final state of a run of `seconds`, and the conflicts reported as events on the way
+fn run(config: &SimulationConfig, seconds: f32) -> Result<(SimulationState, Vec<Conflict>)> {
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(5));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut reported = Vec::new();
+    while state.time < seconds {
+        backend.update(&mut state)?;
+        reported.extend(state.events.iter().filter_map(|event| match event {
+            SimulationEvent::ConflictDetected(conflict) => Some(conflict.clone()),
+            _ => None,
+        }));
+    }
+    Ok((state, reported))
+}
+
+/// Test that followers closing in on the car ahead below the TTC threshold are reported as
+/// conflicts, once per encounter, and that the time spent closing in is logged by TTC
+#[test]
+fn test_time_to_collision_conflicts() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    config.cars.safety.ttc_threshold = 3.0;
+    let (state, reported) = run(&config, 90.0)?;
+    
+    let conflicts = state.safety.conflicts();
+    assert_eq!(conflicts.len(), reported.len());
+    assert!(state.safety.count(ConflictKind::TimeToCollision) > 0, "no TTC conflicts on the ring");
+    for conflict in conflicts.iter().filter(|conflict| conflict.kind == ConflictKind::TimeToCollision) {
+        assert!(conflict.value > 0.0 && conflict.value < 3.0, "TTC conflict of {} s", conflict.value);
+        assert_ne!(conflict.car_a, conflict.car_b);
+    }
+    let mut pairs: Vec<(CarId, CarId, u32)> = conflicts.iter().map(|conflict| (conflict.car_a, conflict.car_b, (conflict.time * 60.0).round() as u32)).collect();
+    pairs.dedup();
+    assert_eq!(pairs.len(), conflicts.len(), "an encounter reported twice in one tick");
+    
+    let under_threshold: f32 = state.safety.ttc_exposure[..6].iter().sum();
+    assert!(under_threshold > 0.0, "no time logged closing in under 3 s");
+    Ok(())
+}
+
+/// Test that cars crossing a grid junction soon after a car on a conflicting movement are
+/// reported with their post-encroachment time, at the junction
+#[test]
+fn test_post_encroachment_at_junctions() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route4.toml", "cars.toml")?;
+    let threshold = config.cars.safety.pet_threshold;
+    let (state, reported) = run(&config, 120.0)?;
+    
+    let pet: Vec<&Conflict> = reported.iter().filter(|conflict| conflict.kind == ConflictKind::PostEncroachment).collect();
+    assert!(!pet.is_empty(), "no PET conflicts at the junctions");
+    for conflict in pet {
+        assert!((0.0..threshold).contains(&conflict.value), "PET conflict of {} s", conflict.value);
+        let at_junction = state.junctions.iter().any(|junction| {
+            let offset = conflict.position - junction.position;
+            offset.x.abs() <= junction.half_size + 1.0 && offset.y.abs() <= junction.half_size + 1.0
+        });
+        assert!(at_junction, "PET conflict at {:?}, away from every junction", conflict.position);
+    }
+    let encroachments: u32 = state.safety.pet_counts.iter().sum();
+    assert!(encroachments as usize >= state.safety.count(ConflictKind::PostEncroachment));
+    Ok(())
+}
+
+/// Test that conflict thresholds must be positive
+#[test]
+fn test_safety_validation() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let check = |ttc_threshold: f32, pet_threshold: f32| {
+        let mut cars = config.cars.clone();
+        cars.safety.ttc_threshold = ttc_threshold;
+        cars.safety.pet_threshold = pet_threshold;
+        cars.validate()
+    };
+    assert!(check(1.5, 1.0).is_ok());
+    assert!(check(0.0, 1.0).is_err());
+    assert!(check(1.5, -1.0).is_err());
+    Ok(())
+}
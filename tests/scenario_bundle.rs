@@ -0,0 +1,104 @@
+use traffic_sim::{
+    config::{ConfigOverride, LaneClosure},
+    scenario::{load_events, EventSchedule, ScenarioBundle, ScenarioEvent},
+    server::ServerCommand,
+    simulation::SignalPhase,
+};
+use anyhow::Result;
+
+fn spawn_event(time: f32, behavior: &str) -> ScenarioEvent {
+    ScenarioEvent { time, command: ServerCommand::SpawnCar { behavior: behavior.to_string() } }
+}
+
+/// Test that a packed bundle holds the configuration with its overrides, the seed and the
+/// events, and reads back as written
+#[test]
+fn test_pack_round_trip() -> Result<()> {
+    let directory = std::env::temp_dir().join(format!("traffic-sim-bundle-{}", std::process::id()));
+    std::fs::create_dir_all(&directory)?;
+    let events_file = directory.join("events.toml");
+    std::fs::write(&events_file, "[[events]]\ntime = \"1 min\"\ncommand = \"spawn_car\"\nbehavior = \"erratic\"\n\n[[events]]\ntime = 10\ncommand = \"set_speed\"\nmultiplier = 2.0\n")?;
+    let events = load_events(&events_file)?;
+    assert_eq!(events[0], spawn_event(60.0, "erratic"));
+    
+    let overrides = ["route.traffic_rules.speed_limit=30.0".parse::<ConfigOverride>()?];
+    let bundle = ScenarioBundle::pack("route.toml", "cars.toml", &overrides, Some(42), events)?;
+    let path = directory.join("experiment.tsim");
+    bundle.save(&path)?;
+    let loaded = ScenarioBundle::load(&path)?;
+    assert_eq!(loaded, bundle);
+    
+    let config = loaded.config(&[])?;
+    assert_eq!(config.route.route.traffic_rules.speed_limit, 30.0);
+    assert_eq!(config.cars.random.seed, Some(42));
+    assert_eq!(loaded.events.len(), 2);
+    let config = loaded.config(&["cars.simulation.total_cars=10".parse()?])?;
+    assert_eq!(config.cars.simulation.total_cars, 10);
+    
+    // Signal groups released with no phase, which TOML has no null for
+    let release = ScenarioEvent { time: 5.0, command: ServerCommand::SetSignalPhase { group: "north".to_string(), phase: None } };
+    let hold = ScenarioEvent { time: 2.0, command: ServerCommand::SetSignalPhase { group: "north".to_string(), phase: Some(SignalPhase::Red) } };
+    let signals = ScenarioBundle { events: vec![hold, release], ..bundle };
+    signals.save(&path)?;
+    assert_eq!(ScenarioBundle::load(&path)?, signals);
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
+
+/// Test that events the configuration cannot carry out keep a bundle from being packed
+#[test]
+fn test_bundle_checks_events() {
+    let pack = |events: Vec<ScenarioEvent>| ScenarioBundle::pack("route.toml", "cars.toml", &[], None, events);
+    assert!(pack(vec![spawn_event(5.0, "normal")]).is_ok());
+    assert!(pack(vec![spawn_event(5.0, "reckless")]).is_err(), "unknown behavior");
+    assert!(pack(vec![spawn_event(-1.0, "normal")]).is_err());
+    let signal = ScenarioEvent { time: 5.0, command: ServerCommand::SetSignalPhase { group: "nowhere".to_string(), phase: None } };
+    assert!(pack(vec![signal]).is_err(), "the donut has no signal groups");
+}
+
+/// Test that lane closures drawn during a scenario run are saved into the bundle, keeping
+/// its seed and events
+#[test]
+fn test_save_closures_into_bundle() -> Result<()> {
+    let directory = std::env::temp_dir().join(format!("traffic-sim-bundle-closures-{}", std::process::id()));
+    std::fs::create_dir_all(&directory)?;
+    let path = directory.join("closures.tsim");
+    let bundle = ScenarioBundle::pack("route.toml", "cars.toml", &[], Some(7), vec![spawn_event(5.0, "normal")])?;
+    bundle.save(&path)?;
+    
+    let closure = LaneClosure { lane: 2, start_angle: 30.0, end_angle: 60.0 };
+    ScenarioBundle::save_closures(&path, std::slice::from_ref(&closure))?;
+    let saved = ScenarioBundle::load(&path)?;
+    assert_eq!(saved.config(&[])?.route.route.closures, [closure]);
+    assert_eq!((saved.seed, saved.events.clone()), (bundle.seed, bundle.events.clone()));
+    
+    ScenarioBundle::save_closures(&path, &[])?;
+    assert!(ScenarioBundle::load(&path)?.config(&[])?.route.route.closures.is_empty());
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
+
+/// Test that events are handed out once each as their times come, skipped before a later
+/// start, and due again after the run goes back in time
+#[test]
+fn test_event_schedule() {
+    let events = vec![spawn_event(10.0, "b"), spawn_event(0.0, "a"), spawn_event(10.0, "c"), spawn_event(30.0, "d")];
+    let behaviors = |due: &[ScenarioEvent]| -> Vec<String> {
+        due.iter().map(|event| match &event.command {
+            ServerCommand::SpawnCar { behavior } => behavior.clone(),
+            _ => unreachable!(),
+        }).collect()
+    };
+    let mut schedule = EventSchedule::new(events.clone(), 0.0);
+    assert_eq!(schedule.len(), 4);
+    assert_eq!(behaviors(schedule.due(0.0)), ["a"]);
+    assert!(schedule.due(9.9).is_empty());
+    assert_eq!(behaviors(schedule.due(10.0)), ["b", "c"]);
+    assert!(schedule.due(10.0).is_empty());
+    assert_eq!(behaviors(schedule.due(45.0)), ["d"]);
+    assert_eq!(behaviors(schedule.due(0.0)), ["a"], "reset");
+    
+    let mut resumed = EventSchedule::new(events, 20.0);
+    assert!(resumed.due(20.0).is_empty());
+    assert_eq!(behaviors(resumed.due(30.0)), ["d"]);
+}
@@ -0,0 +1,48 @@
+use traffic_sim::graphics::{parse_capture_size, Screenshot};
+use anyhow::Result;
+use std::fs::File;
+
+/// Test that row padding is dropped and BGRA texels come out as opaque RGBA
+#[test]
+fn test_from_texture_rows() {
+    // Two 2-pixel rows, each padded to 12 bytes
+    let data = [
+        1, 2, 3, 0, 4, 5, 6, 0, 9, 9, 9, 9,
+        7, 8, 9, 0, 10, 11, 12, 0, 9, 9, 9, 9,
+    ];
+    let bgra = Screenshot::from_texture_rows(2, 2, 12, &data, true);
+    assert_eq!((bgra.width, bgra.height), (2, 2));
+    assert_eq!(bgra.pixels, vec![3, 2, 1, 255, 6, 5, 4, 255, 9, 8, 7, 255, 12, 11, 10, 255]);
+    
+    let rgba = Screenshot::from_texture_rows(2, 2, 12, &data, false);
+    assert_eq!(&rgba.pixels[..4], &[1, 2, 3, 255]);
+}
+
+/// Test that a saved screenshot decodes back to the same image
+#[test]
+fn test_save_png_round_trip() -> Result<()> {
+    let pixels: Vec<u8> = (0..3 * 2 * 4).map(|i| i as u8 * 10).collect();
+    let screenshot = Screenshot { width: 3, height: 2, pixels: pixels.clone() };
+    let path = std::env::temp_dir().join(format!("traffic-sim-{}", std::process::id())).join("shot.png");
+    screenshot.save_png(&path)?;
+    
+    let mut reader = png::Decoder::new(File::open(&path)?).read_info()?;
+    let mut decoded = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut decoded)?;
+    std::fs::remove_dir_all(path.parent().unwrap())?;
+    
+    assert_eq!((info.width, info.height), (3, 2));
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+    assert_eq!(&decoded[..info.buffer_size()], pixels.as_slice());
+    Ok(())
+}
+
+/// Test that capture sizes parse as WIDTHxHEIGHT with positive sides
+#[test]
+fn test_parse_capture_size() {
+    assert_eq!(parse_capture_size("3840x2160"), Ok((3840, 2160)));
+    assert_eq!(parse_capture_size("800X600"), Ok((800, 600)));
+    assert!(parse_capture_size("3840").is_err());
+    assert!(parse_capture_size("0x600").is_err());
+    assert!(parse_capture_size("widexhigh").is_err());
+}
@@ -0,0 +1,64 @@
+use traffic_sim::config::{SimulationConfig, Validate};
+use anyhow::Result;
+use std::path::PathBuf;
+
+/// Write a behavior script to the temporary directory and point every behavior at it
+fn config_with_script(name: &str, source: &str) -> Result<(SimulationConfig, PathBuf)> {
+    let path = std::env::temp_dir().join(format!("traffic_sim_{}_{}.rhai", name, std::process::id()));
+    std::fs::write(&path, source)?;
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    for behavior in config.cars.behavior.values_mut() {
+        behavior.script = Some(path.to_string_lossy().to_string());
+    }
+    Ok((config, path))
+}
+
+/// Test that scripts override target speeds and lane change decisions
+#[cfg(feature = "scripting")]
+#[test]
+fn test_script_overrides_decisions() -> Result<()> {
+    use traffic_sim::{simulation::SimulationState, compute::{ComputeBackend, SimulationBackend}};
+    
+    let (config, path) = config_with_script("override", r#"
+        fn target_speed(car, speed) { 12.5 }
+        fn lane_change(car, lane) { () }
+    "#)?;
+    config.cars.validate()?;
+    
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(2));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    while state.time < 30.0 {
+        backend.update(&mut state)?;
+        assert!(state.cars.iter().all(|car| car.target_lane.is_none()));
+    }
+    std::fs::remove_file(path)?;
+    
+    assert!(!state.cars.is_empty());
+    assert!(state.cars.iter().all(|car| car.behavior.target_speed == 12.5));
+    
+    Ok(())
+}
+
+/// Test that scripts that do not compile are rejected with the configuration
+#[cfg(feature = "scripting")]
+#[test]
+fn test_broken_script_rejected() -> Result<()> {
+    let (config, path) = config_with_script("broken", "fn target_speed(car, speed) {")?;
+    let result = config.cars.validate();
+    std::fs::remove_file(path)?;
+    assert!(result.is_err());
+    
+    Ok(())
+}
+
+/// Test that configurations with scripts are rejected by builds without scripting
+#[cfg(not(feature = "scripting"))]
+#[test]
+fn test_script_needs_feature() -> Result<()> {
+    let (config, path) = config_with_script("disabled", "fn target_speed(car, speed) { speed }")?;
+    let result = config.cars.validate();
+    std::fs::remove_file(path)?;
+    assert!(result.is_err());
+    
+    Ok(())
+}
@@ -0,0 +1,142 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::{AppendLog, SimulationState},
+    compute::{triple_buffer, ComputeBackend, SimulationBackend, SimulationRunner},
+};
+use anyhow::Result;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+fn spawn_runner(config: &SimulationConfig, seed: u64) -> Result<SimulationRunner> {
+    let (cars, route) = (config.cars.clone(), config.route.clone());
+    SimulationRunner::spawn(move || ComputeBackend::new_cpu(cars, route, Some(seed)), SimulationState::new(1.0 / 60.0))
+}
+
+/// Test that the reader gets the newest value published and nothing twice, and that the
+/// values it hands back are the ones the writer fills next
+#[test]
+fn test_triple_buffer() {
+    let (mut writer, mut reader) = triple_buffer(Vec::new(), Vec::new());
+    let mut front = vec![0];
+    assert!(!reader.swap_latest(&mut front));
+    assert_eq!(front, [0]);
+    
+    writer.back_mut().push(1);
+    writer.publish();
+    assert!(!writer.taken());
+    writer.back_mut().push(2);
+    writer.publish();
+    assert_eq!(writer.back_mut(), &[1], "the value never taken is filled again");
+    
+    assert!(reader.swap_latest(&mut front));
+    assert_eq!(front, [2]);
+    assert!(writer.taken());
+    assert!(!reader.swap_latest(&mut front));
+    
+    writer.publish();
+    assert!(reader.swap_latest(&mut front));
+    assert_eq!(front, [1]);
+    assert_eq!(writer.back_mut(), &[0], "the reader's old value goes back to the writer");
+}
+
+/// Test that copying into an earlier copy only adds the newer entries, and that copies
+/// of other logs or of changed copies are copied whole
+#[test]
+fn test_append_log_copies() {
+    let mut log = AppendLog::default();
+    log.push(1);
+    let mut copy = log.clone();
+    log.push(2);
+    log.push(3);
+    copy.clone_from(&log);
+    assert_eq!(&*copy, &[1, 2, 3]);
+    
+    // A copy of a copy catches up from either
+    let mut second = copy.clone();
+    log.push(4);
+    copy.clone_from(&log);
+    second.clone_from(&copy);
+    assert_eq!(&*second, &[1, 2, 3, 4]);
+    
+    let mut other = AppendLog::default();
+    other.push(9);
+    second.clone_from(&other);
+    assert_eq!(&*second, &[9]);
+    
+    copy.push(5);
+    copy.clone_from(&log);
+    assert_eq!(&*copy, &[1, 2, 3, 4]);
+}
+
+/// Test that the simulation thread takes the same steps as stepping inline, that the step
+/// hooks see each of them, and that the snapshot after a call shows where it stopped
+#[test]
+fn test_runner_steps_like_inline() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut runner = spawn_runner(&config, 12)?;
+    let hooked = Arc::new(AtomicU32::new(0));
+    let counter = hooked.clone();
+    runner.add_step_hook(Box::new(move |_| {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }));
+    runner.step(3600);
+    let time = runner.call(|_, state| state.time)?;
+    
+    let mut snapshot = SimulationState::new(1.0 / 60.0);
+    let report = runner.take_latest(&mut snapshot).expect("a snapshot after the call");
+    assert_eq!(snapshot.time, time);
+    assert_eq!(report.steps, 3600, "the snapshot the steps ended on was never taken");
+    assert_eq!(hooked.load(Ordering::Relaxed), 3600);
+    
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(12));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    for _ in 0..3600 {
+        backend.update(&mut state)?;
+        state.update_car_speeds();
+        state.active_cars = state.cars.len() as u32;
+    }
+    assert_eq!(snapshot.time, state.time);
+    assert_eq!(snapshot.total_spawned, state.total_spawned);
+    assert_eq!(snapshot.trips.len(), state.trips.len());
+    assert!(!snapshot.cars.is_empty());
+    for (threaded, inline) in snapshot.cars.iter().zip(&state.cars) {
+        assert_eq!((threaded.id, threaded.position, threaded.velocity), (inline.id, inline.position, inline.velocity));
+    }
+    Ok(())
+}
+
+/// Test that an unthrottled simulation keeps stepping while nobody takes its snapshots,
+/// stops when paused, and pauses on a failing step hook
+#[test]
+fn test_runner_runs_and_pauses() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut runner = spawn_runner(&config, 5)?;
+    runner.set_running(false, f32::INFINITY);
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    runner.set_running(true, f32::INFINITY);
+    let paused_at = runner.call(|_, state| state.time)?;
+    assert!(paused_at > 1.0, "only {}s simulated in 300ms", paused_at);
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    assert_eq!(runner.call(|_, state| state.time)?, paused_at);
+    
+    let mut snapshot = SimulationState::new(1.0 / 60.0);
+    let report = runner.take_latest(&mut snapshot).expect("a snapshot after the call");
+    assert_eq!(snapshot.time, paused_at);
+    assert!(report.steps > 0, "steps of the snapshots never taken are reported with this one");
+    assert!(runner.take_error().is_none());
+    
+    runner.add_step_hook(Box::new(move |state| {
+        if state.time > paused_at + 0.5 {
+            return Err(anyhow::anyhow!("hook failed"));
+        }
+        Ok(())
+    }));
+    runner.set_running(false, f32::INFINITY);
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    let error = runner.take_error().expect("the hook failure");
+    assert_eq!(error.to_string(), "hook failed");
+    let failed_at = runner.call(|_, state| state.time)?;
+    assert!(failed_at < paused_at + 0.55, "stepped on to {}s after the failure", failed_at);
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use traffic_sim::sweep::{run_sweep, SweepConfig};
+use anyhow::Result;
+use std::path::Path;
+
+/// Test that every combination runs once, results land in the CSV, and parallel runs
+/// match running the same combination on its own
+#[test]
+fn test_sweep_runs_every_combination() -> Result<()> {
+    let directory = std::env::temp_dir().join(format!("traffic-sim-sweep-{}", std::process::id()));
+    std::fs::create_dir_all(&directory)?;
+    let sweep_file = directory.join("sweep.toml");
+    std::fs::copy("route.toml", directory.join("route.toml"))?;
+    std::fs::copy("cars.toml", directory.join("cars.toml"))?;
+    std::fs::write(&sweep_file, r#"
+duration = 5.0
+seeds = [1, 2]
+
+[parameters]
+"route.traffic_rules.speed_limit" = [25, 33]
+"#)?;
+
+    let sweep = SweepConfig::load(&sweep_file)?;
+    let output = directory.join("results.csv");
+    let results = run_sweep(&sweep, &directory, &output, 2, 1.0 / 60.0)?;
+    assert_eq!(results.len(), 4);
+    assert_eq!(results.iter().map(|(run, _)| run.index).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+    assert_eq!(results[0].0.config.route.route.traffic_rules.speed_limit, 25.0);
+    assert_eq!(results[3].0.seed, 2);
+    
+    let csv = std::fs::read_to_string(&output)?;
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 5);
+    assert!(lines[0].starts_with("run,route.traffic_rules.speed_limit,seed,duration,spawned,exited"));
+    
+    let (run, result) = &results[1];
+    assert_eq!(&run.execute(5.0, 1.0 / 60.0)?, result);
+    assert!(result.spawned > 0);
+    
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
+
+/// Test that an invalid combination is reported before anything runs
+#[test]
+fn test_invalid_combination_is_refused() -> Result<()> {
+    let sweep: SweepConfig = toml::from_str(r#"
+[[variants]]
+name = "unbalanced"
+set = { "cars.behavior.aggressive.weight" = 90 }
+"#)?;
+    let error = sweep.runs(Path::new(".")).unwrap_err();
+    assert!(error.to_string().contains("unbalanced"));
+    Ok(())
+}
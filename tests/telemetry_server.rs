@@ -0,0 +1,89 @@
+use traffic_sim::{
+    config::{SimulationConfig, SignalGroup, SignalHead},
+    simulation::{SimulationState, SignalPhase},
+    compute::{ComputeBackend, SimulationBackend},
+    server::{ServerCommand, TelemetryServer},
+};
+use anyhow::Result;
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+use tungstenite::Message;
+
+/// Test that a WebSocket client receives tick messages and that its commands
+/// reach the simulation, with bad commands answered by an error
+#[test]
+fn test_server_streams_ticks_and_accepts_commands() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    config.route.route.signals.groups.push(SignalGroup {
+        id: "north".to_string(),
+        green_time: 30.0,
+        yellow_time: 3.0,
+        red_time: 30.0,
+        offset: 0.0,
+        heads: vec![SignalHead {
+            angle: Some(90.0),
+            x: None,
+            y: None,
+            heading: None,
+            width: None,
+            lanes: Vec::new(),
+        }],
+    });
+    
+    let mut server = TelemetryServer::bind("127.0.0.1:0", &config)?;
+    let stream = TcpStream::connect(server.local_addr())?;
+    let (mut client, _) = tungstenite::client(format!("ws://{}", server.local_addr()), stream)?;
+    client.get_ref().set_read_timeout(Some(Duration::from_millis(20)))?;
+    
+    // The server registers the client on its own thread, so publish until it arrives
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(3));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let start = Instant::now();
+    let tick = loop {
+        backend.update(&mut state)?;
+        server.publish(&state)?;
+        if let Ok(Message::Text(text)) = client.read() {
+            break serde_json::from_str::<serde_json::Value>(&text)?;
+        }
+        assert!(start.elapsed() < Duration::from_secs(10), "no tick received");
+    };
+    assert_eq!(tick["type"], "tick");
+    assert!(tick["cars"].is_array());
+    assert_eq!(tick["signals"][0]["group"], "north");
+    assert!(tick["metrics"]["time"].as_f64().unwrap() > 0.0);
+    
+    client.get_ref().set_read_timeout(Some(Duration::from_secs(5)))?;
+    client.send(Message::Text(r#"{"command": "spawn_car", "behavior": "reckless"}"#.to_string()))?;
+    let reply = loop {
+        if let Message::Text(text) = client.read()? {
+            let message: serde_json::Value = serde_json::from_str(&text)?;
+            if message["type"] == "error" {
+                break message;
+            }
+        }
+    };
+    assert!(reply["message"].as_str().unwrap().contains("reckless"));
+    
+    client.send(Message::Text(r#"{"command": "pause"}"#.to_string()))?;
+    client.send(Message::Text(r#"{"command": "set_signal_phase", "group": "north", "phase": "Red"}"#.to_string()))?;
+    let mut commands = Vec::new();
+    while commands.len() < 2 {
+        commands.extend(server.poll_commands());
+        assert!(start.elapsed() < Duration::from_secs(10), "commands not received");
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(commands, vec![
+        ServerCommand::Pause,
+        ServerCommand::SetSignalPhase { group: "north".to_string(), phase: Some(SignalPhase::Red) },
+    ]);
+    
+    // A held group stays at its phase however its timing plan runs
+    state.set_signal_override("north", Some(SignalPhase::Red));
+    backend.update(&mut state)?;
+    assert!(state.signals.iter().all(|signal| signal.phase == SignalPhase::Red));
+    state.set_signal_override("north", None);
+    backend.update(&mut state)?;
+    assert!(state.signals.iter().all(|signal| signal.phase == SignalPhase::Green));
+    
+    Ok(())
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::{SimulationState, TrajectoryBuffer, road_length, road_position},
+    compute::{ComputeBackend, SimulationBackend},
+    export::{ExportFormat, TrajectoryExporter},
+};
+use anyhow::Result;
+use common::template_car;
+use nalgebra::Point2;
+
+/// Test that ring positions run counter-clockwise from the positive x axis and wrap at
+/// the road length
+#[test]
+fn test_ring_positions() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let geometry = &config.route.route.geometry;
+    let length = road_length(geometry).expect("route.toml is a ring");
+    let mut car = template_car(&config)?;
+    let radius = length / (2.0 * std::f32::consts::PI);
+    let center = Point2::new(geometry.center_x, geometry.center_y);
+    
+    car.position = center + nalgebra::Vector2::new(radius, 0.0);
+    assert!(road_position(&car, geometry).abs() < 1e-3);
+    car.position = center + nalgebra::Vector2::new(0.0, radius);
+    assert!((road_position(&car, geometry) - length / 4.0).abs() < 1e-2);
+    car.position = center + nalgebra::Vector2::new(radius, -0.01);
+    assert!((road_position(&car, geometry) - length).abs() < 0.1);
+    Ok(())
+}
+
+/// Test that the buffer samples at its interval and never holds more than it is allowed
+#[test]
+fn test_buffer_is_bounded() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(3));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut buffer = TrajectoryBuffer::new(&config.route.route.geometry, 0.5, 10.0, 40);
+    let mut sampled_ticks = 0;
+    for _ in 0..(30.0 / state.dt) as usize {
+        backend.update(&mut state)?;
+        if buffer.record(&state) > 0 {
+            sampled_ticks += 1;
+        }
+        assert!(buffer.samples().len() <= 40);
+        assert!(buffer.samples().iter().all(|sample| sample.time >= state.time - 10.0));
+    }
+    assert!((55..=61).contains(&sampled_ticks), "{} ticks sampled", sampled_ticks);
+    
+    // Each car's samples are half a second apart
+    let last = buffer.samples().back().expect("cars were sampled");
+    let previous = buffer.samples().iter().rev().find(|sample| sample.car == last.car && sample.frame < last.frame);
+    if let Some(previous) = previous {
+        assert_eq!(previous.frame + 1, last.frame);
+    }
+    Ok(())
+}
+
+/// Test that the export writes one NGSIM-style row per car per sample
+#[test]
+fn test_trajectory_export() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("traffic-sim-trajectories-{}.csv", std::process::id()));
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let geometry = &config.route.route.geometry;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(3));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut exporter = TrajectoryExporter::create(&path, ExportFormat::Csv, geometry, 1.0)?;
+    let mut buffer = TrajectoryBuffer::new(geometry, 1.0, 0.0, usize::MAX);
+    let mut expected_rows = 0;
+    for _ in 0..(10.0 / state.dt) as usize {
+        backend.update(&mut state)?;
+        exporter.record(&state)?;
+        expected_rows += buffer.record(&state);
+    }
+    exporter.flush()?;
+    
+    let csv = std::fs::read_to_string(&path)?;
+    let lines: Vec<&str> = csv.lines().collect();
+    assert!(lines[0].starts_with("Vehicle_ID,Frame_ID,Global_Time,Local_Y,Lane_ID"));
+    assert!(expected_rows > 0);
+    assert_eq!(lines.len() - 1, expected_rows);
+    
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
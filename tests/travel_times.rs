@@ -0,0 +1,120 @@
+use traffic_sim::{
+    config::{SimulationConfig, TravelTimeSegment, Validate, WaypointConfig},
+    simulation::{SimulationState, TravelTimeReliability, road_length},
+    compute::{ComputeBackend, SimulationBackend},
+    export::{ExportFormat, SummaryCollector, TravelTimeExporter},
+};
+use anyhow::Result;
+
+fn waypoint(id: &str, angle: f32) -> WaypointConfig {
+    WaypointConfig {
+        id: id.to_string(),
+        angle: Some(angle),
+        x: None,
+        y: None,
+        heading: None,
+        width: None,
+        lanes: Vec::new(),
+    }
+}
+
+fn segment(id: &str, from: &str, to: &str) -> TravelTimeSegment {
+    TravelTimeSegment {
+        id: id.to_string(),
+        from: from.to_string(),
+        to: to.to_string(),
+    }
+}
+
+/// Test the mean, median, nearest-rank 95th percentile and buffer index of travel times
+#[test]
+fn test_reliability_statistics() {
+    assert!(TravelTimeReliability::from_times(&[]).is_none());
+    
+    let odd = TravelTimeReliability::from_times(&[14.0, 40.0, 10.0, 16.0, 12.0]).expect("statistics");
+    assert_eq!(odd.count, 5);
+    assert!((odd.mean - 18.4).abs() < 1e-4);
+    assert_eq!(odd.median, 14.0);
+    assert_eq!(odd.p95, 40.0);
+    assert!((odd.buffer_index - (40.0 - 18.4) / 18.4).abs() < 1e-4);
+    
+    let times: Vec<f32> = (1..=20).map(|time| time as f32).collect();
+    let even = TravelTimeReliability::from_times(&times).expect("statistics");
+    assert_eq!(even.median, 10.5);
+    assert_eq!(even.p95, 19.0);
+    assert!((even.buffer_index - (19.0 - 10.5) / 10.5).abs() < 1e-4);
+}
+
+/// Test that cars are timed between waypoints on opposite sides of the ring, and that the
+/// traversals are summarized and exported
+#[test]
+fn test_travel_times_in_run() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    config.route.route.waypoints = vec![waypoint("north", 90.0), waypoint("south", 270.0)];
+    config.route.route.travel_times = vec![segment("north_to_south", "north", "south"), segment("south_to_north", "south", "north")];
+    config.route.validate()?;
+    let half_ring = road_length(&config.route.route.geometry).expect("route.toml is a ring") / 2.0;
+    
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(5));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let mut collector = SummaryCollector::new(&config.route, &state);
+    let path = std::env::temp_dir().join(format!("traffic-sim-travel-times-{}.csv", std::process::id()));
+    let mut exporter = TravelTimeExporter::create(&path, ExportFormat::Csv)?;
+    while state.time < 120.0 {
+        backend.update(&mut state)?;
+        collector.record(&state);
+        exporter.record(&state)?;
+    }
+    exporter.flush()?;
+    
+    let traversals = state.travel_times.traversals();
+    assert!(!traversals.is_empty(), "no car was timed");
+    for traversal in traversals {
+        assert!(traversal.segment == "north_to_south" || traversal.segment == "south_to_north");
+        assert!((traversal.end_time - traversal.start_time - traversal.travel_time).abs() < 1e-3);
+        // Half the ring at somewhere between crawling and well over the speed limit
+        assert!(traversal.travel_time > half_ring / 60.0 && traversal.travel_time < half_ring / 2.0,
+                "{:?} over {:.0} m", traversal, half_ring);
+    }
+    
+    let summary = collector.finish(&state).travel_time_reliability;
+    assert_eq!(summary.len(), traversals.iter().map(|traversal| &traversal.segment).collect::<std::collections::BTreeSet<_>>().len());
+    for (segment, reliability) in &summary {
+        assert_eq!(Some(reliability), state.travel_times.reliability(segment, 0.0).as_ref());
+        assert!(reliability.median <= reliability.p95 && reliability.mean <= reliability.p95);
+        assert!(reliability.buffer_index >= 0.0);
+    }
+    
+    let contents = std::fs::read_to_string(&path)?;
+    std::fs::remove_file(&path)?;
+    let mut lines = contents.lines();
+    assert_eq!(lines.next(), Some("segment,car,start_time,end_time,travel_time"));
+    assert_eq!(lines.count(), traversals.len());
+    Ok(())
+}
+
+/// Test that waypoints and travel time segments are checked against the route
+#[test]
+fn test_travel_time_validation() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let check = |waypoints: Vec<WaypointConfig>, segments: Vec<TravelTimeSegment>| {
+        let mut route = config.route.clone();
+        route.route.waypoints = waypoints;
+        route.route.travel_times = segments;
+        route.validate()
+    };
+    let pair = || vec![waypoint("north", 90.0), waypoint("south", 270.0)];
+    assert!(check(pair(), vec![segment("a", "north", "south")]).is_ok());
+    assert!(check(vec![waypoint("north", 90.0), waypoint("north", 270.0)], Vec::new()).is_err());
+    assert!(check(vec![WaypointConfig { lanes: vec![9], ..waypoint("north", 90.0) }], Vec::new()).is_err());
+    assert!(check(vec![WaypointConfig { angle: None, ..waypoint("north", 90.0) }], Vec::new()).is_err());
+    assert!(check(pair(), vec![segment("a", "north", "east")]).is_err());
+    assert!(check(pair(), vec![segment("a", "north", "north")]).is_err());
+    assert!(check(pair(), vec![segment("a", "north", "south"), segment("a", "south", "north")]).is_err());
+    
+    let grid = SimulationConfig::load_from_files("route4.toml", "cars.toml")?;
+    let mut route = grid.route.clone();
+    route.route.waypoints = vec![waypoint("north", 90.0)];
+    assert!(route.validate().is_err());
+    Ok(())
+}
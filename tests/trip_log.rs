@@ -0,0 +1,45 @@
+use traffic_sim::{
+    config::SimulationConfig,
+    simulation::SimulationState,
+    compute::{ComputeBackend, SimulationBackend},
+    export::{ExportFormat, TripExporter},
+};
+use anyhow::Result;
+
+/// Test that every car leaving through an exit leaves a trip behind, with travel time,
+/// free-flow time and delay that agree with each other, and that the exporter writes
+/// each trip once
+#[test]
+fn test_exits_are_logged_as_trips() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(9));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    let path = std::env::temp_dir().join(format!("traffic-sim-trips-{}.csv", std::process::id()));
+    let mut exporter = TripExporter::create(&path, ExportFormat::Csv)?;
+    while state.time < 90.0 {
+        backend.update(&mut state)?;
+        exporter.record(&state)?;
+    }
+    exporter.flush()?;
+    
+    let trips = state.trips.trips();
+    assert!(!trips.is_empty());
+    assert_eq!(trips.len() as u32, state.exit_counts.values().sum::<u32>());
+    let entries: Vec<&str> = config.route.route.entries.iter().map(|entry| entry.id.as_str()).collect();
+    for trip in trips {
+        assert!(entries.contains(&trip.entry.as_str()));
+        assert!(state.exit_counts.contains_key(&trip.exit));
+        assert!(trip.distance > 0.0);
+        assert!((trip.travel_time - (trip.exit_time - trip.spawn_time)).abs() < 1e-3);
+        assert!((trip.delay - (trip.travel_time - trip.free_flow_time).max(0.0)).abs() < 1e-3);
+        assert!(state.cars.iter().all(|car| car.id != trip.car));
+    }
+    assert!(state.trips.mean_delay(0.0).is_some());
+    assert!(state.trips.mean_delay(state.time + 1.0).is_none());
+    
+    let csv = std::fs::read_to_string(&path)?;
+    assert_eq!(csv.lines().count(), trips.len() + 1);
+    std::fs::remove_file(&path)?;
+    
+    Ok(())
+}
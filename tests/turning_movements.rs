@@ -0,0 +1,99 @@
+use traffic_sim::{
+    config::{SimulationConfig, TurningRatio, Validate},
+    simulation::{Approach, Movement, SimulationState, Turn},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+
+fn movement(from: Approach, to: Approach) -> Movement {
+    Movement { from, to }
+}
+
+/// Test which pairs of movements through a junction conflict
+#[test]
+fn test_movement_conflicts() {
+    use Approach::*;
+    
+    assert_eq!(movement(South, West).turn(), Turn::Left);
+    assert_eq!(movement(South, North).turn(), Turn::Through);
+    assert_eq!(movement(South, East).turn(), Turn::Right);
+    
+    // Left turns across oncoming traffic
+    assert!(movement(South, West).conflicts_with(movement(North, South)));
+    assert!(movement(South, West).conflicts_with(movement(East, West)));
+    // Crossing through movements, and movements leaving by the same side
+    assert!(movement(South, North).conflicts_with(movement(East, West)));
+    assert!(movement(South, East).conflicts_with(movement(North, East)));
+    
+    // Opposing turns and through movements pass side by side
+    assert!(!movement(South, West).conflicts_with(movement(North, East)));
+    assert!(!movement(South, East).conflicts_with(movement(North, West)));
+    assert!(!movement(South, North).conflicts_with(movement(North, South)));
+    assert!(!movement(South, East).conflicts_with(movement(West, South)));
+    // Cars from the same approach follow each other in
+    assert!(!movement(South, West).conflicts_with(movement(South, North)));
+}
+
+/// Test that conflicting movements are never reserved through a junction at once, and that
+/// the traffic served follows the configured turning ratios
+#[test]
+fn test_conflict_zones_and_turning_ratios() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route4.toml", "cars.toml")?;
+    config.cars.simulation.spawn_rate = 1.0;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(9));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    
+    while state.time < 300.0 {
+        backend.update(&mut state)?;
+        for junction in &state.junctions {
+            for (i, first) in junction.reservations.iter().enumerate() {
+                for second in &junction.reservations[i + 1..] {
+                    assert!(!first.movement.conflicts_with(second.movement),
+                        "{:?} and {:?} reserved together at ({}, {})", first.movement, second.movement, junction.row, junction.col);
+                }
+            }
+        }
+    }
+    
+    assert!(!state.junctions.is_empty());
+    for ratio in &config.route.route.turning_ratios {
+        let junction = state.junctions.iter().find(|junction| (junction.row, junction.col) == (ratio.row, ratio.col)).expect("junction");
+        let arrived: Vec<_> = junction.served.iter().filter(|count| count.movement.from.name() == ratio.approach).collect();
+        let total: u32 = arrived.iter().map(|count| count.cars).sum();
+        assert!(total >= 20, "only {} cars from the {} at ({}, {})", total, ratio.approach, ratio.row, ratio.col);
+        
+        let configured = ratio.left + ratio.through + ratio.right;
+        for turn in [Turn::Left, Turn::Through, Turn::Right] {
+            let cars: u32 = arrived.iter().filter(|count| count.movement.turn() == turn).map(|count| count.cars).sum();
+            let observed = cars as f32 / total as f32;
+            let expected = turn.share(ratio) / configured;
+            assert!((observed - expected).abs() < 0.2, "{:?} share {:.2}, expected {:.2}", turn, observed, expected);
+        }
+    }
+    Ok(())
+}
+
+/// Test that turning ratios are checked against the route
+#[test]
+fn test_turning_ratio_validation() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route4.toml", "cars.toml")?;
+    let check = |ratios: Vec<TurningRatio>| {
+        let mut route = config.route.clone();
+        route.route.turning_ratios = ratios;
+        route.validate()
+    };
+    let ratio = TurningRatio { row: 2, col: 2, approach: "north".to_string(), left: 0.3, through: 0.5, right: 0.2 };
+    assert!(check(vec![ratio.clone()]).is_ok());
+    assert!(check(vec![TurningRatio { row: 1, col: 1, ..ratio.clone() }]).is_err());
+    assert!(check(vec![TurningRatio { approach: "up".to_string(), ..ratio.clone() }]).is_err());
+    assert!(check(vec![TurningRatio { left: -0.1, ..ratio.clone() }]).is_err());
+    assert!(check(vec![TurningRatio { left: 0.0, through: 0.0, right: 0.0, ..ratio.clone() }]).is_err());
+    assert!(check(vec![ratio.clone(), ratio.clone()]).is_err());
+    assert!(check(vec![ratio.clone(), TurningRatio { approach: "west".to_string(), ..ratio.clone() }]).is_ok());
+    
+    let ring = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    let mut route = ring.route.clone();
+    route.route.turning_ratios = vec![ratio];
+    assert!(route.validate().is_err());
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use traffic_sim::{
+    config::UnitSystem,
+    graphics::{OverlayPreference, PreferencesWindow, UiPreferences, OVERLAYS},
+};
+use anyhow::Result;
+
+/// Test that preferences survive a save and load, and a missing file gives the defaults
+#[test]
+fn test_preferences_round_trip() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("traffic-sim-preferences-{}", std::process::id()));
+    let path = dir.join("preferences.toml");
+    assert_eq!(UiPreferences::load(&path)?, UiPreferences::default());
+    
+    let mut preferences = UiPreferences {
+        font_size: 18.0,
+        ui_scale: 1.25,
+        ..Default::default()
+    };
+    preferences.set_overlay("legend", OverlayPreference { visible: false, ..Default::default() });
+    preferences.set_overlay("pie_chart", OverlayPreference { collapsed: true, position: Some([900.0, 120.0]), ..Default::default() });
+    preferences.save(&path)?;
+    
+    let loaded = UiPreferences::load(&path)?;
+    assert_eq!(loaded, preferences);
+    assert!(!loaded.overlay("legend").visible);
+    assert!(loaded.overlay("pie_chart").collapsed);
+    assert_eq!(loaded.overlay("pie_chart").position, Some([900.0, 120.0]));
+    assert!(loaded.overlay("status").visible);
+    
+    // The window starts from the saved file
+    let window = PreferencesWindow::new(Some(path.clone()));
+    assert_eq!(window.preferences(), &preferences);
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+/// Test that partial and out-of-range files load, and broken ones are reported
+#[test]
+fn test_preferences_file_handling() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("traffic-sim-preferences-{}.toml", std::process::id()));
+    
+    std::fs::write(&path, "font_size = 100.0\n")?;
+    let preferences = UiPreferences::load(&path)?;
+    assert_eq!(preferences.font_size, 32.0);
+    assert_eq!(preferences.ui_scale, 1.0);
+    assert!(OVERLAYS.iter().all(|(id, _)| preferences.overlay(id).visible));
+    
+    std::fs::write(&path, "[overlays.controls]\nvisible = false\n")?;
+    let preferences = UiPreferences::load(&path)?;
+    assert!(!preferences.overlay("controls").visible);
+    assert_eq!(preferences.overlay("controls").position, None);
+    
+    std::fs::write(&path, "font_size = \"large\"\n")?;
+    assert!(UiPreferences::load(&path).is_err());
+    
+    // A window over a broken file falls back to the defaults, and --font-size applies on top
+    let mut window = PreferencesWindow::new(Some(path.clone()));
+    assert_eq!(window.preferences(), &UiPreferences::default());
+    window.set_font_size(20.0);
+    assert_eq!(window.font_size(), 20.0);
+    assert_eq!(window.preferences().font_size, 14.0);
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+/// Test that --font-size and --units are not saved along with later changes
+#[test]
+fn test_command_line_overrides_not_saved() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("traffic-sim-cli-overrides-{}.toml", std::process::id()));
+    let mut window = PreferencesWindow::new(Some(path.clone()));
+    window.set_units(UnitSystem::Imperial);
+    assert_eq!(window.units(), UnitSystem::Imperial);
+    assert_eq!(window.preferences().units, UnitSystem::Metric);
+    window.set_font_size(24.0);
+    window.choose_units(UnitSystem::Imperial);
+    assert_eq!(window.font_size(), 24.0);
+    
+    let saved = UiPreferences::load(&path)?;
+    assert_eq!(saved.units, UnitSystem::Imperial);
+    assert_eq!(saved.font_size, UiPreferences::default().font_size);
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+/// Run one egui frame of an 800x600 screen with `window` showing the legend
+fn legend_frame(ctx: &egui::Context, window: &mut PreferencesWindow) -> Option<egui::Rect> {
+    let input = egui::RawInput {
+        screen_rect: Some(egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(800.0, 600.0))),
+        ..Default::default()
+    };
+    let mut rect = None;
+    let _ = ctx.run(input, |ctx| {
+        rect = window.overlay_window(ctx, "legend", "Legend", egui::pos2(15.0, 585.0), egui::Align2::LEFT_BOTTOM, |ui| {
+            for line in 0..10 {
+                ui.label(format!("Line {}", line));
+            }
+        });
+    });
+    rect
+}
+
+/// Test that overlay windows are pinned by their corner, start where they were left,
+/// and follow the collapsed and hidden preferences
+#[test]
+fn test_overlay_windows() -> Result<()> {
+    let ctx = egui::Context::default();
+    let mut window = PreferencesWindow::new(None);
+    let mut rect = None;
+    for _ in 0..3 {
+        rect = legend_frame(&ctx, &mut window);
+    }
+    let open = rect.expect("legend shown");
+    assert!((open.left() - 15.0).abs() < 1.0 && (open.bottom() - 585.0).abs() < 1.0, "{:?}", open);
+    assert_eq!(window.preferences().overlay("legend"), OverlayPreference::default());
+    
+    // A saved position and collapsed state apply from the first frame
+    let mut preferences = UiPreferences::default();
+    preferences.set_overlay("legend", OverlayPreference { collapsed: true, position: Some([300.0, 400.0]), ..Default::default() });
+    let path = std::env::temp_dir().join(format!("traffic-sim-overlays-{}.toml", std::process::id()));
+    preferences.save(&path)?;
+    let ctx = egui::Context::default();
+    let mut window = PreferencesWindow::new(Some(path.clone()));
+    for _ in 0..3 {
+        rect = legend_frame(&ctx, &mut window);
+    }
+    let collapsed = rect.expect("legend shown");
+    assert!((collapsed.left() - 300.0).abs() < 1.0 && (collapsed.bottom() - 400.0).abs() < 1.0, "{:?}", collapsed);
+    assert!(collapsed.height() < open.height() / 2.0);
+    assert_eq!(window.preferences(), &preferences);
+    std::fs::remove_file(&path)?;
+    
+    preferences.set_overlay("legend", OverlayPreference { visible: false, ..Default::default() });
+    preferences.save(&path)?;
+    let mut window = PreferencesWindow::new(Some(path.clone()));
+    assert_eq!(legend_frame(&ctx, &mut window), None);
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use traffic_sim::graphics::{ffmpeg_args, VideoClock};
+use std::path::Path;
+
+const DT: f32 = 1.0 / 60.0;
+
+/// Test that frames are taken at fixed simulated intervals, whatever steps are run between them
+#[test]
+fn test_frames_follow_simulation_time() {
+    let mut clock = VideoClock::new(30, 0.0);
+    // The first frame is the starting state
+    assert_eq!(clock.frames_due(0.0), 1);
+    
+    // At 60 Hz steps every second step is a 30 fps frame
+    let taken: u32 = (1..=60).map(|step| clock.frames_due(step as f32 * DT)).sum();
+    assert_eq!(taken, 30);
+    
+    // Stepping ahead in one go, as at high playback speed, owes the frames in between
+    assert_eq!(clock.frames_due(2.0), 30);
+    assert_eq!(clock.frames_due(2.0), 0);
+}
+
+/// Test that a frame rate above the step rate repeats frames to keep the video's pace
+#[test]
+fn test_high_frame_rate_repeats_frames() {
+    let mut clock = VideoClock::new(120, 10.0);
+    assert_eq!(clock.frames_due(10.0), 1);
+    let taken: Vec<u32> = (1..=60).map(|step| clock.frames_due(10.0 + step as f32 * DT)).collect();
+    assert!(taken.iter().all(|&frames| frames == 2));
+}
+
+/// Test that ffmpeg reads raw RGBA frames and the output container picks the encoding
+#[test]
+fn test_ffmpeg_args() {
+    let mp4 = ffmpeg_args(Path::new("out/run.mp4"), 1280, 720, 30);
+    let input = mp4.iter().position(|arg| arg == "-i").expect("input option");
+    assert_eq!(mp4[input + 1], "-");
+    assert!(mp4.windows(2).any(|pair| pair == ["-pix_fmt", "rgba"]));
+    assert!(mp4.windows(2).any(|pair| pair == ["-s", "1280x720"]));
+    assert!(mp4.windows(2).any(|pair| pair == ["-framerate", "30"]));
+    assert!(mp4.windows(2).any(|pair| pair == ["-pix_fmt", "yuv420p"]));
+    assert_eq!(mp4.last().map(String::as_str), Some("out/run.mp4"));
+    
+    let gif = ffmpeg_args(Path::new("run.GIF"), 640, 480, 15);
+    assert!(gif.iter().any(|arg| arg.contains("palettegen")));
+    assert!(!gif.iter().any(|arg| arg == "yuv420p"));
+}
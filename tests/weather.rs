@@ -0,0 +1,36 @@
+use traffic_sim::{
+    config::{SimulationConfig, WeatherChange},
+    simulation::{SimulationState, Weather},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+
+/// Test that the weather changes on schedule and that drivers slow down on ice
+#[test]
+fn test_scheduled_ice_slows_traffic() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    config.route.route.weather.schedule.push(WeatherChange {
+        time: 10.0,
+        condition: "ice".to_string(),
+    });
+    let speed_limit = config.route.route.traffic_rules.speed_limit;
+    
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(5));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    while state.time < 9.0 {
+        backend.update(&mut state)?;
+    }
+    assert_eq!(state.weather, Weather::Dry);
+    
+    while state.time < 12.0 {
+        backend.update(&mut state)?;
+    }
+    assert_eq!(state.weather, Weather::Ice);
+    assert!(!state.cars.is_empty());
+    for car in &state.cars {
+        assert!(car.behavior.target_speed <= speed_limit * Weather::Ice.speed_factor() + 1e-3,
+                "car {} wants {} m/s on ice", car.id, car.behavior.target_speed);
+    }
+    
+    Ok(())
+}
@@ -0,0 +1,176 @@
+mod common;
+
+use traffic_sim::{
+    config::{SimulationConfig, Validate, WorldBounds},
+    simulation::{SimulationState, SimulationEvent, Breakdown, RampPosition},
+    compute::{ComputeBackend, SimulationBackend},
+};
+use anyhow::Result;
+use common::first_car_spawned;
+use std::time::{Duration, Instant};
+
+/// Test that each geometry gets bounds just past the ends of its road unless the route
+/// sets its own, and that bounds inside out are rejected
+#[test]
+fn test_world_bounds_per_geometry() -> Result<()> {
+    let donut = SimulationConfig::load_from_files("route.toml", "cars.toml")?.route;
+    let bounds = donut.route.geometry.world_bounds();
+    assert!(bounds.contains(200.0, 0.0) && bounds.contains(-150.0, 150.0));
+    assert!(!bounds.contains(0.0, -240.0));
+    
+    let cloverleaf = SimulationConfig::load_from_files("route2.toml", "cars.toml")?.route;
+    let bounds = cloverleaf.route.geometry.world_bounds();
+    assert!(bounds.contains(-25.0, 250.0), "cars spawn at the highway ends");
+    assert!(!bounds.contains(25.0, 300.0));
+    
+    let mut route = cloverleaf.clone();
+    let configured = WorldBounds { min_x: -100.0, min_y: -100.0, max_x: 100.0, max_y: 100.0 };
+    route.route.geometry.bounds = Some(configured);
+    assert_eq!(route.route.geometry.world_bounds(), configured);
+    assert!(route.validate().is_ok());
+    route.route.geometry.bounds = Some(WorldBounds { min_x: 100.0, ..configured });
+    assert!(route.validate().is_err());
+    Ok(())
+}
+
+/// Test that cloverleaf through traffic leaves by its lane's exit at the end of the
+/// highway instead of driving on out of the world
+#[test]
+fn test_through_traffic_leaves_at_route_end() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route2.toml", "cars.toml")?;
+    config.cars.simulation.total_cars = 40;
+    // Cars leave at the start of the step after the one that took them out
+    let bounds = config.route.route.geometry.world_bounds();
+    let step = 2.0;
+    let bounds = WorldBounds {
+        min_x: bounds.min_x - step,
+        min_y: bounds.min_y - step,
+        max_x: bounds.max_x + step,
+        max_y: bounds.max_y + step,
+    };
+    let through_exits: Vec<String> = config.route.route.exits.iter()
+        .filter(|exit| exit.exit_type == "through")
+        .map(|exit| exit.id.clone())
+        .collect();
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(8));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    
+    let mut exited = 0;
+    while state.time < 60.0 {
+        backend.update(&mut state)?;
+        for event in &state.events {
+            if let SimulationEvent::CarExited { exit, .. } = event {
+                exited += through_exits.contains(exit) as u32;
+            }
+        }
+        for car in &state.cars {
+            assert!(bounds.contains(car.position.x, car.position.y), "car {} at {:?} is out of the world", car.id, car.position);
+        }
+    }
+    assert!(exited > 0, "no car reached the end of the highway");
+    let counted: u32 = through_exits.iter().filter_map(|exit| state.exit_counts.get(exit)).sum();
+    assert_eq!(counted, exited);
+    Ok(())
+}
+
+/// Test that a closed loop keeps its fleet: cars that leave re-enter with their ids and
+/// no car beyond the fleet size is ever spawned
+#[test]
+fn test_closed_loop_recycles_cars() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    config.cars.simulation.total_cars = 12;
+    config.cars.simulation.closed_loop = true;
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(3));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    
+    let (mut exited, mut spawned) = (0, 0);
+    while state.time < 240.0 {
+        backend.update(&mut state)?;
+        for event in &state.events {
+            match event {
+                SimulationEvent::CarExited { .. } => exited += 1,
+                SimulationEvent::CarSpawned { car, .. } => {
+                    assert!(car.index < 12, "car {} spawned beyond the fleet", car);
+                    spawned += 1;
+                }
+                _ => {}
+            }
+        }
+        assert!(state.cars.len() <= 12);
+    }
+    assert!(exited > 0, "no car left the loop");
+    assert!(spawned > 12, "only {} spawns for {} exits", spawned, exited);
+    Ok(())
+}
+
+/// Test that a car reaching the end of its off-ramp as its tow truck arrives leaves once,
+/// and a closed loop brings it back once
+#[test]
+fn test_departing_car_leaves_once() -> Result<()> {
+    let mut config = SimulationConfig::load_from_files("route.toml", "cars.toml")?;
+    config.cars.simulation.total_cars = 12;
+    config.cars.simulation.closed_loop = true;
+    let (mut backend, mut state) = first_car_spawned(&config, 3)?;
+    let exit_id = config.route.route.exits[0].id.clone();
+    let id = state.cars[0].id;
+    let car = state.get_car_mut(id).expect("car just spawned");
+    car.exit_ramp = Some(RampPosition { exit_id: exit_id.clone(), distance: f32::MAX });
+    car.breakdown = Some(Breakdown {
+        start_time: 0.0,
+        end_time: 0.0,
+        pull_over_time: None,
+        shoulder_offset: 1.0,
+    });
+    
+    backend.update(&mut state)?;
+    let exits = state.events.iter()
+        .filter(|event| matches!(event, SimulationEvent::CarExited { car, .. } if *car == id))
+        .count();
+    assert_eq!(exits, 1);
+    assert_eq!(state.exit_counts.get(&exit_id), Some(&1));
+    
+    while state.get_car(id).is_none() {
+        assert!(state.time < 30.0, "the car never came back");
+        backend.update(&mut state)?;
+    }
+    let reentries = state.events.iter()
+        .filter(|event| matches!(event, SimulationEvent::CarSpawned { car, .. } if *car == id))
+        .count();
+    assert_eq!(reentries, 1);
+    assert_eq!(state.cars.iter().filter(|car| car.id == id).count(), 1);
+    Ok(())
+}
+
+/// Test that cloverleaf traffic keeps flowing: cars leave only at the ends of the
+/// highway, spawns are accounted for and two minutes of traffic run in bounded time
+#[test]
+fn test_cloverleaf_run_is_bounded() -> Result<()> {
+    let config = SimulationConfig::load_from_files("route2.toml", "cars.toml")?;
+    let through_exits: Vec<String> = config.route.route.exits.iter()
+        .filter(|exit| exit.exit_type == "through")
+        .map(|exit| exit.id.clone())
+        .collect();
+    let mut backend = ComputeBackend::new_cpu(config.cars.clone(), config.route.clone(), Some(1));
+    let mut state = SimulationState::new(1.0 / 60.0);
+    
+    let start = Instant::now();
+    let (mut spawned, mut exited) = (0, 0);
+    while state.time < 120.0 {
+        backend.update(&mut state)?;
+        for event in &state.events {
+            match event {
+                SimulationEvent::CarSpawned { .. } => spawned += 1,
+                SimulationEvent::CarExited { exit, .. } => {
+                    assert!(through_exits.contains(exit), "car left at {} inside the interchange", exit);
+                    exited += 1;
+                }
+                _ => {}
+            }
+        }
+        assert!(start.elapsed() < Duration::from_secs(120), "{:.0}s simulated in two minutes", state.time);
+    }
+    assert!(exited > 0, "no car reached the end of the highway");
+    assert!(exited + state.cars.len() <= spawned);
+    assert!(state.cars.len() < 300, "{} cars piled up", state.cars.len());
+    Ok(())
+}